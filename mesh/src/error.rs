@@ -1,6 +1,7 @@
 use std::convert::From;
 use std::error;
 use std::fmt;
+use std::io;
 use std::result;
 
 pub type Result<T> = result::Result<T, Error>;
@@ -8,12 +9,47 @@ pub type Result<T> = result::Result<T, Error>;
 #[derive(Debug)]
 pub enum Error {
     Nom(nom_stl::Error),
+    Io(io::Error),
+    /// The buffer ended before the 84 byte header of a binary STL.
+    TruncatedHeader {
+        len: usize,
+    },
+    /// The triangle count in a binary STL header needs more bytes than the
+    /// buffer contains.
+    TriangleCount {
+        reported: u32,
+        available: usize,
+    },
+    /// A triangle has a vertex position or normal component that is NaN or
+    /// infinite.
+    NonFiniteTriangle {
+        triangle: usize,
+    },
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::Nom(e) => write!(f, "Nom STL error: {:?}", e),
+            Error::Io(e) => write!(f, "Error reading STL: {}", e),
+            Error::TruncatedHeader { len } => write!(
+                f,
+                "STL is only {} bytes long, which is too short for a binary STL header.",
+                len
+            ),
+            Error::TriangleCount {
+                reported,
+                available,
+            } => write!(
+                f,
+                "STL header reports {} triangles but the data only contains {}.",
+                reported, available
+            ),
+            Error::NonFiniteTriangle { triangle } => write!(
+                f,
+                "STL triangle {} has a non-finite vertex or normal.",
+                triangle
+            ),
         }
     }
 }
@@ -22,6 +58,10 @@ impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
             Error::Nom(e) => Some(e),
+            Error::Io(e) => Some(e),
+            Error::TruncatedHeader { .. } => None,
+            Error::TriangleCount { .. } => None,
+            Error::NonFiniteTriangle { .. } => None,
         }
     }
 }
@@ -31,3 +71,9 @@ impl From<nom_stl::Error> for Error {
         Error::Nom(error)
     }
 }
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
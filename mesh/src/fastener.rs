@@ -0,0 +1,228 @@
+//! Generates meshes for standard parts, such as bolts, nuts, and washers, from
+//! their nominal sizes.
+//!
+//! Threads are not modeled. Each part's axis is the z axis. A bolt's bearing
+//! face, the underside of its head, is at z = 0, with the head below and the
+//! shank above. Nuts, washers, and dowels sit on the z = 0 plane and extend
+//! upward.
+
+use crate::MeshBuilder;
+use cgmath::{InnerSpace, Point2, Point3, Vector3};
+use std::f32::consts::PI;
+
+/// The number of segments used to approximate a circle. It is a multiple of
+/// six so that hexagons and circles can share segment counts.
+const CIRCLE_SEGMENTS: usize = 48;
+
+/// The dimensions, in millimeters, of the ISO metric hex bolts, hex nuts, and
+/// plain washers for a nominal thread diameter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MetricSize {
+    /// The nominal thread diameter.
+    pub diameter: f32,
+
+    /// The width across the flats of bolt heads and nuts.
+    pub width_across_flats: f32,
+
+    /// The height of a bolt head.
+    pub head_height: f32,
+
+    /// The height of a nut.
+    pub nut_height: f32,
+
+    /// The inside diameter of a washer.
+    pub washer_inside_diameter: f32,
+
+    /// The outside diameter of a washer.
+    pub washer_outside_diameter: f32,
+
+    /// The thickness of a washer.
+    pub washer_thickness: f32,
+}
+
+/// ISO 4017 bolt, ISO 4032 nut, and ISO 7089 washer dimensions for each
+/// designation.
+const METRIC_SIZES: [(&str, MetricSize); 10] = [
+    ("M3", metric(3.0, 5.5, 2.0, 2.4, 3.2, 7.0, 0.5)),
+    ("M4", metric(4.0, 7.0, 2.8, 3.2, 4.3, 9.0, 0.8)),
+    ("M5", metric(5.0, 8.0, 3.5, 4.7, 5.3, 10.0, 1.0)),
+    ("M6", metric(6.0, 10.0, 4.0, 5.2, 6.4, 12.0, 1.6)),
+    ("M8", metric(8.0, 13.0, 5.3, 6.8, 8.4, 16.0, 1.6)),
+    ("M10", metric(10.0, 16.0, 6.4, 8.4, 10.5, 20.0, 2.0)),
+    ("M12", metric(12.0, 18.0, 7.5, 10.8, 13.0, 24.0, 2.5)),
+    ("M16", metric(16.0, 24.0, 10.0, 14.8, 17.0, 30.0, 3.0)),
+    ("M20", metric(20.0, 30.0, 12.5, 18.0, 21.0, 37.0, 3.0)),
+    ("M24", metric(24.0, 36.0, 15.0, 21.5, 25.0, 44.0, 4.0)),
+];
+
+const fn metric(
+    diameter: f32,
+    width_across_flats: f32,
+    head_height: f32,
+    nut_height: f32,
+    washer_inside_diameter: f32,
+    washer_outside_diameter: f32,
+    washer_thickness: f32,
+) -> MetricSize {
+    MetricSize {
+        diameter,
+        width_across_flats,
+        head_height,
+        nut_height,
+        washer_inside_diameter,
+        washer_outside_diameter,
+        washer_thickness,
+    }
+}
+
+impl MetricSize {
+    /// Return the dimensions for a designation such as "M8", or `None` if the
+    /// designation isn't a supported size.
+    pub fn from_designation(designation: &str) -> Option<Self> {
+        METRIC_SIZES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(designation.trim()))
+            .map(|(_, size)| *size)
+    }
+}
+
+/// Generate a hex bolt with a plain shank.
+///
+/// * size - The bolt's nominal size.
+/// * length - The length of the shank, measured from the underside of the
+///   head.
+pub fn hex_bolt(size: &MetricSize, length: f32) -> MeshBuilder {
+    let mut soup = TriangleSoup::default();
+    let hexagon = hexagon(size.width_across_flats);
+    soup.prism(&hexagon, None, -size.head_height, 0.0);
+    soup.prism(&circle(size.diameter / 2.0), None, 0.0, length);
+    soup.build()
+}
+
+/// Generate a hex nut.
+pub fn hex_nut(size: &MetricSize) -> MeshBuilder {
+    let mut soup = TriangleSoup::default();
+    let hole = circle(size.diameter / 2.0);
+    soup.prism(
+        &hexagon(size.width_across_flats),
+        Some(&hole),
+        0.0,
+        size.nut_height,
+    );
+    soup.build()
+}
+
+/// Generate a plain washer.
+pub fn washer(size: &MetricSize) -> MeshBuilder {
+    let mut soup = TriangleSoup::default();
+    let hole = circle(size.washer_inside_diameter / 2.0);
+    soup.prism(
+        &circle(size.washer_outside_diameter / 2.0),
+        Some(&hole),
+        0.0,
+        size.washer_thickness,
+    );
+    soup.build()
+}
+
+/// Generate a cylindrical dowel pin.
+pub fn dowel(diameter: f32, length: f32) -> MeshBuilder {
+    let mut soup = TriangleSoup::default();
+    soup.prism(&circle(diameter / 2.0), None, 0.0, length);
+    soup.build()
+}
+
+/// Return `CIRCLE_SEGMENTS` counterclockwise points on a circle about the
+/// origin.
+fn circle(radius: f32) -> Vec<Point2<f32>> {
+    (0..CIRCLE_SEGMENTS)
+        .map(|i| {
+            let angle = 2.0 * PI * i as f32 / CIRCLE_SEGMENTS as f32;
+            Point2::new(radius * angle.cos(), radius * angle.sin())
+        })
+        .collect()
+}
+
+/// Return `CIRCLE_SEGMENTS` counterclockwise points on a regular hexagon about
+/// the origin, with the corners at the same angles as every
+/// `CIRCLE_SEGMENTS / 6`th point of `circle`.
+fn hexagon(width_across_flats: f32) -> Vec<Point2<f32>> {
+    let corner_radius = width_across_flats / 3.0f32.sqrt();
+    let corners: Vec<Point2<f32>> = (0..6)
+        .map(|i| {
+            let angle = PI / 3.0 * i as f32;
+            Point2::new(corner_radius * angle.cos(), corner_radius * angle.sin())
+        })
+        .collect();
+    let points_per_side = CIRCLE_SEGMENTS / 6;
+    (0..CIRCLE_SEGMENTS)
+        .map(|i| {
+            let (side, step) = (i / points_per_side, i % points_per_side);
+            let (a, b) = (corners[side], corners[(side + 1) % 6]);
+            let t = step as f32 / points_per_side as f32;
+            a + (b - a) * t
+        })
+        .collect()
+}
+
+/// Accumulates triangles, each with its own vertices and a face normal, like
+/// the meshes read from STL files.
+#[derive(Default)]
+struct TriangleSoup {
+    positions: Vec<Point3<f32>>,
+    normals: Vec<Vector3<f32>>,
+}
+
+impl TriangleSoup {
+    /// Add a triangle whose vertices are counterclockwise when seen from the
+    /// outside.
+    fn triangle(&mut self, p0: Point3<f32>, p1: Point3<f32>, p2: Point3<f32>) {
+        let normal = (p1 - p0).cross(p2 - p0).normalize();
+        self.positions.extend_from_slice(&[p0, p1, p2]);
+        self.normals.extend_from_slice(&[normal, normal, normal]);
+    }
+
+    /// Add a counterclockwise quad.
+    fn quad(&mut self, p0: Point3<f32>, p1: Point3<f32>, p2: Point3<f32>, p3: Point3<f32>) {
+        self.triangle(p0, p1, p2);
+        self.triangle(p0, p2, p3);
+    }
+
+    /// Extrude the outline, with an optional hole, from `z0` to `z1`. The
+    /// outline and hole must have the same number of counterclockwise points,
+    /// with each hole point in the same direction from the origin as the
+    /// corresponding outline point.
+    fn prism(&mut self, outline: &[Point2<f32>], hole: Option<&[Point2<f32>]>, z0: f32, z1: f32) {
+        let at = |p: Point2<f32>, z: f32| Point3::new(p.x, p.y, z);
+        let n = outline.len();
+        for i in 0..n {
+            let (a, b) = (outline[i], outline[(i + 1) % n]);
+            // The outside wall.
+            self.quad(at(a, z0), at(b, z0), at(b, z1), at(a, z1));
+            match hole {
+                Some(hole) => {
+                    let (c, d) = (hole[i], hole[(i + 1) % n]);
+                    // The wall of the hole faces inward.
+                    self.quad(at(d, z0), at(c, z0), at(c, z1), at(d, z1));
+                    // The top and bottom are rings between the outline and
+                    // the hole.
+                    self.quad(at(c, z1), at(a, z1), at(b, z1), at(d, z1));
+                    self.quad(at(a, z0), at(c, z0), at(d, z0), at(b, z0));
+                }
+                None => {
+                    // The top and bottom are fans about the axis.
+                    let center = Point2::new(0.0, 0.0);
+                    self.triangle(at(center, z1), at(a, z1), at(b, z1));
+                    self.triangle(at(center, z0), at(b, z0), at(a, z0));
+                }
+            }
+        }
+    }
+
+    fn build(self) -> MeshBuilder {
+        let triangle_vertex_indices = (0..self.positions.len() / 3)
+            .map(|i| (3 * i, 3 * i + 1, 3 * i + 2))
+            .collect();
+        MeshBuilder::new(self.positions, self.normals, triangle_vertex_indices)
+    }
+}
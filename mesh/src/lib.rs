@@ -1,8 +1,11 @@
 mod error;
+pub mod fastener;
 mod triangle;
+mod unwrap;
 
 use cgmath::{
-    num_traits::identities, InnerSpace, Matrix4, Point2, Point3, SquareMatrix, Transform, Vector3,
+    num_traits::identities, EuclideanSpace, InnerSpace, Matrix4, Point2, Point3, SquareMatrix,
+    Transform, Vector3,
 };
 pub use error::{Error, Result};
 pub use triangle::Triangle;
@@ -48,6 +51,64 @@ impl Mesh {
         self.transformation_swaps_handedness = !self.transformation_swaps_handedness
     }
 
+    /// Remove every triangle that has zero area, since such triangles have no
+    /// well-defined normal. Vertices are left in place. Returns the number of
+    /// triangles that were removed.
+    ///
+    /// Meshes are expected to have finite vertices, which `from_stl_bytes`
+    /// guarantees by rejecting STLs with non-finite values.
+    pub fn remove_degenerate_triangles(&mut self) -> usize {
+        let positions = &self.positions;
+        let count_before = self.triangle_vertex_indices.len();
+        self.triangle_vertex_indices.retain(|&(i1, i2, i3)| {
+            let (p1, p2, p3) = (positions[i1], positions[i2], positions[i3]);
+            (p2 - p1).cross(p3 - p1).magnitude2() > 0.0
+        });
+        count_before - self.triangle_vertex_indices.len()
+    }
+
+    /// Remove every vertex that isn't used by a triangle, such as the
+    /// vertices of removed degenerate triangles, so that they don't affect
+    /// the mesh's bounds. Returns the number of vertices that were removed.
+    pub fn remove_unused_vertices(&mut self) -> usize {
+        let count_before = self.positions.len();
+        let mut new_indices = vec![None; count_before];
+        let mut used = vec![];
+        for (i1, i2, i3) in &mut self.triangle_vertex_indices {
+            for i in [i1, i2, i3] {
+                *i = *new_indices[*i].get_or_insert_with(|| {
+                    used.push(*i);
+                    used.len() - 1
+                });
+            }
+        }
+        self.positions = used.iter().map(|&i| self.positions[i]).collect();
+        self.normals = used.iter().map(|&i| self.normals[i]).collect();
+        if let Some(uvs) = &self.uvs {
+            self.uvs = Some(used.iter().map(|&i| uvs[i]).collect());
+        }
+        count_before - self.positions.len()
+    }
+
+    /// Returns the centroid of the mesh's surface, where each triangle is
+    /// weighted by its area. Returns `None` if the mesh has no area.
+    pub fn surface_centroid(&self) -> Option<Point3<f32>> {
+        let mut weighted_sum = Vector3::new(0.0, 0.0, 0.0);
+        let mut total_area = 0.0;
+        for &(i1, i2, i3) in &self.triangle_vertex_indices {
+            let (p1, p2, p3) = (self.positions[i1], self.positions[i2], self.positions[i3]);
+            let area = 0.5 * (p2 - p1).cross(p3 - p1).magnitude();
+            let center = (p1.to_vec() + p2.to_vec() + p3.to_vec()) / 3.0;
+            weighted_sum += center * area;
+            total_area += area;
+        }
+        if total_area > 0.0 {
+            Some(Point3::from_vec(weighted_sum / total_area))
+        } else {
+            None
+        }
+    }
+
     /// Returns the minimum and maximum corners of an axis-aligned bounded box
     /// around the mesh.
     pub fn bounding_box(&self) -> Option<(Point3<f32>, Point3<f32>)> {
@@ -144,11 +205,46 @@ impl MeshBuilder {
         mesh
     }
 
+    /// Read an ASCII or binary STL from the given reader and create a builder
+    /// for a mesh with the STL's triangles.
+    ///
+    /// The whole STL is read into memory and then parsed with `from_stl_bytes`,
+    /// so malformed input results in an error rather than a panic.
+    ///
+    /// * stl_bytes - A reader positioned at the start of the STL.
     pub fn from_stl<R>(stl_bytes: &mut R) -> Result<MeshBuilder>
     where
         R: std::io::Read + std::io::Seek,
     {
-        let stl = nom_stl::parse_stl(stl_bytes)?;
+        let mut buffer = vec![];
+        stl_bytes.read_to_end(&mut buffer)?;
+        Self::from_stl_bytes(&buffer)
+    }
+
+    /// Parse an ASCII or binary STL from a byte slice and create a builder for
+    /// a mesh with the STL's triangles.
+    ///
+    /// This never panics, which makes it suitable as a fuzzing entry point.
+    /// Truncated buffers, binary headers that report more triangles than the
+    /// buffer holds, and triangles with NaN or infinite values all result in
+    /// an error.
+    ///
+    /// * bytes - The complete contents of an STL file.
+    pub fn from_stl_bytes(bytes: &[u8]) -> Result<MeshBuilder> {
+        if !is_ascii_stl(bytes) {
+            check_binary_stl_length(bytes)?;
+        }
+        let stl = nom_stl::parse_stl(&mut std::io::Cursor::new(bytes))?;
+        if let Some(triangle) = stl.triangles().iter().position(|t| {
+            let [v1, v2, v3] = t.vertices();
+            ![v1, v2, v3, t.normal()]
+                .iter()
+                .flatten()
+                .all(|c| c.is_finite())
+        }) {
+            return Err(Error::NonFiniteTriangle { triangle });
+        }
+
         let num_triangles = stl.triangles().len();
 
         let mut positions = vec![Point3::new(0.0, 0.0, 0.0); num_triangles * 3];
@@ -177,3 +273,107 @@ impl MeshBuilder {
         ))
     }
 }
+
+/// The length of a binary STL's header, including the triangle count.
+const BINARY_STL_HEADER_BYTES: usize = 84;
+
+/// The length of each triangle record in a binary STL.
+const BINARY_STL_TRIANGLE_BYTES: usize = 50;
+
+/// Returns true if the STL should be parsed as ASCII. This matches the
+/// heuristic that `nom_stl` uses, which looks for the `facet normal` keyword
+/// anywhere in the buffer.
+fn is_ascii_stl(bytes: &[u8]) -> bool {
+    let keyword = b"facet normal";
+    bytes.windows(keyword.len()).any(|window| window == keyword)
+}
+
+/// Check that a binary STL is long enough to hold its header and every
+/// triangle that the header reports.
+fn check_binary_stl_length(bytes: &[u8]) -> Result<()> {
+    if bytes.len() < BINARY_STL_HEADER_BYTES {
+        return Err(Error::TruncatedHeader { len: bytes.len() });
+    }
+    let reported = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]);
+    let available = (bytes.len() - BINARY_STL_HEADER_BYTES) / BINARY_STL_TRIANGLE_BYTES;
+    if reported as usize > available {
+        return Err(Error::TriangleCount {
+            reported,
+            available,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod stl_tests {
+    use super::{Error, MeshBuilder, BINARY_STL_HEADER_BYTES};
+
+    /// Return a binary STL whose header reports `reported` triangles and that
+    /// holds the given triangles, each of which is a normal followed by three
+    /// vertices.
+    fn binary_stl(reported: u32, triangles: &[[[f32; 3]; 4]]) -> Vec<u8> {
+        let mut bytes = vec![0; BINARY_STL_HEADER_BYTES - 4];
+        bytes.extend_from_slice(&reported.to_le_bytes());
+        for triangle in triangles {
+            for c in triangle.iter().flatten() {
+                bytes.extend_from_slice(&c.to_le_bytes());
+            }
+            bytes.extend_from_slice(&[0, 0]);
+        }
+        bytes
+    }
+
+    const TRIANGLE: [[f32; 3]; 4] = [
+        [0.0, 0.0, 1.0],
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+    ];
+
+    #[test]
+    fn reads_binary_stl() {
+        let bytes = binary_stl(2, &[TRIANGLE, TRIANGLE]);
+        let mesh = MeshBuilder::from_stl_bytes(&bytes).unwrap().build();
+        assert_eq!(mesh.triangle_vertex_indices.len(), 2);
+        assert_eq!(mesh.positions.len(), 6);
+    }
+
+    #[test]
+    fn truncated_header_is_an_error() {
+        let bytes = binary_stl(1, &[TRIANGLE]);
+        match MeshBuilder::from_stl_bytes(&bytes[..40]).err() {
+            Some(Error::TruncatedHeader { len: 40 }) => {}
+            error => panic!("expected a truncated header error, got {:?}", error),
+        }
+    }
+
+    #[test]
+    fn triangle_count_larger_than_file_is_an_error() {
+        let bytes = binary_stl(3, &[TRIANGLE]);
+        match MeshBuilder::from_stl_bytes(&bytes).err() {
+            Some(Error::TriangleCount {
+                reported: 3,
+                available: 1,
+            }) => {}
+            error => panic!("expected a triangle count error, got {:?}", error),
+        }
+    }
+
+    #[test]
+    fn non_finite_coordinates_are_an_error() {
+        for bad in &[f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            let mut bad_vertex = TRIANGLE;
+            bad_vertex[2][1] = *bad;
+            let mut bad_normal = TRIANGLE;
+            bad_normal[0][0] = *bad;
+            for bad_triangle in &[bad_vertex, bad_normal] {
+                let bytes = binary_stl(2, &[TRIANGLE, *bad_triangle]);
+                match MeshBuilder::from_stl_bytes(&bytes).err() {
+                    Some(Error::NonFiniteTriangle { triangle: 1 }) => {}
+                    error => panic!("expected a non-finite triangle error, got {:?}", error),
+                }
+            }
+        }
+    }
+}
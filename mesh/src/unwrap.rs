@@ -0,0 +1,82 @@
+use crate::Mesh;
+use cgmath::{Point2, Vector2};
+
+impl Mesh {
+    /// Return a copy of the mesh in which every triangle has its own vertices
+    /// and its own chart in a UV layout, so that a texture can be rendered for
+    /// a mesh that has no UV coordinates, such as one read from an STL.
+    ///
+    /// The texture is divided into a grid of cells, and each cell holds two
+    /// triangles, which are stretched to fill its halves. Texels are therefore
+    /// spread evenly across triangles rather than in proportion to their
+    /// areas, which suits meshes whose triangles are of similar sizes.
+    ///
+    /// * resolution - The width and height in texels of the texture that the
+    ///   layout is made for. The cells are made as square as possible in
+    ///   texels.
+    /// * padding - The gap in texels that is left around each triangle, so
+    ///   that neighboring charts don't bleed into each other when the texture
+    ///   is filtered.
+    pub fn with_triangle_uvs(&self, resolution: Vector2<u32>, padding: f32) -> Mesh {
+        let triangle_count = self.triangle_vertex_indices.len();
+        let cell_count = triangle_count.div_ceil(2);
+        let (width, height) = (resolution.x.max(1) as f32, resolution.y.max(1) as f32);
+        let columns = ((cell_count as f32 * width / height).sqrt().ceil() as usize).max(1);
+        let rows = cell_count.div_ceil(columns).max(1);
+        let cell = Vector2::new(1.0 / columns as f32, 1.0 / rows as f32);
+        // Keep the margins small enough that each triangle still has area.
+        let margin = Vector2::new(
+            (padding / width).min(cell.x / 4.0),
+            (padding / height).min(cell.y / 4.0),
+        );
+
+        let has_normals = self.normals.len() == self.positions.len();
+        let mut positions = Vec::with_capacity(triangle_count * 3);
+        let mut normals = Vec::with_capacity(if has_normals { triangle_count * 3 } else { 0 });
+        let mut uvs = Vec::with_capacity(triangle_count * 3);
+        let mut triangle_vertex_indices = Vec::with_capacity(triangle_count);
+        for (triangle_index, (i0, i1, i2)) in self.triangle_vertex_indices.iter().enumerate() {
+            let cell_index = triangle_index / 2;
+            let origin = Point2::new(
+                (cell_index % columns) as f32 * cell.x,
+                (cell_index / columns) as f32 * cell.y,
+            );
+            // The first triangle of a cell fills its lower-left half and the
+            // second fills its upper-right half. The second triangle's
+            // diagonal is shifted by one margin so the two don't touch.
+            let (m, c) = (margin, cell);
+            let corners = if triangle_index % 2 == 0 {
+                [
+                    Vector2::new(m.x, m.y),
+                    Vector2::new(c.x - 2.0 * m.x, m.y),
+                    Vector2::new(m.x, c.y - 2.0 * m.y),
+                ]
+            } else {
+                [
+                    Vector2::new(c.x - m.x, c.y - m.y),
+                    Vector2::new(2.0 * m.x, c.y - m.y),
+                    Vector2::new(c.x - m.x, 2.0 * m.y),
+                ]
+            };
+
+            let first = positions.len();
+            for (i, corner) in [*i0, *i1, *i2].iter().zip(corners.iter()) {
+                positions.push(self.positions[*i]);
+                if has_normals {
+                    normals.push(self.normals[*i]);
+                }
+                uvs.push(origin + corner);
+            }
+            triangle_vertex_indices.push((first, first + 1, first + 2));
+        }
+
+        Mesh {
+            positions,
+            normals,
+            uvs: Some(uvs),
+            triangle_vertex_indices,
+            transformation_swaps_handedness: self.transformation_swaps_handedness,
+            reverse_orientation: self.reverse_orientation,
+        }
+    }
+}
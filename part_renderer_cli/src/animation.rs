@@ -0,0 +1,225 @@
+use crate::config::{Animation, CameraKeyframe, Easing, Point, Spherical};
+use crate::error::{Error, Result};
+
+/// The position of a camera and the point it looks at.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraPose {
+    /// The position of the camera relative to `target`.
+    pub position: SphericalPosition,
+    pub target: Point,
+}
+
+/// A position in spherical coordinates, with angles in degrees, that has been
+/// evaluated for a particular frame.
+#[derive(Debug, Clone, Copy)]
+pub struct SphericalPosition {
+    pub radius: f32,
+    pub theta: f32,
+    pub phi: f32,
+}
+
+impl SphericalPosition {
+    /// Evaluate the configured spherical coordinates in the given frame.
+    pub fn at_frame(spherical: &Spherical, frame: usize) -> Result<Self> {
+        Ok(Self {
+            radius: spherical.radius.at_frame(frame)?,
+            theta: spherical.theta.at_frame(frame)?,
+            phi: spherical.phi.at_frame(frame)?,
+        })
+    }
+}
+
+/// Return the camera pose for each frame of the animation. Returns an error if
+/// the frame rate isn't a positive number or if the keyframes aren't sorted by
+/// time.
+pub fn frame_poses(animation: &Animation) -> Result<Vec<CameraPose>> {
+    validate(animation)?;
+    let (first, last) = match (animation.keyframes.first(), animation.keyframes.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Ok(vec![]),
+    };
+    let duration = (last.time - first.time).max(0.0);
+    let frame_count = (duration * animation.frames_per_second).floor() as usize + 1;
+    (0..frame_count)
+        .map(|frame| {
+            let time = first.time + frame as f32 / animation.frames_per_second;
+            pose_at(&animation.keyframes, time, frame)
+        })
+        .collect()
+}
+
+fn validate(animation: &Animation) -> Result<()> {
+    let fps = animation.frames_per_second;
+    if !fps.is_finite() || fps <= 0.0 {
+        return Err(Error::Animation(format!(
+            "frames_per_second must be a positive number but is {}",
+            fps
+        )));
+    }
+    if let Some(keyframe) = animation.keyframes.iter().find(|k| !k.time.is_finite()) {
+        return Err(Error::Animation(format!(
+            "keyframe times must be finite but one is {}",
+            keyframe.time
+        )));
+    }
+    // Equal times are allowed, which cuts from one pose to the next.
+    if let Some(pair) = animation
+        .keyframes
+        .windows(2)
+        .find(|pair| pair[1].time < pair[0].time)
+    {
+        return Err(Error::Animation(format!(
+            "keyframes must be sorted by time but {} comes after {}",
+            pair[1].time, pair[0].time
+        )));
+    }
+    Ok(())
+}
+
+/// Return the camera pose at the given time by interpolating between the
+/// keyframes on either side of it. Times before the first keyframe or after
+/// the last keyframe use the pose of the nearest keyframe. Keyframe positions
+/// that vary with the frame index are evaluated in the given frame.
+fn pose_at(keyframes: &[CameraKeyframe], time: f32, frame: usize) -> Result<CameraPose> {
+    let next_index = keyframes
+        .iter()
+        .position(|k| k.time > time)
+        .unwrap_or(keyframes.len());
+    if next_index == 0 {
+        return keyframe_pose(&keyframes[0], frame);
+    }
+    if next_index == keyframes.len() {
+        return keyframe_pose(&keyframes[keyframes.len() - 1], frame);
+    }
+
+    let k0 = &keyframes[next_index - 1];
+    let k1 = &keyframes[next_index];
+    let t = ease(k0.easing, (time - k0.time) / (k1.time - k0.time));
+    let p0 = SphericalPosition::at_frame(&k0.position, frame)?;
+    let p1 = SphericalPosition::at_frame(&k1.position, frame)?;
+    Ok(CameraPose {
+        position: SphericalPosition {
+            radius: lerp(t, p0.radius, p1.radius),
+            theta: lerp(t, p0.theta, p1.theta),
+            phi: lerp(t, p0.phi, p1.phi),
+        },
+        target: Point {
+            x: lerp(t, k0.target.x, k1.target.x),
+            y: lerp(t, k0.target.y, k1.target.y),
+            z: lerp(t, k0.target.z, k1.target.z),
+        },
+    })
+}
+
+fn keyframe_pose(keyframe: &CameraKeyframe, frame: usize) -> Result<CameraPose> {
+    Ok(CameraPose {
+        position: SphericalPosition::at_frame(&keyframe.position, frame)?,
+        target: keyframe.target,
+    })
+}
+
+/// Remap the linear progress `t` in [0, 1] between two keyframes according to
+/// the easing function.
+fn ease(easing: Easing, t: f32) -> f32 {
+    match easing {
+        Easing::Linear => t,
+        Easing::EaseIn => t * t,
+        Easing::EaseOut => t * (2.0 - t),
+        Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+    }
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    (1.0 - t) * a + t * b
+}
+
+#[cfg(test)]
+mod animation_tests {
+    use super::*;
+    use crate::config::Scalar;
+
+    fn keyframe(time: f32, radius: f32, easing: Easing) -> CameraKeyframe {
+        CameraKeyframe {
+            time,
+            position: Spherical {
+                radius: Scalar::Constant(radius),
+                theta: Scalar::Constant(0.0),
+                phi: Scalar::Constant(0.0),
+            },
+            target: Point {
+                x: radius,
+                y: 0.0,
+                z: 0.0,
+            },
+            easing,
+        }
+    }
+
+    #[test]
+    fn easing_preserves_endpoints() {
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+        ] {
+            assert_eq!(ease(easing, 0.0), 0.0);
+            assert_eq!(ease(easing, 1.0), 1.0);
+        }
+        assert_eq!(ease(Easing::Linear, 0.5), 0.5);
+        assert_eq!(ease(Easing::EaseIn, 0.5), 0.25);
+        assert_eq!(ease(Easing::EaseOut, 0.5), 0.75);
+        assert_eq!(ease(Easing::EaseInOut, 0.5), 0.5);
+    }
+
+    #[test]
+    fn pose_interpolates_between_keyframes_and_clamps_outside() {
+        let keyframes = vec![
+            keyframe(1.0, 10.0, Easing::Linear),
+            keyframe(3.0, 20.0, Easing::Linear),
+        ];
+        let radius = |time| pose_at(&keyframes, time, 0).unwrap().position.radius;
+        assert_eq!(radius(0.0), 10.0);
+        assert_eq!(radius(1.0), 10.0);
+        assert_eq!(radius(2.0), 15.0);
+        assert_eq!(radius(4.0), 20.0);
+        assert_eq!(pose_at(&keyframes, 2.5, 0).unwrap().target.x, 17.5);
+    }
+
+    #[test]
+    fn frame_count_covers_keyframe_duration() {
+        let animation = Animation {
+            frames_per_second: 2.0,
+            keyframes: vec![
+                keyframe(0.0, 10.0, Easing::Linear),
+                keyframe(2.0, 20.0, Easing::Linear),
+            ],
+        };
+        let poses = frame_poses(&animation).unwrap();
+        assert_eq!(poses.len(), 5);
+        assert_eq!(poses[4].position.radius, 20.0);
+    }
+
+    #[test]
+    fn invalid_frame_rate_is_an_error() {
+        for fps in [0.0, -24.0, f32::NAN, f32::INFINITY] {
+            let animation = Animation {
+                frames_per_second: fps,
+                keyframes: vec![keyframe(0.0, 10.0, Easing::Linear)],
+            };
+            assert!(matches!(frame_poses(&animation), Err(Error::Animation(_))));
+        }
+    }
+
+    #[test]
+    fn unsorted_keyframes_are_an_error() {
+        let animation = Animation {
+            frames_per_second: 24.0,
+            keyframes: vec![
+                keyframe(2.0, 10.0, Easing::Linear),
+                keyframe(1.0, 20.0, Easing::Linear),
+            ],
+        };
+        assert!(matches!(frame_poses(&animation), Err(Error::Animation(_))));
+    }
+}
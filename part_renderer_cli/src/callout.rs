@@ -0,0 +1,253 @@
+//! Numbered balloons that point at the named parts of an assembly, and a
+//! bill of materials that matches the numbers to the names.
+
+use crate::error::Result;
+use cgmath::{InnerSpace, Matrix4, Point2, Point3, Transform, Vector2};
+use image::{ImageBuffer, Primitive, Rgba};
+use std::io::Write;
+
+/// The glyphs of the digits 0 through 9, drawn on a grid three cells wide and
+/// five cells tall. Each row is three bits, with the leftmost cell in the
+/// highest bit.
+const DIGIT_GLYPHS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// A part of the assembly that gets a callout.
+pub struct LabeledPart {
+    pub name: String,
+
+    /// The centroid of the part's surface in world space.
+    pub centroid: Point3<f32>,
+}
+
+/// A numbered balloon and the leader line that connects it to a part.
+pub struct Callout {
+    /// The part's item number in the bill of materials, starting at one.
+    pub number: usize,
+    pub name: String,
+
+    /// The raster space point that the leader line points at.
+    pub anchor: Point2<f32>,
+
+    /// The raster space center of the balloon.
+    pub balloon: Point2<f32>,
+}
+
+/// Place a callout for each part, numbered in order. Each part's anchor is its
+/// centroid projected onto the image, and its balloon is pushed away from the
+/// center of all of the anchors so that the balloons surround the assembly.
+///
+/// * world_to_raster - The camera's transformation from world space to the
+///   image's raster space.
+/// * resolution - The image's resolution.
+/// * balloon_radius - The radius of each balloon in pixels.
+pub fn place(
+    parts: &[LabeledPart],
+    world_to_raster: &Matrix4<f32>,
+    resolution: Vector2<usize>,
+    balloon_radius: f32,
+) -> Vec<Callout> {
+    let anchors: Vec<Point2<f32>> = parts
+        .iter()
+        .map(|part| {
+            let p = world_to_raster.transform_point(part.centroid);
+            Point2::new(p.x, p.y)
+        })
+        .collect();
+    if anchors.is_empty() {
+        return vec![];
+    }
+    let center = anchors.iter().fold(Vector2::new(0.0, 0.0), |sum, anchor| {
+        sum + Vector2::new(anchor.x, anchor.y)
+    }) / anchors.len() as f32;
+    let center = Point2::new(center.x, center.y);
+    let leader_length = 0.15 * resolution.x.min(resolution.y) as f32;
+    let margin = balloon_radius + 1.0;
+
+    parts
+        .iter()
+        .zip(anchors)
+        .enumerate()
+        .map(|(i, (part, anchor))| {
+            let away = anchor - center;
+            // Balloons of parts at the center go straight up.
+            let direction = if away.magnitude2() > 1e-6 {
+                away.normalize()
+            } else {
+                Vector2::new(0.0, -1.0)
+            };
+            let balloon = anchor + direction * (leader_length + balloon_radius);
+            let balloon = Point2::new(
+                balloon
+                    .x
+                    .clamp(margin, (resolution.x as f32 - margin).max(margin)),
+                balloon
+                    .y
+                    .clamp(margin, (resolution.y as f32 - margin).max(margin)),
+            );
+            Callout {
+                number: i + 1,
+                name: part.name.clone(),
+                anchor,
+                balloon,
+            }
+        })
+        .collect()
+}
+
+/// Draw each callout's leader line and numbered balloon onto the image in
+/// black on white.
+pub fn draw<S>(image: &mut ImageBuffer<Rgba<S>, Vec<S>>, callouts: &[Callout], balloon_radius: f32)
+where
+    S: Primitive + 'static,
+{
+    let black = Rgba([S::zero(), S::zero(), S::zero(), S::max_value()]);
+    let white = Rgba([S::max_value(); 4]);
+    for callout in callouts {
+        let to_balloon = callout.balloon - callout.anchor;
+        let length = to_balloon.magnitude();
+        if length > balloon_radius {
+            let end = callout.anchor + to_balloon * ((length - balloon_radius) / length);
+            draw_line(image, callout.anchor, end, black);
+        }
+        fill_circle(image, callout.anchor, 2.0, black);
+        fill_circle(image, callout.balloon, balloon_radius, black);
+        fill_circle(image, callout.balloon, balloon_radius - 1.5, white);
+        draw_number(
+            image,
+            callout.number,
+            callout.balloon,
+            balloon_radius,
+            black,
+        );
+    }
+}
+
+/// Write the bill of materials as a JSON array with an object for each
+/// callout, which holds its number, the part's name, and the raster space
+/// positions of its anchor and balloon.
+pub fn write_bill_of_materials<W: Write>(writer: &mut W, callouts: &[Callout]) -> Result<()> {
+    writeln!(writer, "[")?;
+    for (i, callout) in callouts.iter().enumerate() {
+        let separator = if i + 1 < callouts.len() { "," } else { "" };
+        writeln!(
+            writer,
+            "  {{\"number\": {}, \"name\": \"{}\", \"anchor\": [{}, {}], \"balloon\": [{}, {}]}}{}",
+            callout.number,
+            escape_json(&callout.name),
+            callout.anchor.x,
+            callout.anchor.y,
+            callout.balloon.x,
+            callout.balloon.y,
+            separator
+        )?;
+    }
+    writeln!(writer, "]")?;
+    Ok(())
+}
+
+/// Escape the string so that it can be written inside a JSON string.
+pub fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn put_pixel<S: Primitive + 'static>(
+    image: &mut ImageBuffer<Rgba<S>, Vec<S>>,
+    x: i64,
+    y: i64,
+    color: Rgba<S>,
+) {
+    if x >= 0 && y >= 0 && (x as u32) < image.width() && (y as u32) < image.height() {
+        image.put_pixel(x as u32, y as u32, color);
+    }
+}
+
+fn draw_line<S: Primitive + 'static>(
+    image: &mut ImageBuffer<Rgba<S>, Vec<S>>,
+    start: Point2<f32>,
+    end: Point2<f32>,
+    color: Rgba<S>,
+) {
+    let steps = (end - start).magnitude().ceil().max(1.0) as usize;
+    for step in 0..=steps {
+        let p = start + (end - start) * (step as f32 / steps as f32);
+        put_pixel(image, p.x.floor() as i64, p.y.floor() as i64, color);
+    }
+}
+
+fn fill_circle<S: Primitive + 'static>(
+    image: &mut ImageBuffer<Rgba<S>, Vec<S>>,
+    center: Point2<f32>,
+    radius: f32,
+    color: Rgba<S>,
+) {
+    let (min_x, max_x) = ((center.x - radius).floor(), (center.x + radius).ceil());
+    let (min_y, max_y) = ((center.y - radius).floor(), (center.y + radius).ceil());
+    for y in min_y as i64..max_y as i64 {
+        for x in min_x as i64..max_x as i64 {
+            let offset = Point2::new(x as f32 + 0.5, y as f32 + 0.5) - center;
+            if offset.magnitude2() <= radius * radius {
+                put_pixel(image, x, y, color);
+            }
+        }
+    }
+}
+
+/// Draw the number's digits centered on the point, scaled so that they fit in
+/// a balloon with the given radius.
+fn draw_number<S: Primitive + 'static>(
+    image: &mut ImageBuffer<Rgba<S>, Vec<S>>,
+    number: usize,
+    center: Point2<f32>,
+    balloon_radius: f32,
+    color: Rgba<S>,
+) {
+    let digits: Vec<usize> = number
+        .to_string()
+        .bytes()
+        .map(|b| (b - b'0') as usize)
+        .collect();
+    // Each glyph is three cells wide with a one cell gap between glyphs, and
+    // the text should fill about 60% of the balloon's diameter.
+    let width_in_cells = 4 * digits.len() - 1;
+    let cell = ((1.2 * balloon_radius) / width_in_cells.max(5) as f32)
+        .floor()
+        .max(1.0) as i64;
+    let left = center.x.round() as i64 - (width_in_cells as i64 * cell) / 2;
+    let top = center.y.round() as i64 - (5 * cell) / 2;
+    for (i, digit) in digits.into_iter().enumerate() {
+        for (row, bits) in DIGIT_GLYPHS[digit].iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+                let x = left + (4 * i as i64 + column) * cell;
+                let y = top + row as i64 * cell;
+                for dy in 0..cell {
+                    for dx in 0..cell {
+                        put_pixel(image, x + dx, y + dy, color);
+                    }
+                }
+            }
+        }
+    }
+}
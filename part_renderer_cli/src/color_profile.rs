@@ -0,0 +1,108 @@
+//! Writes images with an embedded ICC profile, which the `image` crate's
+//! encoders don't support.
+
+use crate::error::{Error, Result};
+use image::{EncodableLayout, ImageBuffer, Primitive, Rgba};
+use std::{fs::File, io::BufWriter, path::Path};
+
+/// The TIFF tag that holds an ICC profile.
+const TIFF_ICC_PROFILE_TAG: u16 = 34675;
+
+/// Save the image to the path with the ICC profile embedded in it. The image's
+/// format is chosen by the path's extension, and it must be PNG or TIFF.
+///
+/// * image - An image with 8 or 16 bits per channel.
+/// * profile - The ICC profile that describes the image's colors.
+pub fn save<S>(
+    image: &ImageBuffer<Rgba<S>, Vec<S>>,
+    output_path: &str,
+    profile: &[u8],
+) -> Result<()>
+where
+    S: Primitive + 'static,
+    [S]: EncodableLayout,
+{
+    let extension = Path::new(output_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    let writer = BufWriter::new(File::create(output_path)?);
+    match extension.as_deref() {
+        Some("png") => save_png(image, writer, profile),
+        Some("tif") | Some("tiff") => save_tiff(image, writer, profile),
+        _ => Err(Error::ColorProfileFormat(output_path.to_string())),
+    }
+}
+
+/// Write the image as a PNG with the profile in an `iCCP` chunk, which must
+/// come before the image data.
+fn save_png<S>(
+    image: &ImageBuffer<Rgba<S>, Vec<S>>,
+    writer: BufWriter<File>,
+    profile: &[u8],
+) -> Result<()>
+where
+    S: Primitive + 'static,
+    [S]: EncodableLayout,
+{
+    let mut encoder = png::Encoder::new(writer, image.width(), image.height());
+    encoder.set_color(png::ColorType::RGBA);
+    let mut data = image.as_raw().as_bytes().to_vec();
+    if std::mem::size_of::<S>() == 2 {
+        encoder.set_depth(png::BitDepth::Sixteen);
+        // PNG samples are big-endian.
+        for sample in data.chunks_exact_mut(2) {
+            let value = u16::from_ne_bytes([sample[0], sample[1]]);
+            sample.copy_from_slice(&value.to_be_bytes());
+        }
+    } else {
+        encoder.set_depth(png::BitDepth::Eight);
+    }
+    let mut writer = encoder.write_header()?;
+
+    // The chunk holds the profile's name, a compression method of zero, and
+    // the zlib-compressed profile.
+    let mut chunk = b"ICC profile\0\0".to_vec();
+    chunk.extend(deflate::deflate_bytes_zlib(profile));
+    writer.write_chunk(*b"iCCP", &chunk)?;
+    writer.write_image_data(&data)?;
+    Ok(())
+}
+
+/// Write the image as a TIFF with the profile in the ICC profile tag. The
+/// `tiff` crate writes the tag's type as bytes rather than as undefined data,
+/// which readers accept for this tag.
+fn save_tiff<S>(
+    image: &ImageBuffer<Rgba<S>, Vec<S>>,
+    writer: BufWriter<File>,
+    profile: &[u8],
+) -> Result<()>
+where
+    S: Primitive + 'static,
+    [S]: EncodableLayout,
+{
+    use tiff::{encoder::colortype, tags::Tag};
+
+    let mut encoder = tiff::encoder::TiffEncoder::new(writer)?;
+    let bytes = image.as_raw().as_bytes();
+    if std::mem::size_of::<S>() == 2 {
+        let samples: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|sample| u16::from_ne_bytes([sample[0], sample[1]]))
+            .collect();
+        let mut tiff_image =
+            encoder.new_image::<colortype::RGBA16>(image.width(), image.height())?;
+        tiff_image
+            .encoder()
+            .write_tag(Tag::Unknown(TIFF_ICC_PROFILE_TAG), profile)?;
+        tiff_image.write_data(&samples)?;
+    } else {
+        let mut tiff_image =
+            encoder.new_image::<colortype::RGBA8>(image.width(), image.height())?;
+        tiff_image
+            .encoder()
+            .write_tag(Tag::Unknown(TIFF_ICC_PROFILE_TAG), profile)?;
+        tiff_image.write_data(bytes)?;
+    }
+    Ok(())
+}
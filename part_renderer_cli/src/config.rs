@@ -1,3 +1,5 @@
+use crate::error::Result;
+use crate::expression;
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
@@ -6,18 +8,291 @@ pub struct Config {
     pub width: usize,
     pub height: usize,
     pub crop: bool,
+
+    /// The number of bits per channel in the output image. Sixteen bits
+    /// avoids banding in smooth gradients but requires a PNG output path.
+    /// Defaults to eight.
+    #[serde(default)]
+    pub bit_depth: Option<BitDepth>,
+
+    /// If true, the output image holds linear colors rather than sRGB
+    /// encoded colors, such as for compositing in another tool.
+    #[serde(default)]
+    pub linear_output: bool,
+
+    /// The color space of the output image. Defaults to sRGB.
+    #[serde(default)]
+    pub color_space: Option<ColorSpace>,
+
+    /// If true, an ICC profile that describes the output image's color space
+    /// and encoding is embedded in it, so that color-managed viewers display
+    /// it consistently. The output path must be a PNG or TIFF.
+    #[serde(default)]
+    pub embed_color_profile: bool,
     pub sampler: Sampler,
     pub part: Part,
+
+    /// Points, such as a LiDAR scan or inspection measurements of the part,
+    /// that are rendered along with the part.
+    #[serde(default)]
+    pub point_cloud: Option<PointCloud>,
+
+    /// A voxel grid, such as lattice infill, that is rendered along with the
+    /// part.
+    #[serde(default)]
+    pub voxel_grid: Option<VoxelGrid>,
+
+    /// Standard parts, such as bolts and nuts, that are generated from their
+    /// sizes and rendered along with the part.
+    #[serde(default)]
+    pub fasteners: Vec<Fastener>,
+
     pub lights: Vec<Light>,
     pub camera: Camera,
+
+    /// If true, a camera that is inside the geometry is moved back along its
+    /// view axis until it is outside. Otherwise a warning is printed.
+    #[serde(default)]
+    pub back_out_camera: bool,
+
+    /// Image-space operations that are applied, in order, to the rendered
+    /// image before it is written.
+    #[serde(default)]
+    pub post_processing: Vec<PostProcess>,
+
+    /// If present, a luminance histogram of each finished image is checked,
+    /// and a warning is printed if too many pixels are blown out or crushed
+    /// to black.
+    #[serde(default)]
+    pub exposure_check: Option<ExposureCheck>,
+
+    /// If present, a numbered balloon is drawn pointing at each named part,
+    /// and a bill of materials that matches the numbers to the names is
+    /// written next to the output as JSON, such as `part.bom.json`. Callouts
+    /// aren't drawn on stereo images.
+    #[serde(default)]
+    pub callouts: Option<Callouts>,
+
+    /// If present, the silhouettes, creases, and boundaries of the part and
+    /// fasteners are written next to the output as an SVG image, such as
+    /// `part.svg`, so that they can be scaled and edited in vector graphics
+    /// tools. The SVG covers the whole frame, even if the image is cropped.
+    /// Line art isn't written for stereo images.
+    #[serde(default)]
+    pub line_art: Option<LineArt>,
+
+    /// Auxiliary passes that are rendered alongside the image. Each is written
+    /// next to the output, with the pass's name inserted before the
+    /// extension, such as `part.normal.png`.
+    #[serde(default)]
+    pub aovs: Vec<Aov>,
+
+    /// If true, a false-color image of the variance of each pixel's mean
+    /// luminance is written next to the output, such as
+    /// `part.variance.png`. This shows where the image is still noisy.
+    #[serde(default)]
+    pub variance_image: bool,
+
+    /// If true, a chrome ball and a gray ball that are lit like the part and
+    /// seen from the camera's direction are written next to the output, such
+    /// as `part.probes.png`. Lighting artists use them as a reference when
+    /// matching renders to photographs.
+    #[serde(default)]
+    pub light_probes: bool,
+
+    /// If true, an image that marks where the camera's clipping planes cut
+    /// off the part is written next to the output, such as
+    /// `part.clipped.png`. Geometry cut off by the near plane is red, and
+    /// geometry beyond the far plane is blue. A warning is printed whenever
+    /// any geometry is clipped, whether or not this is set.
+    #[serde(default)]
+    pub clipping_image: bool,
+
+    /// The job that is rendered in place of every frame. This is set by the
+    /// `--job` option rather than read from the config.
+    #[serde(skip)]
+    pub job: Option<crate::jobs::Job>,
+
+    /// If present, only the pixels inside this window are rendered. The image
+    /// keeps its full size, and the pixels outside the window are
+    /// transparent.
+    #[serde(default)]
+    pub crop_window: Option<CropWindow>,
+
+    /// An image that biases the order in which the image is rendered and
+    /// where extra samples are spent.
+    #[serde(default)]
+    pub priority_mask: Option<PriorityMask>,
+
+    /// If present, the red, green, and blue components of each sample's
+    /// radiance are clamped to this value to suppress fireflies.
+    #[serde(default)]
+    pub max_sample_radiance: Option<f32>,
+
+    /// If present, the partially rendered image is written to the output path
+    /// every this many seconds while it renders, so that long renders can be
+    /// monitored and stopped early. Stereo renders show the eye that is being
+    /// rendered.
+    #[serde(default)]
+    pub snapshot_interval: Option<f32>,
+
+    /// The shape of the buckets of pixels that are rendered in parallel.
+    /// Defaults to square tiles.
+    #[serde(default)]
+    pub tile_shape: Option<TileShape>,
+
+    /// The order in which tiles are rendered. Defaults to scanline order.
+    #[serde(default)]
+    pub tile_order: Option<TileOrder>,
+
+    /// The largest number of times a path from the camera may bounce.
+    /// Defaults to 5.
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+
+    /// If present, the path tracer roughens mirror-like surfaces to this
+    /// microfacet roughness once a path has bounced off a diffuse or glossy
+    /// surface. This tames noisy caustics while keeping reflections seen from
+    /// the camera sharp. Values around 0.1 to 0.3 work well.
+    #[serde(default)]
+    pub path_regularization: Option<f32>,
+
+    /// How the path tracer chooses the lights whose direct lighting it samples
+    /// at each surface interaction. Defaults to sampling every light.
+    #[serde(default)]
+    pub light_sampling: Option<LightSampling>,
+
+    /// The filter that weights each sample's contribution to nearby pixels.
+    /// Defaults to a Mitchell filter with a radius of two pixels.
+    #[serde(default)]
+    pub filter: Option<Filter>,
+
+    /// How the filter's negative lobes are handled. Defaults to keeping them.
+    #[serde(default)]
+    pub negative_lobes: Option<NegativeLobes>,
+
+    /// If present, the image is rendered at a multiple of its resolution and
+    /// filtered down to it before it's post-processed and written. This is a
+    /// simple alternative to more samples per pixel for crisp silhouettes.
+    #[serde(default)]
+    pub supersampling: Option<Supersampling>,
+
+    /// A keyframed camera path. If this is present, a sequence of frames is
+    /// rendered and the camera's configured position is ignored.
+    #[serde(default)]
+    pub animation: Option<Animation>,
+
+    /// The number of frames to render when there is no keyframed camera
+    /// path. This is useful when other values in the config vary with the
+    /// frame index. Defaults to a single frame.
+    #[serde(default)]
+    pub frame_count: Option<usize>,
+
+    /// If present, a view is rendered for each eye and the views are combined
+    /// into a single 3D image.
+    #[serde(default)]
+    pub stereo: Option<Stereo>,
 }
 
-/// A position in spherical coordinates.
-#[derive(Debug, Clone, Copy, Deserialize)]
+/// A number that may vary with the index of the frame being rendered.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Scalar {
+    /// The same value in every frame.
+    Constant(f32),
+
+    /// An arithmetic expression of the variable `frame`, such as
+    /// `"30 + frame * 2"`.
+    Expression(String),
+
+    /// A list of `(frame, value)` pairs, sorted by frame. The value is
+    /// linearly interpolated between pairs and held constant before the first
+    /// pair and after the last.
+    Keyframes(Vec<(f32, f32)>),
+}
+
+impl Scalar {
+    /// Return the value in the given frame.
+    pub fn at_frame(&self, frame: usize) -> Result<f32> {
+        match self {
+            Scalar::Constant(value) => Ok(*value),
+            Scalar::Expression(source) => expression::evaluate(source, frame),
+            Scalar::Keyframes(keyframes) => Ok(interpolate_keyframes(keyframes, frame as f32)),
+        }
+    }
+}
+
+fn interpolate_keyframes(keyframes: &[(f32, f32)], frame: f32) -> f32 {
+    let next_index = keyframes
+        .iter()
+        .position(|(f, _)| *f > frame)
+        .unwrap_or(keyframes.len());
+    match next_index {
+        _ if keyframes.is_empty() => 0.0,
+        0 => keyframes[0].1,
+        i if i == keyframes.len() => keyframes[i - 1].1,
+        i => {
+            let (f0, v0) = keyframes[i - 1];
+            let (f1, v1) = keyframes[i];
+            let t = (frame - f0) / (f1 - f0);
+            (1.0 - t) * v0 + t * v1
+        }
+    }
+}
+
+/// A position in spherical coordinates, with angles in degrees.
+#[derive(Debug, Clone, Deserialize)]
 pub struct Spherical {
-    pub radius: f32,
-    pub theta: f32,
-    pub phi: f32,
+    pub radius: Scalar,
+    pub theta: Scalar,
+    pub phi: Scalar,
+}
+
+/// Where a light is placed. A light that points in a particular direction,
+/// such as one with an IES profile or a projection light, is aimed at what
+/// it's placed around.
+#[derive(Debug, Clone, Deserialize)]
+pub enum LightPosition {
+    /// Spherical coordinates around the center of the part, with angles in
+    /// degrees.
+    Spherical {
+        radius: Scalar,
+        theta: Scalar,
+        phi: Scalar,
+    },
+
+    /// A direction relative to the camera's view, seen from the camera's
+    /// target, so that the lighting follows the camera as it moves. With both
+    /// angles at zero the light is at the camera.
+    CameraRelative {
+        /// The angle in degrees above the camera's view.
+        up: Scalar,
+
+        /// The angle in degrees to the left of the camera's view.
+        left: Scalar,
+
+        /// The distance from the camera's target. The part is scaled to fit
+        /// in a unit sphere, so this is in multiples of the part's radius.
+        distance: Scalar,
+    },
+
+    /// Spherical coordinates around the center of the named part or fastener,
+    /// with angles in degrees, so that the lighting follows the part as it's
+    /// moved.
+    PartRelative {
+        part: String,
+        radius: Scalar,
+        theta: Scalar,
+        phi: Scalar,
+    },
+}
+
+/// A position in Cartesian coordinates.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
 }
 
 #[derive(Debug, Clone, Copy, Deserialize)]
@@ -27,13 +302,57 @@ pub struct Rgb {
     pub b: f32,
 }
 
+// Variants are named after the samplers they configure.
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Deserialize)]
 pub enum Sampler {
+    /// Either both strata counts or the number of samples per pixel must be
+    /// given. Given a number of samples per pixel, the strata counts are
+    /// chosen automatically and any samples that don't fit the grid of strata
+    /// are Latin hypercube samples. Counts that are left out are zero.
     StratifiedSampler {
+        #[serde(default)]
         x_strata_count: usize,
+        #[serde(default)]
         y_strata_count: usize,
+        #[serde(default)]
+        samples_per_pixel: usize,
         jitter: bool,
     },
+    HaltonSampler {
+        samples_per_pixel: usize,
+    },
+
+    /// Generates progressive multi-jittered (0,2) samples, which are well
+    /// distributed at any power of two samples per pixel.
+    Pmj02Sampler {
+        samples_per_pixel: usize,
+    },
+
+    /// Wraps another sampler and takes more samples in noisy pixels than in
+    /// smooth ones.
+    AdaptiveSampler {
+        /// The sampler that generates the sample vectors.
+        sampler: Box<Sampler>,
+
+        /// The number of samples always taken in each pixel.
+        min_samples: usize,
+
+        /// The largest number of samples taken in any pixel.
+        max_samples: usize,
+
+        /// Sampling in a pixel stops once the estimated variance of its mean
+        /// luminance falls below this value.
+        variance_threshold: f32,
+    },
+
+    /// Wraps another sampler and shifts each pixel's samples by an amount read
+    /// from a blue-noise mask, so that the noise left in renders with few
+    /// samples per pixel is spread evenly and is easier to denoise.
+    BlueNoiseSampler {
+        /// The sampler that generates the sample vectors.
+        sampler: Box<Sampler>,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
@@ -45,6 +364,11 @@ pub enum Handedness {
 #[derive(Debug, Deserialize)]
 pub struct Part {
     pub stl_path: String,
+
+    /// The part's name in the bill of materials. Parts without names don't
+    /// get callouts.
+    #[serde(default)]
+    pub name: Option<String>,
     pub material: Material,
 
     /// Indicates whether the vertex positions in the mesh assume a right hand
@@ -52,6 +376,72 @@ pub struct Part {
     pub handedness: Handedness,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct PointCloud {
+    /// A text file with one point per line, as `x y z` or `x y z r g b`, with
+    /// colors in [0, 255]. The points are in the same coordinate system as the
+    /// part's mesh.
+    pub path: String,
+
+    /// The radius of each point, in the units of the part's mesh.
+    pub radius: f32,
+
+    /// The material of each point. Colors given in the file replace the
+    /// material's color.
+    pub material: Material,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VoxelGrid {
+    /// A raw file with one byte per voxel, where a nonzero byte is a solid
+    /// voxel. The bytes are ordered with x varying fastest, then y, then z.
+    pub path: String,
+
+    /// The number of voxels along the x, y, and z axes.
+    pub resolution: (usize, usize, usize),
+
+    /// The position of the grid's minimum corner, in the coordinate system of
+    /// the part's mesh.
+    pub origin: Point,
+
+    /// The edge length of each voxel, in the units of the part's mesh.
+    pub voxel_size: f32,
+
+    pub material: Material,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Fastener {
+    pub part: FastenerPart,
+
+    /// The fastener's name in the bill of materials. Fasteners without names
+    /// don't get callouts.
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// The position of the fastener's origin, in the coordinate system of the
+    /// part's mesh. A bolt's origin is the center of the underside of its
+    /// head. Other fasteners' origins are the centers of their bottom faces.
+    pub position: Point,
+
+    /// The direction of the fastener's axis, in the coordinate system of the
+    /// part's mesh. A bolt's shank extends along the axis from its origin.
+    pub axis: Point,
+
+    pub material: Material,
+}
+
+/// A standard part. Sizes are ISO metric designations, such as "M8", and
+/// lengths are in millimeters, which are assumed to be the units of the part's
+/// mesh.
+#[derive(Debug, Deserialize)]
+pub enum FastenerPart {
+    HexBolt { size: String, length: f32 },
+    HexNut { size: String },
+    Washer { size: String },
+    Dowel { diameter: f32, length: f32 },
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Material {
     pub color: Rgb,
@@ -59,17 +449,307 @@ pub struct Material {
     pub diffuse: f32,
     pub specular: f32,
     pub shininess: f32,
+
+    /// The fraction of light that the surface blocks, in [0, 1]. Parts that
+    /// are less opaque are rendered as ghosts that show the parts inside and
+    /// behind them. Defaults to 1, which is fully opaque.
+    #[serde(default)]
+    pub opacity: Option<f32>,
+
+    /// How the surface scatters light when the scene is path traced with
+    /// `--path-trace`, which ignores the shading parameters above. The
+    /// material's color is the surface's diffuse color. Defaults to a matte
+    /// surface.
+    #[serde(default)]
+    pub physical: Option<PhysicalMaterial>,
+}
+
+/// A physically based description of how a surface scatters light.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum PhysicalMaterial {
+    /// A purely diffuse surface.
+    Matte {
+        /// The standard deviation of the surface's microfacet orientation
+        /// angle in degrees. Rougher surfaces look flatter. Defaults to 0,
+        /// which is a Lambertian surface.
+        #[serde(default)]
+        roughness: Option<f32>,
+    },
+
+    /// A diffuse surface under a glossy clear coating, like plastic.
+    Plastic {
+        /// The color of the coating's highlights.
+        specular: Rgb,
+
+        /// The roughness of the coating, in [0, 1]. Smaller values give
+        /// sharper highlights.
+        roughness: f32,
+    },
+
+    /// A bare metal surface, such as a machined part. The material's color
+    /// isn't used, since a metal's color comes from its reflections.
+    Metal {
+        metal: Metal,
+
+        /// The roughness of the surface, in [0, 1]. Smaller values give
+        /// sharper reflections, and 0 gives a mirror finish.
+        roughness: f32,
+    },
+
+    /// A smooth, transparent surface, such as a glass lens or a clear plastic
+    /// cover. The material's color tints the light that passes through it.
+    Glass {
+        /// The index of refraction inside the material. Defaults to 1.5,
+        /// which is typical of glasses and clear plastics.
+        #[serde(default)]
+        index_of_refraction: Option<f32>,
+
+        /// Treat the surface as a pane of zero thickness, such as a display
+        /// window or a plastic film, so that light passes straight through
+        /// it rather than being bent. Defaults to false.
+        #[serde(default)]
+        thin: bool,
+
+        /// If present, the Abbe number of the material, which makes its index
+        /// of refraction vary with wavelength so that it splits light into
+        /// colors. This is about 64 for crown glass and 30 for polycarbonate.
+        /// Dispersion is only rendered with `--spectral`.
+        #[serde(default)]
+        abbe_number: Option<f32>,
+    },
 }
 
+/// The metal that a surface is made of.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum Metal {
+    Aluminum,
+    Brass,
+    Copper,
+    Gold,
+    Silver,
+    Steel,
+
+    /// A metal with a measured complex index of refraction.
+    Custom {
+        /// The real part of the index of refraction at red, green, and blue
+        /// wavelengths.
+        eta: Rgb,
+
+        /// The absorption coefficient at red, green, and blue wavelengths.
+        k: Rgb,
+    },
+}
+
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Deserialize)]
 pub enum Light {
     /// A point light source that emits the same amount of light in all directions.
     PointLight {
-        position: Spherical,
+        position: LightPosition,
 
-        /// The amount of power emitted per unit solid angle.
+        /// The amount of power emitted per unit solid angle. Without units,
+        /// this is relative to the scene, in which the part is scaled to fit
+        /// in a unit sphere.
         intensity: Rgb,
+
+        /// If present, each component of the intensity is in these units, and
+        /// light falls off with the square of the distance in meters. The
+        /// part's mesh is assumed to be in millimeters.
+        #[serde(default)]
+        units: Option<IntensityUnit>,
+
+        /// If present, the path of an IES file whose photometric profile
+        /// shapes the light's intensity in each direction, like a real
+        /// fixture. The fixture is aimed at what the light is placed around,
+        /// and the intensity is that of its brightest direction.
+        #[serde(default)]
+        ies_profile: Option<String>,
+
+        /// A name that selects the light with `--solo-light`.
+        #[serde(default)]
+        name: String,
+
+        /// If false, the light is left out of the render. Defaults to true.
+        #[serde(default = "enabled_by_default")]
+        enabled: bool,
+
+        /// If any light is soloed, only the soloed lights are rendered.
+        #[serde(default)]
+        solo: bool,
+
+        /// The name of the light group that the light belongs to. If any light
+        /// is in a group, each group's contribution is also rendered on its
+        /// own and written as a layer of an EXR image next to the output
+        /// image, so the balance of the lighting can be adjusted in
+        /// compositing. Lights without a group are in the `default` layer.
+        #[serde(default)]
+        group: String,
     },
+
+    /// A light that surrounds the scene with the same radiance from every
+    /// direction, like an overcast sky. It fills in shadows, and rays that miss
+    /// the part see its color as the background instead of transparency.
+    SkyLight {
+        color: Rgb,
+
+        /// A factor that the color is multiplied by. Defaults to 1.
+        #[serde(default)]
+        intensity: Option<f32>,
+
+        /// A name that selects the light with `--solo-light`.
+        #[serde(default)]
+        name: String,
+
+        /// If false, the light is left out of the render. Defaults to true.
+        #[serde(default = "enabled_by_default")]
+        enabled: bool,
+
+        /// If any light is soloed, only the soloed lights are rendered.
+        #[serde(default)]
+        solo: bool,
+
+        /// The name of the light group that the light belongs to.
+        #[serde(default)]
+        group: String,
+    },
+
+    /// A light that projects an image onto the part from a point, like a
+    /// slide projector or a structured-light scanner's projector. It's aimed
+    /// at what it's placed around.
+    ProjectionLight {
+        position: LightPosition,
+
+        /// The path of the projected image, whose colors are sRGB. Transparent
+        /// pixels emit no light.
+        image: String,
+
+        /// The amount of power emitted per unit solid angle through a white
+        /// pixel of the image. This is relative to the scene, in which the
+        /// part is scaled to fit in a unit sphere.
+        intensity: Rgb,
+
+        /// The field of view of the image's shorter side in degrees.
+        fov: f32,
+
+        /// The angle in degrees by which the image is rotated about the
+        /// direction that the light points in. Defaults to 0.
+        #[serde(default)]
+        rotation: Option<f32>,
+
+        /// A name that selects the light with `--solo-light`.
+        #[serde(default)]
+        name: String,
+
+        /// If false, the light is left out of the render. Defaults to true.
+        #[serde(default = "enabled_by_default")]
+        enabled: bool,
+
+        /// If any light is soloed, only the soloed lights are rendered.
+        #[serde(default)]
+        solo: bool,
+
+        /// The name of the light group that the light belongs to.
+        #[serde(default)]
+        group: String,
+    },
+
+    /// A light that surrounds the scene, with the radiance arriving from each
+    /// direction read from an equirectangular environment map. Rays that miss
+    /// the part see the map behind it.
+    EnvironmentLight {
+        /// The path of a Radiance HDR (`.hdr`) or uncompressed OpenEXR
+        /// (`.exr`) image. The image's top row is straight up, along the world
+        /// space z axis.
+        path: String,
+
+        /// A factor that every radiance value in the map is multiplied by.
+        /// Defaults to 1.
+        #[serde(default)]
+        intensity: Option<f32>,
+
+        /// The angle in degrees by which the map is rotated about the vertical
+        /// axis. Defaults to 0.
+        #[serde(default)]
+        rotation: Option<f32>,
+
+        /// If present, the map is blurred by about this many degrees, which
+        /// softens the reflections and shadows of small, bright features
+        /// such as the sun.
+        #[serde(default)]
+        blur: Option<f32>,
+
+        /// A name that selects the light with `--solo-light`.
+        #[serde(default)]
+        name: String,
+
+        /// If false, the light is left out of the render. Defaults to true.
+        #[serde(default = "enabled_by_default")]
+        enabled: bool,
+
+        /// If any light is soloed, only the soloed lights are rendered.
+        #[serde(default)]
+        solo: bool,
+
+        /// The name of the light group that the light belongs to.
+        #[serde(default)]
+        group: String,
+    },
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl Light {
+    /// Return the light's name, which is empty if the light isn't named.
+    pub fn name(&self) -> &str {
+        match self {
+            Light::PointLight { name, .. }
+            | Light::ProjectionLight { name, .. }
+            | Light::SkyLight { name, .. }
+            | Light::EnvironmentLight { name, .. } => name,
+        }
+    }
+
+    /// Return whether the light is rendered when no lights are soloed.
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            Light::PointLight { enabled, .. }
+            | Light::ProjectionLight { enabled, .. }
+            | Light::SkyLight { enabled, .. }
+            | Light::EnvironmentLight { enabled, .. } => *enabled,
+        }
+    }
+
+    /// Return the name of the light's group, which is empty if the light isn't
+    /// in a group.
+    pub fn group(&self) -> &str {
+        match self {
+            Light::PointLight { group, .. }
+            | Light::ProjectionLight { group, .. }
+            | Light::SkyLight { group, .. }
+            | Light::EnvironmentLight { group, .. } => group,
+        }
+    }
+
+    /// Return whether the light is soloed.
+    pub fn is_solo(&self) -> bool {
+        match self {
+            Light::PointLight { solo, .. }
+            | Light::ProjectionLight { solo, .. }
+            | Light::SkyLight { solo, .. }
+            | Light::EnvironmentLight { solo, .. } => *solo,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum IntensityUnit {
+    /// Lumens per steradian.
+    Candela,
+
+    /// The total amount of light emitted in every direction.
+    Lumens,
 }
 
 #[derive(Debug, Deserialize)]
@@ -82,11 +762,383 @@ pub enum Camera {
 
         /// Distance between the far clipping plane and the camera.
         z_far: f32,
+
+        /// If present, the camera has a finite aperture and renders with
+        /// depth of field while keeping its parallel projection.
+        #[serde(default)]
+        aperture: Option<Aperture>,
     },
     PerspectiveCamera {
         position: Spherical,
 
         /// Vertical field of view in degrees.
         fov_y: f32,
+
+        /// Distance between the near clipping plane and the camera. Defaults
+        /// to 0.01.
+        #[serde(default)]
+        z_near: Option<f32>,
+
+        /// Distance between the far clipping plane and the camera. Defaults
+        /// to 100.
+        #[serde(default)]
+        z_far: Option<f32>,
+    },
+}
+
+impl Camera {
+    /// Return the distances from the camera to the near and far clipping
+    /// planes.
+    pub fn clipping_range(&self) -> (f32, f32) {
+        match self {
+            Camera::OrthographicCamera { z_near, z_far, .. } => (*z_near, *z_far),
+            Camera::PerspectiveCamera { z_near, z_far, .. } => {
+                (z_near.unwrap_or(0.01), z_far.unwrap_or(100.0))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Aperture {
+    /// The radius of the aperture. The part is scaled to fit in a unit
+    /// sphere, so this is relative to the part's size.
+    pub lens_radius: f32,
+
+    /// The distance from the camera to the plane that is in focus. If this
+    /// isn't given, the distance is computed from `focus`.
+    #[serde(default)]
+    pub focal_distance: Option<f32>,
+
+    /// What the camera is focused on when `focal_distance` isn't given.
+    /// Defaults to the camera's target.
+    #[serde(default)]
+    pub focus: Option<Focus>,
+}
+
+/// Something that the camera focuses on, so that the focal distance is
+/// computed from the camera's pose in each frame.
+#[derive(Debug, Deserialize)]
+pub enum Focus {
+    /// The centroid of the surface of the part or fastener with the name.
+    Part(String),
+
+    /// A point in world space, where the part is centered at the origin and
+    /// scaled to fit in a unit sphere.
+    Point(Point),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PriorityMask {
+    /// Path to an image whose brightness indicates the importance of each
+    /// region of the output image.
+    pub path: String,
+
+    /// The number of sample passes that are rendered for the most important
+    /// regions of the image.
+    pub max_passes: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub enum PostProcess {
+    /// Scale the image's colors as if the exposure were adjusted.
+    Exposure {
+        /// The number of stops to brighten (or darken, if negative) the image.
+        stops: f32,
+    },
+    /// Map radiance from lights in physical units to the image's colors the
+    /// way a camera with the given exposure value at ISO 100 would.
+    ExposureValue { ev100: f32 },
+    /// Map radiance from lights in physical units to the image's colors the
+    /// way a camera with the given settings would.
+    CameraExposure {
+        iso: f32,
+
+        /// The time that the shutter is open, in seconds.
+        shutter: f32,
+
+        /// The ratio of the lens's focal length to its aperture's diameter.
+        f_number: f32,
+    },
+    /// Remap the image's colors through a piecewise-linear tone curve.
+    Curves {
+        /// The `(input, output)` control points of the curve.
+        points: Vec<(f32, f32)>,
+    },
+    /// Exaggerate edges with an unsharp mask.
+    Sharpen { amount: f32 },
+    /// Composite the image over a solid background color.
+    Background { color: Rgb },
+    /// Compress bright colors into the displayable range so that highlights
+    /// roll off smoothly instead of clipping.
+    Tonemap {
+        operator: TonemapOperator,
+
+        /// The number of stops to brighten (or darken, if negative) the image
+        /// before it is tonemapped. Defaults to 0.
+        #[serde(default)]
+        exposure: f32,
+
+        /// The display gamma that the tonemapped colors are encoded for, such
+        /// as 2.2. Defaults to 1, which leaves the colors linear. The output
+        /// image is already sRGB encoded unless `linear_output` is set, so
+        /// other gammas are only useful with linear output.
+        #[serde(default = "linear_gamma")]
+        gamma: f32,
+    },
+}
+
+fn linear_gamma() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum TonemapOperator {
+    /// Leave colors unchanged, so they clip at 1.
+    Clip,
+
+    /// Compress colors by their luminance, preserving their hue.
+    Reinhard,
+
+    /// Like `Reinhard`, but colors at least as bright as `white` become white.
+    ReinhardExtended { white: f32 },
+
+    /// A filmic curve with a soft toe and shoulder.
+    AcesFilmic,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum Aov {
+    /// World-space shading normals, mapped from [-1, 1] to [0, 1].
+    Normal,
+
+    /// Distances from the camera, scaled so that the farthest surface is
+    /// white.
+    Depth,
+
+    /// The reflectance of the visible surfaces.
+    Albedo,
+
+    /// A distinct color for each object.
+    ObjectId,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum BitDepth {
+    Eight,
+    Sixteen,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum TileShape {
+    /// 16-by-16 pixel tiles.
+    Square,
+
+    /// Single rows of pixels with the given length, which finish sooner when
+    /// the cost of rendering is concentrated in a small part of the image.
+    Scanline { length: usize },
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum CropWindow {
+    /// A window whose corners are given as fractions of the image's width and
+    /// height, in [0, 1].
+    Normalized {
+        x_min: f32,
+        y_min: f32,
+        x_max: f32,
+        y_max: f32,
+    },
+
+    /// A window whose corners are given in pixels. The maximums are
+    /// exclusive.
+    Pixels {
+        x_min: i32,
+        y_min: i32,
+        x_max: i32,
+        y_max: i32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum TileOrder {
+    /// Row by row from the top.
+    Scanline,
+
+    /// Several passes over the tiles, each of which renders a different
+    /// portion of the pixels, chosen so that every pass is spread evenly
+    /// over the image. Snapshots of a partial render show the whole image
+    /// filling in.
+    Progressive,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum LightSampling {
+    /// Every light is sampled at each surface interaction.
+    All,
+
+    /// A single light is sampled at each surface interaction, chosen with
+    /// probability proportional to its power. This makes each path much
+    /// cheaper in scenes with many lights, and spends most samples on the
+    /// brightest ones.
+    Power,
+
+    /// A single light is sampled at each surface interaction, chosen by a
+    /// hierarchy over the lights that favors the ones that are bright, near,
+    /// and facing the interaction. This suits scenes with many small lights,
+    /// most of which are far from any given point.
+    Bvh,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum Filter {
+    /// Weights every sample within the filter's extent equally.
+    BoxFilter { half_width: f32, half_height: f32 },
+
+    /// Weights samples by how close they are to the pixel's center, falling
+    /// off linearly to zero at the filter's edges.
+    TriangleFilter { half_width: f32, half_height: f32 },
+
+    /// A Mitchell-Netravali filter, which trades off blurring against ringing
+    /// with its `b` and `c` parameters.
+    MitchellFilter {
+        half_width: f32,
+        half_height: f32,
+        b: f32,
+        c: f32,
     },
 }
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum ColorSpace {
+    Srgb,
+
+    /// The wider gamut of many recent displays. Images in this color space
+    /// look desaturated in viewers that ignore their color profile.
+    DisplayP3,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ExposureCheck {
+    /// The greatest fraction of the pixels that surfaces cover that may have
+    /// a blown out color component before a warning is printed. Defaults to
+    /// 0.01.
+    #[serde(default)]
+    pub max_clipped_fraction: Option<f32>,
+
+    /// The greatest fraction of the pixels that surfaces cover that may be
+    /// crushed to black before a warning is printed. Defaults to 0.05.
+    #[serde(default)]
+    pub max_crushed_fraction: Option<f32>,
+
+    /// If true, an image that marks blown out pixels in red and crushed
+    /// pixels in blue is written next to the output, such as
+    /// `part.clipping.png`.
+    #[serde(default)]
+    pub overlay: bool,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Callouts {
+    /// The radius of each balloon in pixels of the output image. Defaults to
+    /// 12.
+    #[serde(default)]
+    pub balloon_radius: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct LineArt {
+    /// Edges between triangles whose normals differ by more than this angle,
+    /// in degrees, are drawn as creases. Defaults to 30.
+    #[serde(default)]
+    pub crease_angle: Option<f32>,
+
+    /// If true, edges that are hidden behind surfaces are drawn as gray dashed
+    /// lines. Defaults to false.
+    #[serde(default)]
+    pub hidden_lines: Option<bool>,
+
+    /// The width of the lines in pixels of the output image. Defaults to 1.
+    #[serde(default)]
+    pub stroke_width: Option<f32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Supersampling {
+    /// The number of rendered pixels along each axis of an output pixel.
+    pub factor: usize,
+
+    /// The filter that weights rendered pixels when they're downsampled. Its
+    /// extent is measured in output pixels. Defaults to a Mitchell filter
+    /// with a radius of two pixels.
+    #[serde(default)]
+    pub filter: Option<Filter>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum NegativeLobes {
+    /// Use the filter's weights as they are.
+    Keep,
+
+    /// Clamp reconstructed pixels to zero, removing the dark fringes that
+    /// negative lobes leave around bright silhouettes.
+    ClampPixels,
+
+    /// Treat negative filter weights as zero, which avoids fringes at the cost
+    /// of a slightly softer image.
+    ClampKernel,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Animation {
+    pub frames_per_second: f32,
+
+    /// The keyframes of the camera path. These must be sorted by time.
+    pub keyframes: Vec<CameraKeyframe>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CameraKeyframe {
+    /// The time of the keyframe in seconds.
+    pub time: f32,
+
+    /// The position of the camera relative to `target`.
+    pub position: Spherical,
+
+    /// The point that the camera looks at. Defaults to the origin, where the
+    /// part is centered.
+    #[serde(default)]
+    pub target: Point,
+
+    /// How the camera accelerates as it leaves this keyframe for the next.
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Stereo {
+    pub layout: StereoLayout,
+
+    /// The angle, in degrees, between the two eyes as seen from the point the
+    /// camera looks at.
+    pub eye_angle: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum StereoLayout {
+    /// A red-cyan anaglyph for viewing with colored glasses.
+    Anaglyph,
+
+    /// The left and right eye's views next to each other.
+    SideBySide,
+}
@@ -13,9 +13,28 @@ pub enum Error {
     ParseInt(std::num::ParseIntError),
     ParseFloat(std::num::ParseFloatError),
     Image(image::ImageError),
+    Png(png::EncodingError),
+    Tiff(tiff::TiffError),
     ImageContainerTooSmall,
     EmptyMesh,
     ZeroAreaImage,
+    Expression(String),
+    PointCloud(String),
+    VoxelGrid(String),
+    FastenerSize(String),
+    PixelCoordinates(String),
+    SamplerConfig(String),
+    Duration(String),
+    Animation(String),
+    LightKey(String),
+    MeshKey(String),
+    FocusPart(String),
+    LightPart(String),
+    NoFrames,
+    Tiles(String),
+    JobIndex(usize),
+    ImageResolutions((u32, u32), (u32, u32)),
+    ColorProfileFormat(String),
 }
 
 impl fmt::Display for Error {
@@ -27,11 +46,56 @@ impl fmt::Display for Error {
             Error::ParseInt(e) => write!(f, "Error parsing integer: {:?}", e),
             Error::ParseFloat(e) => write!(f, "Error parsing float: {:?}", e),
             Error::Image(e) => write!(f, "Error performing image operation: {:?}", e),
+            Error::Png(e) => write!(f, "Error writing PNG: {:?}", e),
+            Error::Tiff(e) => write!(f, "Error writing TIFF: {:?}", e),
             Error::ImageContainerTooSmall => {
                 write!(f, "The container for the image data is too small.")
             }
             Error::EmptyMesh => write!(f, "Mesh is empty."),
             Error::ZeroAreaImage => write!(f, "Image has an area of zero."),
+            Error::Expression(message) => write!(f, "Error evaluating expression: {}", message),
+            Error::PointCloud(message) => write!(f, "Error reading point cloud: {}", message),
+            Error::VoxelGrid(message) => write!(f, "Error reading voxel grid: {}", message),
+            Error::FastenerSize(size) => write!(f, "Unknown fastener size: {}", size),
+            Error::SamplerConfig(message) => write!(f, "Invalid sampler: {}", message),
+            Error::PixelCoordinates(value) => {
+                write!(f, "Expected pixel coordinates as X,Y but found: {}", value)
+            }
+            Error::LightKey(key) => write!(f, "No light has the name or index: {}", key),
+            Error::MeshKey(key) => write!(
+                f,
+                "Expected part or a fastener's zero-based index but found: {}",
+                key
+            ),
+            Error::Tiles(value) => write!(
+                f,
+                "Expected tiles as COLUMNSxROWS, such as 4x2, but found: {}",
+                value
+            ),
+            Error::JobIndex(index) => write!(f, "No job has the index: {}", index),
+            Error::ImageResolutions(a, b) => write!(
+                f,
+                "Only images with the same resolution can be compared, but found {}x{} and {}x{}",
+                a.0, a.1, b.0, b.1
+            ),
+            Error::FocusPart(name) => write!(f, "No part or fastener has the name: {}", name),
+            Error::NoFrames => write!(f, "The config doesn't describe any frames"),
+            Error::LightPart(name) => write!(
+                f,
+                "A light is placed relative to a part, but no part or fastener has the name: {}",
+                name
+            ),
+            Error::ColorProfileFormat(path) => write!(
+                f,
+                "Color profiles can only be embedded in PNG and TIFF images: {}",
+                path
+            ),
+            Error::Duration(value) => write!(
+                f,
+                "Expected a duration such as 90s, 1.5m, or 500ms but found: {}",
+                value
+            ),
+            Error::Animation(reason) => write!(f, "Invalid animation: {}", reason),
         }
     }
 }
@@ -45,9 +109,28 @@ impl error::Error for Error {
             Error::ParseInt(e) => Some(e),
             Error::ParseFloat(e) => Some(e),
             Error::Image(e) => Some(e),
+            Error::Png(e) => Some(e),
+            Error::Tiff(e) => Some(e),
             Error::ImageContainerTooSmall => None,
             Error::EmptyMesh => None,
             Error::ZeroAreaImage => None,
+            Error::Expression(_) => None,
+            Error::PointCloud(_) => None,
+            Error::VoxelGrid(_) => None,
+            Error::FastenerSize(_) => None,
+            Error::PixelCoordinates(_) => None,
+            Error::SamplerConfig(_) => None,
+            Error::Duration(_) => None,
+            Error::Animation(_) => None,
+            Error::LightKey(_) => None,
+            Error::MeshKey(_) => None,
+            Error::FocusPart(_) => None,
+            Error::LightPart(_) => None,
+            Error::NoFrames => None,
+            Error::Tiles(_) => None,
+            Error::JobIndex(_) => None,
+            Error::ImageResolutions(_, _) => None,
+            Error::ColorProfileFormat(_) => None,
         }
     }
 }
@@ -87,3 +170,15 @@ impl From<image::ImageError> for Error {
         Error::Image(error)
     }
 }
+
+impl From<png::EncodingError> for Error {
+    fn from(error: png::EncodingError) -> Self {
+        Error::Png(error)
+    }
+}
+
+impl From<tiff::TiffError> for Error {
+    fn from(error: tiff::TiffError) -> Self {
+        Error::Tiff(error)
+    }
+}
@@ -0,0 +1,266 @@
+//! Evaluates the arithmetic expressions that config values may use to vary
+//! with the frame index, such as `"30 + frame * 2"`.
+//!
+//! Expressions support numbers, the `frame` variable, the binary operators
+//! `+`, `-`, `*`, `/`, `%`, and `^`, unary negation, parentheses, and the
+//! functions `sin`, `cos`, `abs`, `sqrt`, `min`, and `max`. The trigonometric
+//! functions take angles in degrees, like the rest of the config.
+
+use crate::error::{Error, Result};
+
+/// Evaluate the expression with the `frame` variable bound to the given frame
+/// index.
+pub fn evaluate(source: &str, frame: usize) -> Result<f32> {
+    let mut parser = Parser {
+        source,
+        chars: source.char_indices().peekable(),
+        frame: frame as f32,
+    };
+    let value = parser.expression()?;
+    parser.skip_whitespace();
+    match parser.chars.peek() {
+        None => Ok(value),
+        Some(&(i, c)) => Err(parser.error(&format!("unexpected '{}' at {}", c, i))),
+    }
+}
+
+/// A recursive descent parser that evaluates the expression as it is parsed.
+struct Parser<'a> {
+    source: &'a str,
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    frame: f32,
+}
+
+impl<'a> Parser<'a> {
+    /// expression = term (("+" | "-") term)*
+    fn expression(&mut self) -> Result<f32> {
+        let mut value = self.term()?;
+        loop {
+            if self.consume('+') {
+                value += self.term()?;
+            } else if self.consume('-') {
+                value -= self.term()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// term = unary (("*" | "/" | "%") unary)*
+    fn term(&mut self) -> Result<f32> {
+        let mut value = self.unary()?;
+        loop {
+            if self.consume('*') {
+                value *= self.unary()?;
+            } else if self.consume('/') {
+                value /= self.unary()?;
+            } else if self.consume('%') {
+                value = value.rem_euclid(self.unary()?);
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// unary = "-" unary | power
+    fn unary(&mut self) -> Result<f32> {
+        if self.consume('-') {
+            Ok(-self.unary()?)
+        } else {
+            self.power()
+        }
+    }
+
+    /// power = primary ("^" unary)?
+    fn power(&mut self) -> Result<f32> {
+        let base = self.primary()?;
+        if self.consume('^') {
+            Ok(base.powf(self.unary()?))
+        } else {
+            Ok(base)
+        }
+    }
+
+    /// primary = number | "frame" | function "(" arguments ")" | "(" expression ")"
+    fn primary(&mut self) -> Result<f32> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some(&(_, c)) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some(&(_, c)) if c.is_ascii_alphabetic() => {
+                let name = self.identifier();
+                if name == "frame" {
+                    Ok(self.frame)
+                } else {
+                    self.function(&name)
+                }
+            }
+            Some(&(_, '(')) => {
+                self.chars.next();
+                let value = self.expression()?;
+                self.expect(')')?;
+                Ok(value)
+            }
+            Some(&(i, c)) => Err(self.error(&format!("unexpected '{}' at {}", c, i))),
+            None => Err(self.error("unexpected end of expression")),
+        }
+    }
+
+    fn function(&mut self, name: &str) -> Result<f32> {
+        if !self.consume('(') {
+            return Err(self.error(&format!("unknown variable '{}'", name)));
+        }
+        let mut arguments = vec![self.expression()?];
+        while self.consume(',') {
+            arguments.push(self.expression()?);
+        }
+        self.expect(')')?;
+
+        match (name, arguments.as_slice()) {
+            ("sin", [x]) => Ok(x.to_radians().sin()),
+            ("cos", [x]) => Ok(x.to_radians().cos()),
+            ("abs", [x]) => Ok(x.abs()),
+            ("sqrt", [x]) => Ok(x.sqrt()),
+            ("min", [x, y]) => Ok(x.min(*y)),
+            ("max", [x, y]) => Ok(x.max(*y)),
+            ("sin", _) | ("cos", _) | ("abs", _) | ("sqrt", _) | ("min", _) | ("max", _) => {
+                Err(self.error(&format!("wrong number of arguments to '{}'", name)))
+            }
+            _ => Err(self.error(&format!("unknown function '{}'", name))),
+        }
+    }
+
+    fn number(&mut self) -> Result<f32> {
+        let mut digits = String::new();
+        while let Some(&(_, c)) = self.chars.peek() {
+            if !(c.is_ascii_digit() || c == '.') {
+                break;
+            }
+            digits.push(c);
+            self.chars.next();
+        }
+        Ok(digits.parse::<f32>()?)
+    }
+
+    fn identifier(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(&(_, c)) = self.chars.peek() {
+            if !(c.is_ascii_alphanumeric() || c == '_') {
+                break;
+            }
+            name.push(c);
+            self.chars.next();
+        }
+        name
+    }
+
+    /// Consume the next non-whitespace character if it is `c`. Return whether
+    /// it was consumed.
+    fn consume(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some(&(_, next)) if next == c => {
+                self.chars.next();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.consume(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c)))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(&(_, c)) = self.chars.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.chars.next();
+        }
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::Expression(format!("{} in \"{}\"", message, self.source))
+    }
+}
+
+#[cfg(test)]
+mod expression_tests {
+    use super::evaluate;
+    use crate::error::Error;
+
+    fn assert_evaluates(source: &str, frame: usize, expected: f32) {
+        let value = evaluate(source, frame).unwrap();
+        assert!(
+            (value - expected).abs() < 1e-4,
+            "\"{}\" evaluated to {}, not {}",
+            source,
+            value,
+            expected
+        );
+    }
+
+    fn assert_error(source: &str, message: &str) {
+        match evaluate(source, 0) {
+            Err(Error::Expression(error)) => assert!(
+                error.contains(message),
+                "\"{}\" failed with \"{}\", which doesn't mention \"{}\"",
+                source,
+                error,
+                message
+            ),
+            result => panic!("\"{}\" should have failed, not {:?}", source, result),
+        }
+    }
+
+    #[test]
+    fn operators_follow_precedence_and_associativity() {
+        assert_evaluates("30 + frame * 2", 5, 40.0);
+        assert_evaluates("(30 + frame) * 2", 5, 70.0);
+        assert_evaluates("10 - 4 - 3", 0, 3.0);
+        assert_evaluates("24 / 4 / 2", 0, 3.0);
+        assert_evaluates("1 + 7 % 3 * 2", 0, 3.0);
+        assert_evaluates("2 ^ 3 ^ 2", 0, 512.0);
+        assert_evaluates("2 * 3 ^ 2", 0, 18.0);
+    }
+
+    #[test]
+    fn unary_minus_and_power() {
+        assert_evaluates("-2 ^ 2", 0, -4.0);
+        assert_evaluates("(-2) ^ 2", 0, 4.0);
+        assert_evaluates("2 ^ -1", 0, 0.5);
+        assert_evaluates("--3", 0, 3.0);
+        assert_evaluates("4 - -frame", 2, 6.0);
+        assert_evaluates("-1 % 3", 0, 2.0);
+    }
+
+    #[test]
+    fn functions_take_degrees_and_check_arity() {
+        assert_evaluates("sin(90)", 0, 1.0);
+        assert_evaluates("cos(frame * 60)", 1, 0.5);
+        assert_evaluates("abs(-2) + sqrt(16)", 0, 6.0);
+        assert_evaluates("min(3, frame) + max(3, frame)", 5, 8.0);
+        assert_error("sin(1, 2)", "wrong number of arguments to 'sin'");
+        assert_error("max(1)", "wrong number of arguments to 'max'");
+    }
+
+    #[test]
+    fn unknown_identifiers_are_errors() {
+        assert_error("time * 2", "unknown variable 'time'");
+        assert_error("tan(45)", "unknown function 'tan'");
+    }
+
+    #[test]
+    fn malformed_input_is_an_error() {
+        assert_error("", "unexpected end of expression");
+        assert_error("1 +", "unexpected end of expression");
+        assert_error("(1 + 2", "expected ')'");
+        assert_error("1 2", "unexpected '2' at 2");
+        assert_error("2 * # 3", "unexpected '#' at 4");
+        assert!(evaluate("1.2.3", 0).is_err());
+    }
+}
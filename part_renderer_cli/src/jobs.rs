@@ -0,0 +1,180 @@
+//! Splits a render into independent jobs, such as the tiles of each frame,
+//! that a render farm's scheduler can run on separate machines, and describes
+//! them in a JSON manifest.
+
+use crate::callout::escape_json;
+use crate::error::{Error, Result};
+use std::io::Write;
+
+/// The grid of tiles that each frame is split into.
+#[derive(Debug, Clone, Copy)]
+pub struct Tiles {
+    pub columns: usize,
+    pub rows: usize,
+}
+
+impl Tiles {
+    /// A single tile that covers the whole frame, so that each frame is one
+    /// job.
+    pub fn whole_frame() -> Self {
+        Self {
+            columns: 1,
+            rows: 1,
+        }
+    }
+
+    /// Parse a grid given as `COLUMNSxROWS`, such as `4x2`.
+    pub fn parse(value: &str) -> Result<Self> {
+        let mut parts = value.split('x');
+        match (
+            parts.next().and_then(|s| s.trim().parse().ok()),
+            parts.next().and_then(|s| s.trim().parse().ok()),
+            parts.next(),
+        ) {
+            (Some(columns), Some(rows), None) if columns > 0 && rows > 0 => {
+                Ok(Self { columns, rows })
+            }
+            _ => Err(Error::Tiles(value.to_string())),
+        }
+    }
+}
+
+/// A window of pixels in the output image. The maximums are exclusive.
+#[derive(Debug, Clone, Copy)]
+pub struct TileWindow {
+    pub x_min: i32,
+    pub y_min: i32,
+    pub x_max: i32,
+    pub y_max: i32,
+}
+
+/// A piece of the render that can be rendered on its own.
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub index: usize,
+
+    /// The index of the frame that the job renders part of. The frame's index
+    /// also seeds its samplers, so a job renders exactly the pixels that the
+    /// whole render would.
+    pub frame: usize,
+
+    /// The pixels of the frame that the job renders, or `None` if it renders
+    /// the whole frame.
+    pub tile: Option<TileWindow>,
+
+    /// The path of the image that the job writes.
+    pub output_path: String,
+}
+
+/// Split the frames into jobs, with one job for each tile of each frame.
+/// Frames are split into rows and columns of nearly equal size.
+///
+/// * frames - The index and output path of each frame.
+/// * width - The width of the output image in pixels.
+/// * height - The height of the output image in pixels.
+pub fn split(frames: &[(usize, String)], width: usize, height: usize, tiles: Tiles) -> Vec<Job> {
+    let is_tiled = tiles.columns > 1 || tiles.rows > 1;
+    let edge = |i: usize, count: usize, size: usize| (i * size / count) as i32;
+    let mut jobs = vec![];
+    for (frame, frame_output_path) in frames {
+        for row in 0..tiles.rows {
+            for column in 0..tiles.columns {
+                let tile_index = row * tiles.columns + column;
+                let index = jobs.len();
+                jobs.push(Job {
+                    index,
+                    frame: *frame,
+                    tile: if is_tiled {
+                        Some(TileWindow {
+                            x_min: edge(column, tiles.columns, width),
+                            y_min: edge(row, tiles.rows, height),
+                            x_max: edge(column + 1, tiles.columns, width),
+                            y_max: edge(row + 1, tiles.rows, height),
+                        })
+                    } else {
+                        None
+                    },
+                    output_path: if is_tiled {
+                        crate::auxiliary_output_path(
+                            frame_output_path,
+                            &format!("tile{}", tile_index),
+                        )
+                    } else {
+                        frame_output_path.clone()
+                    },
+                });
+            }
+        }
+    }
+    jobs
+}
+
+/// Write the manifest, which describes each job as the command that renders
+/// it, the frame and tile that it renders, its seed, and its output image. If
+/// the frames are tiled, the manifest also describes the command that merges
+/// the tiles' images into the frames' images once every job has finished.
+///
+/// * job_arguments - The arguments that every job's command starts with. The
+///   job's index is appended to them.
+/// * merge_arguments - The arguments of the command that merges the tiles, if
+///   the frames are tiled.
+/// * frames - The index and output path of each frame.
+pub fn write_manifest<W: Write>(
+    writer: &mut W,
+    jobs: &[Job],
+    job_arguments: &[String],
+    merge_arguments: Option<&[String]>,
+    frames: &[(usize, String)],
+) -> Result<()> {
+    writeln!(writer, "{{")?;
+    writeln!(writer, "  \"jobs\": [")?;
+    for (i, job) in jobs.iter().enumerate() {
+        let mut arguments = job_arguments.to_vec();
+        arguments.push("--job".to_string());
+        arguments.push(job.index.to_string());
+        let tile = match job.tile {
+            Some(tile) => format!(
+                "{{\"x_min\": {}, \"y_min\": {}, \"x_max\": {}, \"y_max\": {}}}",
+                tile.x_min, tile.y_min, tile.x_max, tile.y_max
+            ),
+            None => "null".to_string(),
+        };
+        let separator = if i + 1 < jobs.len() { "," } else { "" };
+        writeln!(
+            writer,
+            "    {{\"index\": {}, \"frame\": {}, \"seed\": {}, \"tile\": {}, \"output\": \"{}\", \"command\": {}}}{}",
+            job.index,
+            job.frame,
+            job.frame,
+            tile,
+            escape_json(&job.output_path),
+            json_strings(&arguments),
+            separator
+        )?;
+    }
+    writeln!(writer, "  ],")?;
+    match merge_arguments {
+        Some(merge_arguments) => {
+            let outputs: Vec<String> = frames.iter().map(|(_, path)| path.clone()).collect();
+            let job_indices: Vec<String> = jobs.iter().map(|job| job.index.to_string()).collect();
+            writeln!(
+                writer,
+                "  \"merge\": {{\"after_jobs\": [{}], \"outputs\": {}, \"command\": {}}}",
+                job_indices.join(", "),
+                json_strings(&outputs),
+                json_strings(merge_arguments)
+            )?;
+        }
+        None => writeln!(writer, "  \"merge\": null")?,
+    }
+    writeln!(writer, "}}")?;
+    Ok(())
+}
+
+fn json_strings(strings: &[String]) -> String {
+    let quoted: Vec<String> = strings
+        .iter()
+        .map(|s| format!("\"{}\"", escape_json(s)))
+        .collect();
+    format!("[{}]", quoted.join(", "))
+}
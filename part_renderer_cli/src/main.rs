@@ -1,26 +1,89 @@
+mod animation;
+mod callout;
+mod color_profile;
 mod config;
 mod error;
+mod expression;
+mod jobs;
+mod placement;
+mod point_cloud;
+mod quality;
+mod report;
+mod sampler;
 
 use cgmath::{
-    point2, point3, vec2, vec3, Deg, InnerSpace, Matrix, Matrix4, Point2, Point3, Rad, Transform,
-    Vector2, Vector3,
+    point2, point3, vec2, vec3, Deg, InnerSpace, Matrix4, Point2, Point3, Quaternion, Rad,
+    SquareMatrix, Transform, Vector2, Vector3,
 };
 use error::{Error, Result};
-use image::{imageops, ImageBuffer, Rgba};
+use image::{imageops, EncodableLayout, GenericImageView, ImageBuffer, Primitive, Rgba};
+use jobs::Tiles;
+use mesh::fastener::{self, MetricSize};
 use mesh::{Mesh, MeshBuilder};
+use renderer::bake::Baker;
+use renderer::camera::clipping;
 use renderer::camera::Camera;
-use renderer::color::RgbaSpectrum;
-use renderer::filter::MitchellFilter;
+use renderer::camera::TelecentricCamera;
+use renderer::color::{self, ColorRamp, RgbaSpectrum};
+use renderer::filter::{BoxFilter, Filter, MitchellFilter, TriangleFilter};
 use renderer::integrator::WhittedRayTracer;
-use renderer::light::{self, Light};
-use renderer::sampler::{ConstantSampler, IncrementalSampler, StratifiedSampler};
+use renderer::integrator::{
+    Aov, ClayRayTracer, ContactShadowRayTracer, PathTracer, PriorityMask, RayTracer, RenderOptions,
+    SnapshotInterval, Snapshots, SpectralPathTracer, TileOrder, TileShape,
+};
+use renderer::light::{self, IntensityUnit, Light, LightBvh, LightDistribution};
+use renderer::line_art;
+use renderer::material::{Alloy, GlassMaterial, MatteMaterial, MetalMaterial, PlasticMaterial};
+use renderer::postprocess::{
+    BackgroundComposite, Curves, Exposure, PostProcess, Sharpen, Tonemap, TonemapOperator,
+};
+use renderer::preview::{self, PreviewShading};
+use renderer::probe;
+use renderer::sampler::{
+    AdaptiveSampler, AdaptiveSampling, BlueNoiseSampler, ConstantSampler, HaltonSampler,
+    IncrementalSampler, Pmj02Sampler, SampleDump, StratifiedSampler,
+};
+use renderer::shape::{Shape, Splat, VoxelGrid};
 use renderer::simple::{Material, OriginalRayTracer, PrimitiveAggregate, Scene};
-use renderer::{camera::OrthographicCamera, film::Film};
+use renderer::stereo::{self, StereoLayout};
+use renderer::swatch;
+use renderer::{
+    camera::OrthographicCamera,
+    film::{
+        self, ColorSpace, Downsampling, Film, LuminanceHistogram, NegativeLobes, TransferFunction,
+    },
+};
+use sampler::ConfiguredSampler;
 use std::cmp;
 use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+use std::time::{Duration, Instant};
 use typed_arena::Arena;
 
+use crate::animation::{CameraPose, SphericalPosition};
+use crate::callout::LabeledPart;
 use crate::config::Config;
+use crate::placement::SceneGeometry;
+use crate::quality::Quality;
+
+/// How far above each texel of a UV render the rays that see the surface
+/// start, in world units.
+const UV_CAGE_DISTANCE: f32 = 1e-3;
+
+/// The gap in texels that is left around each triangle of a generated UV
+/// layout.
+const UV_PADDING: f32 = 1.0;
+
+/// The number of bins in the luminance histograms that exposure checks
+/// print.
+const EXPOSURE_HISTOGRAM_BINS: usize = 10;
+
+/// The radius in pixels of the balloons that callouts are drawn with when the
+/// config doesn't give one.
+const DEFAULT_BALLOON_RADIUS: f32 = 12.0;
+
+/// The farthest that the part reaches to darken the ground in a shadow-only
+/// render, in world units. The part is scaled to fit in a unit sphere.
+const CONTACT_SHADOW_DISTANCE: f32 = 0.5;
 
 fn main() -> Result<()> {
     let matches = clap::App::new("Part Viewer")
@@ -32,72 +95,1885 @@ fn main() -> Result<()> {
                 .required(true)
                 .index(1),
         )
+        .arg(clap::Arg::with_name("clay").long("clay").help(
+            "Render the part as matte gray clay, ignoring the configured material and lights.",
+        ))
+        .arg(clap::Arg::with_name("path-trace").long("path-trace").help(
+            "Path trace the scene with the configured materials' physically based descriptions, which account for glossy reflections and indirect lighting.",
+        ))
+        .arg(
+            clap::Arg::with_name("material-statistics")
+                .long("material-statistics")
+                .requires("path-trace")
+                .help("Print a table of the samples, rays, and time that the path tracer spent on each material after each image is rendered, to find which material is responsible for a slow render."),
+        )
+        .arg(clap::Arg::with_name("spectral").long("spectral").help(
+            "Path trace the scene like --path-trace, but carry full spectra rather than RGB colors along each path, so that glass with an Abbe number splits light into colors.",
+        ))
+        .arg(clap::Arg::with_name("shadow-only").long("shadow-only").help(
+            "Render only a soft contact shadow on the ground beneath the part, seen from the configured camera, as a black image whose alpha is the shadow's opacity. The shadow can be composited under the part in a real-time 3D viewer.",
+        ))
+        .arg(
+            clap::Arg::with_name("draft")
+                .long("draft")
+                .help("Quickly rasterize a rough preview of the part instead of ray tracing it."),
+        )
+        .arg(clap::Arg::with_name("dry-run").long("dry-run").help(
+            "Load the scene and print a report about its bounds, framing, and lights without rendering it.",
+        ))
+        .arg(
+            clap::Arg::with_name("dump-samples")
+                .long("dump-samples")
+                .value_name("X,Y")
+                .help("Write the configured sampler's sample vectors for the pixel to a CSV file and a scatter plot next to the output image, without rendering."),
+        )
+        .arg(
+            clap::Arg::with_name("uv")
+                .long("uv")
+                .value_name("MESH")
+                .help("Render the lighting on the surface of a mesh into its UV layout instead of rendering the camera's view. MESH is `part` or a fastener's zero-based index. Meshes without UV coordinates, such as STL parts, are given a layout with a chart for each triangle."),
+        )
+        .arg(
+            clap::Arg::with_name("quality")
+                .long("quality")
+                .value_name("PRESET")
+                .possible_values(&Quality::NAMES)
+                .help("Replace the configured sampler, maximum path depth, and filter with a preset that trades render time for quality."),
+        )
+        .arg(
+            clap::Arg::with_name("solo-light")
+                .long("solo-light")
+                .value_name("KEY")
+                .multiple(true)
+                .number_of_values(1)
+                .help("Render only the light with the given name or zero-based index. Can be given more than once, and overrides the configured enabled and solo flags."),
+        )
+        .arg(
+            clap::Arg::with_name("verbose")
+                .long("verbose")
+                .help("Print a summary of the scene, including a breakdown of the memory its geometry and textures need, before rendering it."),
+        )
+        .arg(
+            clap::Arg::with_name("emit-jobs")
+                .long("emit-jobs")
+                .value_name("MANIFEST")
+                .help("Write a JSON manifest that splits the render into independent jobs, one for each tile of each frame, instead of rendering. Each job is described by the command that renders it, and a render farm's scheduler can run them on separate machines."),
+        )
+        .arg(tiles_arg())
+        .arg(
+            clap::Arg::with_name("job")
+                .long("job")
+                .value_name("INDEX")
+                .help("Render only the job with the zero-based index from the manifest that --emit-jobs writes. The same --tiles must be given."),
+        )
+        .arg(
+            clap::Arg::with_name("time-budget")
+                .long("time-budget")
+                .value_name("DURATION")
+                .help("Stop sampling each image once the duration, such as 30s or 2m, has elapsed, and write the image with the samples taken so far."),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("merge")
+                .about("Merge the images of the tiles that jobs rendered into the frames' images.")
+                .arg(
+                    clap::Arg::with_name("CONFIG")
+                        .help("Path to the RON configuration file that the jobs rendered.")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(tiles_arg()),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("compare")
+                .about("Compare two renders of the same image, and print their PSNR, SSIM, and RMSE. The images' stored values are compared, so they should have the same encoding.")
+                .arg(
+                    clap::Arg::with_name("A")
+                        .help("Path to the first image, such as a reference.")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::with_name("B")
+                        .help("Path to the second image.")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(
+                    clap::Arg::with_name("out")
+                        .long("out")
+                        .value_name("IMAGE")
+                        .help("Write a heat map of the error of each pixel to the path."),
+                )
+                .arg(
+                    clap::Arg::with_name("max-error")
+                        .long("max-error")
+                        .value_name("ERROR")
+                        .help("The error that's hottest in the heat map. Defaults to the greatest error in the image."),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("swatch")
+                .about("Render a swatch of a material, which is a ball of the material on a checkered floor under a fixed studio environment, so materials can be reviewed independent of any part. The material is path traced with its physical description.")
+                .arg(
+                    clap::Arg::with_name("MATERIAL")
+                        .help("Path to a RON file that describes a single material, in the same form as a part's material in a configuration file.")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::with_name("out")
+                        .long("out")
+                        .value_name("IMAGE")
+                        .help("Write the swatch to the path. Defaults to the material's path with a .png extension."),
+                )
+                .arg(
+                    clap::Arg::with_name("size")
+                        .long("size")
+                        .value_name("PIXELS")
+                        .help("The width and height of the swatch. Defaults to 256."),
+                )
+                .arg(
+                    clap::Arg::with_name("samples")
+                        .long("samples")
+                        .value_name("COUNT")
+                        .help("The number of samples taken in each pixel. Defaults to 64."),
+                ),
+        )
+        .setting(clap::AppSettings::SubcommandsNegateReqs)
         .get_matches();
 
-    // The CONFIG argument is required by Clap, so unwrapping is ok.
+    if let Some(merge_matches) = matches.subcommand_matches("merge") {
+        // The CONFIG argument is required by Clap, so unwrapping is ok.
+        let config = load_config(merge_matches.value_of("CONFIG").unwrap())?;
+        return merge_jobs_from_config(&config, tiles(merge_matches)?);
+    }
+
+    if let Some(compare_matches) = matches.subcommand_matches("compare") {
+        return compare_images(compare_matches);
+    }
+
+    if let Some(swatch_matches) = matches.subcommand_matches("swatch") {
+        return render_material_swatch(swatch_matches);
+    }
+
+    // The CONFIG argument is required by Clap unless a subcommand is given,
+    // so unwrapping is ok.
     let config_path = matches.value_of("CONFIG").unwrap();
-    let config_file = std::fs::File::open(&config_path)?;
-    let config: Config = ron::de::from_reader(config_file)?;
-
-    render_from_config(&config)
-}
-
-fn render_from_config(config: &Config) -> Result<()> {
-    let mut mesh_arena = Arena::new();
-    let mesh = load_mesh(&mut mesh_arena, &config.part)?;
-    let material = load_material(&config.part.material);
-    let lights = config.lights.iter().map(load_light).collect();
-    let scene = Scene::new(
-        PrimitiveAggregate::Vector(vec![
-            // PrimitiveAggregate::from_mesh(plane_mesh, material),
-            PrimitiveAggregate::from_mesh(mesh, material),
-        ]),
-        lights,
+    let mut config = load_config(config_path)?;
+    // Clap only accepts the presets' names, so unwrapping is ok.
+    if let Some(quality) = matches.value_of("quality") {
+        Quality::from_name(quality).unwrap().apply(&mut config);
+    }
+    let solo_keys: Vec<&str> = matches
+        .values_of("solo-light")
+        .map(|keys| keys.collect())
+        .unwrap_or_default();
+    select_lights(&mut config, &solo_keys)?;
+    let verbose = matches.is_present("verbose");
+    let time_budget = match matches.value_of("time-budget") {
+        Some(duration) => Some(parse_duration(duration)?),
+        None => None,
+    };
+
+    let tiles = tiles(&matches)?;
+    if let Some(manifest_path) = matches.value_of("emit-jobs") {
+        return emit_jobs(&config, config_path, tiles, manifest_path);
+    }
+    if let Some(job_index) = matches.value_of("job") {
+        select_job(&mut config, tiles, job_index.parse()?)?;
+    }
+
+    if let Some(pixel) = matches.value_of("dump-samples") {
+        dump_samples_from_config(&config, pixel)
+    } else if matches.is_present("dry-run") {
+        dry_run_from_config(&config)
+    } else if let Some(mesh_key) = matches.value_of("uv") {
+        render_uv_from_config(&config, mesh_key)
+    } else if matches.is_present("draft") {
+        render_draft_from_config(&config)
+    } else if matches.is_present("clay") {
+        render_clay_from_config(&config, time_budget, verbose)
+    } else if matches.is_present("path-trace") {
+        let material_statistics = matches.is_present("material-statistics");
+        render_path_traced_from_config(&config, time_budget, verbose, material_statistics)
+    } else if matches.is_present("spectral") {
+        render_spectral_from_config(&config, time_budget, verbose)
+    } else if matches.is_present("shadow-only") {
+        render_shadow_from_config(&config, time_budget, verbose)
+    } else {
+        render_from_config(&config, time_budget, verbose)
+    }
+}
+
+fn load_config(config_path: &str) -> Result<Config> {
+    let config_file = std::fs::File::open(config_path)?;
+    Ok(ron::de::from_reader(config_file)?)
+}
+
+fn tiles_arg<'a, 'b>() -> clap::Arg<'a, 'b> {
+    clap::Arg::with_name("tiles")
+        .long("tiles")
+        .value_name("COLUMNSxROWS")
+        .help("Split each frame into a grid of tiles, such as 4x2, that are rendered as separate jobs. Defaults to 1x1.")
+}
+
+/// Return the grid of tiles that the arguments split each frame into.
+fn tiles(matches: &clap::ArgMatches) -> Result<Tiles> {
+    match matches.value_of("tiles") {
+        Some(tiles) => Tiles::parse(tiles),
+        None => Ok(Tiles::whole_frame()),
+    }
+}
+
+/// Return the index and output path of each frame that the config renders.
+fn frame_outputs(config: &Config) -> Result<Vec<(usize, String)>> {
+    Ok(frames(config)?
+        .into_iter()
+        .map(|frame| (frame.index, frame.output_path))
+        .collect())
+}
+
+/// Split the render into jobs and write a manifest that describes them. Each
+/// job's command repeats the arguments that this program was run with, so
+/// that every job renders with the same options.
+fn emit_jobs(config: &Config, config_path: &str, tiles: Tiles, manifest_path: &str) -> Result<()> {
+    let frames = frame_outputs(config)?;
+    let jobs = jobs::split(&frames, config.width, config.height, tiles);
+
+    let mut arguments = std::env::args();
+    let program = arguments
+        .next()
+        .unwrap_or_else(|| "part_renderer_cli".to_string());
+    let mut job_arguments = vec![program.clone()];
+    while let Some(argument) = arguments.next() {
+        if argument == "--emit-jobs" {
+            arguments.next();
+        } else if !argument.starts_with("--emit-jobs=") {
+            job_arguments.push(argument);
+        }
+    }
+    let tiles_value = format!("{}x{}", tiles.columns, tiles.rows);
+    let merge_arguments = vec![
+        program,
+        "merge".to_string(),
+        config_path.to_string(),
+        "--tiles".to_string(),
+        tiles_value,
+    ];
+    let is_tiled = jobs.iter().any(|job| job.tile.is_some());
+
+    let mut manifest = std::io::BufWriter::new(std::fs::File::create(manifest_path)?);
+    jobs::write_manifest(
+        &mut manifest,
+        &jobs,
+        &job_arguments,
+        if is_tiled {
+            Some(&merge_arguments)
+        } else {
+            None
+        },
+        &frames,
+    )?;
+    println!("Wrote {} jobs to {}", jobs.len(), manifest_path);
+    Ok(())
+}
+
+/// Modify the config so that only the job with the index is rendered. A job
+/// that renders a tile renders its frame with a crop window, which replaces
+/// any configured crop window, and its image isn't cropped so that it can be
+/// merged.
+fn select_job(config: &mut Config, tiles: Tiles, index: usize) -> Result<()> {
+    let frames = frame_outputs(config)?;
+    let job = jobs::split(&frames, config.width, config.height, tiles)
+        .into_iter()
+        .nth(index)
+        .ok_or(Error::JobIndex(index))?;
+    if let Some(tile) = job.tile {
+        config.crop_window = Some(config::CropWindow::Pixels {
+            x_min: tile.x_min,
+            y_min: tile.y_min,
+            x_max: tile.x_max,
+            y_max: tile.y_max,
+        });
+        config.crop = false;
+    }
+    config.job = Some(job);
+    Ok(())
+}
+
+/// Merge the images that the jobs rendered for each tile into the images of
+/// their frames, cropping them if the config asks for it. Only the frames'
+/// main images are merged, not auxiliary images such as AOVs.
+fn merge_jobs_from_config(config: &Config, tiles: Tiles) -> Result<()> {
+    let frames = frame_outputs(config)?;
+    let jobs = jobs::split(&frames, config.width, config.height, tiles);
+    for (frame, output_path) in &frames {
+        let frame_jobs: Vec<&jobs::Job> = jobs
+            .iter()
+            .filter(|job| job.frame == *frame && job.tile.is_some())
+            .collect();
+        if frame_jobs.is_empty() {
+            continue;
+        }
+        match config.bit_depth {
+            Some(config::BitDepth::Sixteen) => {
+                merge_tiles(config, &frame_jobs, output_path, |image| image.to_rgba16())?
+            }
+            Some(config::BitDepth::Eight) | None => {
+                merge_tiles(config, &frame_jobs, output_path, |image| image.to_rgba8())?
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Copy each job's tile out of the image that it rendered into an image of
+/// the whole frame, and save the frame's image.
+///
+/// * convert - Converts a job's image to the output's bit depth.
+fn merge_tiles<S, F>(
+    config: &Config,
+    jobs: &[&jobs::Job],
+    output_path: &str,
+    convert: F,
+) -> Result<()>
+where
+    S: Primitive + 'static,
+    [S]: EncodableLayout,
+    F: Fn(image::DynamicImage) -> ImageBuffer<Rgba<S>, Vec<S>>,
+{
+    let mut image = ImageBuffer::new(config.width as u32, config.height as u32);
+    for job in jobs {
+        let tile_image = convert(image::open(&job.output_path)?);
+        if let Some(tile) = job.tile {
+            for y in tile.y_min.max(0) as u32..(tile.y_max as u32).min(tile_image.height()) {
+                for x in tile.x_min.max(0) as u32..(tile.x_max as u32).min(tile_image.width()) {
+                    image.put_pixel(x, y, *tile_image.get_pixel(x, y));
+                }
+            }
+        }
+    }
+    save_color_image(config, image, output_path)
+}
+
+/// Print the metrics that compare the two images given to the compare
+/// subcommand, and write the heat map if it's asked for.
+fn compare_images(matches: &clap::ArgMatches) -> Result<()> {
+    // The A and B arguments are required by Clap, so unwrapping is ok.
+    let a = image::open(matches.value_of("A").unwrap())?;
+    let b = image::open(matches.value_of("B").unwrap())?;
+    if a.dimensions() != b.dimensions() {
+        return Err(Error::ImageResolutions(a.dimensions(), b.dimensions()));
+    }
+    let max_error = match matches.value_of("max-error") {
+        Some(max_error) => Some(max_error.parse()?),
+        None => None,
+    };
+
+    println!("PSNR: {:.2} dB", film::compare::psnr(&a, &b));
+    println!("SSIM: {:.4}", film::compare::ssim(&a, &b));
+    println!("RMSE: {:.6}", film::compare::rmse(&a, &b));
+    if let Some(out) = matches.value_of("out") {
+        film::compare::heat_map(&a, &b, &ColorRamp::default(), max_error).save(out)?;
+    }
+    Ok(())
+}
+
+/// Render the swatch of the material file that's given to the `swatch`
+/// subcommand.
+fn render_material_swatch(matches: &clap::ArgMatches) -> Result<()> {
+    // The MATERIAL argument is required by Clap, so unwrapping is ok.
+    let material_path = matches.value_of("MATERIAL").unwrap();
+    let material_config: config::Material =
+        ron::de::from_reader(std::fs::File::open(material_path)?)?;
+    let size = match matches.value_of("size") {
+        Some(size) => size.parse()?,
+        None => 256,
+    };
+    let samples_per_pixel = match matches.value_of("samples") {
+        Some(samples) => samples.parse()?,
+        None => 64,
+    };
+    let out = match matches.value_of("out") {
+        Some(out) => std::path::PathBuf::from(out),
+        None => std::path::Path::new(material_path).with_extension("png"),
+    };
+
+    let material = load_physical_material(&material_config, None);
+    let film = swatch::render_swatch(&*material, Vector2::new(size, size), samples_per_pixel);
+    film.write_image().save(out)?;
+    Ok(())
+}
+
+/// Remove the lights that shouldn't be rendered from the config.
+///
+/// If any solo keys are given, only the lights whose names or zero-based
+/// indices match the keys are kept. Otherwise, if any lights are soloed in the
+/// config, only they are kept. Otherwise, only enabled lights are kept.
+fn select_lights(config: &mut Config, solo_keys: &[&str]) -> Result<()> {
+    let keep: Vec<bool> = if solo_keys.is_empty() {
+        let any_solo = config.lights.iter().any(|light| light.is_solo());
+        config
+            .lights
+            .iter()
+            .map(|light| {
+                if any_solo {
+                    light.is_solo()
+                } else {
+                    light.is_enabled()
+                }
+            })
+            .collect()
+    } else {
+        let matches_key = |index: usize, light: &config::Light, key: &str| {
+            (!light.name().is_empty() && light.name() == key) || index.to_string() == key
+        };
+        if let Some(key) = solo_keys.iter().find(|key| {
+            !config
+                .lights
+                .iter()
+                .enumerate()
+                .any(|(index, light)| matches_key(index, light, key))
+        }) {
+            return Err(Error::LightKey(key.to_string()));
+        }
+        config
+            .lights
+            .iter()
+            .enumerate()
+            .map(|(index, light)| solo_keys.iter().any(|key| matches_key(index, light, key)))
+            .collect()
+    };
+    let mut keep = keep.into_iter();
+    config.lights.retain(|_| keep.next().unwrap_or(false));
+    Ok(())
+}
+
+/// Parse a duration that is a number followed by a unit of `ms`, `s`, `m`, or
+/// `h`. A number without a unit is a number of seconds.
+fn parse_duration(duration: &str) -> Result<Duration> {
+    let duration = duration.trim();
+    let split = duration
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(duration.len());
+    let (value, unit) = duration.split_at(split);
+    let seconds_per_unit = match unit {
+        "ms" => 0.001,
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => return Err(Error::Duration(duration.to_string())),
+    };
+    let seconds = value.trim().parse::<f32>()? * seconds_per_unit;
+    if !seconds.is_finite() || seconds < 0.0 {
+        return Err(Error::Duration(duration.to_string()));
+    }
+    Ok(Duration::from_secs_f32(seconds))
+}
+
+/// * time_budget - If present, sampling of each image stops once this much
+///   time has elapsed since the image's render started.
+/// * verbose - If true, a summary of the scene is printed before rendering.
+fn render_from_config(config: &Config, time_budget: Option<Duration>, verbose: bool) -> Result<()> {
+    with_scene(config, None, |scene, meshes, part_to_world| {
+        render_scene(
+            config,
+            scene,
+            meters_per_world_unit(part_to_world),
+            time_budget,
+            verbose,
+            Some(|scene, lights| scene.set_lights(lights)),
+            meshes,
+            |_| OriginalRayTracer {},
+        )
+    })
+}
+
+/// Render the light leaving the surface of one of the config's meshes directly
+/// into the mesh's UV layout, such as for a lightmap or a sticker that is
+/// aligned to the part's unwrap. The mesh is lit by the configured lights of
+/// the first frame, and the camera is ignored.
+///
+/// * mesh_key - `part`, or the zero-based index of a fastener.
+fn render_uv_from_config(config: &Config, mesh_key: &str) -> Result<()> {
+    let resolution = Vector2::new(config.width, config.height);
+    let uv_resolution = Vector2::new(config.width as u32, config.height as u32);
+    with_scene(
+        config,
+        Some(uv_resolution),
+        |scene, meshes, part_to_world| {
+            let mesh_index = match mesh_key {
+                "part" => 0,
+                index => index
+                    .parse::<usize>()
+                    .ok()
+                    .map(|index| index + 1)
+                    .filter(|index| *index < meshes.len())
+                    .ok_or_else(|| Error::MeshKey(mesh_key.to_string()))?,
+            };
+            let first_frame = frames(config)?.into_iter().next().ok_or(Error::NoFrames)?;
+            let camera_pose = placement::place_camera(
+                &*scene,
+                &first_frame.camera_pose,
+                config.back_out_camera,
+                first_frame.index,
+            );
+            let labeled_parts = labeled_parts(config, meshes);
+            let anchors = LightAnchors::new(config, &camera_pose, &labeled_parts);
+            scene.set_lights(
+                config
+                    .lights
+                    .iter()
+                    .map(|light_config| {
+                        load_light(
+                            light_config,
+                            first_frame.index,
+                            &anchors,
+                            meters_per_world_unit(part_to_world),
+                        )
+                    })
+                    .collect::<Result<Vec<Light>>>()?,
+            );
+            let sampler = load_sampler(&config.sampler)?;
+            let max_depth = config.max_depth.unwrap_or(5);
+            // The part is scaled to fit in a unit sphere, so the cage only needs to
+            // clear the surface's own facets.
+            let baker = Baker::new(resolution, UV_CAGE_DISTANCE, 0, 0.0);
+            let film = baker
+                .bake_lighting(
+                    meshes[mesh_index],
+                    &*scene,
+                    &OriginalRayTracer {},
+                    &sampler,
+                    max_depth,
+                )
+                .ok_or(Error::EmptyMesh)?
+                .with_transfer_function(transfer_function(config))
+                .with_color_space(color_space(config));
+            // The layout is cropped by the UV charts, not by coverage, so the
+            // texture keeps its full resolution.
+            match config.bit_depth {
+                Some(config::BitDepth::Sixteen) => {
+                    write_color_image(config, &post_process_16(config, &film), &config.output_path)?
+                }
+                Some(config::BitDepth::Eight) | None => {
+                    write_color_image(config, &post_process(config, &film), &config.output_path)?
+                }
+            }
+            Ok(())
+        },
+    )
+}
+
+/// A piece of the geometry described by a config, paired with the config of
+/// the material that it's made of.
+enum ScenePiece<'a, 'c> {
+    Mesh(&'a Mesh, &'c config::Material),
+
+    /// Each splat is paired with the color given for it in the point cloud
+    /// file, if any.
+    Splats(Vec<(Splat, Option<RgbaSpectrum>)>, &'c config::Material),
+
+    Voxels(&'a VoxelGrid, &'c config::Material),
+}
+
+/// Load all of the geometry described by the config, and pass its pieces, its
+/// meshes, and the transformation that was applied to the part's mesh to `f`.
+/// The part's mesh is first in the list of meshes, followed by the fasteners'
+/// meshes.
+///
+/// * uv_resolution - If present, each mesh that has no UV coordinates is given
+///   a layout with a chart for each triangle, made for a texture with this
+///   resolution.
+fn with_scene_pieces<T, F>(config: &Config, uv_resolution: Option<Vector2<u32>>, f: F) -> Result<T>
+where
+    F: FnOnce(Vec<ScenePiece>, &[&Mesh], &Matrix4<f32>) -> Result<T>,
+{
+    let mesh_arena = Arena::new();
+    let with_uvs = |mesh: &'_ Mesh| -> Option<Mesh> {
+        match (uv_resolution, &mesh.uvs) {
+            (Some(resolution), None) => Some(mesh.with_triangle_uvs(resolution, UV_PADDING)),
+            _ => None,
+        }
+    };
+    let (mesh, part_to_world) = load_mesh(&mesh_arena, &config.part)?;
+    let mesh: &Mesh = match with_uvs(mesh) {
+        Some(unwrapped) => mesh_arena.alloc(unwrapped),
+        None => mesh,
+    };
+    let mut meshes = vec![mesh];
+    let mut pieces = vec![ScenePiece::Mesh(mesh, &config.part.material)];
+    if let Some(point_cloud_config) = &config.point_cloud {
+        let splats = load_splats(point_cloud_config, &part_to_world)?
+            .into_iter()
+            .map(|(splat, color)| {
+                (
+                    splat,
+                    color.map(|[r, g, b]| RgbaSpectrum::from_rgb(r, g, b)),
+                )
+            })
+            .collect();
+        pieces.push(ScenePiece::Splats(splats, &point_cloud_config.material));
+    }
+    let voxel_grid = match &config.voxel_grid {
+        Some(voxel_grid_config) => Some((
+            load_voxel_grid(voxel_grid_config, &part_to_world)?,
+            &voxel_grid_config.material,
+        )),
+        None => None,
+    };
+    if let Some((voxel_grid, material_config)) = &voxel_grid {
+        pieces.push(ScenePiece::Voxels(voxel_grid, material_config));
+    }
+    for (fastener_mesh, material_config) in
+        load_fasteners(&mesh_arena, &config.fasteners, &part_to_world)?
+    {
+        let fastener_mesh: &Mesh = match with_uvs(fastener_mesh) {
+            Some(unwrapped) => mesh_arena.alloc(unwrapped),
+            None => fastener_mesh,
+        };
+        meshes.push(fastener_mesh);
+        pieces.push(ScenePiece::Mesh(fastener_mesh, material_config));
+    }
+
+    f(pieces, &meshes, &part_to_world.recentered_to_world)
+}
+
+/// Load all of the geometry described by the config into a scene with the
+/// configured materials, and pass the scene, its meshes, and the
+/// transformation that was applied to the part's mesh to `f`. The part's mesh
+/// is first in the list of meshes, followed by the fasteners' meshes. The
+/// scene has no lights.
+///
+/// * uv_resolution - If present, each mesh that has no UV coordinates is given
+///   a layout with a chart for each triangle, made for a texture with this
+///   resolution.
+fn with_scene<T, F>(config: &Config, uv_resolution: Option<Vector2<u32>>, f: F) -> Result<T>
+where
+    F: FnOnce(&mut Scene, &[&Mesh], &Matrix4<f32>) -> Result<T>,
+{
+    with_scene_pieces(config, uv_resolution, |pieces, meshes, part_to_world| {
+        let aggregates = pieces
+            .into_iter()
+            .map(|piece| match piece {
+                ScenePiece::Mesh(mesh, material_config) => {
+                    PrimitiveAggregate::from_mesh(mesh, load_material(material_config))
+                }
+                ScenePiece::Splats(splats, material_config) => PrimitiveAggregate::from_splats(
+                    splats
+                        .into_iter()
+                        .map(|(splat, color)| {
+                            let mut material = load_material(material_config);
+                            if let Some(color) = color {
+                                material.color = color;
+                            }
+                            (splat, material)
+                        })
+                        .collect(),
+                ),
+                ScenePiece::Voxels(voxel_grid, material_config) => PrimitiveAggregate::primitive(
+                    Shape::Voxels(voxel_grid),
+                    load_material(material_config),
+                ),
+            })
+            .collect();
+        let mut scene = Scene::new(PrimitiveAggregate::Vector(aggregates), vec![]);
+
+        f(&mut scene, meshes, part_to_world)
+    })
+}
+
+/// Render the part described by the config with the clay ray tracer. The
+/// configured material and lights are ignored.
+fn render_clay_from_config(
+    config: &Config,
+    time_budget: Option<Duration>,
+    verbose: bool,
+) -> Result<()> {
+    with_clay_scene(config, |scene, _, part_to_world| {
+        render_scene(
+            config,
+            scene,
+            meters_per_world_unit(part_to_world),
+            time_budget,
+            verbose,
+            None,
+            &[],
+            |_| ClayRayTracer::default(),
+        )
+    })
+}
+
+/// Render the scene described by the config with the path tracer, using the
+/// physically based description of each configured material.
+///
+/// * material_statistics - If true, a table of the work that the path tracer
+///   attributed to each material is printed after each image is rendered.
+fn render_path_traced_from_config(
+    config: &Config,
+    time_budget: Option<Duration>,
+    verbose: bool,
+    material_statistics: bool,
+) -> Result<()> {
+    // The lights can change from image to image, so a path tracer that chooses
+    // among them is created for each image.
+    let path_tracer = |scene: &renderer::scene::Scene| {
+        let mut path_tracer = if material_statistics {
+            PathTracer::with_statistics(PathTracer::default().russian_roulette_depth())
+        } else {
+            PathTracer::default()
+        };
+        if let Some(min_alpha) = config.path_regularization {
+            path_tracer = path_tracer.with_regularization(min_alpha);
+        }
+        match config.light_sampling {
+            Some(config::LightSampling::Power) => {
+                path_tracer.with_light_distribution(LightDistribution::from_power(scene.lights()))
+            }
+            Some(config::LightSampling::Bvh) => {
+                path_tracer.with_light_bvh(LightBvh::new(scene.lights()))
+            }
+            Some(config::LightSampling::All) | None => path_tracer,
+        }
+    };
+    let material_arena = Arena::new();
+    let material = |material_config: &config::Material, color| {
+        let material: &(dyn renderer::material::Material + Send + Sync) =
+            &**material_arena.alloc(load_physical_material(material_config, color));
+        material
+    };
+    with_physical_scene(config, material, |scene, meshes, part_to_world| {
+        render_scene(
+            config,
+            scene,
+            meters_per_world_unit(part_to_world),
+            time_budget,
+            verbose,
+            Some(|scene, lights| scene.set_lights(lights)),
+            meshes,
+            path_tracer,
+        )
+    })
+}
+
+/// Render the scene described by the config with the spectral path tracer,
+/// using the physically based description of each configured material.
+fn render_spectral_from_config(
+    config: &Config,
+    time_budget: Option<Duration>,
+    verbose: bool,
+) -> Result<()> {
+    let material_arena = Arena::new();
+    let material = |material_config: &config::Material, color| {
+        let material: &(dyn renderer::material::Material + Send + Sync) =
+            &**material_arena.alloc(load_physical_material(material_config, color));
+        material
+    };
+    with_physical_scene(config, material, |scene, meshes, part_to_world| {
+        render_scene(
+            config,
+            scene,
+            meters_per_world_unit(part_to_world),
+            time_budget,
+            verbose,
+            Some(|scene, lights| scene.set_lights(lights)),
+            meshes,
+            |_| SpectralPathTracer::default(),
+        )
+    })
+}
+
+/// Render only the contact shadow that the part described by the config casts
+/// on the ground, which is the horizontal plane that the part rests on. The
+/// configured materials and lights are ignored.
+fn render_shadow_from_config(
+    config: &Config,
+    time_budget: Option<Duration>,
+    verbose: bool,
+) -> Result<()> {
+    with_clay_scene(config, |scene, _, part_to_world| {
+        render_scene(
+            config,
+            scene,
+            meters_per_world_unit(part_to_world),
+            time_budget,
+            verbose,
+            None,
+            &[],
+            |scene| {
+                ContactShadowRayTracer::new(
+                    point3(0.0, 0.0, scene.world_bound().min.z),
+                    Vector3::unit_z(),
+                    RgbaSpectrum::constant(1.0),
+                    CONTACT_SHADOW_DISTANCE,
+                )
+                .with_shadow_only(true)
+            },
+        )
+    })
+}
+
+/// Load the scene described by the config and print a report about it,
+/// without rendering it.
+fn dry_run_from_config(config: &Config) -> Result<()> {
+    with_clay_scene(config, |scene, meshes, part_to_world| {
+        let labeled_parts = labeled_parts(config, meshes);
+        let load_lights = |frame: &Frame| {
+            let anchors = LightAnchors::new(config, &frame.camera_pose, &labeled_parts);
+            config
+                .lights
+                .iter()
+                .map(|light_config| {
+                    load_light(
+                        light_config,
+                        frame.index,
+                        &anchors,
+                        meters_per_world_unit(part_to_world),
+                    )
+                })
+                .collect::<Result<Vec<Light>>>()
+        };
+        let frames = frames(config)?;
+        // The scene is given the first frame's lights so that its memory
+        // estimate includes their environment maps and projected images.
+        if let Some(frame) = frames.first() {
+            scene.set_lights(load_lights(frame)?);
+        }
+
+        report::print_system();
+        report::print_scene(scene);
+        let resolution = Vector2::new(config.width, config.height);
+        for frame in &frames {
+            let lights = load_lights(frame)?;
+            let camera = load_camera(&config.camera, &frame.camera_pose, Deg(0.0), resolution);
+            report::print_frame(frame.index, scene, &camera, &lights, resolution);
+        }
+        Ok(())
+    })
+}
+
+/// Write the sample vectors that the configured sampler generates for a pixel
+/// to a CSV file and a scatter plot, named after the output image.
+///
+/// * pixel - The pixel's coordinates, as `X,Y`.
+fn dump_samples_from_config(config: &Config, pixel: &str) -> Result<()> {
+    let (x, y) = pixel
+        .split_once(',')
+        .ok_or_else(|| Error::PixelCoordinates(pixel.to_string()))?;
+    let pixel = point2(x.trim().parse()?, y.trim().parse()?);
+    let sampler = load_sampler(&config.sampler)?;
+    let dump = SampleDump::record(&sampler, pixel, 0, 4);
+
+    let output_path = std::path::Path::new(&config.output_path);
+    let csv_path = output_path.with_extension("samples.csv");
+    let mut csv_file = std::io::BufWriter::new(std::fs::File::create(&csv_path)?);
+    dump.write_csv(&mut csv_file)?;
+    let plot_path = output_path.with_extension("samples.png");
+    dump.scatter_plot(256).save(&plot_path)?;
+    println!(
+        "Wrote {} sample vectors to {} and {}",
+        dump.vectors.len(),
+        csv_path.display(),
+        plot_path.display()
     );
+    Ok(())
+}
+
+/// Load all of the geometry described by the config into a scene in which
+/// every primitive has the same matte material, and pass the scene, the
+/// part's mesh followed by the fasteners' meshes, and the transformation that
+/// was applied to the part's mesh to `f`. The configured materials and lights
+/// are ignored.
+fn with_clay_scene<T, F>(config: &Config, f: F) -> Result<T>
+where
+    F: FnOnce(&mut renderer::scene::Scene, &[&Mesh], &Matrix4<f32>) -> Result<T>,
+{
+    // The clay ray tracer doesn't evaluate materials, but every primitive
+    // needs one.
+    let clay = MatteMaterial::new(RgbaSpectrum::constant(1.0), 0.0);
+    with_physical_scene(config, |_, _| &clay, f)
+}
+
+/// Load all of the geometry described by the config into a physically based
+/// scene, and pass the scene, the part's mesh followed by the fasteners'
+/// meshes, and the transformation that was applied to the part's mesh to `f`.
+/// The scene has no lights.
+///
+/// * material - Returns the material of a piece of geometry, given the config
+///   of the material that it's made of and, for a point of a point cloud, the
+///   point's color, if any.
+fn with_physical_scene<'m, T, M, F>(config: &Config, mut material: M, f: F) -> Result<T>
+where
+    M: FnMut(
+        &config::Material,
+        Option<RgbaSpectrum>,
+    ) -> &'m (dyn renderer::material::Material + Send + Sync),
+    F: FnOnce(&mut renderer::scene::Scene, &[&Mesh], &Matrix4<f32>) -> Result<T>,
+{
+    with_scene_pieces(config, None, |pieces, meshes, part_to_world| {
+        let aggregates = pieces
+            .into_iter()
+            .map(|piece| match piece {
+                ScenePiece::Mesh(mesh, material_config) => {
+                    renderer::primitive::PrimitiveAggregate::from_mesh(
+                        mesh,
+                        material(material_config, None),
+                    )
+                }
+                ScenePiece::Splats(splats, material_config) => {
+                    let uncolored = material(material_config, None);
+                    renderer::primitive::PrimitiveAggregate::from_splats(
+                        splats
+                            .into_iter()
+                            .map(|(splat, color)| match color {
+                                Some(color) => (splat, material(material_config, Some(color))),
+                                None => (splat, uncolored),
+                            })
+                            .collect(),
+                    )
+                }
+                ScenePiece::Voxels(voxel_grid, material_config) => {
+                    renderer::primitive::PrimitiveAggregate::primitive(
+                        Shape::Voxels(voxel_grid),
+                        material(material_config, None),
+                    )
+                }
+            })
+            .collect();
+        let mut scene = renderer::scene::Scene::new(
+            renderer::primitive::PrimitiveAggregate::Vector(aggregates),
+            vec![],
+        );
+
+        f(&mut scene, meshes, part_to_world)
+    })
+}
+
+/// Rasterize a rough preview of the scene described by the config, including
+/// the fasteners, point cloud, and voxel grid. The configured materials,
+/// lights, sampler, and post-processing are ignored.
+fn render_draft_from_config(config: &Config) -> Result<()> {
+    // The preview doesn't evaluate materials, so the scene is loaded like the
+    // clay render's, with the part, fasteners, point cloud, and voxel grid.
+    with_clay_scene(config, |scene, _, _| {
+        let resolution = Vector2::new(config.width, config.height);
+        for frame in frames(config)? {
+            let camera_pose = placement::place_camera(
+                scene,
+                &frame.camera_pose,
+                config.back_out_camera,
+                frame.index,
+            );
+            let image = render_views(config, |orbit| {
+                let camera = load_camera(&config.camera, &camera_pose, orbit, resolution);
+                preview::rasterize(scene, &camera, resolution, PreviewShading::Flat)
+            });
+            save(config, image, &frame.output_path)?;
+        }
+        Ok(())
+    })
+}
+
+/// Render the scene with the configured camera, lights, sampler, and priority
+/// mask, and save the result.
+///
+/// * meters_per_world_unit - The length in meters of one unit of world space.
+/// * time_budget - If present, sampling of each view stops once this much time
+///   has elapsed since the view's render started.
+/// * verbose - If true, a summary of the scene and the memory it needs is
+///   printed before the first frame is rendered.
+/// * set_lights - Replaces the scene's lights with the configured lights, which
+///   may vary from frame to frame. This is `None` if the ray tracer ignores
+///   lights. Otherwise, if any lights are assigned to groups, each group is
+///   also rendered on its own and written as a layer of an EXR image.
+/// * meshes - The part's mesh followed by the fasteners' meshes, as they're
+///   passed by `with_scene`. They get callouts and line art if the config asks
+///   for them.
+/// * ray_tracer - Creates the ray tracer for each image, given the scene with
+///   the image's lights. If the ray tracer collects per-material statistics,
+///   they're printed once the image is rendered.
+#[allow(clippy::too_many_arguments)]
+fn render_scene<S, R, F>(
+    config: &Config,
+    scene: &mut S,
+    meters_per_world_unit: f32,
+    time_budget: Option<Duration>,
+    verbose: bool,
+    set_lights: Option<fn(&mut S, Vec<Light>)>,
+    meshes: &[&Mesh],
+    ray_tracer: F,
+) -> Result<()>
+where
+    S: Send + Sync + SceneGeometry,
+    R: RayTracer<S, ConfiguredSampler> + Send + Sync,
+    F: Fn(&S) -> R,
+{
+    // Supersampled images are rendered at a multiple of the configured
+    // resolution, and the film filters them down when they're written.
+    let supersampling_factor = config
+        .supersampling
+        .as_ref()
+        .map_or(1, |supersampling| supersampling.factor.max(1));
+    let resolution = Vector2::new(config.width, config.height) * supersampling_factor;
+    let filter = load_filter(config.filter.as_ref());
+    let max_depth = config.max_depth.unwrap_or(5);
+    let sampler = load_sampler(&config.sampler)?;
+    let priority_mask = match &config.priority_mask {
+        Some(priority_mask_config) => Some(load_priority_mask(priority_mask_config, resolution)?),
+        None => None,
+    };
+    let aovs: Vec<Aov> = config.aovs.iter().map(load_aov).collect();
+    let labeled_parts = labeled_parts(config, meshes);
+    let focus = focus_point(config, &labeled_parts)?;
+
+    // The scene is prepared once and reused for every frame.
+    for (frame_number, frame) in frames(config)?.into_iter().enumerate() {
+        // The camera is placed before the lights so that lights can be placed
+        // relative to it. Backing the camera out only depends on the scene's
+        // geometry.
+        let camera_pose = placement::place_camera(
+            &*scene,
+            &frame.camera_pose,
+            config.back_out_camera,
+            frame.index,
+        );
+        let anchors = LightAnchors::new(config, &camera_pose, &labeled_parts);
+        let load_lights = |group: Option<&str>| {
+            config
+                .lights
+                .iter()
+                .filter(|light_config| group.is_none_or(|group| light_group(light_config) == group))
+                .map(|light_config| {
+                    load_light(light_config, frame.index, &anchors, meters_per_world_unit)
+                })
+                .collect::<Result<Vec<Light>>>()
+        };
+        if let Some(set_lights) = set_lights {
+            set_lights(scene, load_lights(None)?);
+        }
+        // The summary is printed once the lights are loaded, so that it
+        // includes their environment maps and projected images.
+        if verbose && frame_number == 0 {
+            println!("Scene");
+            report::print_memory(&scene.memory_report());
+        }
+
+        let take_snapshot = |film: &Film| {
+            // Snapshots aren't cropped, since the first ones may be empty.
+            if let Err(e) = post_process(config, film).save(&frame.output_path) {
+                eprintln!("Warning: Couldn't write snapshot: {}", e);
+            }
+        };
+        let render_film = |scene: &S, orbit| {
+            let mut film = Film::new(resolution)
+                .with_negative_lobes(match config.negative_lobes {
+                    Some(config::NegativeLobes::ClampPixels) => NegativeLobes::ClampPixels,
+                    Some(config::NegativeLobes::ClampKernel) => NegativeLobes::ClampKernel,
+                    Some(config::NegativeLobes::Keep) | None => NegativeLobes::Keep,
+                })
+                .with_transfer_function(transfer_function(config))
+                .with_color_space(color_space(config))
+                .with_aovs(&aovs);
+            film = match config.crop_window {
+                Some(config::CropWindow::Normalized {
+                    x_min,
+                    y_min,
+                    x_max,
+                    y_max,
+                }) => film.with_crop_window(Point2::new(x_min, y_min), Point2::new(x_max, y_max)),
+                Some(config::CropWindow::Pixels {
+                    x_min,
+                    y_min,
+                    x_max,
+                    y_max,
+                }) => {
+                    let factor = supersampling_factor as i32;
+                    film.with_pixel_crop_window(
+                        Point2::new(x_min, y_min) * factor,
+                        Point2::new(x_max, y_max) * factor,
+                    )
+                }
+                None => film,
+            };
+            if let Some(supersampling) = &config.supersampling {
+                film = film.with_downsampling(Downsampling::new(
+                    supersampling_factor,
+                    load_filter(supersampling.filter.as_ref()),
+                ));
+            }
+            let orthographic = load_camera(&config.camera, &camera_pose, orbit, resolution);
+            let focus_point = focus.unwrap_or_else(|| {
+                point3(
+                    camera_pose.target.x,
+                    camera_pose.target.y,
+                    camera_pose.target.z,
+                )
+            });
+            let camera = with_aperture(&config.camera, orthographic, focus_point);
+            let options = RenderOptions {
+                priority_mask: priority_mask.as_ref(),
+                max_sample_radiance: config.max_sample_radiance,
+                tile_shape: match config.tile_shape {
+                    Some(config::TileShape::Scanline { length }) => TileShape::Scanline(length),
+                    Some(config::TileShape::Square) | None => TileShape::Square,
+                },
+                tile_order: match config.tile_order {
+                    Some(config::TileOrder::Progressive) => TileOrder::Progressive,
+                    Some(config::TileOrder::Scanline) | None => TileOrder::Scanline,
+                },
+                deadline: time_budget.map(|budget| Instant::now() + budget),
+                snapshots: config.snapshot_interval.map(|seconds| Snapshots {
+                    interval: SnapshotInterval::Time(Duration::from_secs_f32(seconds)),
+                    take: &take_snapshot,
+                }),
+                seed: frame.index as u64,
+            };
+            let ray_tracer = ray_tracer(scene);
+            renderer::integrator::render_with_options(
+                scene,
+                &*camera,
+                &mut film,
+                &*filter,
+                &sampler,
+                &ray_tracer,
+                max_depth,
+                &options,
+            );
+            if let Some(statistics) = ray_tracer.statistics() {
+                print!("{}", statistics);
+            }
+            if let Some(deadline) = options.deadline {
+                if Instant::now() >= deadline {
+                    eprintln!("Warning: Time budget elapsed; some pixels received fewer samples.");
+                }
+            }
+            film
+        };
+        let films: Vec<Film> = view_orbits(config)
+            .into_iter()
+            .map(|orbit| render_film(scene, orbit))
+            .collect();
+        check_clipping(config, &*scene, &camera_pose, &frame.output_path)?;
+        let callouts = frame_callouts(config, &labeled_parts, &camera_pose, &frame.output_path)?;
+        write_line_art(config, &*scene, meshes, &camera_pose, &frame.output_path)?;
+        let balloon_radius = config
+            .callouts
+            .and_then(|callouts| callouts.balloon_radius)
+            .unwrap_or(DEFAULT_BALLOON_RADIUS);
+        match config.bit_depth {
+            Some(config::BitDepth::Sixteen) => {
+                let views = films.iter().map(|film| post_process_16(config, film));
+                let mut image = combine_views(config, views.collect());
+                callout::draw(&mut image, &callouts, balloon_radius);
+                save_color_image(config, image, &frame.output_path)?;
+            }
+            Some(config::BitDepth::Eight) | None => {
+                let views = films.iter().map(|film| post_process(config, film));
+                let mut image = combine_views(config, views.collect());
+                callout::draw(&mut image, &callouts, balloon_radius);
+                save_color_image(config, image, &frame.output_path)?;
+            }
+        }
+        if let Some(exposure_check) = &config.exposure_check {
+            check_exposure(config, exposure_check, &films, &frame.output_path, verbose)?;
+        }
+        for aov in &aovs {
+            let views = films.iter().filter_map(|film| film.write_aov_image(*aov));
+            let aov_path = auxiliary_output_path(&frame.output_path, aov.name());
+            save(config, combine_views(config, views.collect()), &aov_path)?;
+        }
+        if config.variance_image {
+            let ramp = ColorRamp::default();
+            let views = films.iter().map(|film| film.write_variance_image(&ramp));
+            let variance_path = auxiliary_output_path(&frame.output_path, "variance");
+            save(
+                config,
+                combine_views(config, views.collect()),
+                &variance_path,
+            )?;
+        }
+        if config.light_probes {
+            write_light_probes(
+                config,
+                &load_lights(None)?,
+                &camera_pose,
+                &frame.output_path,
+            )?;
+        }
+        // The views' films are no longer needed while light groups render.
+        drop(films);
+
+        let groups = light_groups(config);
+        if let (Some(set_lights), false) = (set_lights, groups.is_empty()) {
+            let mut films = vec![];
+            for group in &groups {
+                set_lights(scene, load_lights(Some(group))?);
+                films.push(render_film(scene, Deg(0.0)));
+            }
+            let layers: Vec<(&str, &Film)> = groups.iter().copied().zip(&films).collect();
+            let exr_path = std::path::Path::new(&frame.output_path).with_extension("exr");
+            let mut exr_file = std::io::BufWriter::new(std::fs::File::create(&exr_path)?);
+            film::write_exr_layers(&mut exr_file, &layers)?;
+        }
+    }
+    Ok(())
+}
+
+/// Print a warning if the camera's clipping planes cut off any of the
+/// geometry, and write an image that marks where if the config asks for one.
+///
+/// * camera_pose - The pose of the frame's camera.
+fn check_clipping<S: SceneGeometry + Sync>(
+    config: &Config,
+    scene: &S,
+    camera_pose: &CameraPose,
+    output_path: &str,
+) -> Result<()> {
+    let resolution = Vector2::new(config.width, config.height);
+    let camera = load_camera(&config.camera, camera_pose, Deg(0.0), resolution);
+    let clipping = clipping::find_clipping(&camera, resolution, |origin, direction| {
+        scene.first_hit(origin, direction)
+    });
+    let clipped_fraction = clipping::clipped_fraction(&clipping);
+    if clipped_fraction > 0.0 {
+        let (z_near, z_far) = camera.clipping_range();
+        println!(
+            "Warning: The clipping planes at {} and {} cut off the geometry in {:.1}% of the covered pixels of {}. Try widening the camera's z_near and z_far.",
+            z_near,
+            z_far,
+            100.0 * clipped_fraction,
+            output_path
+        );
+    }
+    if config.clipping_image {
+        save(
+            config,
+            clipping::clipping_image(&clipping, resolution),
+            &auxiliary_output_path(output_path, "clipped"),
+        )?;
+    }
+    Ok(())
+}
+
+/// Render a chrome ball and a gray ball lit by the lights, as seen from the
+/// direction of the frame's camera, and write them next to the frame's image.
+/// The image is as wide as the output and half as tall.
+///
+/// * camera_pose - The pose of the frame's camera.
+fn write_light_probes(
+    config: &Config,
+    lights: &[Light],
+    camera_pose: &CameraPose,
+    output_path: &str,
+) -> Result<()> {
+    let resolution = Vector2::new(config.width, (config.width / 2).max(1));
+    let camera = load_camera(&config.camera, camera_pose, Deg(0.0), resolution);
+    let film = probe::render_probes(lights, &camera.camera_to_world, resolution);
+    save(
+        config,
+        post_process(config, &film),
+        &auxiliary_output_path(output_path, "probes"),
+    )
+}
+
+/// Return the parts that get callouts, which are the part and the fasteners
+/// that have names, in that order.
+///
+/// * meshes - The part's mesh followed by the fasteners' meshes, as they're
+///   passed by `with_scene`.
+fn labeled_parts(config: &Config, meshes: &[&Mesh]) -> Vec<LabeledPart> {
+    let names = std::iter::once(&config.part.name)
+        .chain(config.fasteners.iter().map(|fastener| &fastener.name));
+    names
+        .zip(meshes)
+        .filter_map(|(name, mesh)| {
+            Some(LabeledPart {
+                name: name.clone()?,
+                centroid: mesh.surface_centroid()?,
+            })
+        })
+        .collect()
+}
 
+/// Place the callouts of a frame's labeled parts and write the matching bill
+/// of materials next to the frame's image. No callouts are placed unless the
+/// config asks for them, and none are placed on stereo images, whose views
+/// don't share a single projection.
+///
+/// * camera_pose - The pose of the frame's camera.
+/// * output_path - The path that the frame's image is written to.
+fn frame_callouts(
+    config: &Config,
+    labeled_parts: &[LabeledPart],
+    camera_pose: &CameraPose,
+    output_path: &str,
+) -> Result<Vec<callout::Callout>> {
+    let callouts_config = match config.callouts {
+        Some(callouts_config) if config.stereo.is_none() && !labeled_parts.is_empty() => {
+            callouts_config
+        }
+        _ => return Ok(vec![]),
+    };
     let resolution = Vector2::new(config.width, config.height);
-    let mut film = Film::new(resolution);
-    let camera = load_camera(&config.camera, resolution);
-
-    let filter = MitchellFilter::new(2.0, 2.0, 1.0 / 3.0, 1.0 / 3.0);
-    let sampler = load_sampler(&config.sampler);
-
-    renderer::render(
-        &scene,
-        &camera,
-        &mut film,
-        &filter,
-        &sampler,
-        &OriginalRayTracer {},
-        5,
+    let camera = load_camera(&config.camera, camera_pose, Deg(0.0), resolution);
+    let callouts = callout::place(
+        labeled_parts,
+        &camera.world_to_raster(),
+        resolution,
+        callouts_config
+            .balloon_radius
+            .unwrap_or(DEFAULT_BALLOON_RADIUS),
     );
-    let mut image = film.write_image();
+    let bom_path =
+        std::path::Path::new(&auxiliary_output_path(output_path, "bom")).with_extension("json");
+    let mut bom_file = std::io::BufWriter::new(std::fs::File::create(bom_path)?);
+    callout::write_bill_of_materials(&mut bom_file, &callouts)?;
+    Ok(callouts)
+}
+
+/// Write the edges of the meshes that a frame's camera sees as an SVG image
+/// next to the frame's image, if the config asks for line art. None is
+/// written for stereo images, whose views don't share a single projection.
+///
+/// * camera_pose - The pose of the frame's camera.
+/// * output_path - The path that the frame's image is written to.
+fn write_line_art<S: SceneGeometry>(
+    config: &Config,
+    scene: &S,
+    meshes: &[&Mesh],
+    camera_pose: &CameraPose,
+    output_path: &str,
+) -> Result<()> {
+    let line_art_config = match config.line_art {
+        Some(line_art_config) if config.stereo.is_none() && !meshes.is_empty() => line_art_config,
+        _ => return Ok(()),
+    };
+    let resolution = Vector2::new(config.width, config.height);
+    let camera = load_camera(&config.camera, camera_pose, Deg(0.0), resolution);
+    let crease_angle = Deg(line_art_config.crease_angle.unwrap_or(30.0));
+    let segments = line_art::find_edges(meshes, &camera, Rad::from(crease_angle).0, |o, d| {
+        scene.first_hit(o, d)
+    });
+    let svg_path = std::path::Path::new(output_path).with_extension("svg");
+    let mut svg_file = std::io::BufWriter::new(std::fs::File::create(svg_path)?);
+    line_art::write_svg(
+        &mut svg_file,
+        &segments,
+        config.width,
+        config.height,
+        line_art_config.hidden_lines.unwrap_or(false),
+        line_art_config.stroke_width.unwrap_or(1.0),
+    )?;
+    Ok(())
+}
+
+/// Print a warning for each of a frame's views in which too many pixels are
+/// blown out or crushed to black, and write the clipping overlay if the
+/// config asks for it.
+///
+/// * films - The films of the frame's views, in the order returned by
+///   `view_orbits`.
+/// * output_path - The path that the frame's image is written to.
+/// * verbose - If true, each view's histogram is printed.
+fn check_exposure(
+    config: &Config,
+    exposure_check: &config::ExposureCheck,
+    films: &[Film],
+    output_path: &str,
+    verbose: bool,
+) -> Result<()> {
+    let post_processing: Vec<Box<dyn PostProcess>> = config
+        .post_processing
+        .iter()
+        .map(load_post_process)
+        .collect();
+    let max_clipped_fraction = exposure_check.max_clipped_fraction.unwrap_or(0.01);
+    let max_crushed_fraction = exposure_check.max_crushed_fraction.unwrap_or(0.05);
+    let mut overlays = vec![];
+    for film in films {
+        let pixels = film.post_processed_pixels(&post_processing);
+        let histogram = LuminanceHistogram::new(&pixels, EXPOSURE_HISTOGRAM_BINS);
+        if verbose {
+            report::print_histogram(&histogram);
+        }
+        if histogram.clipped_fraction() > max_clipped_fraction {
+            eprintln!(
+                "Warning: {:.1}% of the pixels in {} are blown out. Try dimming the lights.",
+                100.0 * histogram.clipped_fraction(),
+                output_path
+            );
+        }
+        if histogram.crushed_fraction() > max_crushed_fraction {
+            eprintln!(
+                "Warning: {:.1}% of the pixels in {} are crushed to black. Try brightening the lights.",
+                100.0 * histogram.crushed_fraction(),
+                output_path
+            );
+        }
+        if exposure_check.overlay {
+            overlays.push(film::clipping_overlay(&pixels, film.output_resolution()));
+        }
+    }
+    if exposure_check.overlay {
+        let overlay_path = auxiliary_output_path(output_path, "clipping");
+        save(config, combine_views(config, overlays), &overlay_path)?;
+    }
+    Ok(())
+}
+
+/// Return the names of the groups that the configured lights belong to, in the
+/// order they first appear. If no light is assigned to a group, there are no
+/// groups.
+fn light_groups(config: &Config) -> Vec<&str> {
+    if config.lights.iter().all(|light| light.group().is_empty()) {
+        return vec![];
+    }
+    let mut groups: Vec<&str> = vec![];
+    for light in &config.lights {
+        if !groups.contains(&light_group(light)) {
+            groups.push(light_group(light));
+        }
+    }
+    groups
+}
+
+/// Return the name of the light's group. Lights without a group are in the
+/// `default` group.
+fn light_group(light_config: &config::Light) -> &str {
+    match light_config.group() {
+        "" => "default",
+        group => group,
+    }
+}
+
+/// Render the image for a frame. If the config asks for stereo output, a view
+/// is rendered for each eye and the views are combined.
+///
+/// * render_view - Renders the view from a camera that is orbited about its
+///   target, around the camera's vertical axis, by the given angle.
+fn render_views<S, F>(config: &Config, render_view: F) -> ImageBuffer<Rgba<S>, Vec<S>>
+where
+    S: Primitive + 'static,
+    F: Fn(Deg<f32>) -> ImageBuffer<Rgba<S>, Vec<S>>,
+{
+    let views = view_orbits(config).into_iter().map(render_view).collect();
+    combine_views(config, views)
+}
+
+/// Return the angles by which the camera is orbited about its target for each
+/// view of a frame. A stereo frame has a view for each eye.
+fn view_orbits(config: &Config) -> Vec<Deg<f32>> {
+    match &config.stereo {
+        Some(stereo_config) => {
+            let half_angle = Deg(stereo_config.eye_angle / 2.0);
+            vec![-half_angle, half_angle]
+        }
+        None => vec![Deg(0.0)],
+    }
+}
+
+/// Combine the images of a frame's views, in the order returned by
+/// `view_orbits`, into the frame's image.
+fn combine_views<S>(
+    config: &Config,
+    views: Vec<ImageBuffer<Rgba<S>, Vec<S>>>,
+) -> ImageBuffer<Rgba<S>, Vec<S>>
+where
+    S: Primitive + 'static,
+{
+    let mut views = views.into_iter();
+    let first = views.next().expect("a frame has at least one view");
+    match (&config.stereo, views.next()) {
+        (Some(stereo_config), Some(right)) => {
+            let layout = match stereo_config.layout {
+                config::StereoLayout::Anaglyph => StereoLayout::Anaglyph,
+                config::StereoLayout::SideBySide => StereoLayout::SideBySide,
+            };
+            stereo::combine(&first, &right, layout)
+        }
+        _ => first,
+    }
+}
+
+/// A frame of the output that should be rendered.
+struct Frame {
+    /// The index of the frame, which values in the config may depend on.
+    index: usize,
+    output_path: String,
+    camera_pose: CameraPose,
+}
+
+/// Return each frame that should be rendered. With a keyframed camera path,
+/// the camera follows the path. Otherwise the configured number of frames,
+/// or a single frame by default, is rendered from the configured camera
+/// position.
+fn frames(config: &Config) -> Result<Vec<Frame>> {
+    let camera_poses = match &config.animation {
+        Some(animation_config) => animation::frame_poses(animation_config)?,
+        None => {
+            let position = match &config.camera {
+                config::Camera::OrthographicCamera { position, .. } => position,
+                config::Camera::PerspectiveCamera { position, .. } => position,
+            };
+            (0..config.frame_count.unwrap_or(1))
+                .map(|frame| {
+                    Ok(CameraPose {
+                        position: SphericalPosition::at_frame(position, frame)?,
+                        target: config::Point::default(),
+                    })
+                })
+                .collect::<Result<Vec<CameraPose>>>()?
+        }
+    };
+
+    let is_sequence = config.animation.is_some() || camera_poses.len() > 1;
+    let frames = camera_poses
+        .into_iter()
+        .enumerate()
+        .map(|(index, camera_pose)| Frame {
+            index,
+            output_path: if is_sequence {
+                frame_output_path(&config.output_path, index)
+            } else {
+                config.output_path.clone()
+            },
+            camera_pose,
+        });
+    // A job renders only its frame, and writes it to the job's output path.
+    Ok(match &config.job {
+        Some(job) => frames
+            .filter(|frame| frame.index == job.frame)
+            .map(|frame| Frame {
+                output_path: job.output_path.clone(),
+                ..frame
+            })
+            .collect(),
+        None => frames.collect(),
+    })
+}
+
+/// Return the path for the given frame of an animation. A `{frame}`
+/// placeholder in the output path is replaced with the zero-padded frame
+/// number. Otherwise the frame number is appended to the file name.
+fn frame_output_path(output_path: &str, frame: usize) -> String {
+    let frame_number = format!("{:04}", frame);
+    if output_path.contains("{frame}") {
+        return output_path.replace("{frame}", &frame_number);
+    }
+    let path = std::path::Path::new(output_path);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let file_name = match path.extension().and_then(|e| e.to_str()) {
+        Some(extension) => format!("{}_{}.{}", stem, frame_number, extension),
+        None => format!("{}_{}", stem, frame_number),
+    };
+    path.with_file_name(file_name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Return the path that an auxiliary image of the frame, such as an AOV, is
+/// written to. The image's name is inserted before the output path's
+/// extension.
+fn auxiliary_output_path(output_path: &str, name: &str) -> String {
+    let path = std::path::Path::new(output_path);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let file_name = match path.extension().and_then(|e| e.to_str()) {
+        Some(extension) => format!("{}.{}.{}", stem, name, extension),
+        None => format!("{}.{}", stem, name),
+    };
+    path.with_file_name(file_name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Return the transfer function that films encode their images with.
+fn transfer_function(config: &Config) -> TransferFunction {
+    if config.linear_output {
+        TransferFunction::Linear
+    } else {
+        TransferFunction::Srgb
+    }
+}
+
+/// Return the color space that films write their images in.
+fn color_space(config: &Config) -> ColorSpace {
+    match config.color_space {
+        Some(config::ColorSpace::DisplayP3) => ColorSpace::DisplayP3,
+        Some(config::ColorSpace::Srgb) | None => ColorSpace::Srgb,
+    }
+}
+
+/// Apply the configured post-processing to the film and return the resulting
+/// image.
+fn post_process(config: &Config, film: &Film) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let post_processing: Vec<Box<dyn PostProcess>> = config
+        .post_processing
+        .iter()
+        .map(load_post_process)
+        .collect();
+    film.write_post_processed_image(&post_processing)
+}
+
+/// Like `post_process`, but write an image with 16 bits per channel.
+fn post_process_16(config: &Config, film: &Film) -> ImageBuffer<Rgba<u16>, Vec<u16>> {
+    let post_processing: Vec<Box<dyn PostProcess>> = config
+        .post_processing
+        .iter()
+        .map(load_post_process)
+        .collect();
+    film.write_post_processed_image_16(&post_processing)
+}
 
+/// Save the image to the output path, cropping it first if the config asks for
+/// it.
+fn save<S>(config: &Config, image: ImageBuffer<Rgba<S>, Vec<S>>, output_path: &str) -> Result<()>
+where
+    S: Primitive + 'static,
+    [S]: EncodableLayout,
+{
+    let mut image = image;
     if config.crop {
         image = crop_to_non_transparent(&image)?;
     }
 
-    image.save(config.output_path.clone())?;
+    image.save(output_path)?;
     Ok(())
 }
 
-fn load_mesh<'a>(mesh_arena: &'a mut Arena<Mesh>, part_config: &config::Part) -> Result<&'a Mesh> {
+/// Like `save`, but embed an ICC profile that describes the colors of the
+/// images that films write if the config asks for it.
+fn save_color_image<S>(
+    config: &Config,
+    image: ImageBuffer<Rgba<S>, Vec<S>>,
+    output_path: &str,
+) -> Result<()>
+where
+    S: Primitive + 'static,
+    [S]: EncodableLayout,
+{
+    let mut image = image;
+    if config.crop {
+        image = crop_to_non_transparent(&image)?;
+    }
+    write_color_image(config, &image, output_path)
+}
+
+/// Save the image to the output path without cropping it, embedding an ICC
+/// profile that describes the colors of the images that films write if the
+/// config asks for it.
+fn write_color_image<S>(
+    config: &Config,
+    image: &ImageBuffer<Rgba<S>, Vec<S>>,
+    output_path: &str,
+) -> Result<()>
+where
+    S: Primitive + 'static,
+    [S]: EncodableLayout,
+{
+    if config.embed_color_profile {
+        let profile = film::icc_profile(color_space(config), transfer_function(config));
+        color_profile::save(image, output_path, &profile)
+    } else {
+        image.save(output_path)?;
+        Ok(())
+    }
+}
+
+/// The transformation from the coordinate system of the part's files to the
+/// world.
+///
+/// Parts may be exported in a machine's global coordinates, millions of
+/// millimeters from the origin. Composing such a large translation with the
+/// part's scale in a single-precision matrix would round every transformed
+/// point differently, which makes the geometry jitter. Instead, coordinates
+/// are first re-centered on the part in double precision, and only the small
+/// re-centered coordinates are transformed in single precision.
+#[derive(Debug, Clone, Copy)]
+struct PartToWorld {
+    /// The point in the coordinate system of the part's files that becomes
+    /// the origin of the re-centered coordinate system.
+    origin: Point3<f64>,
+
+    /// The transformation from the re-centered coordinate system to the
+    /// world.
+    recentered_to_world: Matrix4<f32>,
+}
+
+impl PartToWorld {
+    /// Return the point, which is in the coordinate system of the part's
+    /// files, in the re-centered coordinate system.
+    fn recenter(&self, p: Point3<f32>) -> Point3<f32> {
+        let v = Point3::new(f64::from(p.x), f64::from(p.y), f64::from(p.z)) - self.origin;
+        Point3::new(v.x as f32, v.y as f32, v.z as f32)
+    }
+
+    /// Return the point, which is in the coordinate system of the part's
+    /// files, in world space.
+    fn transform_point(&self, p: Point3<f32>) -> Point3<f32> {
+        self.recentered_to_world.transform_point(self.recenter(p))
+    }
+}
+
+/// Load the part's mesh, centered on the origin and scaled to fit in a unit
+/// sphere. Degenerate triangles are dropped. Return the mesh and the
+/// transformation from the coordinate system of the mesh file to the world.
+fn load_mesh<'a>(
+    mesh_arena: &'a Arena<Mesh>,
+    part_config: &config::Part,
+) -> Result<(&'a Mesh, PartToWorld)> {
     let file = std::fs::File::open(part_config.stl_path.clone())?;
     let mut reader = std::io::BufReader::new(&file);
     let mesh = mesh_arena.alloc(MeshBuilder::from_stl(&mut reader)?.build());
+    // Degenerate triangles and the vertices that only they use are removed
+    // first, so that they can't affect the part's center or scale.
+    warn_degenerate_triangles(mesh, "the part");
+    mesh.remove_unused_vertices();
     let (bounds_min, bounds_max) = mesh.bounding_box().ok_or(Error::EmptyMesh)?;
-    let center = bounds_min + (bounds_max - bounds_min) / 2.0;
-    let center_to_origin = Matrix4::from_translation(Point3::new(0.0f32, 0.0f32, 0.0f32) - center);
-    mesh.transform(center_to_origin);
+    let midpoint = |min: f32, max: f32| (f64::from(min) + f64::from(max)) / 2.0;
+    let mut part_to_world = PartToWorld {
+        origin: Point3::new(
+            midpoint(bounds_min.x, bounds_max.x),
+            midpoint(bounds_min.y, bounds_max.y),
+            midpoint(bounds_min.z, bounds_max.z),
+        ),
+        recentered_to_world: Matrix4::identity(),
+    };
+    for p in &mut mesh.positions {
+        *p = part_to_world.recenter(*p);
+    }
 
     let bounding_sphere_radius = max_distance_from_origin(mesh);
-    mesh.transform(Matrix4::from_scale(1.0 / bounding_sphere_radius));
+    let scale = Matrix4::from_scale(1.0 / bounding_sphere_radius);
+    mesh.transform(scale);
+    part_to_world.recentered_to_world = scale;
 
     if part_config.handedness == config::Handedness::RightHanded {
-        mesh.transform_swapping_handedness(Matrix4::from_nonuniform_scale(1.0, -1.0, 1.0));
+        let flip = Matrix4::from_nonuniform_scale(1.0, -1.0, 1.0);
+        mesh.transform_swapping_handedness(flip);
+        part_to_world.recentered_to_world = flip * part_to_world.recentered_to_world;
     }
 
-    Ok(mesh)
+    Ok((mesh, part_to_world))
+}
+
+/// Load the configured point cloud as splats in world space. Each splat is
+/// paired with the color given for it in the file, if any.
+///
+/// * part_to_world - The transformation that was applied to the part's mesh.
+fn load_splats(
+    point_cloud_config: &config::PointCloud,
+    part_to_world: &PartToWorld,
+) -> Result<Vec<(Splat, Option<[f32; 3]>)>> {
+    // The part's transformation scales uniformly, apart from a possible
+    // reflection, so any axis gives the scale of the radius.
+    let radius = part_to_world
+        .recentered_to_world
+        .transform_vector(Vector3::new(point_cloud_config.radius, 0.0, 0.0))
+        .magnitude();
+    let points = point_cloud::load(&point_cloud_config.path)?;
+    Ok(points
+        .into_iter()
+        .map(|point| {
+            let center = part_to_world.transform_point(point.position);
+            (Splat::new(center, radius), point.color)
+        })
+        .collect())
+}
+
+/// Load the configured voxel grid and place it in world space.
+///
+/// * part_to_world - The transformation that was applied to the part's mesh.
+fn load_voxel_grid(
+    voxel_grid_config: &config::VoxelGrid,
+    part_to_world: &PartToWorld,
+) -> Result<VoxelGrid> {
+    let bytes = std::fs::read(&voxel_grid_config.path)?;
+    let (x, y, z) = voxel_grid_config.resolution;
+    let origin = &voxel_grid_config.origin;
+    let origin = part_to_world.recenter(point3(origin.x, origin.y, origin.z));
+    let grid_to_world = part_to_world.recentered_to_world
+        * Matrix4::from_translation(origin - point3(0.0, 0.0, 0.0))
+        * Matrix4::from_scale(voxel_grid_config.voxel_size);
+    VoxelGrid::from_bytes(Vector3::new(x, y, z), grid_to_world, &bytes).ok_or_else(|| {
+        Error::VoxelGrid(format!(
+            "expected {} bytes for a {}x{}x{} grid, but the file has {}",
+            x * y * z,
+            x,
+            y,
+            z,
+            bytes.len()
+        ))
+    })
+}
+
+/// Generate the configured fasteners' meshes and place them in world space,
+/// dropping any degenerate triangles. Each mesh is paired with its fastener's
+/// material.
+///
+/// * part_to_world - The transformation that was applied to the part's mesh.
+fn load_fasteners<'a, 'c>(
+    mesh_arena: &'a Arena<Mesh>,
+    fastener_configs: &'c [config::Fastener],
+    part_to_world: &PartToWorld,
+) -> Result<Vec<(&'a Mesh, &'c config::Material)>> {
+    let metric_size = |size: &str| {
+        MetricSize::from_designation(size).ok_or_else(|| Error::FastenerSize(size.to_string()))
+    };
+    fastener_configs
+        .iter()
+        .enumerate()
+        .map(|(i, fastener_config)| {
+            let builder = match &fastener_config.part {
+                config::FastenerPart::HexBolt { size, length } => {
+                    fastener::hex_bolt(&metric_size(size)?, *length)
+                }
+                config::FastenerPart::HexNut { size } => fastener::hex_nut(&metric_size(size)?),
+                config::FastenerPart::Washer { size } => fastener::washer(&metric_size(size)?),
+                config::FastenerPart::Dowel { diameter, length } => {
+                    fastener::dowel(*diameter, *length)
+                }
+            };
+            let mesh = mesh_arena.alloc(builder.build());
+
+            let (position, axis) = (&fastener_config.position, &fastener_config.axis);
+            // The fallback axis is used when the fastener's axis points down
+            // the negative z axis.
+            let rotation = Quaternion::from_arc(
+                Vector3::unit_z(),
+                vec3(axis.x, axis.y, axis.z).normalize(),
+                Some(Vector3::unit_x()),
+            );
+            let position = part_to_world.recenter(point3(position.x, position.y, position.z));
+            let fastener_to_world = part_to_world.recentered_to_world
+                * Matrix4::from_translation(position - point3(0.0, 0.0, 0.0))
+                * Matrix4::from(rotation);
+            // The part's transformation flips the y axis of right-handed
+            // meshes.
+            if part_to_world.recentered_to_world.determinant() < 0.0 {
+                mesh.transform_swapping_handedness(fastener_to_world);
+            } else {
+                mesh.transform(fastener_to_world);
+            }
+            warn_degenerate_triangles(mesh, &format!("fastener {}", i));
+            Ok((&*mesh, &fastener_config.material))
+        })
+        .collect()
+}
+
+/// Remove the mesh's degenerate triangles, which have zero area, and print a
+/// warning if there were any.
+///
+/// * name - Describes the mesh in the warning.
+fn warn_degenerate_triangles(mesh: &mut Mesh, name: &str) {
+    let removed = mesh.remove_degenerate_triangles();
+    if removed > 0 {
+        eprintln!(
+            "Warning: Dropped {} degenerate triangle{} from {}.",
+            removed,
+            if removed == 1 { "" } else { "s" },
+            name
+        );
+    }
 }
 
 fn load_material<'a>(material_config: &config::Material) -> Material {
@@ -113,45 +1989,323 @@ fn load_material<'a>(material_config: &config::Material) -> Material {
         material_config.shininess,
         0.0,
     )
+    .with_opacity(material_config.opacity.unwrap_or(1.0))
+}
+
+/// Create the physically based description of the configured material.
+///
+/// * color - If present, replaces the configured color, such as with a point's
+///   color from a point cloud file.
+fn load_physical_material(
+    material_config: &config::Material,
+    color: Option<RgbaSpectrum>,
+) -> Box<dyn renderer::material::Material + Send + Sync> {
+    let kd = color.unwrap_or_else(|| {
+        RgbaSpectrum::from_rgb(
+            material_config.color.r,
+            material_config.color.g,
+            material_config.color.b,
+        )
+    });
+    match material_config.physical {
+        Some(config::PhysicalMaterial::Matte { roughness }) => Box::new(MatteMaterial::new(
+            kd,
+            roughness.unwrap_or(0.0).to_radians(),
+        )),
+        Some(config::PhysicalMaterial::Plastic {
+            specular,
+            roughness,
+        }) => Box::new(PlasticMaterial::new(
+            kd,
+            RgbaSpectrum::from_rgb(specular.r, specular.g, specular.b),
+            roughness,
+            true,
+        )),
+        Some(config::PhysicalMaterial::Metal { metal, roughness }) => {
+            let alloy = match metal {
+                config::Metal::Aluminum => Alloy::Aluminum,
+                config::Metal::Brass => Alloy::Brass,
+                config::Metal::Copper => Alloy::Copper,
+                config::Metal::Gold => Alloy::Gold,
+                config::Metal::Silver => Alloy::Silver,
+                config::Metal::Steel => Alloy::Steel,
+                config::Metal::Custom { eta, k } => {
+                    return Box::new(MetalMaterial::new(
+                        RgbaSpectrum::from_rgb(eta.r, eta.g, eta.b),
+                        RgbaSpectrum::from_rgb(k.r, k.g, k.b),
+                        roughness,
+                        true,
+                    ))
+                }
+            };
+            Box::new(MetalMaterial::alloy(alloy, roughness))
+        }
+        Some(config::PhysicalMaterial::Glass {
+            index_of_refraction,
+            thin,
+            abbe_number,
+        }) => {
+            let mut glass = GlassMaterial::new(
+                RgbaSpectrum::constant(1.0),
+                kd,
+                index_of_refraction.unwrap_or(1.5),
+            )
+            .with_thin(thin);
+            if let Some(abbe_number) = abbe_number {
+                glass = glass.with_abbe_number(abbe_number);
+            }
+            Box::new(glass)
+        }
+        None => Box::new(MatteMaterial::new(kd, 0.0)),
+    }
+}
+
+/// What lights can be placed relative to in a frame.
+struct LightAnchors<'a> {
+    /// The pose of the frame's camera, after it's been backed out to fit the
+    /// scene.
+    camera_pose: &'a CameraPose,
+
+    /// The camera's frame in world space, which looks towards its positive z
+    /// axis and has its positive y axis as "up".
+    camera_to_world: Matrix4<f32>,
+
+    /// The named part and fasteners.
+    labeled_parts: &'a [LabeledPart],
+}
+
+impl<'a> LightAnchors<'a> {
+    fn new(config: &Config, camera_pose: &'a CameraPose, labeled_parts: &'a [LabeledPart]) -> Self {
+        // The camera's orientation doesn't depend on the resolution.
+        let camera = load_camera(&config.camera, camera_pose, Deg(0.0), Vector2::new(1, 1));
+        Self {
+            camera_pose,
+            camera_to_world: camera.camera_to_world,
+            labeled_parts,
+        }
+    }
+}
+
+/// Return the transformation that places a light at the configured position
+/// in the given frame. The light looks at what it's placed around along its
+/// positive z axis.
+fn light_to_world(
+    position: &config::LightPosition,
+    frame: usize,
+    anchors: &LightAnchors,
+) -> Result<Matrix4<f32>> {
+    match position {
+        config::LightPosition::Spherical { radius, theta, phi } => {
+            Ok(origin_to_spherical_position(
+                radius.at_frame(frame)?,
+                Deg(theta.at_frame(frame)?),
+                Deg(phi.at_frame(frame)?),
+            ))
+        }
+        config::LightPosition::CameraRelative { up, left, distance } => {
+            let up = up.at_frame(frame)?.to_radians();
+            let left = left.at_frame(frame)?.to_radians();
+            let right_axis = anchors
+                .camera_to_world
+                .transform_vector(Vector3::unit_x())
+                .normalize();
+            let up_axis = anchors
+                .camera_to_world
+                .transform_vector(Vector3::unit_y())
+                .normalize();
+            let forward_axis = anchors
+                .camera_to_world
+                .transform_vector(Vector3::unit_z())
+                .normalize();
+            // The direction from the target towards the light, which is
+            // towards the camera when both angles are zero.
+            let direction = -forward_axis * up.cos() * left.cos()
+                - right_axis * up.cos() * left.sin()
+                + up_axis * up.sin();
+            let target = anchors.camera_pose.target;
+            Ok(
+                Matrix4::from_translation(vec3(target.x, target.y, target.z))
+                    * origin_to_direction(distance.at_frame(frame)?, direction),
+            )
+        }
+        config::LightPosition::PartRelative {
+            part,
+            radius,
+            theta,
+            phi,
+        } => {
+            let centroid = anchors
+                .labeled_parts
+                .iter()
+                .find(|labeled_part| &labeled_part.name == part)
+                .map(|labeled_part| labeled_part.centroid)
+                .ok_or_else(|| Error::LightPart(part.clone()))?;
+            Ok(Matrix4::from_translation(centroid - point3(0.0, 0.0, 0.0))
+                * origin_to_spherical_position(
+                    radius.at_frame(frame)?,
+                    Deg(theta.at_frame(frame)?),
+                    Deg(phi.at_frame(frame)?),
+                ))
+        }
+    }
 }
 
-fn load_light(light_config: &config::Light) -> Light {
+/// Create the configured light as it is placed in the given frame.
+///
+/// * anchors - What the light can be placed relative to in the frame.
+/// * meters_per_world_unit - The length in meters of one unit of world space,
+///   which converts intensities given in physical units.
+fn load_light(
+    light_config: &config::Light,
+    frame: usize,
+    anchors: &LightAnchors,
+    meters_per_world_unit: f32,
+) -> Result<Light> {
     match light_config {
         config::Light::PointLight {
             position,
             intensity,
+            units,
+            ies_profile,
+            ..
         } => {
-            let light_position = origin_to_spherical_position(
-                position.radius,
-                Deg(position.theta),
-                Deg(position.phi),
-            )
-            .transform_point(point3(0.0, 0.0, 0.0));
-            Light::point_light(
-                light_position,
+            let light_to_world = light_to_world(position, frame, anchors)?;
+            let light_position = light_to_world.transform_point(point3(0.0, 0.0, 0.0));
+            let to_world = |value: f32| match units {
+                Some(config::IntensityUnit::Candela) => {
+                    IntensityUnit::Candela.to_world_intensity(value, meters_per_world_unit)
+                }
+                Some(config::IntensityUnit::Lumens) => {
+                    IntensityUnit::Lumens.to_world_intensity(value, meters_per_world_unit)
+                }
+                None => value,
+            };
+            let intensity = RgbaSpectrum::from_rgb(
+                to_world(intensity.r),
+                to_world(intensity.g),
+                to_world(intensity.b),
+            );
+            match ies_profile {
+                Some(path) => {
+                    let profile = light::read_ies_profile(std::path::Path::new(path))?;
+                    // The light's frame looks at what it's placed around along
+                    // its positive z axis, but a fixture points down its
+                    // negative z axis.
+                    Ok(Light::profiled_point_light(
+                        light_position,
+                        intensity,
+                        profile,
+                        light_to_world * Matrix4::from_angle_x(Deg(180.0)),
+                    ))
+                }
+                None => Ok(Light::point_light(light_position, intensity)),
+            }
+        }
+        config::Light::ProjectionLight {
+            position,
+            image,
+            intensity,
+            fov,
+            rotation,
+            ..
+        } => {
+            let light_to_world = light_to_world(position, frame, anchors)?
+                * Matrix4::from_angle_z(Deg(rotation.unwrap_or(0.0)));
+            let image = image::open(image)?.to_rgba8();
+            let texels = image
+                .pixels()
+                .map(|pixel| {
+                    let [r, g, b, a] = pixel.0;
+                    let alpha = a as f32 / 255.0;
+                    let linear = |c: u8| color::srgb_to_linear(c as f32 / 255.0) * alpha;
+                    RgbaSpectrum::from_rgb(linear(r), linear(g), linear(b))
+                })
+                .collect();
+            Ok(Light::projection_light(
+                light_to_world,
                 RgbaSpectrum::from_rgb(intensity.r, intensity.g, intensity.b),
-            )
+                texels,
+                Vector2::new(image.width() as usize, image.height() as usize),
+                Rad::from(Deg(*fov)).0,
+            ))
+        }
+        config::Light::SkyLight {
+            color, intensity, ..
+        } => {
+            let intensity = intensity.unwrap_or(1.0);
+            Ok(Light::uniform_infinite_light(RgbaSpectrum::from_rgb(
+                color.r * intensity,
+                color.g * intensity,
+                color.b * intensity,
+            )))
+        }
+        config::Light::EnvironmentLight {
+            path,
+            intensity,
+            rotation,
+            blur,
+            ..
+        } => {
+            let (mut radiance_map, resolution) =
+                light::read_environment_map(std::path::Path::new(path))?;
+            if let Some(blur) = blur {
+                radiance_map = light::blur_environment_map(&radiance_map, resolution, *blur);
+            }
+            let intensity = intensity.unwrap_or(1.0);
+            let radiance_map = radiance_map
+                .into_iter()
+                .map(|radiance| radiance * intensity)
+                .collect();
+            Ok(Light::infinite_area_light(
+                Matrix4::from_angle_z(Deg(rotation.unwrap_or(0.0))),
+                radiance_map,
+                resolution,
+            ))
         }
     }
 }
 
-fn load_camera(camera_config: &config::Camera, resolution: Vector2<usize>) -> OrthographicCamera {
+/// Return the length in meters of one unit of world space, assuming the part's
+/// mesh is in millimeters.
+///
+/// * part_to_world - The transformation that was applied to the part's mesh.
+fn meters_per_world_unit(part_to_world: &Matrix4<f32>) -> f32 {
+    let world_units_per_millimeter = part_to_world
+        .transform_vector(Vector3::new(1.0, 0.0, 0.0))
+        .magnitude();
+    0.001 / world_units_per_millimeter
+}
+
+/// Create the configured camera, placed at the given pose rather than the
+/// configured position.
+///
+/// * orbit - An angle by which the camera is rotated about its target, around
+///   the camera's vertical axis. This is used to offset the eyes of a stereo
+///   pair.
+fn load_camera(
+    camera_config: &config::Camera,
+    pose: &CameraPose,
+    orbit: Deg<f32>,
+    resolution: Vector2<usize>,
+) -> OrthographicCamera {
     // TODO: Return Camera trait object instead.
     match camera_config {
-        config::Camera::OrthographicCamera {
-            position,
-            z_near,
-            z_far,
-        } => {
-            let camera_to_world = origin_to_spherical_position(
-                position.radius,
-                Deg(position.theta),
-                Deg(position.phi),
-            );
+        config::Camera::OrthographicCamera { .. } => {
+            let (z_near, z_far) = camera_config.clipping_range();
+            let camera_to_world =
+                Matrix4::from_translation(vec3(pose.target.x, pose.target.y, pose.target.z))
+                    * origin_to_spherical_position(
+                        pose.position.radius,
+                        Deg(pose.position.theta),
+                        Deg(pose.position.phi),
+                    )
+                    * Matrix4::from_translation(vec3(0.0, 0.0, pose.position.radius))
+                    * Matrix4::from_angle_y(orbit)
+                    * Matrix4::from_translation(vec3(0.0, 0.0, -pose.position.radius));
             OrthographicCamera::new(
                 camera_to_world,
-                *z_near,
-                *z_far,
+                z_near,
+                z_far,
                 orthographic_screen_size(resolution.x as f32 / resolution.y as f32),
                 resolution,
             )
@@ -160,13 +2314,195 @@ fn load_camera(camera_config: &config::Camera, resolution: Vector2<usize>) -> Or
     }
 }
 
-fn load_sampler(sampler_config: &config::Sampler) -> StratifiedSampler {
-    match sampler_config {
+/// Return the camera with the configured aperture, if any, so that it renders
+/// with depth of field.
+///
+/// * focus_point - The point in world space that is in focus if the aperture
+///   doesn't give a focal distance.
+fn with_aperture(
+    camera_config: &config::Camera,
+    orthographic: OrthographicCamera,
+    focus_point: Point3<f32>,
+) -> Box<dyn Camera + Send + Sync> {
+    match camera_config {
+        config::Camera::OrthographicCamera {
+            aperture: Some(aperture),
+            ..
+        } => {
+            let focal_distance = aperture.focal_distance.unwrap_or_else(|| {
+                // The focal distance is measured along the viewing direction,
+                // which is the camera's z axis.
+                let world_to_camera = orthographic
+                    .camera_to_world
+                    .invert()
+                    .unwrap_or_else(Matrix4::identity);
+                world_to_camera.transform_point(focus_point).z
+            });
+            Box::new(TelecentricCamera::new(
+                orthographic,
+                aperture.lens_radius,
+                focal_distance,
+            ))
+        }
+        _ => Box::new(orthographic),
+    }
+}
+
+/// Return the point that the configured aperture focuses on, if it focuses on
+/// a part or a point rather than the camera's target.
+///
+/// * labeled_parts - The named part and fasteners.
+fn focus_point(config: &Config, labeled_parts: &[LabeledPart]) -> Result<Option<Point3<f32>>> {
+    let focus = match &config.camera {
+        config::Camera::OrthographicCamera {
+            aperture: Some(aperture),
+            ..
+        } if aperture.focal_distance.is_none() => aperture.focus.as_ref(),
+        _ => None,
+    };
+    Ok(match focus {
+        Some(config::Focus::Part(name)) => Some(
+            labeled_parts
+                .iter()
+                .find(|part| &part.name == name)
+                .map(|part| part.centroid)
+                .ok_or_else(|| Error::FocusPart(name.clone()))?,
+        ),
+        Some(config::Focus::Point(point)) => Some(point3(point.x, point.y, point.z)),
+        None => None,
+    })
+}
+
+fn load_sampler(sampler_config: &config::Sampler) -> Result<ConfiguredSampler> {
+    Ok(match sampler_config {
         config::Sampler::StratifiedSampler {
             x_strata_count,
             y_strata_count,
+            samples_per_pixel,
             jitter,
-        } => StratifiedSampler::new(*x_strata_count, *y_strata_count, 5, 0, *jitter),
+        } => ConfiguredSampler::Stratified(load_stratified_sampler(
+            *x_strata_count,
+            *y_strata_count,
+            *samples_per_pixel,
+            *jitter,
+        )?),
+        config::Sampler::HaltonSampler { samples_per_pixel } => {
+            ConfiguredSampler::Halton(HaltonSampler::new(*samples_per_pixel, 0))
+        }
+        config::Sampler::Pmj02Sampler { samples_per_pixel } => {
+            ConfiguredSampler::Pmj02(Pmj02Sampler::new(*samples_per_pixel, 0))
+        }
+        config::Sampler::AdaptiveSampler {
+            sampler,
+            min_samples,
+            max_samples,
+            variance_threshold,
+        } => ConfiguredSampler::Adaptive(Box::new(AdaptiveSampler::new(
+            load_sampler(sampler)?,
+            AdaptiveSampling::new(*min_samples, *max_samples, *variance_threshold),
+        ))),
+        config::Sampler::BlueNoiseSampler { sampler } => {
+            ConfiguredSampler::BlueNoise(Box::new(BlueNoiseSampler::new(load_sampler(sampler)?, 0)))
+        }
+    })
+}
+
+/// Create a stratified sampler from either its strata counts or its number of
+/// samples per pixel. Counts that aren't configured are zero.
+fn load_stratified_sampler(
+    x_strata_count: usize,
+    y_strata_count: usize,
+    samples_per_pixel: usize,
+    jitter: bool,
+) -> Result<StratifiedSampler> {
+    match (x_strata_count, y_strata_count, samples_per_pixel) {
+        (x, y, 0) if x > 0 && y > 0 => Ok(StratifiedSampler::new(x, y, 5, 0, jitter)),
+        (0, 0, spp) if spp > 0 => Ok(StratifiedSampler::from_samples_per_pixel(spp, 5, 0, jitter)),
+        _ => Err(Error::SamplerConfig(
+            "a stratified sampler needs either both strata counts or samples_per_pixel".to_string(),
+        )),
+    }
+}
+
+/// Load the priority mask image, resizing it to match the output resolution
+/// if necessary.
+fn load_priority_mask(
+    priority_mask_config: &config::PriorityMask,
+    resolution: Vector2<usize>,
+) -> Result<PriorityMask> {
+    let image = image::open(&priority_mask_config.path)?.resize_exact(
+        resolution.x as u32,
+        resolution.y as u32,
+        imageops::FilterType::Triangle,
+    );
+    Ok(PriorityMask::from_image(
+        &image,
+        priority_mask_config.max_passes,
+    ))
+}
+
+fn load_aov(aov_config: &config::Aov) -> Aov {
+    match aov_config {
+        config::Aov::Normal => Aov::Normal,
+        config::Aov::Depth => Aov::Depth,
+        config::Aov::Albedo => Aov::Albedo,
+        config::Aov::ObjectId => Aov::ObjectId,
+    }
+}
+
+fn load_filter(filter_config: Option<&config::Filter>) -> Box<dyn Filter + Send + Sync> {
+    match filter_config {
+        Some(config::Filter::BoxFilter {
+            half_width,
+            half_height,
+        }) => Box::new(BoxFilter::new(*half_width, *half_height)),
+        Some(config::Filter::TriangleFilter {
+            half_width,
+            half_height,
+        }) => Box::new(TriangleFilter::new(*half_width, *half_height)),
+        Some(config::Filter::MitchellFilter {
+            half_width,
+            half_height,
+            b,
+            c,
+        }) => Box::new(MitchellFilter::new(*half_width, *half_height, *b, *c)),
+        None => Box::new(MitchellFilter::new(2.0, 2.0, 1.0 / 3.0, 1.0 / 3.0)),
+    }
+}
+
+fn load_post_process(post_process_config: &config::PostProcess) -> Box<dyn PostProcess> {
+    match post_process_config {
+        config::PostProcess::Exposure { stops } => Box::new(Exposure::new(*stops)),
+        config::PostProcess::ExposureValue { ev100 } => Box::new(Exposure::from_ev100(*ev100)),
+        config::PostProcess::CameraExposure {
+            iso,
+            shutter,
+            f_number,
+        } => Box::new(Exposure::from_camera_settings(*iso, *shutter, *f_number)),
+        config::PostProcess::Curves { points } => Box::new(Curves::new(points.clone())),
+        config::PostProcess::Sharpen { amount } => Box::new(Sharpen::new(*amount)),
+        config::PostProcess::Background { color } => Box::new(BackgroundComposite::new(
+            RgbaSpectrum::from_rgb(color.r, color.g, color.b),
+        )),
+        config::PostProcess::Tonemap {
+            operator,
+            exposure,
+            gamma,
+        } => {
+            let operator = match operator {
+                config::TonemapOperator::Clip => TonemapOperator::Clip,
+                config::TonemapOperator::Reinhard => TonemapOperator::Reinhard,
+                config::TonemapOperator::ReinhardExtended { white } => {
+                    TonemapOperator::ReinhardExtended { white: *white }
+                }
+                config::TonemapOperator::AcesFilmic => TonemapOperator::AcesFilmic,
+            };
+            Box::new(
+                Tonemap::new(operator)
+                    .with_exposure(*exposure)
+                    .with_gamma(*gamma),
+            )
+        }
     }
 }
 
@@ -194,6 +2530,15 @@ fn origin_to_spherical_position(r: f32, theta: Deg<f32>, phi: Deg<f32>) -> Matri
         * Matrix4::from_translation(Vector3::new(0.0, 0.0, -1.0 * r))
 }
 
+/// Returns a transformation matrix like `origin_to_spherical_position` for the
+/// point at the given distance from the origin in the given direction.
+fn origin_to_direction(r: f32, direction: Vector3<f32>) -> Matrix4<f32> {
+    let direction = direction.normalize();
+    let theta = Rad(direction.z.clamp(-1.0, 1.0).acos());
+    let phi = Rad((-direction.y).atan2(direction.x));
+    origin_to_spherical_position(r, Deg::from(theta), Deg::from(phi))
+}
+
 /// Return the maximum distance between any vertex and the origin.
 fn max_distance_from_origin(mesh: &Mesh) -> f32 {
     mesh.positions
@@ -206,9 +2551,9 @@ fn max_distance_from_origin(mesh: &Mesh) -> f32 {
 }
 
 /// Crop transparent edges from the image.
-fn crop_to_non_transparent(
-    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
-) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>> {
+fn crop_to_non_transparent<S: Primitive + 'static>(
+    image: &ImageBuffer<Rgba<S>, Vec<S>>,
+) -> Result<ImageBuffer<Rgba<S>, Vec<S>>> {
     let (crop_bounds_min, crop_bounds_max) =
         non_transparent_bounds(image).ok_or(Error::ZeroAreaImage)?;
     let crop_bounds_diag = crop_bounds_max - crop_bounds_min;
@@ -225,13 +2570,13 @@ fn crop_to_non_transparent(
 
 /// Return the min and max (inclusive) pixels of a 2D bounding box around any
 /// non-transparent content in the image.
-fn non_transparent_bounds(
-    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+fn non_transparent_bounds<S: Primitive + 'static>(
+    image: &ImageBuffer<Rgba<S>, Vec<S>>,
 ) -> Option<(Point2<u32>, Point2<u32>)> {
     let mut min_max = None;
 
     for (x, y, rgba) in image.enumerate_pixels() {
-        if rgba.0[3] == 0 {
+        if rgba.0[3] == S::zero() {
             continue;
         }
 
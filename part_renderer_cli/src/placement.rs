@@ -0,0 +1,113 @@
+use crate::animation::CameraPose;
+use bvh::aabb::AABB;
+use cgmath::{point3, Deg, Point3, Transform, Vector3};
+use renderer::scene::MemoryReport;
+
+/// The largest number of steps that a camera is moved back when it is inside
+/// the geometry.
+const MAX_BACK_OUT_STEPS: usize = 1000;
+
+/// The queries about a scene's geometry that are needed to check the
+/// camera's placement and to report on the scene.
+pub trait SceneGeometry {
+    fn world_bound(&self) -> AABB;
+    fn is_inside(&self, point: Point3<f32>) -> bool;
+    fn memory_report(&self) -> MemoryReport;
+
+    /// Return the parametric value at which a ray with the given origin and
+    /// direction first hits the scene's geometry, if it hits anything.
+    fn first_hit(&self, origin: Point3<f32>, direction: Vector3<f32>) -> Option<f32>;
+}
+
+impl<'msh, 'mtrl> SceneGeometry for renderer::scene::Scene<'msh, 'mtrl> {
+    fn world_bound(&self) -> AABB {
+        renderer::scene::Scene::world_bound(self)
+    }
+
+    fn is_inside(&self, point: Point3<f32>) -> bool {
+        renderer::scene::Scene::is_inside(self, point)
+    }
+
+    fn memory_report(&self) -> MemoryReport {
+        renderer::scene::Scene::memory_report(self)
+    }
+
+    fn first_hit(&self, origin: Point3<f32>, direction: Vector3<f32>) -> Option<f32> {
+        renderer::scene::Scene::first_hit(self, origin, direction)
+    }
+}
+
+impl<'msh> SceneGeometry for renderer::simple::Scene<'msh> {
+    fn world_bound(&self) -> AABB {
+        renderer::simple::Scene::world_bound(self)
+    }
+
+    fn is_inside(&self, point: Point3<f32>) -> bool {
+        renderer::simple::Scene::is_inside(self, point)
+    }
+
+    fn memory_report(&self) -> MemoryReport {
+        renderer::simple::Scene::memory_report(self)
+    }
+
+    fn first_hit(&self, origin: Point3<f32>, direction: Vector3<f32>) -> Option<f32> {
+        renderer::simple::Scene::first_hit(self, origin, direction)
+    }
+}
+
+/// Return the position of the camera in world space.
+pub fn camera_position(pose: &CameraPose) -> Point3<f32> {
+    let target = point3(pose.target.x, pose.target.y, pose.target.z);
+    let offset = crate::origin_to_spherical_position(
+        pose.position.radius,
+        Deg(pose.position.theta),
+        Deg(pose.position.phi),
+    )
+    .transform_point(point3(0.0, 0.0, 0.0));
+    target + (offset - point3(0.0, 0.0, 0.0))
+}
+
+/// Check whether the camera is inside the scene's geometry, which would make
+/// the rendered image black. If it is and `back_out` is true, return a pose
+/// that has been moved back along the camera's view axis until the camera is
+/// outside. Otherwise print a warning and return the pose unchanged.
+///
+/// * frame - The index of the frame, which is used in messages.
+pub fn place_camera<S: SceneGeometry>(
+    scene: &S,
+    pose: &CameraPose,
+    back_out: bool,
+    frame: usize,
+) -> CameraPose {
+    if !scene.is_inside(camera_position(pose)) {
+        return *pose;
+    }
+    if !back_out {
+        eprintln!(
+            "Warning: The camera is inside the geometry in frame {}. \
+             Set `back_out_camera: true` to move it outside.",
+            frame
+        );
+        return *pose;
+    }
+
+    let bound = scene.world_bound();
+    let step = 0.01 * (bound.max - bound.min).length();
+    let mut placed = *pose;
+    for _ in 0..MAX_BACK_OUT_STEPS {
+        placed.position.radius += step;
+        if !scene.is_inside(camera_position(&placed)) {
+            eprintln!(
+                "The camera was inside the geometry in frame {}, so it was moved back by {:.3}.",
+                frame,
+                placed.position.radius - pose.position.radius
+            );
+            return placed;
+        }
+    }
+    eprintln!(
+        "Warning: The camera is inside the geometry in frame {}, and it couldn't be moved outside.",
+        frame
+    );
+    *pose
+}
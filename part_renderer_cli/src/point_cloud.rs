@@ -0,0 +1,51 @@
+use crate::error::{Error, Result};
+use cgmath::Point3;
+use std::io::BufRead;
+
+/// A point read from a point cloud file.
+pub struct Point {
+    pub position: Point3<f32>,
+
+    /// The point's linear RGB color in [0, 1], if the file gives one.
+    pub color: Option<[f32; 3]>,
+}
+
+/// Read a point cloud from a text file with one point per line. Each line
+/// contains either an x, y, and z coordinate, or an x, y, and z coordinate
+/// followed by red, green, and blue components in [0, 255], separated by
+/// whitespace or commas. Blank lines and lines starting with `#` are skipped.
+pub fn load(path: &str) -> Result<Vec<Point>> {
+    let file = std::fs::File::open(path)?;
+    let mut points = vec![];
+    for (line_index, line) in std::io::BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let values = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|value| !value.is_empty())
+            .map(|value| value.parse::<f32>())
+            .collect::<std::result::Result<Vec<f32>, _>>()?;
+        let point = match values[..] {
+            [x, y, z] => Point {
+                position: Point3::new(x, y, z),
+                color: None,
+            },
+            [x, y, z, r, g, b] => Point {
+                position: Point3::new(x, y, z),
+                color: Some([r / 255.0, g / 255.0, b / 255.0]),
+            },
+            _ => {
+                return Err(Error::PointCloud(format!(
+                    "line {} has {} values, but expected 3 or 6",
+                    line_index + 1,
+                    values.len()
+                )))
+            }
+        };
+        points.push(point);
+    }
+    Ok(points)
+}
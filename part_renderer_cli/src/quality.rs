@@ -0,0 +1,72 @@
+use crate::config::{Config, Filter, NegativeLobes, Sampler};
+
+/// A bundle of sampler, path depth, and reconstruction filter settings that
+/// trade render time for image quality.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quality {
+    /// Few samples and bounces, for quickly checking a scene's framing and
+    /// lighting.
+    Draft,
+
+    /// A balance of speed and quality that suits most renders.
+    Standard,
+
+    /// Enough samples that noise is rarely visible.
+    High,
+
+    /// Many samples and bounces, for images that will be published.
+    Final,
+}
+
+impl Quality {
+    /// The names that the presets are selected by on the command line.
+    pub const NAMES: [&'static str; 4] = ["draft", "standard", "high", "final"];
+
+    /// Return the preset with the given name, if there is one.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "draft" => Some(Quality::Draft),
+            "standard" => Some(Quality::Standard),
+            "high" => Some(Quality::High),
+            "final" => Some(Quality::Final),
+            _ => None,
+        }
+    }
+
+    /// Replace the config's sampler, maximum path depth, filter, and handling
+    /// of the filter's negative lobes with the preset's.
+    pub fn apply(&self, config: &mut Config) {
+        let (samples_per_pixel, max_depth) = match self {
+            Quality::Draft => (4, 2),
+            Quality::Standard => (16, 5),
+            Quality::High => (64, 8),
+            Quality::Final => (256, 12),
+        };
+        let sampler = Sampler::Pmj02Sampler { samples_per_pixel };
+        config.sampler = match self {
+            // Blue-noise offsets make the little noise that's left at higher
+            // sample counts look like fine grain rather than blotches.
+            Quality::High | Quality::Final => Sampler::BlueNoiseSampler {
+                sampler: Box::new(sampler),
+            },
+            Quality::Draft | Quality::Standard => sampler,
+        };
+        config.max_depth = Some(max_depth);
+        config.filter = Some(match self {
+            Quality::Draft => Filter::BoxFilter {
+                half_width: 0.5,
+                half_height: 0.5,
+            },
+            Quality::Standard | Quality::High | Quality::Final => Filter::MitchellFilter {
+                half_width: 2.0,
+                half_height: 2.0,
+                b: 1.0 / 3.0,
+                c: 1.0 / 3.0,
+            },
+        });
+        config.negative_lobes = Some(match self {
+            Quality::Draft | Quality::Standard => NegativeLobes::Keep,
+            Quality::High | Quality::Final => NegativeLobes::ClampPixels,
+        });
+    }
+}
@@ -0,0 +1,214 @@
+use bvh::aabb::AABB;
+use cgmath::{Point3, Transform, Vector2};
+use renderer::camera::OrthographicCamera;
+use renderer::cpu::SimdLevel;
+use renderer::film::LuminanceHistogram;
+use renderer::light::Light;
+use renderer::scene::{MemoryReport, Scene};
+
+/// Print the capabilities of the machine that the render would run on.
+pub fn print_system() {
+    let simd = SimdLevel::current();
+    println!("System");
+    if let Ok(threads) = std::thread::available_parallelism() {
+        println!("  Threads: {}", threads);
+    }
+    println!("  SIMD: {} ({} f32 lanes)", simd.name(), simd.f32_lanes());
+}
+
+/// Print the scene's bounds, its size, and an estimate of the memory its
+/// geometry and textures need.
+pub fn print_scene(scene: &Scene) {
+    let bound = scene.world_bound();
+    println!("Scene");
+    if scene.primitives.primitives().is_empty() {
+        println!("  WARNING: The scene has no geometry.");
+        return;
+    }
+    println!(
+        "  Bounds: ({:.3}, {:.3}, {:.3}) to ({:.3}, {:.3}, {:.3})",
+        bound.min.x, bound.min.y, bound.min.z, bound.max.x, bound.max.y, bound.max.z
+    );
+    println!("  Primitives: {}", scene.primitives.primitives().len());
+    print_memory(&scene.memory_report());
+}
+
+/// Print an estimate of the memory that a scene's geometry and textures need,
+/// broken down by what it's spent on.
+pub fn print_memory(report: &MemoryReport) {
+    println!("  Estimated memory: {}", format_bytes(report.total_bytes()));
+    println!(
+        "    Meshes: {} ({} distinct, {} instances)",
+        format_bytes(report.mesh_bytes),
+        report.mesh_count,
+        report.mesh_instance_count
+    );
+    if report.voxel_grid_count > 0 {
+        println!(
+            "    Voxel grids: {} ({} grids)",
+            format_bytes(report.voxel_grid_bytes),
+            report.voxel_grid_count
+        );
+    }
+    println!(
+        "    Primitives: {} ({} primitives)",
+        format_bytes(report.primitive_bytes),
+        report.primitive_count
+    );
+    println!(
+        "    BVH nodes: {} ({} nodes)",
+        format_bytes(report.bvh_node_bytes),
+        report.bvh_node_count
+    );
+    if report.texture_count > 0 {
+        println!(
+            "    Textures: {} ({} textures)",
+            format_bytes(report.texture_bytes),
+            report.texture_count
+        );
+    }
+}
+
+/// Print the luminance histogram of a finished image as a bar chart, along
+/// with the fractions of its pixels that are blown out or crushed to black.
+pub fn print_histogram(histogram: &LuminanceHistogram) {
+    const BAR_WIDTH: usize = 40;
+    println!("Luminance");
+    let max_count = histogram.bins.iter().copied().max().unwrap_or(0).max(1);
+    let bin_width = 1.0 / histogram.bins.len() as f32;
+    for (i, count) in histogram.bins.iter().enumerate() {
+        println!(
+            "  {:.2}-{:.2} {:<width$} {}",
+            i as f32 * bin_width,
+            (i + 1) as f32 * bin_width,
+            "#".repeat(count * BAR_WIDTH / max_count),
+            count,
+            width = BAR_WIDTH
+        );
+    }
+    println!(
+        "  Blown out: {:.1}%, crushed: {:.1}%",
+        100.0 * histogram.clipped_fraction(),
+        100.0 * histogram.crushed_fraction()
+    );
+}
+
+/// Print how the scene is framed by the camera and where the lights are
+/// relative to the scene for a single frame.
+pub fn print_frame(
+    frame: usize,
+    scene: &Scene,
+    camera: &OrthographicCamera,
+    lights: &[Light],
+    resolution: Vector2<usize>,
+) {
+    println!("Frame {}", frame);
+    let bound = scene.world_bound();
+    let has_geometry = !scene.primitives.primitives().is_empty();
+
+    let camera_position = camera
+        .camera_to_world
+        .transform_point(Point3::new(0.0, 0.0, 0.0));
+    println!(
+        "  Camera: ({:.3}, {:.3}, {:.3})",
+        camera_position.x, camera_position.y, camera_position.z
+    );
+    if has_geometry {
+        print_framing(&bound, camera, resolution);
+        if scene.is_inside(camera_position) {
+            println!("  WARNING: The camera is inside the geometry.");
+        }
+    }
+
+    for (i, light) in lights.iter().enumerate() {
+        match light.position() {
+            Some(p) => {
+                let location = if has_geometry && contains(&bound, p) {
+                    "inside the scene bounds"
+                } else {
+                    "outside the scene bounds"
+                };
+                println!(
+                    "  Light {}: ({:.3}, {:.3}, {:.3}), {}",
+                    i, p.x, p.y, p.z, location
+                );
+                if has_geometry && scene.is_inside(p) {
+                    println!("  WARNING: Light {} is inside the geometry.", i);
+                }
+            }
+            None => println!("  Light {}: no single position", i),
+        }
+    }
+    if lights.is_empty() {
+        println!("  WARNING: The frame has no lights.");
+    }
+}
+
+/// Print how much of the image the scene's bounds cover and how much of the
+/// bounds are visible.
+fn print_framing(bound: &AABB, camera: &OrthographicCamera, resolution: Vector2<usize>) {
+    let world_to_raster = camera.world_to_raster();
+    let corners = [
+        Point3::new(bound.min.x, bound.min.y, bound.min.z),
+        Point3::new(bound.max.x, bound.min.y, bound.min.z),
+        Point3::new(bound.min.x, bound.max.y, bound.min.z),
+        Point3::new(bound.min.x, bound.min.y, bound.max.z),
+        Point3::new(bound.max.x, bound.max.y, bound.min.z),
+        Point3::new(bound.max.x, bound.min.y, bound.max.z),
+        Point3::new(bound.min.x, bound.max.y, bound.max.z),
+        Point3::new(bound.max.x, bound.max.y, bound.max.z),
+    ];
+    let (mut min, mut max) = (
+        Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+        Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+    );
+    for corner in corners.iter() {
+        let p = world_to_raster.transform_point(*corner);
+        min = Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+        max = Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+    }
+
+    // The area of the scene's projected bounds that falls within the image.
+    let (width, height) = (resolution.x as f32, resolution.y as f32);
+    let visible_width = (max.x.min(width) - min.x.max(0.0)).max(0.0);
+    let visible_height = (max.y.min(height) - min.y.max(0.0)).max(0.0);
+    let visible_area = visible_width * visible_height;
+    let projected_area = (max.x - min.x) * (max.y - min.y);
+    println!(
+        "  Scene bounds cover {:.1}% of the image",
+        100.0 * visible_area / (width * height)
+    );
+    if projected_area > 0.0 && visible_area < projected_area {
+        println!(
+            "  WARNING: Only {:.1}% of the scene bounds are in view.",
+            100.0 * visible_area / projected_area
+        );
+    }
+    if visible_area == 0.0 {
+        println!("  WARNING: The scene is entirely out of view.");
+    }
+
+    // Raster space depths are 0 at the near clipping plane and 1 at the far
+    // clipping plane.
+    if min.z < 0.0 {
+        println!("  WARNING: The scene bounds extend past the near clipping plane.");
+    }
+    if max.z > 1.0 {
+        println!("  WARNING: The scene bounds extend past the far clipping plane.");
+    }
+}
+
+fn contains(bound: &AABB, p: Point3<f32>) -> bool {
+    bound.contains(&bvh::Point3::new(p.x, p.y, p.z))
+}
+
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
@@ -0,0 +1,143 @@
+use cgmath::Point2;
+use renderer::color::RgbaSpectrum;
+use renderer::sampler::{
+    AdaptiveSampler, BlueNoiseSampler, HaltonSampler, IncrementalSampler, Pmj02Sampler,
+    StratifiedSampler,
+};
+
+/// One of the samplers that can be chosen in the config.
+pub enum ConfiguredSampler {
+    Stratified(StratifiedSampler),
+    Halton(HaltonSampler),
+    Pmj02(Pmj02Sampler),
+    Adaptive(Box<AdaptiveSampler<ConfiguredSampler>>),
+    BlueNoise(Box<BlueNoiseSampler<ConfiguredSampler>>),
+}
+
+impl IncrementalSampler for ConfiguredSampler {
+    fn clone_with_seed(&self, seed: u64) -> Self {
+        match self {
+            ConfiguredSampler::Stratified(s) => {
+                ConfiguredSampler::Stratified(s.clone_with_seed(seed))
+            }
+            ConfiguredSampler::Halton(s) => ConfiguredSampler::Halton(s.clone_with_seed(seed)),
+            ConfiguredSampler::Pmj02(s) => ConfiguredSampler::Pmj02(s.clone_with_seed(seed)),
+            ConfiguredSampler::Adaptive(s) => {
+                ConfiguredSampler::Adaptive(Box::new(s.clone_with_seed(seed)))
+            }
+            ConfiguredSampler::BlueNoise(s) => {
+                ConfiguredSampler::BlueNoise(Box::new(s.clone_with_seed(seed)))
+            }
+        }
+    }
+
+    fn samples_per_pixel(&self) -> usize {
+        match self {
+            ConfiguredSampler::Stratified(s) => s.samples_per_pixel(),
+            ConfiguredSampler::Halton(s) => s.samples_per_pixel(),
+            ConfiguredSampler::Pmj02(s) => s.samples_per_pixel(),
+            ConfiguredSampler::Adaptive(s) => s.samples_per_pixel(),
+            ConfiguredSampler::BlueNoise(s) => s.samples_per_pixel(),
+        }
+    }
+
+    fn start_pixel(&mut self, pixel: Point2<i32>) {
+        match self {
+            ConfiguredSampler::Stratified(s) => s.start_pixel(pixel),
+            ConfiguredSampler::Halton(s) => s.start_pixel(pixel),
+            ConfiguredSampler::Pmj02(s) => s.start_pixel(pixel),
+            ConfiguredSampler::Adaptive(s) => s.start_pixel(pixel),
+            ConfiguredSampler::BlueNoise(s) => s.start_pixel(pixel),
+        }
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        match self {
+            ConfiguredSampler::Stratified(s) => s.get_1d(),
+            ConfiguredSampler::Halton(s) => s.get_1d(),
+            ConfiguredSampler::Pmj02(s) => s.get_1d(),
+            ConfiguredSampler::Adaptive(s) => s.get_1d(),
+            ConfiguredSampler::BlueNoise(s) => s.get_1d(),
+        }
+    }
+
+    fn get_2d(&mut self) -> Point2<f32> {
+        match self {
+            ConfiguredSampler::Stratified(s) => s.get_2d(),
+            ConfiguredSampler::Halton(s) => s.get_2d(),
+            ConfiguredSampler::Pmj02(s) => s.get_2d(),
+            ConfiguredSampler::Adaptive(s) => s.get_2d(),
+            ConfiguredSampler::BlueNoise(s) => s.get_2d(),
+        }
+    }
+
+    fn start_next_sample(&mut self) -> bool {
+        match self {
+            ConfiguredSampler::Stratified(s) => s.start_next_sample(),
+            ConfiguredSampler::Halton(s) => s.start_next_sample(),
+            ConfiguredSampler::Pmj02(s) => s.start_next_sample(),
+            ConfiguredSampler::Adaptive(s) => s.start_next_sample(),
+            ConfiguredSampler::BlueNoise(s) => s.start_next_sample(),
+        }
+    }
+
+    fn record_radiance(&mut self, radiance: &RgbaSpectrum) {
+        match self {
+            ConfiguredSampler::Stratified(s) => s.record_radiance(radiance),
+            ConfiguredSampler::Halton(s) => s.record_radiance(radiance),
+            ConfiguredSampler::Pmj02(s) => s.record_radiance(radiance),
+            ConfiguredSampler::Adaptive(s) => s.record_radiance(radiance),
+            ConfiguredSampler::BlueNoise(s) => s.record_radiance(radiance),
+        }
+    }
+
+    fn prepare_1d_array(&mut self, count: usize) {
+        match self {
+            ConfiguredSampler::Stratified(s) => s.prepare_1d_array(count),
+            ConfiguredSampler::Halton(s) => s.prepare_1d_array(count),
+            ConfiguredSampler::Pmj02(s) => s.prepare_1d_array(count),
+            ConfiguredSampler::Adaptive(s) => s.prepare_1d_array(count),
+            ConfiguredSampler::BlueNoise(s) => s.prepare_1d_array(count),
+        }
+    }
+
+    fn prepare_2d_array(&mut self, count: usize) {
+        match self {
+            ConfiguredSampler::Stratified(s) => s.prepare_2d_array(count),
+            ConfiguredSampler::Halton(s) => s.prepare_2d_array(count),
+            ConfiguredSampler::Pmj02(s) => s.prepare_2d_array(count),
+            ConfiguredSampler::Adaptive(s) => s.prepare_2d_array(count),
+            ConfiguredSampler::BlueNoise(s) => s.prepare_2d_array(count),
+        }
+    }
+
+    fn round_count(&self, ideal_count: usize) -> usize {
+        match self {
+            ConfiguredSampler::Stratified(s) => s.round_count(ideal_count),
+            ConfiguredSampler::Halton(s) => s.round_count(ideal_count),
+            ConfiguredSampler::Pmj02(s) => s.round_count(ideal_count),
+            ConfiguredSampler::Adaptive(s) => s.round_count(ideal_count),
+            ConfiguredSampler::BlueNoise(s) => s.round_count(ideal_count),
+        }
+    }
+
+    fn get_1d_vec(&mut self) -> Option<Vec<f32>> {
+        match self {
+            ConfiguredSampler::Stratified(s) => s.get_1d_vec(),
+            ConfiguredSampler::Halton(s) => s.get_1d_vec(),
+            ConfiguredSampler::Pmj02(s) => s.get_1d_vec(),
+            ConfiguredSampler::Adaptive(s) => s.get_1d_vec(),
+            ConfiguredSampler::BlueNoise(s) => s.get_1d_vec(),
+        }
+    }
+
+    fn get_2d_vec(&mut self) -> Option<Vec<Point2<f32>>> {
+        match self {
+            ConfiguredSampler::Stratified(s) => s.get_2d_vec(),
+            ConfiguredSampler::Halton(s) => s.get_2d_vec(),
+            ConfiguredSampler::Pmj02(s) => s.get_2d_vec(),
+            ConfiguredSampler::Adaptive(s) => s.get_2d_vec(),
+            ConfiguredSampler::BlueNoise(s) => s.get_2d_vec(),
+        }
+    }
+}
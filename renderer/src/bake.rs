@@ -0,0 +1,487 @@
+//! Provides a utility that bakes the surface detail of a detailed, high-poly
+//! mesh into texture maps for a simplified, low-poly version of the same
+//! shape.
+//!
+//! For each texel in the low-poly mesh's UV layout, a ray is cast inward from a
+//! cage around the low-poly surface to find the high-poly surface. The
+//! high-poly normal at the hit is written to a tangent-space normal map, and
+//! the ambient occlusion at the hit is written to an AO map.
+//!
+//! The lighting on a mesh can be baked into its UV layout in the same way, so
+//! that renders from this crate can be reused by real-time viewers.
+
+use crate::{
+    color::RgbaSpectrum,
+    film::Film,
+    filter::{BoxFilter, Filter},
+    geometry::bounds::Bounds2,
+    integrator::RayTracer,
+    interaction::OffsetRayOrigin,
+    material::MatteMaterial,
+    primitive::PrimitiveAggregate,
+    ray::Ray,
+    sampler::IncrementalSampler,
+    sampling,
+};
+use cgmath::{EuclideanSpace, InnerSpace, Point2, Point3, Vector2, Vector3, Zero};
+use image::{ImageBuffer, Luma, Rgba};
+use mesh::Mesh;
+
+/// The color of a texel in a tangent-space normal map whose normal is
+/// unperturbed.
+const FLAT_NORMAL: Rgba<u8> = Rgba([128, 128, 255, 255]);
+
+/// The texture maps produced by baking.
+pub struct BakedMaps {
+    /// The high-poly surface normals in the tangent space of the low-poly
+    /// surface, with each component mapped from [-1, 1] to [0, 255]. Texels
+    /// that aren't covered by the low-poly UV layout are transparent.
+    pub normal_map: ImageBuffer<Rgba<u8>, Vec<u8>>,
+
+    /// The fraction of the hemisphere above the high-poly surface that is
+    /// unoccluded, where 255 is fully unoccluded.
+    pub ao_map: ImageBuffer<Luma<u8>, Vec<u8>>,
+}
+
+/// Bakes normal and ambient occlusion maps from a high-poly mesh onto the UV
+/// layout of a low-poly mesh.
+pub struct Baker {
+    resolution: Vector2<usize>,
+    cage_distance: f32,
+    ao_sample_count: usize,
+    ao_distance: f32,
+}
+
+impl Baker {
+    /// Create a baker.
+    ///
+    /// * resolution - The width and height of the baked maps in texels.
+    /// * cage_distance - How far the cage is pushed out from the low-poly
+    ///   surface along its normals. Rays are cast inward from the cage, so
+    ///   high-poly detail that lies further than this from the low-poly
+    ///   surface is missed.
+    /// * ao_sample_count - The number of occlusion rays cast from each high-poly
+    ///   hit. This is rounded up to a square number.
+    /// * ao_distance - The distance beyond which geometry doesn't occlude.
+    pub fn new(
+        resolution: Vector2<usize>,
+        cage_distance: f32,
+        ao_sample_count: usize,
+        ao_distance: f32,
+    ) -> Self {
+        Self {
+            resolution,
+            cage_distance,
+            ao_sample_count,
+            ao_distance,
+        }
+    }
+
+    /// Bake maps for the low-poly mesh from the high-poly mesh. Return `None`
+    /// if the low-poly mesh has no UV coordinates.
+    pub fn bake(&self, low_poly: &Mesh, high_poly: &Mesh) -> Option<BakedMaps> {
+        // The high-poly surface is only intersected, never shaded, but every
+        // primitive needs a material.
+        let material = MatteMaterial::new(RgbaSpectrum::constant(1.0), 0.0);
+        let high_poly = PrimitiveAggregate::from_mesh(high_poly, &material);
+
+        let grid_width = (self.ao_sample_count as f32).sqrt().ceil().max(1.0) as usize;
+        let ao_samples = sampling::grid_samples_2d(grid_width);
+
+        let (width, height) = (self.resolution.x as u32, self.resolution.y as u32);
+        let mut normal_map = ImageBuffer::from_pixel(width, height, Rgba([0, 0, 0, 0]));
+        let mut ao_map = ImageBuffer::from_pixel(width, height, Luma([255]));
+
+        self.for_each_texel(low_poly, |texel, frame| {
+            let (texel_normal, ao) =
+                match self.cast_to_high_poly(&high_poly, texel.point, texel.normal) {
+                    Some((hit_normal, hit)) => {
+                        let ao = self.ambient_occlusion(&high_poly, &hit, hit_normal, &ao_samples);
+                        let tangent_normal = frame.to_tangent_space(hit_normal, texel.normal);
+                        (encode_normal(tangent_normal), ao)
+                    }
+                    None => (FLAT_NORMAL, 1.0),
+                };
+            normal_map.put_pixel(texel.x, texel.y, texel_normal);
+            ao_map.put_pixel(texel.x, texel.y, Luma([(ao * 255.0).round() as u8]));
+        })?;
+
+        Some(BakedMaps { normal_map, ao_map })
+    }
+
+    /// Bake the light leaving the mesh's surface into a film that covers its UV
+    /// layout. Return `None` if the mesh has no UV coordinates.
+    ///
+    /// Each texel is seen by rays that look straight down at the surface from
+    /// the cage, so the ray tracer determines what is baked. For example,
+    /// `WhittedRayTracer` bakes direct lighting and `PathTracer` bakes global
+    /// illumination. Since each texel is only seen from one direction, the
+    /// result is only exact for diffuse materials.
+    ///
+    /// Texels that aren't covered by the UV layout are transparent.
+    ///
+    /// * mesh - The mesh whose lighting is baked. It must be part of `scene`.
+    /// * scene - The scene that is ray traced.
+    /// * ray_tracer - Computes the radiance leaving the surface at each texel.
+    /// * sampler - Generates the samples that the ray tracer uses. Its number
+    ///   of samples per pixel is the number of rays traced per texel.
+    /// * max_depth - The maximum recursion depth of each ray.
+    pub fn bake_lighting<Scene, Sampler: IncrementalSampler>(
+        &self,
+        mesh: &Mesh,
+        scene: &Scene,
+        ray_tracer: &dyn RayTracer<Scene, Sampler>,
+        sampler: &Sampler,
+        max_depth: usize,
+    ) -> Option<Film> {
+        let mut film = Film::new(self.resolution);
+        let filter = BoxFilter::new(0.5, 0.5);
+        let bounds = Bounds2::new(
+            Point2::new(0, 0),
+            Point2::new(self.resolution.x as i32, self.resolution.y as i32),
+        );
+        let mut film_tile = film.tile(&bounds, filter.half_width(), filter.half_height())?;
+        let mut sampler = sampler.clone_with_seed(0);
+
+        self.for_each_texel(mesh, |texel, _frame| {
+            let origin = texel.point + texel.normal * self.cage_distance;
+            let ray = Ray::new(
+                origin,
+                Vector3::zero() - texel.normal,
+                2.0 * self.cage_distance,
+            );
+            // Each texel's samples are recorded at its center so that they
+            // don't bleed into neighboring texels, which may belong to
+            // another part of the surface.
+            let texel_center = Point2::new(texel.x as f32 + 0.5, texel.y as f32 + 0.5);
+            let pixel = Point2::new(texel.x as i32, texel.y as i32);
+            sampler.start_pixel(pixel);
+            loop {
+                let radiance =
+                    ray_tracer.incoming_radiance(&ray, scene, &mut sampler, 0, max_depth);
+                film_tile.add_sample(&texel_center, &radiance, 1.0, &filter);
+                sampler.record_radiance(&radiance);
+                if !sampler.start_next_sample() {
+                    break;
+                }
+            }
+        })?;
+        ray_tracer.finish_tile();
+
+        film.merge_tile(&film_tile);
+        Some(film)
+    }
+
+    /// Call `f` with each texel whose center is covered by the mesh's UV
+    /// layout, along with the tangent space of the triangle that covers it.
+    /// Return `None` if the mesh has no UV coordinates.
+    fn for_each_texel<F>(&self, mesh: &Mesh, mut f: F) -> Option<()>
+    where
+        F: FnMut(&SurfaceTexel, &TangentFrame),
+    {
+        let uvs = mesh.uvs.as_ref()?;
+        for (triangle_index, (i0, i1, i2)) in mesh.triangle_vertex_indices.iter().enumerate() {
+            let triangle = mesh.triangle_at(triangle_index);
+            let (p0, p1, p2) = triangle.positions();
+            let (uv0, uv1, uv2) = (uvs[*i0], uvs[*i1], uvs[*i2]);
+            let frame = match TangentFrame::new(mesh, [p0, p1, p2], [uv0, uv1, uv2]) {
+                Some(frame) => frame,
+                None => continue,
+            };
+            let vertex_normals = [*i0, *i1, *i2].map(|i| mesh.normals.get(i).cloned());
+
+            for (x, y, b) in self.covered_texels([uv0, uv1, uv2]) {
+                let point =
+                    Point3::from_vec(b[0] * p0.to_vec() + b[1] * p1.to_vec() + b[2] * p2.to_vec());
+                let normal = interpolate_normal(&vertex_normals, b).unwrap_or(frame.normal);
+                f(
+                    &SurfaceTexel {
+                        x,
+                        y,
+                        point,
+                        normal,
+                    },
+                    &frame,
+                );
+            }
+        }
+        Some(())
+    }
+
+    /// Return the texels whose centers lie in the triangle with the given UV
+    /// coordinates, along with the barycentric coordinates of each texel
+    /// center. The top row of texels is at v = 1.
+    fn covered_texels(&self, uvs: [Point2<f32>; 3]) -> Vec<(u32, u32, [f32; 3])> {
+        let to_texel = |uv: Point2<f32>| {
+            Point2::new(
+                uv.x * self.resolution.x as f32,
+                (1.0 - uv.y) * self.resolution.y as f32,
+            )
+        };
+        let [t0, t1, t2] = uvs.map(to_texel);
+        let area = edge_function(t0, t1, t2);
+        if area == 0.0 {
+            return vec![];
+        }
+
+        let min_x = t0.x.min(t1.x).min(t2.x).floor().max(0.0) as u32;
+        let min_y = t0.y.min(t1.y).min(t2.y).floor().max(0.0) as u32;
+        let max_x = (t0.x.max(t1.x).max(t2.x).ceil() as u32).min(self.resolution.x as u32);
+        let max_y = (t0.y.max(t1.y).max(t2.y).ceil() as u32).min(self.resolution.y as u32);
+
+        let mut texels = vec![];
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let center = Point2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let b = [
+                    edge_function(t1, t2, center) / area,
+                    edge_function(t2, t0, center) / area,
+                    edge_function(t0, t1, center) / area,
+                ];
+                if b.iter().all(|b| *b >= 0.0) {
+                    texels.push((x, y, b));
+                }
+            }
+        }
+        texels
+    }
+
+    /// Cast a ray inward from the cage above the low-poly point to find the
+    /// high-poly surface. Return the high-poly normal, oriented to agree with
+    /// the low-poly normal, and the hit.
+    fn cast_to_high_poly(
+        &self,
+        high_poly: &PrimitiveAggregate,
+        point: Point3<f32>,
+        normal: Vector3<f32>,
+    ) -> Option<(Vector3<f32>, crate::interaction::SurfaceInteraction)> {
+        let origin = point + normal * self.cage_distance;
+        // Subtract from zero rather than negating so that zero components
+        // stay positive. The BVH traversal misses rays whose direction has
+        // negative zero components.
+        let ray = Ray::new(origin, Vector3::zero() - normal, 2.0 * self.cage_distance);
+        let (_t, _primitive, hit) = high_poly.ray_intersection(&ray)?;
+        let hit_normal = hit.original_geometry.normal.normalize();
+        if hit_normal.dot(normal) < 0.0 {
+            Some((-hit_normal, hit))
+        } else {
+            Some((hit_normal, hit))
+        }
+    }
+
+    /// Return the fraction of occlusion rays from the high-poly hit that escape
+    /// without hitting the high-poly surface within `ao_distance`.
+    fn ambient_occlusion(
+        &self,
+        high_poly: &PrimitiveAggregate,
+        hit: &crate::interaction::SurfaceInteraction,
+        normal: Vector3<f32>,
+        samples: &[Point2<f32>],
+    ) -> f32 {
+        let tangent = if normal.x.abs() > 0.9 {
+            Vector3::new(0.0, 1.0, 0.0)
+        } else {
+            Vector3::new(1.0, 0.0, 0.0)
+        }
+        .cross(normal)
+        .normalize();
+        let bitangent = normal.cross(tangent);
+
+        let unoccluded = samples
+            .iter()
+            .filter(|sample| {
+                let local = sampling::cosine_sample_hemisphere(sample);
+                let direction = tangent * local.x + bitangent * local.y + normal * local.z;
+                let mut ray = hit.spawn_ray(&direction);
+                ray.t_max = self.ao_distance;
+                high_poly.ray_intersection(&ray).is_none()
+            })
+            .count();
+        unoccluded as f32 / samples.len() as f32
+    }
+}
+
+/// A texel in a mesh's UV layout and the point on the mesh's surface that
+/// maps to the texel's center.
+struct SurfaceTexel {
+    x: u32,
+    y: u32,
+    point: Point3<f32>,
+
+    /// The mesh's interpolated normal at the point.
+    normal: Vector3<f32>,
+}
+
+/// The tangent space of a low-poly triangle.
+struct TangentFrame {
+    /// The direction of increasing u.
+    tangent: Vector3<f32>,
+
+    /// The direction of increasing v.
+    bitangent: Vector3<f32>,
+
+    /// The triangle's face normal.
+    normal: Vector3<f32>,
+}
+
+impl TangentFrame {
+    /// Return the tangent space of the triangle, or `None` if the triangle or
+    /// its UV coordinates are degenerate.
+    fn new(mesh: &Mesh, positions: [Point3<f32>; 3], uvs: [Point2<f32>; 3]) -> Option<Self> {
+        let [p0, p1, p2] = positions;
+        let [uv0, uv1, uv2] = uvs;
+        let (dp02, dp12) = (p0 - p2, p1 - p2);
+        let (duv02, duv12) = (uv0 - uv2, uv1 - uv2);
+        let determinant = duv02.x * duv12.y - duv02.y * duv12.x;
+        let face_normal = dp02.cross(dp12);
+        if determinant.abs() < 1e-12 || face_normal.magnitude2() == 0.0 {
+            return None;
+        }
+
+        let inv_det = 1.0 / determinant;
+        let dpdu = (dp02 * duv12.y - dp12 * duv02.y) * inv_det;
+        let dpdv = (dp12 * duv02.x - dp02 * duv12.x) * inv_det;
+        let normal = if mesh.reverse_orientation || mesh.transformation_swaps_handedness {
+            -face_normal.normalize()
+        } else {
+            face_normal.normalize()
+        };
+        Some(Self {
+            tangent: dpdu.normalize(),
+            bitangent: dpdv.normalize(),
+            normal,
+        })
+    }
+
+    /// Express the world-space direction in tangent space, using the
+    /// interpolated low-poly normal as the z axis.
+    fn to_tangent_space(&self, direction: Vector3<f32>, normal: Vector3<f32>) -> Vector3<f32> {
+        // Gram-Schmidt orthogonalize the tangent against the interpolated
+        // normal, and keep the bitangent's handedness from the UV layout.
+        let tangent = (self.tangent - normal * normal.dot(self.tangent)).normalize();
+        let bitangent = normal.cross(tangent);
+        let bitangent = if bitangent.dot(self.bitangent) < 0.0 {
+            -bitangent
+        } else {
+            bitangent
+        };
+        Vector3::new(
+            direction.dot(tangent),
+            direction.dot(bitangent),
+            direction.dot(normal),
+        )
+        .normalize()
+    }
+}
+
+/// Interpolate the vertex normals at the barycentric coordinates. Return
+/// `None` if the mesh doesn't have usable vertex normals.
+fn interpolate_normal(normals: &[Option<Vector3<f32>>; 3], b: [f32; 3]) -> Option<Vector3<f32>> {
+    let normal = normals[0]? * b[0] + normals[1]? * b[1] + normals[2]? * b[2];
+    if normal.magnitude2() > 0.0 {
+        Some(normal.normalize())
+    } else {
+        None
+    }
+}
+
+/// Map each component of the unit vector from [-1, 1] to [0, 255].
+fn encode_normal(n: Vector3<f32>) -> Rgba<u8> {
+    let encode = |c: f32| ((c * 0.5 + 0.5).clamp(0.0, 1.0) * 255.0).round() as u8;
+    Rgba([encode(n.x), encode(n.y), encode(n.z), 255])
+}
+
+fn edge_function(a: Point2<f32>, b: Point2<f32>, p: Point2<f32>) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+#[cfg(test)]
+mod baker_tests {
+    use super::{Baker, FLAT_NORMAL};
+    use crate::{
+        color::RgbaSpectrum, integrator::ClayRayTracer, material::MatteMaterial,
+        primitive::PrimitiveAggregate, sampler::ConstantSampler, scene::Scene,
+    };
+    use cgmath::{Point2, Point3, Vector2, Vector3};
+    use image::{Luma, Rgba};
+    use mesh::{Mesh, MeshBuilder};
+
+    /// Return a unit square in the z = 0 plane, facing negative z, with UV
+    /// coordinates that match its x and y coordinates.
+    fn low_poly_square() -> Mesh {
+        let positions = vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        ];
+        let uvs = positions.iter().map(|p| Point2::new(p.x, p.y)).collect();
+        let normals = vec![Vector3::new(0.0, 0.0, -1.0); 4];
+        MeshBuilder::new(positions, normals, vec![(0, 1, 2), (0, 2, 3)])
+            .uvs(uvs)
+            .build()
+    }
+
+    /// Return a square that is slightly in front of the low-poly square and
+    /// tilted about the y axis.
+    fn tilted_square() -> Mesh {
+        let positions = vec![
+            Point3::new(-1.0, -1.0, -0.05),
+            Point3::new(2.0, -1.0, 0.05),
+            Point3::new(2.0, 2.0, 0.05),
+            Point3::new(-1.0, 2.0, -0.05),
+        ];
+        let normals = vec![Vector3::new(0.0, 0.0, -1.0); 4];
+        MeshBuilder::new(positions, normals, vec![(0, 1, 2), (0, 2, 3)]).build()
+    }
+
+    #[test]
+    fn identical_surfaces_bake_flat_normals() {
+        let baker = Baker::new(Vector2::new(8, 8), 0.1, 4, 1.0);
+        let maps = baker.bake(&low_poly_square(), &low_poly_square()).unwrap();
+        let Rgba([r, g, b, a]) = *maps.normal_map.get_pixel(3, 4);
+        let Rgba([fr, fg, fb, _]) = FLAT_NORMAL;
+        assert!((r as i32 - fr as i32).abs() <= 1);
+        assert!((g as i32 - fg as i32).abs() <= 1);
+        assert!((b as i32 - fb as i32).abs() <= 1);
+        assert_eq!(a, 255);
+        assert_eq!(*maps.ao_map.get_pixel(3, 4), Luma([255]));
+    }
+
+    #[test]
+    fn tilted_surface_bakes_tilted_normals() {
+        let baker = Baker::new(Vector2::new(8, 8), 0.2, 4, 1.0);
+        let maps = baker.bake(&low_poly_square(), &tilted_square()).unwrap();
+        let Rgba([r, _, b, _]) = *maps.normal_map.get_pixel(3, 4);
+        assert_ne!(r, 128);
+        assert!(b > 200);
+    }
+
+    #[test]
+    fn bakes_lighting_into_uv_layout() {
+        let mesh = low_poly_square();
+        let material = MatteMaterial::new(RgbaSpectrum::constant(1.0), 0.0);
+        let scene = Scene::new(PrimitiveAggregate::from_mesh(&mesh, &material), vec![]);
+        let baker = Baker::new(Vector2::new(8, 8), 0.1, 4, 1.0);
+        let film = baker
+            .bake_lighting(
+                &mesh,
+                &scene,
+                &ClayRayTracer::default(),
+                &ConstantSampler {},
+                1,
+            )
+            .unwrap();
+
+        // The clay headlight shines straight down at the unoccluded square.
+        let texel = film.to_rgba()[4 * 8 + 3];
+        assert!((texel.r() - 0.6).abs() < 1e-3);
+        assert!((texel.a() - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn low_poly_mesh_needs_uvs() {
+        let baker = Baker::new(Vector2::new(8, 8), 0.2, 4, 1.0);
+        assert!(baker.bake(&tilted_square(), &tilted_square()).is_none());
+    }
+}
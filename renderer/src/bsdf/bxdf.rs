@@ -1,6 +1,8 @@
-use crate::color::RgbaSpectrum;
+use super::geometry;
+use crate::{color::RgbaSpectrum, sampling};
 use bitflags::bitflags;
 use cgmath::{Point2, Vector3};
+use std::f32::consts::PI;
 
 bitflags! {
     /// A bit flag representing the different types of bidirectional relectance
@@ -54,42 +56,117 @@ pub trait Bxdf {
     ///
     /// * The incident light direction that would scatter light in the viewing
     ///   direction.
-    /// * PDF ?
+    /// * The probability density, with respect to solid angle, of sampling the
+    ///   returned incident light direction.
     /// * The spetrum of light that is scattered in the viewing direction due to
     ///   light arriving at the surface from the returned incident light
     ///   direction.
     ///
     /// This method is useful for evaluating BxDFs that scatter light in only a
-    /// single direction, such as perfectly specular BxDFs.
+    /// single direction, such as perfectly specular BxDFs. It is also used by
+    /// Monte Carlo integrators to choose the direction in which a path should
+    /// continue.
+    ///
+    /// The default implementation samples incident directions with a
+    /// cosine-weighted distribution over the hemisphere that `wo` is in.
     ///
     /// * wo - The view direction. A normalized vector in the shading coordinate
     ///   system that points from the point on the surface to the point from
     ///   which the surface is being viewed.
+    /// * sample - A 2D sample in [0, 1) x [0, 1) that is used to choose the
+    ///   incident light direction.
     fn sample_f(
         &self,
         wo: &Vector3<f32>,
         sample: Point2<f32>,
-        sampled_type: BxdfType,
+        _sampled_type: BxdfType,
     ) -> (Vector3<f32>, f32, RgbaSpectrum) {
-        // TODO: There should actually be a default implementation.
-        todo!()
+        let mut wi = sampling::cosine_sample_hemisphere(&sample);
+        if wo.z < 0.0 {
+            wi.z *= -1.0;
+        }
+        (wi, self.pdf(wo, &wi), self.f(wo, &wi))
+    }
+
+    /// Return the probability density, with respect to solid angle, that
+    /// `sample_f` samples the incident light direction `wi` given the viewing
+    /// direction `wo`.
+    ///
+    /// BxDFs that override `sample_f` with a different sampling strategy must
+    /// also override this method so that the two agree. The default
+    /// implementation matches the default cosine-weighted `sample_f`.
+    ///
+    /// * wo - The view direction in the shading coordinate system.
+    /// * wi - The incident light direction in the shading coordinate system.
+    fn pdf(&self, wo: &Vector3<f32>, wi: &Vector3<f32>) -> f32 {
+        if geometry::same_hemisphere(wo, wi) {
+            sampling::cosine_hemisphere_pdf(geometry::abs_cos_theta(wi))
+        } else {
+            0.0
+        }
     }
 
     /// Evaluate the hemispherical-directional reflectance function. This
     /// returns the total reflection in the direction `wo` due to constant
     /// illumination over the hemisphere.
+    ///
+    /// The default implementation computes a Monte Carlo estimate by sampling
+    /// incident directions with `sample_f`.
+    ///
+    /// * wo - The view direction in the shading coordinate system.
+    /// * samples - 2D samples in [0, 1) x [0, 1) that are used to sample
+    ///   incident light directions.
     fn rho_hd(&self, wo: &Vector3<f32>, samples: &[Point2<f32>]) -> RgbaSpectrum {
-        // TODO: There should actually be a default implementation when I get to
-        // Monte Carlo.
-        todo!()
+        let sum = samples.iter().fold(RgbaSpectrum::black(), |sum, sample| {
+            let (wi, pdf, f) = self.sample_f(wo, *sample, self.bxdf_type());
+            if pdf > 0.0 {
+                sum + f * (geometry::abs_cos_theta(&wi) / pdf)
+            } else {
+                sum
+            }
+        });
+        sum / samples.len() as f32
     }
 
     /// Evaluate the hemispherical-hemispherical reflectance function. This
     /// returns the fraction of incident light reflected by a surface when
     /// incident light is the same from all directions.
+    ///
+    /// The default implementation computes a Monte Carlo estimate by uniformly
+    /// sampling outgoing directions over the hemisphere and sampling incident
+    /// directions with `sample_f`.
+    ///
+    /// * samples1 - 2D samples that are used to sample outgoing directions.
+    /// * samples2 - 2D samples that are used to sample incident directions.
+    ///   This should be the same length as `samples1`.
     fn rho_hh(&self, samples1: &[Point2<f32>], samples2: &[Point2<f32>]) -> RgbaSpectrum {
-        // TODO: There should actually be a default implementation when I get to
-        // Monte Carlo.
-        todo!()
+        let pdf_o = sampling::uniform_hemisphere_pdf();
+        let sum =
+            samples1
+                .iter()
+                .zip(samples2)
+                .fold(RgbaSpectrum::black(), |sum, (sample1, sample2)| {
+                    let wo = sampling::uniform_sample_hemisphere(sample1);
+                    let (wi, pdf_i, f) = self.sample_f(&wo, *sample2, self.bxdf_type());
+                    if pdf_i > 0.0 {
+                        let cos_product =
+                            geometry::abs_cos_theta(&wi) * geometry::abs_cos_theta(&wo);
+                        sum + f * (cos_product / (pdf_o * pdf_i))
+                    } else {
+                        sum
+                    }
+                });
+        sum / (PI * samples1.len() as f32)
+    }
+
+    /// Return a copy of the BxDF with any lobe that is smoother than
+    /// `min_alpha` roughened to it, or `None` if the BxDF can't be roughened
+    /// or is already at least that rough. Path tracers use this to blur
+    /// near-specular lobes deep in a path, where they would otherwise produce
+    /// noisy caustics.
+    ///
+    /// * min_alpha - The smallest microfacet roughness that the BxDF may have.
+    fn regularized(&self, _min_alpha: f32) -> Option<Box<dyn Bxdf>> {
+        None
     }
 }
@@ -1,4 +1,4 @@
-use super::{geometry, Bxdf, BxdfType};
+use super::{geometry, Bxdf, BxdfType, MicrofacetReflection, TrowbridgeReitzDistribution};
 use crate::{bsdf::geometry::abs_cos_theta, color::RgbaSpectrum, TransportMode};
 use cgmath::{vec3, Point2, Vector3};
 
@@ -42,6 +42,12 @@ impl Bxdf for FresnelSpecularReflection {
         RgbaSpectrum::black()
     }
 
+    fn pdf(&self, _wo: &Vector3<f32>, _wi: &Vector3<f32>) -> f32 {
+        // The probability of sampling the single scattering direction by
+        // choosing an arbitrary `wi` is zero.
+        0.0
+    }
+
     fn sample_f(
         &self,
         wo: &Vector3<f32>,
@@ -54,6 +60,14 @@ impl Bxdf for FresnelSpecularReflection {
             self.fresnel.evaluate(geometry::cos_theta(&wi)) * self.r / geometry::abs_cos_theta(&wi);
         (wi, pdf, light)
     }
+
+    fn regularized(&self, min_alpha: f32) -> Option<Box<dyn Bxdf>> {
+        Some(Box::new(MicrofacetReflection::new(
+            self.r,
+            TrowbridgeReitzDistribution::isotropic(min_alpha),
+            self.fresnel,
+        )))
+    }
 }
 
 /// A BTDF that models transmission through a perfectly specular surface,
@@ -67,8 +81,8 @@ pub struct FresnelSpecularTransmission {
     /// that the normal points).
     eta_below: f32,
 
-    /// Transmission scale factor.
-    t: f32,
+    /// A spectrum that scales the transmitted color.
+    t: RgbaSpectrum,
 
     transport_mode: TransportMode,
 
@@ -80,7 +94,7 @@ impl FresnelSpecularTransmission {
     pub fn dielectric(
         eta_above: f32,
         eta_below: f32,
-        t: f32,
+        t: RgbaSpectrum,
         transport_mode: TransportMode,
     ) -> Self {
         Self {
@@ -105,6 +119,10 @@ impl Bxdf for FresnelSpecularTransmission {
         RgbaSpectrum::black()
     }
 
+    fn pdf(&self, _wo: &Vector3<f32>, _wi: &Vector3<f32>) -> f32 {
+        0.0
+    }
+
     fn sample_f(
         &self,
         wo: &Vector3<f32>,
@@ -149,8 +167,82 @@ impl Bxdf for FresnelSpecularTransmission {
     }
 }
 
+/// A BSDF that models a perfectly specular dielectric slab that's so thin
+/// that light passes straight through it without being bent, like a display
+/// window or a plastic film. Light that enters the slab bounces between its
+/// two surfaces, and each bounce either reflects light back out or transmits
+/// it through the slab.
+pub struct FresnelThinDielectric {
+    /// A spectrum that scales the reflected color.
+    r: RgbaSpectrum,
+
+    /// A spectrum that scales the transmitted color.
+    t: RgbaSpectrum,
+
+    /// The index of refraction inside the slab. The slab is surrounded by a
+    /// medium with an index of refraction of 1.
+    eta: f32,
+}
+
+impl FresnelThinDielectric {
+    pub fn new(r: RgbaSpectrum, t: RgbaSpectrum, eta: f32) -> Self {
+        Self { r, t, eta }
+    }
+
+    /// Return the fractions of light that are reflected by and transmitted
+    /// through the slab, accounting for every bounce inside it.
+    ///
+    /// * cos_theta - The cosine of the angle between the light's direction and
+    ///   the surface normal.
+    fn reflectance_transmittance(&self, cos_theta: f32) -> (f32, f32) {
+        let r = fresnel_dielectric(cos_theta.abs(), 1.0, self.eta);
+        let t = 1.0 - r;
+        let r = if r < 1.0 {
+            r + t * t * r / (1.0 - r * r)
+        } else {
+            r
+        };
+        (r, 1.0 - r)
+    }
+}
+
+impl Bxdf for FresnelThinDielectric {
+    fn bxdf_type(&self) -> BxdfType {
+        BxdfType::REFLECTION | BxdfType::TRANSMISSION | BxdfType::SPECULAR
+    }
+
+    fn f(&self, _wo: &Vector3<f32>, _wi: &Vector3<f32>) -> RgbaSpectrum {
+        RgbaSpectrum::black()
+    }
+
+    fn pdf(&self, _wo: &Vector3<f32>, _wi: &Vector3<f32>) -> f32 {
+        0.0
+    }
+
+    fn sample_f(
+        &self,
+        wo: &Vector3<f32>,
+        sample: Point2<f32>,
+        _sampled_type: BxdfType,
+    ) -> (Vector3<f32>, f32, RgbaSpectrum) {
+        let (r, t) = self.reflectance_transmittance(geometry::cos_theta(wo));
+        // Choose between reflection and transmission in proportion to how
+        // much light each of them scatters.
+        if sample.x < r / (r + t) {
+            let wi = geometry::reflect(wo);
+            (wi, r / (r + t), self.r * r / abs_cos_theta(&wi))
+        } else {
+            // Light leaves the far side of the slab in the direction that it
+            // entered the near side.
+            let wi = -*wo;
+            (wi, t / (r + t), self.t * t / abs_cos_theta(&wi))
+        }
+    }
+}
+
 /// A description of the Fresnel properties at the boundry between two media.
-enum Fresnel {
+#[derive(Clone, Copy)]
+pub(super) enum Fresnel {
     /// A description of the boundry between two dielectric media.
     Dielectric {
         /// The index of refraction for the incident media.
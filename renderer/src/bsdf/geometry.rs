@@ -98,6 +98,12 @@ pub fn sin2_phi(w: &Vector3<f32>) -> f32 {
     sin_phi * sin_phi
 }
 
+/// Return true if the unit vectors `w1` and `w2` are in the same hemisphere,
+/// where the hemispheres are divided by the surface.
+pub fn same_hemisphere(w1: &Vector3<f32>, w2: &Vector3<f32>) -> bool {
+    w1.z * w2.z > 0.0
+}
+
 /// Reflect the vector `w`, which starts at the origin of the shading coordinate
 /// system, across the surface normal.
 pub fn reflect(w: &Vector3<f32>) -> Vector3<f32> {
@@ -1,5 +1,5 @@
-use super::{Bxdf, BxdfType};
-use crate::color::RgbaSpectrum;
+use super::{geometry, Bxdf, BxdfType};
+use crate::{color::RgbaSpectrum, sampling};
 use cgmath::{Point2, Vector3};
 use std::f32::consts::FRAC_1_PI;
 
@@ -28,15 +28,6 @@ impl Bxdf for LambertianDiffuseReflection {
         self.r * FRAC_1_PI
     }
 
-    fn sample_f(
-        &self,
-        wo: &Vector3<f32>,
-        sample: Point2<f32>,
-        sampled_type: BxdfType,
-    ) -> (Vector3<f32>, f32, RgbaSpectrum) {
-        todo!()
-    }
-
     fn rho_hd(&self, _wo: &Vector3<f32>, _samples: &[Point2<f32>]) -> RgbaSpectrum {
         self.r
     }
@@ -75,9 +66,23 @@ impl Bxdf for LambertianDiffuseTransmission {
         &self,
         wo: &Vector3<f32>,
         sample: Point2<f32>,
-        sampled_type: BxdfType,
+        _sampled_type: BxdfType,
     ) -> (Vector3<f32>, f32, RgbaSpectrum) {
-        todo!()
+        // Light is transmitted, so sample a direction in the hemisphere
+        // opposite the one that `wo` is in.
+        let mut wi = sampling::cosine_sample_hemisphere(&sample);
+        if wo.z > 0.0 {
+            wi.z *= -1.0;
+        }
+        (wi, self.pdf(wo, &wi), self.f(wo, &wi))
+    }
+
+    fn pdf(&self, wo: &Vector3<f32>, wi: &Vector3<f32>) -> f32 {
+        if geometry::same_hemisphere(wo, wi) {
+            0.0
+        } else {
+            sampling::cosine_hemisphere_pdf(geometry::abs_cos_theta(wi))
+        }
     }
 
     fn rho_hd(&self, _wo: &Vector3<f32>, _samples: &[Point2<f32>]) -> RgbaSpectrum {
@@ -88,3 +93,39 @@ impl Bxdf for LambertianDiffuseTransmission {
         self.t
     }
 }
+
+#[cfg(test)]
+mod lambertian_tests {
+    use super::{LambertianDiffuseReflection, LambertianDiffuseTransmission};
+    use crate::{
+        bsdf::{Bxdf, BxdfType},
+        color::RgbaSpectrum,
+        test::ApproxEq,
+    };
+    use cgmath::{InnerSpace, Point2, Vector3};
+    use std::f32::consts::FRAC_1_PI;
+
+    #[test]
+    fn reflection_samples_wo_hemisphere() {
+        let bxdf = LambertianDiffuseReflection::new(RgbaSpectrum::constant(0.5));
+        for wo in &[Vector3::new(0.0, 0.6, 0.8), Vector3::new(0.0, 0.6, -0.8)] {
+            let (wi, pdf, f) = bxdf.sample_f(wo, Point2::new(0.3, 0.7), BxdfType::ALL);
+            assert!(wi.z * wo.z > 0.0);
+            wi.magnitude().assert_approx_eq(&1.0);
+            pdf.assert_approx_eq(&(wi.z.abs() * FRAC_1_PI));
+            f.assert_approx_eq(&(RgbaSpectrum::constant(0.5) * FRAC_1_PI));
+        }
+    }
+
+    #[test]
+    fn transmission_samples_opposite_hemisphere() {
+        let bxdf = LambertianDiffuseTransmission::new(RgbaSpectrum::constant(0.5));
+        for wo in &[Vector3::new(0.0, 0.6, 0.8), Vector3::new(0.0, 0.6, -0.8)] {
+            let (wi, pdf, f) = bxdf.sample_f(wo, Point2::new(0.3, 0.7), BxdfType::ALL);
+            assert!(wi.z * wo.z < 0.0);
+            wi.magnitude().assert_approx_eq(&1.0);
+            pdf.assert_approx_eq(&(wi.z.abs() * FRAC_1_PI));
+            f.assert_approx_eq(&(RgbaSpectrum::constant(0.5) * FRAC_1_PI));
+        }
+    }
+}
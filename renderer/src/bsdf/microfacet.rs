@@ -0,0 +1,326 @@
+use super::{fresnel::Fresnel, geometry, Bxdf, BxdfType};
+use crate::color::RgbaSpectrum;
+use cgmath::{vec3, InnerSpace, Point2, Vector3, Zero};
+use std::f32::consts::PI;
+
+/// The Trowbridge-Reitz (GGX) distribution of microfacet orientations. It
+/// describes a rough surface as a collection of tiny perfectly specular facets,
+/// and has longer tails than the Beckmann distribution, which gives highlights
+/// the soft falloff that real surfaces have.
+#[derive(Debug, Clone, Copy)]
+pub struct TrowbridgeReitzDistribution {
+    /// The roughness along the shading coordinate system's x axis.
+    alpha_x: f32,
+
+    /// The roughness along the shading coordinate system's y axis.
+    alpha_y: f32,
+}
+
+impl TrowbridgeReitzDistribution {
+    /// * alpha_x - The roughness along the x axis of the shading coordinate
+    ///   system, which is the surface's primary tangent.
+    /// * alpha_y - The roughness along the y axis of the shading coordinate
+    ///   system.
+    pub fn new(alpha_x: f32, alpha_y: f32) -> Self {
+        // Perfectly smooth facets would make the distribution a delta
+        // function, which should be modeled with a specular BxDF instead.
+        Self {
+            alpha_x: alpha_x.max(1e-4),
+            alpha_y: alpha_y.max(1e-4),
+        }
+    }
+
+    /// Create a distribution that has the same roughness in every direction
+    /// along the surface.
+    pub fn isotropic(alpha: f32) -> Self {
+        Self::new(alpha, alpha)
+    }
+
+    /// Return a copy of the distribution that is at least as rough as
+    /// `min_alpha` in every direction, or `None` if it already is.
+    pub fn regularized(&self, min_alpha: f32) -> Option<Self> {
+        if self.alpha_x >= min_alpha && self.alpha_y >= min_alpha {
+            return None;
+        }
+        Some(Self::new(
+            self.alpha_x.max(min_alpha),
+            self.alpha_y.max(min_alpha),
+        ))
+    }
+
+    /// Return the differential area of microfacets with the surface normal
+    /// `wh`.
+    pub fn d(&self, wh: &Vector3<f32>) -> f32 {
+        let tan2_theta = geometry::tan2_theta(wh);
+        if !tan2_theta.is_finite() {
+            return 0.0;
+        }
+        let cos4_theta = geometry::cos2_theta(wh) * geometry::cos2_theta(wh);
+        let e = (geometry::cos2_phi(wh) / (self.alpha_x * self.alpha_x)
+            + geometry::sin2_phi(wh) / (self.alpha_y * self.alpha_y))
+            * tan2_theta;
+        1.0 / (PI * self.alpha_x * self.alpha_y * cos4_theta * (1.0 + e) * (1.0 + e))
+    }
+
+    /// Return the ratio of the microfacet area that is masked from the
+    /// direction `w` to the area that is visible.
+    fn lambda(&self, w: &Vector3<f32>) -> f32 {
+        let abs_tan_theta = geometry::tan_theta(w).abs();
+        if !abs_tan_theta.is_finite() {
+            return 0.0;
+        }
+        let alpha = (geometry::cos2_phi(w) * self.alpha_x * self.alpha_x
+            + geometry::sin2_phi(w) * self.alpha_y * self.alpha_y)
+            .sqrt();
+        let alpha2_tan2_theta = (alpha * abs_tan_theta) * (alpha * abs_tan_theta);
+        (-1.0 + (1.0 + alpha2_tan2_theta).sqrt()) / 2.0
+    }
+
+    /// Return the fraction of microfacets that are visible from both `wo` and
+    /// `wi`.
+    pub fn g(&self, wo: &Vector3<f32>, wi: &Vector3<f32>) -> f32 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Sample a microfacet normal in the same hemisphere as `wo` from the
+    /// distribution of normals weighted by their projected area.
+    ///
+    /// * sample - A 2D sample in [0, 1) x [0, 1).
+    pub fn sample_wh(&self, wo: &Vector3<f32>, sample: &Point2<f32>) -> Vector3<f32> {
+        let (tan2_theta, phi) = if self.alpha_x == self.alpha_y {
+            let phi = 2.0 * PI * sample.y;
+            let tan2_theta = self.alpha_x * self.alpha_x * sample.x / (1.0 - sample.x);
+            (tan2_theta, phi)
+        } else {
+            let mut phi =
+                (self.alpha_y / self.alpha_x * (2.0 * PI * sample.y + 0.5 * PI).tan()).atan();
+            if sample.y > 0.5 {
+                phi += PI;
+            }
+            let (sin_phi, cos_phi) = phi.sin_cos();
+            let alpha2 = 1.0
+                / (cos_phi * cos_phi / (self.alpha_x * self.alpha_x)
+                    + sin_phi * sin_phi / (self.alpha_y * self.alpha_y));
+            (alpha2 * sample.x / (1.0 - sample.x), phi)
+        };
+        let cos_theta = 1.0 / (1.0 + tan2_theta).sqrt();
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let wh = vec3(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+        if geometry::same_hemisphere(wo, &wh) {
+            wh
+        } else {
+            -wh
+        }
+    }
+
+    /// Return the probability density, with respect to solid angle, that
+    /// `sample_wh` samples the microfacet normal `wh`.
+    pub fn pdf(&self, wh: &Vector3<f32>) -> f32 {
+        self.d(wh) * geometry::abs_cos_theta(wh)
+    }
+}
+
+/// A BRDF that models glossy reflection off a rough surface with the
+/// Torrance-Sparrow model. Each microfacet reflects light specularly, so the
+/// amount of light that's reflected depends on how many microfacets are
+/// oriented halfway between the viewing and incident directions, how many of
+/// those aren't shadowed or masked by other microfacets, and the Fresnel
+/// reflectance at the microfacets.
+pub struct MicrofacetReflection {
+    /// A spectrum that scales the reflected color.
+    r: RgbaSpectrum,
+
+    distribution: TrowbridgeReitzDistribution,
+
+    /// The Fresnel properties at the surface.
+    fresnel: Fresnel,
+}
+
+impl MicrofacetReflection {
+    pub(super) fn new(
+        r: RgbaSpectrum,
+        distribution: TrowbridgeReitzDistribution,
+        fresnel: Fresnel,
+    ) -> Self {
+        Self {
+            r,
+            distribution,
+            fresnel,
+        }
+    }
+
+    /// Create a BRDF for the boundary between two dielectric media, such as
+    /// the clear coat of a plastic.
+    ///
+    /// * eta_i - The index of refraction for the incident media.
+    /// * eta_t - The index of refraction for the transmitted media.
+    pub fn dielectric(
+        r: RgbaSpectrum,
+        distribution: TrowbridgeReitzDistribution,
+        eta_i: f32,
+        eta_t: f32,
+    ) -> Self {
+        Self {
+            r,
+            distribution,
+            fresnel: Fresnel::Dielectric { eta_i, eta_t },
+        }
+    }
+
+    /// Create a BRDF for the boundary between a dielectric media and a
+    /// conductor, such as the surface of a metal.
+    ///
+    /// * eta_i - The index of refraction across a spectrum for the incident
+    ///   media.
+    /// * eta_t - The index of refraction across a spectrum for the conductor.
+    /// * k - The conductor's absorption coefficient.
+    pub fn conductor(
+        r: RgbaSpectrum,
+        distribution: TrowbridgeReitzDistribution,
+        eta_i: RgbaSpectrum,
+        eta_t: RgbaSpectrum,
+        k: RgbaSpectrum,
+    ) -> Self {
+        Self {
+            r,
+            distribution,
+            fresnel: Fresnel::Conductor { eta_i, eta_t, k },
+        }
+    }
+}
+
+impl Bxdf for MicrofacetReflection {
+    fn bxdf_type(&self) -> BxdfType {
+        BxdfType::REFLECTION | BxdfType::GLOSSY
+    }
+
+    fn f(&self, wo: &Vector3<f32>, wi: &Vector3<f32>) -> RgbaSpectrum {
+        let cos_theta_o = geometry::abs_cos_theta(wo);
+        let cos_theta_i = geometry::abs_cos_theta(wi);
+        let wh = wi + wo;
+        // Grazing directions and directions that are exactly opposite each
+        // other don't reflect any light.
+        if cos_theta_i == 0.0 || cos_theta_o == 0.0 || wh.is_zero() {
+            return RgbaSpectrum::black();
+        }
+        let wh = wh.normalize();
+        // The Fresnel reflectance is evaluated on the side of the microfacet
+        // that faces the surface's normal.
+        let wh_forward = if wh.z < 0.0 { -wh } else { wh };
+        let fresnel = self.fresnel.evaluate(wi.dot(wh_forward));
+        self.r * self.distribution.d(&wh) * self.distribution.g(wo, wi) * fresnel
+            / (4.0 * cos_theta_i * cos_theta_o)
+    }
+
+    fn sample_f(
+        &self,
+        wo: &Vector3<f32>,
+        sample: Point2<f32>,
+        _sampled_type: BxdfType,
+    ) -> (Vector3<f32>, f32, RgbaSpectrum) {
+        if wo.z == 0.0 {
+            return (Vector3::zero(), 0.0, RgbaSpectrum::black());
+        }
+        let wh = self.distribution.sample_wh(wo, &sample);
+        if wo.dot(wh) < 0.0 {
+            // Microfacets that face away from the viewer can't be seen.
+            return (Vector3::zero(), 0.0, RgbaSpectrum::black());
+        }
+        let wi = -*wo + 2.0 * wo.dot(wh) * wh;
+        if !geometry::same_hemisphere(wo, &wi) {
+            return (wi, 0.0, RgbaSpectrum::black());
+        }
+        let pdf = self.distribution.pdf(&wh) / (4.0 * wo.dot(wh));
+        (wi, pdf, self.f(wo, &wi))
+    }
+
+    fn pdf(&self, wo: &Vector3<f32>, wi: &Vector3<f32>) -> f32 {
+        let wh = wo + wi;
+        if !geometry::same_hemisphere(wo, wi) || wh.is_zero() {
+            return 0.0;
+        }
+        let wh = wh.normalize();
+        self.distribution.pdf(&wh) / (4.0 * wo.dot(wh).abs())
+    }
+
+    fn regularized(&self, min_alpha: f32) -> Option<Box<dyn Bxdf>> {
+        let distribution = self.distribution.regularized(min_alpha)?;
+        Some(Box::new(MicrofacetReflection::new(
+            self.r,
+            distribution,
+            self.fresnel,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod microfacet_tests {
+    use super::{MicrofacetReflection, TrowbridgeReitzDistribution};
+    use crate::{
+        bsdf::{Bxdf, BxdfType},
+        color::RgbaSpectrum,
+        material::Alloy,
+        sampling,
+        test::ApproxEq,
+    };
+    use cgmath::{InnerSpace, Point2, Vector3};
+
+    #[test]
+    fn sampled_pdf_and_value_agree_with_pdf_and_f() {
+        let bxdf = MicrofacetReflection::dielectric(
+            RgbaSpectrum::constant(1.0),
+            TrowbridgeReitzDistribution::new(0.3, 0.5),
+            1.0,
+            1.5,
+        );
+        let wo = Vector3::new(0.3, -0.2, 0.9).normalize();
+        for sample in sampling::grid_samples_2d(4) {
+            let (wi, pdf, f) = bxdf.sample_f(&wo, sample, BxdfType::ALL);
+            if pdf > 0.0 {
+                pdf.assert_approx_eq(&bxdf.pdf(&wo, &wi));
+                f.r().assert_approx_eq(&bxdf.f(&wo, &wi).r());
+            }
+        }
+    }
+
+    #[test]
+    fn normals_are_sampled_in_the_viewing_hemisphere() {
+        let distribution = TrowbridgeReitzDistribution::isotropic(0.4);
+        let wo = Vector3::new(0.0, 0.6, -0.8);
+        let wh = distribution.sample_wh(&wo, &Point2::new(0.3, 0.7));
+        assert!(wh.z < 0.0);
+        wh.magnitude().assert_approx_eq(&1.0);
+    }
+
+    #[test]
+    fn rough_dielectric_conserves_energy() {
+        let bxdf = MicrofacetReflection::dielectric(
+            RgbaSpectrum::constant(1.0),
+            TrowbridgeReitzDistribution::isotropic(0.5),
+            1.0,
+            1.5,
+        );
+        let samples1 = sampling::grid_samples_2d(32);
+        let mut samples2 = samples1.clone();
+        samples2.reverse();
+        let rho = bxdf.rho_hh(&samples1, &samples2);
+        assert!(rho.r() > 0.0);
+        assert!(rho.r() <= 1.0 + 0.01);
+    }
+
+    #[test]
+    fn copper_reflects_more_red_than_blue() {
+        let (eta, k) = Alloy::Copper.eta_k();
+        let bxdf = MicrofacetReflection::conductor(
+            RgbaSpectrum::constant(1.0),
+            TrowbridgeReitzDistribution::isotropic(0.2),
+            RgbaSpectrum::constant(1.0),
+            eta,
+            k,
+        );
+        let w = Vector3::new(0.0, 0.0, 1.0);
+        let f = bxdf.f(&w, &w);
+        assert!(f.r() > f.g());
+        assert!(f.g() > f.b());
+    }
+}
@@ -2,15 +2,18 @@ mod bxdf;
 mod fresnel;
 mod geometry;
 mod lambertian;
+mod microfacet;
 mod oren_nayar;
 mod scale;
 
 pub use bxdf::{Bxdf, BxdfType};
+pub use fresnel::{FresnelSpecularReflection, FresnelSpecularTransmission, FresnelThinDielectric};
 pub use lambertian::{LambertianDiffuseReflection, LambertianDiffuseTransmission};
+pub use microfacet::{MicrofacetReflection, TrowbridgeReitzDistribution};
 pub use oren_nayar::OrenNayarDiffuseReflection;
 
-use crate::{color::RgbaSpectrum, interaction::SurfaceInteraction};
-use cgmath::{vec3, InnerSpace, Point2, Vector3};
+use crate::{color::RgbaSpectrum, interaction::SurfaceInteraction, sampler::MAX_SAMPLE};
+use cgmath::{vec3, InnerSpace, Point2, Vector3, Zero};
 
 /// The bidirectional scattering distribution function (BSDF). Describes the way
 /// light scatters at a point on a surface. A BSDF is composed of multiple
@@ -60,16 +63,26 @@ impl Bsdf {
         interaction: &SurfaceInteraction,
         // relative_refraction: f32
     ) -> Self {
+        let normal = interaction.shading_geometry.normal;
+        // The shading coordinate system must be orthonormal, but the partial
+        // derivative of the surface's position isn't necessarily a unit vector
+        // or perpendicular to the shading normal.
+        let dpdu = interaction.shading_geometry.dpdu;
+        let tangent = dpdu - normal * normal.dot(dpdu);
+        let tangent = if tangent.magnitude2() > 0.0 {
+            tangent.normalize()
+        } else if normal.x.abs() > normal.y.abs() {
+            vec3(-normal.z, 0.0, normal.x).normalize()
+        } else {
+            vec3(0.0, normal.z, -normal.y).normalize()
+        };
         Self {
             // relative_refraction,
             bxdfs: vec![],
             original_normal: interaction.original_geometry.normal,
-            shading_normal: interaction.shading_geometry.normal,
-            shading_primary_tangent: interaction.shading_geometry.dpdu,
-            shading_secondary_tangent: interaction
-                .shading_geometry
-                .normal
-                .cross(interaction.shading_geometry.dpdu),
+            shading_normal: normal,
+            shading_primary_tangent: tangent,
+            shading_secondary_tangent: normal.cross(tangent),
         }
     }
 
@@ -78,6 +91,18 @@ impl Bsdf {
         self.bxdfs.push(bxdf)
     }
 
+    /// Roughen each BxDF whose lobe is smoother than `min_alpha` to that
+    /// microfacet roughness. Specular reflection becomes glossy reflection,
+    /// while specular transmission, which has no rough counterpart, is left
+    /// unchanged.
+    pub fn regularize(&mut self, min_alpha: f32) {
+        for bxdf in self.bxdfs.iter_mut() {
+            if let Some(regularized) = bxdf.regularized(min_alpha) {
+                *bxdf = regularized;
+            }
+        }
+    }
+
     /// Return the number of elements in the BSDF's collection of BxDFs that
     /// have the given BxDF type.
     pub fn count_with_type(&self, ty: BxdfType) -> usize {
@@ -127,25 +152,152 @@ impl Bsdf {
                 BxdfType::TRANSMISSION
             };
 
-        let type_to_eval = flags | reflect_or_transmit;
-
+        // Only evaluate BxDFs that match the given flags and that scatter light
+        // into the hemisphere that `wi` is in.
         self.bxdfs
             .iter()
-            .filter(|bxdf| bxdf.has_type(type_to_eval))
+            .filter(|bxdf| bxdf.has_type(flags) && bxdf.bxdf_type().intersects(reflect_or_transmit))
             .fold(RgbaSpectrum::black(), |light, bxdf| {
                 light + bxdf.f(&wo, &wi)
             })
     }
 
+    /// Given a viewing direction, `wo_world`, choose one of the BxDFs that
+    /// match the given flags and use it to sample an incident light direction.
+    /// This returns the following:
+    ///
+    /// * The sampled incident light direction in world space.
+    /// * The probability density of sampling the incident light direction. This
+    ///   accounts for the probability of choosing the BxDF that was sampled.
+    /// * The spectrum of light that is scattered in the viewing direction due to
+    ///   light arriving from the incident light direction.
+    /// * The type of the sampled BxDF.
+    ///
+    /// If the sampled BxDF isn't perfectly specular, the returned probability
+    /// density and spectrum include every matching BxDF, since any of them
+    /// could have sampled the incident light direction.
+    ///
+    /// If no BxDFs match the flags, the returned probability density is 0.
+    ///
+    /// * wo_world - The viewing direction in world space.
+    /// * sample - A 2D sample in [0, 1) x [0, 1). The first component is used
+    ///   to choose a BxDF, and both components are then used to sample an
+    ///   incident direction from that BxDF.
+    /// * flags - The types of BxDFs that may be sampled.
+    pub fn sample_f(
+        &self,
+        wo_world: &Vector3<f32>,
+        sample: &Point2<f32>,
+        flags: BxdfType,
+    ) -> (Vector3<f32>, f32, RgbaSpectrum, BxdfType) {
+        let matching_count = self.count_with_type(flags);
+        if matching_count == 0 {
+            return (
+                Vector3::zero(),
+                0.0,
+                RgbaSpectrum::black(),
+                BxdfType::empty(),
+            );
+        }
+
+        // Choose the BxDF to sample, and remap the first sample component back
+        // to [0, 1) so that it can be reused to sample the BxDF.
+        let scaled = sample.x * matching_count as f32;
+        let index = (scaled.floor() as usize).min(matching_count - 1);
+        let bxdf = self
+            .bxdfs
+            .iter()
+            .filter(|bxdf| bxdf.has_type(flags))
+            .nth(index)
+            .unwrap();
+        let remapped = Point2::new((scaled - index as f32).min(MAX_SAMPLE), sample.y);
+
+        let wo = self.transform_world_to_local(wo_world);
+        if wo.z == 0.0 {
+            return (
+                Vector3::zero(),
+                0.0,
+                RgbaSpectrum::black(),
+                bxdf.bxdf_type(),
+            );
+        }
+        let (wi, pdf, f) = bxdf.sample_f(&wo, remapped, bxdf.bxdf_type());
+        if pdf == 0.0 {
+            return (
+                Vector3::zero(),
+                0.0,
+                RgbaSpectrum::black(),
+                bxdf.bxdf_type(),
+            );
+        }
+        let wi_world = self.transform_local_to_world(&wi);
+
+        // A perfectly specular BxDF scatters light in a single direction that
+        // none of the other BxDFs could have sampled, so its sampled values are
+        // used as they are.
+        if bxdf.bxdf_type().contains(BxdfType::SPECULAR) || matching_count == 1 {
+            return (wi_world, pdf / matching_count as f32, f, bxdf.bxdf_type());
+        }
+
+        let pdf = self.pdf(wo_world, &wi_world, flags);
+        let f = self.f(wo_world, &wi_world, flags);
+        (wi_world, pdf, f, bxdf.bxdf_type())
+    }
+
+    /// Return the probability density, with respect to solid angle, that
+    /// `sample_f` samples the incident light direction `wi_world` given the
+    /// viewing direction `wo_world`. This is the average of the probability
+    /// densities of the BxDFs that match the given flags, since `sample_f`
+    /// chooses between them with equal probability.
+    ///
+    /// This is useful for weighting samples with multiple importance sampling,
+    /// where a direction that was sampled from a light source must also be
+    /// evaluated with the BSDF's sampling distribution.
+    ///
+    /// * wo_world - The viewing direction in world space.
+    /// * wi_world - The incident light direction in world space.
+    /// * flags - The types of BxDFs that may be sampled.
+    pub fn pdf(&self, wo_world: &Vector3<f32>, wi_world: &Vector3<f32>, flags: BxdfType) -> f32 {
+        let wo = self.transform_world_to_local(wo_world);
+        let wi = self.transform_world_to_local(wi_world);
+        if wo.z == 0.0 {
+            return 0.0;
+        }
+
+        let (pdf_sum, matching_count) = self
+            .bxdfs
+            .iter()
+            .filter(|bxdf| bxdf.has_type(flags))
+            .fold((0.0, 0), |(pdf_sum, count), bxdf| {
+                (pdf_sum + bxdf.pdf(&wo, &wi), count + 1)
+            });
+        if matching_count > 0 {
+            pdf_sum / matching_count as f32
+        } else {
+            0.0
+        }
+    }
+
     /// Evaluate the hemispherical-directional reflectance function. This
-    /// returns the total reflection in the direction `wo` due to constant
-    /// illumination over the hemisphere.
-    fn rho_hd(&self, wo: &Vector3<f32>, samples: &[Point2<f32>], flags: BxdfType) -> RgbaSpectrum {
+    /// returns the total reflection in the direction `wo_world` due to
+    /// constant illumination over the hemisphere.
+    ///
+    /// * wo_world - The viewing direction in world space.
+    /// * samples - 2D samples in [0, 1) x [0, 1) that are used by BxDFs that
+    ///   estimate their reflectance with Monte Carlo integration.
+    /// * flags - The types of BxDFs that are evaluated.
+    pub fn rho_hd(
+        &self,
+        wo_world: &Vector3<f32>,
+        samples: &[Point2<f32>],
+        flags: BxdfType,
+    ) -> RgbaSpectrum {
+        let wo = self.transform_world_to_local(wo_world);
         self.bxdfs
             .iter()
             .filter(|bxdf| bxdf.has_type(flags))
             .fold(RgbaSpectrum::black(), |light, bxdf| {
-                light + bxdf.rho_hd(wo, samples)
+                light + bxdf.rho_hd(&wo, samples)
             })
     }
 
@@ -166,3 +318,145 @@ impl Bsdf {
             })
     }
 }
+
+#[cfg(test)]
+mod bsdf_tests {
+    use super::{
+        Bsdf, BxdfType, FresnelSpecularReflection, FresnelSpecularTransmission,
+        LambertianDiffuseReflection, LambertianDiffuseTransmission,
+    };
+    use crate::{
+        color::RgbaSpectrum, interaction::SurfaceInteraction, test::ApproxEq, TransportMode,
+    };
+    use cgmath::{InnerSpace, Point2, Point3, Vector3};
+    use std::f32::consts::FRAC_1_PI;
+
+    fn flat_interaction() -> SurfaceInteraction {
+        SurfaceInteraction::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        )
+    }
+
+    fn diffuse_bsdf() -> Bsdf {
+        let mut bsdf = Bsdf::new(&flat_interaction());
+        bsdf.add(Box::new(LambertianDiffuseReflection::new(
+            RgbaSpectrum::constant(0.5),
+        )));
+        bsdf.add(Box::new(LambertianDiffuseTransmission::new(
+            RgbaSpectrum::constant(0.25),
+        )));
+        bsdf
+    }
+
+    #[test]
+    fn pdf_averages_matching_bxdfs() {
+        let bsdf = diffuse_bsdf();
+        let wo = Vector3::new(0.0, 0.6, 0.8);
+        let wi = Vector3::new(0.6, 0.0, 0.8);
+        bsdf.pdf(&wo, &wi, BxdfType::ALL)
+            .assert_approx_eq(&(0.5 * 0.8 * FRAC_1_PI));
+        bsdf.pdf(&wo, &wi, BxdfType::REFLECTION | BxdfType::DIFFUSE)
+            .assert_approx_eq(&(0.8 * FRAC_1_PI));
+        bsdf.pdf(&wo, &wi, BxdfType::TRANSMISSION | BxdfType::DIFFUSE)
+            .assert_approx_eq(&0.0);
+    }
+
+    #[test]
+    fn f_only_evaluates_lobes_on_the_side_of_wi() {
+        // Both lobes match `BxdfType::ALL`, but only the reflection lobe
+        // scatters light into the hemisphere that `wo` is in, and only the
+        // transmission lobe scatters light into the opposite hemisphere.
+        let bsdf = diffuse_bsdf();
+        let wo = Vector3::new(0.0, 0.6, 0.8);
+        bsdf.f(&wo, &Vector3::new(0.6, 0.0, 0.8), BxdfType::ALL)
+            .assert_approx_eq(&RgbaSpectrum::constant(0.5 * FRAC_1_PI));
+        bsdf.f(&wo, &Vector3::new(0.6, 0.0, -0.8), BxdfType::ALL)
+            .assert_approx_eq(&RgbaSpectrum::constant(0.25 * FRAC_1_PI));
+    }
+
+    #[test]
+    fn sample_f_agrees_with_pdf() {
+        let bsdf = diffuse_bsdf();
+        let wo = Vector3::new(0.0, 0.6, 0.8);
+        for sample in &[Point2::new(0.2, 0.3), Point2::new(0.7, 0.4)] {
+            let (wi, pdf, f, _) = bsdf.sample_f(&wo, sample, BxdfType::ALL);
+            pdf.assert_approx_eq(&bsdf.pdf(&wo, &wi, BxdfType::ALL));
+            f.assert_approx_eq(&bsdf.f(&wo, &wi, BxdfType::ALL));
+        }
+    }
+
+    #[test]
+    fn shading_frame_is_orthonormal() {
+        // The partial derivatives are neither unit vectors nor perpendicular to
+        // the normal.
+        let interaction = SurfaceInteraction::new_with_normal(
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(2.0, 0.0, 1.0),
+            Vector3::new(0.0, 3.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+        );
+        let bsdf = Bsdf::new(&interaction);
+        bsdf.transform_world_to_local(&Vector3::new(0.0, 0.0, 1.0))
+            .assert_approx_eq(&Vector3::new(0.0, 0.0, 1.0));
+        let v = Vector3::new(0.36, 0.48, 0.8);
+        let local = bsdf.transform_world_to_local(&v);
+        local.magnitude().assert_approx_eq(&1.0);
+        bsdf.transform_local_to_world(&local).assert_approx_eq(&v);
+    }
+
+    #[test]
+    fn specular_lobe_is_sampled_alongside_others() {
+        let mut bsdf = Bsdf::new(&flat_interaction());
+        bsdf.add(Box::new(FresnelSpecularReflection::dielectric(
+            RgbaSpectrum::constant(1.0),
+            1.0,
+            1.5,
+        )));
+        bsdf.add(Box::new(FresnelSpecularTransmission::dielectric(
+            1.0,
+            1.5,
+            RgbaSpectrum::constant(1.0),
+            TransportMode::Radiance,
+        )));
+        let wo = Vector3::new(0.0, 0.6, 0.8);
+        for sample in &[Point2::new(0.2, 0.3), Point2::new(0.7, 0.4)] {
+            // Each specular lobe is chosen half of the time, and its sampled
+            // values are used as they are rather than being evaluated against
+            // the other lobe.
+            let (_, pdf, f, sampled_type) = bsdf.sample_f(&wo, sample, BxdfType::ALL);
+            assert!(sampled_type.contains(BxdfType::SPECULAR));
+            pdf.assert_approx_eq(&0.5);
+            assert!(!f.is_black());
+        }
+    }
+
+    #[test]
+    fn regularized_mirror_becomes_glossy() {
+        let mut bsdf = Bsdf::new(&flat_interaction());
+        bsdf.add(Box::new(FresnelSpecularReflection::dielectric(
+            RgbaSpectrum::constant(1.0),
+            1.0,
+            1.5,
+        )));
+        let wo = Vector3::new(0.0, 0.6, 0.8);
+        let mirrored = Vector3::new(0.0, -0.6, 0.8);
+        bsdf.f(&wo, &mirrored, BxdfType::ALL)
+            .assert_approx_eq(&RgbaSpectrum::black());
+
+        bsdf.regularize(0.2);
+        assert_eq!(
+            bsdf.count_with_type(BxdfType::SPECULAR | BxdfType::REFLECTION),
+            0
+        );
+        assert!(!bsdf.f(&wo, &mirrored, BxdfType::ALL).is_black());
+        let (_, pdf, _, sampled_type) = bsdf.sample_f(&wo, &Point2::new(0.3, 0.6), BxdfType::ALL);
+        assert!(sampled_type.contains(BxdfType::GLOSSY));
+        assert!(pdf > 0.0);
+    }
+}
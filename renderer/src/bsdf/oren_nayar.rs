@@ -53,3 +53,33 @@ impl Bxdf for OrenNayarDiffuseReflection {
         self.r * FRAC_1_PI * (self.a + self.b * max_cos * sin_alpha * tan_beta)
     }
 }
+
+#[cfg(test)]
+mod oren_nayar_tests {
+    use super::OrenNayarDiffuseReflection;
+    use crate::{bsdf::Bxdf, color::RgbaSpectrum, sampling, test::ApproxEq};
+    use cgmath::Vector3;
+
+    #[test]
+    fn smooth_surface_reflectance_matches_lambertian() {
+        // With no roughness the model reduces to a Lambertian reflector, and
+        // cosine-weighted sampling makes every estimate exact.
+        let bxdf = OrenNayarDiffuseReflection::new(RgbaSpectrum::constant(0.5), 0.0);
+        let samples = sampling::grid_samples_2d(8);
+        let rho = bxdf.rho_hd(&Vector3::new(0.0, 0.6, 0.8), &samples);
+        rho.r().assert_approx_eq(&0.5);
+        rho.g().assert_approx_eq(&0.5);
+        rho.b().assert_approx_eq(&0.5);
+    }
+
+    #[test]
+    fn rough_surface_conserves_energy() {
+        let bxdf = OrenNayarDiffuseReflection::new(RgbaSpectrum::constant(1.0), 0.5);
+        let samples1 = sampling::grid_samples_2d(32);
+        let mut samples2 = samples1.clone();
+        samples2.reverse();
+        let rho = bxdf.rho_hh(&samples1, &samples2);
+        assert!(rho.r() > 0.0);
+        assert!(rho.r() <= 1.0 + 0.01);
+    }
+}
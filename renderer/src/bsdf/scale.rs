@@ -32,6 +32,10 @@ impl Bxdf for ScaledBxdf {
         (wi, pdf, self.scale * light)
     }
 
+    fn pdf(&self, wo: &Vector3<f32>, wi: &Vector3<f32>) -> f32 {
+        self.bxdf.pdf(wo, wi)
+    }
+
     fn rho_hd(&self, wo: &Vector3<f32>, samples: &[Point2<f32>]) -> RgbaSpectrum {
         self.scale * self.bxdf.rho_hd(wo, samples)
     }
@@ -39,4 +43,10 @@ impl Bxdf for ScaledBxdf {
     fn rho_hh(&self, samples1: &[Point2<f32>], samples2: &[Point2<f32>]) -> RgbaSpectrum {
         self.scale * self.bxdf.rho_hh(samples1, samples2)
     }
+
+    fn regularized(&self, min_alpha: f32) -> Option<Box<dyn Bxdf>> {
+        self.bxdf
+            .regularized(min_alpha)
+            .map(|bxdf| Box::new(ScaledBxdf::new(bxdf, self.scale)) as Box<dyn Bxdf>)
+    }
 }
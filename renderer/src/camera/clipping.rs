@@ -0,0 +1,144 @@
+//! Finds where a camera's clipping planes cut off the scene's geometry, so
+//! that a clipping range that's too tight for a large part can be noticed
+//! rather than silently truncating the part.
+
+use super::{Camera, OrthographicCamera};
+use crate::color::RgbaSpectrum;
+use cgmath::{InnerSpace, Point2, Point3, Vector2, Vector3};
+use image::{ImageBuffer, Rgba};
+use rayon::prelude::*;
+
+/// How far behind the near clipping plane, in world space, rays start when
+/// looking for geometry that the near plane cuts off. The scene is assumed to
+/// fit well within this distance.
+const LOOK_BEHIND_DISTANCE: f32 = 1e4;
+
+/// What a pixel sees along its whole line of sight, ignoring the clipping
+/// planes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clipping {
+    /// No geometry is along the line of sight.
+    Empty,
+
+    /// The first geometry along the line of sight is between the clipping
+    /// planes.
+    Visible,
+
+    /// Geometry in front of the near clipping plane is cut off, so the pixel
+    /// may see into the part.
+    Near,
+
+    /// The only geometry along the line of sight is beyond the far clipping
+    /// plane, so the pixel sees nothing.
+    Far,
+}
+
+/// Return what the ray through each pixel's center sees, in row-major order
+/// starting at the top-left pixel.
+///
+/// * resolution - The resolution that the camera was created with.
+/// * first_hit - Returns the parametric value at which a ray with an origin
+///   and direction first hits the scene's geometry, if it hits anything.
+pub fn find_clipping<F>(
+    camera: &OrthographicCamera,
+    resolution: Vector2<usize>,
+    first_hit: F,
+) -> Vec<Clipping>
+where
+    F: Fn(Point3<f32>, Vector3<f32>) -> Option<f32> + Sync,
+{
+    let direction = camera.view_direction().normalize();
+    let (z_near, z_far) = camera.clipping_range();
+    (0..resolution.x * resolution.y)
+        .into_par_iter()
+        .map(|index| {
+            let raster_point = Point2::new(
+                (index % resolution.x) as f32 + 0.5,
+                (index / resolution.x) as f32 + 0.5,
+            );
+            let near_point = camera.near_plane_point(raster_point);
+            let behind = near_point - LOOK_BEHIND_DISTANCE * direction;
+            match first_hit(behind, direction) {
+                Some(t) if t < LOOK_BEHIND_DISTANCE => Clipping::Near,
+                Some(t) if t <= LOOK_BEHIND_DISTANCE + z_far - z_near => Clipping::Visible,
+                Some(_) => Clipping::Far,
+                None => Clipping::Empty,
+            }
+        })
+        .collect()
+}
+
+/// Return the fraction of the pixels that see any geometry in which the
+/// geometry is clipped.
+pub fn clipped_fraction(clipping: &[Clipping]) -> f32 {
+    let covered = clipping
+        .iter()
+        .filter(|clipping| **clipping != Clipping::Empty)
+        .count();
+    let clipped = clipping
+        .iter()
+        .filter(|clipping| matches!(clipping, Clipping::Near | Clipping::Far))
+        .count();
+    if covered == 0 {
+        0.0
+    } else {
+        clipped as f32 / covered as f32
+    }
+}
+
+/// Write an image that marks pixels where the near plane cuts off geometry in
+/// red and pixels where geometry is beyond the far plane in blue. Other
+/// pixels that see geometry are gray, and the rest are transparent.
+pub fn clipping_image(
+    clipping: &[Clipping],
+    resolution: Vector2<usize>,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    ImageBuffer::from_fn(resolution.x as u32, resolution.y as u32, |x, y| {
+        let color = match clipping[y as usize * resolution.x + x as usize] {
+            Clipping::Empty => RgbaSpectrum::transparent(),
+            Clipping::Visible => RgbaSpectrum::from_rgba(0.5, 0.5, 0.5, 1.0),
+            Clipping::Near => RgbaSpectrum::from_rgba(1.0, 0.0, 0.0, 1.0),
+            Clipping::Far => RgbaSpectrum::from_rgba(0.0, 0.0, 1.0, 1.0),
+        };
+        color.into()
+    })
+}
+
+#[cfg(test)]
+mod clipping_tests {
+    use super::{clipped_fraction, find_clipping, Clipping};
+    use crate::{camera::OrthographicCamera, geometry::matrix::identity4};
+    use cgmath::Vector2;
+
+    #[test]
+    fn finds_geometry_outside_of_the_clipping_range() {
+        let camera = OrthographicCamera::new(
+            identity4(),
+            1.0,
+            5.0,
+            Vector2::new(4.0, 1.0),
+            Vector2::new(4, 1),
+        );
+        // The scene is a wall whose distance from the camera depends on x.
+        // The pixels' centers are at x = -1.5, -0.5, 0.5, and 1.5.
+        let clipping = find_clipping(&camera, Vector2::new(4, 1), |origin, direction| {
+            let z = match origin.x {
+                x if x < -1.0 => 0.5,
+                x if x < 0.0 => 3.0,
+                x if x < 1.0 => 8.0,
+                _ => return None,
+            };
+            Some((z - origin.z) / direction.z)
+        });
+        assert_eq!(
+            clipping,
+            vec![
+                Clipping::Near,
+                Clipping::Visible,
+                Clipping::Far,
+                Clipping::Empty
+            ]
+        );
+        assert!((clipped_fraction(&clipping) - 2.0 / 3.0).abs() < 1e-6);
+    }
+}
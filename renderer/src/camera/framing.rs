@@ -0,0 +1,98 @@
+use super::OrthographicCamera;
+use cgmath::{Deg, InnerSpace, Matrix4, Point3, Rad, Transform, Vector3};
+
+/// The placement and field of view of a perspective camera.
+#[derive(Debug, Clone, Copy)]
+pub struct PerspectiveFraming {
+    /// The transformation from camera space to world space. As with the
+    /// orthographic camera, the perspective camera looks toward positive z in
+    /// camera space.
+    pub camera_to_world: Matrix4<f32>,
+
+    /// The vertical field of view.
+    pub fov_y: Deg<f32>,
+
+    /// The distance between the perspective camera and the focus plane.
+    pub distance: f32,
+}
+
+/// Return the framing of a perspective camera that produces nearly the same
+/// composition as the orthographic camera.
+///
+/// Objects in the focus plane, which is parallel to the orthographic camera's
+/// screen, appear at exactly the same size and position in both images. The
+/// perspective camera looks in the same direction as the orthographic camera
+/// and is pulled back from the focus plane far enough that its field of view
+/// spans the orthographic screen. Narrower fields of view place the camera
+/// farther away and reduce perspective distortion.
+///
+/// * ortho - The orthographic camera whose framing is matched.
+/// * focus_distance - The distance along the orthographic camera's viewing
+///   direction from the camera to the focus plane. This is usually the
+///   distance to the center of the subject.
+/// * fov_y - The vertical field of view of the perspective camera.
+pub fn match_orthographic_framing(
+    ortho: &OrthographicCamera,
+    focus_distance: f32,
+    fov_y: Rad<f32>,
+) -> PerspectiveFraming {
+    let screen_bounds = ortho.screen_bounds;
+    let screen_height = screen_bounds.max.y - screen_bounds.min.y;
+    let distance = 0.5 * screen_height / (0.5 * fov_y.0).tan();
+
+    // Center the perspective camera on the orthographic screen window so that
+    // shifted windows are matched as well.
+    let center_x = 0.5 * (screen_bounds.min.x + screen_bounds.max.x);
+    let center_y = 0.5 * (screen_bounds.min.y + screen_bounds.max.y);
+    let camera_to_world = ortho.camera_to_world
+        * Matrix4::from_translation(Vector3::new(center_x, center_y, focus_distance - distance));
+
+    PerspectiveFraming {
+        camera_to_world,
+        fov_y: fov_y.into(),
+        distance,
+    }
+}
+
+impl PerspectiveFraming {
+    /// Return the position of the camera in world space.
+    pub fn position(&self) -> Point3<f32> {
+        self.camera_to_world
+            .transform_point(Point3::new(0.0, 0.0, 0.0))
+    }
+
+    /// Return the direction that the camera looks in world space.
+    pub fn direction(&self) -> Vector3<f32> {
+        self.camera_to_world
+            .transform_vector(Vector3::new(0.0, 0.0, 1.0))
+            .normalize()
+    }
+}
+
+#[cfg(test)]
+mod match_orthographic_framing_tests {
+    use super::match_orthographic_framing;
+    use crate::{camera::OrthographicCamera, test::ApproxEq};
+    use cgmath::{Deg, Matrix4, Point3, Rad, Vector2, Vector3};
+
+    #[test]
+    fn perspective_camera_spans_orthographic_screen() {
+        let ortho = OrthographicCamera::new(
+            Matrix4::from_translation(Vector3::new(0.0, 0.0, -10.0)),
+            0.0,
+            100.0,
+            Vector2::new(4.0, 2.0),
+            Vector2::new(400, 200),
+        );
+        let framing = match_orthographic_framing(&ortho, 10.0, Rad::from(Deg(90.0)));
+
+        framing.distance.assert_approx_eq(&1.0);
+        framing.fov_y.0.assert_approx_eq(&90.0);
+        framing
+            .position()
+            .assert_approx_eq(&Point3::new(0.0, 0.0, -1.0));
+        framing
+            .direction()
+            .assert_approx_eq(&Vector3::new(0.0, 0.0, 1.0));
+    }
+}
@@ -1,9 +1,18 @@
+pub mod clipping;
+mod framing;
 mod orthographic;
 mod sample;
+mod telecentric;
 
-pub use {orthographic::OrthographicCamera, sample::CameraSample};
+pub use {
+    framing::{match_orthographic_framing, PerspectiveFraming},
+    orthographic::OrthographicCamera,
+    sample::CameraSample,
+    telecentric::TelecentricCamera,
+};
 
 use crate::ray::{Ray, RayDifferential};
+use cgmath::Vector3;
 
 pub trait Camera {
     /// Generate a ray for the given sample.
@@ -29,4 +38,7 @@ pub trait Camera {
         &self,
         sample: &CameraSample,
     ) -> (Ray, Option<RayDifferential>, f32);
+
+    /// Return the direction in world space that the camera looks in.
+    fn view_direction(&self) -> Vector3<f32>;
 }
@@ -10,14 +10,19 @@ use cgmath::{Matrix4, Point2, Point3, Transform, Vector2, Vector3};
 /// The camera sits at the origin of camera space and renders images onto a
 /// canvas one unit away.
 pub struct OrthographicCamera {
-    /// The bounds of the screen in screen space. This should be centered at the
-    /// origin (around the camera), and its width and height should be
-    /// equivalent to the width and height of the screen (at the near clipping
-    /// plane) in world or camera space.
+    /// The bounds of the screen in screen space. This is centered at the origin
+    /// (around the camera) unless the screen window is shifted, and its width
+    /// and height should be equivalent to the width and height of the screen
+    /// (at the near clipping plane) in world or camera space.
     pub screen_bounds: Bounds2<f32>,
 
     pub camera_to_world: Matrix4<f32>,
 
+    /// The distances from the camera to the near and far clipping planes.
+    /// Rays start at the near plane and end at the far plane.
+    z_near: f32,
+    z_far: f32,
+
     camera_to_screen: Matrix4<f32>,
     screen_to_raster: Matrix4<f32>,
     raster_to_screen: Matrix4<f32>,
@@ -44,9 +49,41 @@ impl OrthographicCamera {
         screen_size: Vector2<f32>,
         resolution: Vector2<usize>,
     ) -> Self {
+        Self::new_with_screen_offset(
+            camera_to_world,
+            z_near,
+            z_far,
+            screen_size,
+            Vector2::new(0.0, 0.0),
+            resolution,
+        )
+    }
+
+    /// Create a camera whose screen window is shifted away from the center of
+    /// the view. The projection is unchanged; only the region of the screen
+    /// plane that is rendered onto the film moves. This is useful for rendering
+    /// off-center crops of a larger image, for example when splitting a very
+    /// large render into separately rendered tiles.
+    ///
+    /// * `screen_size` - Width and height of the screen in world space. In
+    ///   general, this should have the same aspect ratio as `resolution`.
+    /// * `screen_offset` - The amount that the center of the screen window is
+    ///   shifted from the center of the view, in world space. Positive x
+    ///   shifts the window right, and positive y shifts the window up.
+    /// * `resolution` - Width and height of the screen in raster space. In
+    ///   general, this should have the same aspect ratio as `screen_size`.
+    pub fn new_with_screen_offset(
+        camera_to_world: Matrix4<f32>,
+        z_near: f32,
+        z_far: f32,
+        screen_size: Vector2<f32>,
+        screen_offset: Vector2<f32>,
+        resolution: Vector2<usize>,
+    ) -> Self {
+        let screen_center = Point2::new(0.0, 0.0) + screen_offset;
         let screen_bounds = Bounds2::new(
-            Point2::new(0.0, 0.0) - 0.5 * screen_size,
-            Point2::new(0.0, 0.0) + 0.5 * screen_size,
+            screen_center - 0.5 * screen_size,
+            screen_center + 0.5 * screen_size,
         );
         let camera_to_screen = Self::camera_to_screen(z_near, z_far);
         let screen_to_camera = camera_to_screen.inverse_transform().unwrap();
@@ -60,6 +97,8 @@ impl OrthographicCamera {
         Self {
             screen_bounds,
             camera_to_world,
+            z_near,
+            z_far,
             camera_to_screen,
             screen_to_raster,
             raster_to_screen,
@@ -69,6 +108,30 @@ impl OrthographicCamera {
         }
     }
 
+    /// Return the matrix that transforms world space to raster space. The z
+    /// component of a transformed point is 0 at the near clipping plane and 1
+    /// at the far clipping plane.
+    pub fn world_to_raster(&self) -> Matrix4<f32> {
+        self.screen_to_raster
+            * self.camera_to_screen
+            * self.camera_to_world.inverse_transform().unwrap()
+    }
+
+    /// Return the distances from the camera to the near and far clipping
+    /// planes.
+    pub fn clipping_range(&self) -> (f32, f32) {
+        (self.z_near, self.z_far)
+    }
+
+    /// Return the point in world space where the ray through the point in
+    /// raster space starts, on the near clipping plane.
+    pub fn near_plane_point(&self, raster_point: Point2<f32>) -> Point3<f32> {
+        let camera_point =
+            self.raster_to_camera
+                .transform_point(Point3::new(raster_point.x, raster_point.y, 0.0));
+        self.camera_to_world.transform_point(camera_point)
+    }
+
     /// Returns the projective orthographic matrix that transforms camera space
     /// to screen space.
     ///
@@ -101,7 +164,11 @@ impl OrthographicCamera {
         // TODO: Modify ray for depth of field.
         // TODO: Set ray time.
         // TODO: Set ray medium equal to camera medium.
-        Ray::new(camera_point, Vector3::new(0.0, 0.0, 1.0), f32::MAX)
+        Ray::new(
+            camera_point,
+            Vector3::new(0.0, 0.0, 1.0),
+            self.z_far - self.z_near,
+        )
     }
 }
 
@@ -131,6 +198,11 @@ impl Camera for OrthographicCamera {
 
         (world_primay_ray, Some(world_ray_differential), 1.0)
     }
+
+    fn view_direction(&self) -> Vector3<f32> {
+        self.camera_to_world
+            .transform_vector(Vector3::new(0.0, 0.0, 1.0))
+    }
 }
 
 #[cfg(test)]
@@ -160,7 +232,7 @@ mod generate_ray_tests {
         ray.assert_approx_eq(&Ray::new(
             Point3::new(-1.995, 0.995, 0.0),
             Vector3::new(0.0, 0.0, 1.0),
-            f32::MAX,
+            100.0,
         ));
 
         let sample = CameraSample::at_pixel_center(Point2::new(399, 199));
@@ -168,7 +240,7 @@ mod generate_ray_tests {
         ray.assert_approx_eq(&Ray::new(
             Point3::new(1.995, -0.995, 0.0),
             Vector3::new(0.0, 0.0, 1.0),
-            f32::MAX,
+            100.0,
         ));
     }
 
@@ -188,7 +260,7 @@ mod generate_ray_tests {
         ray.assert_approx_eq(&Ray::new(
             Point3::new(1.005, 3.995, 3.0),
             Vector3::new(0.0, 0.0, 1.0),
-            f32::MAX,
+            100.0,
         ));
 
         let sample = CameraSample::at_pixel_center(Point2::new(399, 199));
@@ -196,7 +268,7 @@ mod generate_ray_tests {
         ray.assert_approx_eq(&Ray::new(
             Point3::new(4.995, 2.005, 3.0),
             Vector3::new(0.0, 0.0, 1.0),
-            f32::MAX,
+            100.0,
         ));
     }
 
@@ -216,7 +288,7 @@ mod generate_ray_tests {
         ray.assert_approx_eq(&Ray::new(
             Point3::new(0.0, 0.995, 1.995),
             Vector3::new(1.0, 0.0, 0.0),
-            f32::MAX,
+            100.0,
         ));
 
         let sample = CameraSample::at_pixel_center(Point2::new(399, 199));
@@ -224,7 +296,36 @@ mod generate_ray_tests {
         ray.assert_approx_eq(&Ray::new(
             Point3::new(0.0, -0.995, -1.995),
             Vector3::new(1.0, 0.0, 0.0),
-            f32::MAX,
+            100.0,
+        ));
+    }
+
+    #[test]
+    fn shifted_screen_window() {
+        let camera_to_world = identity4();
+        let camera = OrthographicCamera::new_with_screen_offset(
+            camera_to_world,
+            0.0,
+            100.0,
+            Vector2::new(4.0, 2.0),
+            Vector2::new(2.0, -1.0),
+            Vector2::new(400, 200),
+        );
+
+        let sample = CameraSample::at_pixel_center(Point2::new(0, 0));
+        let (ray, _) = camera.generate_ray(&sample);
+        ray.assert_approx_eq(&Ray::new(
+            Point3::new(0.005, -0.005, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            100.0,
+        ));
+
+        let sample = CameraSample::at_pixel_center(Point2::new(399, 199));
+        let (ray, _) = camera.generate_ray(&sample);
+        ray.assert_approx_eq(&Ray::new(
+            Point3::new(3.995, -1.995, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            100.0,
         ));
     }
 }
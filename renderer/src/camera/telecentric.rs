@@ -0,0 +1,141 @@
+use super::{Camera, CameraSample, OrthographicCamera};
+use crate::{
+    ray::{Ray, RayDifferential},
+    sampling,
+};
+use cgmath::{InnerSpace, Point3, Transform, Vector3};
+
+/// An orthographic camera with a finite aperture, like the telecentric lenses
+/// used on measurement microscopes.
+///
+/// The projection is still parallel, so a part's size in the image doesn't
+/// depend on its distance from the camera, but only points at the focal
+/// distance are in sharp focus. Points in front of or behind the focal plane
+/// are blurred by an amount that grows with their distance from it.
+pub struct TelecentricCamera {
+    orthographic: OrthographicCamera,
+
+    /// The radius of the aperture in camera space.
+    lens_radius: f32,
+
+    /// The distance from the camera, along the viewing direction, to the plane
+    /// that is in focus.
+    focal_distance: f32,
+}
+
+impl TelecentricCamera {
+    /// Create a telecentric camera.
+    ///
+    /// * orthographic - The camera whose projection is used.
+    /// * lens_radius - The radius of the aperture in camera space. A radius of
+    ///   zero is equivalent to `orthographic`.
+    /// * focal_distance - The distance from the camera, along the viewing
+    ///   direction, to the plane that is in focus.
+    pub fn new(orthographic: OrthographicCamera, lens_radius: f32, focal_distance: f32) -> Self {
+        Self {
+            orthographic,
+            lens_radius,
+            focal_distance,
+        }
+    }
+
+    /// Return the offset of the sample's point on the lens from the center of
+    /// the lens, and the offset of the focal plane from the camera, both in
+    /// world space.
+    fn lens_and_focus_offsets(&self, sample: &CameraSample) -> (Vector3<f32>, Vector3<f32>) {
+        let lens_point = sampling::concentric_sample_disk(&sample.lens_point);
+        let camera_to_world = &self.orthographic.camera_to_world;
+        let lens_offset = camera_to_world.transform_vector(Vector3::new(
+            self.lens_radius * lens_point.x,
+            self.lens_radius * lens_point.y,
+            0.0,
+        ));
+        let focus_offset =
+            camera_to_world.transform_vector(Vector3::new(0.0, 0.0, self.focal_distance));
+        (lens_offset, focus_offset)
+    }
+
+    /// Move the origin of the ray, which passes through the center of the
+    /// lens, to the point on the lens, and aim it at the point where the
+    /// original ray meets the focal plane.
+    fn refocus(
+        origin: Point3<f32>,
+        lens_offset: Vector3<f32>,
+        focus_offset: Vector3<f32>,
+    ) -> (Point3<f32>, Vector3<f32>) {
+        let focus_point = origin + focus_offset;
+        let origin = origin + lens_offset;
+        (origin, (focus_point - origin).normalize())
+    }
+}
+
+impl Camera for TelecentricCamera {
+    fn generate_ray(&self, sample: &CameraSample) -> (Ray, f32) {
+        let (ray, weight) = self.orthographic.generate_ray(sample);
+        let (lens_offset, focus_offset) = self.lens_and_focus_offsets(sample);
+        let (origin, direction) = Self::refocus(ray.origin, lens_offset, focus_offset);
+        (Ray::new(origin, direction, ray.t_max), weight)
+    }
+
+    fn generate_ray_differential(
+        &self,
+        sample: &CameraSample,
+    ) -> (Ray, Option<RayDifferential>, f32) {
+        let (ray, differential, weight) = self.orthographic.generate_ray_differential(sample);
+        let (lens_offset, focus_offset) = self.lens_and_focus_offsets(sample);
+        let (origin, direction) = Self::refocus(ray.origin, lens_offset, focus_offset);
+        let differential = differential.map(|d| {
+            let (dx_origin, dx_direction) = Self::refocus(d.dx_origin, lens_offset, focus_offset);
+            let (dy_origin, dy_direction) = Self::refocus(d.dy_origin, lens_offset, focus_offset);
+            RayDifferential::new(dx_origin, dx_direction, dy_origin, dy_direction)
+        });
+        (Ray::new(origin, direction, ray.t_max), differential, weight)
+    }
+
+    fn view_direction(&self) -> Vector3<f32> {
+        self.orthographic.view_direction()
+    }
+}
+
+#[cfg(test)]
+mod telecentric_camera_tests {
+    use super::TelecentricCamera;
+    use crate::{
+        camera::{Camera, CameraSample, OrthographicCamera},
+        geometry::matrix::identity4,
+        test::ApproxEq,
+    };
+    use cgmath::{Point2, Point3, Vector2, Vector3};
+
+    fn camera() -> TelecentricCamera {
+        let orthographic = OrthographicCamera::new(
+            identity4(),
+            0.0,
+            100.0,
+            Vector2::new(4.0, 2.0),
+            Vector2::new(400, 200),
+        );
+        TelecentricCamera::new(orthographic, 0.5, 10.0)
+    }
+
+    #[test]
+    fn ray_through_lens_center_is_orthographic() {
+        let sample = CameraSample::new(Point2::new(0.5, 0.5), Point2::new(0.5, 0.5), 0.0);
+        let (ray, _) = camera().generate_ray(&sample);
+        assert!(ray.origin.approx_eq(&Point3::new(-1.995, 0.995, 0.0)));
+        assert!(ray.direction.approx_eq(&Vector3::new(0.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn rays_converge_on_focal_plane() {
+        let film_point = Point2::new(0.5, 0.5);
+        let focus_point = Point3::new(-1.995, 0.995, 10.0);
+        for lens_point in &[Point2::new(0.0, 0.5), Point2::new(0.9, 0.2)] {
+            let sample = CameraSample::new(film_point, *lens_point, 0.0);
+            let (ray, _) = camera().generate_ray(&sample);
+            assert!(!ray.origin.approx_eq(&Point3::new(-1.995, 0.995, 0.0)));
+            let t = (focus_point.z - ray.origin.z) / ray.direction.z;
+            assert!(ray.at_t(t).approx_eq(&focus_point));
+        }
+    }
+}
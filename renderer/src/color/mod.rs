@@ -1,5 +1,11 @@
+mod ramp;
 mod rgba;
+mod sampled;
+mod srgb;
 mod xyza;
 
+pub use ramp::ColorRamp;
 pub use rgba::RgbaSpectrum;
+pub use sampled::{SampledSpectrum, SAMPLE_COUNT};
+pub use srgb::{linear_to_srgb, srgb_to_linear};
 pub use xyza::Xyza;
@@ -0,0 +1,89 @@
+use super::RgbaSpectrum;
+
+/// Maps scalar values in [0, 1] to colors by linearly interpolating between a
+/// sequence of color stops.
+#[derive(Debug, Clone)]
+pub struct ColorRamp {
+    /// The positions and colors of the ramp's stops, sorted by position.
+    stops: Vec<(f32, RgbaSpectrum)>,
+}
+
+impl ColorRamp {
+    /// Create a color ramp.
+    ///
+    /// * stops - The positions, in [0, 1], and colors of the ramp's stops.
+    ///   Values before the first stop or after the last stop are mapped to the
+    ///   color of the nearest stop. Stops whose position isn't finite are
+    ///   ignored. There must be at least one stop with a finite position.
+    pub fn new(stops: Vec<(f32, RgbaSpectrum)>) -> Self {
+        let mut stops: Vec<(f32, RgbaSpectrum)> =
+            stops.into_iter().filter(|(t, _)| t.is_finite()).collect();
+        assert!(!stops.is_empty());
+        stops.sort_by(|(t1, _), (t2, _)| t1.total_cmp(t2));
+        Self { stops }
+    }
+
+    /// Return the color of the ramp at the value `t`. A value that is NaN is
+    /// mapped to the color of the first stop.
+    pub fn eval(&self, t: f32) -> RgbaSpectrum {
+        let (first_t, first_color) = self.stops[0];
+        let (last_t, last_color) = self.stops[self.stops.len() - 1];
+        if t.is_nan() || t <= first_t {
+            return first_color;
+        }
+        if t >= last_t {
+            return last_color;
+        }
+
+        let i = self.stops.iter().position(|(t1, _)| *t1 > t).unwrap();
+        let (t0, c0) = self.stops[i - 1];
+        let (t1, c1) = self.stops[i];
+        RgbaSpectrum::lerp((t - t0) / (t1 - t0), &c0, &c1)
+    }
+}
+
+impl Default for ColorRamp {
+    /// Return a ramp that runs from blue, through green and yellow, to red.
+    fn default() -> Self {
+        Self::new(vec![
+            (0.0, RgbaSpectrum::from_rgb(0.0, 0.0, 1.0)),
+            (0.25, RgbaSpectrum::from_rgb(0.0, 1.0, 1.0)),
+            (0.5, RgbaSpectrum::from_rgb(0.0, 1.0, 0.0)),
+            (0.75, RgbaSpectrum::from_rgb(1.0, 1.0, 0.0)),
+            (1.0, RgbaSpectrum::from_rgb(1.0, 0.0, 0.0)),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod color_ramp_tests {
+    use super::ColorRamp;
+    use crate::{color::RgbaSpectrum, test::ApproxEq};
+
+    #[test]
+    fn eval_interpolates_between_stops() {
+        let ramp = ColorRamp::new(vec![
+            (1.0, RgbaSpectrum::from_rgb(1.0, 0.0, 0.0)),
+            (0.0, RgbaSpectrum::from_rgb(0.0, 0.0, 1.0)),
+        ]);
+        ramp.eval(-1.0)
+            .assert_approx_eq(&RgbaSpectrum::from_rgb(0.0, 0.0, 1.0));
+        ramp.eval(0.25)
+            .assert_approx_eq(&RgbaSpectrum::from_rgb(0.25, 0.0, 0.75));
+        ramp.eval(2.0)
+            .assert_approx_eq(&RgbaSpectrum::from_rgb(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn non_finite_stops_and_values_are_ignored() {
+        let ramp = ColorRamp::new(vec![
+            (f32::NAN, RgbaSpectrum::from_rgb(0.0, 1.0, 0.0)),
+            (1.0, RgbaSpectrum::from_rgb(1.0, 0.0, 0.0)),
+            (0.0, RgbaSpectrum::from_rgb(0.0, 0.0, 1.0)),
+        ]);
+        ramp.eval(0.25)
+            .assert_approx_eq(&RgbaSpectrum::from_rgb(0.25, 0.0, 0.75));
+        ramp.eval(f32::NAN)
+            .assert_approx_eq(&RgbaSpectrum::from_rgb(0.0, 0.0, 1.0));
+    }
+}
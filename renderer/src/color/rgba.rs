@@ -79,6 +79,11 @@ impl RgbaSpectrum {
     //     todo!() // TODO: Finish implementing. See p. 333.
     // }
 
+    /// Return the luminance of the linear sRGB color, ignoring alpha.
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r() + 0.7152 * self.g() + 0.0722 * self.b()
+    }
+
     pub fn is_black(&self) -> bool {
         self.r().is_zero() && self.g().is_zero() && self.b().is_zero()
     }
@@ -91,6 +96,14 @@ impl RgbaSpectrum {
         Self { samples }
     }
 
+    pub fn exp(&self) -> Self {
+        let mut samples = [0.0; SAMPLE_COUNT];
+        for (sample, input) in samples.iter_mut().zip(&self.samples) {
+            *sample = input.exp()
+        }
+        Self { samples }
+    }
+
     pub fn powf(&self, n: f32) -> Self {
         let mut samples = [0.0; SAMPLE_COUNT];
         for (sample, input) in samples.iter_mut().zip(&self.samples) {
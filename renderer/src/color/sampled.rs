@@ -0,0 +1,324 @@
+use super::{RgbaSpectrum, Xyza};
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign};
+use std::sync::OnceLock;
+
+/// The number of samples in a `SampledSpectrum`.
+pub const SAMPLE_COUNT: usize = 60;
+
+/// The shortest wavelength, in nanometers, that a `SampledSpectrum` covers.
+pub const LAMBDA_START: f32 = 400.0;
+
+/// The longest wavelength, in nanometers, that a `SampledSpectrum` covers.
+pub const LAMBDA_END: f32 = 700.0;
+
+/// Represents a spectral power distribution (SPD) with samples at evenly
+/// spaced wavelengths across the visible spectrum.
+///
+/// Unlike `RgbaSpectrum`, this representation can describe effects that
+/// depend on wavelength, such as dispersion. It is converted to XYZ and then
+/// RGB when the radiance it describes reaches the film.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SampledSpectrum {
+    samples: [f32; SAMPLE_COUNT],
+}
+
+impl SampledSpectrum {
+    pub fn constant(value: f32) -> Self {
+        Self {
+            samples: [value; SAMPLE_COUNT],
+        }
+    }
+
+    pub fn black() -> Self {
+        Self::constant(0.0)
+    }
+
+    /// Return the center wavelength, in nanometers, of the `i`th sample.
+    pub fn wavelength(i: usize) -> f32 {
+        let width = (LAMBDA_END - LAMBDA_START) / SAMPLE_COUNT as f32;
+        LAMBDA_START + (i as f32 + 0.5) * width
+    }
+
+    /// Create a smooth spectrum whose color is the given linear RGB color.
+    ///
+    /// The spectrum is a combination of three smooth basis spectra for the
+    /// red, green, and blue ends of the spectrum. Converting the result back
+    /// to RGB with `to_rgb` returns the original color, except for saturated
+    /// colors that would require negative samples, which are clamped to zero.
+    pub fn from_rgb(rgb: &RgbaSpectrum) -> Self {
+        let inverse = rgb_to_basis_weights();
+        let rgb = [rgb.r(), rgb.g(), rgb.b()];
+        let weights: Vec<f32> = inverse
+            .iter()
+            .map(|row| row.iter().zip(&rgb).map(|(m, c)| m * c).sum())
+            .collect();
+        let mut samples = [0.0; SAMPLE_COUNT];
+        for (i, sample) in samples.iter_mut().enumerate() {
+            let basis = basis_spectra(Self::wavelength(i));
+            let value: f32 = basis.iter().zip(&weights).map(|(b, w)| b * w).sum();
+            *sample = value.max(0.0);
+        }
+        Self { samples }
+    }
+
+    pub fn samples(&self) -> &[f32; SAMPLE_COUNT] {
+        &self.samples
+    }
+
+    pub fn is_black(&self) -> bool {
+        self.samples.iter().all(|s| *s == 0.0)
+    }
+
+    /// Return a spectrum that keeps only the `i`th sample, scaled by the
+    /// number of samples. If `i` is chosen uniformly at random, the result is
+    /// an unbiased estimate of this spectrum that carries a single wavelength.
+    pub fn single_wavelength(&self, i: usize) -> Self {
+        let mut samples = [0.0; SAMPLE_COUNT];
+        samples[i] = self.samples[i] * SAMPLE_COUNT as f32;
+        Self { samples }
+    }
+
+    pub fn max_value(&self) -> f32 {
+        self.samples.iter().cloned().fold(0.0, f32::max)
+    }
+
+    /// Return the spectrum's XYZ color. The color matching functions are
+    /// normalized so that a constant spectrum with a value of 1 has a
+    /// luminance, Y, of 1.
+    pub fn to_xyza(&self) -> Xyza {
+        let (mut x, mut y, mut z, mut y_integral) = (0.0, 0.0, 0.0, 0.0);
+        for (i, sample) in self.samples.iter().enumerate() {
+            let (x_bar, y_bar, z_bar) = cie_color_matching(Self::wavelength(i));
+            x += sample * x_bar;
+            y += sample * y_bar;
+            z += sample * z_bar;
+            y_integral += y_bar;
+        }
+        Xyza::new(x / y_integral, y / y_integral, z / y_integral, 1.0)
+    }
+
+    /// Return the spectrum's linear RGB color.
+    ///
+    /// The result is white balanced so that a constant spectrum maps to a
+    /// neutral gray. This keeps the colors of spectral renders consistent with
+    /// renders of the same scene in RGB.
+    pub fn to_rgb(&self) -> RgbaSpectrum {
+        let unbalanced = RgbaSpectrum::from(self.to_xyza());
+        let white = white_balance();
+        RgbaSpectrum::from_rgb(
+            unbalanced.r() / white[0],
+            unbalanced.g() / white[1],
+            unbalanced.b() / white[2],
+        )
+    }
+}
+
+/// Return the CIE 1931 color matching functions at the wavelength, in
+/// nanometers, using the multi-lobe Gaussian fit from Wyman, Sloan, and
+/// Shirley's "Simple Analytic Approximations to the CIE XYZ Color Matching
+/// Functions".
+fn cie_color_matching(lambda: f32) -> (f32, f32, f32) {
+    let lobe = |mu: f32, sigma_below: f32, sigma_above: f32| {
+        let sigma = if lambda < mu {
+            sigma_below
+        } else {
+            sigma_above
+        };
+        let t = (lambda - mu) / sigma;
+        (-0.5 * t * t).exp()
+    };
+    let x = 1.056 * lobe(599.8, 37.9, 31.0) + 0.362 * lobe(442.0, 16.0, 26.7)
+        - 0.065 * lobe(501.1, 20.4, 26.2);
+    let y = 0.821 * lobe(568.8, 46.9, 40.5) + 0.286 * lobe(530.9, 16.3, 31.1);
+    let z = 1.217 * lobe(437.0, 11.8, 36.0) + 0.681 * lobe(459.0, 26.0, 13.8);
+    (x, y, z)
+}
+
+/// Return the values of the red, green, and blue basis spectra at the
+/// wavelength. The basis spectra are smooth, non-negative, and sum to 1 at
+/// every wavelength, so a white RGB color maps to a constant spectrum.
+fn basis_spectra(lambda: f32) -> [f32; 3] {
+    let red = smoothstep(570.0, 610.0, lambda);
+    let blue = 1.0 - smoothstep(470.0, 520.0, lambda);
+    [red, 1.0 - red - blue, blue]
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Return the RGB color of a constant spectrum before white balancing.
+fn white_balance() -> &'static [f32; 3] {
+    static WHITE: OnceLock<[f32; 3]> = OnceLock::new();
+    WHITE.get_or_init(|| {
+        let white = RgbaSpectrum::from(SampledSpectrum::constant(1.0).to_xyza());
+        [white.r(), white.g(), white.b()]
+    })
+}
+
+/// Return the matrix that maps an RGB color to the weights of the basis
+/// spectra that reproduce the color.
+fn rgb_to_basis_weights() -> &'static [[f32; 3]; 3] {
+    static INVERSE: OnceLock<[[f32; 3]; 3]> = OnceLock::new();
+    INVERSE.get_or_init(|| {
+        // Each column of the matrix is the RGB color of one basis spectrum.
+        let mut columns = [[0.0; 3]; 3];
+        for (basis_index, column) in columns.iter_mut().enumerate() {
+            let mut samples = [0.0; SAMPLE_COUNT];
+            for (i, sample) in samples.iter_mut().enumerate() {
+                *sample = basis_spectra(SampledSpectrum::wavelength(i))[basis_index];
+            }
+            let rgb = SampledSpectrum { samples }.to_rgb();
+            *column = [rgb.r(), rgb.g(), rgb.b()];
+        }
+        let m = [
+            [columns[0][0], columns[1][0], columns[2][0]],
+            [columns[0][1], columns[1][1], columns[2][1]],
+            [columns[0][2], columns[1][2], columns[2][2]],
+        ];
+        invert_3x3(&m)
+    })
+}
+
+fn invert_3x3(m: &[[f32; 3]; 3]) -> [[f32; 3]; 3] {
+    let cofactor =
+        |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+    let det = m[0][0] * cofactor(1, 2, 1, 2) - m[0][1] * cofactor(1, 2, 0, 2)
+        + m[0][2] * cofactor(1, 2, 0, 1);
+    let inv_det = 1.0 / det;
+    [
+        [
+            cofactor(1, 2, 1, 2) * inv_det,
+            -cofactor(0, 2, 1, 2) * inv_det,
+            cofactor(0, 1, 1, 2) * inv_det,
+        ],
+        [
+            -cofactor(1, 2, 0, 2) * inv_det,
+            cofactor(0, 2, 0, 2) * inv_det,
+            -cofactor(0, 1, 0, 2) * inv_det,
+        ],
+        [
+            cofactor(1, 2, 0, 1) * inv_det,
+            -cofactor(0, 2, 0, 1) * inv_det,
+            cofactor(0, 1, 0, 1) * inv_det,
+        ],
+    ]
+}
+
+// Arithmetic
+
+impl Add<SampledSpectrum> for SampledSpectrum {
+    type Output = SampledSpectrum;
+
+    fn add(mut self, rhs: SampledSpectrum) -> Self::Output {
+        self += rhs;
+        self
+    }
+}
+
+impl AddAssign<SampledSpectrum> for SampledSpectrum {
+    fn add_assign(&mut self, rhs: SampledSpectrum) {
+        for (left, right) in self.samples.iter_mut().zip(&rhs.samples) {
+            *left += right
+        }
+    }
+}
+
+impl Mul<SampledSpectrum> for SampledSpectrum {
+    type Output = SampledSpectrum;
+
+    fn mul(mut self, rhs: SampledSpectrum) -> Self::Output {
+        self *= rhs;
+        self
+    }
+}
+
+impl MulAssign<SampledSpectrum> for SampledSpectrum {
+    fn mul_assign(&mut self, rhs: SampledSpectrum) {
+        for (left, right) in self.samples.iter_mut().zip(&rhs.samples) {
+            *left *= right
+        }
+    }
+}
+
+impl Mul<f32> for SampledSpectrum {
+    type Output = SampledSpectrum;
+
+    fn mul(mut self, rhs: f32) -> Self::Output {
+        self *= rhs;
+        self
+    }
+}
+
+impl MulAssign<f32> for SampledSpectrum {
+    fn mul_assign(&mut self, rhs: f32) {
+        for sample in self.samples.iter_mut() {
+            *sample *= rhs
+        }
+    }
+}
+
+impl Div<f32> for SampledSpectrum {
+    type Output = SampledSpectrum;
+
+    fn div(mut self, rhs: f32) -> Self::Output {
+        self /= rhs;
+        self
+    }
+}
+
+impl DivAssign<f32> for SampledSpectrum {
+    fn div_assign(&mut self, rhs: f32) {
+        for sample in self.samples.iter_mut() {
+            *sample /= rhs
+        }
+    }
+}
+
+#[cfg(test)]
+mod sampled_spectrum_tests {
+    use super::{SampledSpectrum, SAMPLE_COUNT};
+    use crate::{color::RgbaSpectrum, test::ApproxEq};
+
+    #[test]
+    fn single_wavelengths_average_to_spectrum() {
+        let spectrum = SampledSpectrum::from_rgb(&RgbaSpectrum::from_rgb(0.2, 0.5, 0.8));
+        let sum = (0..SAMPLE_COUNT).fold(SampledSpectrum::black(), |sum, i| {
+            sum + spectrum.single_wavelength(i)
+        });
+        for (average, sample) in (sum / SAMPLE_COUNT as f32)
+            .samples()
+            .iter()
+            .zip(spectrum.samples())
+        {
+            average.assert_approx_eq(sample);
+        }
+    }
+
+    #[test]
+    fn constant_spectrum_is_neutral() {
+        let rgb = SampledSpectrum::constant(0.5).to_rgb();
+        assert!((rgb.r() - 0.5).abs() < 1e-3);
+        assert!((rgb.g() - 0.5).abs() < 1e-3);
+        assert!((rgb.b() - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn white_maps_to_constant_spectrum() {
+        let spectrum = SampledSpectrum::from_rgb(&RgbaSpectrum::constant(1.0));
+        for sample in spectrum.samples() {
+            assert!((sample - 1.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn rgb_round_trip() {
+        let color = RgbaSpectrum::from_rgb(0.6, 0.4, 0.2);
+        let rgb = SampledSpectrum::from_rgb(&color).to_rgb();
+        assert!((rgb.r() - 0.6).abs() < 1e-3);
+        assert!((rgb.g() - 0.4).abs() < 1e-3);
+        assert!((rgb.b() - 0.2).abs() < 1e-3);
+        assert!(rgb.a().approx_eq(&1.0));
+    }
+}
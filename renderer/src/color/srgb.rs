@@ -0,0 +1,34 @@
+/// Encode a linear color component with the sRGB transfer function, which
+/// spends more of the encoded range on dark values, as displays expect.
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Decode an sRGB encoded color component into a linear component.
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod srgb_tests {
+    use super::{linear_to_srgb, srgb_to_linear};
+
+    #[test]
+    fn encodes_midtones_brighter() {
+        assert_eq!(linear_to_srgb(0.0), 0.0);
+        assert!((linear_to_srgb(1.0) - 1.0).abs() < 1e-6);
+        assert!((linear_to_srgb(0.5) - 0.7354).abs() < 1e-4);
+        assert!((linear_to_srgb(0.18) - 0.4614).abs() < 1e-4);
+        for c in [0.001, 0.01, 0.2, 0.5, 0.9] {
+            assert!((srgb_to_linear(linear_to_srgb(c)) - c).abs() < 1e-5);
+        }
+    }
+}
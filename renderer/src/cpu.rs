@@ -0,0 +1,80 @@
+//! Detects the SIMD instruction sets that are available on the CPU the
+//! renderer is running on, so that a single binary can choose the fastest
+//! kernels at runtime.
+
+use std::sync::OnceLock;
+
+/// The widest SIMD instruction set that the renderer can use on this CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimdLevel {
+    /// No SIMD instruction set that the renderer uses is available, so only
+    /// scalar code runs.
+    Scalar,
+
+    /// x86 SSE4.1, which operates on 4 lanes of `f32`.
+    Sse41,
+
+    /// x86 AVX2 with FMA, which operates on 8 lanes of `f32`.
+    Avx2,
+
+    /// ARM NEON, which operates on 4 lanes of `f32`.
+    Neon,
+}
+
+impl SimdLevel {
+    /// Return the SIMD level of the current CPU. Detection runs once, and the
+    /// result is reused afterwards.
+    ///
+    /// Kernels that have SIMD implementations, such as the one that rules out
+    /// the triangles a ray misses while traversing a bounding volume
+    /// hierarchy, match on this level to dispatch to the widest
+    /// implementation the CPU supports, falling back to scalar code.
+    pub fn current() -> Self {
+        static LEVEL: OnceLock<SimdLevel> = OnceLock::new();
+        *LEVEL.get_or_init(Self::detect)
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn detect() -> Self {
+        if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+            SimdLevel::Avx2
+        } else if is_x86_feature_detected!("sse4.1") {
+            SimdLevel::Sse41
+        } else {
+            SimdLevel::Scalar
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    fn detect() -> Self {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            SimdLevel::Neon
+        } else {
+            SimdLevel::Scalar
+        }
+    }
+
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+    fn detect() -> Self {
+        SimdLevel::Scalar
+    }
+
+    /// Return the number of `f32` lanes in a vector register at this level.
+    pub fn f32_lanes(&self) -> usize {
+        match self {
+            SimdLevel::Scalar => 1,
+            SimdLevel::Sse41 | SimdLevel::Neon => 4,
+            SimdLevel::Avx2 => 8,
+        }
+    }
+
+    /// Return the conventional name of the instruction set.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SimdLevel::Scalar => "scalar",
+            SimdLevel::Sse41 => "SSE4.1",
+            SimdLevel::Avx2 => "AVX2",
+            SimdLevel::Neon => "NEON",
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{color::RgbaSpectrum, integrator::Aov};
+
+/// The accumulated samples of an arbitrary output variable (AOV) for each pixel
+/// of a film or film tile, in row-major order.
+///
+/// AOV values aren't colors, so unlike the film's radiance they're stored as
+/// they are rather than converted to XYZ.
+#[derive(Debug, Clone)]
+pub(crate) struct AovBuffer {
+    pub aov: Aov,
+
+    /// The filter-weighted sum of the values of the samples that have
+    /// contributed to each pixel.
+    weighted_sums: Vec<RgbaSpectrum>,
+
+    /// The sum of the filter weights of the samples that have contributed to
+    /// each pixel.
+    filter_weight_sums: Vec<f32>,
+}
+
+impl AovBuffer {
+    pub fn new(aov: Aov, pixel_count: usize) -> Self {
+        Self {
+            aov,
+            weighted_sums: vec![RgbaSpectrum::transparent(); pixel_count],
+            filter_weight_sums: vec![0.0; pixel_count],
+        }
+    }
+
+    /// Add a sample's value to the pixel at the index.
+    pub fn add(&mut self, index: usize, value: &RgbaSpectrum, filter_weight: f32) {
+        self.weighted_sums[index] += filter_weight * *value;
+        self.filter_weight_sums[index] += filter_weight;
+    }
+
+    /// Add the sums of a pixel in another buffer for the same AOV to the pixel
+    /// at the index.
+    pub fn merge(&mut self, index: usize, other: &AovBuffer, other_index: usize) {
+        self.weighted_sums[index] += other.weighted_sums[other_index];
+        self.filter_weight_sums[index] += other.filter_weight_sums[other_index];
+    }
+
+    /// Return the final value of each pixel. Pixels that no samples have
+    /// contributed to are transparent.
+    pub fn to_rgba(&self) -> Vec<RgbaSpectrum> {
+        self.weighted_sums
+            .iter()
+            .zip(&self.filter_weight_sums)
+            .map(|(sum, weight_sum)| {
+                if *weight_sum > 0.0 {
+                    *sum / *weight_sum
+                } else {
+                    RgbaSpectrum::transparent()
+                }
+            })
+            .collect()
+    }
+}
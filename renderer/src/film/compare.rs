@@ -0,0 +1,344 @@
+//! Error metrics for comparing two renders of the same image, such as a render
+//! and a converged reference, so that changes to materials, integrators, and
+//! samplers can be validated quantitatively.
+//!
+//! Films are compared by their final linear RGB colors, before they are
+//! post-processed or encoded. Decoded images are compared by the values that
+//! they store. Every function accepts either, and panics if the resolutions of
+//! the two images differ.
+
+use super::Film;
+use crate::color::{ColorRamp, RgbaSpectrum};
+use cgmath::Vector2;
+use image::{DynamicImage, ImageBuffer, Rgba};
+
+/// The standard deviation, in pixels, of the Gaussian window that SSIM
+/// compares the neighborhood of each pixel with.
+const SSIM_SIGMA: f32 = 1.5;
+
+/// The radius, in pixels, at which the Gaussian window is truncated.
+const SSIM_RADIUS: usize = 5;
+
+/// The constants that stabilize SSIM's division where the neighborhoods are
+/// nearly black or flat, for values that range from zero to one.
+const SSIM_C1: f32 = 0.01 * 0.01;
+const SSIM_C2: f32 = 0.03 * 0.03;
+
+/// The colors of an image's pixels in row-major order, starting at the
+/// top-left pixel.
+pub struct Pixels {
+    pub resolution: Vector2<usize>,
+    pub colors: Vec<RgbaSpectrum>,
+}
+
+impl Pixels {
+    /// Create the pixels of an image.
+    ///
+    /// * colors - The color of each pixel in row-major order. There must be
+    ///   one for each pixel.
+    pub fn new(resolution: Vector2<usize>, colors: Vec<RgbaSpectrum>) -> Self {
+        assert_eq!(
+            resolution.x * resolution.y,
+            colors.len(),
+            "There must be one color for each pixel."
+        );
+        Self { resolution, colors }
+    }
+}
+
+impl From<&Film> for Pixels {
+    fn from(film: &Film) -> Self {
+        Self::new(film.resolution, film.to_rgba())
+    }
+}
+
+impl From<&DynamicImage> for Pixels {
+    /// Return the values stored in the image, scaled to [0, 1]. They aren't
+    /// decoded, so an sRGB image is compared as it's displayed.
+    fn from(image: &DynamicImage) -> Self {
+        let image = image.to_rgba16();
+        let scale = 1.0 / u16::MAX as f32;
+        let colors = image
+            .pixels()
+            .map(|pixel| {
+                let [r, g, b, a] = pixel.0;
+                RgbaSpectrum::from_rgba(
+                    r as f32 * scale,
+                    g as f32 * scale,
+                    b as f32 * scale,
+                    a as f32 * scale,
+                )
+            })
+            .collect();
+        Self::new(
+            Vector2::new(image.width() as usize, image.height() as usize),
+            colors,
+        )
+    }
+}
+
+/// Return the mean squared error between the red, green, and blue components
+/// of the two images' pixels.
+pub fn mse(a: impl Into<Pixels>, b: impl Into<Pixels>) -> f32 {
+    let squared_errors = squared_errors(a, b);
+    if squared_errors.is_empty() {
+        0.0
+    } else {
+        squared_errors.iter().sum::<f32>() / squared_errors.len() as f32
+    }
+}
+
+/// Return the root mean squared error between the red, green, and blue
+/// components of the two images' pixels.
+pub fn rmse(a: impl Into<Pixels>, b: impl Into<Pixels>) -> f32 {
+    mse(a, b).sqrt()
+}
+
+/// Return the peak signal-to-noise ratio between the two images in decibels,
+/// with a peak value of one. Higher is better, and identical images have an
+/// infinite ratio.
+pub fn psnr(a: impl Into<Pixels>, b: impl Into<Pixels>) -> f32 {
+    let mse = mse(a, b);
+    if mse == 0.0 {
+        f32::INFINITY
+    } else {
+        -10.0 * mse.log10()
+    }
+}
+
+/// Return the mean structural similarity (SSIM) of the two images'
+/// luminances, which measures how closely their local structure, contrast,
+/// and brightness match. It ranges up to one, for identical images, and
+/// unlike the squared error it's less sensitive to a uniform shift in
+/// brightness than to noise and blur.
+pub fn ssim(a: impl Into<Pixels>, b: impl Into<Pixels>) -> f32 {
+    let similarities = ssim_map(a, b);
+    if similarities.is_empty() {
+        1.0
+    } else {
+        similarities.iter().sum::<f32>() / similarities.len() as f32
+    }
+}
+
+/// Return the structural similarity of the neighborhood of each pixel, in
+/// row-major order. Each neighborhood is weighted by a Gaussian window that's
+/// truncated at the image's edges.
+pub fn ssim_map(a: impl Into<Pixels>, b: impl Into<Pixels>) -> Vec<f32> {
+    let (a, b) = pixels(a, b);
+    let resolution = a.resolution;
+    let la: Vec<f32> = a.colors.iter().map(|c| c.luminance()).collect();
+    let lb: Vec<f32> = b.colors.iter().map(|c| c.luminance()).collect();
+    let window = gaussian_window();
+    let blur = |values: Vec<f32>| blur(&values, resolution, &window);
+
+    let mean_a = blur(la.clone());
+    let mean_b = blur(lb.clone());
+    let mean_aa = blur(la.iter().map(|a| a * a).collect());
+    let mean_bb = blur(lb.iter().map(|b| b * b).collect());
+    let mean_ab = blur(la.iter().zip(&lb).map(|(a, b)| a * b).collect());
+    (0..la.len())
+        .map(|i| {
+            let (ma, mb) = (mean_a[i], mean_b[i]);
+            let variance_a = (mean_aa[i] - ma * ma).max(0.0);
+            let variance_b = (mean_bb[i] - mb * mb).max(0.0);
+            let covariance = mean_ab[i] - ma * mb;
+            ((2.0 * ma * mb + SSIM_C1) * (2.0 * covariance + SSIM_C2))
+                / ((ma * ma + mb * mb + SSIM_C1) * (variance_a + variance_b + SSIM_C2))
+        })
+        .collect()
+}
+
+/// Return the mean squared error of each pixel's red, green, and blue
+/// components, in row-major order.
+pub fn squared_errors(a: impl Into<Pixels>, b: impl Into<Pixels>) -> Vec<f32> {
+    let (a, b) = pixels(a, b);
+    a.colors
+        .iter()
+        .zip(b.colors.iter())
+        .map(|(a, b)| {
+            let d = *a - *b;
+            (d.r() * d.r() + d.g() * d.g() + d.b() * d.b()) / 3.0
+        })
+        .collect()
+}
+
+/// Write an opaque image of the absolute difference between the two images'
+/// pixels, where black means the pixels match.
+///
+/// * scale - The factor that differences are multiplied by before they're
+///   written, so that small differences are visible.
+pub fn difference_image(
+    a: impl Into<Pixels>,
+    b: impl Into<Pixels>,
+    scale: f32,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let (a, b) = pixels(a, b);
+    let differences: Vec<RgbaSpectrum> = a
+        .colors
+        .iter()
+        .zip(b.colors.iter())
+        .map(|(a, b)| {
+            let d = *a - *b;
+            RgbaSpectrum::from_rgba(
+                d.r().abs() * scale,
+                d.g().abs() * scale,
+                d.b().abs() * scale,
+                1.0,
+            )
+        })
+        .collect();
+    to_image(a.resolution, &differences)
+}
+
+/// Write an opaque false-color image of the root mean squared error of each
+/// pixel, mapped through the ramp, so that where two renders differ and by how
+/// much can be seen at a glance.
+///
+/// * max_error - The error that's mapped to the end of the ramp. Defaults to
+///   the greatest error in the image. A fixed value keeps the colors of heat
+///   maps of different renders comparable.
+pub fn heat_map(
+    a: impl Into<Pixels>,
+    b: impl Into<Pixels>,
+    ramp: &ColorRamp,
+    max_error: Option<f32>,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let (a, b) = pixels(a, b);
+    let resolution = a.resolution;
+    let errors: Vec<f32> = squared_errors(a, b).iter().map(|e| e.sqrt()).collect();
+    let max_error = max_error.unwrap_or_else(|| errors.iter().copied().fold(0.0, f32::max));
+    let colors: Vec<RgbaSpectrum> = errors
+        .iter()
+        .map(|error| {
+            let mut color = if max_error > 0.0 {
+                ramp.eval(error / max_error)
+            } else {
+                ramp.eval(0.0)
+            };
+            color.set_a(1.0);
+            color
+        })
+        .collect();
+    to_image(resolution, &colors)
+}
+
+/// Convert both images to pixels, and check that they can be compared.
+fn pixels(a: impl Into<Pixels>, b: impl Into<Pixels>) -> (Pixels, Pixels) {
+    let (a, b) = (a.into(), b.into());
+    assert_eq!(
+        a.resolution, b.resolution,
+        "Only images with the same resolution can be compared."
+    );
+    (a, b)
+}
+
+fn to_image(resolution: Vector2<usize>, colors: &[RgbaSpectrum]) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    ImageBuffer::from_fn(resolution.x as u32, resolution.y as u32, |x, y| {
+        colors[y as usize * resolution.x + x as usize].into()
+    })
+}
+
+/// Return the normalized weights of the one-dimensional Gaussian window,
+/// starting at the offset `-SSIM_RADIUS`.
+fn gaussian_window() -> Vec<f32> {
+    let weights: Vec<f32> = (0..=2 * SSIM_RADIUS)
+        .map(|i| {
+            let offset = i as f32 - SSIM_RADIUS as f32;
+            (-offset * offset / (2.0 * SSIM_SIGMA * SSIM_SIGMA)).exp()
+        })
+        .collect();
+    let sum: f32 = weights.iter().sum();
+    weights.iter().map(|w| w / sum).collect()
+}
+
+/// Return the weighted mean of the neighborhood of each value, blurring the
+/// rows and then the columns with the window. Weights that fall outside of
+/// the image are dropped, and the rest are renormalized.
+fn blur(values: &[f32], resolution: Vector2<usize>, window: &[f32]) -> Vec<f32> {
+    let blur_1d = |values: &[f32], step: Vector2<usize>| -> Vec<f32> {
+        (0..values.len())
+            .map(|i| {
+                let (x, y) = (i % resolution.x, i / resolution.x);
+                let (mut sum, mut weight_sum) = (0.0, 0.0);
+                for (k, weight) in window.iter().enumerate() {
+                    let offset = k as isize - SSIM_RADIUS as isize;
+                    let nx = x as isize + offset * step.x as isize;
+                    let ny = y as isize + offset * step.y as isize;
+                    if nx < 0
+                        || ny < 0
+                        || nx >= resolution.x as isize
+                        || ny >= resolution.y as isize
+                    {
+                        continue;
+                    }
+                    sum += weight * values[ny as usize * resolution.x + nx as usize];
+                    weight_sum += weight;
+                }
+                sum / weight_sum
+            })
+            .collect()
+    };
+    let rows = blur_1d(values, Vector2::new(1, 0));
+    blur_1d(&rows, Vector2::new(0, 1))
+}
+
+#[cfg(test)]
+mod compare_tests {
+    use super::{difference_image, heat_map, mse, psnr, rmse, ssim};
+    use crate::{
+        color::{ColorRamp, RgbaSpectrum},
+        film::Film,
+        filter::BoxFilter,
+        geometry::bounds::Bounds2,
+    };
+    use cgmath::{Point2, Vector2};
+
+    /// Return a film in which each pixel has the color returned by `color`.
+    fn film_from_fn(color: impl Fn(i32, i32) -> f32) -> Film {
+        let mut film = Film::new(Vector2::new(4, 4));
+        let filter = BoxFilter::new(0.5, 0.5);
+        let bounds = Bounds2::new(Point2::new(0, 0), Point2::new(4, 4));
+        let mut tile = film.tile(&bounds, 0.5, 0.5).unwrap();
+        for p in bounds.range() {
+            let center = Point2::new(p.x as f32 + 0.5, p.y as f32 + 0.5);
+            let mut radiance = RgbaSpectrum::constant(color(p.x, p.y));
+            radiance.set_a(1.0);
+            tile.add_sample(&center, &radiance, 1.0, &filter);
+        }
+        film.merge_tile(&tile);
+        film
+    }
+
+    #[test]
+    fn measures_differences_between_films() {
+        let a = film_from_fn(|_, _| 0.5);
+        assert_eq!(mse(&a, &a), 0.0);
+
+        // Only one of the sixteen pixels differs, by 0.4 in each component.
+        let b = film_from_fn(|x, y| if (x, y) == (1, 2) { 0.9 } else { 0.5 });
+        assert!((mse(&a, &b) - 0.16 / 16.0).abs() < 1e-5);
+        assert!((rmse(&a, &b) - 0.1).abs() < 1e-4);
+
+        let image = difference_image(&a, &b, 2.0);
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert!(image.get_pixel(1, 2).0[0] >= 204);
+    }
+
+    #[test]
+    fn psnr_and_ssim_rank_noise_and_shifts() {
+        let a = film_from_fn(|x, y| (x + y) as f32 / 6.0);
+        assert_eq!(psnr(&a, &a), f32::INFINITY);
+        assert!((ssim(&a, &a) - 1.0).abs() < 1e-5);
+
+        // Every component differs by 0.1, so the squared error is 0.01.
+        let shifted = film_from_fn(|x, y| (x + y) as f32 / 6.0 + 0.1);
+        assert!((psnr(&a, &shifted) - 20.0).abs() < 1e-3);
+        let noisy =
+            film_from_fn(|x, y| (x + y) as f32 / 6.0 + if (x + y) % 2 == 0 { 0.1 } else { -0.1 });
+        assert!(ssim(&a, &noisy) < ssim(&a, &shifted));
+
+        let ramp = ColorRamp::default();
+        let image = heat_map(&a, &shifted, &ramp, Some(0.2));
+        assert_eq!(image.get_pixel(0, 0).0, [0, 255, 0, 255]);
+    }
+}
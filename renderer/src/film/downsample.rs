@@ -0,0 +1,121 @@
+use crate::{color::RgbaSpectrum, filter::Filter};
+use cgmath::{Point2, Vector2};
+
+/// Reduces an image that was rendered at a multiple of its output resolution,
+/// which gives crisp silhouettes without raising the number of samples per
+/// pixel.
+pub struct Downsampling {
+    factor: usize,
+    filter: Box<dyn Filter + Send + Sync>,
+}
+
+impl Downsampling {
+    /// * factor - The number of rendered pixels along each axis of an output
+    ///   pixel.
+    /// * filter - Weights each rendered pixel by the offset of its center from
+    ///   the output pixel's center. The offset is measured in output pixels,
+    ///   so a box filter with a half width of 0.5 averages the rendered pixels
+    ///   that make up each output pixel.
+    pub fn new(factor: usize, filter: Box<dyn Filter + Send + Sync>) -> Self {
+        Self {
+            factor: factor.max(1),
+            filter,
+        }
+    }
+
+    pub fn factor(&self) -> usize {
+        self.factor
+    }
+
+    /// Return the resolution of an image that's downsampled from an image
+    /// with the given resolution.
+    pub fn output_resolution(&self, resolution: Vector2<usize>) -> Vector2<usize> {
+        resolution / self.factor
+    }
+
+    /// Downsample the pixels, which are in row-major order, starting at the
+    /// top-left pixel. Colors are filtered along with alpha, so they should
+    /// be premultiplied.
+    pub fn apply(&self, pixels: &[RgbaSpectrum], resolution: Vector2<usize>) -> Vec<RgbaSpectrum> {
+        let output_resolution = self.output_resolution(resolution);
+        let factor = self.factor as f32;
+        let reach_x = self.filter.half_width() * factor;
+        let reach_y = self.filter.half_height() * factor;
+        let mut output = Vec::with_capacity(output_resolution.x * output_resolution.y);
+        for y in 0..output_resolution.y {
+            for x in 0..output_resolution.x {
+                // The output pixel's center in the rendered image's raster
+                // space.
+                let center_x = (x as f32 + 0.5) * factor;
+                let center_y = (y as f32 + 0.5) * factor;
+                let min_x = (center_x - reach_x).floor().max(0.0) as usize;
+                let min_y = (center_y - reach_y).floor().max(0.0) as usize;
+                let max_x = ((center_x + reach_x).ceil() as usize).min(resolution.x);
+                let max_y = ((center_y + reach_y).ceil() as usize).min(resolution.y);
+
+                let mut sum = RgbaSpectrum::transparent();
+                let mut weight_sum = 0.0;
+                for source_y in min_y..max_y {
+                    for source_x in min_x..max_x {
+                        let offset = Point2::new(
+                            (source_x as f32 + 0.5 - center_x) / factor,
+                            (source_y as f32 + 0.5 - center_y) / factor,
+                        );
+                        if offset.x.abs() > self.filter.half_width()
+                            || offset.y.abs() > self.filter.half_height()
+                        {
+                            continue;
+                        }
+                        let weight = self.filter.eval_at(offset);
+                        sum += pixels[source_y * resolution.x + source_x] * weight;
+                        weight_sum += weight;
+                    }
+                }
+                output.push(if weight_sum != 0.0 {
+                    sum / weight_sum
+                } else {
+                    RgbaSpectrum::transparent()
+                });
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod downsample_tests {
+    use super::Downsampling;
+    use crate::{
+        color::RgbaSpectrum,
+        filter::{BoxFilter, MitchellFilter},
+    };
+    use cgmath::Vector2;
+
+    #[test]
+    fn box_filter_averages_each_block() {
+        let resolution = Vector2::new(4, 2);
+        let pixels: Vec<RgbaSpectrum> = (0..8)
+            .map(|i| RgbaSpectrum::from_rgba(i as f32, 0.0, 0.0, 1.0))
+            .collect();
+        let downsampling = Downsampling::new(2, Box::new(BoxFilter::new(0.5, 0.5)));
+        let output = downsampling.apply(&pixels, resolution);
+        assert_eq!(output.len(), 2);
+        // The left block holds pixels 0, 1, 4, and 5.
+        assert!((output[0].r() - 2.5).abs() < 1e-5);
+        assert!((output[1].r() - 4.5).abs() < 1e-5);
+        assert!((output[1].a() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn constant_image_stays_constant() {
+        let resolution = Vector2::new(9, 9);
+        let pixels = vec![RgbaSpectrum::from_rgba(0.25, 0.5, 0.75, 1.0); 81];
+        let filter = MitchellFilter::new(2.0, 2.0, 1.0 / 3.0, 1.0 / 3.0);
+        let output = Downsampling::new(3, Box::new(filter)).apply(&pixels, resolution);
+        assert_eq!(output.len(), 9);
+        for pixel in output {
+            assert!((pixel.g() - 0.5).abs() < 1e-5);
+            assert!((pixel.a() - 1.0).abs() < 1e-5);
+        }
+    }
+}
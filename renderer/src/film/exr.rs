@@ -0,0 +1,182 @@
+//! Writes films to OpenEXR files, so that their linear, unclamped colors can
+//! be adjusted in compositing software.
+
+use super::Film;
+use std::io::{self, Write};
+
+/// The EXR pixel type of 32-bit floating point channels.
+const FLOAT_PIXEL_TYPE: i32 = 2;
+
+/// Write the films as layers of a single uncompressed, scanline EXR image.
+/// Each layer has red, green, blue, and alpha channels, named with the
+/// layer's name as a prefix, such as `key.R`. A layer with an empty name is
+/// written to the unprefixed `R`, `G`, `B`, and `A` channels.
+///
+/// The channels hold the films' linear colors, before any post-processing.
+///
+/// * writer - The destination of the EXR file.
+/// * layers - Pairs of layer names and films. The films must all have the
+///   same output resolution, and downsampled films are written at it.
+pub fn write_exr_layers<W: Write>(writer: &mut W, layers: &[(&str, &Film)]) -> io::Result<()> {
+    let resolution = match layers.first() {
+        Some((_, film)) => film.output_resolution(),
+        None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "no layers")),
+    };
+    if layers
+        .iter()
+        .any(|(_, film)| film.output_resolution() != resolution)
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "layers have different resolutions",
+        ));
+    }
+    let (width, height) = (resolution.x, resolution.y);
+
+    // EXR requires channels to be sorted by name, both in the header and in
+    // each scanline.
+    let layer_pixels: Vec<_> = layers
+        .iter()
+        .map(|(_, film)| film.downsample(film.to_rgba()))
+        .collect();
+    let mut channels: Vec<(String, usize, usize)> = layers
+        .iter()
+        .enumerate()
+        .flat_map(|(layer_index, (name, _))| {
+            ["R", "G", "B", "A"]
+                .iter()
+                .enumerate()
+                .map(move |(component, suffix)| {
+                    let channel_name = if name.is_empty() {
+                        suffix.to_string()
+                    } else {
+                        format!("{}.{}", name, suffix)
+                    };
+                    (channel_name, layer_index, component)
+                })
+        })
+        .collect();
+    channels.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut header = vec![];
+    header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01]);
+    // Version 2, single-part scanline image. Long names are allowed since
+    // layer names can make channel names longer than 31 bytes.
+    header.extend_from_slice(&(2i32 | 0x400).to_le_bytes());
+
+    let mut channel_list = vec![];
+    for (name, _, _) in &channels {
+        channel_list.extend_from_slice(name.as_bytes());
+        channel_list.push(0);
+        channel_list.extend_from_slice(&FLOAT_PIXEL_TYPE.to_le_bytes());
+        // pLinear and three reserved bytes.
+        channel_list.extend_from_slice(&[0, 0, 0, 0]);
+        // The x and y sampling rates.
+        channel_list.extend_from_slice(&1i32.to_le_bytes());
+        channel_list.extend_from_slice(&1i32.to_le_bytes());
+    }
+    channel_list.push(0);
+    write_attribute(&mut header, "channels", "chlist", &channel_list);
+    // No compression.
+    write_attribute(&mut header, "compression", "compression", &[0]);
+    let mut window = vec![];
+    for value in &[0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend_from_slice(&value.to_le_bytes());
+    }
+    write_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_attribute(&mut header, "displayWindow", "box2i", &window);
+    // Increasing y.
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    write_attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1.0f32.to_le_bytes(),
+    );
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    write_attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1.0f32.to_le_bytes(),
+    );
+    header.push(0);
+
+    // Each scanline is stored in its own block, which starts with the line's
+    // y coordinate and the size of its pixel data.
+    let block_data_size = channels.len() * width * 4;
+    let block_size = 8 + block_data_size;
+    let first_block_offset = header.len() + height * 8;
+    writer.write_all(&header)?;
+    for y in 0..height {
+        let offset = (first_block_offset + y * block_size) as u64;
+        writer.write_all(&offset.to_le_bytes())?;
+    }
+
+    let mut block = Vec::with_capacity(block_size);
+    for y in 0..height {
+        block.clear();
+        block.extend_from_slice(&(y as i32).to_le_bytes());
+        block.extend_from_slice(&(block_data_size as i32).to_le_bytes());
+        for (_, layer_index, component) in &channels {
+            for x in 0..width {
+                let pixel = layer_pixels[*layer_index][y * width + x];
+                let value = match component {
+                    0 => pixel.r(),
+                    1 => pixel.g(),
+                    2 => pixel.b(),
+                    _ => pixel.a(),
+                };
+                block.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        writer.write_all(&block)?;
+    }
+    Ok(())
+}
+
+fn write_attribute(header: &mut Vec<u8>, name: &str, type_name: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(type_name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod exr_tests {
+    use super::write_exr_layers;
+    use crate::film::Film;
+    use cgmath::Vector2;
+    use std::convert::TryInto;
+
+    #[test]
+    fn writes_a_channel_for_each_layer_component() {
+        let key = Film::new(Vector2::new(3, 2));
+        let fill = Film::new(Vector2::new(3, 2));
+        let mut bytes = vec![];
+        write_exr_layers(&mut bytes, &[("key", &key), ("fill", &fill)]).unwrap();
+
+        assert_eq!(&bytes[..4], &[0x76, 0x2f, 0x31, 0x01]);
+        let contains = |needle: &[u8]| bytes.windows(needle.len()).any(|w| w == needle);
+        assert!(contains(b"fill.A\0"));
+        assert!(contains(b"key.R\0"));
+
+        // Two rows, each with 8 channels of 3 pixels, plus the rows' offsets
+        // and block headers, follow the header.
+        let pixel_bytes = 2 * (8 + 8 * 3 * 4);
+        let header_end = bytes.len() - pixel_bytes - 2 * 8;
+        let first_offset =
+            u64::from_le_bytes(bytes[header_end..header_end + 8].try_into().unwrap());
+        assert_eq!(first_offset as usize, header_end + 16);
+    }
+
+    #[test]
+    fn rejects_layers_with_different_resolutions() {
+        let a = Film::new(Vector2::new(3, 2));
+        let b = Film::new(Vector2::new(2, 2));
+        let mut bytes = vec![];
+        assert!(write_exr_layers(&mut bytes, &[("a", &a), ("b", &b)]).is_err());
+    }
+}
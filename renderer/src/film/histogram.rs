@@ -0,0 +1,152 @@
+//! A histogram of the brightness of a finished image, which shows whether
+//! the lights are too strong or too weak before the image is re-rendered.
+//!
+//! Pixels are measured after they've been post-processed and encoded, so that
+//! clipping reflects what is actually written to an 8-bit image.
+
+use crate::color::RgbaSpectrum;
+use cgmath::Vector2;
+use image::{ImageBuffer, Rgba};
+
+/// Components at or above this value are written as the greatest 8-bit value.
+const CLIP_THRESHOLD: f32 = 255.0 / 256.0;
+
+/// Components below this value are written as zero.
+const CRUSH_THRESHOLD: f32 = 1.0 / 256.0;
+
+/// Counts the pixels that surfaces cover by their luma.
+#[derive(Debug, Clone)]
+pub struct LuminanceHistogram {
+    /// The number of covered pixels whose luma falls in each bin. The bins
+    /// divide [0, 1] evenly, and the last bin also holds brighter pixels.
+    pub bins: Vec<usize>,
+
+    /// The number of pixels that are at least partially covered.
+    pub covered_pixels: usize,
+
+    /// The number of covered pixels with a red, green, or blue component that
+    /// is blown out to the greatest output value.
+    pub clipped_pixels: usize,
+
+    /// The number of covered pixels whose red, green, and blue components are
+    /// all crushed to zero.
+    pub crushed_pixels: usize,
+}
+
+impl LuminanceHistogram {
+    /// * pixels - Post-processed and encoded pixels with premultiplied alpha,
+    ///   such as those returned by `Film::post_processed_pixels`. Transparent
+    ///   pixels are ignored.
+    /// * bin_count - The number of bins, which must be positive.
+    pub fn new(pixels: &[RgbaSpectrum], bin_count: usize) -> Self {
+        let mut histogram = Self {
+            bins: vec![0; bin_count],
+            covered_pixels: 0,
+            clipped_pixels: 0,
+            crushed_pixels: 0,
+        };
+        for pixel in pixels {
+            let (r, g, b) = match unpremultiplied(pixel) {
+                Some(rgb) => rgb,
+                None => continue,
+            };
+            histogram.covered_pixels += 1;
+            let luma = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+            let bin = ((luma.max(0.0) * bin_count as f32) as usize).min(bin_count - 1);
+            histogram.bins[bin] += 1;
+            if is_clipped(r, g, b) {
+                histogram.clipped_pixels += 1;
+            } else if is_crushed(r, g, b) {
+                histogram.crushed_pixels += 1;
+            }
+        }
+        histogram
+    }
+
+    /// Return the fraction of covered pixels that are blown out.
+    pub fn clipped_fraction(&self) -> f32 {
+        self.fraction(self.clipped_pixels)
+    }
+
+    /// Return the fraction of covered pixels that are crushed to black.
+    pub fn crushed_fraction(&self) -> f32 {
+        self.fraction(self.crushed_pixels)
+    }
+
+    fn fraction(&self, pixel_count: usize) -> f32 {
+        if self.covered_pixels == 0 {
+            0.0
+        } else {
+            pixel_count as f32 / self.covered_pixels as f32
+        }
+    }
+}
+
+/// Write an image that marks blown out pixels in red and crushed pixels in
+/// blue. Other covered pixels are shown in gray at their luma, and pixels
+/// that aren't covered are transparent.
+///
+/// * pixels - Post-processed and encoded pixels with premultiplied alpha in
+///   row-major order, starting at the top-left pixel.
+/// * resolution - The resolution of the image that the pixels make up.
+pub fn clipping_overlay(
+    pixels: &[RgbaSpectrum],
+    resolution: Vector2<usize>,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    ImageBuffer::from_fn(resolution.x as u32, resolution.y as u32, |x, y| {
+        let pixel = &pixels[y as usize * resolution.x + x as usize];
+        let color = match unpremultiplied(pixel) {
+            Some((r, g, b)) if is_clipped(r, g, b) => RgbaSpectrum::from_rgba(1.0, 0.0, 0.0, 1.0),
+            Some((r, g, b)) if is_crushed(r, g, b) => RgbaSpectrum::from_rgba(0.0, 0.0, 1.0, 1.0),
+            Some((r, g, b)) => RgbaSpectrum::constant(0.2126 * r + 0.7152 * g + 0.0722 * b),
+            None => RgbaSpectrum::transparent(),
+        };
+        color.into()
+    })
+}
+
+/// Return the pixel's red, green, and blue components as if it were fully
+/// covered, or `None` if it isn't covered at all. Alpha above one is treated
+/// as full coverage.
+fn unpremultiplied(pixel: &RgbaSpectrum) -> Option<(f32, f32, f32)> {
+    let coverage = pixel.a().min(1.0);
+    if coverage <= 0.0 {
+        return None;
+    }
+    Some((
+        pixel.r() / coverage,
+        pixel.g() / coverage,
+        pixel.b() / coverage,
+    ))
+}
+
+fn is_clipped(r: f32, g: f32, b: f32) -> bool {
+    r.max(g).max(b) >= CLIP_THRESHOLD
+}
+
+fn is_crushed(r: f32, g: f32, b: f32) -> bool {
+    r.max(g).max(b) < CRUSH_THRESHOLD
+}
+
+#[cfg(test)]
+mod histogram_tests {
+    use super::LuminanceHistogram;
+    use crate::color::RgbaSpectrum;
+
+    #[test]
+    fn counts_clipped_and_crushed_pixels() {
+        let pixels = [
+            RgbaSpectrum::from_rgba(2.0, 0.5, 0.5, 1.0),
+            RgbaSpectrum::from_rgba(0.0, 0.0, 0.0, 1.0),
+            RgbaSpectrum::from_rgba(0.25, 0.25, 0.25, 0.5),
+            RgbaSpectrum::transparent(),
+        ];
+        let histogram = LuminanceHistogram::new(&pixels, 4);
+        assert_eq!(histogram.covered_pixels, 3);
+        assert_eq!(histogram.clipped_pixels, 1);
+        assert_eq!(histogram.crushed_pixels, 1);
+        // The half-covered pixel's luma is 0.5 once it's un-premultiplied.
+        assert_eq!(histogram.bins, vec![1, 0, 1, 1]);
+        assert!((histogram.clipped_fraction() - 1.0 / 3.0).abs() < 1e-6);
+    }
+}
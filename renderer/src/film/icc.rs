@@ -0,0 +1,210 @@
+//! Builds ICC color profiles that describe the images written from a film, so
+//! that color-managed viewers display them the way they were rendered.
+//!
+//! The profiles are version 4 display profiles with a matrix and a tone curve
+//! for each channel. Their colorants are adapted to the D50 profile connection
+//! space with the Bradford transform, as the ICC specification requires.
+
+use super::{ColorSpace, TransferFunction};
+
+/// The D50 white point of the profile connection space.
+const D50: [f32; 3] = [0.9642, 1.0, 0.8249];
+
+/// The Bradford transform that adapts colors from the D65 white point of
+/// sRGB and Display P3 to D50.
+const D65_TO_D50: [f32; 9] = [
+    1.047_811, 0.022_887, -0.050_127, 0.029_542, 0.990_484, -0.017_049, -0.009_234, 0.015_044,
+    0.752_132,
+];
+
+/// The D50-adapted XYZ coordinates of the sRGB red, green, and blue
+/// primaries.
+const SRGB_COLORANTS: [[f32; 3]; 3] = [
+    [0.436_075, 0.222_504, 0.013_932],
+    [0.385_065, 0.716_879, 0.097_105],
+    [0.143_080, 0.060_617, 0.714_173],
+];
+
+/// The D50-adapted XYZ coordinates of the Display P3 red, green, and blue
+/// primaries.
+const DISPLAY_P3_COLORANTS: [[f32; 3]; 3] = [
+    [0.515_102, 0.241_182, -0.001_049],
+    [0.291_965, 0.692_236, 0.041_882],
+    [0.157_153, 0.066_591, 0.784_378],
+];
+
+/// Return an ICC profile for images whose colors are in the color space and
+/// are encoded with the transfer function.
+pub fn icc_profile(color_space: ColorSpace, transfer_function: TransferFunction) -> Vec<u8> {
+    let (name, colorants) = match color_space {
+        ColorSpace::Srgb => ("sRGB", SRGB_COLORANTS),
+        ColorSpace::DisplayP3 => ("Display P3", DISPLAY_P3_COLORANTS),
+    };
+    let (description, curve) = match transfer_function {
+        TransferFunction::Srgb => (name.to_string(), srgb_curve()),
+        TransferFunction::Linear => (format!("Linear {}", name), linear_curve()),
+    };
+
+    let tags: Vec<([u8; 4], Vec<u8>)> = vec![
+        (*b"desc", text(&description)),
+        (*b"cprt", text("No copyright, use freely")),
+        (*b"wtpt", xyz(D50)),
+        (*b"chad", s15_fixed16_array(&D65_TO_D50)),
+        (*b"rXYZ", xyz(colorants[0])),
+        (*b"gXYZ", xyz(colorants[1])),
+        (*b"bXYZ", xyz(colorants[2])),
+        (*b"rTRC", curve.clone()),
+        (*b"gTRC", curve.clone()),
+        (*b"bTRC", curve),
+    ];
+
+    // The tag data follows the header and the tag table, and each tag starts
+    // on a four byte boundary.
+    let mut tag_table = vec![];
+    let mut tag_data = vec![];
+    let data_start = 128 + 4 + 12 * tags.len();
+    for (signature, data) in &tags {
+        tag_table.extend_from_slice(signature);
+        push_u32(&mut tag_table, (data_start + tag_data.len()) as u32);
+        push_u32(&mut tag_table, data.len() as u32);
+        tag_data.extend_from_slice(data);
+        while tag_data.len() % 4 != 0 {
+            tag_data.push(0);
+        }
+    }
+
+    let mut profile = header((data_start + tag_data.len()) as u32);
+    push_u32(&mut profile, tags.len() as u32);
+    profile.extend(tag_table);
+    profile.extend(tag_data);
+    profile
+}
+
+/// Return the 128 byte profile header.
+fn header(profile_size: u32) -> Vec<u8> {
+    let mut header = vec![];
+    push_u32(&mut header, profile_size);
+    header.extend_from_slice(&[0; 4]); // Preferred CMM
+    header.extend_from_slice(&[4, 0x30, 0, 0]); // Version 4.3
+    header.extend_from_slice(b"mntr");
+    header.extend_from_slice(b"RGB ");
+    header.extend_from_slice(b"XYZ ");
+    // The creation date, as the year, month, day, hours, minutes, and
+    // seconds. It's fixed so that identical renders have identical files.
+    for field in &[2021u16, 1, 1, 0, 0, 0] {
+        header.extend_from_slice(&field.to_be_bytes());
+    }
+    header.extend_from_slice(b"acsp");
+    // The platform, flags, manufacturer, model, attributes, and rendering
+    // intent, which are all unspecified or zero.
+    header.extend_from_slice(&[0; 28]);
+    for component in &D50 {
+        push_s15_fixed16(&mut header, *component);
+    }
+    // The creator, profile ID, and reserved bytes. A zero profile ID means
+    // that it hasn't been computed.
+    header.extend_from_slice(&[0; 48]);
+    header
+}
+
+/// Return a `mluc` tag with a single English string.
+fn text(text: &str) -> Vec<u8> {
+    let utf16: Vec<u16> = text.encode_utf16().collect();
+    let mut data = vec![];
+    data.extend_from_slice(b"mluc");
+    data.extend_from_slice(&[0; 4]);
+    push_u32(&mut data, 1); // Record count
+    push_u32(&mut data, 12); // Record size
+    data.extend_from_slice(b"enUS");
+    push_u32(&mut data, 2 * utf16.len() as u32);
+    push_u32(&mut data, 28); // Offset of the string from the tag's start
+    for unit in utf16 {
+        data.extend_from_slice(&unit.to_be_bytes());
+    }
+    data
+}
+
+/// Return an `XYZ ` tag with a single XYZ value.
+fn xyz(xyz: [f32; 3]) -> Vec<u8> {
+    let mut data = vec![];
+    data.extend_from_slice(b"XYZ ");
+    data.extend_from_slice(&[0; 4]);
+    for component in &xyz {
+        push_s15_fixed16(&mut data, *component);
+    }
+    data
+}
+
+/// Return an `sf32` tag with the values.
+fn s15_fixed16_array(values: &[f32]) -> Vec<u8> {
+    let mut data = vec![];
+    data.extend_from_slice(b"sf32");
+    data.extend_from_slice(&[0; 4]);
+    for value in values {
+        push_s15_fixed16(&mut data, *value);
+    }
+    data
+}
+
+/// Return a `para` tag with the sRGB transfer function, which is shared by
+/// Display P3.
+fn srgb_curve() -> Vec<u8> {
+    parametric_curve(3, &[2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045])
+}
+
+/// Return a `para` tag with the identity function.
+fn linear_curve() -> Vec<u8> {
+    parametric_curve(0, &[1.0])
+}
+
+fn parametric_curve(function_type: u16, parameters: &[f32]) -> Vec<u8> {
+    let mut data = vec![];
+    data.extend_from_slice(b"para");
+    data.extend_from_slice(&[0; 4]);
+    data.extend_from_slice(&function_type.to_be_bytes());
+    data.extend_from_slice(&[0; 2]);
+    for parameter in parameters {
+        push_s15_fixed16(&mut data, *parameter);
+    }
+    data
+}
+
+fn push_u32(data: &mut Vec<u8>, value: u32) {
+    data.extend_from_slice(&value.to_be_bytes());
+}
+
+/// Append the value as a signed fixed point number with 16 fractional bits.
+fn push_s15_fixed16(data: &mut Vec<u8>, value: f32) {
+    data.extend_from_slice(&((value * 65536.0).round() as i32).to_be_bytes());
+}
+
+#[cfg(test)]
+mod icc_tests {
+    use super::icc_profile;
+    use crate::film::{ColorSpace, TransferFunction};
+
+    #[test]
+    fn profile_has_valid_layout() {
+        let profile = icc_profile(ColorSpace::DisplayP3, TransferFunction::Srgb);
+        let read_u32 = |offset: usize| {
+            u32::from_be_bytes([
+                profile[offset],
+                profile[offset + 1],
+                profile[offset + 2],
+                profile[offset + 3],
+            ]) as usize
+        };
+        assert_eq!(read_u32(0), profile.len());
+        assert_eq!(&profile[36..40], b"acsp");
+        // Every tag lies within the profile and starts on a four byte
+        // boundary.
+        let tag_count = read_u32(128);
+        assert_eq!(tag_count, 10);
+        for i in 0..tag_count {
+            let entry = 132 + 12 * i;
+            let (offset, size) = (read_u32(entry + 4), read_u32(entry + 8));
+            assert_eq!(offset % 4, 0);
+            assert!(offset + size <= profile.len());
+        }
+    }
+}
@@ -1,15 +1,31 @@
+mod aov;
+pub mod compare;
+mod downsample;
+mod exr;
+mod histogram;
+mod icc;
+mod splat;
+mod statistics;
 mod tile;
 
+pub use downsample::Downsampling;
+pub use exr::write_exr_layers;
+pub use histogram::{clipping_overlay, LuminanceHistogram};
+pub use icc::icc_profile;
 use image::ImageBuffer;
+pub use statistics::SampleStatistics;
 pub use tile::FilmTile;
 
 use crate::{
-    color::{RgbaSpectrum, Xyza},
+    color::{self, ColorRamp, RgbaSpectrum, Xyza},
     geometry::bounds::Bounds2,
+    integrator::Aov,
+    postprocess::{self, PostProcess},
+    sampler::mix,
 };
-use cgmath::{point2, Point2, Vector2};
+use cgmath::{Point2, Vector2};
 
-use self::tile::FilmTilePixel;
+use self::{aov::AovBuffer, splat::SplatBuffer, tile::FilmTilePixel};
 
 /// Models the sensing device in a simulated camera. Acts as a 2D plane of
 /// pixels onto which a final image is rendered.
@@ -28,7 +44,140 @@ pub struct Film {
     /// NOT the index of the bottom-right pixel, as one might mistakenly expect.
     pub pixel_bounds: Bounds2<i32>,
 
-    pixels: Vec<FilmPixel>,
+    /// A bounding box around the pixels (in raster space) that are rendered.
+    /// By default this is every pixel. Pixels outside of it are left
+    /// transparent.
+    crop_bounds: Bounds2<i32>,
+
+    /// The filter-weighted sum of the XYZ colors of the samples that have
+    /// contributed to each pixel, in row-major order.
+    xyz_sums: Vec<Xyza>,
+
+    /// The sum of the filter weights of the samples that have contributed to
+    /// each pixel, in row-major order.
+    filter_weight_sums: Vec<f32>,
+
+    /// Statistics of the luminance of the samples that fall inside each
+    /// pixel, in row-major order.
+    sample_statistics: Vec<SampleStatistics>,
+
+    /// How the negative lobes of the reconstruction filter are handled.
+    negative_lobes: NegativeLobes,
+
+    /// A buffer for each arbitrary output variable (AOV) that the film
+    /// records, in addition to radiance.
+    aovs: Vec<AovBuffer>,
+
+    /// The unfiltered contributions that have been splatted onto each pixel.
+    splats: SplatBuffer,
+
+    /// The factor that splatted contributions are scaled by when they're
+    /// added to the final pixel colors.
+    splat_scale: f32,
+
+    /// How the final colors are encoded when they're written to an image.
+    transfer_function: TransferFunction,
+
+    /// The primaries of the colors that are written to an image.
+    color_space: ColorSpace,
+
+    /// If present, images are written at a fraction of the film's resolution.
+    downsampling: Option<Downsampling>,
+}
+
+/// Filters with negative lobes, such as the Mitchell filter, sharpen the image
+/// but can subtract a bright sample's radiance from its neighbors. Around
+/// bright silhouettes on a transparent background this leaves dark fringes
+/// whose values can even drop below zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NegativeLobes {
+    /// Use the filter's weights as they are.
+    #[default]
+    Keep,
+
+    /// Clamp each reconstructed pixel's color and alpha components to zero,
+    /// which removes values below zero but keeps the filter's sharpening.
+    ClampPixels,
+
+    /// Treat negative filter weights as zero. Since each pixel is normalized
+    /// by the sum of its weights, the clamped kernel still preserves energy,
+    /// but the image is slightly softer.
+    ClampKernel,
+}
+
+/// The transfer function that maps the film's linear colors to the values
+/// that are written to an image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TransferFunction {
+    /// Encode the colors as sRGB, which is what image viewers expect.
+    #[default]
+    Srgb,
+
+    /// Write the linear colors as they are, such as for compositing.
+    Linear,
+}
+
+impl TransferFunction {
+    /// Encode the color components of each pixel. Partially covered pixels at
+    /// the edges of objects are un-premultiplied before they're encoded, so
+    /// they're encoded like fully covered pixels, and then they're
+    /// premultiplied again. Alpha above one is treated as full coverage. Alpha
+    /// itself is always linear.
+    fn encode(&self, pixels: &mut [RgbaSpectrum]) {
+        if *self == TransferFunction::Linear {
+            return;
+        }
+        for pixel in pixels {
+            let coverage = pixel.a().min(1.0);
+            if coverage <= 0.0 {
+                continue;
+            }
+            let encode = |c: f32| color::linear_to_srgb((c / coverage).max(0.0)) * coverage;
+            *pixel = RgbaSpectrum::from_rgba(
+                encode(pixel.r()),
+                encode(pixel.g()),
+                encode(pixel.b()),
+                pixel.a(),
+            );
+        }
+    }
+}
+
+/// The RGB color space that the film's colors are written in. The film
+/// records linear colors with the sRGB primaries, and they're converted to the
+/// color space before they're encoded with the transfer function.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// The sRGB primaries, which image viewers assume when an image has no
+    /// color profile.
+    #[default]
+    Srgb,
+
+    /// The Display P3 primaries, which are used by wide gamut displays. They
+    /// share sRGB's white point.
+    DisplayP3,
+}
+
+impl ColorSpace {
+    /// Convert the linear sRGB color components of each pixel to the color
+    /// space. Alpha is unchanged.
+    fn convert(&self, pixels: &mut [RgbaSpectrum]) {
+        // The rows of the matrix that maps linear sRGB to linear Display P3.
+        const SRGB_TO_DISPLAY_P3: [[f32; 3]; 3] = [
+            [0.822_462, 0.177_538, 0.0],
+            [0.033_194, 0.966_806, 0.0],
+            [0.017_083, 0.072_397, 0.910_520],
+        ];
+        if *self == ColorSpace::Srgb {
+            return;
+        }
+        for pixel in pixels {
+            let rgb = [pixel.r(), pixel.g(), pixel.b()];
+            let [r, g, b] =
+                SRGB_TO_DISPLAY_P3.map(|row| row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2]);
+            *pixel = RgbaSpectrum::from_rgba(r, g, b, pixel.a());
+        }
+    }
 }
 
 impl Film {
@@ -37,15 +186,261 @@ impl Film {
             Point2::new(0, 0),
             Point2::new(resolution.x as i32, resolution.y as i32),
         );
-        let pixels = vec![FilmPixel::default(); resolution.x * resolution.y];
+        let pixel_count = resolution.x * resolution.y;
 
         Self {
             resolution,
             pixel_bounds,
-            pixels,
+            crop_bounds: pixel_bounds,
+            xyz_sums: vec![Xyza::transparent(); pixel_count],
+            filter_weight_sums: vec![0.0; pixel_count],
+            sample_statistics: vec![SampleStatistics::default(); pixel_count],
+            negative_lobes: NegativeLobes::default(),
+            aovs: vec![],
+            splats: SplatBuffer::new(pixel_count),
+            splat_scale: 1.0,
+            transfer_function: TransferFunction::default(),
+            color_space: ColorSpace::default(),
+            downsampling: None,
+        }
+    }
+
+    /// Set how the final colors are encoded when they're written to an 8 or 16
+    /// bit image. Images are sRGB encoded by default.
+    pub fn with_transfer_function(mut self, transfer_function: TransferFunction) -> Self {
+        self.transfer_function = transfer_function;
+        self
+    }
+
+    /// Set the color space that the final colors are converted to when they're
+    /// written to an 8 or 16 bit image. Images are sRGB by default.
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    /// Return an ICC profile that describes the colors of the images that are
+    /// written from the film, for embedding in those images.
+    pub fn icc_profile(&self) -> Vec<u8> {
+        icc_profile(self.color_space, self.transfer_function)
+    }
+
+    /// Render the film at a multiple of the resolution of the images that are
+    /// written from it, and filter its pixels down to that resolution before
+    /// they're post-processed. The film's resolution should be a multiple of
+    /// the downsampling factor.
+    pub fn with_downsampling(mut self, downsampling: Downsampling) -> Self {
+        self.downsampling = Some(downsampling);
+        self
+    }
+
+    /// Return the resolution of the images that are written from the film.
+    /// This is the film's resolution unless it's downsampled.
+    pub fn output_resolution(&self) -> Vector2<usize> {
+        match &self.downsampling {
+            Some(downsampling) => downsampling.output_resolution(self.resolution),
+            None => self.resolution,
+        }
+    }
+
+    /// Only render the pixels inside a window of the film. The image keeps its
+    /// full resolution, and the pixels outside of the window are left
+    /// transparent. This is useful for iterating on a detail of a large
+    /// image.
+    ///
+    /// * min - The top-left corner of the window, as a fraction of the film's
+    ///   width and height in [0, 1].
+    /// * max - The bottom-right corner of the window, as a fraction of the
+    ///   film's width and height in [0, 1].
+    pub fn with_crop_window(self, min: Point2<f32>, max: Point2<f32>) -> Self {
+        // A pixel is inside the window if its center is.
+        let to_pixel = |p: Point2<f32>| {
+            Point2::new(
+                (p.x * self.resolution.x as f32 - 0.5).ceil() as i32,
+                (p.y * self.resolution.y as f32 - 0.5).ceil() as i32,
+            )
+        };
+        let (min, max) = (to_pixel(min), to_pixel(max));
+        self.with_pixel_crop_window(min, max)
+    }
+
+    /// Like `with_crop_window`, but the window is given in pixels.
+    ///
+    /// * min - The top-left pixel of the window.
+    /// * max - The pixel just past the bottom-right pixel of the window.
+    pub fn with_pixel_crop_window(mut self, min: Point2<i32>, max: Point2<i32>) -> Self {
+        self.crop_bounds = Bounds2::new(min, max)
+            .intersect(&self.pixel_bounds)
+            .unwrap_or_else(|| Bounds2::new(self.pixel_bounds.min, self.pixel_bounds.min));
+        self
+    }
+
+    /// Record a buffer for each of the AOVs in addition to radiance. Ray
+    /// tracers fill the buffers as they render the film. Duplicate AOVs are
+    /// only recorded once.
+    pub fn with_aovs(mut self, aovs: &[Aov]) -> Self {
+        let pixel_count = self.resolution.x * self.resolution.y;
+        self.aovs = vec![];
+        for aov in aovs {
+            if !self.aovs.iter().any(|buffer| buffer.aov == *aov) {
+                self.aovs.push(AovBuffer::new(*aov, pixel_count));
+            }
+        }
+        self
+    }
+
+    /// Set the factor that splatted contributions are scaled by when they're
+    /// added to the final pixel colors. Light-tracing methods typically
+    /// splat every path's contribution with unit weight and then divide by
+    /// the number of samples taken per pixel.
+    pub fn with_splat_scale(mut self, splat_scale: f32) -> Self {
+        self.splat_scale = splat_scale;
+        self
+    }
+
+    /// Add a contribution directly to the pixel that contains the point.
+    /// Unlike samples added to a `FilmTile`, splats can land anywhere on the
+    /// film and aren't filtered, which suits light-transport methods that
+    /// trace paths from lights and find the pixels they reach only at the end.
+    /// Splats from many threads can be added at once. Points outside of the
+    /// film are ignored.
+    ///
+    /// * point - The point on the film in raster space.
+    /// * spectrum - The contribution to add. Its alpha is ignored.
+    pub fn add_splat(&self, point: &Point2<f32>, spectrum: &RgbaSpectrum) {
+        let pixel = Point2::new(point.x.floor() as i32, point.y.floor() as i32);
+        if self.pixel_bounds.contains(&pixel) {
+            self.splats
+                .add(self.pixel_index(&pixel), &Xyza::from(*spectrum));
         }
     }
 
+    /// Return the AOVs that the film records.
+    pub fn aovs(&self) -> Vec<Aov> {
+        self.aovs.iter().map(|buffer| buffer.aov).collect()
+    }
+
+    /// Set how the negative lobes of the reconstruction filter are handled.
+    pub fn with_negative_lobes(mut self, negative_lobes: NegativeLobes) -> Self {
+        self.negative_lobes = negative_lobes;
+        self
+    }
+
+    /// Return how the negative lobes of the reconstruction filter are handled.
+    pub fn negative_lobes(&self) -> NegativeLobes {
+        self.negative_lobes
+    }
+
+    /// Return the filter-weighted sum of the XYZ colors of the samples that
+    /// have contributed to each pixel. Pixels are in row-major order, starting
+    /// at the top-left pixel.
+    ///
+    /// Each sum must be divided by the corresponding value in
+    /// `filter_weight_buffer` to get the pixel's final color. This is useful
+    /// for tools that need the film's unprocessed data, such as custom tone
+    /// mapping or denoising.
+    pub fn xyz_buffer(&self) -> &[Xyza] {
+        &self.xyz_sums
+    }
+
+    /// Return the sum of the filter weights of the samples that have
+    /// contributed to each pixel. Pixels are in row-major order, starting at
+    /// the top-left pixel.
+    pub fn filter_weight_buffer(&self) -> &[f32] {
+        &self.filter_weight_sums
+    }
+
+    /// Return statistics of the luminance of the samples that fall inside each
+    /// pixel. Pixels are in row-major order, starting at the top-left pixel.
+    pub fn sample_statistics_buffer(&self) -> &[SampleStatistics] {
+        &self.sample_statistics
+    }
+
+    /// Return the estimated variance of the mean luminance of each pixel, in
+    /// row-major order starting at the top-left pixel. This indicates how much
+    /// noise remains in each pixel.
+    pub fn to_variance(&self) -> Vec<f32> {
+        self.sample_statistics
+            .iter()
+            .map(|statistics| {
+                if statistics.count == 0 {
+                    0.0
+                } else {
+                    statistics.variance_of_mean()
+                }
+            })
+            .collect()
+    }
+
+    /// Write a false-color image of the variance of each pixel's mean
+    /// luminance. Variances are divided by the greatest variance in the image
+    /// and mapped through the ramp, so the noisiest pixels have the color at
+    /// the end of the ramp. Pixels that no samples fall inside are
+    /// transparent.
+    pub fn write_variance_image(
+        &self,
+        ramp: &ColorRamp,
+    ) -> image::ImageBuffer<image::Rgba<u8>, std::vec::Vec<u8>> {
+        let variances = self.to_variance();
+        let max_variance = variances.iter().copied().fold(0.0, f32::max);
+        let colors: Vec<RgbaSpectrum> = variances
+            .iter()
+            .zip(&self.sample_statistics)
+            .map(|(variance, statistics)| {
+                if statistics.count == 0 {
+                    RgbaSpectrum::transparent()
+                } else if max_variance > 0.0 {
+                    ramp.eval(variance / max_variance)
+                } else {
+                    ramp.eval(0.0)
+                }
+            })
+            .collect();
+        self.write_rgba8(&self.downsample(colors))
+    }
+
+    /// Return the final XYZ color of each pixel in row-major order, starting
+    /// at the top-left pixel. Pixels that no samples have contributed to are
+    /// transparent. Splatted contributions are scaled and added to each
+    /// pixel's color.
+    pub fn to_xyz(&self) -> Vec<Xyza> {
+        self.xyz_sums
+            .iter()
+            .zip(&self.filter_weight_sums)
+            .enumerate()
+            .map(|(index, (xyz, weight_sum))| {
+                let filtered = if *weight_sum > 0.0 {
+                    let inv_weight_sum = 1.0 / weight_sum;
+                    let xyz = Xyza::new(
+                        xyz.x() * inv_weight_sum,
+                        xyz.y() * inv_weight_sum,
+                        xyz.z() * inv_weight_sum,
+                        xyz.a() * inv_weight_sum,
+                    );
+                    if self.negative_lobes == NegativeLobes::ClampPixels {
+                        Xyza::new(
+                            xyz.x().max(0.0),
+                            xyz.y().max(0.0),
+                            xyz.z().max(0.0),
+                            xyz.a().max(0.0),
+                        )
+                    } else {
+                        xyz
+                    }
+                } else {
+                    Xyza::transparent()
+                };
+                let [x, y, z] = self.splats.get(index);
+                Xyza::new(
+                    filtered.x() + self.splat_scale * x,
+                    filtered.y() + self.splat_scale * y,
+                    filtered.z() + self.splat_scale * z,
+                    filtered.a(),
+                )
+            })
+            .collect()
+    }
+
     /// Return a bounding box around the film's pixels in raster space that a
     /// `Sampler` will be responsible for generating samples for.
     ///
@@ -53,15 +448,16 @@ impl Film {
     /// a kernel, it's important that we generate samples for "pixels" that are
     /// outside of the image's bounds. If we don't, then when pixels at the
     /// image edge are reconstructed by a `Filter`, they will be biased towards
-    /// the inner pixels.
+    /// the inner pixels. If the film has a crop window, the same holds for
+    /// the edges of the window.
     pub fn sample_bounds(&self, filter_half_width: f32, filter_half_height: f32) -> Bounds2<i32> {
         let top_left_pixel_center = Point2::new(
-            self.pixel_bounds.min.x as f32 + 0.5,
-            self.pixel_bounds.min.y as f32 + 0.5,
+            self.crop_bounds.min.x as f32 + 0.5,
+            self.crop_bounds.min.y as f32 + 0.5,
         );
         let bottom_right_pixel_center = Point2::new(
-            self.pixel_bounds.max.x as f32 - 0.5,
-            self.pixel_bounds.max.y as f32 - 0.5,
+            self.crop_bounds.max.x as f32 - 0.5,
+            self.crop_bounds.max.y as f32 - 0.5,
         );
 
         let min = Point2::new(
@@ -90,7 +486,7 @@ impl Film {
         filter_half_height: f32,
     ) -> Option<FilmTile> {
         self.pixel_bounds_for_sample_bounds(sample_bounds, filter_half_width, filter_half_height)
-            .map(FilmTile::new)
+            .map(|pixel_bounds| FilmTile::new(pixel_bounds).with_aovs(&self.aovs()))
     }
 
     /// Merge the tile into the film.
@@ -100,27 +496,150 @@ impl Film {
                 self.merge_pixel(pixel, &pixel_min_corner);
             }
         }
+
+        for buffer in &mut self.aovs {
+            if let Some(tile_buffer) = tile.aov_buffer(buffer.aov) {
+                for pixel_min_corner in tile.pixel_bounds.range() {
+                    let index = pixel_index(&self.pixel_bounds, &pixel_min_corner);
+                    buffer.merge(index, tile_buffer, tile.pixel_index(&pixel_min_corner));
+                }
+            }
+        }
+    }
+
+    /// Return the final value of each pixel of the AOV in row-major order,
+    /// starting at the top-left pixel, or `None` if the film doesn't record
+    /// the AOV. Pixels that no samples have contributed to are transparent.
+    pub fn to_aov(&self, aov: Aov) -> Option<Vec<RgbaSpectrum>> {
+        self.aovs
+            .iter()
+            .find(|buffer| buffer.aov == aov)
+            .map(AovBuffer::to_rgba)
+    }
+
+    /// Write the AOV to an image buffer for viewing, or return `None` if the
+    /// film doesn't record the AOV.
+    ///
+    /// Values are mapped into [0, 1] before they're quantized. Normals are
+    /// mapped from [-1, 1], and depths are divided by the greatest depth in
+    /// the image, so that the farthest surface is white. Albedos and object
+    /// colors are already in [0, 1]. Like radiance, values at the edges of
+    /// surfaces are weighted by the surfaces' coverage, which is stored in
+    /// the alpha channel.
+    pub fn write_aov_image(
+        &self,
+        aov: Aov,
+    ) -> Option<image::ImageBuffer<image::Rgba<u8>, std::vec::Vec<u8>>> {
+        let mut pixels = self.downsample(self.to_aov(aov)?);
+        match aov {
+            Aov::Normal => {
+                for p in &mut pixels {
+                    let a = p.a();
+                    *p = RgbaSpectrum::from_rgba(
+                        (p.r() + a) * 0.5,
+                        (p.g() + a) * 0.5,
+                        (p.b() + a) * 0.5,
+                        a,
+                    );
+                }
+            }
+            Aov::Depth => {
+                // Pixels at the edges of surfaces are weighted by their
+                // coverage, which can be tiny, so dividing by it to find the
+                // greatest depth would be unstable.
+                let max_depth = pixels.iter().map(|p| p.r()).fold(0.0, f32::max);
+                if max_depth > 0.0 {
+                    for p in &mut pixels {
+                        let a = p.a();
+                        *p /= max_depth;
+                        p.set_a(a);
+                    }
+                }
+            }
+            Aov::Albedo | Aov::ObjectId => {}
+        }
+        Some(self.write_rgba8(&pixels))
+    }
+
+    /// Return the final RGBA color of each pixel in row-major order, starting
+    /// at the top-left pixel. Pixels that no samples have contributed to are
+    /// transparent.
+    pub fn to_rgba(&self) -> Vec<RgbaSpectrum> {
+        self.to_xyz().into_iter().map(RgbaSpectrum::from).collect()
     }
 
     /// Write the contents of the film to an image buffer.
     pub fn write_image(&self) -> image::ImageBuffer<image::Rgba<u8>, std::vec::Vec<u8>> {
-        ImageBuffer::from_fn(
-            self.resolution.x as u32,
-            self.resolution.y as u32,
-            |x, y| {
-                let index = self.pixel_index(&point2(x as i32, y as i32));
-                let pixel = self.pixels[index];
-
-                let color = if pixel.filter_weight_sum > 0.0 {
-                    (1.0 / pixel.filter_weight_sum) * RgbaSpectrum::from(pixel.xyz)
-                } else {
-                    RgbaSpectrum::transparent()
-                };
+        self.write_post_processed_image(&[])
+    }
 
-                let output: image::Rgba<u8> = color.into();
-                output
-            },
-        )
+    /// Apply the chain of post-processing steps to the film's pixels and write
+    /// the result to an image buffer. The steps operate on the linear floating
+    /// point colors, before they are encoded with the film's transfer function
+    /// and quantized.
+    pub fn write_post_processed_image(
+        &self,
+        chain: &[Box<dyn PostProcess>],
+    ) -> image::ImageBuffer<image::Rgba<u8>, std::vec::Vec<u8>> {
+        self.write_rgba8(&self.post_processed_pixels(chain))
+    }
+
+    /// Return the pixels that `write_post_processed_image` quantizes, in
+    /// row-major order at the output resolution. They've been post-processed,
+    /// converted to the film's color space, and encoded with the film's
+    /// transfer function, and they still have premultiplied alpha.
+    pub fn post_processed_pixels(&self, chain: &[Box<dyn PostProcess>]) -> Vec<RgbaSpectrum> {
+        let mut pixels = self.downsample(self.to_rgba());
+        postprocess::apply_all(chain, &mut pixels, self.output_resolution());
+        self.color_space.convert(&mut pixels);
+        self.transfer_function.encode(&mut pixels);
+        pixels
+    }
+
+    /// Apply the chain of post-processing steps to the film's pixels and write
+    /// the result to an image buffer with 16 bits per channel. This avoids the
+    /// banding that 8 bits per channel can leave in smooth gradients.
+    ///
+    /// Each channel is dithered with triangular noise of one least significant
+    /// bit before it is quantized, so the rounding error is noise rather than
+    /// contours. Values of exactly zero or one, such as a transparent
+    /// background, aren't dithered.
+    pub fn write_post_processed_image_16(
+        &self,
+        chain: &[Box<dyn PostProcess>],
+    ) -> image::ImageBuffer<image::Rgba<u16>, std::vec::Vec<u16>> {
+        let pixels = self.post_processed_pixels(chain);
+        let resolution = self.output_resolution();
+        ImageBuffer::from_fn(resolution.x as u32, resolution.y as u32, |x, y| {
+            let p = pixels[y as usize * resolution.x + x as usize];
+            let hash = mix(((y as u64) << 32) | x as u64);
+            image::Rgba([
+                quantize_u16(p.r(), hash),
+                quantize_u16(p.g(), hash >> 16),
+                quantize_u16(p.b(), hash >> 32),
+                quantize_u16(p.a(), hash >> 48),
+            ])
+        })
+    }
+
+    /// Downsample the pixels, which cover the whole film, to the output
+    /// resolution if the film is downsampled.
+    fn downsample(&self, pixels: Vec<RgbaSpectrum>) -> Vec<RgbaSpectrum> {
+        match &self.downsampling {
+            Some(downsampling) => downsampling.apply(&pixels, self.resolution),
+            None => pixels,
+        }
+    }
+
+    /// Quantize pixels at the output resolution into an 8-bit image.
+    fn write_rgba8(
+        &self,
+        pixels: &[RgbaSpectrum],
+    ) -> image::ImageBuffer<image::Rgba<u8>, std::vec::Vec<u8>> {
+        let resolution = self.output_resolution();
+        ImageBuffer::from_fn(resolution.x as u32, resolution.y as u32, |x, y| {
+            pixels[y as usize * resolution.x + x as usize].into()
+        })
     }
 
     /// Return a bounding box around the pixels (in raster space) that samples
@@ -137,60 +656,309 @@ impl Film {
     ///   samples will be generated in.
     /// * filter_half_width
     /// * filter_half_height
-    fn pixel_bounds_for_sample_bounds(
+    pub(crate) fn pixel_bounds_for_sample_bounds(
         &self,
         sample_bounds: &Bounds2<i32>,
         filter_half_width: f32,
         filter_half_height: f32,
     ) -> Option<Bounds2<i32>> {
-        let min = Point2::new(
-            (sample_bounds.min.x as f32 - 0.5 - filter_half_width).ceil() as i32,
-            (sample_bounds.min.y as f32 - 0.5 - filter_half_height).ceil() as i32,
-        );
-        let max = Point2::new(
-            (sample_bounds.max.x as f32 - 0.5 + filter_half_width).floor() as i32 + 1,
-            (sample_bounds.max.y as f32 - 0.5 + filter_half_height).floor() as i32 + 1,
-        );
-        let possible_pixel_bounds = Bounds2::new(min, max);
-
         // Clip the possible pixel bounds to only include pixels that are
-        // actually on the film.
-        possible_pixel_bounds.intersect(&self.pixel_bounds)
+        // actually on the film and inside the crop window.
+        clipped_pixel_bounds(
+            sample_bounds,
+            filter_half_width,
+            filter_half_height,
+            &self.crop_bounds,
+        )
     }
 
     /// Merge the pixel from the tile into the film.
     fn merge_pixel(&mut self, pixel: &FilmTilePixel, pixel_min_corner: &Point2<i32>) {
         let index = self.pixel_index(pixel_min_corner);
-        self.pixels[index].xyz += Xyza::from(pixel.weighted_spectrum_sum);
-        self.pixels[index].filter_weight_sum += pixel.filter_weight_sum;
+        self.xyz_sums[index] += Xyza::from(pixel.weighted_spectrum_sum);
+        self.filter_weight_sums[index] += pixel.filter_weight_sum;
+        self.sample_statistics[index].merge(&pixel.sample_statistics);
     }
 
     /// Get the index into `pixels` of the pixel with the given top-left corner
     /// in raster space.
     fn pixel_index(&self, pixel_min_corner: &Point2<i32>) -> usize {
-        let relative = Point2::new(
-            pixel_min_corner.x - self.pixel_bounds.min.x,
-            pixel_min_corner.y - self.pixel_bounds.min.y,
-        );
-        // FIXME: Be careful. Passing in an out-of-bounds point could panic.
-        (relative.y * self.pixel_bounds.diagonal().x + relative.x) as usize
+        pixel_index(&self.pixel_bounds, pixel_min_corner)
     }
 }
 
-#[repr(C)]
-#[derive(Debug, Clone, Copy)]
-struct FilmPixel {
-    /// The color at the pixel in the XYZ color space.
-    xyz: Xyza,
+/// Get the index of the pixel with the given top-left corner in raster space
+/// within row-major buffers that cover `pixel_bounds`.
+fn pixel_index(pixel_bounds: &Bounds2<i32>, pixel_min_corner: &Point2<i32>) -> usize {
+    let relative = Point2::new(
+        pixel_min_corner.x - pixel_bounds.min.x,
+        pixel_min_corner.y - pixel_bounds.min.y,
+    );
+    // FIXME: Be careful. Passing in an out-of-bounds point could panic.
+    (relative.y * pixel_bounds.diagonal().x + relative.x) as usize
+}
 
-    filter_weight_sum: f32,
+/// Quantize a color component in [0, 1] to 16 bits, dithering it with
+/// triangular noise.
+///
+/// * c - The color component.
+/// * bits - Random bits. The lowest 16 are used.
+fn quantize_u16(c: f32, bits: u64) -> u16 {
+    if c <= 0.0 {
+        0
+    } else if c >= 1.0 {
+        u16::MAX
+    } else {
+        // The sum of two uniform values in [0, 1) has a triangular
+        // distribution, so their difference is in (-1, 1) and centered at 0.
+        let u1 = (bits & 0xff) as f32 / 256.0;
+        let u2 = ((bits >> 8) & 0xff) as f32 / 256.0;
+        let dithered = c * u16::MAX as f32 + (u1 - u2);
+        dithered.round().clamp(0.0, u16::MAX as f32) as u16
+    }
 }
 
-impl Default for FilmPixel {
-    fn default() -> Self {
-        Self {
-            xyz: Xyza::transparent(),
-            filter_weight_sum: 0.0,
+/// Return a bounding box around the pixels (in raster space) that samples
+/// taken from `sample_bounds` will contribute to, clipped to `clip_bounds`.
+pub(crate) fn clipped_pixel_bounds(
+    sample_bounds: &Bounds2<i32>,
+    filter_half_width: f32,
+    filter_half_height: f32,
+    clip_bounds: &Bounds2<i32>,
+) -> Option<Bounds2<i32>> {
+    let min = Point2::new(
+        (sample_bounds.min.x as f32 - 0.5 - filter_half_width).ceil() as i32,
+        (sample_bounds.min.y as f32 - 0.5 - filter_half_height).ceil() as i32,
+    );
+    let max = Point2::new(
+        (sample_bounds.max.x as f32 - 0.5 + filter_half_width).floor() as i32 + 1,
+        (sample_bounds.max.y as f32 - 0.5 + filter_half_height).floor() as i32 + 1,
+    );
+    let possible_pixel_bounds = Bounds2::new(min, max);
+    possible_pixel_bounds.intersect(clip_bounds)
+}
+
+#[cfg(test)]
+mod film_tests {
+    use super::{Film, NegativeLobes, TransferFunction};
+    use crate::{
+        color::{ColorRamp, RgbaSpectrum},
+        filter::{BoxFilter, MitchellFilter},
+        geometry::bounds::Bounds2,
+        integrator::Aov,
+    };
+    use cgmath::{Point2, Vector2};
+
+    #[test]
+    fn buffers_hold_accumulated_samples() {
+        let mut film = Film::new(Vector2::new(2, 2));
+        let filter = BoxFilter::new(0.5, 0.5);
+        let bounds = Bounds2::new(Point2::new(0, 0), Point2::new(2, 2));
+        let mut tile = film.tile(&bounds, 0.5, 0.5).unwrap();
+        let white = RgbaSpectrum::constant(1.0);
+        tile.add_sample(&Point2::new(0.5, 0.5), &white, 1.0, &filter);
+        tile.add_sample(&Point2::new(0.5, 0.5), &white, 1.0, &filter);
+        film.merge_tile(&tile);
+
+        assert_eq!(film.xyz_buffer().len(), 4);
+        assert_eq!(film.filter_weight_buffer().len(), 4);
+        assert!(film.filter_weight_buffer()[0] > 0.0);
+        assert!(film.filter_weight_buffer()[1..].iter().all(|w| *w == 0.0));
+
+        let xyz = film.to_xyz();
+        assert!((xyz[0].y() - 1.0).abs() < 0.0001);
+        assert!((xyz[0].a() - 1.0).abs() < 0.0001);
+        assert!(xyz[1].a() == 0.0);
+    }
+
+    #[test]
+    fn sub_tiles_merge_into_their_tile() {
+        let film = Film::new(Vector2::new(4, 4));
+        let filter = BoxFilter::new(0.5, 0.5);
+        let bounds = Bounds2::new(Point2::new(0, 0), Point2::new(4, 4));
+        let mut tile = film.tile(&bounds, 0.5, 0.5).unwrap();
+        let row_bounds = Bounds2::new(Point2::new(0, 2), Point2::new(4, 3));
+        let mut row = tile.sub_tile(&row_bounds, 0.5, 0.5).unwrap();
+
+        let white = RgbaSpectrum::constant(1.0);
+        row.add_sample(&Point2::new(1.5, 2.5), &white, 1.0, &filter);
+        tile.merge_sub_tile(&row);
+        let pixel = tile.pixel_at(Point2::new(1, 2)).unwrap();
+        assert!(pixel.filter_weight_sum > 0.0);
+        assert_eq!(pixel.sample_statistics.count, 1);
+    }
+
+    #[test]
+    fn crop_window_limits_rendered_pixels() {
+        let mut film = Film::new(Vector2::new(4, 4))
+            .with_crop_window(Point2::new(0.5, 0.0), Point2::new(1.0, 0.5));
+        let filter = BoxFilter::new(1.0, 1.0);
+        let sample_bounds = film.sample_bounds(1.0, 1.0);
+        assert!(sample_bounds == Bounds2::new(Point2::new(1, -1), Point2::new(5, 3)));
+
+        let mut tile = film.tile(&sample_bounds, 1.0, 1.0).unwrap();
+        assert!(tile.pixel_bounds == Bounds2::new(Point2::new(2, 0), Point2::new(4, 2)));
+        // A sample just outside of the window contributes to the pixels inside
+        // it, but not to the pixels outside it.
+        let white = RgbaSpectrum::constant(1.0);
+        tile.add_sample(&Point2::new(1.5, 0.5), &white, 1.0, &filter);
+        film.merge_tile(&tile);
+
+        let weights = film.filter_weight_buffer();
+        assert!(weights[2] > 0.0);
+        assert_eq!(weights[0], 0.0);
+        assert_eq!(weights[1], 0.0);
+        assert_eq!(film.to_xyz().len(), 16);
+    }
+
+    #[test]
+    fn variance_image_marks_noisy_pixels() {
+        let mut film = Film::new(Vector2::new(3, 1));
+        let filter = BoxFilter::new(0.5, 0.5);
+        let bounds = Bounds2::new(Point2::new(0, 0), Point2::new(3, 1));
+        let mut tile = film.tile(&bounds, 0.5, 0.5).unwrap();
+        for value in &[0.0, 1.0, 0.0, 1.0] {
+            let noisy = RgbaSpectrum::constant(*value);
+            tile.add_sample(&Point2::new(0.5, 0.5), &noisy, 1.0, &filter);
+            let smooth = RgbaSpectrum::constant(0.5);
+            tile.add_sample(&Point2::new(1.5, 0.5), &smooth, 1.0, &filter);
         }
+        film.merge_tile(&tile);
+
+        let ramp = ColorRamp::default();
+        let image = film.write_variance_image(&ramp);
+        assert_eq!(*image.get_pixel(0, 0), ramp.eval(1.0).into());
+        assert_eq!(*image.get_pixel(1, 0), ramp.eval(0.0).into());
+        assert_eq!(image.get_pixel(2, 0)[3], 0);
+    }
+
+    #[test]
+    fn splats_from_many_threads_are_scaled_and_added() {
+        let film = Film::new(Vector2::new(2, 1)).with_splat_scale(0.5);
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..100 {
+                        film.add_splat(&Point2::new(1.5, 0.5), &RgbaSpectrum::constant(0.01));
+                    }
+                });
+            }
+        });
+        // Splats outside of the film are ignored.
+        film.add_splat(&Point2::new(2.5, 0.5), &RgbaSpectrum::constant(1.0));
+
+        let xyz = film.to_xyz();
+        assert_eq!(xyz[0].y(), 0.0);
+        assert!((xyz[1].y() - 2.0).abs() < 0.001);
+        // Splats don't change the pixel's coverage.
+        assert_eq!(xyz[1].a(), 0.0);
+    }
+
+    #[test]
+    fn aov_samples_merge_into_their_own_buffer() {
+        let mut film = Film::new(Vector2::new(2, 1)).with_aovs(&[Aov::Depth, Aov::Normal]);
+        let filter = BoxFilter::new(0.5, 0.5);
+        let bounds = Bounds2::new(Point2::new(0, 0), Point2::new(2, 1));
+        let mut tile = film.tile(&bounds, 0.5, 0.5).unwrap();
+        let mut row = tile.sub_tile(&bounds, 0.5, 0.5).unwrap();
+        row.add_sample_aov(
+            Aov::Depth,
+            &Point2::new(0.5, 0.5),
+            &RgbaSpectrum::constant(2.0),
+            &filter,
+        );
+        row.add_sample_aov(
+            Aov::Depth,
+            &Point2::new(0.5, 0.5),
+            &RgbaSpectrum::constant(4.0),
+            &filter,
+        );
+        // The film doesn't record albedo, so this sample is ignored.
+        row.add_sample_aov(
+            Aov::Albedo,
+            &Point2::new(0.5, 0.5),
+            &RgbaSpectrum::constant(1.0),
+            &filter,
+        );
+        tile.merge_sub_tile(&row);
+        film.merge_tile(&tile);
+
+        assert_eq!(film.aovs(), vec![Aov::Depth, Aov::Normal]);
+        let depth = film.to_aov(Aov::Depth).unwrap();
+        assert!((depth[0].r() - 3.0).abs() < 0.0001);
+        assert!((depth[0].a() - 1.0).abs() < 0.0001);
+        assert_eq!(depth[1], RgbaSpectrum::transparent());
+        let normal = film.to_aov(Aov::Normal).unwrap();
+        assert!(normal.iter().all(|p| *p == RgbaSpectrum::transparent()));
+        assert!(film.to_aov(Aov::Albedo).is_none());
+        // The radiance buffers are untouched.
+        assert!(film.filter_weight_buffer().iter().all(|w| *w == 0.0));
+    }
+
+    #[test]
+    fn clamping_pixels_removes_negative_lobes() {
+        let render = |negative_lobes| {
+            let mut film = Film::new(Vector2::new(1, 1)).with_negative_lobes(negative_lobes);
+            let filter = MitchellFilter::new(2.0, 2.0, 1.0 / 3.0, 1.0 / 3.0);
+            let bounds = Bounds2::new(Point2::new(0, 0), Point2::new(2, 1));
+            let mut tile = film.tile(&bounds, 2.0, 2.0).unwrap();
+            // A bright sample in the Mitchell filter's negative lobe pulls the
+            // dark pixel below zero.
+            tile.add_sample(
+                &Point2::new(0.5, 0.5),
+                &RgbaSpectrum::from_rgb(0.0, 0.0, 0.0),
+                1.0,
+                &filter,
+            );
+            tile.add_sample(
+                &Point2::new(1.9, 0.5),
+                &RgbaSpectrum::constant(1000.0),
+                1.0,
+                &filter,
+            );
+            film.merge_tile(&tile);
+            film.to_xyz()[0]
+        };
+
+        assert!(render(NegativeLobes::Keep).y() < 0.0);
+        let clamped = render(NegativeLobes::ClampPixels);
+        assert!(clamped.y() >= 0.0);
+        assert!(clamped.a() >= 0.0);
+    }
+
+    #[test]
+    fn sixteen_bit_output_dithers_without_bias() {
+        let mut film =
+            Film::new(Vector2::new(16, 16)).with_transfer_function(TransferFunction::Linear);
+        let filter = BoxFilter::new(0.5, 0.5);
+        let bounds = Bounds2::new(Point2::new(0, 0), Point2::new(16, 16));
+        let mut tile = film.tile(&bounds, 0.5, 0.5).unwrap();
+        // Halfway between two 16-bit levels.
+        let gray = RgbaSpectrum::constant(1000.5 / u16::MAX as f32);
+        for p in bounds.range() {
+            let center = Point2::new(p.x as f32 + 0.5, p.y as f32 + 0.5);
+            if p.x > 0 {
+                tile.add_sample(&center, &gray, 1.0, &filter);
+            }
+        }
+        film.merge_tile(&tile);
+        let image = film.write_post_processed_image_16(&[]);
+
+        // The empty column stays exactly transparent.
+        assert!((0..16).all(|y| image.get_pixel(0, y).0 == [0, 0, 0, 0]));
+        assert!(image
+            .pixels()
+            .skip(1)
+            .step_by(16)
+            .all(|p| p.0[3] == u16::MAX));
+
+        let greens: Vec<f32> = image
+            .enumerate_pixels()
+            .filter(|(x, _, _)| *x > 0)
+            .map(|(_, _, p)| p.0[1] as f32)
+            .collect();
+        assert!(greens.iter().all(|g| (999.0..=1002.0).contains(g)));
+        let mean = greens.iter().sum::<f32>() / greens.len() as f32;
+        assert!((mean - 1000.5).abs() < 0.25);
     }
 }
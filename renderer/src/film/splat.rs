@@ -0,0 +1,51 @@
+use crate::color::Xyza;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Unfiltered contributions that have been splatted onto each pixel of a film,
+/// in row-major order. Each XYZ component is stored as the bits of an `f32` in
+/// an atomic integer, so that many threads can add to the buffer at once
+/// without locking it.
+pub(crate) struct SplatBuffer {
+    sums: Vec<[AtomicU32; 3]>,
+}
+
+impl SplatBuffer {
+    pub fn new(pixel_count: usize) -> Self {
+        let sums = (0..pixel_count)
+            .map(|_| [AtomicU32::new(0), AtomicU32::new(0), AtomicU32::new(0)])
+            .collect();
+        Self { sums }
+    }
+
+    /// Add the color to the pixel at the index.
+    pub fn add(&self, index: usize, xyz: &Xyza) {
+        let sum = &self.sums[index];
+        atomic_add(&sum[0], xyz.x());
+        atomic_add(&sum[1], xyz.y());
+        atomic_add(&sum[2], xyz.z());
+    }
+
+    /// Return the sum of the colors that have been splatted onto the pixel at
+    /// the index.
+    pub fn get(&self, index: usize) -> [f32; 3] {
+        let sum = &self.sums[index];
+        [
+            f32::from_bits(sum[0].load(Ordering::Relaxed)),
+            f32::from_bits(sum[1].load(Ordering::Relaxed)),
+            f32::from_bits(sum[2].load(Ordering::Relaxed)),
+        ]
+    }
+}
+
+/// Atomically add the value to the `f32` whose bits are stored in the atomic
+/// integer.
+fn atomic_add(bits: &AtomicU32, value: f32) {
+    let mut old = bits.load(Ordering::Relaxed);
+    loop {
+        let new = (f32::from_bits(old) + value).to_bits();
+        match bits.compare_exchange_weak(old, new, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => return,
+            Err(current) => old = current,
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use crate::color::{RgbaSpectrum, Xyza};
+
+/// Running statistics of the luminance of the samples taken in a pixel. These
+/// are used to estimate how noisy the pixel is.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SampleStatistics {
+    /// The number of samples.
+    pub count: usize,
+
+    /// The sum of the samples' luminances.
+    pub sum: f32,
+
+    /// The sum of the squares of the samples' luminances.
+    pub squared_sum: f32,
+}
+
+impl SampleStatistics {
+    /// Add the luminance of the radiance to the statistics.
+    pub fn add(&mut self, radiance: &RgbaSpectrum) {
+        let luminance = Xyza::from(*radiance).y();
+        self.count += 1;
+        self.sum += luminance;
+        self.squared_sum += luminance * luminance;
+    }
+
+    /// Combine the statistics with another set of statistics.
+    pub fn merge(&mut self, other: &Self) {
+        self.count += other.count;
+        self.sum += other.sum;
+        self.squared_sum += other.squared_sum;
+    }
+
+    /// Return the mean luminance of the samples.
+    pub fn mean(&self) -> f32 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum / self.count as f32
+        }
+    }
+
+    /// Return the unbiased sample variance of the samples' luminances.
+    pub fn variance(&self) -> f32 {
+        if self.count < 2 {
+            0.0
+        } else {
+            let n = self.count as f32;
+            ((self.squared_sum - self.sum * self.sum / n) / (n - 1.0)).max(0.0)
+        }
+    }
+
+    /// Return the estimated variance of the mean luminance. This decreases as
+    /// more samples are taken, and it indicates how much noise remains in the
+    /// pixel.
+    pub fn variance_of_mean(&self) -> f32 {
+        if self.count == 0 {
+            f32::INFINITY
+        } else {
+            self.variance() / self.count as f32
+        }
+    }
+}
+
+#[cfg(test)]
+mod sample_statistics_tests {
+    use super::SampleStatistics;
+    use crate::{color::RgbaSpectrum, test::ApproxEq};
+
+    #[test]
+    fn mean_and_variance_of_luminance() {
+        let mut statistics = SampleStatistics::default();
+        for value in &[1.0, 2.0, 3.0, 4.0] {
+            statistics.add(&RgbaSpectrum::constant(*value));
+        }
+        assert_eq!(statistics.count, 4);
+        statistics.mean().assert_approx_eq(&2.5);
+        statistics.variance().assert_approx_eq(&(5.0 / 3.0));
+        statistics
+            .variance_of_mean()
+            .assert_approx_eq(&(5.0 / 12.0));
+    }
+}
@@ -1,6 +1,7 @@
 use cgmath::{Point2, Vector2};
 
-use crate::{color::RgbaSpectrum, filter::Filter, geometry::bounds::Bounds2};
+use super::{aov::AovBuffer, SampleStatistics};
+use crate::{color::RgbaSpectrum, filter::Filter, geometry::bounds::Bounds2, integrator::Aov};
 
 /// Stores the pixel data for a subset of a larger `Film`. A `FilmTile` can be
 /// merged into a `Film` to add its pixel contributions to the `Film`.
@@ -10,6 +11,10 @@ pub struct FilmTile {
     pub pixel_bounds: Bounds2<i32>,
 
     pub pixels: Vec<FilmTilePixel>,
+
+    /// A buffer for each arbitrary output variable (AOV) that the tile
+    /// records, in addition to radiance.
+    aovs: Vec<AovBuffer>,
 }
 
 impl FilmTile {
@@ -18,9 +23,25 @@ impl FilmTile {
         Self {
             pixel_bounds,
             pixels,
+            aovs: vec![],
         }
     }
 
+    /// Record a buffer for each of the AOVs in addition to radiance.
+    pub fn with_aovs(mut self, aovs: &[Aov]) -> Self {
+        let pixel_count = self.pixels.len();
+        self.aovs = aovs
+            .iter()
+            .map(|aov| AovBuffer::new(*aov, pixel_count))
+            .collect();
+        self
+    }
+
+    /// Return the AOVs that the tile records.
+    pub fn aovs(&self) -> Vec<Aov> {
+        self.aovs.iter().map(|buffer| buffer.aov).collect()
+    }
+
     /// Add the radiance from a sample to the pixels in the tile.
     ///
     /// * sample_film_point - Location of the sample on the film in raster
@@ -31,13 +52,24 @@ impl FilmTile {
     ///   given sample contributes to the final image relative to the radiance
     ///   from other samples.
     /// * filter - The filter used to reconstruct pixels from various samples.
-    pub fn add_sample(
+    pub fn add_sample<F: Filter + ?Sized>(
         &mut self,
         sample_film_point: &Point2<f32>,
         radiance: &RgbaSpectrum,
         sample_weight: f32,
-        filter: &dyn Filter,
+        filter: &F,
     ) {
+        // Record the sample's luminance in the statistics of the pixel that
+        // contains it.
+        let containing_pixel = Point2::new(
+            sample_film_point.x.floor() as i32,
+            sample_film_point.y.floor() as i32,
+        );
+        if self.pixel_bounds.contains(&containing_pixel) {
+            let index = self.pixel_index(&containing_pixel);
+            self.pixels[index].sample_statistics.add(radiance);
+        }
+
         if let Some(bounds) = self.pixel_bounds_for_sample_point(
             sample_film_point,
             filter.half_width(),
@@ -58,6 +90,88 @@ impl FilmTile {
         }
     }
 
+    /// Add the value of an AOV from a sample to the pixels in the tile. The
+    /// value is ignored if the tile doesn't record the AOV.
+    ///
+    /// * aov - The AOV that the value belongs to.
+    /// * sample_film_point - Location of the sample on the film in raster
+    ///   space.
+    /// * value - The value of the AOV along the ray whose origin is at the
+    ///   `sample_film_point`.
+    /// * filter - The filter used to reconstruct pixels from various samples.
+    pub fn add_sample_aov<F: Filter + ?Sized>(
+        &mut self,
+        aov: Aov,
+        sample_film_point: &Point2<f32>,
+        value: &RgbaSpectrum,
+        filter: &F,
+    ) {
+        let buffer_index = match self.aovs.iter().position(|buffer| buffer.aov == aov) {
+            Some(buffer_index) => buffer_index,
+            None => return,
+        };
+        if let Some(bounds) = self.pixel_bounds_for_sample_point(
+            sample_film_point,
+            filter.half_width(),
+            filter.half_height(),
+        ) {
+            for pixel_min_corner in bounds.range() {
+                let sample_offset = sample_film_point
+                    - Point2::new(pixel_min_corner.x as f32, pixel_min_corner.y as f32);
+                let filter_weight = filter.eval_at(Point2::new(0.0, 0.0) + sample_offset);
+                let index = self.pixel_index(&pixel_min_corner);
+                self.aovs[buffer_index].add(index, value, filter_weight);
+            }
+        }
+    }
+
+    /// Return an empty tile for the pixels within this tile that samples taken
+    /// from `sample_bounds` could contribute to. Rendering into sub-tiles lets
+    /// separate threads share the work of a single tile.
+    ///
+    /// * sample_bounds - Bounding box of a pixel area (in raster space) that
+    ///   samples will be generated in.
+    /// * filter_half_width
+    /// * filter_half_height
+    pub fn sub_tile(
+        &self,
+        sample_bounds: &Bounds2<i32>,
+        filter_half_width: f32,
+        filter_half_height: f32,
+    ) -> Option<FilmTile> {
+        super::clipped_pixel_bounds(
+            sample_bounds,
+            filter_half_width,
+            filter_half_height,
+            &self.pixel_bounds,
+        )
+        .map(|pixel_bounds| FilmTile::new(pixel_bounds).with_aovs(&self.aovs()))
+    }
+
+    /// Add the pixel contributions of a sub-tile, which must lie within this
+    /// tile, to this tile.
+    pub fn merge_sub_tile(&mut self, sub_tile: &FilmTile) {
+        for pixel_min_corner in sub_tile.pixel_bounds.range() {
+            if let Some(pixel) = sub_tile.pixel_at(pixel_min_corner) {
+                let index = self.pixel_index(&pixel_min_corner);
+                let merged = &mut self.pixels[index];
+                merged.weighted_spectrum_sum += pixel.weighted_spectrum_sum;
+                merged.filter_weight_sum += pixel.filter_weight_sum;
+                merged.sample_statistics.merge(&pixel.sample_statistics);
+
+                let sub_tile_index = sub_tile.pixel_index(&pixel_min_corner);
+                for (buffer, sub_tile_buffer) in self.aovs.iter_mut().zip(&sub_tile.aovs) {
+                    buffer.merge(index, sub_tile_buffer, sub_tile_index);
+                }
+            }
+        }
+    }
+
+    /// Get the tile's buffer for the AOV, if the tile records it.
+    pub(crate) fn aov_buffer(&self, aov: Aov) -> Option<&AovBuffer> {
+        self.aovs.iter().find(|buffer| buffer.aov == aov)
+    }
+
     /// Get the pixel whose top-left corner is at the given point.
     pub fn pixel_at(&self, pixel_min_corner: Point2<i32>) -> Option<&FilmTilePixel> {
         let index = self.pixel_index(&pixel_min_corner);
@@ -91,7 +205,7 @@ impl FilmTile {
 
     /// Get the index into `pixels` of the pixel with the given top-left corner
     /// in raster space.
-    fn pixel_index(&self, p: &Point2<i32>) -> usize {
+    pub(crate) fn pixel_index(&self, p: &Point2<i32>) -> usize {
         let relative_p = Point2::new(p.x - self.pixel_bounds.min.x, p.y - self.pixel_bounds.min.y);
         // FIXME: Be careful, especially if this is made public. Passing in an
         // out-of-bounds point could panic.
@@ -111,6 +225,9 @@ pub struct FilmTilePixel {
     /// p. 490 of PBR ed. 3). This value is the sum of filter values at the
     /// contributing sample points.
     pub filter_weight_sum: f32,
+
+    /// Statistics of the luminance of the samples that fall inside the pixel.
+    pub sample_statistics: SampleStatistics,
 }
 
 impl Default for FilmTilePixel {
@@ -118,6 +235,7 @@ impl Default for FilmTilePixel {
         Self {
             weighted_spectrum_sum: RgbaSpectrum::from_rgba(0.0, 0.0, 0.0, 0.0),
             filter_weight_sum: 0.0,
+            sample_statistics: SampleStatistics::default(),
         }
     }
 }
@@ -1,8 +1,12 @@
 mod box_filter;
 mod mitchell;
 mod precomputed;
+mod triangle;
 
-pub use {box_filter::BoxFilter, mitchell::MitchellFilter, precomputed::PrecomputedFilter};
+pub use {
+    box_filter::BoxFilter, mitchell::MitchellFilter, precomputed::PrecomputedFilter,
+    triangle::TriangleFilter,
+};
 
 use cgmath::Point2;
 
@@ -7,6 +7,10 @@ pub struct PrecomputedFilter {
     half_width: f32,
     half_height: f32,
 
+    /// The number of table cells per unit of distance along each axis.
+    cells_per_x: f32,
+    cells_per_y: f32,
+
     /// A 16-by-16 row-major grid of pre-computed filter values for points
     /// within the source filter's `half_width`.
     table: [f32; 256],
@@ -19,6 +23,15 @@ impl PrecomputedFilter {
     /// * filter - A filter that is symmetric about the x and y axes. That is,
     ///   f(x, y) must be equal to f(|x|, |y|).
     pub fn new(filter: Box<dyn Filter>) -> Self {
+        Self::from_filter(&*filter)
+    }
+
+    /// Pre-compute values for various inputs to the borrowed filter, and return
+    /// a new filter that uses those pre-computed values.
+    ///
+    /// * filter - A filter that is symmetric about the x and y axes. That is,
+    ///   f(x, y) must be equal to f(|x|, |y|).
+    pub fn from_filter(filter: &dyn Filter) -> Self {
         let mut table = [0.0; TABLE_WIDTH * TABLE_WIDTH];
 
         // Divide the filter's positive quadrant into a 16-by-16 grid and
@@ -38,9 +51,20 @@ impl PrecomputedFilter {
             table,
             half_width: filter.half_width(),
             half_height: filter.half_height(),
+            cells_per_x: TABLE_WIDTH as f32 / filter.half_width(),
+            cells_per_y: TABLE_WIDTH as f32 / filter.half_height(),
         }
     }
 
+    /// Return a copy of the filter in which negative values are replaced with
+    /// zero.
+    pub fn without_negative_lobes(mut self) -> Self {
+        for value in self.table.iter_mut() {
+            *value = value.max(0.0);
+        }
+        self
+    }
+
     fn table_index(x: usize, y: usize) -> usize {
         y * TABLE_WIDTH + x
     }
@@ -55,8 +79,9 @@ impl Filter for PrecomputedFilter {
             return 0.0;
         }
 
-        let cell_x = (((x / self.half_width) * TABLE_WIDTH as f32) as usize).min(TABLE_WIDTH);
-        let cell_y = (((y / self.half_height) * TABLE_WIDTH as f32) as usize).min(TABLE_WIDTH);
+        // Points on the filter's edge would land just past the last cell.
+        let cell_x = ((x * self.cells_per_x) as usize).min(TABLE_WIDTH - 1);
+        let cell_y = ((y * self.cells_per_y) as usize).min(TABLE_WIDTH - 1);
         self.table[Self::table_index(cell_x, cell_y)]
     }
 
@@ -68,3 +93,24 @@ impl Filter for PrecomputedFilter {
         self.half_height
     }
 }
+
+#[cfg(test)]
+mod precomputed_filter_tests {
+    use super::PrecomputedFilter;
+    use crate::filter::{Filter, MitchellFilter};
+    use cgmath::Point2;
+
+    #[test]
+    fn matches_source_filter_and_handles_edges() {
+        let mitchell = MitchellFilter::new(2.0, 2.0, 1.0 / 3.0, 1.0 / 3.0);
+        let precomputed = PrecomputedFilter::from_filter(&mitchell);
+
+        let center = Point2::new(0.0625, -0.0625);
+        assert!((precomputed.eval_at(center) - mitchell.eval_at(center)).abs() < 1e-6);
+
+        // The corner of the filter is the far edge of the last table cell.
+        let corner = Point2::new(2.0, -2.0);
+        assert!((precomputed.eval_at(corner) - mitchell.eval_at(corner)).abs() < 0.05);
+        assert_eq!(precomputed.eval_at(Point2::new(2.5, 0.0)), 0.0);
+    }
+}
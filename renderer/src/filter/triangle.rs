@@ -0,0 +1,32 @@
+use super::Filter;
+use cgmath::Point2;
+
+/// A filter whose weight falls off linearly from the center of the filter to
+/// its edges in both the x and y directions.
+pub struct TriangleFilter {
+    half_width: f32,
+    half_height: f32,
+}
+
+impl TriangleFilter {
+    pub fn new(half_width: f32, half_height: f32) -> Self {
+        Self {
+            half_width,
+            half_height,
+        }
+    }
+}
+
+impl Filter for TriangleFilter {
+    fn eval_at(&self, p: Point2<f32>) -> f32 {
+        (self.half_width - p.x.abs()).max(0.0) * (self.half_height - p.y.abs()).max(0.0)
+    }
+
+    fn half_width(&self) -> f32 {
+        self.half_width
+    }
+
+    fn half_height(&self) -> f32 {
+        self.half_height
+    }
+}
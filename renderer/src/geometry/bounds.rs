@@ -35,6 +35,12 @@ impl Bounds2<i32> {
         }
     }
 
+    /// Return true if the point is inside the bounds, where the lower bounds
+    /// are inclusive and the upper bounds are exclusive.
+    pub fn contains(&self, p: &Point2<i32>) -> bool {
+        p.x >= self.min.x && p.x < self.max.x && p.y >= self.min.y && p.y < self.max.y
+    }
+
     /// Return the range of points inside the bounds, where the lower bounds are
     /// inclusive and the upper bounds are exclusive.
     pub fn range(&self) -> Vec<Point2<i32>> {
@@ -0,0 +1,225 @@
+use crate::{
+    bsdf::BxdfType,
+    color::RgbaSpectrum,
+    ray::Ray,
+    sampler::{mix, IncrementalSampler},
+    sampling,
+    scene::Scene,
+    TransportMode,
+};
+use cgmath::{InnerSpace, Point2, Vector3};
+
+use super::RayTracer;
+
+/// The number of samples per dimension used to estimate the albedo of
+/// materials that don't have a closed-form reflectance.
+const ALBEDO_SAMPLES_PER_DIMENSION: usize = 4;
+
+/// An arbitrary output variable (AOV). Describes a property of the surfaces
+/// that are visible from the camera, other than their radiance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    /// The world-space shading normal at the first surface that camera rays
+    /// hit. The normal's x, y, and z components are stored in the red, green,
+    /// and blue channels and are in [-1, 1].
+    Normal,
+
+    /// The camera-space depth of the first surface that camera rays hit, which
+    /// is its distance from the camera along the direction that the camera
+    /// looks in.
+    Depth,
+
+    /// The reflectance of the first surface that camera rays hit. This is the
+    /// fraction of light arriving uniformly from all directions that the
+    /// surface scatters toward the camera.
+    Albedo,
+
+    /// A color that identifies the object that camera rays first hit. Every
+    /// pixel covered by the same mesh has the same color, so the pass can be
+    /// used to build selection masks when compositing.
+    ObjectId,
+}
+
+impl Aov {
+    /// Return a short name for the AOV that is suitable for use in file
+    /// names.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Normal => "normal",
+            Aov::Depth => "depth",
+            Aov::Albedo => "albedo",
+            Aov::ObjectId => "object_id",
+        }
+    }
+
+    /// Return the depth of the point at which the ray hits a surface.
+    ///
+    /// * t - The parametric value of the point along the ray.
+    /// * view_direction - The direction in world space that the camera looks
+    ///   in.
+    pub fn depth(ray: &Ray, t: f32, view_direction: &Vector3<f32>) -> f32 {
+        t * ray.direction.dot(view_direction.normalize())
+    }
+
+    /// Return an opaque color that identifies the object. Colors are derived
+    /// from a hash of the identifier, so different objects are very likely
+    /// to have visibly different colors.
+    pub fn object_id_color(object_id: u64) -> RgbaSpectrum {
+        let hash = mix(object_id);
+        let channel = |shift: u64| ((hash >> shift) & 0xff) as f32 / 255.0;
+        RgbaSpectrum::from_rgb(channel(0), channel(8), channel(16))
+    }
+}
+
+/// Return the value of the AOV at the first surface that the ray hits. Rays
+/// that don't hit anything have a transparent value.
+///
+/// * view_direction - The direction in world space that the camera looks in.
+pub(crate) fn first_hit_aov(
+    aov: Aov,
+    ray: &Ray,
+    view_direction: &Vector3<f32>,
+    scene: &Scene,
+) -> RgbaSpectrum {
+    if let Some((t, prim, interaction)) = scene.ray_intersection(ray) {
+        let mut value = match aov {
+            Aov::Normal => {
+                let n = interaction.shading_geometry.normal.normalize();
+                RgbaSpectrum::from_rgb(n.x, n.y, n.z)
+            }
+            Aov::Depth => RgbaSpectrum::constant(Aov::depth(ray, t, view_direction)),
+            Aov::Albedo => {
+                let bsdf = prim
+                    .material
+                    .scattering_functions(&interaction, TransportMode::Radiance);
+                let wo = interaction.neg_ray_direction.normalize();
+                let samples: Vec<Point2<f32>> =
+                    sampling::grid_samples_2d(ALBEDO_SAMPLES_PER_DIMENSION);
+                bsdf.rho_hd(&wo, &samples, BxdfType::ALL)
+            }
+            Aov::ObjectId => Aov::object_id_color(prim.shape.object_id()),
+        };
+        value.set_a(1.0);
+        value
+    } else {
+        RgbaSpectrum::transparent()
+    }
+}
+
+/// A ray tracer that renders an auxiliary pass, such as normals, depth, or
+/// albedo, instead of radiance.
+///
+/// Rendering an AOV with the same camera, film resolution, filter, and sampler
+/// as the beauty pass produces an image whose pixels correspond to those of
+/// the beauty pass. These images are commonly used as inputs to denoisers and
+/// for compositing.
+pub struct AovRayTracer {
+    aov: Aov,
+
+    /// The direction in world space that the camera looks in.
+    view_direction: Vector3<f32>,
+}
+
+impl AovRayTracer {
+    /// Create a ray tracer that renders the AOV.
+    ///
+    /// * view_direction - The direction in world space that the camera looks
+    ///   in, such as the camera's `view_direction`. Depth is measured along
+    ///   it.
+    pub fn new(aov: Aov, view_direction: Vector3<f32>) -> Self {
+        Self {
+            aov,
+            view_direction,
+        }
+    }
+}
+
+impl<'msh, 'mtrl, S: IncrementalSampler> RayTracer<Scene<'msh, 'mtrl>, S> for AovRayTracer {
+    fn incoming_radiance(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        _sampler: &mut S,
+        _depth: usize,
+        _max_depth: usize,
+    ) -> RgbaSpectrum {
+        first_hit_aov(self.aov, ray, &self.view_direction, scene)
+    }
+
+    fn aov(
+        &self,
+        aov: Aov,
+        ray: &Ray,
+        view_direction: &Vector3<f32>,
+        scene: &Scene,
+    ) -> Option<RgbaSpectrum> {
+        Some(first_hit_aov(aov, ray, view_direction, scene))
+    }
+}
+
+#[cfg(test)]
+mod aov_ray_tracer_tests {
+    use super::{Aov, AovRayTracer};
+    use crate::{
+        color::RgbaSpectrum, integrator::RayTracer, ray::Ray, sampler::ConstantSampler,
+        test::ApproxEq, validation,
+    };
+    use cgmath::{Point3, Vector3};
+    use typed_arena::Arena;
+
+    fn trace(aov: Aov, origin: Point3<f32>) -> RgbaSpectrum {
+        let mesh_arena = Arena::new();
+        let material_arena = Arena::new();
+        let scene = validation::macbeth_chart_scene(&mesh_arena, &material_arena);
+        let ray = Ray::new(origin, Vector3::new(0.0, 0.0, 1.0), f32::INFINITY);
+        AovRayTracer::new(aov, Vector3::new(0.0, 0.0, 1.0)).incoming_radiance(
+            &ray,
+            &scene,
+            &mut ConstantSampler {},
+            0,
+            5,
+        )
+    }
+
+    #[test]
+    fn passes_describe_first_hit() {
+        // Aim at the center of the top-left patch of the chart, which lies in
+        // the z = 0 plane.
+        let origin = Point3::new(-3.0, 1.8, -10.0);
+
+        let normal = trace(Aov::Normal, origin);
+        normal.r().assert_approx_eq(&0.0);
+        normal.g().assert_approx_eq(&0.0);
+        normal.b().abs().assert_approx_eq(&1.0);
+
+        trace(Aov::Depth, origin).assert_approx_eq(&RgbaSpectrum::constant(10.0));
+
+        let albedo = trace(Aov::Albedo, origin);
+        albedo.assert_approx_eq(&validation::macbeth_chart_reflectances()[0]);
+    }
+
+    #[test]
+    fn depth_is_measured_along_view_direction() {
+        let mesh_arena = Arena::new();
+        let material_arena = Arena::new();
+        let scene = validation::macbeth_chart_scene(&mesh_arena, &material_arena);
+        // A ray that passes obliquely through a lens still hits a point whose
+        // depth is the distance from the lens plane to the chart.
+        let ray = Ray::new(
+            Point3::new(-3.0, 1.8, -10.0),
+            Vector3::new(0.05, 0.0, 1.0),
+            f32::INFINITY,
+        );
+        AovRayTracer::new(Aov::Depth, Vector3::new(0.0, 0.0, 1.0))
+            .incoming_radiance(&ray, &scene, &mut ConstantSampler {}, 0, 5)
+            .assert_approx_eq(&RgbaSpectrum::constant(10.0));
+    }
+
+    #[test]
+    fn misses_are_transparent() {
+        let origin = Point3::new(50.0, 50.0, -10.0);
+        for aov in &[Aov::Normal, Aov::Depth, Aov::Albedo, Aov::ObjectId] {
+            trace(*aov, origin).assert_approx_eq(&RgbaSpectrum::transparent());
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{color::RgbaSpectrum, ray::Ray, sampler::IncrementalSampler, scene::Scene};
+use cgmath::{InnerSpace, Vector3};
+
+use super::{aov, occlusion, Aov, RayTracer};
+
+/// The default reflectance of the clay surface.
+const DEFAULT_ALBEDO: f32 = 0.6;
+
+/// The default number of rays used to estimate ambient occlusion at each hit.
+const DEFAULT_AO_SAMPLE_COUNT: usize = 16;
+
+/// The fraction of the clay's shading that comes from the headlight. The rest
+/// comes from the ambient occlusion term.
+const HEADLIGHT_WEIGHT: f32 = 0.7;
+
+/// A ray tracer that ignores the materials and lights in the scene and shades
+/// every surface as matte gray clay.
+///
+/// Each surface is lit by a headlight that shines along the camera ray, so
+/// surfaces facing the camera are brightest, and by uniform ambient light that
+/// is attenuated by ambient occlusion, which darkens creases and contact
+/// regions. This is useful for previewing geometry without authoring
+/// materials or lights.
+pub struct ClayRayTracer {
+    /// The reflectance of the clay surface.
+    albedo: f32,
+
+    /// The number of rays used to estimate ambient occlusion at each hit.
+    ao_sample_count: usize,
+
+    /// The maximum distance at which geometry occludes ambient light.
+    ao_distance: f32,
+}
+
+impl ClayRayTracer {
+    /// Create a clay ray tracer.
+    ///
+    /// * albedo - The reflectance of the clay surface, in [0, 1].
+    /// * ao_sample_count - The number of rays used to estimate ambient
+    ///   occlusion at each hit.
+    /// * ao_distance - The maximum distance at which geometry occludes ambient
+    ///   light.
+    pub fn new(albedo: f32, ao_sample_count: usize, ao_distance: f32) -> Self {
+        Self {
+            albedo,
+            ao_sample_count,
+            ao_distance,
+        }
+    }
+}
+
+impl Default for ClayRayTracer {
+    fn default() -> Self {
+        Self::new(DEFAULT_ALBEDO, DEFAULT_AO_SAMPLE_COUNT, f32::INFINITY)
+    }
+}
+
+impl<'msh, 'mtrl, S: IncrementalSampler> RayTracer<Scene<'msh, 'mtrl>, S> for ClayRayTracer {
+    fn incoming_radiance(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        sampler: &mut S,
+        _depth: usize,
+        _max_depth: usize,
+    ) -> RgbaSpectrum {
+        let interaction = match scene.ray_intersection(ray) {
+            Some((_t, _prim, interaction)) => interaction,
+            None => return RgbaSpectrum::transparent(),
+        };
+
+        let wo = interaction.neg_ray_direction.normalize();
+        let cos_theta = wo.dot(interaction.shading_geometry.normal);
+
+        let ambient_occlusion = occlusion::ambient_occlusion(
+            &interaction,
+            scene,
+            sampler,
+            self.ao_sample_count,
+            self.ao_distance,
+        );
+
+        let shade = self.albedo
+            * (HEADLIGHT_WEIGHT * cos_theta.abs() + (1.0 - HEADLIGHT_WEIGHT) * ambient_occlusion);
+        let mut radiance = RgbaSpectrum::constant(shade);
+        radiance.set_a(1.0);
+        radiance
+    }
+
+    fn aov(
+        &self,
+        aov: Aov,
+        ray: &Ray,
+        view_direction: &Vector3<f32>,
+        scene: &Scene,
+    ) -> Option<RgbaSpectrum> {
+        Some(aov::first_hit_aov(aov, ray, view_direction, scene))
+    }
+}
+
+#[cfg(test)]
+mod clay_ray_tracer_tests {
+    use super::ClayRayTracer;
+    use crate::{
+        color::RgbaSpectrum, integrator::RayTracer, material::MatteMaterial, ray::Ray,
+        sampler::ConstantSampler, test::ApproxEq, validation,
+    };
+    use cgmath::{Point3, Vector3};
+    use typed_arena::Arena;
+
+    #[test]
+    fn unoccluded_surface_facing_camera_is_fully_lit() {
+        let mesh_arena = Arena::new();
+        let material_arena = Arena::<MatteMaterial>::new();
+        let scene = validation::macbeth_chart_scene(&mesh_arena, &material_arena);
+        let clay = ClayRayTracer::new(0.5, 4, f32::INFINITY);
+        let mut sampler = ConstantSampler {};
+
+        let ray = Ray::new(
+            Point3::new(-3.0, 1.8, -10.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            f32::INFINITY,
+        );
+        let radiance = clay.incoming_radiance(&ray, &scene, &mut sampler, 0, 5);
+        radiance.assert_approx_eq(&RgbaSpectrum::from_rgb(0.5, 0.5, 0.5));
+    }
+}
@@ -0,0 +1,243 @@
+use crate::{
+    bsdf::BxdfType, color::RgbaSpectrum, geometry::vector, interaction::SurfaceInteraction,
+    ray::Ray, sampler::IncrementalSampler, scene::Scene, TransportMode,
+};
+use cgmath::{InnerSpace, Point3, Vector3};
+
+use super::{occlusion, RayTracer};
+
+/// The default number of rays used to estimate ambient occlusion on the
+/// ground.
+const DEFAULT_AO_SAMPLE_COUNT: usize = 32;
+
+/// A fast ray tracer for catalog images of a part resting on the ground.
+///
+/// The part is shaded with the unshadowed direct lighting from each light in
+/// the scene. The ground is an infinite plane that isn't part of the scene.
+/// Instead of computing how light is transported onto the ground, its color is
+/// darkened by the ambient occlusion of the part, which produces soft contact
+/// shadows where the part rests on it. This is much faster than path tracing
+/// the scene with a ground mesh.
+pub struct ContactShadowRayTracer {
+    /// A point on the ground plane.
+    ground_point: Point3<f32>,
+
+    /// The normal of the ground plane.
+    ground_normal: Vector3<f32>,
+
+    /// The color of unoccluded regions of the ground.
+    ground_color: RgbaSpectrum,
+
+    /// The number of rays used to estimate ambient occlusion on the ground.
+    ao_sample_count: usize,
+
+    /// The maximum distance at which the part occludes the ground.
+    ao_distance: f32,
+
+    /// If true, only the shadow on the ground is rendered.
+    shadow_only: bool,
+}
+
+impl ContactShadowRayTracer {
+    /// Create a contact shadow ray tracer.
+    ///
+    /// * ground_point - A point on the ground plane.
+    /// * ground_normal - The normal of the ground plane.
+    /// * ground_color - The color of unoccluded regions of the ground.
+    /// * ao_distance - The maximum distance at which the part occludes the
+    ///   ground. Smaller distances produce tighter contact shadows.
+    pub fn new(
+        ground_point: Point3<f32>,
+        ground_normal: Vector3<f32>,
+        ground_color: RgbaSpectrum,
+        ao_distance: f32,
+    ) -> Self {
+        Self {
+            ground_point,
+            ground_normal: ground_normal.normalize(),
+            ground_color,
+            ao_sample_count: DEFAULT_AO_SAMPLE_COUNT,
+            ao_distance,
+            shadow_only: false,
+        }
+    }
+
+    /// Render only the shadow that the part casts on the ground, which is
+    /// useful for compositing under a model in a real-time viewer. The part
+    /// itself is invisible, so the shadow continues beneath it. Each pixel is
+    /// black, with an alpha that is the shadow's opacity, and the ground color
+    /// is ignored.
+    pub fn with_shadow_only(mut self, shadow_only: bool) -> Self {
+        self.shadow_only = shadow_only;
+        self
+    }
+
+    /// Return the parametric value at which the ray intersects the ground
+    /// plane, if it does.
+    fn ground_intersection(&self, ray: &Ray) -> Option<f32> {
+        let denominator = ray.direction.dot(self.ground_normal);
+        if denominator == 0.0 {
+            return None;
+        }
+        let t = (self.ground_point - ray.origin).dot(self.ground_normal) / denominator;
+        if t > 0.0 && t < ray.t_max {
+            Some(t)
+        } else {
+            None
+        }
+    }
+
+    /// Return the interaction at the point where the ray hits the ground.
+    fn ground_interaction(&self, ray: &Ray, t: f32) -> SurfaceInteraction {
+        let (dpdu, dpdv) = vector::arbitrary_coordinate_system(self.ground_normal);
+        SurfaceInteraction::new(
+            ray.at_t(t),
+            Vector3::new(0.0, 0.0, 0.0),
+            -ray.direction,
+            dpdu,
+            dpdv,
+        )
+    }
+}
+
+impl<'msh, 'mtrl, S: IncrementalSampler> RayTracer<Scene<'msh, 'mtrl>, S>
+    for ContactShadowRayTracer
+{
+    fn incoming_radiance(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        sampler: &mut S,
+        _depth: usize,
+        _max_depth: usize,
+    ) -> RgbaSpectrum {
+        let ground_t = self.ground_intersection(ray);
+        if self.shadow_only {
+            return match ground_t {
+                Some(t) => {
+                    let interaction = self.ground_interaction(ray, t);
+                    let ambient_occlusion = occlusion::ambient_occlusion(
+                        &interaction,
+                        scene,
+                        sampler,
+                        self.ao_sample_count,
+                        self.ao_distance,
+                    );
+                    RgbaSpectrum::from_rgba(0.0, 0.0, 0.0, 1.0 - ambient_occlusion)
+                }
+                None => RgbaSpectrum::transparent(),
+            };
+        }
+        let part_hit = scene.ray_intersection(ray);
+
+        let mut radiance = match (part_hit, ground_t) {
+            (Some((t, prim, interaction)), _) if ground_t.is_none_or(|g| t < g) => {
+                let normal = interaction.shading_geometry.normal;
+                let wo = interaction.neg_ray_direction;
+                let bsdf = prim
+                    .material
+                    .scattering_functions(&interaction, TransportMode::Radiance);
+                let mut radiance = RgbaSpectrum::black();
+                for light in scene.lights() {
+                    let sample = sampler.get_2d();
+                    let (incident_light, wi, _vis, pdf) = light.sample_li(&interaction, &sample);
+                    if incident_light.is_black() || pdf == 0.0 {
+                        continue;
+                    }
+                    let f = bsdf.f(&wo, &wi, BxdfType::ALL);
+                    radiance += f * incident_light * (wi.dot(normal).abs() / pdf);
+                }
+                radiance
+            }
+            (_, Some(t)) => {
+                let interaction = self.ground_interaction(ray, t);
+                let ambient_occlusion = occlusion::ambient_occlusion(
+                    &interaction,
+                    scene,
+                    sampler,
+                    self.ao_sample_count,
+                    self.ao_distance,
+                );
+                self.ground_color * ambient_occlusion
+            }
+            _ => return RgbaSpectrum::transparent(),
+        };
+        radiance.set_a(1.0);
+        radiance
+    }
+}
+
+#[cfg(test)]
+mod contact_shadow_ray_tracer_tests {
+    use super::ContactShadowRayTracer;
+    use crate::{
+        color::RgbaSpectrum, integrator::RayTracer, material::MatteMaterial, ray::Ray,
+        sampler::ConstantSampler, test::ApproxEq, validation,
+    };
+    use cgmath::{Point3, Vector3};
+    use typed_arena::Arena;
+
+    #[test]
+    fn ground_is_darkened_only_near_part() {
+        let mesh_arena = Arena::new();
+        let material_arena = Arena::<MatteMaterial>::new();
+        let scene = validation::macbeth_chart_scene(&mesh_arena, &material_arena);
+        // Place the ground just behind the chart.
+        let tracer = ContactShadowRayTracer::new(
+            Point3::new(0.0, 0.0, 0.1),
+            Vector3::new(0.0, 0.0, -1.0),
+            RgbaSpectrum::constant(1.0),
+            1.0,
+        );
+        let mut sampler = ConstantSampler {};
+
+        let direction = Vector3::new(0.0, 0.0, 1.0);
+        let far_from_chart = Ray::new(Point3::new(20.0, 0.0, -10.0), direction, f32::INFINITY);
+        tracer
+            .incoming_radiance(&far_from_chart, &scene, &mut sampler, 0, 5)
+            .assert_approx_eq(&RgbaSpectrum::from_rgb(1.0, 1.0, 1.0));
+
+        // Look through the gap between the first two rows of patches at the
+        // ground right behind the top-left patch, which occludes it.
+        let behind_patch = Ray::new(
+            Point3::new(-3.0, -58.2, -9.9),
+            Vector3::new(0.0, 6.0, 1.0),
+            f32::INFINITY,
+        );
+        let shadowed = tracer.incoming_radiance(&behind_patch, &scene, &mut sampler, 0, 5);
+        shadowed.assert_approx_eq(&RgbaSpectrum::from_rgb(0.0, 0.0, 0.0));
+
+        let miss = Ray::new(Point3::new(0.0, 0.0, -10.0), -direction, f32::INFINITY);
+        tracer
+            .incoming_radiance(&miss, &scene, &mut sampler, 0, 5)
+            .assert_approx_eq(&RgbaSpectrum::transparent());
+    }
+
+    #[test]
+    fn shadow_only_pass_sees_through_part() {
+        let mesh_arena = Arena::new();
+        let material_arena = Arena::<MatteMaterial>::new();
+        let scene = validation::macbeth_chart_scene(&mesh_arena, &material_arena);
+        let tracer = ContactShadowRayTracer::new(
+            Point3::new(0.0, 0.0, 0.1),
+            Vector3::new(0.0, 0.0, -1.0),
+            RgbaSpectrum::constant(1.0),
+            1.0,
+        )
+        .with_shadow_only(true);
+        let mut sampler = ConstantSampler {};
+
+        // The ray would hit the top-left patch, but it continues to the
+        // ground behind it, which the patch fully shadows.
+        let direction = Vector3::new(0.0, 0.0, 1.0);
+        let at_patch = Ray::new(Point3::new(-3.0, 1.8, -10.0), direction, f32::INFINITY);
+        tracer
+            .incoming_radiance(&at_patch, &scene, &mut sampler, 0, 5)
+            .assert_approx_eq(&RgbaSpectrum::from_rgba(0.0, 0.0, 0.0, 1.0));
+
+        let far_from_chart = Ray::new(Point3::new(20.0, 0.0, -10.0), direction, f32::INFINITY);
+        tracer
+            .incoming_radiance(&far_from_chart, &scene, &mut sampler, 0, 5)
+            .assert_approx_eq(&RgbaSpectrum::transparent());
+    }
+}
@@ -0,0 +1,142 @@
+use crate::{
+    color::{ColorRamp, RgbaSpectrum},
+    ray::Ray,
+    sampler::IncrementalSampler,
+    scene::Scene,
+};
+use cgmath::{InnerSpace, Point3, Vector3};
+
+use super::RayTracer;
+
+/// A scalar property of the first surface that camera rays hit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HeatmapScalar {
+    /// The distance along the camera ray to the surface.
+    Depth,
+
+    /// The angle, in degrees, between the surface's shading normal and the
+    /// direction back toward the camera. Surfaces that face the camera have an
+    /// angle of 0 and surfaces seen edge-on have an angle of 90.
+    ViewAngle,
+
+    /// The signed distance from a reference plane to the surface. Points on
+    /// the side of the plane that the normal points toward have a positive
+    /// distance.
+    PlaneDistance {
+        point: Point3<f32>,
+        normal: Vector3<f32>,
+    },
+}
+
+/// An inspection ray tracer that maps a scalar property of each visible
+/// surface through a color ramp, producing a false-color image. This is useful
+/// for checking the geometry of a part, such as the flatness of a machined
+/// face, rather than its appearance.
+pub struct HeatmapRayTracer {
+    scalar: HeatmapScalar,
+    ramp: ColorRamp,
+
+    /// The scalar value that is mapped to the start of the ramp.
+    min: f32,
+
+    /// The scalar value that is mapped to the end of the ramp.
+    max: f32,
+}
+
+impl HeatmapRayTracer {
+    /// Create a heatmap ray tracer.
+    ///
+    /// * scalar - The property of each hit that is visualized.
+    /// * ramp - The color ramp that the normalized scalar is mapped through.
+    /// * min - The scalar value that is mapped to the start of the ramp.
+    /// * max - The scalar value that is mapped to the end of the ramp.
+    pub fn new(scalar: HeatmapScalar, ramp: ColorRamp, min: f32, max: f32) -> Self {
+        Self {
+            scalar,
+            ramp,
+            min,
+            max,
+        }
+    }
+}
+
+impl<'msh, 'mtrl, S: IncrementalSampler> RayTracer<Scene<'msh, 'mtrl>, S> for HeatmapRayTracer {
+    fn incoming_radiance(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        _sampler: &mut S,
+        _depth: usize,
+        _max_depth: usize,
+    ) -> RgbaSpectrum {
+        if let Some((t, _prim, interaction)) = scene.ray_intersection(ray) {
+            let value = match self.scalar {
+                HeatmapScalar::Depth => t * ray.direction.magnitude(),
+                HeatmapScalar::ViewAngle => {
+                    let n = interaction.shading_geometry.normal.normalize();
+                    let wo = interaction.neg_ray_direction.normalize();
+                    n.dot(wo).abs().min(1.0).acos().to_degrees()
+                }
+                HeatmapScalar::PlaneDistance { point, normal } => {
+                    (interaction.point - point).dot(normal.normalize())
+                }
+            };
+            let normalized = if self.max > self.min {
+                (value - self.min) / (self.max - self.min)
+            } else {
+                0.0
+            };
+            let mut color = self.ramp.eval(normalized);
+            color.set_a(1.0);
+            color
+        } else {
+            RgbaSpectrum::transparent()
+        }
+    }
+}
+
+#[cfg(test)]
+mod heatmap_ray_tracer_tests {
+    use super::{HeatmapRayTracer, HeatmapScalar};
+    use crate::{
+        color::{ColorRamp, RgbaSpectrum},
+        integrator::RayTracer,
+        material::MatteMaterial,
+        ray::Ray,
+        sampler::ConstantSampler,
+        test::ApproxEq,
+        validation,
+    };
+    use cgmath::{Point3, Vector3};
+    use typed_arena::Arena;
+
+    fn trace(scalar: HeatmapScalar) -> RgbaSpectrum {
+        let mesh_arena = Arena::new();
+        let material_arena = Arena::<MatteMaterial>::new();
+        let scene = validation::macbeth_chart_scene(&mesh_arena, &material_arena);
+        let ramp = ColorRamp::new(vec![
+            (0.0, RgbaSpectrum::from_rgb(0.0, 0.0, 0.0)),
+            (1.0, RgbaSpectrum::from_rgb(1.0, 1.0, 1.0)),
+        ]);
+        let heatmap = HeatmapRayTracer::new(scalar, ramp, 0.0, 20.0);
+        // Aim at the center of the top-left patch of the chart, which lies in
+        // the z = 0 plane.
+        let ray = Ray::new(
+            Point3::new(-3.0, 1.8, -10.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            f32::INFINITY,
+        );
+        heatmap.incoming_radiance(&ray, &scene, &mut ConstantSampler {}, 0, 5)
+    }
+
+    #[test]
+    fn scalars_are_mapped_through_ramp() {
+        trace(HeatmapScalar::Depth).assert_approx_eq(&RgbaSpectrum::from_rgb(0.5, 0.5, 0.5));
+        trace(HeatmapScalar::ViewAngle).assert_approx_eq(&RgbaSpectrum::from_rgb(0.0, 0.0, 0.0));
+        trace(HeatmapScalar::PlaneDistance {
+            point: Point3::new(0.0, 0.0, -5.0),
+            normal: Vector3::new(0.0, 0.0, 1.0),
+        })
+        .assert_approx_eq(&RgbaSpectrum::from_rgb(0.25, 0.25, 0.25));
+    }
+}
@@ -1,19 +1,41 @@
+mod aov;
+mod clay;
+mod contact_shadow;
+mod heatmap;
+mod occlusion;
+mod path;
+mod priority;
+mod progressive;
+mod spectral;
+mod volumetric;
 mod whitted;
 
+pub use aov::{Aov, AovRayTracer};
+pub use clay::ClayRayTracer;
+pub use contact_shadow::ContactShadowRayTracer;
+pub use heatmap::{HeatmapRayTracer, HeatmapScalar};
+pub use path::PathTracer;
+pub use priority::PriorityMask;
+pub use spectral::SpectralPathTracer;
+pub use volumetric::VolumetricPathTracer;
 pub use whitted::WhittedRayTracer;
 
 use crate::{
     camera::Camera,
     color::RgbaSpectrum,
-    film::{Film, FilmTile},
-    filter::Filter,
+    film::{Film, FilmTile, NegativeLobes},
+    filter::{Filter, PrecomputedFilter},
     geometry::bounds::Bounds2,
     ray::Ray,
-    sampler::IncrementalSampler,
+    sampler::{pass_seed, IncrementalSampler},
     // scene::Scene,
+    statistics::MaterialStatistics,
 };
-use cgmath::{point2, Point2, Zero};
+use cgmath::{point2, Point2, Vector2, Vector3};
+use progressive::{progressive_pass, PROGRESSIVE_PASS_COUNT};
 use rayon::prelude::*;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
 
 pub trait RayTracer<Scene, Sampler: IncrementalSampler> {
     /// Determine the incoming radiance that arrives along the ray at the ray
@@ -30,13 +52,43 @@ pub trait RayTracer<Scene, Sampler: IncrementalSampler> {
     ///   up until the current call to this method.
     fn incoming_radiance(
         &self,
-        // TODO: Change to ray differential.
         ray: &Ray,
         scene: &Scene,
         sampler: &mut Sampler,
         depth: usize,
         max_depth: usize,
     ) -> RgbaSpectrum;
+
+    /// Determine the value of an arbitrary output variable (AOV) along a
+    /// camera ray. The value is written into the film's buffer for the AOV
+    /// alongside the radiance. Returns `None` if the ray tracer can't produce
+    /// the AOV, in which case the buffer is left empty.
+    ///
+    /// * `aov` - The output variable to evaluate.
+    /// * `ray` - The camera ray.
+    /// * `view_direction` - The direction in world space that the camera looks
+    ///   in. Depth is measured along it.
+    /// * `scene` - The scene being rendered.
+    fn aov(
+        &self,
+        _aov: Aov,
+        _ray: &Ray,
+        _view_direction: &Vector3<f32>,
+        _scene: &Scene,
+    ) -> Option<RgbaSpectrum> {
+        None
+    }
+
+    /// Called on the thread that rendered a tile once the tile's samples have
+    /// all been taken, so that the ray tracer can merge anything that it
+    /// accumulated for the tile, such as per-material statistics.
+    fn finish_tile(&self) {}
+
+    /// Return the per-material render-time statistics if the ray tracer is
+    /// collecting them.
+    fn statistics(&self) -> Option<&MaterialStatistics> {
+        None
+    }
 }
 
 /// * S - The type of sampler that is responsible for (1) choosing points on the image from
@@ -54,85 +106,432 @@ pub fn render<Scene: Send + Sync, Sampler: IncrementalSampler + Send + Sync>(
     sampler: &Sampler,
     ray_tracer: &(dyn RayTracer<Scene, Sampler> + Send + Sync),
     max_depth: usize,
+) {
+    render_with_options(
+        scene,
+        camera,
+        film,
+        filter,
+        sampler,
+        ray_tracer,
+        max_depth,
+        &RenderOptions::default(),
+    );
+}
+
+/// Options that change the order in which the render loop renders tiles and
+/// how it takes samples in each pixel.
+#[derive(Clone, Copy, Default)]
+pub struct RenderOptions<'a> {
+    /// If present, tiles are rendered in order of decreasing priority, and
+    /// tiles with high priority receive additional passes of samples.
+    pub priority_mask: Option<&'a PriorityMask>,
+
+    /// If present, the red, green, and blue components of each sample's
+    /// radiance are scaled down so that none of them exceed this value. This
+    /// biases the image but suppresses "fireflies", isolated bright pixels
+    /// that are left by rare high-energy paths, such as caustics from specular
+    /// surfaces.
+    pub max_sample_radiance: Option<f32>,
+
+    /// The shape of the buckets of pixels that are rendered as units of work.
+    pub tile_shape: TileShape,
+
+    /// The order in which tiles are rendered.
+    pub tile_order: TileOrder,
+
+    /// If present, pixels stop taking samples once this instant has passed.
+    /// Every pixel still receives at least one sample so that the image has
+    /// no holes, and each pixel is normalized by the weight of the samples it
+    /// did receive, so the image is noisier but not darker where sampling was
+    /// cut short.
+    pub deadline: Option<Instant>,
+
+    /// If present, the film is periodically passed to a callback while it is
+    /// rendered, so that long renders can be monitored.
+    pub snapshots: Option<Snapshots<'a>>,
+
+    /// Seeds the pseudo-random streams of the samplers. Renders that should
+    /// have independent noise, such as the frames of an animation, should use
+    /// different seeds.
+    pub seed: u64,
+}
+
+/// Describes how often the partially rendered film is passed to a callback,
+/// such as one that writes it to disk.
+#[derive(Clone, Copy)]
+pub struct Snapshots<'a> {
+    pub interval: SnapshotInterval,
+
+    /// Called with the film each time a snapshot is due. The film contains
+    /// every tile that has been rendered so far. Tiles aren't merged into the
+    /// film while the callback runs, so it should return quickly.
+    pub take: &'a (dyn Fn(&Film) + Sync),
+}
+
+/// How often a snapshot of the partially rendered film is taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotInterval {
+    /// Take a snapshot once at least this much time has passed since the
+    /// render started or since the last snapshot.
+    Time(Duration),
+
+    /// Take a snapshot each time this many tiles have been rendered.
+    Tiles(usize),
+}
+
+/// The shape of the buckets of pixels that the render loop divides the image
+/// into and renders in parallel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TileShape {
+    /// 16-by-16 pixel tiles, which are rendered efficiently because
+    /// neighboring rays tend to hit the same geometry.
+    #[default]
+    Square,
+
+    /// Buckets that are a single row of pixels with the given length. These
+    /// are useful when the cost of rendering is very uneven across the image,
+    /// such as when a single shiny part sits in a corner, because the
+    /// expensive region is split among many small buckets and no single
+    /// bucket holds up the end of the render.
+    Scanline(usize),
+}
+
+/// The order in which the render loop renders tiles. Tiles are rendered in
+/// parallel, so the order is only approximately followed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TileOrder {
+    /// Row by row, starting at the top-left tile.
+    #[default]
+    Scanline,
+
+    /// Several passes over the tiles in scanline order, in which each pass
+    /// visits a different portion of each tile's pixels. The pixels are
+    /// ranked with a blue-noise mask, so the pixels that the first passes
+    /// visit are spread evenly over the image. Snapshots of a partial render
+    /// then show the whole image filling in, rather than a band that grows
+    /// from the top.
+    Progressive,
+}
+
+impl TileShape {
+    /// Return the width and height of a tile, in pixels.
+    fn size(&self) -> Vector2<usize> {
+        match self {
+            TileShape::Square => Vector2::new(16, 16),
+            TileShape::Scanline(length) => Vector2::new((*length).max(1), 1),
+        }
+    }
+}
+
+/// Render the scene like `render`, but with the given options.
+pub fn render_with_options<Scene: Send + Sync, Sampler: IncrementalSampler + Send + Sync>(
+    scene: &Scene,
+    camera: &(dyn Camera + Send + Sync),
+    film: &mut Film,
+    filter: &(dyn Filter + Send + Sync),
+    sampler: &Sampler,
+    ray_tracer: &(dyn RayTracer<Scene, Sampler> + Send + Sync),
+    max_depth: usize,
+    options: &RenderOptions,
 ) {
     let image_sample_bounds = film.sample_bounds(filter.half_width(), filter.half_height());
+    let tiles = Tile::span_image_sample_bounds(&image_sample_bounds, options.tile_shape);
+    // Sorting by priority is stable, so tiles with equal priority keep their
+    // order.
+    let tiles: Vec<(Tile, usize)> = match options.priority_mask {
+        Some(priority_mask) => {
+            let mut tiles: Vec<(Tile, f32)> = tiles
+                .into_iter()
+                .map(|tile| {
+                    let priority = priority_mask.priority(&tile.sample_bounds);
+                    (tile, priority)
+                })
+                .collect();
+            tiles.sort_by(|(_, p1), (_, p2)| p2.total_cmp(p1));
+            tiles
+                .into_iter()
+                .map(|(tile, _)| {
+                    let pass_count = priority_mask.pass_count(&tile.sample_bounds);
+                    (tile, pass_count)
+                })
+                .collect()
+        }
+        None => tiles.into_iter().map(|tile| (tile, 1)).collect(),
+    };
+    let tiles = match options.tile_order {
+        TileOrder::Scanline => tiles,
+        TileOrder::Progressive => (0..PROGRESSIVE_PASS_COUNT)
+            .flat_map(|pass| {
+                tiles.iter().map(move |(tile, pass_count)| {
+                    let progressive_pass = ProgressivePass {
+                        index: pass,
+                        origin: image_sample_bounds.min,
+                    };
+                    (tile.with_progressive_pass(progressive_pass), *pass_count)
+                })
+            })
+            .collect(),
+    };
+    render_tiles(
+        scene, camera, film, filter, sampler, ray_tracer, max_depth, tiles, options,
+    );
+}
+
+/// Render the scene like `render`, but use the priority mask to decide the
+/// order in which tiles are rendered and how many passes of samples each tile
+/// receives. Tiles with the highest priority are rendered first.
+pub fn render_with_priority<Scene: Send + Sync, Sampler: IncrementalSampler + Send + Sync>(
+    scene: &Scene,
+    camera: &(dyn Camera + Send + Sync),
+    film: &mut Film,
+    filter: &(dyn Filter + Send + Sync),
+    sampler: &Sampler,
+    ray_tracer: &(dyn RayTracer<Scene, Sampler> + Send + Sync),
+    max_depth: usize,
+    priority_mask: &PriorityMask,
+) {
+    let options = RenderOptions {
+        priority_mask: Some(priority_mask),
+        ..RenderOptions::default()
+    };
+    render_with_options(
+        scene, camera, film, filter, sampler, ray_tracer, max_depth, &options,
+    );
+}
 
-    let film_tiles: Vec<FilmTile> = Tile::span_image_sample_bounds(&image_sample_bounds)
-        .par_iter()
-        .filter_map(|tile| {
-            // If the sampler generates random numbers, we don't want samplers in
-            // different tiles generating duplicate sequences of random numbers, so we
-            // use the tile's row-major index as a unique seed.
-            let mut sampler = sampler.clone_with_seed(tile.row_major_index as u64);
+/// Render each tile with the given number of sample passes and merge the
+/// results into the film. Samples in each pixel of a pass are taken according
+/// to the render options.
+///
+/// Finished tiles are sent through a bounded channel and merged into the film
+/// as they arrive, so only a few tiles are held in memory at once, no matter
+/// how large the image is.
+///
+/// The filter is tabulated once up front, so that weighting each sample's
+/// contribution to its neighboring pixels is a table lookup. If the film
+/// clamps the kernel's negative lobes, they are clamped in the table.
+#[allow(clippy::too_many_arguments)]
+fn render_tiles<Scene: Send + Sync, Sampler: IncrementalSampler + Send + Sync>(
+    scene: &Scene,
+    camera: &(dyn Camera + Send + Sync),
+    film: &mut Film,
+    filter: &(dyn Filter + Send + Sync),
+    sampler: &Sampler,
+    ray_tracer: &(dyn RayTracer<Scene, Sampler> + Send + Sync),
+    max_depth: usize,
+    tiles: Vec<(Tile, usize)>,
+    options: &RenderOptions,
+) {
+    let filter = PrecomputedFilter::from_filter(filter);
+    let filter = &match film.negative_lobes() {
+        NegativeLobes::ClampKernel => filter.without_negative_lobes(),
+        NegativeLobes::Keep | NegativeLobes::ClampPixels => filter,
+    };
+    // The film is merged into while tiles are rendered, so the tiles' pixel
+    // bounds are found up front.
+    let tiles: Vec<(Tile, usize, Bounds2<i32>)> = tiles
+        .into_iter()
+        .filter_map(|(tile, pass_count)| {
+            let pixel_bounds = film.pixel_bounds_for_sample_bounds(
+                &tile.sample_bounds,
+                filter.half_width(),
+                filter.half_height(),
+            )?;
+            Some((tile, pass_count, pixel_bounds))
+        })
+        .collect();
+
+    let aovs = film.aovs();
+    let (sender, receiver) = mpsc::sync_channel::<FilmTile>(rayon::current_num_threads());
+    std::thread::scope(|s| {
+        s.spawn(move || {
+            tiles
+                .par_iter()
+                .for_each_with(sender, |sender, (tile, pass_count, pixel_bounds)| {
+                    let mut film_tile = FilmTile::new(*pixel_bounds).with_aovs(&aovs);
+                    for pass in 0..*pass_count {
+                        // Samplers mix the seed with each pixel, so tiles can share
+                        // a seed, but each additional pass over a tile needs its own
+                        // seed so that it doesn't repeat the earlier passes' samples.
+                        let seed = pass_seed(options.seed, pass as u64);
+                        render_tile_rows::<Scene, Sampler>(
+                            camera,
+                            scene,
+                            tile,
+                            &mut film_tile,
+                            filter,
+                            sampler,
+                            seed,
+                            ray_tracer,
+                            max_depth,
+                            options,
+                        );
+                    }
+                    // The receiver is only dropped once every tile is merged.
+                    sender.send(film_tile).unwrap();
+                });
+        });
+
+        // The channel closes once every tile has been rendered and sent.
+        let mut last_snapshot = Instant::now();
+        for (merged_count, film_tile) in receiver.into_iter().enumerate() {
+            film.merge_tile(&film_tile);
+            if let Some(snapshots) = &options.snapshots {
+                let is_due = match snapshots.interval {
+                    SnapshotInterval::Time(interval) => last_snapshot.elapsed() >= interval,
+                    SnapshotInterval::Tiles(interval) => (merged_count + 1) % interval.max(1) == 0,
+                };
+                if is_due {
+                    (snapshots.take)(film);
+                    last_snapshot = Instant::now();
+                }
+            }
+        }
+    });
+}
+
+/// Render a pass of samples over the tile, one row of pixels at a time. The
+/// rows are rendered in parallel, so when only a few expensive tiles remain,
+/// idle threads steal rows from them rather than waiting for them to finish.
+///
+/// * seed - A seed that is unique to the pass. Each row's sampler is cloned
+///   with it and mixes it with each pixel, so the image doesn't depend on
+///   which thread renders which row.
+#[allow(clippy::too_many_arguments)]
+fn render_tile_rows<Scene: Send + Sync, Sampler: IncrementalSampler + Send + Sync>(
+    camera: &(dyn Camera + Send + Sync),
+    scene: &Scene,
+    tile: &Tile,
+    film_tile: &mut FilmTile,
+    filter: &PrecomputedFilter,
+    sampler: &Sampler,
+    seed: u64,
+    ray_tracer: &(dyn RayTracer<Scene, Sampler> + Send + Sync),
+    max_depth: usize,
+    options: &RenderOptions,
+) {
+    let bounds = tile.sample_bounds;
+    let row_film_tiles: Vec<FilmTile> = (bounds.min.y..bounds.max.y)
+        .into_par_iter()
+        .filter_map(|y| {
+            let row = Tile {
+                sample_bounds: Bounds2::new(point2(bounds.min.x, y), point2(bounds.max.x, y + 1)),
+                row_major_index: tile.row_major_index,
+                progressive_pass: tile.progressive_pass,
+            };
+            let mut row_film_tile = film_tile.sub_tile(
+                &row.sample_bounds,
+                filter.half_width(),
+                filter.half_height(),
+            )?;
+            let mut sampler = sampler.clone_with_seed(seed);
             render_tile::<Scene, Sampler>(
                 camera,
-                film,
                 scene,
-                tile,
+                &row,
+                &mut row_film_tile,
                 filter,
                 &mut sampler,
                 ray_tracer,
                 max_depth,
-            )
+                options,
+            );
+            Some(row_film_tile)
         })
         .collect();
 
-    for ft in film_tiles {
-        film.merge_tile(&ft);
+    // Rows are merged in order, so the sums are the same from run to run.
+    for row_film_tile in row_film_tiles {
+        film_tile.merge_sub_tile(&row_film_tile);
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn render_tile<Scene, Sampler: IncrementalSampler>(
     camera: &dyn Camera,
-    film: &Film,
     scene: &Scene,
     tile: &Tile,
-    filter: &dyn Filter,
+    film_tile: &mut FilmTile,
+    filter: &PrecomputedFilter,
     sampler: &mut Sampler,
     ray_tracer: &dyn RayTracer<Scene, Sampler>,
     max_depth: usize,
-) -> Option<FilmTile> {
+    options: &RenderOptions,
+) {
     let sample_bounds = tile.sample_bounds;
-
-    if let Some(mut film_tile) =
-        film.tile(&sample_bounds, filter.half_width(), filter.half_height())
+    let aovs = film_tile.aovs();
+    let differential_scale = 1.0 / (sampler.samples_per_pixel() as f32).sqrt();
+    let view_direction = camera.view_direction();
+    for pixel_min_corner in sample_bounds
+        .range()
+        .into_iter()
+        .filter(|p| tile.visits(*p))
     {
-        for pixel_min_corner in sample_bounds.range() {
-            let mut sample_count = 0;
-            sampler.start_pixel(pixel_min_corner);
-            loop {
-                let sample = sampler.get_camera_sample(pixel_min_corner);
-                let (ray, _differential, weight) = camera.generate_ray_differential(&sample);
-                // TODO: Scale differential.
-
-                let radiance = if weight > 0.0 {
-                    ray_tracer.incoming_radiance(&ray, scene, sampler, 0, max_depth)
-                } else {
-                    RgbaSpectrum::transparent()
-                };
+        let mut sample_count = 0;
+        sampler.start_pixel(pixel_min_corner);
+        loop {
+            let sample = sampler.get_camera_sample(pixel_min_corner);
+            let (ray, differential, weight) = camera.generate_ray_differential(&sample);
+            let mut ray = ray.with_differential(differential);
+            ray.scale_differentials(differential_scale);
 
-                // println!(
-                //     "At ({}, {})\tsample {}\tradiance {}",
-                //     pixel_min_corner.x,
-                //     pixel_min_corner.y,
-                //     sample_count,
-                //     radiance.a()
-                // );
+            let mut radiance = if weight > 0.0 {
+                ray_tracer.incoming_radiance(&ray, scene, sampler, 0, max_depth)
+            } else {
+                RgbaSpectrum::transparent()
+            };
+            if let Some(max_radiance) = options.max_sample_radiance {
+                radiance = clamp_radiance(radiance, max_radiance);
+            }
 
-                // TODO: Check for NaN or Inf values in spectrum.
+            // println!(
+            //     "At ({}, {})\tsample {}\tradiance {}",
+            //     pixel_min_corner.x,
+            //     pixel_min_corner.y,
+            //     sample_count,
+            //     radiance.a()
+            // );
 
-                film_tile.add_sample(&sample.film_point, &radiance, weight, filter);
+            // TODO: Check for NaN or Inf values in spectrum.
 
-                sample_count += 1;
-                if !sampler.start_next_sample() {
-                    break;
+            film_tile.add_sample(&sample.film_point, &radiance, weight, filter);
+            if weight > 0.0 {
+                for aov in &aovs {
+                    if let Some(value) = ray_tracer.aov(*aov, &ray, &view_direction, scene) {
+                        film_tile.add_sample_aov(*aov, &sample.film_point, &value, filter);
+                    }
                 }
             }
+
+            sample_count += 1;
+            sampler.record_radiance(&radiance);
+            if options
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                break;
+            }
+            if !sampler.start_next_sample() {
+                break;
+            }
         }
-        Some(film_tile)
-    } else {
-        None
     }
+    ray_tracer.finish_tile();
+}
+
+/// Scale the red, green, and blue components of the radiance down, preserving
+/// their ratios, so that none of them exceed `max_radiance`. Alpha is left
+/// unchanged.
+pub(crate) fn clamp_radiance(radiance: RgbaSpectrum, max_radiance: f32) -> RgbaSpectrum {
+    let max_component = radiance.r().max(radiance.g()).max(radiance.b());
+    if max_component <= max_radiance {
+        return radiance;
+    }
+    let a = radiance.a();
+    let mut clamped = radiance * (max_radiance / max_component);
+    clamped.set_a(a);
+    clamped
 }
 
 /// A tile in an image's sample bounds that can be rendered in parallel with
@@ -142,48 +541,144 @@ struct Tile {
 
     /// The index of the tile in a vector represeting a row-major grid of tiles.
     ///
-    /// This index is mostly useful as a unique ID for each tile. We will be
-    /// able to use this as a unique pseudo-random number generator seed for
-    /// each tile.
+    /// This index is mostly useful as a unique ID for each tile, such as for
+    /// looking up the tile's priority.
     row_major_index: usize,
+
+    /// If present, only the tile's pixels that are visited in this pass of a
+    /// progressive render are rendered.
+    progressive_pass: Option<ProgressivePass>,
+}
+
+/// One of the passes that a progressive render makes over the image.
+#[derive(Clone, Copy)]
+struct ProgressivePass {
+    index: usize,
+
+    /// The top-left corner of the image's sample bounds, which the pixels'
+    /// positions in the blue-noise mask are relative to.
+    origin: Point2<i32>,
 }
 
 impl Tile {
-    /// Return a vector of 16-by-16 tiles that span the given image sample
-    /// bounds.
-    pub fn span_image_sample_bounds(image_sample_bounds: &Bounds2<i32>) -> Vec<Tile> {
-        const TILE_SIZE: usize = 16;
+    /// Return a vector of tiles with the given shape that span the given image
+    /// sample bounds.
+    pub fn span_image_sample_bounds(
+        image_sample_bounds: &Bounds2<i32>,
+        shape: TileShape,
+    ) -> Vec<Tile> {
+        let tile_size = shape.size();
         let image_sample_extent = image_sample_bounds.diagonal();
-        let tile_count_x = (image_sample_extent.x as usize + TILE_SIZE - 1) / TILE_SIZE;
-        let tile_count_y = (image_sample_extent.y as usize + TILE_SIZE - 1) / TILE_SIZE;
+        let tile_count_x = (image_sample_extent.x as usize + tile_size.x - 1) / tile_size.x;
+        let tile_count_y = (image_sample_extent.y as usize + tile_size.y - 1) / tile_size.y;
 
         let xs = 0..tile_count_x;
         let ys = 0..tile_count_y;
         ys.flat_map(|y| xs.clone().map(move |x| (x, y)))
             .map(|(x, y)| Tile {
-                sample_bounds: Self::tile_sample_bounds(image_sample_bounds, x, y),
+                sample_bounds: Self::tile_sample_bounds(image_sample_bounds, tile_size, x, y),
                 row_major_index: y * tile_count_x + x,
+                progressive_pass: None,
             })
             .collect()
     }
 
+    /// Return a copy of the tile that only renders the pixels that are
+    /// visited in the given pass of a progressive render.
+    fn with_progressive_pass(&self, progressive_pass: ProgressivePass) -> Tile {
+        Tile {
+            sample_bounds: self.sample_bounds,
+            row_major_index: self.row_major_index,
+            progressive_pass: Some(progressive_pass),
+        }
+    }
+
+    /// Return whether the pixel with the given minimum corner is rendered
+    /// when the tile is rendered.
+    fn visits(&self, pixel_min_corner: Point2<i32>) -> bool {
+        match self.progressive_pass {
+            Some(pass) => progressive_pass(pixel_min_corner - pass.origin) == pass.index,
+            None => true,
+        }
+    }
+
     fn tile_sample_bounds(
         image_sample_bounds: &Bounds2<i32>,
+        tile_size: Vector2<usize>,
         tile_x_index: usize,
         tile_y_index: usize,
     ) -> Bounds2<i32> {
-        const TILE_SIZE: usize = 16;
         let min = Point2::new(
-            image_sample_bounds.min.x + (tile_x_index * TILE_SIZE) as i32,
-            image_sample_bounds.min.y + (tile_y_index * TILE_SIZE) as i32,
+            image_sample_bounds.min.x + (tile_x_index * tile_size.x) as i32,
+            image_sample_bounds.min.y + (tile_y_index * tile_size.y) as i32,
         );
         let max = Point2::new(
             // Tiles on the bottom and right edges might extend beyond the image
             // sample bounds, so be sure to limit the tile sample bounds to the
             // image sample bounds.
-            (min.x + TILE_SIZE as i32).min(image_sample_bounds.max.x),
-            (min.y + TILE_SIZE as i32).min(image_sample_bounds.max.y),
+            (min.x + tile_size.x as i32).min(image_sample_bounds.max.x),
+            (min.y + tile_size.y as i32).min(image_sample_bounds.max.y),
         );
         Bounds2::new(min, max)
     }
 }
+
+#[cfg(test)]
+mod tile_tests {
+    use super::{ProgressivePass, Tile, TileShape, PROGRESSIVE_PASS_COUNT};
+    use crate::geometry::bounds::Bounds2;
+    use cgmath::point2;
+
+    #[test]
+    fn progressive_passes_visit_each_pixel_once() {
+        let bounds = Bounds2::new(point2(-2, -2), point2(40, 30));
+        let tiles = Tile::span_image_sample_bounds(&bounds, TileShape::Square);
+        for pixel in bounds.range() {
+            let tile = tiles
+                .iter()
+                .find(|tile| tile.sample_bounds.contains(&pixel))
+                .unwrap();
+            let visits = (0..PROGRESSIVE_PASS_COUNT)
+                .filter(|index| {
+                    tile.with_progressive_pass(ProgressivePass {
+                        index: *index,
+                        origin: bounds.min,
+                    })
+                    .visits(pixel)
+                })
+                .count();
+            assert_eq!(visits, 1);
+        }
+    }
+
+    #[test]
+    fn scanline_tiles_span_bounds() {
+        let bounds = Bounds2::new(point2(-2, -2), point2(40, 5));
+        let tiles = Tile::span_image_sample_bounds(&bounds, TileShape::Scanline(32));
+        assert_eq!(tiles.len(), 14);
+        assert!(tiles
+            .iter()
+            .all(|tile| tile.sample_bounds.max.y - tile.sample_bounds.min.y == 1));
+        let area: i32 = tiles.iter().map(|tile| tile.sample_bounds.area()).sum();
+        assert_eq!(area, bounds.area());
+    }
+}
+
+#[cfg(test)]
+mod clamp_radiance_tests {
+    use super::clamp_radiance;
+    use crate::{color::RgbaSpectrum, test::ApproxEq};
+
+    #[test]
+    fn bright_radiance_is_scaled_down_preserving_hue() {
+        let radiance = RgbaSpectrum::from_rgba(20.0, 10.0, 5.0, 1.0);
+        clamp_radiance(radiance, 4.0)
+            .assert_approx_eq(&RgbaSpectrum::from_rgba(4.0, 2.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn dim_radiance_is_unchanged() {
+        let radiance = RgbaSpectrum::from_rgba(0.5, 0.25, 0.0, 1.0);
+        clamp_radiance(radiance, 4.0).assert_approx_eq(&radiance);
+    }
+}
@@ -0,0 +1,45 @@
+use crate::{
+    bsdf::Bsdf,
+    interaction::{OffsetRayOrigin, SurfaceInteraction},
+    sampler::IncrementalSampler,
+    sampling,
+    scene::Scene,
+};
+use cgmath::InnerSpace;
+
+/// Estimate the fraction of the hemisphere on the side of the surface that
+/// faces the viewer that isn't occluded by geometry in the scene. Directions
+/// are cosine-weighted, so occluders near the normal count for more, as they
+/// do for diffuse ambient light.
+///
+/// * sample_count - The number of rays used to estimate the occlusion. If it's
+///   zero, the surface is treated as unoccluded.
+/// * max_distance - The maximum distance at which geometry occludes.
+pub(crate) fn ambient_occlusion<S: IncrementalSampler>(
+    interaction: &SurfaceInteraction,
+    scene: &Scene,
+    sampler: &mut S,
+    sample_count: usize,
+    max_distance: f32,
+) -> f32 {
+    if sample_count == 0 {
+        return 1.0;
+    }
+
+    let frame = Bsdf::new(interaction);
+    let side = interaction
+        .neg_ray_direction
+        .dot(interaction.shading_geometry.normal)
+        .signum();
+    let mut unoccluded = 0;
+    for _ in 0..sample_count {
+        let mut w = sampling::cosine_sample_hemisphere(&sampler.get_2d());
+        w.z *= side;
+        let mut ao_ray = interaction.spawn_ray(&frame.transform_local_to_world(&w));
+        ao_ray.t_max = max_distance;
+        if scene.ray_intersection(&ao_ray).is_none() {
+            unoccluded += 1;
+        }
+    }
+    unoccluded as f32 / sample_count as f32
+}
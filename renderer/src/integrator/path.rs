@@ -0,0 +1,461 @@
+use crate::{
+    bsdf::{Bsdf, BxdfType},
+    color::RgbaSpectrum,
+    interaction::{OffsetRayOrigin, SurfaceInteraction},
+    light::{Light, LightBvh, LightDistribution},
+    ray::Ray,
+    sampler::IncrementalSampler,
+    scene::Scene,
+    statistics::MaterialStatistics,
+    TransportMode,
+};
+use cgmath::{InnerSpace, Vector3};
+use std::time::Instant;
+
+use super::{aov, clamp_radiance, Aov, RayTracer};
+
+/// The default number of bounces after which Russian roulette may terminate a
+/// path.
+const DEFAULT_RUSSIAN_ROULETTE_DEPTH: usize = 3;
+
+/// The lowest probability with which Russian roulette will terminate a path.
+/// This keeps paths that carry a lot of light from continuing indefinitely.
+const MIN_TERMINATION_PROBABILITY: f32 = 0.05;
+
+/// A ray tracer that solves the light transport equation with unbiased path
+/// tracing. This accounts for indirect lighting as well as direct lighting.
+///
+/// At each surface interaction along a path the direct lighting is estimated by
+/// sampling each light source, and the path is then extended in a direction
+/// that is sampled from the surface's BSDF. Paths are cut off at the maximum
+/// depth, and once a path has made more than a given number of bounces Russian
+/// roulette is used to terminate paths that are unlikely to carry much light.
+pub struct PathTracer {
+    /// The number of bounces that a path must make before it can be terminated
+    /// with Russian roulette.
+    russian_roulette_depth: usize,
+
+    /// Render-time statistics for each material. These are only collected if
+    /// the ray tracer was created with `with_statistics`.
+    statistics: Option<MaterialStatistics>,
+
+    /// If present, the radiance that each path vertex after the first
+    /// contributes is clamped to this value. See `with_indirect_clamp`.
+    max_indirect_radiance: Option<f32>,
+
+    /// If present, a single light chosen with this is sampled at each surface
+    /// interaction. Otherwise every light is sampled.
+    light_selection: Option<LightSelection>,
+
+    /// If present, near-specular lobes are roughened to this microfacet
+    /// roughness once a path has scattered off a non-specular surface. See
+    /// `with_regularization`.
+    regularization: Option<f32>,
+}
+
+/// The ways in which the path tracer can choose a single light to sample.
+enum LightSelection {
+    /// Lights are chosen in the same proportions at every point.
+    Distribution(LightDistribution),
+
+    /// Lights are chosen by how much they could contribute to each point.
+    Bvh(LightBvh),
+}
+
+impl LightSelection {
+    fn count(&self) -> usize {
+        match self {
+            LightSelection::Distribution(distribution) => distribution.count(),
+            LightSelection::Bvh(bvh) => bvh.count(),
+        }
+    }
+
+    fn sample(&self, interaction: &SurfaceInteraction, u: f32) -> Option<(usize, f32)> {
+        match self {
+            LightSelection::Distribution(distribution) => distribution.sample(u),
+            LightSelection::Bvh(bvh) => bvh.sample(
+                &interaction.point,
+                Some(&interaction.shading_geometry.normal),
+                u,
+            ),
+        }
+    }
+}
+
+impl PathTracer {
+    /// Create a path tracer.
+    ///
+    /// * russian_roulette_depth - The number of bounces that a path must make
+    ///   before it can be terminated with Russian roulette.
+    pub fn new(russian_roulette_depth: usize) -> Self {
+        Self {
+            russian_roulette_depth,
+            statistics: None,
+            max_indirect_radiance: None,
+            light_selection: None,
+            regularization: None,
+        }
+    }
+
+    /// Create a path tracer that records render-time statistics for each
+    /// material that it shades.
+    ///
+    /// * russian_roulette_depth - The number of bounces that a path must make
+    ///   before it can be terminated with Russian roulette.
+    pub fn with_statistics(russian_roulette_depth: usize) -> Self {
+        Self {
+            russian_roulette_depth,
+            statistics: Some(MaterialStatistics::new()),
+            max_indirect_radiance: None,
+            light_selection: None,
+            regularization: None,
+        }
+    }
+
+    /// Return the path tracer, modified so that the red, green, and blue
+    /// components of the indirect light contributed by each path vertex after
+    /// the first are clamped to `max_radiance`.
+    ///
+    /// This suppresses fireflies from rare high-energy indirect paths while
+    /// leaving direct lighting, which is rarely the source of fireflies,
+    /// unbiased.
+    pub fn with_indirect_clamp(mut self, max_radiance: f32) -> Self {
+        self.max_indirect_radiance = Some(max_radiance);
+        self
+    }
+
+    /// Return the path tracer, modified so that once a path has scattered off
+    /// a diffuse or glossy surface, any smoother lobe that it meets is
+    /// roughened to the microfacet roughness `min_alpha`.
+    ///
+    /// This trades a little blur in caustics, such as the light that a
+    /// polished fastener reflects onto a matte part, for far less noise in
+    /// them. Camera rays and chains of mirror reflections from the camera are
+    /// unaffected, so reflections seen directly stay sharp. Values around 0.1
+    /// to 0.3 work well.
+    pub fn with_regularization(mut self, min_alpha: f32) -> Self {
+        self.regularization = Some(min_alpha);
+        self
+    }
+
+    /// Return the path tracer, modified so that the direct lighting at each
+    /// surface interaction is estimated by sampling a single light chosen from
+    /// the distribution, rather than by sampling every light. This makes each
+    /// path much cheaper in scenes with many lights, at the cost of more noise
+    /// per path.
+    ///
+    /// * light_distribution - A distribution over the scene's lights, such as
+    ///   one created with `LightDistribution::from_power`. If it doesn't have
+    ///   one entry for each of the scene's lights, every light is sampled.
+    pub fn with_light_distribution(mut self, light_distribution: LightDistribution) -> Self {
+        self.light_selection = Some(LightSelection::Distribution(light_distribution));
+        self
+    }
+
+    /// Return the path tracer, modified so that the direct lighting at each
+    /// surface interaction is estimated by sampling a single light that the
+    /// hierarchy chooses for the interaction's point. This suits scenes with
+    /// many small lights, most of which are far from any given point.
+    ///
+    /// * light_bvh - A hierarchy built over the scene's lights. If it wasn't
+    ///   built over the same number of lights as the scene has, every light is
+    ///   sampled.
+    pub fn with_light_bvh(mut self, light_bvh: LightBvh) -> Self {
+        self.light_selection = Some(LightSelection::Bvh(light_bvh));
+        self
+    }
+
+    /// Return the number of bounces that a path must make before it can be
+    /// terminated with Russian roulette.
+    pub fn russian_roulette_depth(&self) -> usize {
+        self.russian_roulette_depth
+    }
+
+    /// Estimate the radiance that is scattered in the direction `wo` at the
+    /// surface interaction due to light arriving directly from the light
+    /// sources. Return the estimate and the number of shadow rays that were
+    /// traced.
+    fn direct_lighting<S: IncrementalSampler>(
+        &self,
+        interaction: &SurfaceInteraction,
+        bsdf: &Bsdf,
+        scene: &Scene,
+        sampler: &mut S,
+    ) -> (RgbaSpectrum, usize) {
+        match &self.light_selection {
+            Some(selection) if selection.count() == scene.lights().len() => {
+                let u = sampler.get_1d();
+                match selection.sample(interaction, u) {
+                    // The light's contribution is divided by the probability
+                    // that it's chosen, so that the estimate is unbiased.
+                    Some((index, pmf)) if pmf > 0.0 => {
+                        let (radiance, shadow_rays) = Self::light_contribution(
+                            &scene.lights()[index],
+                            interaction,
+                            bsdf,
+                            scene,
+                            sampler,
+                        );
+                        (radiance / pmf, shadow_rays)
+                    }
+                    _ => (RgbaSpectrum::black(), 0),
+                }
+            }
+            _ => scene.lights().iter().fold(
+                (RgbaSpectrum::black(), 0),
+                |(radiance, shadow_rays), light| {
+                    let (light_radiance, light_shadow_rays) =
+                        Self::light_contribution(light, interaction, bsdf, scene, sampler);
+                    (radiance + light_radiance, shadow_rays + light_shadow_rays)
+                },
+            ),
+        }
+    }
+
+    /// Estimate the radiance that is scattered in the direction `wo` at the
+    /// surface interaction due to light arriving directly from the light.
+    /// Return the estimate and the number of shadow rays that were traced.
+    fn light_contribution<S: IncrementalSampler>(
+        light: &Light,
+        interaction: &SurfaceInteraction,
+        bsdf: &Bsdf,
+        scene: &Scene,
+        sampler: &mut S,
+    ) -> (RgbaSpectrum, usize) {
+        let normal = interaction.shading_geometry.normal;
+        let wo = interaction.neg_ray_direction.normalize();
+
+        let sample = sampler.get_2d();
+        let (incident_light, wi, vis, pdf) = light.sample_li(interaction, &sample);
+        if incident_light.is_black() || pdf == 0.0 {
+            return (RgbaSpectrum::black(), 0);
+        }
+
+        let f = bsdf.f(&wo, &wi, BxdfType::ALL);
+        if f.is_black() {
+            return (RgbaSpectrum::black(), 0);
+        }
+
+        if vis.unocculuded(scene) {
+            (f * incident_light * (wi.dot(normal).abs() / pdf), 1)
+        } else {
+            (RgbaSpectrum::black(), 1)
+        }
+    }
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        Self::new(DEFAULT_RUSSIAN_ROULETTE_DEPTH)
+    }
+}
+
+impl<'msh, 'mtrl, S: IncrementalSampler> RayTracer<Scene<'msh, 'mtrl>, S> for PathTracer {
+    fn incoming_radiance(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        sampler: &mut S,
+        depth: usize,
+        max_depth: usize,
+    ) -> RgbaSpectrum {
+        let mut radiance = RgbaSpectrum::black();
+
+        // The fraction of radiance arriving at the current path vertex that
+        // makes it back to the ray origin.
+        let mut throughput = RgbaSpectrum::constant(1.0);
+
+        let mut ray = *ray;
+        let mut bounces = depth;
+        let mut specular_bounce = false;
+        let mut any_non_specular_bounce = false;
+
+        loop {
+            let (prim, interaction) = match scene.ray_intersection(&ray) {
+                Some((_t, prim, interaction)) => (prim, interaction),
+                None if bounces == depth => {
+                    return scene
+                        .escaped_radiance(&ray)
+                        .unwrap_or_else(RgbaSpectrum::transparent)
+                }
+                // Add the light from lights that surround the scene, unless
+                // direct lighting at the previous vertex already counted it.
+                None => {
+                    if specular_bounce {
+                        if let Some(escaped) = scene.escaped_radiance(&ray) {
+                            radiance += throughput * escaped;
+                        }
+                    }
+                    break;
+                }
+            };
+            let shading_start = Instant::now();
+
+            // Add the light emitted by an area light that the path hits. Light
+            // from area lights is already counted by direct lighting at the
+            // previous vertex, unless that vertex scattered specularly, where
+            // light sampling can't find it.
+            if bounces == depth || specular_bounce {
+                let wo = interaction.neg_ray_direction.normalize();
+                radiance += throughput * prim.emitted_radiance(&interaction, &wo);
+            }
+
+            let mut bsdf = prim
+                .material
+                .scattering_functions(&interaction, TransportMode::Radiance);
+            if let Some(min_alpha) = self.regularization {
+                if any_non_specular_bounce {
+                    bsdf.regularize(min_alpha);
+                }
+            }
+            let (direct, shadow_rays) = self.direct_lighting(&interaction, &bsdf, scene, sampler);
+            match self.max_indirect_radiance {
+                Some(max_radiance) if bounces > depth => {
+                    radiance += clamp_radiance(throughput * direct, max_radiance);
+                }
+                _ => radiance += throughput * direct,
+            }
+
+            bounces += 1;
+            if bounces >= max_depth {
+                if let Some(statistics) = &self.statistics {
+                    statistics.record(prim.material, shadow_rays, shading_start);
+                }
+                break;
+            }
+
+            // Sample the BSDF to choose the direction in which the path
+            // continues.
+            let wo = interaction.neg_ray_direction.normalize();
+            let sample = sampler.get_2d();
+            let (wi, pdf, f, sampled_type) = bsdf.sample_f(&wo, &sample, BxdfType::ALL);
+            let scatters = !f.is_black() && pdf > 0.0;
+            if scatters {
+                specular_bounce = sampled_type.contains(BxdfType::SPECULAR);
+                any_non_specular_bounce |= !specular_bounce;
+                let cos_theta = wi.dot(interaction.shading_geometry.normal).abs();
+                throughput *= f * (cos_theta / pdf);
+                ray = interaction.spawn_ray(&wi);
+            }
+
+            if let Some(statistics) = &self.statistics {
+                let rays_spawned = shadow_rays + if scatters { 1 } else { 0 };
+                statistics.record(prim.material, rays_spawned, shading_start);
+            }
+
+            if !scatters {
+                break;
+            }
+
+            // Possibly terminate the path with Russian roulette. Surviving
+            // paths are weighted so that the estimate remains unbiased.
+            if bounces > self.russian_roulette_depth {
+                let max_component = throughput.r().max(throughput.g()).max(throughput.b());
+                let q = (1.0 - max_component).max(MIN_TERMINATION_PROBABILITY);
+                if sampler.get_1d() < q {
+                    break;
+                }
+                throughput /= 1.0 - q;
+            }
+        }
+
+        radiance.set_a(1.0);
+        radiance
+    }
+
+    fn aov(
+        &self,
+        aov: Aov,
+        ray: &Ray,
+        view_direction: &Vector3<f32>,
+        scene: &Scene,
+    ) -> Option<RgbaSpectrum> {
+        Some(aov::first_hit_aov(aov, ray, view_direction, scene))
+    }
+
+    fn finish_tile(&self) {
+        if let Some(statistics) = &self.statistics {
+            statistics.finish_tile();
+        }
+    }
+
+    fn statistics(&self) -> Option<&MaterialStatistics> {
+        self.statistics.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod path_tracer_tests {
+    use super::PathTracer;
+    use crate::{
+        color::RgbaSpectrum, integrator::RayTracer, light::Light, material::MatteMaterial,
+        ray::Ray, sampler::ConstantSampler, test::ApproxEq, validation,
+    };
+    use cgmath::{Matrix4, Point3, SquareMatrix, Vector2, Vector3};
+    use typed_arena::Arena;
+
+    #[test]
+    fn lit_diffuse_patch_matches_reflectance() {
+        let mesh_arena = Arena::new();
+        let material_arena = Arena::new();
+        let scene = validation::macbeth_chart_scene(&mesh_arena, &material_arena);
+        let path_tracer = PathTracer::default();
+        let mut sampler = ConstantSampler {};
+
+        // Aim at the center of the top-left patch. Since the chart is flat,
+        // none of the light scattered off of it can return to it, so only
+        // direct lighting contributes.
+        let ray = Ray::new(
+            Point3::new(-3.0, 1.8, -10.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            f32::INFINITY,
+        );
+        let radiance = path_tracer.incoming_radiance(&ray, &scene, &mut sampler, 0, 5);
+        radiance.assert_approx_eq(&validation::macbeth_chart_reflectances()[0]);
+    }
+
+    #[test]
+    fn escaping_camera_ray_is_transparent() {
+        let mesh_arena = Arena::new();
+        let material_arena = Arena::<MatteMaterial>::new();
+        let scene = validation::macbeth_chart_scene(&mesh_arena, &material_arena);
+        let path_tracer = PathTracer::default();
+        let mut sampler = ConstantSampler {};
+
+        let ray = Ray::new(
+            Point3::new(0.0, 0.0, -10.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            f32::INFINITY,
+        );
+        let radiance = path_tracer.incoming_radiance(&ray, &scene, &mut sampler, 0, 5);
+        radiance.assert_approx_eq(&RgbaSpectrum::transparent());
+    }
+
+    #[test]
+    fn escaping_camera_ray_sees_environment() {
+        let mesh_arena = Arena::new();
+        let material_arena = Arena::<MatteMaterial>::new();
+        let mut scene = validation::macbeth_chart_scene(&mesh_arena, &material_arena);
+        let sky = RgbaSpectrum::from_rgb(0.2, 0.4, 0.8);
+        scene.set_lights(vec![Light::infinite_area_light(
+            Matrix4::identity(),
+            vec![sky; 8],
+            Vector2::new(4, 2),
+        )]);
+        let path_tracer = PathTracer::default();
+        let mut sampler = ConstantSampler {};
+
+        let ray = Ray::new(
+            Point3::new(0.0, 0.0, -10.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            f32::INFINITY,
+        );
+        let radiance = path_tracer.incoming_radiance(&ray, &scene, &mut sampler, 0, 5);
+        radiance.assert_approx_eq(&sky);
+
+        // A ray that starts at a later depth is still the first ray of its
+        // path, so it sees the environment too.
+        let radiance = path_tracer.incoming_radiance(&ray, &scene, &mut sampler, 2, 5);
+        radiance.assert_approx_eq(&sky);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::geometry::bounds::Bounds2;
+use cgmath::Vector2;
+
+/// A per-pixel importance map that biases where the renderer spends its
+/// sample budget.
+///
+/// Tiles are rendered in order of decreasing priority, and tiles that cover
+/// important regions of the image (a product logo, for example) are rendered
+/// with additional passes of samples. This improves the perceived quality of
+/// renders that have a fixed budget.
+pub struct PriorityMask {
+    resolution: Vector2<usize>,
+
+    /// The priority of each pixel in [0, 1], in row-major order starting at
+    /// the top-left pixel.
+    values: Vec<f32>,
+
+    /// The number of sample passes that are rendered for a tile whose pixels
+    /// all have a priority of 1.
+    max_passes: usize,
+}
+
+impl PriorityMask {
+    /// Create a priority mask.
+    ///
+    /// * resolution - The resolution of the mask. This should match the
+    ///   resolution of the film being rendered.
+    /// * values - The priority of each pixel in [0, 1], in row-major order
+    ///   starting at the top-left pixel.
+    /// * max_passes - The number of sample passes that are rendered for a tile
+    ///   whose pixels all have a priority of 1. Tiles with a priority of 0 are
+    ///   rendered with a single pass.
+    pub fn new(resolution: Vector2<usize>, values: Vec<f32>, max_passes: usize) -> Self {
+        assert_eq!(values.len(), resolution.x * resolution.y);
+        Self {
+            resolution,
+            values: values.into_iter().map(|v| v.clamp(0.0, 1.0)).collect(),
+            max_passes: max_passes.max(1),
+        }
+    }
+
+    /// Create a priority mask from the luminance of an image. White pixels
+    /// have the highest priority and black pixels have the lowest.
+    ///
+    /// * max_passes - The number of sample passes that are rendered for a tile
+    ///   whose pixels are all white.
+    pub fn from_image(image: &image::DynamicImage, max_passes: usize) -> Self {
+        let luma = image.to_luma8();
+        let resolution = Vector2::new(luma.width() as usize, luma.height() as usize);
+        let values = luma.pixels().map(|p| p.0[0] as f32 / 255.0).collect();
+        Self::new(resolution, values, max_passes)
+    }
+
+    /// Return the average priority of the pixels in the bounds. Bounds that
+    /// extend beyond the mask use the priority of the nearest pixel on the
+    /// mask's edge.
+    pub fn priority(&self, bounds: &Bounds2<i32>) -> f32 {
+        if self.values.is_empty() {
+            return 0.0;
+        }
+        let max_x = self.resolution.x as i32 - 1;
+        let max_y = self.resolution.y as i32 - 1;
+        let mut sum = 0.0;
+        let mut count = 0;
+        for p in bounds.range() {
+            let x = p.x.clamp(0, max_x) as usize;
+            let y = p.y.clamp(0, max_y) as usize;
+            sum += self.values[y * self.resolution.x + x];
+            count += 1;
+        }
+        if count > 0 {
+            sum / count as f32
+        } else {
+            0.0
+        }
+    }
+
+    /// Return the number of sample passes that should be rendered for a tile
+    /// with the given bounds.
+    pub fn pass_count(&self, bounds: &Bounds2<i32>) -> usize {
+        1 + (self.priority(bounds) * (self.max_passes - 1) as f32).round() as usize
+    }
+}
+
+#[cfg(test)]
+mod priority_mask_tests {
+    use super::PriorityMask;
+    use crate::geometry::bounds::Bounds2;
+    use cgmath::{Point2, Vector2};
+
+    #[test]
+    fn pass_count_scales_with_priority() {
+        let mask = PriorityMask::new(Vector2::new(2, 1), vec![0.0, 1.0], 5);
+        let left = Bounds2::new(Point2::new(-3, 0), Point2::new(1, 1));
+        let right = Bounds2::new(Point2::new(1, 0), Point2::new(4, 1));
+        let both = Bounds2::new(Point2::new(0, 0), Point2::new(2, 1));
+        assert_eq!(mask.pass_count(&left), 1);
+        assert_eq!(mask.pass_count(&right), 5);
+        assert_eq!(mask.pass_count(&both), 3);
+    }
+}
@@ -0,0 +1,138 @@
+//! Provides the blue-noise order in which a progressive render visits pixels.
+
+use cgmath::Vector2;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::sync::OnceLock;
+
+/// The number of passes that a progressive render makes over the image. Each
+/// pass visits a different portion of the pixels.
+pub(crate) const PROGRESSIVE_PASS_COUNT: usize = 16;
+
+/// The width and height of the blue-noise mask, which repeats across the
+/// image.
+const MASK_SIZE: usize = 64;
+
+/// The standard deviation, in pixels, of the Gaussian that measures how
+/// clustered the pixels that have been ranked are.
+const ENERGY_SIGMA: f32 = 1.5;
+
+/// Return the pass of a progressive render in which the pixel is visited.
+///
+/// The pixels are ranked by a blue-noise mask, and each pass visits the next
+/// portion of the ranks. The pixels that are visited by the first few passes
+/// are therefore spread evenly over the image, rather than filling it in from
+/// the top.
+///
+/// * offset - The position of the pixel relative to the top-left corner of
+///   the image's sample bounds.
+pub(crate) fn progressive_pass(offset: Vector2<i32>) -> usize {
+    blue_noise_rank(offset) * PROGRESSIVE_PASS_COUNT / (MASK_SIZE * MASK_SIZE)
+}
+
+/// Return the rank of the pixel in the blue-noise mask. Every pixel in a
+/// square of `MASK_SIZE` pixels has a different rank, and the pixels with the
+/// lowest ranks are spread evenly over the square.
+fn blue_noise_rank(offset: Vector2<i32>) -> usize {
+    static RANKS: OnceLock<Vec<u16>> = OnceLock::new();
+    let ranks = RANKS.get_or_init(void_and_cluster);
+    let x = offset.x.rem_euclid(MASK_SIZE as i32) as usize;
+    let y = offset.y.rem_euclid(MASK_SIZE as i32) as usize;
+    ranks[y * MASK_SIZE + x] as usize
+}
+
+/// Rank the pixels of a square mask that wraps around at its edges with the
+/// void-and-cluster method. Each pixel in turn is ranked where the pixels
+/// that have already been ranked are least clustered, as measured by the sum
+/// of Gaussians centered on them.
+fn void_and_cluster() -> Vec<u16> {
+    let radius = (4.0 * ENERGY_SIGMA).ceil() as isize;
+    let kernel: Vec<(isize, isize, f32)> = (-radius..=radius)
+        .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+        .map(|(dx, dy)| {
+            let distance_squared = (dx * dx + dy * dy) as f32;
+            let energy = (-distance_squared / (2.0 * ENERGY_SIGMA * ENERGY_SIGMA)).exp();
+            (dx, dy, energy)
+        })
+        .collect();
+
+    // A little noise breaks the ties between equally empty pixels, which
+    // would otherwise be ranked in a regular pattern.
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    let mut energy: Vec<f32> = (0..MASK_SIZE * MASK_SIZE)
+        .map(|_| rng.gen_range(0.0..1e-3))
+        .collect();
+    let mut ranks: Vec<Option<u16>> = vec![None; MASK_SIZE * MASK_SIZE];
+    for rank in 0..MASK_SIZE * MASK_SIZE {
+        let emptiest = (0..MASK_SIZE * MASK_SIZE)
+            .filter(|i| ranks[*i].is_none())
+            .min_by(|i, j| energy[*i].total_cmp(&energy[*j]))
+            .unwrap();
+        ranks[emptiest] = Some(rank as u16);
+
+        let (x, y) = (
+            (emptiest % MASK_SIZE) as isize,
+            (emptiest / MASK_SIZE) as isize,
+        );
+        for (dx, dy, e) in &kernel {
+            let nx = (x + dx).rem_euclid(MASK_SIZE as isize) as usize;
+            let ny = (y + dy).rem_euclid(MASK_SIZE as isize) as usize;
+            energy[ny * MASK_SIZE + nx] += e;
+        }
+    }
+    ranks.into_iter().map(|rank| rank.unwrap()).collect()
+}
+
+#[cfg(test)]
+mod progressive_tests {
+    use super::{blue_noise_rank, progressive_pass, MASK_SIZE, PROGRESSIVE_PASS_COUNT};
+    use cgmath::Vector2;
+
+    fn mask_offsets() -> impl Iterator<Item = Vector2<i32>> {
+        (0..MASK_SIZE as i32).flat_map(|y| (0..MASK_SIZE as i32).map(move |x| Vector2::new(x, y)))
+    }
+
+    #[test]
+    fn ranks_are_a_permutation() {
+        let mut ranks: Vec<usize> = mask_offsets().map(blue_noise_rank).collect();
+        ranks.sort_unstable();
+        assert_eq!(ranks, (0..MASK_SIZE * MASK_SIZE).collect::<Vec<usize>>());
+        assert_eq!(
+            blue_noise_rank(Vector2::new(3, 5)),
+            blue_noise_rank(Vector2::new(3 + MASK_SIZE as i32, 5 - MASK_SIZE as i32))
+        );
+    }
+
+    #[test]
+    fn each_pass_visits_an_equal_share_of_pixels() {
+        let mut counts = [0; PROGRESSIVE_PASS_COUNT];
+        for offset in mask_offsets() {
+            counts[progressive_pass(offset)] += 1;
+        }
+        let share = MASK_SIZE * MASK_SIZE / PROGRESSIVE_PASS_COUNT;
+        assert!(counts.iter().all(|count| *count == share));
+    }
+
+    #[test]
+    fn first_pixels_are_spread_out() {
+        // 64 pixels in a 64-by-64 square are 8 pixels apart if they're on a
+        // grid. Randomly placed pixels would often be adjacent.
+        let first: Vec<Vector2<i32>> = mask_offsets()
+            .filter(|offset| blue_noise_rank(*offset) < 64)
+            .collect();
+        let wrapped = |d: i32| d.abs().min(MASK_SIZE as i32 - d.abs());
+        for (i, a) in first.iter().enumerate() {
+            for b in &first[i + 1..] {
+                let (dx, dy) = (wrapped(a.x - b.x), wrapped(a.y - b.y));
+                assert!(dx * dx + dy * dy >= 25, "{:?} and {:?}", a, b);
+            }
+        }
+
+        // Every 16-by-16 block has some of them.
+        let mut blocks = [0; 16];
+        for offset in &first {
+            blocks[(offset.y / 16 * 4 + offset.x / 16) as usize] += 1;
+        }
+        assert!(blocks.iter().all(|count| *count >= 2), "{:?}", blocks);
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{
+    bsdf::{Bsdf, BxdfType},
+    color::{RgbaSpectrum, SampledSpectrum, SAMPLE_COUNT},
+    interaction::{OffsetRayOrigin, SurfaceInteraction},
+    ray::Ray,
+    sampler::IncrementalSampler,
+    scene::Scene,
+    TransportMode,
+};
+use cgmath::{InnerSpace, Vector3};
+
+use super::{aov, Aov, RayTracer};
+
+/// The default number of bounces after which Russian roulette may terminate a
+/// path.
+const DEFAULT_RUSSIAN_ROULETTE_DEPTH: usize = 3;
+
+/// The lowest probability with which Russian roulette will terminate a path.
+const MIN_TERMINATION_PROBABILITY: f32 = 0.05;
+
+/// A path tracer that carries radiance and path throughput as full sampled
+/// spectra rather than as RGB triples. The radiance arriving along each camera
+/// ray is converted to XYZ, and then RGB, only once the path is complete and
+/// the result is handed to the film.
+///
+/// Materials and lights are described with RGB colors, so their reflectances
+/// and intensities are upsampled to smooth spectra with
+/// `SampledSpectrum::from_rgb` when they are evaluated.
+///
+/// Dispersive materials, such as glass with an Abbe number, refract each
+/// wavelength in a different direction. When a path meets one, a single
+/// wavelength is chosen at random and the rest of the path carries only that
+/// wavelength, which splits white light into colors in refractions and
+/// caustics.
+pub struct SpectralPathTracer {
+    /// The number of bounces that a path must make before it can be terminated
+    /// with Russian roulette.
+    russian_roulette_depth: usize,
+}
+
+impl SpectralPathTracer {
+    /// Create a spectral path tracer.
+    ///
+    /// * russian_roulette_depth - The number of bounces that a path must make
+    ///   before it can be terminated with Russian roulette.
+    pub fn new(russian_roulette_depth: usize) -> Self {
+        Self {
+            russian_roulette_depth,
+        }
+    }
+
+    /// Estimate the spectral radiance that is scattered in the direction `wo`
+    /// at the surface interaction due to light arriving directly from each
+    /// light source.
+    fn direct_lighting<S: IncrementalSampler>(
+        &self,
+        interaction: &SurfaceInteraction,
+        bsdf: &Bsdf,
+        scene: &Scene,
+        sampler: &mut S,
+    ) -> SampledSpectrum {
+        let normal = interaction.shading_geometry.normal;
+        let wo = interaction.neg_ray_direction.normalize();
+        let mut radiance = SampledSpectrum::black();
+
+        for light in scene.lights() {
+            let sample = sampler.get_2d();
+            let (incident_light, wi, vis, pdf) = light.sample_li(interaction, &sample);
+            if incident_light.is_black() || pdf == 0.0 {
+                continue;
+            }
+
+            let f = bsdf.f(&wo, &wi, BxdfType::ALL);
+            if f.is_black() || !vis.unocculuded(scene) {
+                continue;
+            }
+
+            radiance += SampledSpectrum::from_rgb(&f)
+                * SampledSpectrum::from_rgb(&incident_light)
+                * (wi.dot(normal).abs() / pdf);
+        }
+
+        radiance
+    }
+}
+
+impl Default for SpectralPathTracer {
+    fn default() -> Self {
+        Self::new(DEFAULT_RUSSIAN_ROULETTE_DEPTH)
+    }
+}
+
+impl<'msh, 'mtrl, S: IncrementalSampler> RayTracer<Scene<'msh, 'mtrl>, S> for SpectralPathTracer {
+    fn incoming_radiance(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        sampler: &mut S,
+        depth: usize,
+        max_depth: usize,
+    ) -> RgbaSpectrum {
+        let mut radiance = SampledSpectrum::black();
+        let mut throughput = SampledSpectrum::constant(1.0);
+        let mut ray = *ray;
+        let mut bounces = depth;
+        let mut specular_bounce = false;
+        // The index of the single wavelength that the path carries once it
+        // has met a dispersive material. The sample that chooses it is drawn
+        // up front, where the sampler's dimensions are well stratified, even
+        // though most paths never use it.
+        let wavelength_sample = sampler.get_1d();
+        let mut wavelength_index: Option<usize> = None;
+
+        loop {
+            let (prim, interaction) = match scene.ray_intersection(&ray) {
+                Some((_t, prim, interaction)) => (prim, interaction),
+                None if bounces == 0 => {
+                    return scene
+                        .escaped_radiance(&ray)
+                        .unwrap_or_else(RgbaSpectrum::transparent)
+                }
+                // Add the light from lights that surround the scene, unless
+                // direct lighting at the previous vertex already counted it.
+                None => {
+                    if specular_bounce {
+                        if let Some(escaped) = scene.escaped_radiance(&ray) {
+                            radiance += throughput * SampledSpectrum::from_rgb(&escaped);
+                        }
+                    }
+                    break;
+                }
+            };
+
+            // Add the light emitted by an area light that the path hits unless
+            // direct lighting at the previous vertex already counted it.
+            if bounces == depth || specular_bounce {
+                let wo = interaction.neg_ray_direction.normalize();
+                let emitted = prim.emitted_radiance(&interaction, &wo);
+                radiance += throughput * SampledSpectrum::from_rgb(&emitted);
+            }
+
+            let bsdf = if prim.material.is_dispersive() {
+                let index = match wavelength_index {
+                    Some(index) => index,
+                    None => {
+                        // Divide the path's throughput by the probability of
+                        // choosing the wavelength, so the estimate stays
+                        // unbiased.
+                        let index = ((wavelength_sample * SAMPLE_COUNT as f32) as usize)
+                            .min(SAMPLE_COUNT - 1);
+                        throughput = throughput.single_wavelength(index);
+                        wavelength_index = Some(index);
+                        index
+                    }
+                };
+                prim.material.scattering_functions_at_wavelength(
+                    &interaction,
+                    TransportMode::Radiance,
+                    SampledSpectrum::wavelength(index),
+                )
+            } else {
+                prim.material
+                    .scattering_functions(&interaction, TransportMode::Radiance)
+            };
+            radiance += throughput * self.direct_lighting(&interaction, &bsdf, scene, sampler);
+
+            bounces += 1;
+            if bounces >= max_depth {
+                break;
+            }
+
+            let wo = interaction.neg_ray_direction.normalize();
+            let sample = sampler.get_2d();
+            let (wi, pdf, f, sampled_type) = bsdf.sample_f(&wo, &sample, BxdfType::ALL);
+            if f.is_black() || pdf == 0.0 {
+                break;
+            }
+            specular_bounce = sampled_type.contains(BxdfType::SPECULAR);
+            let cos_theta = wi.dot(interaction.shading_geometry.normal).abs();
+            throughput *= SampledSpectrum::from_rgb(&f) * (cos_theta / pdf);
+            ray = interaction.spawn_ray(&wi);
+
+            if bounces > self.russian_roulette_depth {
+                let q = (1.0 - throughput.max_value()).max(MIN_TERMINATION_PROBABILITY);
+                if sampler.get_1d() < q {
+                    break;
+                }
+                throughput /= 1.0 - q;
+            }
+        }
+
+        radiance.to_rgb()
+    }
+
+    fn aov(
+        &self,
+        aov: Aov,
+        ray: &Ray,
+        view_direction: &Vector3<f32>,
+        scene: &Scene,
+    ) -> Option<RgbaSpectrum> {
+        Some(aov::first_hit_aov(aov, ray, view_direction, scene))
+    }
+}
+
+#[cfg(test)]
+mod spectral_path_tracer_tests {
+    use super::SpectralPathTracer;
+    use crate::{integrator::RayTracer, ray::Ray, sampler::ConstantSampler, validation};
+    use cgmath::{Point3, Vector3};
+    use typed_arena::Arena;
+
+    #[test]
+    fn lit_diffuse_patch_matches_reflectance() {
+        let mesh_arena = Arena::new();
+        let material_arena = Arena::new();
+        let scene = validation::macbeth_chart_scene(&mesh_arena, &material_arena);
+        let ray_tracer = SpectralPathTracer::default();
+        let mut sampler = ConstantSampler {};
+
+        // Aim at the center of the "orange" patch.
+        let ray = Ray::new(
+            Point3::new(-3.0, 0.6, -10.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            f32::INFINITY,
+        );
+        let radiance = ray_tracer.incoming_radiance(&ray, &scene, &mut sampler, 0, 5);
+        let expected = validation::macbeth_chart_reflectances()[6];
+        assert!((radiance.r() - expected.r()).abs() < 0.01);
+        assert!((radiance.g() - expected.g()).abs() < 0.01);
+        assert!((radiance.b() - expected.b()).abs() < 0.01);
+    }
+}
@@ -0,0 +1,396 @@
+use crate::{
+    bsdf::{Bsdf, BxdfType},
+    color::RgbaSpectrum,
+    interaction::{OffsetRayOrigin, SurfaceInteraction},
+    medium::{Medium, MediumInteraction, MediumInterface},
+    ray::Ray,
+    sampler::IncrementalSampler,
+    scene::Scene,
+    TransportMode,
+};
+use cgmath::{InnerSpace, Vector3};
+
+use super::{aov, Aov, RayTracer};
+
+/// The default number of bounces after which Russian roulette may terminate a
+/// path.
+const DEFAULT_RUSSIAN_ROULETTE_DEPTH: usize = 3;
+
+/// The lowest probability with which Russian roulette will terminate a path.
+const MIN_TERMINATION_PROBABILITY: f32 = 0.05;
+
+/// A path tracer that accounts for participating media, such as fog or
+/// translucent solids, in addition to surfaces.
+///
+/// Paths are scattered at points sampled inside media as well as at surfaces,
+/// and light arriving along shadow rays is attenuated by the media that it
+/// travels through. The medium that a path is in is tracked as the path
+/// crosses the surfaces of primitives, using each primitive's medium interface.
+pub struct VolumetricPathTracer {
+    /// The number of bounces that a path must make before it can be terminated
+    /// with Russian roulette.
+    russian_roulette_depth: usize,
+
+    /// The medium that the camera is in. `None` indicates a vacuum.
+    camera_medium: Option<Medium>,
+}
+
+impl VolumetricPathTracer {
+    /// Create a volumetric path tracer.
+    ///
+    /// * russian_roulette_depth - The number of bounces that a path must make
+    ///   before it can be terminated with Russian roulette.
+    /// * camera_medium - The medium that the camera is in. `None` indicates a
+    ///   vacuum.
+    pub fn new(russian_roulette_depth: usize, camera_medium: Option<Medium>) -> Self {
+        Self {
+            russian_roulette_depth,
+            camera_medium,
+        }
+    }
+
+    /// Estimate the radiance that is scattered in the direction `wo` at the
+    /// surface interaction due to light arriving directly from each light
+    /// source.
+    fn surface_direct_lighting<S: IncrementalSampler>(
+        &self,
+        interaction: &SurfaceInteraction,
+        bsdf: &Bsdf,
+        medium_interface: &MediumInterface,
+        scene: &Scene,
+        medium: Option<&Medium>,
+        sampler: &mut S,
+    ) -> RgbaSpectrum {
+        let normal = interaction.shading_geometry.normal;
+        let wo = interaction.neg_ray_direction.normalize();
+        let mut radiance = RgbaSpectrum::black();
+
+        for light in scene.lights() {
+            let sample = sampler.get_2d();
+            let (incident_light, wi, vis, pdf) = light.sample_li(interaction, &sample);
+            if incident_light.is_black() || pdf == 0.0 {
+                continue;
+            }
+
+            let f = bsdf.f(&wo, &wi, BxdfType::ALL);
+            if f.is_black() {
+                continue;
+            }
+
+            // The shadow ray starts in the medium on the light's side of the
+            // surface.
+            let shadow_medium =
+                medium_interface.medium(&wi, &interaction.original_geometry.normal, medium);
+            let tr = vis.beam_transmittance(scene, shadow_medium);
+            radiance += f * incident_light * tr * (wi.dot(normal).abs() / pdf);
+        }
+
+        radiance
+    }
+
+    /// Estimate the radiance that is scattered in the direction `wo` at the
+    /// point in a medium due to light arriving directly from each light
+    /// source.
+    fn medium_direct_lighting<S: IncrementalSampler>(
+        &self,
+        interaction: &MediumInteraction,
+        scene: &Scene,
+        medium: Option<&Medium>,
+        sampler: &mut S,
+    ) -> RgbaSpectrum {
+        let wo = interaction.neg_ray_direction;
+        let mut radiance = RgbaSpectrum::black();
+
+        for light in scene.lights() {
+            let sample = sampler.get_2d();
+            let (incident_light, wi, vis, pdf) =
+                light.sample_li_from(Box::new(*interaction), &sample);
+            if incident_light.is_black() || pdf == 0.0 {
+                continue;
+            }
+
+            let p = interaction.phase.p(&wo, &wi);
+            let tr = vis.beam_transmittance(scene, medium);
+            radiance += incident_light * tr * (p / pdf);
+        }
+
+        radiance
+    }
+}
+
+impl Default for VolumetricPathTracer {
+    fn default() -> Self {
+        Self::new(DEFAULT_RUSSIAN_ROULETTE_DEPTH, None)
+    }
+}
+
+impl<'msh, 'mtrl, S: IncrementalSampler> RayTracer<Scene<'msh, 'mtrl>, S> for VolumetricPathTracer {
+    fn incoming_radiance(
+        &self,
+        ray: &Ray,
+        scene: &Scene<'msh, 'mtrl>,
+        sampler: &mut S,
+        depth: usize,
+        max_depth: usize,
+    ) -> RgbaSpectrum {
+        let mut radiance = RgbaSpectrum::black();
+        let mut throughput = RgbaSpectrum::constant(1.0);
+        let mut ray = *ray;
+        let mut medium: Option<&Medium> = self.camera_medium.as_ref();
+        let mut bounces = depth;
+        let mut specular_bounce = false;
+
+        // Tracks whether the path has interacted with anything, so that camera
+        // rays that escape the scene can be made transparent.
+        let mut interacted = false;
+
+        loop {
+            let intersection = scene.ray_intersection(&ray);
+
+            // Sample a scattering point in the current medium before the
+            // surface that the ray intersects, if any.
+            let medium_interaction = match medium {
+                Some(m) => {
+                    let t_max = intersection.as_ref().map_or(ray.t_max, |(t, _, _)| *t);
+                    let segment = Ray::new(ray.origin, ray.direction, t_max);
+                    let (weight, mi) = m.sample(&segment, &sampler.get_2d());
+                    throughput *= weight;
+                    mi
+                }
+                None => None,
+            };
+            if throughput.is_black() {
+                break;
+            }
+
+            if let Some(mi) = medium_interaction {
+                interacted = true;
+                radiance += throughput * self.medium_direct_lighting(&mi, scene, medium, sampler);
+
+                bounces += 1;
+                if bounces >= max_depth {
+                    break;
+                }
+
+                // Sampling the phase function is exact, so the throughput
+                // doesn't change.
+                let (wi, _pdf) = mi.phase.sample_p(&mi.neg_ray_direction, &sampler.get_2d());
+                ray = mi.spawn_ray(&wi);
+                specular_bounce = false;
+            } else {
+                let (prim, interaction) = match intersection {
+                    Some((_t, prim, interaction)) => (prim, interaction),
+                    None if !interacted && bounces == 0 => {
+                        return scene
+                            .escaped_radiance(&ray)
+                            .unwrap_or_else(RgbaSpectrum::transparent)
+                    }
+                    // Add the light from lights that surround the scene,
+                    // attenuated by any media the path passed through, unless
+                    // direct lighting already counted it.
+                    None => {
+                        if bounces == depth || specular_bounce {
+                            if let Some(escaped) = scene.escaped_radiance(&ray) {
+                                radiance += throughput * escaped;
+                            }
+                        }
+                        break;
+                    }
+                };
+
+                // Surfaces that only bound media don't scatter light, so the
+                // path continues through them without counting a bounce.
+                if prim.material.is_medium_interface() {
+                    medium = prim.medium_interface.medium(
+                        &ray.direction,
+                        &interaction.original_geometry.normal,
+                        medium,
+                    );
+                    ray = interaction.spawn_ray(&ray.direction);
+                    continue;
+                }
+                interacted = true;
+
+                // Add the light emitted by an area light that the path hits
+                // unless direct lighting at the previous vertex already
+                // counted it.
+                if bounces == depth || specular_bounce {
+                    let wo = interaction.neg_ray_direction.normalize();
+                    radiance += throughput * prim.emitted_radiance(&interaction, &wo);
+                }
+
+                let bsdf = prim
+                    .material
+                    .scattering_functions(&interaction, TransportMode::Radiance);
+                radiance += throughput
+                    * self.surface_direct_lighting(
+                        &interaction,
+                        &bsdf,
+                        &prim.medium_interface,
+                        scene,
+                        medium,
+                        sampler,
+                    );
+
+                bounces += 1;
+                if bounces >= max_depth {
+                    break;
+                }
+
+                let wo = interaction.neg_ray_direction.normalize();
+                let sample = sampler.get_2d();
+                let (wi, pdf, f, sampled_type) = bsdf.sample_f(&wo, &sample, BxdfType::ALL);
+                if f.is_black() || pdf == 0.0 {
+                    break;
+                }
+                specular_bounce = sampled_type.contains(BxdfType::SPECULAR);
+                let cos_theta = wi.dot(interaction.shading_geometry.normal).abs();
+                throughput *= f * (cos_theta / pdf);
+
+                // A path that is transmitted through the surface enters the
+                // medium on the other side.
+                medium = prim.medium_interface.medium(
+                    &wi,
+                    &interaction.original_geometry.normal,
+                    medium,
+                );
+                ray = interaction.spawn_ray(&wi);
+            }
+
+            // Possibly terminate the path with Russian roulette.
+            if bounces > self.russian_roulette_depth {
+                let max_component = throughput.r().max(throughput.g()).max(throughput.b());
+                let q = (1.0 - max_component).max(MIN_TERMINATION_PROBABILITY);
+                if sampler.get_1d() < q {
+                    break;
+                }
+                throughput /= 1.0 - q;
+            }
+        }
+
+        radiance.set_a(1.0);
+        radiance
+    }
+
+    fn aov(
+        &self,
+        aov: Aov,
+        ray: &Ray,
+        view_direction: &Vector3<f32>,
+        scene: &Scene,
+    ) -> Option<RgbaSpectrum> {
+        Some(aov::first_hit_aov(aov, ray, view_direction, scene))
+    }
+}
+
+#[cfg(test)]
+mod volumetric_path_tracer_tests {
+    use super::VolumetricPathTracer;
+    use crate::{
+        color::RgbaSpectrum,
+        integrator::RayTracer,
+        material::{InterfaceMaterial, MatteMaterial},
+        medium::{Medium, MediumInterface},
+        primitive::PrimitiveAggregate,
+        ray::Ray,
+        sampler::ConstantSampler,
+        scene::Scene,
+        test::ApproxEq,
+        validation,
+    };
+    use cgmath::{Point3, Vector3};
+    use mesh::{Mesh, MeshBuilder};
+    use typed_arena::Arena;
+
+    /// Return a square in the plane `z` whose geometric normal points in the
+    /// direction `normal_z` along the z axis.
+    fn square(z: f32, normal_z: f32) -> Mesh {
+        let mut positions = vec![
+            Point3::new(-10.0, -10.0, z),
+            Point3::new(10.0, -10.0, z),
+            Point3::new(10.0, 10.0, z),
+            Point3::new(-10.0, 10.0, z),
+        ];
+        if normal_z < 0.0 {
+            positions.reverse();
+        }
+        let normals = vec![Vector3::new(0.0, 0.0, normal_z); 4];
+        MeshBuilder::new(positions, normals, vec![(0, 1, 2), (0, 2, 3)]).build()
+    }
+
+    /// Add a slab of the medium between z = -2 and z = -1 to the Macbeth chart
+    /// scene, in front of the chart.
+    fn add_slab<'msh, 'mtrl>(
+        scene: &mut Scene<'msh, 'mtrl>,
+        mesh_arena: &'msh Arena<Mesh>,
+        interface: &'mtrl InterfaceMaterial,
+        medium: &'mtrl Medium,
+    ) {
+        let medium_interface = MediumInterface::new(Some(medium), None);
+        if let PrimitiveAggregate::Vector(primitives) = &mut scene.primitives {
+            for (z, normal_z) in &[(-2.0, -1.0), (-1.0, 1.0)] {
+                let mesh = mesh_arena.alloc(square(*z, *normal_z));
+                primitives.push(PrimitiveAggregate::from_mesh_with_medium_interface(
+                    mesh,
+                    interface,
+                    medium_interface,
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn absorbing_medium_attenuates_light() {
+        let mesh_arena = Arena::new();
+        let material_arena = Arena::<MatteMaterial>::new();
+        let interface = InterfaceMaterial {};
+        let medium = Medium::homogeneous(
+            RgbaSpectrum::constant(0.1),
+            RgbaSpectrum::constant(0.0),
+            0.0,
+        );
+        let mut scene = validation::macbeth_chart_scene(&mesh_arena, &material_arena);
+        add_slab(&mut scene, &mesh_arena, &interface, &medium);
+        let ray_tracer = VolumetricPathTracer::default();
+        let mut sampler = ConstantSampler {};
+
+        // The camera ray passes through the slab and hits the top-left patch.
+        // With these samples the camera ray isn't absorbed, so only the shadow
+        // ray's transmittance through the slab attenuates the light.
+        let ray = Ray::new(
+            Point3::new(-3.0, 1.8, -10.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            f32::INFINITY,
+        );
+        let radiance = ray_tracer.incoming_radiance(&ray, &scene, &mut sampler, 0, 5);
+        let expected = validation::macbeth_chart_reflectances()[0] * (-0.1f32).exp();
+        radiance.r().assert_approx_eq(&expected.r());
+        radiance.g().assert_approx_eq(&expected.g());
+        radiance.b().assert_approx_eq(&expected.b());
+    }
+
+    #[test]
+    fn ray_through_medium_boundary_can_escape() {
+        let mesh_arena = Arena::new();
+        let material_arena = Arena::<MatteMaterial>::new();
+        let interface = InterfaceMaterial {};
+        let medium = Medium::homogeneous(
+            RgbaSpectrum::constant(0.1),
+            RgbaSpectrum::constant(0.0),
+            0.0,
+        );
+        let mut scene = validation::macbeth_chart_scene(&mesh_arena, &material_arena);
+        add_slab(&mut scene, &mesh_arena, &interface, &medium);
+        let ray_tracer = VolumetricPathTracer::default();
+        let mut sampler = ConstantSampler {};
+
+        // The ray passes through the slab but misses the chart.
+        let ray = Ray::new(
+            Point3::new(9.0, 9.0, -10.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            f32::INFINITY,
+        );
+        let radiance = ray_tracer.incoming_radiance(&ray, &scene, &mut sampler, 0, 5);
+        radiance.assert_approx_eq(&RgbaSpectrum::transparent());
+    }
+}
@@ -1,21 +1,39 @@
 use crate::{
-    bsdf::BxdfType, camera::Camera, color::RgbaSpectrum, filter::Filter, geometry::bounds::Bounds2,
-    interaction::SurfaceInteraction, ray::Ray, sampler::IncrementalSampler, scene::Scene,
+    bsdf::BxdfType, color::RgbaSpectrum, interaction::OffsetRayOrigin, ray::Ray,
+    sampler::IncrementalSampler, scene::Scene, statistics::MaterialStatistics, TransportMode,
 };
-use cgmath::InnerSpace;
-use typed_arena::Arena;
+use cgmath::{InnerSpace, Vector3};
+use std::time::Instant;
 
-use super::RayTracer;
+use super::{aov, Aov, RayTracer};
 
 /// An ray tracer based on Whitted's ray tracing algorithm. This can accurately
 /// compute reflected and transmitted light from specular surfaces like glass,
 /// mirrors, and water. It does not account for indirect lighting effects.
-pub struct WhittedRayTracer {}
+#[derive(Default)]
+pub struct WhittedRayTracer {
+    /// Render-time statistics for each material. These are only collected if
+    /// the ray tracer was created with `with_statistics`.
+    statistics: Option<MaterialStatistics>,
+}
+
+impl WhittedRayTracer {
+    pub fn new() -> Self {
+        Self { statistics: None }
+    }
+
+    /// Create a ray tracer that records render-time statistics for each
+    /// material that it shades.
+    pub fn with_statistics() -> Self {
+        Self {
+            statistics: Some(MaterialStatistics::new()),
+        }
+    }
+}
 
 impl<'msh, 'mtrl, S: IncrementalSampler> RayTracer<Scene<'msh, 'mtrl>, S> for WhittedRayTracer {
     fn incoming_radiance(
         &self,
-        // TODO: Change to ray differential.
         ray: &Ray,
         scene: &Scene,
         sampler: &mut S,
@@ -23,6 +41,8 @@ impl<'msh, 'mtrl, S: IncrementalSampler> RayTracer<Scene<'msh, 'mtrl>, S> for Wh
         max_depth: usize,
     ) -> RgbaSpectrum {
         if let Some((_t, prim, interaction)) = scene.ray_intersection(ray) {
+            let shading_start = Instant::now();
+
             // We will calculate the outgoing radiance along the ray at the
             // surface. Since we ignore all particpating media (like smoke or
             // fog), the outgoing radiance at the intersected surface will equal
@@ -35,13 +55,16 @@ impl<'msh, 'mtrl, S: IncrementalSampler> RayTracer<Scene<'msh, 'mtrl>, S> for Wh
             let wo = interaction.neg_ray_direction;
 
             // Compute scattering functions for surface interaction.
-            let bsdf = prim.material.scattering_functions(&interaction);
+            let bsdf = prim
+                .material
+                .scattering_functions(&interaction, TransportMode::Radiance);
 
-            // // Compute emitted light if ray hit an area light source.
-            // outgoing_radiance += interaction.emitted_radiance(&point_to_ray_origin_direction);
+            // Compute emitted light if ray hit an area light source.
+            outgoing_radiance += prim.emitted_radiance(&interaction, &wo.normalize());
 
             // Add the contribution of each light source.
-            for light in &scene.lights {
+            let mut rays_spawned = 0;
+            for light in scene.lights() {
                 let sample = sampler.get_2d();
                 let (incident_light, wi, vis, pdf) = light.sample_li(&interaction, &sample);
                 if incident_light.is_black() || pdf == 0.0 {
@@ -49,30 +72,101 @@ impl<'msh, 'mtrl, S: IncrementalSampler> RayTracer<Scene<'msh, 'mtrl>, S> for Wh
                 }
 
                 let f = bsdf.f(&wo, &wi, BxdfType::ALL);
-                outgoing_radiance += f * incident_light * (wi.dot(normal).abs() / 1.0);
-                // if !f.is_black() && vis.unocculuded(scene) {
-                //     outgoing_radiance += f * incident_light * (wi.dot(normal).abs() / 1.0);
-                // }
+                if !f.is_black() {
+                    rays_spawned += 1;
+                    if vis.unocculuded(scene) {
+                        outgoing_radiance += f * incident_light * (wi.dot(normal).abs() / pdf);
+                    }
+                }
             }
 
+            // Trace rays for specular reflection and refraction.
             if depth + 1 < max_depth {
-                // Trace rays for specular reflection and refraction.
+                for lobe in &[BxdfType::REFLECTION, BxdfType::TRANSMISSION] {
+                    let sample = sampler.get_2d();
+                    let (wi, pdf, f, _) =
+                        bsdf.sample_f(&wo.normalize(), &sample, *lobe | BxdfType::SPECULAR);
+                    let cos_theta = wi.dot(normal).abs();
+                    if f.is_black() || pdf == 0.0 || cos_theta == 0.0 {
+                        continue;
+                    }
+                    rays_spawned += 1;
+                    let ray = interaction.spawn_ray(&wi);
+                    let incoming_radiance =
+                        self.incoming_radiance(&ray, scene, sampler, depth + 1, max_depth);
+                    outgoing_radiance += f * incoming_radiance * (cos_theta / pdf);
+                }
+            }
+
+            if let Some(statistics) = &self.statistics {
+                statistics.record(prim.material, rays_spawned, shading_start);
             }
 
             outgoing_radiance.set_a(1.0);
             outgoing_radiance
         } else {
-            // TODO: Add back in after rest of Whitted is working.
-            // let mut ray_origin_incoming_radiance = RgbSpectrum::constant(0.0);
-            // for light in &scene.lights {
-            //     ray_origin_incoming_radiance += light.outgoing_radiance_onto_ray(ray);
-            // }
-            // ray_origin_incoming_radiance
-            if depth == 0 {
-                RgbaSpectrum::from_rgba(0.0, 0.0, 0.0, 0.0)
-            } else {
-                RgbaSpectrum::black()
+            // Rays that escape see the lights that surround the scene, if
+            // there are any. Otherwise, camera rays that escape are
+            // transparent.
+            match scene.escaped_radiance(ray) {
+                Some(radiance) => radiance,
+                None if depth == 0 => RgbaSpectrum::transparent(),
+                None => RgbaSpectrum::black(),
             }
         }
     }
+
+    fn aov(
+        &self,
+        aov: Aov,
+        ray: &Ray,
+        view_direction: &Vector3<f32>,
+        scene: &Scene,
+    ) -> Option<RgbaSpectrum> {
+        Some(aov::first_hit_aov(aov, ray, view_direction, scene))
+    }
+
+    fn finish_tile(&self) {
+        if let Some(statistics) = &self.statistics {
+            statistics.finish_tile();
+        }
+    }
+
+    fn statistics(&self) -> Option<&MaterialStatistics> {
+        self.statistics.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod whitted_ray_tracer_tests {
+    use super::WhittedRayTracer;
+    use crate::{
+        integrator::RayTracer, material::MatteMaterial, ray::Ray, sampler::ConstantSampler,
+        validation,
+    };
+    use cgmath::{Point3, Vector3};
+    use typed_arena::Arena;
+
+    #[test]
+    fn statistics_count_shadow_rays() {
+        let mesh_arena = Arena::new();
+        let material_arena = Arena::<MatteMaterial>::new();
+        let scene = validation::macbeth_chart_scene(&mesh_arena, &material_arena);
+        let ray_tracer = WhittedRayTracer::with_statistics();
+        let mut sampler = ConstantSampler {};
+
+        let ray = Ray::new(
+            Point3::new(-3.0, 1.8, -10.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            f32::INFINITY,
+        );
+        ray_tracer.incoming_radiance(&ray, &scene, &mut sampler, 0, 5);
+        RayTracer::<_, ConstantSampler>::finish_tile(&ray_tracer);
+
+        let statistics = RayTracer::<_, ConstantSampler>::statistics(&ray_tracer);
+        let records = statistics.unwrap().records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].samples, 1);
+        assert_eq!(records[0].rays_spawned, scene.lights().len());
+    }
 }
@@ -0,0 +1,161 @@
+use super::SurfaceGeometry;
+use crate::ray::RayDifferential;
+use cgmath::{InnerSpace, Point3, Vector3, Zero};
+
+/// Estimates of how the position and UV coordinates of a surface interaction
+/// change from one pixel to the next on the film plane. These describe the
+/// footprint of a camera sample on the surface, which texture filtering and
+/// bump mapping can use to avoid aliasing.
+///
+/// Every derivative is zero if the ray that found the interaction had no
+/// differentials.
+#[derive(Debug, Clone, Copy)]
+pub struct SurfaceDifferentials {
+    /// The change in position for a one pixel shift in x on the film plane.
+    pub dpdx: Vector3<f32>,
+
+    /// The change in position for a one pixel shift in y on the film plane.
+    pub dpdy: Vector3<f32>,
+
+    pub dudx: f32,
+    pub dvdx: f32,
+    pub dudy: f32,
+    pub dvdy: f32,
+}
+
+impl SurfaceDifferentials {
+    /// Estimate the differentials at a point on a surface by intersecting the
+    /// auxilary rays with the plane that is tangent to the surface at the
+    /// point.
+    ///
+    /// * point - The point where the primary ray intersects the surface.
+    /// * geometry - The geometry of the surface at the point.
+    /// * differential - The auxilary rays of the primary ray.
+    pub fn new(
+        point: Point3<f32>,
+        geometry: &SurfaceGeometry,
+        differential: &RayDifferential,
+    ) -> Self {
+        let normal = geometry.normal;
+        let plane_distance = |origin: Point3<f32>, direction: Vector3<f32>| {
+            normal.dot(point - origin) / normal.dot(direction)
+        };
+        let tx = plane_distance(differential.dx_origin, differential.dx_direction);
+        let ty = plane_distance(differential.dy_origin, differential.dy_direction);
+        if !tx.is_finite() || !ty.is_finite() {
+            return Self::default();
+        }
+
+        let px = differential.dx_origin + differential.dx_direction * tx;
+        let py = differential.dy_origin + differential.dy_direction * ty;
+        let dpdx = px - point;
+        let dpdy = py - point;
+
+        // Solve the overdetermined system dp = dpdu * du + dpdv * dv for du and
+        // dv using the two dimensions that are least aligned with the normal.
+        let (dim0, dim1) = if normal.x.abs() > normal.y.abs() && normal.x.abs() > normal.z.abs() {
+            (1, 2)
+        } else if normal.y.abs() > normal.z.abs() {
+            (0, 2)
+        } else {
+            (0, 1)
+        };
+        let a = [
+            [geometry.dpdu[dim0], geometry.dpdv[dim0]],
+            [geometry.dpdu[dim1], geometry.dpdv[dim1]],
+        ];
+        let (dudx, dvdx) =
+            solve_linear_system_2x2(a, [dpdx[dim0], dpdx[dim1]]).unwrap_or((0.0, 0.0));
+        let (dudy, dvdy) =
+            solve_linear_system_2x2(a, [dpdy[dim0], dpdy[dim1]]).unwrap_or((0.0, 0.0));
+
+        Self {
+            dpdx,
+            dpdy,
+            dudx,
+            dvdx,
+            dudy,
+            dvdy,
+        }
+    }
+}
+
+impl Default for SurfaceDifferentials {
+    fn default() -> Self {
+        Self {
+            dpdx: Vector3::zero(),
+            dpdy: Vector3::zero(),
+            dudx: 0.0,
+            dvdx: 0.0,
+            dudy: 0.0,
+            dvdy: 0.0,
+        }
+    }
+}
+
+/// Solve the linear system `a * x = b` for `x`. Return `None` if `a` is
+/// singular.
+fn solve_linear_system_2x2(a: [[f32; 2]; 2], b: [f32; 2]) -> Option<(f32, f32)> {
+    let det = a[0][0] * a[1][1] - a[0][1] * a[1][0];
+    if det.abs() < 1e-10 {
+        return None;
+    }
+    let x0 = (a[1][1] * b[0] - a[0][1] * b[1]) / det;
+    let x1 = (a[0][0] * b[1] - a[1][0] * b[0]) / det;
+    if x0.is_finite() && x1.is_finite() {
+        Some((x0, x1))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod surface_differentials_tests {
+    use super::SurfaceDifferentials;
+    use crate::{interaction::SurfaceGeometry, ray::RayDifferential, test::ApproxEq};
+    use cgmath::{Point3, Vector3};
+
+    #[test]
+    fn footprint_on_plane_facing_ray() {
+        // A plane at z = 0 whose UV coordinates are scaled copies of x and y.
+        let geometry = SurfaceGeometry {
+            normal: Vector3::new(0.0, 0.0, -1.0),
+            dpdu: Vector3::new(2.0, 0.0, 0.0),
+            dpdv: Vector3::new(0.0, 4.0, 0.0),
+        };
+        let differential = RayDifferential::new(
+            Point3::new(0.1, 0.0, -1.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Point3::new(0.0, 0.2, -1.0),
+            Vector3::new(0.0, 0.0, 1.0),
+        );
+        let differentials =
+            SurfaceDifferentials::new(Point3::new(0.0, 0.0, 0.0), &geometry, &differential);
+
+        assert!(differentials.dpdx.approx_eq(&Vector3::new(0.1, 0.0, 0.0)));
+        assert!(differentials.dpdy.approx_eq(&Vector3::new(0.0, 0.2, 0.0)));
+        assert!(differentials.dudx.approx_eq(&0.05));
+        assert!(differentials.dvdx.approx_eq(&0.0));
+        assert!(differentials.dudy.approx_eq(&0.0));
+        assert!(differentials.dvdy.approx_eq(&0.05));
+    }
+
+    #[test]
+    fn parallel_auxilary_rays_have_no_footprint() {
+        let geometry = SurfaceGeometry {
+            normal: Vector3::new(0.0, 0.0, -1.0),
+            dpdu: Vector3::new(1.0, 0.0, 0.0),
+            dpdv: Vector3::new(0.0, 1.0, 0.0),
+        };
+        let differential = RayDifferential::new(
+            Point3::new(0.1, 0.0, -1.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 0.2, -1.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
+        let differentials =
+            SurfaceDifferentials::new(Point3::new(0.0, 0.0, 0.0), &geometry, &differential);
+        assert!(differentials.dpdx.approx_eq(&Vector3::new(0.0, 0.0, 0.0)));
+        assert!(differentials.dudx.approx_eq(&0.0));
+    }
+}
@@ -1,7 +1,10 @@
+mod differentials;
 mod offset;
 
+pub use differentials::SurfaceDifferentials;
 pub use offset::OffsetRayOrigin;
 
+use crate::ray::Ray;
 use cgmath::{InnerSpace, Matrix4, Point3, Transform, Vector3};
 
 #[derive(Debug, Clone, Copy)]
@@ -23,6 +26,11 @@ pub struct SurfaceInteraction {
     /// perturbed (by bump mapping, for example) before they are used in shading
     /// calculations by the integrator.
     pub shading_geometry: SurfaceGeometry,
+
+    /// Estimates of the footprint of the ray that found the interaction. These
+    /// are zero until `compute_differentials` is called with a ray that has
+    /// differentials.
+    pub differentials: SurfaceDifferentials,
 }
 
 /// Represents the geometry at a specific point on a surface. Includes a normal,
@@ -54,6 +62,7 @@ impl SurfaceInteraction {
             neg_ray_direction,
             original_geometry: SurfaceGeometry { normal, dpdu, dpdv },
             shading_geometry: SurfaceGeometry { normal, dpdu, dpdv },
+            differentials: SurfaceDifferentials::default(),
         }
     }
 
@@ -71,8 +80,21 @@ impl SurfaceInteraction {
             neg_ray_direction,
             original_geometry: SurfaceGeometry { normal, dpdu, dpdv },
             shading_geometry: SurfaceGeometry { normal, dpdu, dpdv },
+            differentials: SurfaceDifferentials::default(),
         }
     }
+
+    /// Estimate the footprint of the ray on the surface from the ray's
+    /// differentials. If the ray has no differentials then the footprint is
+    /// left at zero.
+    pub fn compute_differentials(&mut self, ray: &Ray) {
+        self.differentials = match &ray.differential {
+            Some(differential) => {
+                SurfaceDifferentials::new(self.point, &self.original_geometry, differential)
+            }
+            None => SurfaceDifferentials::default(),
+        };
+    }
 }
 
 // TODO: When interaction transformation is needed, account for error introduced by transformation.
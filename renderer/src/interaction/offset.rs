@@ -66,11 +66,13 @@ fn offset_ray_origin(
         .map(|comp| comp.abs())
         .dot(*interaction_point_error_bound);
     let offset = if ray_direction.dot(*normal) < 0.0 {
-        // The ray is going out of the surface, so offset along normal.
-        offset_along_normal * normal
-    } else {
-        // The ray is going into the surface, so offset against normal.
+        // The ray leaves the side of the surface that the normal faces away
+        // from, so offset against the normal.
         -1.0 * offset_along_normal * normal
+    } else {
+        // The ray leaves the side of the surface that the normal faces, so
+        // offset along the normal.
+        offset_along_normal * normal
     };
     let offset_point = interaction_point + offset;
 
@@ -1,6 +1,8 @@
+pub mod bake;
 mod bsdf;
 pub mod camera;
 pub mod color;
+pub mod cpu;
 mod demo;
 pub mod film;
 pub mod filter;
@@ -8,18 +10,32 @@ mod geometry;
 pub mod integrator;
 mod interaction;
 pub mod light;
+pub mod line_art;
 pub mod material;
+pub mod medium;
 mod number;
+pub mod postprocess;
+pub mod preview;
 pub mod primitive;
+pub mod probe;
 mod ray;
 pub mod sampler;
+mod sampling;
 pub mod scene;
+pub mod shape;
 pub mod simple;
+pub mod statistics;
+pub mod stereo;
+pub mod swatch;
 mod texture;
 mod triangle;
+mod triangle_simd;
+pub mod validation;
 
 pub use integrator::render;
 
+use cgmath::{InnerSpace, Vector3};
+
 #[cfg(test)]
 mod test;
 
@@ -31,3 +47,72 @@ pub enum TransportMode {
     Radiance,
     Importance,
 }
+
+impl TransportMode {
+    /// Return the factor that a BSDF value must be scaled by to account for
+    /// the use of shading normals at a surface interaction.
+    ///
+    /// Shading normals make BSDFs non-symmetric, so light and importance
+    /// scatter differently at the same interaction. Paths that start at the
+    /// camera need no correction, but paths that start at a light must be
+    /// corrected so that light tracing and bidirectional methods converge to
+    /// the same result as camera paths.
+    ///
+    /// * wo - The outgoing direction at the surface interaction.
+    /// * wi - The incident direction at the surface interaction.
+    /// * shading_normal - The surface normal used for shading.
+    /// * geometric_normal - The true normal of the surface geometry.
+    pub fn shading_normal_correction(
+        &self,
+        wo: &Vector3<f32>,
+        wi: &Vector3<f32>,
+        shading_normal: &Vector3<f32>,
+        geometric_normal: &Vector3<f32>,
+    ) -> f32 {
+        match self {
+            Self::Radiance => 1.0,
+            Self::Importance => {
+                let numerator = wo.dot(*shading_normal).abs() * wi.dot(*geometric_normal).abs();
+                let denominator = wo.dot(*geometric_normal).abs() * wi.dot(*shading_normal).abs();
+                if denominator == 0.0 {
+                    0.0
+                } else {
+                    numerator / denominator
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod transport_mode_tests {
+    use super::TransportMode;
+    use crate::test::ApproxEq;
+    use cgmath::Vector3;
+
+    #[test]
+    fn radiance_is_never_corrected() {
+        let ns = Vector3::new(0.0, 0.6, 0.8);
+        let ng = Vector3::new(0.0, 0.0, 1.0);
+        let wo = Vector3::new(0.6, 0.0, 0.8);
+        let wi = Vector3::new(0.0, -0.6, 0.8);
+        TransportMode::Radiance
+            .shading_normal_correction(&wo, &wi, &ns, &ng)
+            .assert_approx_eq(&1.0);
+    }
+
+    #[test]
+    fn importance_is_corrected_by_normal_ratio() {
+        let ng = Vector3::new(0.0, 0.0, 1.0);
+        let wo = Vector3::new(0.6, 0.0, 0.8);
+        let wi = Vector3::new(0.0, -0.6, 0.8);
+        TransportMode::Importance
+            .shading_normal_correction(&wo, &wi, &ng, &ng)
+            .assert_approx_eq(&1.0);
+
+        let ns = Vector3::new(0.0, 0.6, 0.8);
+        TransportMode::Importance
+            .shading_normal_correction(&wo, &wi, &ns, &ng)
+            .assert_approx_eq(&((0.64 * 0.8) / (0.8 * 0.28)));
+    }
+}
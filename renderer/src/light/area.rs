@@ -0,0 +1,216 @@
+use super::{LightFlags, VisibilityTester};
+use crate::{
+    color::RgbaSpectrum, interaction::OffsetRayOrigin, sampling, scene::WorldBound,
+    triangle::Triangle,
+};
+use cgmath::{EuclideanSpace, InnerSpace, Point2, Point3, Vector3};
+use mesh::Mesh;
+use std::f32::consts::PI;
+
+/// The light that a surface emits. This is attached both to the primitives of
+/// an emissive mesh, so that rays that hit the mesh see its emission, and to
+/// the `AreaLight` that samples points on the mesh for direct lighting.
+#[derive(Debug, Clone, Copy)]
+pub struct AreaEmission {
+    /// The radiance that leaves each point on the surface in every direction
+    /// that the surface emits in.
+    pub radiance: RgbaSpectrum,
+
+    /// If true, the surface emits from both of its sides. Otherwise it only
+    /// emits from the side that its geometric normal faces.
+    pub two_sided: bool,
+}
+
+impl AreaEmission {
+    pub fn new(radiance: RgbaSpectrum, two_sided: bool) -> Self {
+        Self {
+            radiance,
+            two_sided,
+        }
+    }
+
+    /// Return the radiance that leaves a point on the surface in the
+    /// direction `w`.
+    ///
+    /// * normal - The surface's geometric normal at the point.
+    /// * w - The direction that the light leaves in.
+    pub fn l(&self, normal: &Vector3<f32>, w: &Vector3<f32>) -> RgbaSpectrum {
+        if self.two_sided || normal.dot(*w) > 0.0 {
+            self.radiance
+        } else {
+            RgbaSpectrum::black()
+        }
+    }
+}
+
+/// A light source that emits from the surface of a mesh, which casts soft
+/// shadows.
+pub struct AreaLight {
+    /// The vertex positions and geometric normal of each of the mesh's
+    /// triangles in world space.
+    triangles: Vec<([Point3<f32>; 3], Vector3<f32>)>,
+
+    /// The sum of the areas of each triangle and the triangles before it.
+    cumulative_areas: Vec<f32>,
+
+    emission: AreaEmission,
+}
+
+impl AreaLight {
+    /// Create a light source that emits from the surface of the mesh. The
+    /// mesh's vertices are copied, so the light doesn't borrow the mesh.
+    ///
+    /// * mesh - A mesh in world space. Triangles with zero area are ignored.
+    /// * emission - The light that the mesh's surface emits. The primitives of
+    ///   the mesh should be given the same emission.
+    pub fn new(mesh: &Mesh, emission: AreaEmission) -> Self {
+        let mut triangles = vec![];
+        let mut cumulative_areas = vec![];
+        let mut total_area = 0.0;
+        for triangle in mesh.triangles().into_iter().map(Triangle) {
+            let area = triangle.area();
+            if area > 0.0 && area.is_finite() {
+                let (p0, p1, p2) = triangle.0.positions();
+                triangles.push(([p0, p1, p2], triangle.geometric_normal()));
+                total_area += area;
+                cumulative_areas.push(total_area);
+            }
+        }
+        Self {
+            triangles,
+            cumulative_areas,
+            emission,
+        }
+    }
+
+    /// Return the total surface area of the light.
+    pub fn area(&self) -> f32 {
+        self.cumulative_areas.last().copied().unwrap_or(0.0)
+    }
+
+    /// Sample a point on the light's surface that is uniformly distributed by
+    /// area, and return the radiance that arrives at the reference point from
+    /// it, ignoring occlusion. Also return the direction from the reference
+    /// point to the sampled point, a visibility tester, and the probability
+    /// density of the direction with respect to solid angle.
+    pub fn sample_li(
+        &self,
+        reference: Box<dyn OffsetRayOrigin>,
+        u: &Point2<f32>,
+    ) -> (RgbaSpectrum, Vector3<f32>, VisibilityTester, f32) {
+        let area = self.area();
+        let origin = reference.non_offset_ray_origin();
+        if area == 0.0 {
+            let vis = VisibilityTester::new(reference, origin);
+            return (RgbaSpectrum::black(), Vector3::unit_z(), vis, 0.0);
+        }
+
+        // Choose a triangle with probability proportional to its area, and
+        // then remap the sample's first dimension so that it's uniformly
+        // distributed over the chosen triangle.
+        let target = u.x * area;
+        let index = self
+            .cumulative_areas
+            .partition_point(|cumulative| *cumulative <= target)
+            .min(self.triangles.len() - 1);
+        let start = if index == 0 {
+            0.0
+        } else {
+            self.cumulative_areas[index - 1]
+        };
+        let triangle_area = self.cumulative_areas[index] - start;
+        let u0 = ((target - start) / triangle_area).clamp(0.0, 1.0);
+        let b = sampling::uniform_sample_triangle(&Point2::new(u0, u.y));
+
+        let ([p0, p1, p2], normal) = &self.triangles[index];
+        let point = Point3::from_vec(
+            b.x * p0.to_vec() + b.y * p1.to_vec() + (1.0 - b.x - b.y) * p2.to_vec(),
+        );
+        let to_light = point - origin;
+        let distance_squared = to_light.magnitude2();
+        let wi = to_light.normalize();
+
+        // Convert the density from area to solid angle.
+        let cos_light = normal.dot(-1.0 * wi).abs();
+        let pdf = if cos_light == 0.0 || distance_squared == 0.0 {
+            0.0
+        } else {
+            distance_squared / (cos_light * area)
+        };
+        let li = self.emission.l(normal, &(-1.0 * wi));
+        let vis = VisibilityTester::new(reference, point);
+        (li, wi, vis, pdf)
+    }
+
+    /// Return an approximation of the light's total emitted power.
+    pub fn power(&self) -> RgbaSpectrum {
+        let sides = if self.emission.two_sided { 2.0 } else { 1.0 };
+        self.emission.radiance * (sides * PI * self.area())
+    }
+
+    /// Return the minimum and maximum corners of a box around the light's
+    /// surface, or `None` if the light has no triangles.
+    pub fn bounds(&self) -> Option<(Point3<f32>, Point3<f32>)> {
+        let mut points = self.triangles.iter().flat_map(|(points, _)| points.iter());
+        let first = *points.next()?;
+        Some(points.fold((first, first), |(min, max), p| {
+            (
+                Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+            )
+        }))
+    }
+
+    pub fn preprocess<S: WorldBound + ?Sized>(&mut self, _scene: &S) {}
+
+    pub fn flags(&self) -> LightFlags {
+        LightFlags::AREA
+    }
+}
+
+#[cfg(test)]
+mod area_light_tests {
+    use super::{AreaEmission, AreaLight};
+    use crate::{color::RgbaSpectrum, interaction::SurfaceInteraction, sampling};
+    use cgmath::{Point3, Vector3};
+    use mesh::MeshBuilder;
+
+    #[test]
+    fn estimates_irradiance_from_a_square() {
+        // A one-sided square of unit radiance, facing down onto a point one
+        // unit below its center.
+        let positions = vec![
+            Point3::new(-0.5, -0.5, 1.0),
+            Point3::new(-0.5, 0.5, 1.0),
+            Point3::new(0.5, 0.5, 1.0),
+            Point3::new(0.5, -0.5, 1.0),
+        ];
+        let normals = vec![Vector3::new(0.0, 0.0, -1.0); 4];
+        let mesh = MeshBuilder::new(positions, normals, vec![(0, 1, 2), (0, 2, 3)]).build();
+        let light = AreaLight::new(&mesh, AreaEmission::new(RgbaSpectrum::constant(1.0), false));
+        assert!((light.area() - 1.0).abs() < 1e-6);
+
+        let reference = SurfaceInteraction::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::unit_z(),
+            Vector3::unit_x(),
+            Vector3::unit_y(),
+        );
+        let samples = sampling::grid_samples_2d(64);
+        let irradiance = samples
+            .iter()
+            .map(|u| {
+                let (li, wi, _vis, pdf) = light.sample_li(Box::new(reference), u);
+                li.r() * wi.z / pdf
+            })
+            .sum::<f32>()
+            / samples.len() as f32;
+
+        // The irradiance below the center of a square of side 2a at height
+        // one is 4 * a / sqrt(1 + a^2) * atan(a / sqrt(1 + a^2)).
+        let c = 0.5 / 1.25f32.sqrt();
+        let expected = 4.0 * c * c.atan();
+        assert!((irradiance - expected).abs() < 0.01 * expected);
+    }
+}
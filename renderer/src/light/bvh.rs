@@ -0,0 +1,343 @@
+use super::Light;
+use cgmath::{EuclideanSpace, InnerSpace, Point3, Vector3};
+
+/// The largest float below one, which keeps remapped samples in [0, 1).
+const ONE_MINUS_EPSILON: f32 = 1.0 - f32::EPSILON / 2.0;
+
+/// A bounding volume hierarchy over a scene's lights, which chooses a light
+/// for each shading point in proportion to a conservative bound on how much
+/// light it could contribute there. This keeps the noise down in scenes with
+/// many small lights, such as the LEDs on a circuit board, where most of the
+/// lights are too far away to matter at any given point.
+///
+/// Lights that surround the scene don't have a position, so they're kept out
+/// of the hierarchy and chosen uniformly instead, with the hierarchy as a
+/// whole counted as one more choice.
+pub struct LightBvh {
+    /// The tree's nodes in depth-first order. An interior node's first child
+    /// directly follows it.
+    nodes: Vec<LightNode>,
+
+    /// The indices of the lights that surround the scene.
+    infinite_lights: Vec<usize>,
+
+    /// For each light in the hierarchy, the directions taken from the root to
+    /// reach its leaf, starting with the least significant bit. A set bit
+    /// means the second child is taken. Other lights don't have a trail.
+    bit_trails: Vec<Option<u64>>,
+}
+
+struct LightNode {
+    min: Point3<f32>,
+    max: Point3<f32>,
+
+    /// The luminance of the total power emitted by the lights in the node.
+    power: f32,
+
+    kind: LightNodeKind,
+}
+
+enum LightNodeKind {
+    /// A node with a single light, which holds the light's index.
+    Leaf(usize),
+
+    /// A node with two children, which holds the index of its second child.
+    Interior(usize),
+}
+
+/// A light's bounds and power, which the tree is built from.
+struct LightBounds {
+    index: usize,
+    min: Point3<f32>,
+    max: Point3<f32>,
+    power: f32,
+}
+
+impl LightBounds {
+    fn centroid(&self) -> Point3<f32> {
+        self.min.midpoint(self.max)
+    }
+}
+
+impl LightBvh {
+    /// Build the hierarchy over the lights. Lights that emit no power are
+    /// never chosen.
+    pub fn new(lights: &[Light]) -> Self {
+        let mut infinite_lights = vec![];
+        let mut bounded_lights = vec![];
+        for (index, light) in lights.iter().enumerate() {
+            let power = light.power().luminance();
+            if power.is_nan() || power <= 0.0 {
+                continue;
+            }
+            match light.bounds() {
+                Some((min, max)) => bounded_lights.push(LightBounds {
+                    index,
+                    min,
+                    max,
+                    power,
+                }),
+                None => infinite_lights.push(index),
+            }
+        }
+        let mut bvh = Self {
+            nodes: vec![],
+            infinite_lights,
+            bit_trails: vec![None; lights.len()],
+        };
+        if !bounded_lights.is_empty() {
+            bvh.build(&mut bounded_lights, 0, 0);
+        }
+        bvh
+    }
+
+    /// Return the number of lights that the hierarchy was built over,
+    /// including those that it never chooses.
+    pub fn count(&self) -> usize {
+        self.bit_trails.len()
+    }
+
+    /// Choose a light for the shading point with a uniformly distributed
+    /// sample in [0, 1). Return the light's index and the probability that
+    /// it's chosen, or `None` if no light can contribute to the point.
+    ///
+    /// * point - The shading point in world space.
+    /// * normal - The surface normal at the shading point, if the point is on
+    ///   a surface. Light can arrive from either side of the surface.
+    pub fn sample(
+        &self,
+        point: &Point3<f32>,
+        normal: Option<&Vector3<f32>>,
+        u: f32,
+    ) -> Option<(usize, f32)> {
+        let infinite_pmf = self.infinite_pmf();
+        let mut u = u;
+        if u < infinite_pmf * self.infinite_lights.len() as f32 {
+            let choice = ((u / infinite_pmf) as usize).min(self.infinite_lights.len() - 1);
+            return Some((self.infinite_lights[choice], infinite_pmf));
+        }
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let bvh_pmf = 1.0 - infinite_pmf * self.infinite_lights.len() as f32;
+        u = ((u - (1.0 - bvh_pmf)) / bvh_pmf).min(ONE_MINUS_EPSILON);
+
+        let mut pmf = bvh_pmf;
+        let mut node_index = 0;
+        loop {
+            match self.nodes[node_index].kind {
+                LightNodeKind::Leaf(light_index) => {
+                    return if self.nodes[node_index].importance(point, normal) > 0.0 {
+                        Some((light_index, pmf))
+                    } else {
+                        None
+                    };
+                }
+                LightNodeKind::Interior(second_child) => {
+                    let first = self.nodes[node_index + 1].importance(point, normal);
+                    let second = self.nodes[second_child].importance(point, normal);
+                    if first == 0.0 && second == 0.0 {
+                        return None;
+                    }
+                    let first_probability = first / (first + second);
+                    if u < first_probability {
+                        node_index += 1;
+                        u = (u / first_probability).min(ONE_MINUS_EPSILON);
+                        pmf *= first_probability;
+                    } else {
+                        node_index = second_child;
+                        u = ((u - first_probability) / (1.0 - first_probability))
+                            .min(ONE_MINUS_EPSILON);
+                        pmf *= 1.0 - first_probability;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Return the probability that `sample` chooses the light with the index
+    /// for the shading point.
+    pub fn pmf(&self, point: &Point3<f32>, normal: Option<&Vector3<f32>>, index: usize) -> f32 {
+        if self.infinite_lights.contains(&index) {
+            return self.infinite_pmf();
+        }
+        let mut trail = match self.bit_trails.get(index) {
+            Some(Some(trail)) => *trail,
+            _ => return 0.0,
+        };
+        let mut pmf = 1.0 - self.infinite_pmf() * self.infinite_lights.len() as f32;
+        let mut node_index = 0;
+        loop {
+            match self.nodes[node_index].kind {
+                LightNodeKind::Leaf(_) => {
+                    return if self.nodes[node_index].importance(point, normal) > 0.0 {
+                        pmf
+                    } else {
+                        0.0
+                    };
+                }
+                LightNodeKind::Interior(second_child) => {
+                    let first = self.nodes[node_index + 1].importance(point, normal);
+                    let second = self.nodes[second_child].importance(point, normal);
+                    if first == 0.0 && second == 0.0 {
+                        return 0.0;
+                    }
+                    if trail & 1 == 0 {
+                        pmf *= first / (first + second);
+                        node_index += 1;
+                    } else {
+                        pmf *= second / (first + second);
+                        node_index = second_child;
+                    }
+                    trail >>= 1;
+                }
+            }
+        }
+    }
+
+    /// Return the probability of choosing each of the lights that surround
+    /// the scene. The hierarchy counts as one more light when it isn't empty.
+    fn infinite_pmf(&self) -> f32 {
+        let choices = self.infinite_lights.len() + if self.nodes.is_empty() { 0 } else { 1 };
+        if choices == 0 {
+            0.0
+        } else {
+            1.0 / choices as f32
+        }
+    }
+
+    /// Add the nodes of the subtree over the lights, and return the index of
+    /// the subtree's root.
+    ///
+    /// * depth - The depth of the subtree's root.
+    /// * trail - The directions taken from the root to reach the subtree.
+    fn build(&mut self, lights: &mut [LightBounds], depth: u32, trail: u64) -> usize {
+        let min = lights.iter().fold(lights[0].min, |min, light| {
+            Point3::new(
+                min.x.min(light.min.x),
+                min.y.min(light.min.y),
+                min.z.min(light.min.z),
+            )
+        });
+        let max = lights.iter().fold(lights[0].max, |max, light| {
+            Point3::new(
+                max.x.max(light.max.x),
+                max.y.max(light.max.y),
+                max.z.max(light.max.z),
+            )
+        });
+        let power = lights.iter().map(|light| light.power).sum();
+        let node_index = self.nodes.len();
+        if let [light] = lights {
+            self.bit_trails[light.index] = Some(trail);
+            self.nodes.push(LightNode {
+                min,
+                max,
+                power,
+                kind: LightNodeKind::Leaf(light.index),
+            });
+            return node_index;
+        }
+
+        // The lights are split in half along the axis in which their
+        // centroids are most spread out. Splitting by count rather than by
+        // position keeps the tree balanced, so its depth never exceeds the
+        // number of bits in a trail, even when many lights share a position.
+        let spread = lights.iter().fold(
+            (lights[0].centroid(), lights[0].centroid()),
+            |(min, max), light| {
+                let c = light.centroid();
+                (
+                    Point3::new(min.x.min(c.x), min.y.min(c.y), min.z.min(c.z)),
+                    Point3::new(max.x.max(c.x), max.y.max(c.y), max.z.max(c.z)),
+                )
+            },
+        );
+        let extent = spread.1 - spread.0;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+        let middle = lights.len() / 2;
+        lights.select_nth_unstable_by(middle, |a, b| {
+            a.centroid()[axis].total_cmp(&b.centroid()[axis])
+        });
+
+        self.nodes.push(LightNode {
+            min,
+            max,
+            power,
+            kind: LightNodeKind::Interior(0),
+        });
+        let (first, second) = lights.split_at_mut(middle);
+        self.build(first, depth + 1, trail);
+        let second_child = self.build(second, depth + 1, trail | (1 << depth));
+        self.nodes[node_index].kind = LightNodeKind::Interior(second_child);
+        node_index
+    }
+}
+
+impl LightNode {
+    /// Return a conservative estimate of how much light the node's lights
+    /// could contribute to the shading point, relative to other nodes.
+    fn importance(&self, point: &Point3<f32>, normal: Option<&Vector3<f32>>) -> f32 {
+        let center = self.min.midpoint(self.max);
+        let radius = 0.5 * (self.max - self.min).magnitude();
+        let to_center = center - *point;
+        // The distance is clamped to the bounds' radius so that points near or
+        // inside of the bounds don't get an unbounded importance.
+        let distance2 = to_center.magnitude2().max(radius * radius);
+        let cos_bound = match normal {
+            Some(normal) if to_center.magnitude2() > radius * radius => {
+                // The smallest angle between the normal, or its opposite, and
+                // any direction towards the bounding sphere.
+                let distance = to_center.magnitude();
+                let cos_theta = (normal.dot(to_center) / (normal.magnitude() * distance)).abs();
+                let theta = cos_theta.min(1.0).acos();
+                let theta_bound = (radius / distance).min(1.0).asin();
+                (theta - theta_bound).max(0.0).cos()
+            }
+            _ => 1.0,
+        };
+        self.power * cos_bound / distance2
+    }
+}
+
+#[cfg(test)]
+mod light_bvh_tests {
+    use super::LightBvh;
+    use crate::{color::RgbaSpectrum, light::Light};
+    use cgmath::{Point3, Vector3};
+
+    #[test]
+    fn prefers_nearby_lights_and_pmf_matches_samples() {
+        let lights: Vec<Light> = (0..8)
+            .map(|i| {
+                Light::point_light(Point3::new(i as f32, 0.0, 1.0), RgbaSpectrum::constant(1.0))
+            })
+            .chain(std::iter::once(Light::uniform_infinite_light(
+                RgbaSpectrum::constant(1.0),
+            )))
+            .collect();
+        let bvh = LightBvh::new(&lights);
+        let point = Point3::new(0.0, 0.0, 0.0);
+        let normal = Vector3::new(0.0, 0.0, 1.0);
+
+        let total: f32 = (0..lights.len())
+            .map(|i| bvh.pmf(&point, Some(&normal), i))
+            .sum();
+        assert!((total - 1.0).abs() < 1e-5);
+        assert!(bvh.pmf(&point, Some(&normal), 0) > bvh.pmf(&point, Some(&normal), 7));
+        // The infinite light is one of two choices at the top.
+        assert!((bvh.pmf(&point, Some(&normal), 8) - 0.5).abs() < 1e-6);
+
+        for k in 0..32 {
+            let u = (k as f32 + 0.5) / 32.0;
+            let (index, pmf) = bvh.sample(&point, Some(&normal), u).unwrap();
+            assert!((bvh.pmf(&point, Some(&normal), index) - pmf).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use super::Light;
+use crate::sampling::Distribution1D;
+
+/// Chooses one of a scene's lights in proportion to its importance, so that a
+/// ray tracer can sample a single light at each shading point instead of every
+/// light.
+pub struct LightDistribution {
+    /// The distribution over the lights' indices, or `None` if there are no
+    /// lights.
+    distribution: Option<Distribution1D>,
+}
+
+impl LightDistribution {
+    /// Create a distribution that chooses each light in proportion to the
+    /// luminance of its total emitted power. Lights that emit no power are
+    /// never chosen, unless none of the lights emit any power, in which case
+    /// each light is equally likely.
+    ///
+    /// Infinite lights' power depends on the size of the scene, which they
+    /// learn when they're preprocessed, so the distribution should be created
+    /// from the lights of a scene.
+    pub fn from_power(lights: &[Light]) -> Self {
+        let powers = lights
+            .iter()
+            .map(|light| light.power().luminance().max(0.0))
+            .collect();
+        Self::from_weights(powers)
+    }
+
+    /// Create a distribution that chooses each of the lights with the same
+    /// probability.
+    pub fn uniform(light_count: usize) -> Self {
+        Self::from_weights(vec![1.0; light_count])
+    }
+
+    fn from_weights(weights: Vec<f32>) -> Self {
+        Self {
+            distribution: if weights.is_empty() {
+                None
+            } else {
+                Some(Distribution1D::new(weights))
+            },
+        }
+    }
+
+    /// Return the number of lights that the distribution chooses between.
+    pub fn count(&self) -> usize {
+        self.distribution
+            .as_ref()
+            .map_or(0, |distribution| distribution.count())
+    }
+
+    /// Choose a light with a uniformly distributed sample in [0, 1). Return the
+    /// light's index and the probability that it's chosen, or `None` if there
+    /// are no lights.
+    pub fn sample(&self, u: f32) -> Option<(usize, f32)> {
+        self.distribution
+            .as_ref()
+            .map(|distribution| distribution.sample_discrete(u))
+    }
+
+    /// Return the probability that `sample` chooses the light with the index.
+    pub fn pmf(&self, index: usize) -> f32 {
+        match &self.distribution {
+            Some(distribution) if index < distribution.count() => distribution.discrete_pdf(index),
+            _ => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod light_distribution_tests {
+    use super::LightDistribution;
+    use crate::{color::RgbaSpectrum, light::Light};
+    use cgmath::Point3;
+
+    #[test]
+    fn chooses_lights_in_proportion_to_power() {
+        let lights = vec![
+            Light::point_light(Point3::new(0.0, 0.0, 1.0), RgbaSpectrum::constant(1.0)),
+            Light::point_light(Point3::new(0.0, 0.0, -1.0), RgbaSpectrum::constant(3.0)),
+        ];
+        let distribution = LightDistribution::from_power(&lights);
+        assert!((distribution.pmf(0) - 0.25).abs() < 1e-6);
+        assert!((distribution.pmf(1) - 0.75).abs() < 1e-6);
+        assert_eq!(distribution.sample(0.2), Some((0, distribution.pmf(0))));
+        assert_eq!(distribution.sample(0.3), Some((1, distribution.pmf(1))));
+        assert_eq!(LightDistribution::from_power(&[]).sample(0.5), None);
+    }
+}
@@ -0,0 +1,386 @@
+//! Reads and blurs the equirectangular environment maps that infinite area
+//! lights are made from. Radiance HDR files and uncompressed, scanline OpenEXR
+//! files are supported.
+
+use crate::color::RgbaSpectrum;
+use cgmath::Vector2;
+use image::codecs::hdr::HdrDecoder;
+use std::convert::TryInto;
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+/// The EXR pixel type of 16-bit floating point channels.
+const HALF_PIXEL_TYPE: i32 = 1;
+
+/// The EXR pixel type of 32-bit floating point channels.
+const FLOAT_PIXEL_TYPE: i32 = 2;
+
+/// Read the environment map at the path, choosing the format by the file's
+/// extension. Return the map's linear radiance values in row-major order,
+/// starting at the top-left texel, and its resolution.
+pub fn read_environment_map(path: &Path) -> io::Result<(Vec<RgbaSpectrum>, Vector2<usize>)> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("hdr") => read_hdr(BufReader::new(File::open(path)?)),
+        Some("exr") => {
+            let mut bytes = vec![];
+            File::open(path)?.read_to_end(&mut bytes)?;
+            read_exr(&bytes)
+        }
+        _ => Err(invalid_data(
+            "environment maps must be Radiance HDR (.hdr) or OpenEXR (.exr) images",
+        )),
+    }
+}
+
+fn read_hdr<R: io::BufRead>(reader: R) -> io::Result<(Vec<RgbaSpectrum>, Vector2<usize>)> {
+    let decoder = HdrDecoder::new(reader).map_err(|e| invalid_data(&e.to_string()))?;
+    let metadata = decoder.metadata();
+    let resolution = Vector2::new(metadata.width as usize, metadata.height as usize);
+    let pixels = decoder
+        .read_image_hdr()
+        .map_err(|e| invalid_data(&e.to_string()))?
+        .into_iter()
+        .map(|pixel| RgbaSpectrum::from_rgb(pixel[0], pixel[1], pixel[2]))
+        .collect();
+    Ok((pixels, resolution))
+}
+
+/// Read a single-part, scanline EXR image with no compression, whose red,
+/// green, and blue channels hold 16-bit or 32-bit floats. An image with only a
+/// luminance (`Y`) channel is read as gray.
+fn read_exr(bytes: &[u8]) -> io::Result<(Vec<RgbaSpectrum>, Vector2<usize>)> {
+    let mut reader = ByteReader { bytes, position: 0 };
+    if reader.take(4)? != [0x76, 0x2f, 0x31, 0x01] {
+        return Err(invalid_data("not an OpenEXR image"));
+    }
+    let version = reader.i32()?;
+    // Tiled, deep, and multi-part images aren't supported.
+    if version & (0x200 | 0x800 | 0x1000) != 0 {
+        return Err(invalid_data("only scanline OpenEXR images are supported"));
+    }
+
+    // Each channel's name and pixel type, sorted by name.
+    let mut channels: Vec<(String, i32)> = vec![];
+    let mut compression = None;
+    let mut data_window = None;
+    loop {
+        let name = reader.string()?;
+        if name.is_empty() {
+            break;
+        }
+        let _type_name = reader.string()?;
+        let size = reader.i32()? as usize;
+        let mut value = ByteReader {
+            bytes: reader.take(size)?,
+            position: 0,
+        };
+        match name.as_str() {
+            "channels" => loop {
+                let channel = value.string()?;
+                if channel.is_empty() {
+                    break;
+                }
+                let pixel_type = value.i32()?;
+                // pLinear, the reserved bytes, and the sampling rates.
+                value.take(12)?;
+                channels.push((channel, pixel_type));
+            },
+            "compression" => compression = Some(value.take(1)?[0]),
+            "dataWindow" => {
+                data_window = Some([value.i32()?, value.i32()?, value.i32()?, value.i32()?])
+            }
+            _ => {}
+        }
+    }
+    if compression != Some(0) {
+        return Err(invalid_data(
+            "only uncompressed OpenEXR environment maps are supported",
+        ));
+    }
+    let [min_x, min_y, max_x, max_y] =
+        data_window.ok_or_else(|| invalid_data("the OpenEXR image has no data window"))?;
+    if max_x < min_x || max_y < min_y {
+        return Err(invalid_data("the OpenEXR image is empty"));
+    }
+    let (width, height) = ((max_x - min_x + 1) as usize, (max_y - min_y + 1) as usize);
+    if channels
+        .iter()
+        .any(|(_, pixel_type)| *pixel_type != HALF_PIXEL_TYPE && *pixel_type != FLOAT_PIXEL_TYPE)
+    {
+        return Err(invalid_data(
+            "only half and float OpenEXR channels are supported",
+        ));
+    }
+    let channel_index = |name: &str| channels.iter().position(|(channel, _)| channel == name);
+    let rgb_channels = match (channel_index("R"), channel_index("G"), channel_index("B")) {
+        (Some(r), Some(g), Some(b)) => [r, g, b],
+        _ => match channel_index("Y") {
+            Some(y) => [y, y, y],
+            None => {
+                return Err(invalid_data(
+                    "the OpenEXR image has no R, G, and B channels",
+                ))
+            }
+        },
+    };
+
+    // Each scanline is stored in its own block. The blocks are found through
+    // the offset table, since they don't need to be stored in order.
+    let channel_sizes: Vec<usize> = channels
+        .iter()
+        .map(|(_, pixel_type)| if *pixel_type == HALF_PIXEL_TYPE { 2 } else { 4 })
+        .collect();
+    // The offset of each channel's values within a scanline.
+    let channel_starts: Vec<usize> = channel_sizes
+        .iter()
+        .scan(0, |start, size| {
+            let channel_start = *start;
+            *start += size * width;
+            Some(channel_start)
+        })
+        .collect();
+    let line_size: usize = channel_sizes.iter().map(|size| size * width).sum();
+    let offsets = (0..height)
+        .map(|_| reader.u64())
+        .collect::<io::Result<Vec<u64>>>()?;
+    let mut pixels = vec![RgbaSpectrum::black(); width * height];
+    for offset in offsets {
+        let mut block = ByteReader {
+            bytes,
+            position: offset as usize,
+        };
+        let y = block.i32()? - min_y;
+        let data_size = block.i32()? as usize;
+        if y < 0 || y as usize >= height || data_size != line_size {
+            return Err(invalid_data("the OpenEXR image has a malformed scanline"));
+        }
+        let data = block.take(data_size)?;
+        for x in 0..width {
+            let mut rgb = [0.0; 3];
+            for (component, channel) in rgb.iter_mut().zip(&rgb_channels) {
+                let size = channel_sizes[*channel];
+                let start = channel_starts[*channel] + x * size;
+                *component = if size == 2 {
+                    f16_to_f32(u16::from_le_bytes([data[start], data[start + 1]]))
+                } else {
+                    f32::from_le_bytes(data[start..start + 4].try_into().unwrap())
+                };
+            }
+            pixels[y as usize * width + x] = RgbaSpectrum::from_rgb(rgb[0], rgb[1], rgb[2]);
+        }
+    }
+    Ok((pixels, Vector2::new(width, height)))
+}
+
+/// The number of box blurs that approximate a Gaussian blur.
+const BOX_BLUR_PASSES: usize = 3;
+
+/// Blur an equirectangular environment map with a Gaussian whose standard
+/// deviation is about `angle` degrees on the sphere. This softens the
+/// reflections and shadows of a map with small, bright features.
+///
+/// The rows wrap around horizontally. Each row is blurred across more texels
+/// the closer it is to a pole, since the texels there cover less of the
+/// sphere. The Gaussian is approximated by repeated box blurs, so the cost
+/// doesn't depend on the angle.
+///
+/// * radiance_map - The map's radiance values in row-major order, starting at
+///   the top-left texel.
+/// * resolution - The width and height of the map in texels.
+/// * angle - The standard deviation of the blur in degrees. The map is
+///   returned unchanged if this isn't positive.
+pub fn blur_environment_map(
+    radiance_map: &[RgbaSpectrum],
+    resolution: Vector2<usize>,
+    angle: f32,
+) -> Vec<RgbaSpectrum> {
+    let mut map = radiance_map.to_vec();
+    if angle.is_nan() || angle <= 0.0 || resolution.x == 0 || resolution.y == 0 {
+        return map;
+    }
+    let radians = angle.to_radians();
+
+    for y in 0..resolution.y {
+        let theta = PI * (y as f32 + 0.5) / resolution.y as f32;
+        let sigma = radians * resolution.x as f32 / (2.0 * PI) / theta.sin();
+        let row = &mut map[y * resolution.x..(y + 1) * resolution.x];
+        let blurred = box_blur(row, box_radius(sigma), true);
+        row.copy_from_slice(&blurred);
+    }
+
+    let radius = box_radius(radians * resolution.y as f32 / PI);
+    for x in 0..resolution.x {
+        let column: Vec<RgbaSpectrum> = (0..resolution.y)
+            .map(|y| map[y * resolution.x + x])
+            .collect();
+        for (y, value) in box_blur(&column, radius, false).into_iter().enumerate() {
+            map[y * resolution.x + x] = value;
+        }
+    }
+    map
+}
+
+/// Return the radius of the box whose repeated blurs have the variance of a
+/// Gaussian with the standard deviation `sigma`, in texels. A box with radius
+/// r has variance r(r + 1) / 3.
+fn box_radius(sigma: f32) -> usize {
+    let variance = sigma * sigma * 3.0 / BOX_BLUR_PASSES as f32;
+    (((1.0 + 4.0 * variance).sqrt() - 1.0) / 2.0).round() as usize
+}
+
+/// Repeatedly replace each value with the mean of the values within `radius`
+/// of it. If `wrap` is true, the values wrap around at the ends. Otherwise
+/// values past the ends are dropped from the mean.
+fn box_blur(values: &[RgbaSpectrum], radius: usize, wrap: bool) -> Vec<RgbaSpectrum> {
+    let len = values.len() as isize;
+    let mut values = values.to_vec();
+    if radius == 0 {
+        return values;
+    }
+    // A box that covers the whole row makes every value the row's mean.
+    if wrap && 2 * radius + 1 >= values.len() {
+        let mean = values.iter().fold(RgbaSpectrum::black(), |sum, v| sum + v) / len as f32;
+        return vec![mean; values.len()];
+    }
+
+    let radius = radius as isize;
+    for _ in 0..BOX_BLUR_PASSES {
+        // prefix_sums[i] is the sum of the first i values.
+        let mut prefix_sums = Vec::with_capacity(values.len() + 1);
+        prefix_sums.push(RgbaSpectrum::black());
+        for value in &values {
+            let sum = prefix_sums[prefix_sums.len() - 1] + value;
+            prefix_sums.push(sum);
+        }
+        let total = prefix_sums[values.len()];
+        // The sum of the values before index i, counting whole laps around
+        // the row when i is outside of it.
+        let sum_before =
+            |i: isize| total * i.div_euclid(len) as f32 + prefix_sums[i.rem_euclid(len) as usize];
+
+        values = (0..len)
+            .map(|i| {
+                let (start, end) = if wrap {
+                    (i - radius, i + radius + 1)
+                } else {
+                    ((i - radius).max(0), (i + radius + 1).min(len))
+                };
+                (sum_before(end) - sum_before(start)) / (end - start) as f32
+            })
+            .collect();
+    }
+    values
+}
+
+/// Convert an IEEE 754 half-precision float to single precision.
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Reads little-endian values from a byte slice, failing rather than panicking
+/// if the slice ends early.
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(count)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| invalid_data("the OpenEXR image is truncated"))?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// Read a null-terminated string.
+    fn string(&mut self) -> io::Result<String> {
+        let length = self.bytes[self.position.min(self.bytes.len())..]
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or_else(|| invalid_data("the OpenEXR image is truncated"))?;
+        let string = String::from_utf8_lossy(self.take(length)?).into_owned();
+        self.take(1)?;
+        Ok(string)
+    }
+}
+
+#[cfg(test)]
+mod environment_map_tests {
+    use super::{blur_environment_map, f16_to_f32, read_exr};
+    use crate::{
+        color::RgbaSpectrum,
+        film::{write_exr_layers, Film},
+        test::ApproxEq,
+    };
+    use cgmath::Vector2;
+
+    #[test]
+    fn reads_an_exr_that_the_film_wrote() {
+        let film = Film::new(Vector2::new(3, 2));
+        let mut bytes = vec![];
+        write_exr_layers(&mut bytes, &[("", &film)]).unwrap();
+        let (pixels, resolution) = read_exr(&bytes).unwrap();
+        assert_eq!(resolution, Vector2::new(3, 2));
+        assert_eq!(pixels.len(), 6);
+        assert!(pixels.iter().all(|pixel| pixel.is_black()));
+    }
+
+    #[test]
+    fn converts_half_floats() {
+        assert_eq!(f16_to_f32(0x3c00), 1.0);
+        assert_eq!(f16_to_f32(0xc000), -2.0);
+        assert_eq!(f16_to_f32(0x3555), 0.333_251_95);
+        assert_eq!(f16_to_f32(0x7c00), f32::INFINITY);
+    }
+
+    #[test]
+    fn blur_preserves_a_uniform_map() {
+        let resolution = Vector2::new(16, 8);
+        let map = vec![RgbaSpectrum::constant(2.0); 16 * 8];
+        for blurred in blur_environment_map(&map, resolution, 20.0) {
+            blurred.assert_approx_eq(&RgbaSpectrum::constant(2.0));
+        }
+    }
+
+    #[test]
+    fn blur_spreads_a_bright_texel_across_the_seam() {
+        let resolution = Vector2::new(36, 18);
+        let mut map = vec![RgbaSpectrum::black(); 36 * 18];
+        map[9 * 36] = RgbaSpectrum::constant(1.0);
+        let blurred = blur_environment_map(&map, resolution, 10.0);
+        assert!(blurred[9 * 36].r() < 1.0);
+        assert!(blurred[9 * 36 + 35].r() > 0.0);
+        assert!(blurred[10 * 36].r() > 0.0);
+        assert_eq!(blur_environment_map(&map, resolution, 0.0), map);
+    }
+}
@@ -0,0 +1,271 @@
+//! Reads the IES LM-63 photometric profiles that lighting manufacturers
+//! publish for their fixtures, which describe how a fixture's luminous
+//! intensity varies with direction. Only type C photometry, which is used for
+//! nearly all architectural and enclosure lighting, is supported.
+
+use cgmath::{InnerSpace, Vector3};
+use std::f32::consts::PI;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The number of steps in each angle of the grid over the sphere that a
+/// profile's mean intensity is estimated with.
+const MEAN_GRID_SIZE: usize = 64;
+
+/// The angular distribution of a fixture's luminous intensity.
+///
+/// Directions are given in the fixture's own space, in which the fixture
+/// points down the negative z axis. The vertical angle is measured from that
+/// axis, which is the fixture's nadir, and the horizontal angle is measured
+/// around it from the positive x axis towards the positive y axis.
+#[derive(Debug, Clone)]
+pub struct PhotometricProfile {
+    /// The vertical angles in degrees, in increasing order.
+    vertical_angles: Vec<f32>,
+
+    /// The horizontal angles in degrees, in increasing order. Their range
+    /// implies the profile's symmetry.
+    horizontal_angles: Vec<f32>,
+
+    /// The intensity in candela at each horizontal angle, and then each
+    /// vertical angle.
+    candela: Vec<Vec<f32>>,
+
+    /// The greatest intensity in candela.
+    max_candela: f32,
+}
+
+impl PhotometricProfile {
+    /// Parse the text of an IES file.
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let tilt_start = text
+            .find("TILT=")
+            .ok_or_else(|| invalid_data("the IES file has no TILT line"))?;
+        let after_tilt = &text[tilt_start + "TILT=".len()..];
+        let (tilt, rest) = after_tilt.split_at(after_tilt.find('\n').unwrap_or(after_tilt.len()));
+        let mut values = rest.split_whitespace().map(|value| {
+            value
+                .parse::<f32>()
+                .map_err(|_| invalid_data(&format!("expected a number but found {}", value)))
+        });
+        let mut next = || {
+            values
+                .next()
+                .unwrap_or_else(|| Err(invalid_data("the IES file ends early")))
+        };
+
+        if tilt.trim() == "INCLUDE" {
+            // The tilt data only matters for fixtures that are tilted from
+            // how they were measured, so it's skipped.
+            let _geometry = next()?;
+            let count = next()? as usize;
+            for _ in 0..2 * count {
+                next()?;
+            }
+        }
+
+        let _lamp_count = next()?;
+        let _lumens_per_lamp = next()?;
+        let multiplier = next()?;
+        let vertical_count = next()? as usize;
+        let horizontal_count = next()? as usize;
+        let photometric_type = next()? as i32;
+        let _units = next()?;
+        let _width = next()?;
+        let _length = next()?;
+        let _height = next()?;
+        let ballast_factor = next()?;
+        let _future_use = next()?;
+        let _input_watts = next()?;
+        if photometric_type != 1 {
+            return Err(invalid_data("only type C IES profiles are supported"));
+        }
+        if vertical_count == 0 || horizontal_count == 0 {
+            return Err(invalid_data("the IES profile has no angles"));
+        }
+
+        let vertical_angles = (0..vertical_count)
+            .map(|_| next())
+            .collect::<io::Result<Vec<f32>>>()?;
+        let horizontal_angles = (0..horizontal_count)
+            .map(|_| next())
+            .collect::<io::Result<Vec<f32>>>()?;
+        let scale = multiplier * ballast_factor;
+        let candela = (0..horizontal_count)
+            .map(|_| {
+                (0..vertical_count)
+                    .map(|_| next().map(|value| value * scale))
+                    .collect::<io::Result<Vec<f32>>>()
+            })
+            .collect::<io::Result<Vec<Vec<f32>>>>()?;
+        let max_candela = candela.iter().flatten().copied().fold(0.0, f32::max);
+        Ok(Self {
+            vertical_angles,
+            horizontal_angles,
+            candela,
+            max_candela,
+        })
+    }
+
+    /// Return the greatest intensity in candela.
+    pub fn max_candela(&self) -> f32 {
+        self.max_candela
+    }
+
+    /// Return the intensity in the direction, which is in the fixture's space,
+    /// as a fraction of the greatest intensity.
+    pub fn scale(&self, direction: &Vector3<f32>) -> f32 {
+        if self.max_candela <= 0.0 {
+            return 0.0;
+        }
+        let direction = direction.normalize();
+        let vertical = (-direction.z).clamp(-1.0, 1.0).acos().to_degrees();
+        let horizontal = direction.y.atan2(direction.x).to_degrees();
+        self.candela(vertical, horizontal) / self.max_candela
+    }
+
+    /// Return the mean of `scale` over every direction, which is the fraction
+    /// of an equally bright light's power that the fixture emits.
+    pub fn mean_scale(&self) -> f32 {
+        let mut sum = 0.0;
+        let mut weight_sum = 0.0;
+        for i in 0..MEAN_GRID_SIZE {
+            // Each row of the grid is weighted by its share of the sphere.
+            let theta = (i as f32 + 0.5) / MEAN_GRID_SIZE as f32 * PI;
+            let weight = theta.sin();
+            for j in 0..2 * MEAN_GRID_SIZE {
+                let phi = (j as f32 + 0.5) / (2 * MEAN_GRID_SIZE) as f32 * 2.0 * PI;
+                let direction = Vector3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    -theta.cos(),
+                );
+                sum += weight * self.scale(&direction);
+                weight_sum += weight;
+            }
+        }
+        sum / weight_sum
+    }
+
+    /// Return the intensity in candela at the angles, in degrees, by
+    /// interpolating between the measured angles.
+    fn candela(&self, vertical: f32, horizontal: f32) -> f32 {
+        let horizontal = self.fold_horizontal(horizontal.rem_euclid(360.0));
+        let (h0, h1, ht) = match interpolation(&self.horizontal_angles, horizontal) {
+            Some(interpolation) => interpolation,
+            None => return 0.0,
+        };
+        let (v0, v1, vt) = match interpolation(&self.vertical_angles, vertical) {
+            Some(interpolation) => interpolation,
+            None => return 0.0,
+        };
+        let at = |h: usize| (1.0 - vt) * self.candela[h][v0] + vt * self.candela[h][v1];
+        (1.0 - ht) * at(h0) + ht * at(h1)
+    }
+
+    /// Map a horizontal angle in [0, 360) into the range of measured angles,
+    /// using the symmetry that the range implies.
+    fn fold_horizontal(&self, horizontal: f32) -> f32 {
+        // The angles are in increasing order, and there's at least one.
+        let last = self.horizontal_angles[self.horizontal_angles.len() - 1];
+        if self.horizontal_angles.len() == 1 {
+            // The profile is the same around the nadir.
+            self.horizontal_angles[0]
+        } else if last <= 90.0 {
+            // The profile is symmetric in each quadrant.
+            let horizontal = if horizontal > 180.0 {
+                360.0 - horizontal
+            } else {
+                horizontal
+            };
+            if horizontal > 90.0 {
+                180.0 - horizontal
+            } else {
+                horizontal
+            }
+        } else if last <= 180.0 && horizontal > 180.0 {
+            // The profile is symmetric about the plane at 0 and 180 degrees.
+            360.0 - horizontal
+        } else {
+            horizontal
+        }
+    }
+}
+
+/// Read the IES profile at the path.
+pub fn read_ies_profile(path: &Path) -> io::Result<PhotometricProfile> {
+    // Some manufacturers write IES files in Latin-1, so invalid UTF-8 in the
+    // header's comments is tolerated.
+    PhotometricProfile::parse(&String::from_utf8_lossy(&fs::read(path)?))
+}
+
+/// Return the indices of the angles on either side of the angle, and how far
+/// the angle is between them, or `None` if the angle is outside of the range.
+fn interpolation(angles: &[f32], angle: f32) -> Option<(usize, usize, f32)> {
+    let first = angles[0];
+    let last = angles[angles.len() - 1];
+    if angle < first - 1e-3 || angle > last + 1e-3 {
+        return None;
+    }
+    let upper = angles
+        .partition_point(|a| *a < angle)
+        .max(1)
+        .min(angles.len() - 1);
+    if angles.len() == 1 {
+        return Some((0, 0, 0.0));
+    }
+    let lower = upper - 1;
+    let span = angles[upper] - angles[lower];
+    let t = if span > 0.0 {
+        ((angle - angles[lower]) / span).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    Some((lower, upper, t))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod ies_tests {
+    use super::PhotometricProfile;
+    use cgmath::Vector3;
+
+    /// A downlight that's symmetric about the plane at 0 and 180 degrees,
+    /// and twice as bright towards 90 degrees as towards 0 and 180 degrees.
+    const DOWNLIGHT: &str = "IESNA:LM-63-2002
+[MANUFAC] Test
+TILT=NONE
+1 1000 2 3 3 1 1 0 0 0
+1 1 10
+0 45 90
+0 90 180
+100 50 0
+200 100 0
+100 50 0
+";
+
+    #[test]
+    fn interpolates_and_mirrors_the_profile() {
+        let profile = PhotometricProfile::parse(DOWNLIGHT).unwrap();
+        assert_eq!(profile.max_candela(), 400.0);
+
+        let nadir = Vector3::new(0.0, 0.0, -1.0);
+        assert!((profile.scale(&nadir) - 0.5).abs() < 1e-5);
+
+        // Halfway between 0 and 45 degrees from the nadir, towards 90 degrees
+        // around it and towards 270 degrees, which mirrors 90 degrees.
+        let angle = 22.5f32.to_radians();
+        let towards_y = Vector3::new(0.0, angle.sin(), -angle.cos());
+        let towards_negative_y = Vector3::new(0.0, -angle.sin(), -angle.cos());
+        assert!((profile.scale(&towards_y) - 0.75).abs() < 1e-4);
+        assert!((profile.scale(&towards_negative_y) - 0.75).abs() < 1e-4);
+
+        // Nothing is emitted above the horizon.
+        assert_eq!(profile.scale(&Vector3::new(0.0, 0.0, 1.0)), 0.0);
+        assert!(profile.mean_scale() > 0.0 && profile.mean_scale() < 0.5);
+    }
+}
@@ -0,0 +1,247 @@
+use super::{LightFlags, VisibilityTester};
+use crate::{
+    color::RgbaSpectrum, interaction::OffsetRayOrigin, sampling::Distribution2D, scene::WorldBound,
+};
+use cgmath::{InnerSpace, Matrix4, Point2, Point3, SquareMatrix, Transform, Vector2, Vector3};
+use std::f32::consts::PI;
+
+/// The radius of the sphere around the origin that the scene is assumed to
+/// fit in until the light is preprocessed with the actual scene.
+const DEFAULT_WORLD_RADIUS: f32 = 1e3;
+
+/// The number of rows in the map of a light with uniform radiance.
+const UNIFORM_MAP_ROWS: usize = 32;
+
+/// A light source infinitely far away that surrounds the scene, such as the
+/// sky, whose radiance in each direction is read from an environment map.
+/// This is also known as image-based lighting.
+///
+/// The map is an equirectangular (latitude-longitude) image. Its top row is
+/// the light's positive z axis, and its left and right edges meet at the
+/// positive x axis.
+pub struct InfiniteAreaLight {
+    light_to_world: Matrix4<f32>,
+    world_to_light: Matrix4<f32>,
+
+    /// The radiance arriving from each texel's direction, in row-major order.
+    radiance_map: Vec<RgbaSpectrum>,
+    resolution: Vector2<usize>,
+
+    /// Chooses directions in proportion to the radiance that arrives from
+    /// them, so that bright regions like the sun are sampled often.
+    distribution: Distribution2D,
+
+    /// The radius of a sphere that bounds the scene.
+    world_radius: f32,
+}
+
+impl InfiniteAreaLight {
+    /// * light_to_world - Orients the environment map in the world. Only its
+    ///   rotation matters.
+    /// * radiance_map - The radiance arriving from each texel's direction, in
+    ///   row-major order, starting at the top-left texel.
+    /// * resolution - The map's width and height, which must both be positive.
+    pub fn new(
+        light_to_world: Matrix4<f32>,
+        radiance_map: Vec<RgbaSpectrum>,
+        resolution: Vector2<usize>,
+    ) -> Self {
+        // Each texel is weighted by the solid angle that it covers, which
+        // shrinks towards the poles, so that the distribution is proportional
+        // to the radiance per unit solid angle rather than per texel.
+        let weights: Vec<f32> = radiance_map
+            .iter()
+            .enumerate()
+            .map(|(i, radiance)| {
+                let row = i / resolution.x;
+                let sin_theta = (PI * (row as f32 + 0.5) / resolution.y as f32).sin();
+                radiance.luminance().max(0.0) * sin_theta
+            })
+            .collect();
+        let distribution = Distribution2D::new(&weights, resolution.x);
+        Self {
+            light_to_world,
+            world_to_light: light_to_world.invert().unwrap_or_else(Matrix4::identity),
+            radiance_map,
+            resolution,
+            distribution,
+            world_radius: DEFAULT_WORLD_RADIUS,
+        }
+    }
+
+    /// Return the size of the environment map and of the distribution that
+    /// samples it.
+    pub(crate) fn memory_bytes(&self) -> usize {
+        self.radiance_map.len() * std::mem::size_of::<RgbaSpectrum>()
+            + self.distribution.memory_bytes()
+    }
+
+    /// Create a light that surrounds the scene with the same radiance from
+    /// every direction, like an overcast sky.
+    pub fn uniform(radiance: RgbaSpectrum) -> Self {
+        // A single texel would do, but the map's rows are weighted by the
+        // solid angle at their centers, so a column of rows makes the
+        // sampled directions close to uniform over the sphere. That keeps
+        // the variance of estimates low.
+        Self::new(
+            Matrix4::identity(),
+            vec![radiance; UNIFORM_MAP_ROWS],
+            Vector2::new(1, UNIFORM_MAP_ROWS),
+        )
+    }
+
+    /// Return the radiance arriving from the environment along a ray that
+    /// leaves the scene in the direction `w`.
+    pub fn le(&self, w: &Vector3<f32>) -> RgbaSpectrum {
+        let w_light = self.world_to_light.transform_vector(*w).normalize();
+        let theta = w_light.z.clamp(-1.0, 1.0).acos();
+        let phi = w_light.y.atan2(w_light.x);
+        let phi = if phi < 0.0 { phi + 2.0 * PI } else { phi };
+        self.lookup(&Point2::new(phi / (2.0 * PI), theta / PI))
+    }
+
+    /// Sample a direction in proportion to the radiance that arrives from it,
+    /// and return the radiance, the direction, a visibility tester, and the
+    /// probability density of the direction with respect to solid angle.
+    pub fn sample_li(
+        &self,
+        reference: Box<dyn OffsetRayOrigin>,
+        u: &Point2<f32>,
+    ) -> (RgbaSpectrum, Vector3<f32>, VisibilityTester, f32) {
+        let (uv, map_pdf) = self.distribution.sample_continuous(u);
+        let theta = uv.y * PI;
+        let phi = uv.x * 2.0 * PI;
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        let w_light = Vector3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+        let wi = self.light_to_world.transform_vector(w_light).normalize();
+
+        // Convert the density from the map's (u, v) space to solid angle.
+        let pdf = if sin_theta == 0.0 {
+            0.0
+        } else {
+            map_pdf / (2.0 * PI * PI * sin_theta)
+        };
+        let origin = reference.non_offset_ray_origin();
+        let vis = VisibilityTester::new(reference, origin + wi * (2.0 * self.world_radius));
+        (self.lookup(&uv), wi, vis, pdf)
+    }
+
+    /// Return the probability density with respect to solid angle that
+    /// `sample_li` samples the direction `w`.
+    pub fn pdf_li(&self, w: &Vector3<f32>) -> f32 {
+        let w_light = self.world_to_light.transform_vector(*w).normalize();
+        let theta = w_light.z.clamp(-1.0, 1.0).acos();
+        let phi = w_light.y.atan2(w_light.x);
+        let phi = if phi < 0.0 { phi + 2.0 * PI } else { phi };
+        let sin_theta = theta.sin();
+        if sin_theta == 0.0 {
+            return 0.0;
+        }
+        let uv = Point2::new(phi / (2.0 * PI), theta / PI);
+        self.distribution.pdf(&uv) / (2.0 * PI * PI * sin_theta)
+    }
+
+    /// Return an approximation of the light's total emitted power, which is
+    /// the power that crosses a disk as large as the scene.
+    pub fn power(&self) -> RgbaSpectrum {
+        let sum = self
+            .radiance_map
+            .iter()
+            .fold(RgbaSpectrum::black(), |sum, radiance| sum + *radiance);
+        let average = sum / self.radiance_map.len() as f32;
+        average * (PI * self.world_radius * self.world_radius)
+    }
+
+    /// Record the bounds of the scene, which the light needs to place the
+    /// ends of its shadow rays outside of the scene.
+    pub fn preprocess<S: WorldBound + ?Sized>(&mut self, scene: &S) {
+        let bound = scene.world_bound();
+        if bound.is_empty() {
+            return;
+        }
+        let min = Point3::new(bound.min.x, bound.min.y, bound.min.z);
+        let max = Point3::new(bound.max.x, bound.max.y, bound.max.z);
+        // A sphere around the box's center would be tighter, but shadow rays
+        // start inside the box, so a sphere of its diagonal always reaches
+        // past the scene from anywhere in it.
+        self.world_radius = (max - min).magnitude();
+    }
+
+    pub fn flags(&self) -> LightFlags {
+        LightFlags::INFINITE
+    }
+
+    /// Return the radiance at the point in the map's (u, v) space, with
+    /// bilinear interpolation between texels. The map wraps around
+    /// horizontally.
+    fn lookup(&self, uv: &Point2<f32>) -> RgbaSpectrum {
+        let (width, height) = (self.resolution.x, self.resolution.y);
+        let x = uv.x * width as f32 - 0.5;
+        let y = (uv.y * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (dx, dy) = (x - x0, y - y0);
+        let texel = |x: i64, y: i64| {
+            let x = x.rem_euclid(width as i64) as usize;
+            let y = (y.max(0) as usize).min(height - 1);
+            self.radiance_map[y * width + x]
+        };
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        texel(x0, y0) * ((1.0 - dx) * (1.0 - dy))
+            + texel(x0 + 1, y0) * (dx * (1.0 - dy))
+            + texel(x0, y0 + 1) * ((1.0 - dx) * dy)
+            + texel(x0 + 1, y0 + 1) * (dx * dy)
+    }
+}
+
+#[cfg(test)]
+mod infinite_area_light_tests {
+    use super::InfiniteAreaLight;
+    use crate::{color::RgbaSpectrum, interaction::SurfaceInteraction, sampling};
+    use cgmath::{Matrix4, Point3, SquareMatrix, Vector2, Vector3};
+    use std::f32::consts::PI;
+
+    #[test]
+    fn estimates_irradiance_from_uniform_sky() {
+        // A uniform environment of unit radiance gives an irradiance of pi on
+        // every surface.
+        let resolution = Vector2::new(16, 8);
+        let map = vec![RgbaSpectrum::constant(1.0); 16 * 8];
+        let light = InfiniteAreaLight::new(Matrix4::identity(), map, resolution);
+        let reference = SurfaceInteraction::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::unit_z(),
+            Vector3::unit_x(),
+            Vector3::unit_y(),
+        );
+        let samples = sampling::grid_samples_2d(256);
+        let irradiance = samples
+            .iter()
+            .map(|u| {
+                let (li, wi, _vis, pdf) = light.sample_li(Box::new(reference), u);
+                assert!((light.pdf_li(&wi) - pdf).abs() < 1e-3 * pdf);
+                if pdf == 0.0 {
+                    0.0
+                } else {
+                    li.r() * wi.z.max(0.0) / pdf
+                }
+            })
+            .sum::<f32>()
+            / samples.len() as f32;
+        assert!((irradiance - PI).abs() < 0.02 * PI);
+    }
+
+    #[test]
+    fn bright_texel_is_seen_in_its_direction() {
+        let resolution = Vector2::new(4, 2);
+        let mut map = vec![RgbaSpectrum::black(); 8];
+        // The second texel of the top row is centered on the direction with
+        // phi = 3pi/4 and theta = pi/4.
+        map[1] = RgbaSpectrum::constant(8.0);
+        let light = InfiniteAreaLight::new(Matrix4::identity(), map, resolution);
+        let (sin_theta, cos_theta) = (PI / 4.0).sin_cos();
+        let (sin_phi, cos_phi) = (3.0 * PI / 4.0).sin_cos();
+        let w = Vector3::new(sin_theta * cos_phi, sin_theta * sin_phi, cos_theta);
+        assert!((light.le(&w).r() - 8.0).abs() < 1e-3);
+    }
+}
@@ -1,15 +1,40 @@
+mod area;
+mod bvh;
+mod distribution;
+mod environment_map;
+mod ies;
+mod infinite;
 mod point;
+mod projection;
+mod units;
 mod visibility;
 
-pub use visibility::VisibilityTester;
+pub use area::{AreaEmission, AreaLight};
+pub use bvh::LightBvh;
+pub use distribution::LightDistribution;
+pub use environment_map::{blur_environment_map, read_environment_map};
+pub use ies::{read_ies_profile, PhotometricProfile};
+pub use infinite::InfiniteAreaLight;
+pub use projection::ProjectionLight;
+pub use units::IntensityUnit;
+pub use visibility::{Occluder, VisibilityTester};
 
 use self::point::PointLight;
-use crate::{color::RgbaSpectrum, interaction::SurfaceInteraction, scene::Scene};
+use crate::{
+    color::RgbaSpectrum,
+    interaction::{OffsetRayOrigin, SurfaceInteraction},
+    ray::Ray,
+    scene::WorldBound,
+};
 use bitflags::bitflags;
-use cgmath::{Point2, Point3, Vector3};
+use cgmath::{Matrix4, Point2, Point3, Vector2, Vector3};
+use mesh::Mesh;
 
 pub enum Light {
     PointLight(PointLight),
+    AreaLight(AreaLight),
+    InfiniteAreaLight(InfiniteAreaLight),
+    ProjectionLight(ProjectionLight),
 }
 
 impl Light {
@@ -17,6 +42,83 @@ impl Light {
         Self::PointLight(PointLight::new(position, intensity))
     }
 
+    /// Create a point light source whose intensity in each direction is shaped
+    /// by a photometric profile, such as one read from a fixture's IES file.
+    ///
+    /// * intensity - The amount of power emitted per unit solid angle in the
+    ///   profile's brightest direction.
+    /// * light_to_world - Orients the profile in the world. The fixture points
+    ///   down the negative z axis of the light's space.
+    pub fn profiled_point_light(
+        position: Point3<f32>,
+        intensity: RgbaSpectrum,
+        profile: PhotometricProfile,
+        light_to_world: Matrix4<f32>,
+    ) -> Self {
+        Self::PointLight(PointLight::new(position, intensity).with_profile(profile, light_to_world))
+    }
+
+    /// Create a light source that emits from the surface of a mesh. The mesh's
+    /// primitives should be created with the same emission, using
+    /// `PrimitiveAggregate::from_emissive_mesh`, so that rays which hit the
+    /// mesh see it glow.
+    pub fn area_light(mesh: &Mesh, emission: AreaEmission) -> Self {
+        Self::AreaLight(AreaLight::new(mesh, emission))
+    }
+
+    /// Create a light source that projects an image into the scene from a
+    /// single point, like a slide projector.
+    ///
+    /// * light_to_world - Places and orients the light. It points down the
+    ///   positive z axis of the light's space, with the image's top towards
+    ///   the positive y axis.
+    /// * intensity - The amount of power emitted per unit solid angle through
+    ///   a white texel.
+    /// * image - The image's linear texels in row-major order, starting at the
+    ///   top-left texel.
+    /// * fov - The field of view of the image's shorter side, in radians.
+    pub fn projection_light(
+        light_to_world: Matrix4<f32>,
+        intensity: RgbaSpectrum,
+        image: Vec<RgbaSpectrum>,
+        resolution: Vector2<usize>,
+        fov: f32,
+    ) -> Self {
+        Self::ProjectionLight(ProjectionLight::new(
+            light_to_world,
+            intensity,
+            image,
+            resolution,
+            fov,
+        ))
+    }
+
+    /// Create a light source that surrounds the scene, with the radiance from
+    /// each direction read from an equirectangular environment map.
+    ///
+    /// * light_to_world - Orients the environment map in the world.
+    /// * radiance_map - The map's texels in row-major order, starting at the
+    ///   top-left texel.
+    /// * resolution - The map's width and height.
+    pub fn infinite_area_light(
+        light_to_world: Matrix4<f32>,
+        radiance_map: Vec<RgbaSpectrum>,
+        resolution: Vector2<usize>,
+    ) -> Self {
+        Self::InfiniteAreaLight(InfiniteAreaLight::new(
+            light_to_world,
+            radiance_map,
+            resolution,
+        ))
+    }
+
+    /// Create a light source that surrounds the scene with the same radiance
+    /// from every direction. It fills in the shadows of other lights, and rays
+    /// that escape the scene see its radiance as the background.
+    pub fn uniform_infinite_light(radiance: RgbaSpectrum) -> Self {
+        Self::InfiniteAreaLight(InfiniteAreaLight::uniform(radiance))
+    }
+
     /// Given a surface interation containing a point and a time, return the
     /// radiance arriving at that point and time due to the light source,
     /// ignoring possible occlusion. In addition to incoming radiance, this
@@ -26,9 +128,36 @@ impl Light {
     pub fn li(
         &self,
         interaction: &SurfaceInteraction,
+    ) -> (RgbaSpectrum, Vector3<f32>, VisibilityTester) {
+        self.li_from(Box::new(*interaction))
+    }
+
+    /// Return the radiance arriving at the reference point due to the light
+    /// source, ignoring possible occlusion, along with the incident direction
+    /// and a visibility tester. Unlike `li`, the reference point doesn't need
+    /// to be on a surface. It can be a scattering point in a participating
+    /// medium, for example.
+    ///
+    /// Area lights are treated as if all of their power came from a single
+    /// point on their surface, so callers that can take several samples should
+    /// prefer `sample_li`.
+    pub(crate) fn li_from(
+        &self,
+        reference: Box<dyn OffsetRayOrigin>,
     ) -> (RgbaSpectrum, Vector3<f32>, VisibilityTester) {
         match self {
-            Light::PointLight(pl) => pl.li(interaction),
+            Light::PointLight(pl) => pl.li(reference),
+            Light::ProjectionLight(pl) => pl.li(reference),
+            Light::AreaLight(al) => {
+                let (li, wi, vis, pdf) = al.sample_li(reference, &Point2::new(0.5, 0.5));
+                let li = if pdf > 0.0 { li / pdf } else { li * 0.0 };
+                (li, wi, vis)
+            }
+            Light::InfiniteAreaLight(il) => {
+                let (li, wi, vis, pdf) = il.sample_li(reference, &Point2::new(0.5, 0.5));
+                let li = if pdf > 0.0 { li / pdf } else { li * 0.0 };
+                (li, wi, vis)
+            }
         }
     }
 
@@ -36,10 +165,31 @@ impl Light {
     pub fn sample_li(
         &self,
         interaction: &SurfaceInteraction,
-        _u: &Point2<f32>,
+        u: &Point2<f32>,
+    ) -> (RgbaSpectrum, Vector3<f32>, VisibilityTester, f32) {
+        self.sample_li_from(Box::new(*interaction), u)
+    }
+
+    /// Sample the radiance arriving at the reference point due to the light
+    /// source. This is the counterpart of `sample_li` for reference points that
+    /// aren't necessarily on a surface.
+    pub(crate) fn sample_li_from(
+        &self,
+        reference: Box<dyn OffsetRayOrigin>,
+        u: &Point2<f32>,
     ) -> (RgbaSpectrum, Vector3<f32>, VisibilityTester, f32) {
-        let (li, wi, vis) = self.li(interaction);
-        (li, wi, vis, 1.0)
+        match self {
+            Light::PointLight(pl) => {
+                let (li, wi, vis) = pl.li(reference);
+                (li, wi, vis, 1.0)
+            }
+            Light::ProjectionLight(pl) => {
+                let (li, wi, vis) = pl.li(reference);
+                (li, wi, vis, 1.0)
+            }
+            Light::AreaLight(al) => al.sample_li(reference, u),
+            Light::InfiniteAreaLight(il) => il.sample_li(reference, u),
+        }
     }
 
     /// Return an approximation of the light's total emitted power.
@@ -49,15 +199,63 @@ impl Light {
     pub fn power(&self) -> RgbaSpectrum {
         match self {
             Light::PointLight(pl) => pl.power(),
+            Light::ProjectionLight(pl) => pl.power(),
+            Light::AreaLight(al) => al.power(),
+            Light::InfiniteAreaLight(il) => il.power(),
+        }
+    }
+
+    /// Return the radiance that the light contributes along a ray that leaves
+    /// the scene without hitting anything. Only lights that surround the scene
+    /// contribute any.
+    pub fn le(&self, ray: &Ray) -> RgbaSpectrum {
+        match self {
+            Light::InfiniteAreaLight(il) => il.le(&ray.direction),
+            _ => RgbaSpectrum::black(),
+        }
+    }
+
+    /// Return the light's position in world space, or `None` if the light
+    /// doesn't emit from a single point.
+    pub fn position(&self) -> Option<Point3<f32>> {
+        match self {
+            Light::PointLight(pl) => Some(pl.position()),
+            Light::ProjectionLight(pl) => Some(pl.position()),
+            Light::AreaLight(_) | Light::InfiniteAreaLight(_) => None,
+        }
+    }
+
+    /// Return the minimum and maximum corners of a box around the points that
+    /// the light emits from, or `None` if the light surrounds the scene or
+    /// emits from nowhere.
+    pub fn bounds(&self) -> Option<(Point3<f32>, Point3<f32>)> {
+        match self {
+            Light::PointLight(pl) => Some((pl.position(), pl.position())),
+            Light::ProjectionLight(pl) => Some((pl.position(), pl.position())),
+            Light::AreaLight(al) => al.bounds(),
+            Light::InfiniteAreaLight(_) => None,
+        }
+    }
+
+    /// Return the size of the image that the light reads its emission from,
+    /// such as an environment map, or `None` if the light doesn't have one.
+    pub(crate) fn texture_bytes(&self) -> Option<usize> {
+        match self {
+            Light::InfiniteAreaLight(il) => Some(il.memory_bytes()),
+            Light::ProjectionLight(pl) => Some(pl.memory_bytes()),
+            Light::PointLight(_) | Light::AreaLight(_) => None,
         }
     }
 
     /// Determine characteristics of the scene that could affect the light
-    /// before rendering starts. This method should be called before reding
-    /// begins.
-    pub fn preprocess(&mut self, scene: &Scene) {
+    /// before rendering starts. Scenes call this for each light that they're
+    /// given, so lights never need to be preprocessed by hand.
+    pub fn preprocess<S: WorldBound + ?Sized>(&mut self, scene: &S) {
         match self {
             Light::PointLight(pl) => pl.preprocess(scene),
+            Light::ProjectionLight(pl) => pl.preprocess(scene),
+            Light::AreaLight(al) => al.preprocess(scene),
+            Light::InfiniteAreaLight(il) => il.preprocess(scene),
         }
     }
 
@@ -65,6 +263,9 @@ impl Light {
     pub fn flags(&self) -> LightFlags {
         match self {
             Light::PointLight(pl) => pl.flags(),
+            Light::ProjectionLight(pl) => pl.flags(),
+            Light::AreaLight(al) => al.flags(),
+            Light::InfiniteAreaLight(il) => il.flags(),
         }
     }
 }
@@ -1,16 +1,24 @@
 use std::f32::consts::PI;
 
-use super::{Light, LightFlags, VisibilityTester};
-use crate::{color::RgbaSpectrum, interaction::SurfaceInteraction, scene::Scene};
-use cgmath::{InnerSpace, Matrix4, Point3, Vector3};
+use super::{Light, LightFlags, PhotometricProfile, VisibilityTester};
+use crate::{color::RgbaSpectrum, interaction::OffsetRayOrigin, scene::WorldBound};
+use cgmath::{InnerSpace, Matrix4, Point3, SquareMatrix, Transform, Vector3};
 
-/// A point light source that emits the same amount of light in all directions.
+/// A point light source that emits the same amount of light in all directions,
+/// unless a photometric profile shapes its distribution.
 pub struct PointLight {
     /// The light's position in world space.
     position: Point3<f32>,
 
-    /// The amount of power emitted per unit solid angle.
+    /// The amount of power emitted per unit solid angle. With a profile, this
+    /// is the intensity in the profile's brightest direction.
     intensity: RgbaSpectrum,
+
+    /// The profile that scales the intensity in each direction, if any.
+    profile: Option<PhotometricProfile>,
+
+    /// Transforms directions from world space to the profile's space.
+    world_to_light: Matrix4<f32>,
 }
 
 impl PointLight {
@@ -22,25 +30,60 @@ impl PointLight {
         Self {
             position,
             intensity,
+            profile: None,
+            world_to_light: Matrix4::identity(),
+        }
+    }
+
+    /// Shape the light's distribution with a photometric profile.
+    ///
+    /// * light_to_world - Orients the profile in the world. Only its rotation
+    ///   matters.
+    pub fn with_profile(
+        mut self,
+        profile: PhotometricProfile,
+        light_to_world: Matrix4<f32>,
+    ) -> Self {
+        self.profile = Some(profile);
+        self.world_to_light = light_to_world.invert().unwrap_or_else(Matrix4::identity);
+        self
+    }
+
+    /// Return the intensity in the direction from the light, which is in world
+    /// space.
+    fn intensity_towards(&self, direction: &Vector3<f32>) -> RgbaSpectrum {
+        match &self.profile {
+            Some(profile) => {
+                self.intensity * profile.scale(&self.world_to_light.transform_vector(*direction))
+            }
+            None => self.intensity,
         }
     }
 
     pub fn li(
         &self,
-        interaction: &SurfaceInteraction,
+        reference: Box<dyn OffsetRayOrigin>,
     ) -> (RgbaSpectrum, Vector3<f32>, VisibilityTester) {
-        let light_to_point = self.position - interaction.point;
-        let li = self.intensity / light_to_point.magnitude2();
+        let light_to_point = self.position - reference.non_offset_ray_origin();
+        let li = self.intensity_towards(&-light_to_point) / light_to_point.magnitude2();
         let wi = light_to_point.normalize();
-        let vis = VisibilityTester::new(Box::new(*interaction), self.position);
+        let vis = VisibilityTester::new(reference, self.position);
         (li, wi, vis)
     }
 
+    pub fn position(&self) -> Point3<f32> {
+        self.position
+    }
+
     pub fn power(&self) -> RgbaSpectrum {
-        4.0 * PI * self.intensity
+        let mean_scale = self
+            .profile
+            .as_ref()
+            .map_or(1.0, |profile| profile.mean_scale());
+        4.0 * PI * self.intensity * mean_scale
     }
 
-    pub fn preprocess(&mut self, _scene: &Scene) {}
+    pub fn preprocess<S: WorldBound + ?Sized>(&mut self, _scene: &S) {}
 
     pub fn flags(&self) -> LightFlags {
         LightFlags::DELTA_POSITION
@@ -0,0 +1,163 @@
+use super::{LightFlags, VisibilityTester};
+use crate::{color::RgbaSpectrum, interaction::OffsetRayOrigin, scene::WorldBound};
+use cgmath::{InnerSpace, Matrix4, Point3, SquareMatrix, Transform, Vector2, Vector3};
+
+/// A point light source that projects an image into the scene like a slide
+/// projector, or a theater light with a gobo in front of it. The light's
+/// intensity in each direction is modulated by the image's texel in that
+/// direction, and no light leaves outside of the image's frustum. Projecting
+/// stripes or a grid shows a part's shape the way that structured-light
+/// scanners see it.
+///
+/// The light points down its positive z axis, and the image's top is towards
+/// its positive y axis.
+pub struct ProjectionLight {
+    light_to_world: Matrix4<f32>,
+    world_to_light: Matrix4<f32>,
+
+    /// The amount of power emitted per unit solid angle through a white
+    /// texel.
+    intensity: RgbaSpectrum,
+
+    /// The projected image's texels in row-major order, starting at the
+    /// top-left texel.
+    image: Vec<RgbaSpectrum>,
+    resolution: Vector2<usize>,
+
+    /// The half-width and half-height of the image on a plane one unit in
+    /// front of the light.
+    half_extent: Vector2<f32>,
+}
+
+impl ProjectionLight {
+    /// Create a new projection light.
+    ///
+    /// * light_to_world - Places and orients the light in the world.
+    /// * intensity - The amount of power emitted per unit solid angle through
+    ///   a white texel.
+    /// * image - The projected image's texels in row-major order, starting at
+    ///   the top-left texel.
+    /// * fov - The field of view of the image's shorter side, in radians.
+    pub fn new(
+        light_to_world: Matrix4<f32>,
+        intensity: RgbaSpectrum,
+        image: Vec<RgbaSpectrum>,
+        resolution: Vector2<usize>,
+        fov: f32,
+    ) -> Self {
+        let tan_half_fov = (0.5 * fov).tan();
+        let aspect = resolution.x as f32 / resolution.y.max(1) as f32;
+        let half_extent = if aspect >= 1.0 {
+            Vector2::new(aspect * tan_half_fov, tan_half_fov)
+        } else {
+            Vector2::new(tan_half_fov, tan_half_fov / aspect)
+        };
+        Self {
+            light_to_world,
+            world_to_light: light_to_world.invert().unwrap_or_else(Matrix4::identity),
+            intensity,
+            image,
+            resolution,
+            half_extent,
+        }
+    }
+
+    /// Return the size of the projected image.
+    pub(crate) fn memory_bytes(&self) -> usize {
+        self.image.len() * std::mem::size_of::<RgbaSpectrum>()
+    }
+
+    pub fn li(
+        &self,
+        reference: Box<dyn OffsetRayOrigin>,
+    ) -> (RgbaSpectrum, Vector3<f32>, VisibilityTester) {
+        let position = self.position();
+        let light_to_point = position - reference.non_offset_ray_origin();
+        let li = self.projection(&-light_to_point) * self.intensity / light_to_point.magnitude2();
+        let wi = light_to_point.normalize();
+        let vis = VisibilityTester::new(reference, position);
+        (li, wi, vis)
+    }
+
+    /// Return the texel of the image that's projected in the direction, which
+    /// is in world space, or black if the direction is outside of the image's
+    /// frustum. The nearest texel is used so that sharp patterns, such as
+    /// stripes, stay sharp.
+    fn projection(&self, direction: &Vector3<f32>) -> RgbaSpectrum {
+        let w = self.world_to_light.transform_vector(*direction);
+        if w.z <= 0.0 || self.image.is_empty() {
+            return RgbaSpectrum::black();
+        }
+        let u = 0.5 + 0.5 * (w.x / w.z) / self.half_extent.x;
+        let v = 0.5 - 0.5 * (w.y / w.z) / self.half_extent.y;
+        if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) {
+            return RgbaSpectrum::black();
+        }
+        let x = ((u * self.resolution.x as f32) as usize).min(self.resolution.x - 1);
+        let y = ((v * self.resolution.y as f32) as usize).min(self.resolution.y - 1);
+        self.image[y * self.resolution.x + x]
+    }
+
+    pub fn position(&self) -> Point3<f32> {
+        self.light_to_world
+            .transform_point(Point3::new(0.0, 0.0, 0.0))
+    }
+
+    /// Return the power that's emitted through the image's frustum, assuming
+    /// that each texel covers the same solid angle.
+    pub fn power(&self) -> RgbaSpectrum {
+        if self.image.is_empty() {
+            return RgbaSpectrum::black();
+        }
+        let mean = self
+            .image
+            .iter()
+            .fold(RgbaSpectrum::black(), |sum, texel| sum + *texel)
+            / self.image.len() as f32;
+        // The solid angle of a rectangular pyramid with the frustum's
+        // half-angles.
+        let sin_x = self.half_extent.x.atan().sin();
+        let sin_y = self.half_extent.y.atan().sin();
+        let solid_angle = 4.0 * (sin_x * sin_y).asin();
+        self.intensity * mean * solid_angle
+    }
+
+    pub fn preprocess<S: WorldBound + ?Sized>(&mut self, _scene: &S) {}
+
+    pub fn flags(&self) -> LightFlags {
+        LightFlags::DELTA_POSITION
+    }
+}
+
+#[cfg(test)]
+mod projection_light_tests {
+    use super::ProjectionLight;
+    use crate::{color::RgbaSpectrum, test::ApproxEq};
+    use cgmath::{Matrix4, SquareMatrix, Vector2, Vector3};
+
+    #[test]
+    fn projects_the_image_within_its_frustum() {
+        // A 2x1 image with a white left half and a black right half, whose
+        // height spans 90 degrees.
+        let light = ProjectionLight::new(
+            Matrix4::identity(),
+            RgbaSpectrum::constant(2.0),
+            vec![RgbaSpectrum::constant(1.0), RgbaSpectrum::constant(0.0)],
+            Vector2::new(2, 1),
+            std::f32::consts::FRAC_PI_2,
+        );
+        light
+            .projection(&Vector3::new(-1.0, 0.0, 1.0))
+            .assert_approx_eq(&RgbaSpectrum::constant(1.0));
+        light
+            .projection(&Vector3::new(1.0, 0.0, 1.0))
+            .assert_approx_eq(&RgbaSpectrum::constant(0.0));
+        // Outside of the image's frustum, and behind the light.
+        light
+            .projection(&Vector3::new(-3.0, 0.0, 1.0))
+            .assert_approx_eq(&RgbaSpectrum::black());
+        light
+            .projection(&Vector3::new(0.0, 0.0, -1.0))
+            .assert_approx_eq(&RgbaSpectrum::black());
+    }
+}
@@ -0,0 +1,55 @@
+use std::f32::consts::PI;
+
+/// A physical unit of the intensity of a light that emits the same amount of
+/// light in all directions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntensityUnit {
+    /// Luminous intensity, in lumens per steradian.
+    Candela,
+
+    /// Luminous flux, which is the total amount of light emitted in every
+    /// direction.
+    Lumens,
+}
+
+impl IntensityUnit {
+    /// Convert a value in this unit to candela.
+    pub fn to_candela(self, value: f32) -> f32 {
+        match self {
+            IntensityUnit::Candela => value,
+            // An isotropic light spreads its flux over the whole sphere of
+            // directions.
+            IntensityUnit::Lumens => value / (4.0 * PI),
+        }
+    }
+
+    /// Convert a value in this unit to an intensity in world units, so that
+    /// light falls off with the square of the distance in meters rather than
+    /// the distance in world units. The resulting radiance is in candela per
+    /// square meter.
+    ///
+    /// * meters_per_world_unit - The length in meters of one unit of world
+    ///   space.
+    pub fn to_world_intensity(self, value: f32, meters_per_world_unit: f32) -> f32 {
+        self.to_candela(value) / (meters_per_world_unit * meters_per_world_unit)
+    }
+}
+
+#[cfg(test)]
+mod intensity_unit_tests {
+    use super::IntensityUnit;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn falloff_follows_distance_in_meters() {
+        // A 100 cd light 2 m away lights a surface with 25 lux, however long
+        // a unit of world space is.
+        let meters_per_world_unit = 0.25;
+        let distance = 2.0 / meters_per_world_unit;
+        let intensity = IntensityUnit::Candela.to_world_intensity(100.0, meters_per_world_unit);
+        assert!((intensity / (distance * distance) - 25.0).abs() < 1e-4);
+
+        let lumens = IntensityUnit::Lumens.to_world_intensity(400.0 * PI, meters_per_world_unit);
+        assert!((lumens - intensity).abs() < 1e-2);
+    }
+}
@@ -1,7 +1,33 @@
 use cgmath::Point3;
 
-use crate::{interaction::OffsetRayOrigin, scene::Scene};
+use crate::{
+    color::RgbaSpectrum, interaction::OffsetRayOrigin, medium::Medium, ray::Ray, scene::Scene,
+    simple::Scene as SimpleScene,
+};
 
+/// Geometry that shadow rays can be traced through.
+pub trait Occluder {
+    /// Return true if the ray hits anything in the (0, `ray.t_max`)
+    /// parametric range along it.
+    fn occludes(&self, ray: &Ray) -> bool;
+}
+
+impl<'msh, 'mtrl> Occluder for Scene<'msh, 'mtrl> {
+    fn occludes(&self, ray: &Ray) -> bool {
+        self.primitives.ray_intersection(ray).is_some()
+    }
+}
+
+impl<'msh> Occluder for SimpleScene<'msh> {
+    fn occludes(&self, ray: &Ray) -> bool {
+        self.primitives.ray_intersection(ray).is_some()
+    }
+}
+
+/// Tests whether light travels between a reference point and a point on a
+/// light. The shadow ray between them ends just short of the light, and its
+/// origin is offset from the reference's surface by the surface's error bound,
+/// so that neither the surface nor the light itself occludes the path.
 pub struct VisibilityTester {
     reference: Box<dyn OffsetRayOrigin>,
     light: Point3<f32>,
@@ -16,10 +42,81 @@ impl VisibilityTester {
     /// if there is an unoccluded path between the two points.
     ///
     /// This ignores the effects of any scattering medium that the ray passes
-    /// through. If the effects of a scattering medium need to be take n into
+    /// through. If the effects of a scattering medium need to be taken into
     /// account, `beam_transmittance` should be called instead.
-    pub fn unocculuded(&self, scene: &Scene) -> bool {
+    pub fn unocculuded<O: Occluder + ?Sized>(&self, scene: &O) -> bool {
         let ray = self.reference.spawn_shadow_ray_to_point(&self.light);
-        scene.ray_intersection(&ray).is_none()
+        !scene.occludes(&ray)
+    }
+
+    /// Trace a shadow ray between the reference and the light, and return the
+    /// fraction of light that is transmitted between the two points.
+    ///
+    /// Light is attenuated by the participating media that the ray passes
+    /// through. The ray passes through surfaces that only mark the boundaries
+    /// of media, but any other surface blocks the light entirely.
+    ///
+    /// * scene - The scene that the shadow ray is traced through.
+    /// * medium - The medium that the reference point is in.
+    pub fn beam_transmittance(&self, scene: &Scene, medium: Option<&Medium>) -> RgbaSpectrum {
+        let mut ray = self.reference.spawn_shadow_ray_to_point(&self.light);
+        let mut medium = medium;
+        let mut tr = RgbaSpectrum::constant(1.0);
+
+        loop {
+            let intersection = scene.ray_intersection(&ray);
+
+            if let Some((t, prim, _)) = &intersection {
+                if !prim.material.is_medium_interface() {
+                    return RgbaSpectrum::black();
+                }
+
+                if let Some(m) = medium {
+                    tr *= m.tr(&Ray::new(ray.origin, ray.direction, *t));
+                }
+            } else if let Some(m) = medium {
+                tr *= m.tr(&ray);
+            }
+
+            match intersection {
+                Some((_, prim, interaction)) => {
+                    medium = prim.medium_interface.medium(
+                        &ray.direction,
+                        &interaction.original_geometry.normal,
+                        medium,
+                    );
+                    ray = interaction.spawn_shadow_ray_to_point(&self.light);
+                }
+                None => return tr,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod visibility_tests {
+    use super::VisibilityTester;
+    use crate::{interaction::SurfaceInteraction, material::MatteMaterial, validation};
+    use cgmath::{Point3, Vector3};
+    use typed_arena::Arena;
+
+    #[test]
+    fn shadow_ray_stops_at_the_light() {
+        let mesh_arena = Arena::new();
+        let material_arena = Arena::<MatteMaterial>::new();
+        // The chart lies in the z = 0 plane, and a patch is centered on
+        // (-3, 1.8).
+        let scene = validation::macbeth_chart_scene(&mesh_arena, &material_arena);
+        let reference = SurfaceInteraction::new(
+            Point3::new(-3.0, 1.8, 5.0),
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            Vector3::unit_x(),
+            Vector3::unit_y(),
+        );
+        let behind_chart = VisibilityTester::new(Box::new(reference), Point3::new(-3.0, 1.8, -5.0));
+        assert!(!behind_chart.unocculuded(&scene));
+        let before_chart = VisibilityTester::new(Box::new(reference), Point3::new(-3.0, 1.8, 1.0));
+        assert!(before_chart.unocculuded(&scene));
     }
 }
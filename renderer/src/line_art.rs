@@ -0,0 +1,330 @@
+//! Finds the edges of meshes that a technical illustration draws, as seen by
+//! an orthographic camera, and writes them as an SVG image.
+//!
+//! Three kinds of edges are found: silhouettes, where a surface turns away
+//! from the camera; creases, where adjacent triangles meet at a sharp angle;
+//! and boundaries, where a surface ends. Each edge is split into the pieces
+//! that are visible and the pieces that are hidden behind other surfaces.
+
+use crate::camera::OrthographicCamera;
+use cgmath::{InnerSpace, Point2, Point3, SquareMatrix, Transform, Vector3};
+use mesh::Mesh;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// The distance in world space by which a surface must be in front of a
+/// point on an edge to hide it. This keeps the triangles that meet at an edge
+/// from hiding it.
+const HIDING_DISTANCE: f32 = 2e-3;
+
+/// The kinds of edges that are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// An edge between a triangle that faces the camera and one that faces
+    /// away from it.
+    Silhouette,
+
+    /// An edge between triangles whose normals differ by more than the crease
+    /// angle.
+    Crease,
+
+    /// An edge with only one triangle, where an open surface ends.
+    Boundary,
+}
+
+impl EdgeKind {
+    /// Return the name of the kind of edge, which is used as the class of the
+    /// SVG paths that hold such edges.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EdgeKind::Silhouette => "silhouette",
+            EdgeKind::Crease => "crease",
+            EdgeKind::Boundary => "boundary",
+        }
+    }
+}
+
+/// A straight piece of an edge in raster space, which is either entirely
+/// visible or entirely hidden.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EdgeSegment {
+    pub start: Point2<f32>,
+    pub end: Point2<f32>,
+    pub kind: EdgeKind,
+    pub visible: bool,
+}
+
+/// Find the edges of the meshes that are drawn in a line art image of the
+/// scene, and split them into visible and hidden segments.
+///
+/// * meshes - The meshes in world space.
+/// * camera - The camera that the edges are seen from.
+/// * crease_angle - Edges between triangles whose normals differ by more than
+///   this angle, in radians, are creases.
+/// * first_hit - Returns the parametric value at which a ray with the given
+///   origin and direction first hits the scene, if it hits anything. The
+///   direction isn't normalized.
+pub fn find_edges<F>(
+    meshes: &[&Mesh],
+    camera: &OrthographicCamera,
+    crease_angle: f32,
+    first_hit: F,
+) -> Vec<EdgeSegment>
+where
+    F: Fn(Point3<f32>, Vector3<f32>) -> Option<f32>,
+{
+    let world_to_raster = camera.world_to_raster();
+    let raster_to_world = match world_to_raster.invert() {
+        Some(raster_to_world) => raster_to_world,
+        None => return vec![],
+    };
+    let view_direction = camera
+        .camera_to_world
+        .transform_vector(Vector3::new(0.0, 0.0, 1.0))
+        .normalize();
+    let cos_crease_angle = crease_angle.cos();
+
+    // Since the camera is orthographic, every ray has the same direction, and
+    // a point's raster space z coordinate is its parametric value along the
+    // ray through it.
+    let ray_direction = raster_to_world.transform_vector(Vector3::new(0.0, 0.0, 1.0));
+    let hiding_t = HIDING_DISTANCE / ray_direction.magnitude();
+    let is_visible = |p: Point3<f32>| {
+        let raster = Point3::new(p.x, p.y, 0.0);
+        let origin = raster_to_world.transform_point(raster);
+        first_hit(origin, ray_direction).is_none_or(|t| t >= p.z - hiding_t)
+    };
+
+    let mut segments = vec![];
+    for mesh in meshes {
+        for (p1, p2, normals) in mesh_edges(mesh) {
+            let kind = match normals.as_slice() {
+                [_] => EdgeKind::Boundary,
+                [n1, n2] => {
+                    let (facing1, facing2) = (n1.dot(view_direction), n2.dot(view_direction));
+                    if (facing1 < 0.0) != (facing2 < 0.0) {
+                        EdgeKind::Silhouette
+                    } else if n1.dot(*n2) < cos_crease_angle {
+                        EdgeKind::Crease
+                    } else {
+                        continue;
+                    }
+                }
+                // Edges shared by more than two triangles aren't part of a
+                // well-formed surface, but they're usually meant to be sharp.
+                _ => EdgeKind::Crease,
+            };
+            let (r1, r2) = (
+                world_to_raster.transform_point(p1),
+                world_to_raster.transform_point(p2),
+            );
+            split_by_visibility(r1, r2, kind, &is_visible, &mut segments);
+        }
+    }
+    segments
+}
+
+/// Write the segments as an SVG image with the given size in pixels. Visible
+/// segments are solid black, and hidden segments are gray and dashed. The
+/// segments of each kind and visibility are in their own path, with a class
+/// such as `silhouette` or `crease hidden`, so they can be restyled.
+///
+/// * include_hidden - If false, hidden segments are left out.
+/// * stroke_width - The width of the lines in pixels.
+pub fn write_svg<W: Write>(
+    writer: &mut W,
+    segments: &[EdgeSegment],
+    width: usize,
+    height: usize,
+    include_hidden: bool,
+    stroke_width: f32,
+) -> io::Result<()> {
+    writeln!(
+        writer,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">",
+        width, height, width, height
+    )?;
+    writeln!(
+        writer,
+        "  <g fill=\"none\" stroke-width=\"{}\" stroke-linecap=\"round\">",
+        stroke_width
+    )?;
+    // Hidden lines are drawn first so that visible lines are on top of them.
+    let visibilities: &[bool] = if include_hidden {
+        &[false, true]
+    } else {
+        &[true]
+    };
+    for visible in visibilities {
+        for kind in &[EdgeKind::Boundary, EdgeKind::Crease, EdgeKind::Silhouette] {
+            let mut path = String::new();
+            for segment in segments
+                .iter()
+                .filter(|segment| segment.kind == *kind && segment.visible == *visible)
+            {
+                path.push_str(&format!(
+                    "M{:.2} {:.2}L{:.2} {:.2}",
+                    segment.start.x, segment.start.y, segment.end.x, segment.end.y
+                ));
+            }
+            if path.is_empty() {
+                continue;
+            }
+            let (class, style) = if *visible {
+                (kind.name().to_string(), "stroke=\"black\"".to_string())
+            } else {
+                (
+                    format!("{} hidden", kind.name()),
+                    format!(
+                        "stroke=\"gray\" stroke-dasharray=\"{} {}\"",
+                        4.0 * stroke_width,
+                        3.0 * stroke_width
+                    ),
+                )
+            };
+            writeln!(
+                writer,
+                "    <path class=\"{}\" {} d=\"{}\"/>",
+                class, style, path
+            )?;
+        }
+    }
+    writeln!(writer, "  </g>")?;
+    writeln!(writer, "</svg>")?;
+    Ok(())
+}
+
+/// The endpoints of an edge and the normals of the triangles that share it.
+type Edge = (Point3<f32>, Point3<f32>, Vec<Vector3<f32>>);
+
+/// Return each edge of the mesh, with the normals of the triangles that share
+/// it. Vertices at the same position are treated as the same vertex, since
+/// meshes read from STLs don't share vertices between triangles.
+fn mesh_edges(mesh: &Mesh) -> Vec<Edge> {
+    let mut vertex_ids: HashMap<[u32; 3], usize> = HashMap::new();
+    let mut vertex_id = |p: &Point3<f32>| {
+        let key = [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()];
+        let next_id = vertex_ids.len();
+        *vertex_ids.entry(key).or_insert(next_id)
+    };
+    let mut edges: HashMap<(usize, usize), Edge> = HashMap::new();
+    for &(i1, i2, i3) in &mesh.triangle_vertex_indices {
+        let ps = [mesh.positions[i1], mesh.positions[i2], mesh.positions[i3]];
+        let normal = (ps[1] - ps[0]).cross(ps[2] - ps[0]);
+        if normal.magnitude2() == 0.0 {
+            continue;
+        }
+        let normal = normal.normalize();
+        let ids = [vertex_id(&ps[0]), vertex_id(&ps[1]), vertex_id(&ps[2])];
+        for (a, b) in &[(0, 1), (1, 2), (2, 0)] {
+            let key = (ids[*a].min(ids[*b]), ids[*a].max(ids[*b]));
+            edges
+                .entry(key)
+                .or_insert_with(|| (ps[*a], ps[*b], vec![]))
+                .2
+                .push(normal);
+        }
+    }
+    // Edges are sorted so that the output doesn't depend on the order in
+    // which the hash map iterates.
+    let mut edges: Vec<_> = edges.into_iter().collect();
+    edges.sort_by_key(|(key, _)| *key);
+    edges.into_iter().map(|(_, edge)| edge).collect()
+}
+
+/// Split the raster space edge into pieces about a pixel long, test whether
+/// the middle of each piece is visible, and add the runs of pieces with the
+/// same visibility to the segments.
+fn split_by_visibility<F>(
+    r1: Point3<f32>,
+    r2: Point3<f32>,
+    kind: EdgeKind,
+    is_visible: &F,
+    segments: &mut Vec<EdgeSegment>,
+) where
+    F: Fn(Point3<f32>) -> bool,
+{
+    let length = Point2::new(r1.x, r1.y) - Point2::new(r2.x, r2.y);
+    let steps = length.magnitude().ceil().max(1.0) as usize;
+    let at = |step: usize| r1 + (r2 - r1) * (step as f32 / steps as f32);
+    let mut run_start = 0;
+    let mut run_visible = None;
+    for step in 0..steps {
+        let visible = is_visible(r1 + (r2 - r1) * ((step as f32 + 0.5) / steps as f32));
+        match run_visible {
+            Some(run) if run != visible => {
+                segments.push(raster_segment(at(run_start), at(step), kind, run));
+                run_start = step;
+            }
+            _ => {}
+        }
+        run_visible = Some(visible);
+    }
+    if let Some(run) = run_visible {
+        segments.push(raster_segment(at(run_start), r2, kind, run));
+    }
+}
+
+fn raster_segment(
+    start: Point3<f32>,
+    end: Point3<f32>,
+    kind: EdgeKind,
+    visible: bool,
+) -> EdgeSegment {
+    EdgeSegment {
+        start: Point2::new(start.x, start.y),
+        end: Point2::new(end.x, end.y),
+        kind,
+        visible,
+    }
+}
+
+#[cfg(test)]
+mod line_art_tests {
+    use super::{find_edges, EdgeKind};
+    use crate::camera::OrthographicCamera;
+    use cgmath::{Matrix4, Point3, Vector2, Vector3};
+    use mesh::MeshBuilder;
+
+    #[test]
+    fn finds_the_outline_of_a_square_and_its_hidden_diagonal() {
+        // A square in the z = 0 plane that faces the camera, which looks
+        // down the z axis, is made of two triangles that share a diagonal.
+        // Its outline is a boundary and the diagonal isn't drawn.
+        let square = MeshBuilder::new(
+            vec![
+                Point3::new(-1.0, -1.0, 0.0),
+                Point3::new(1.0, -1.0, 0.0),
+                Point3::new(1.0, 1.0, 0.0),
+                Point3::new(-1.0, 1.0, 0.0),
+            ],
+            vec![],
+            vec![(0, 2, 1), (0, 3, 2)],
+        )
+        .build();
+        let camera = OrthographicCamera::new(
+            Matrix4::from_translation(Vector3::new(0.0, 0.0, -5.0)),
+            0.0,
+            10.0,
+            Vector2::new(4.0, 4.0),
+            Vector2::new(40, 40),
+        );
+        let segments = find_edges(&[&square], &camera, 0.5, |_, _| None);
+        assert!(segments
+            .iter()
+            .all(|segment| segment.kind == EdgeKind::Boundary && segment.visible));
+        let length: f32 = segments
+            .iter()
+            .map(|segment| {
+                let d = segment.end - segment.start;
+                (d.x * d.x + d.y * d.y).sqrt()
+            })
+            .sum();
+        // Each side is two units long, which is 20 pixels.
+        assert!((length - 80.0).abs() < 1e-3);
+
+        // If something is always in front of the edges, they're all hidden.
+        let segments = find_edges(&[&square], &camera, 0.5, |_, _| Some(0.0));
+        assert!(segments.iter().all(|segment| !segment.visible));
+    }
+}
@@ -1,4 +1,8 @@
-use crate::bsdf::{LambertianDiffuseReflection, OrenNayarDiffuseReflection};
+use crate::bsdf::{
+    FresnelSpecularReflection, FresnelSpecularTransmission, FresnelThinDielectric,
+    LambertianDiffuseReflection, MicrofacetReflection, OrenNayarDiffuseReflection,
+    TrowbridgeReitzDistribution,
+};
 use crate::color::RgbaSpectrum;
 use crate::TransportMode;
 use crate::{bsdf::Bsdf, interaction::SurfaceInteraction};
@@ -14,7 +18,9 @@ pub trait Material {
     /// function (BSSRDF) is returned as well.
     ///
     /// * interaction -
-    /// * transport_mode -
+    /// * transport_mode - Indicates whether the interaction was found along a
+    ///   path starting from the camera or from a light source. Materials that
+    ///   refract light scale it differently for each.
     /// * allow_multiple_lobes - Indicates whether the material should use BxDFs
     ///   that aggregate multiple types of scattering into a single BxDF when
     ///   such BxDFs are available. Setting this to `true` can improve results
@@ -23,9 +29,49 @@ pub trait Material {
     fn scattering_functions(
         &self,
         interaction: &SurfaceInteraction,
-        // transport_mode: TransportMode,
+        transport_mode: TransportMode,
         // allow_multiple_lobes: bool,
     ) -> Bsdf;
+
+    /// Return a human-readable name for the material. This is used to identify
+    /// the material in reports, such as render statistics. By default this is
+    /// the name of the material's type.
+    fn name(&self) -> &str {
+        let type_name = std::any::type_name::<Self>();
+        type_name.rsplit("::").next().unwrap_or(type_name)
+    }
+
+    /// Return true if the material only marks the boundary between two
+    /// participating media. Such a surface doesn't scatter light, and rays
+    /// pass straight through it into the medium on the other side.
+    fn is_medium_interface(&self) -> bool {
+        false
+    }
+
+    /// Return true if the way the material scatters light depends on the
+    /// light's wavelength, such as glass that disperses white light into a
+    /// spectrum. Spectral integrators evaluate such materials at a single
+    /// wavelength with `scattering_functions_at_wavelength`.
+    fn is_dispersive(&self) -> bool {
+        false
+    }
+
+    /// Return the BSDF at the given surface interaction point for light of a
+    /// single wavelength. By default this is the same BSDF that
+    /// `scattering_functions` returns.
+    ///
+    /// * interaction -
+    /// * transport_mode - Indicates whether the interaction was found along a
+    ///   path starting from the camera or from a light source.
+    /// * wavelength - The light's wavelength in nanometers.
+    fn scattering_functions_at_wavelength(
+        &self,
+        interaction: &SurfaceInteraction,
+        transport_mode: TransportMode,
+        _wavelength: f32,
+    ) -> Bsdf {
+        self.scattering_functions(interaction, transport_mode)
+    }
 }
 
 /// A purely diffuse surface.
@@ -48,7 +94,7 @@ impl Material for MatteMaterial {
     fn scattering_functions(
         &self,
         interaction: &SurfaceInteraction,
-        // transport_mode: TransportMode,
+        _transport_mode: TransportMode,
         // allow_multiple_lobes: bool,
     ) -> Bsdf {
         let mut bsdf = Bsdf::new(interaction);
@@ -63,7 +109,28 @@ impl Material for MatteMaterial {
     }
 }
 
-/// A purely diffuse surface.
+/// A material that marks the boundary of a participating medium, such as the
+/// surface of a fog-filled enclosure, without scattering any light itself.
+pub struct InterfaceMaterial {}
+
+impl Material for InterfaceMaterial {
+    fn scattering_functions(
+        &self,
+        interaction: &SurfaceInteraction,
+        _transport_mode: TransportMode,
+        // allow_multiple_lobes: bool,
+    ) -> Bsdf {
+        Bsdf::new(interaction)
+    }
+
+    fn is_medium_interface(&self) -> bool {
+        true
+    }
+}
+
+/// A diffuse surface under a glossy dielectric coating, like plastic. Light
+/// that's reflected by the coating forms highlights in the color of `ks`,
+/// while the rest of the light is scattered diffusely in the color of `kd`.
 pub struct PlasticMaterial {
     /// Diffuse reflection.
     kd: RgbaSpectrum,
@@ -73,36 +140,455 @@ pub struct PlasticMaterial {
 
     roughness: f32,
 
+    /// Indicates whether `roughness` is a perceptual roughness in [0, 1] that
+    /// should be converted with `remap_roughness` before being used as a
+    /// microfacet distribution's alpha parameter.
     remap_roughness: bool,
 }
 
-// impl PlasticMaterial {
-//     pub fn new(kd: RgbSpectrum, ks: RgbSpectrum, roughness: f32) -> Self {
-//         Self {
-//             kd,
-//             ks,
-//             roughness,
-//         }
-//     }
-// }
-
-// impl Material for PlasticMaterial {
-//     fn scattering_functions(
-//         &self,
-//         interaction: &SurfaceInteraction,
-//         // transport_mode: TransportMode,
-//         // allow_multiple_lobes: bool,
-//     ) -> Bsdf {
-//         let mut bsdf = Bsdf::new(interaction);
-
-//         if !self.kd.is_black() {
-//             bsdf.add(Box::new(LambertianDiffuseReflection::new(self.kd)));
-//         }
-
-//         if !self.ks.is_black() {
-
-//         }
-
-//         bsdf
-//     }
-// }
+impl PlasticMaterial {
+    /// * kd - Diffuse reflection.
+    /// * ks - Glossy specular reflection.
+    /// * roughness - The roughness of the glossy coating. Smaller values give
+    ///   sharper highlights.
+    /// * remap_roughness - Indicates whether `roughness` is a perceptual
+    ///   roughness in [0, 1] rather than a microfacet distribution's alpha
+    ///   parameter.
+    pub fn new(kd: RgbaSpectrum, ks: RgbaSpectrum, roughness: f32, remap_roughness: bool) -> Self {
+        Self {
+            kd,
+            ks,
+            roughness,
+            remap_roughness,
+        }
+    }
+}
+
+impl Material for PlasticMaterial {
+    fn scattering_functions(
+        &self,
+        interaction: &SurfaceInteraction,
+        _transport_mode: TransportMode,
+        // allow_multiple_lobes: bool,
+    ) -> Bsdf {
+        let mut bsdf = Bsdf::new(interaction);
+
+        if !self.kd.is_black() {
+            bsdf.add(Box::new(LambertianDiffuseReflection::new(self.kd)));
+        }
+
+        if !self.ks.is_black() {
+            let alpha = if self.remap_roughness {
+                remap_roughness(self.roughness)
+            } else {
+                self.roughness
+            };
+            // The coating is a dielectric with the index of refraction of
+            // typical plastics.
+            bsdf.add(Box::new(MicrofacetReflection::dielectric(
+                self.ks,
+                TrowbridgeReitzDistribution::isotropic(alpha),
+                1.0,
+                1.5,
+            )));
+        }
+
+        bsdf
+    }
+}
+
+/// A metal surface. Metals don't scatter any light diffusely. Light is only
+/// reflected by the surface, by an amount that depends on the metal's complex
+/// index of refraction, which tints the reflections of colored metals like
+/// copper.
+pub struct MetalMaterial {
+    /// The real part of the metal's index of refraction.
+    eta: RgbaSpectrum,
+
+    /// The metal's absorption coefficient, which is the imaginary part of its
+    /// index of refraction.
+    k: RgbaSpectrum,
+
+    roughness: f32,
+
+    /// Indicates whether `roughness` is a perceptual roughness in [0, 1] that
+    /// should be converted with `remap_roughness` before being used as a
+    /// microfacet distribution's alpha parameter.
+    remap_roughness: bool,
+}
+
+impl MetalMaterial {
+    /// * eta - The real part of the metal's index of refraction.
+    /// * k - The metal's absorption coefficient.
+    /// * roughness - The roughness of the surface. Smaller values give sharper
+    ///   reflections, and 0 gives a mirror finish.
+    /// * remap_roughness - Indicates whether `roughness` is a perceptual
+    ///   roughness in [0, 1] rather than a microfacet distribution's alpha
+    ///   parameter.
+    pub fn new(eta: RgbaSpectrum, k: RgbaSpectrum, roughness: f32, remap_roughness: bool) -> Self {
+        Self {
+            eta,
+            k,
+            roughness,
+            remap_roughness,
+        }
+    }
+
+    /// Create a material for a common alloy, whose roughness is a perceptual
+    /// roughness in [0, 1].
+    pub fn alloy(alloy: Alloy, roughness: f32) -> Self {
+        let (eta, k) = alloy.eta_k();
+        Self::new(eta, k, roughness, true)
+    }
+}
+
+impl Material for MetalMaterial {
+    fn scattering_functions(
+        &self,
+        interaction: &SurfaceInteraction,
+        _transport_mode: TransportMode,
+        // allow_multiple_lobes: bool,
+    ) -> Bsdf {
+        let mut bsdf = Bsdf::new(interaction);
+        // The metal is surrounded by air.
+        if self.roughness == 0.0 {
+            // A perfectly smooth metal is a mirror.
+            bsdf.add(Box::new(FresnelSpecularReflection::conductor(
+                RgbaSpectrum::constant(1.0),
+                RgbaSpectrum::constant(1.0),
+                self.eta,
+                self.k,
+            )));
+            return bsdf;
+        }
+        let alpha = if self.remap_roughness {
+            remap_roughness(self.roughness)
+        } else {
+            self.roughness
+        };
+        bsdf.add(Box::new(MicrofacetReflection::conductor(
+            RgbaSpectrum::constant(1.0),
+            TrowbridgeReitzDistribution::isotropic(alpha),
+            RgbaSpectrum::constant(1.0),
+            self.eta,
+            self.k,
+        )));
+        bsdf
+    }
+}
+
+/// A smooth, transparent dielectric, like glass or a clear polycarbonate
+/// cover. Light is either specularly reflected or refracted through the
+/// surface, in proportions that depend on the angle of incidence.
+pub struct GlassMaterial {
+    /// A spectrum that scales the reflected color.
+    kr: RgbaSpectrum,
+
+    /// A spectrum that scales the transmitted color, which tints the glass.
+    kt: RgbaSpectrum,
+
+    /// The index of refraction inside the glass.
+    eta: f32,
+
+    /// Indicates whether the glass is a thin-walled pane or film, which light
+    /// passes straight through without being bent.
+    thin: bool,
+
+    /// If present, the Abbe number of the glass, which describes how much its
+    /// index of refraction varies with wavelength. See `with_abbe_number`.
+    abbe_number: Option<f32>,
+}
+
+impl GlassMaterial {
+    /// * kr - A spectrum that scales the reflected color.
+    /// * kt - A spectrum that scales the transmitted color.
+    /// * eta - The index of refraction inside the glass. This is about 1.5 for
+    ///   most glasses and clear plastics.
+    pub fn new(kr: RgbaSpectrum, kt: RgbaSpectrum, eta: f32) -> Self {
+        Self {
+            kr,
+            kt,
+            eta,
+            thin: false,
+            abbe_number: None,
+        }
+    }
+
+    /// Return the material, modified so that each surface is treated as a
+    /// pane of zero thickness if `thin` is true. This is the right model for
+    /// display windows and thin plastic films, where refracting light at
+    /// each surface as if it entered a solid would distort what's behind
+    /// them.
+    pub fn with_thin(mut self, thin: bool) -> Self {
+        self.thin = thin;
+        self
+    }
+
+    /// Return the material, modified so that its index of refraction varies
+    /// with wavelength, which splits white light into colors. The glass's
+    /// index of refraction is then taken to be its index at the helium d line,
+    /// 587.6 nm, and the index at other wavelengths follows Cauchy's equation.
+    /// Only spectral integrators render the dispersion.
+    ///
+    /// * abbe_number - The Abbe number of the glass. Lower numbers disperse
+    ///   light more. This is about 64 for crown glass, 30 for polycarbonate,
+    ///   and 20 for very dense flint glass.
+    pub fn with_abbe_number(mut self, abbe_number: f32) -> Self {
+        self.abbe_number = Some(abbe_number);
+        self
+    }
+
+    /// Return the index of refraction inside the glass for light with the
+    /// given wavelength in nanometers.
+    fn eta_at(&self, wavelength: f32) -> f32 {
+        // The wavelengths of the Fraunhofer d, F, and C lines that define the
+        // Abbe number.
+        const LAMBDA_D: f32 = 587.6;
+        const LAMBDA_F: f32 = 486.1;
+        const LAMBDA_C: f32 = 656.3;
+        match self.abbe_number {
+            Some(abbe_number) if abbe_number > 0.0 => {
+                // Choose b in Cauchy's equation, n = a + b / lambda^2, so that
+                // n_d - 1 = abbe_number * (n_F - n_C).
+                let b = (self.eta - 1.0)
+                    / (abbe_number * (1.0 / (LAMBDA_F * LAMBDA_F) - 1.0 / (LAMBDA_C * LAMBDA_C)));
+                let a = self.eta - b / (LAMBDA_D * LAMBDA_D);
+                a + b / (wavelength * wavelength)
+            }
+            _ => self.eta,
+        }
+    }
+
+    /// Return the glass's BSDF at the surface interaction with the given index
+    /// of refraction inside the glass.
+    fn bsdf(
+        &self,
+        interaction: &SurfaceInteraction,
+        transport_mode: TransportMode,
+        eta: f32,
+    ) -> Bsdf {
+        let mut bsdf = Bsdf::new(interaction);
+        // The glass is surrounded by air.
+        if self.thin {
+            bsdf.add(Box::new(FresnelThinDielectric::new(self.kr, self.kt, eta)));
+            return bsdf;
+        }
+        if !self.kr.is_black() {
+            bsdf.add(Box::new(FresnelSpecularReflection::dielectric(
+                self.kr, 1.0, eta,
+            )));
+        }
+        if !self.kt.is_black() {
+            bsdf.add(Box::new(FresnelSpecularTransmission::dielectric(
+                1.0,
+                eta,
+                self.kt,
+                transport_mode,
+            )));
+        }
+        bsdf
+    }
+}
+
+impl Material for GlassMaterial {
+    fn scattering_functions(
+        &self,
+        interaction: &SurfaceInteraction,
+        transport_mode: TransportMode,
+        // allow_multiple_lobes: bool,
+    ) -> Bsdf {
+        self.bsdf(interaction, transport_mode, self.eta)
+    }
+
+    fn is_dispersive(&self) -> bool {
+        // Thin glass doesn't bend light, so there's nothing to disperse.
+        self.abbe_number.is_some() && !self.thin
+    }
+
+    fn scattering_functions_at_wavelength(
+        &self,
+        interaction: &SurfaceInteraction,
+        transport_mode: TransportMode,
+        wavelength: f32,
+    ) -> Bsdf {
+        self.bsdf(interaction, transport_mode, self.eta_at(wavelength))
+    }
+}
+
+/// Common metals that parts are made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alloy {
+    Aluminum,
+    Brass,
+    Copper,
+    Gold,
+    Silver,
+    Steel,
+}
+
+impl Alloy {
+    /// Return the real part of the alloy's index of refraction and its
+    /// absorption coefficient, at the red, green, and blue wavelengths.
+    pub fn eta_k(&self) -> (RgbaSpectrum, RgbaSpectrum) {
+        let (eta, k) = match self {
+            Alloy::Aluminum => ((1.657, 0.880, 0.521), (9.224, 6.270, 4.837)),
+            Alloy::Brass => ((0.444, 0.527, 1.094), (3.695, 2.765, 1.829)),
+            Alloy::Copper => ((0.200, 0.924, 1.102), (3.912, 2.452, 2.142)),
+            Alloy::Gold => ((0.143, 0.374, 1.442), (3.983, 2.385, 1.603)),
+            Alloy::Silver => ((0.155, 0.117, 0.138), (4.828, 3.122, 2.147)),
+            // Steel is mostly iron.
+            Alloy::Steel => ((2.911, 2.950, 2.585), (3.089, 2.932, 2.767)),
+        };
+        (
+            RgbaSpectrum::from_rgb(eta.0, eta.1, eta.2),
+            RgbaSpectrum::from_rgb(k.0, k.1, k.2),
+        )
+    }
+}
+
+/// Map a perceptual roughness value in [0, 1] to the alpha parameter of a
+/// microfacet distribution.
+///
+/// Alpha doesn't change the appearance of a surface linearly, so roughness
+/// values that are chosen by hand are easier to work with when they're
+/// remapped. Every microfacet-based material should use this function so that
+/// the same roughness value produces similar highlights on every material.
+pub fn remap_roughness(roughness: f32) -> f32 {
+    let x = roughness.max(1e-3).ln();
+    1.62142 + 0.819955 * x + 0.1734 * x * x + 0.0171201 * x * x * x + 0.000640711 * x * x * x * x
+}
+
+#[cfg(test)]
+mod remap_roughness_tests {
+    use super::remap_roughness;
+    use crate::test::ApproxEq;
+
+    #[test]
+    fn remapped_roughness_increases_with_roughness() {
+        let alphas: Vec<f32> = [0.0, 0.1, 0.25, 0.5, 0.75, 1.0]
+            .iter()
+            .map(|r| remap_roughness(*r))
+            .collect();
+        assert!(alphas.windows(2).all(|w| w[0] < w[1]));
+        alphas[5].assert_approx_eq(&1.62142);
+    }
+
+    #[test]
+    fn tiny_roughness_is_clamped() {
+        remap_roughness(0.0).assert_approx_eq(&remap_roughness(1e-3));
+        assert!(remap_roughness(0.0) > 0.0);
+    }
+}
+
+#[cfg(test)]
+mod glass_tests {
+    use super::{GlassMaterial, Material};
+    use crate::{
+        bsdf::BxdfType, color::RgbaSpectrum, interaction::SurfaceInteraction, test::ApproxEq,
+        TransportMode,
+    };
+    use cgmath::{InnerSpace, Point2, Point3, Vector3};
+
+    /// Return the sum of the reflected and transmitted light, weighted by the
+    /// probability of sampling each of them, for light arriving at normal
+    /// incidence.
+    fn scattered(glass: GlassMaterial, transport_mode: TransportMode) -> (f32, f32) {
+        let wo = Vector3::new(0.0, 0.0, 1.0);
+        let interaction = SurfaceInteraction::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 0.0),
+            wo,
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
+        let bsdf = glass.scattering_functions(&interaction, transport_mode);
+        // Each sample is weighted by the probability of drawing it.
+        let weight = |sample: Point2<f32>, probability: f32| {
+            let (wi, pdf, f, _) = bsdf.sample_f(&wo, &sample, BxdfType::ALL);
+            f.r() * wi.dot(wo).abs() / pdf * probability
+        };
+        if glass.thin {
+            // The thin glass has a single lobe that reflects when the sample
+            // falls below its reflectance.
+            let reflectance = probability_of_reflection(&bsdf, wo);
+            (
+                weight(Point2::new(0.0, 0.5), reflectance),
+                weight(Point2::new(0.99, 0.5), 1.0 - reflectance),
+            )
+        } else {
+            (
+                weight(Point2::new(0.25, 0.5), 0.5),
+                weight(Point2::new(0.75, 0.5), 0.5),
+            )
+        }
+    }
+
+    /// Return the probability that sampling the thin glass's BSDF chooses
+    /// reflection.
+    fn probability_of_reflection(bsdf: &crate::bsdf::Bsdf, wo: Vector3<f32>) -> f32 {
+        let (_, pdf, _, _) = bsdf.sample_f(&wo, &Point2::new(0.0, 0.5), BxdfType::ALL);
+        pdf
+    }
+
+    fn solid_glass() -> GlassMaterial {
+        GlassMaterial::new(
+            RgbaSpectrum::constant(1.0),
+            RgbaSpectrum::constant(1.0),
+            1.5,
+        )
+    }
+
+    #[test]
+    fn glass_reflects_or_transmits_all_light() {
+        let (reflected, transmitted) = scattered(solid_glass(), TransportMode::Importance);
+        reflected.assert_approx_eq(&0.04);
+        (reflected + transmitted).assert_approx_eq(&1.0);
+    }
+
+    #[test]
+    fn radiance_is_compressed_entering_glass() {
+        let (_, importance) = scattered(solid_glass(), TransportMode::Importance);
+        let (_, radiance) = scattered(solid_glass(), TransportMode::Radiance);
+        radiance.assert_approx_eq(&(importance / (1.5 * 1.5)));
+    }
+
+    #[test]
+    fn thin_glass_reflects_both_surfaces_and_transmits_the_rest() {
+        let (reflected, transmitted) =
+            scattered(solid_glass().with_thin(true), TransportMode::Radiance);
+        // Interreflection between the two surfaces: R + T^2 R / (1 - R^2).
+        reflected.assert_approx_eq(&(0.04 + 0.96 * 0.96 * 0.04 / (1.0 - 0.04 * 0.04)));
+        (reflected + transmitted).assert_approx_eq(&1.0);
+    }
+
+    #[test]
+    fn dispersive_glass_bends_blue_light_more() {
+        let glass = solid_glass().with_abbe_number(30.0);
+        assert!(glass.is_dispersive());
+        glass.eta_at(587.6).assert_approx_eq(&1.5);
+        assert!(glass.eta_at(450.0) > glass.eta_at(650.0));
+        // The Abbe number is the ratio of the refractivity at the d line to
+        // the difference between the indices at the F and C lines.
+        let abbe_number = 0.5 / (glass.eta_at(486.1) - glass.eta_at(656.3));
+        assert!((abbe_number - 30.0).abs() < 1e-3);
+        assert!(!solid_glass().is_dispersive());
+        solid_glass().eta_at(450.0).assert_approx_eq(&1.5);
+    }
+
+    #[test]
+    fn thin_glass_does_not_bend_light() {
+        let wo = Vector3::new(0.6, 0.0, 0.8);
+        let interaction = SurfaceInteraction::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
+        let bsdf = solid_glass()
+            .with_thin(true)
+            .scattering_functions(&interaction, TransportMode::Radiance);
+        let (wi, _, _, _) = bsdf.sample_f(&wo, &Point2::new(0.99, 0.5), BxdfType::ALL);
+        wi.assert_approx_eq(&-wo);
+    }
+}
@@ -0,0 +1,73 @@
+use super::{HenyeyGreenstein, MediumInteraction};
+use crate::{color::RgbaSpectrum, ray::Ray};
+use cgmath::{InnerSpace, Point2};
+
+/// A participating medium with the same scattering properties throughout.
+#[derive(Debug, Clone, Copy)]
+pub struct HomogeneousMedium {
+    /// The scattering coefficient. The probability density per unit distance
+    /// that light is scattered in another direction.
+    sigma_s: RgbaSpectrum,
+
+    /// The attenuation coefficient. The sum of the absorption and scattering
+    /// coefficients.
+    sigma_t: RgbaSpectrum,
+
+    phase: HenyeyGreenstein,
+}
+
+impl HomogeneousMedium {
+    /// Create a homogeneous medium.
+    ///
+    /// * sigma_a - The absorption coefficient. The probability density per unit
+    ///   distance that light is absorbed.
+    /// * sigma_s - The scattering coefficient. The probability density per unit
+    ///   distance that light is scattered in another direction.
+    /// * g - The asymmetry parameter of the medium's Henyey–Greenstein phase
+    ///   function.
+    pub fn new(sigma_a: RgbaSpectrum, sigma_s: RgbaSpectrum, g: f32) -> Self {
+        Self {
+            sigma_s,
+            sigma_t: sigma_a + sigma_s,
+            phase: HenyeyGreenstein::new(g),
+        }
+    }
+
+    pub fn tr(&self, ray: &Ray) -> RgbaSpectrum {
+        let distance = ray.t_max.min(f32::MAX) * ray.direction.magnitude();
+        (-distance * self.sigma_t).exp()
+    }
+
+    pub fn sample(&self, ray: &Ray, u: &Point2<f32>) -> (RgbaSpectrum, Option<MediumInteraction>) {
+        // Choose a color channel and sample a distance along the ray according
+        // to that channel's attenuation.
+        let channel = ((u.x * 3.0) as usize).min(2);
+        let sigma_t = [self.sigma_t.r(), self.sigma_t.g(), self.sigma_t.b()][channel];
+        let direction_length = ray.direction.magnitude();
+        let distance = -(1.0 - u.y).ln() / sigma_t;
+        let t = (distance / direction_length).min(ray.t_max);
+        let sampled_medium = t < ray.t_max;
+
+        // Compute the transmittance and the sampling density, which is the
+        // average of the densities for each color channel.
+        let tr = (-t.min(f32::MAX) * direction_length * self.sigma_t).exp();
+        let density = if sampled_medium {
+            self.sigma_t * tr
+        } else {
+            tr
+        };
+        let pdf = (density.r() + density.g() + density.b()) / 3.0;
+        let pdf = if pdf == 0.0 { 1.0 } else { pdf };
+
+        if sampled_medium {
+            let interaction = MediumInteraction {
+                point: ray.at_t(t),
+                neg_ray_direction: (-1.0 * ray.direction).normalize(),
+                phase: self.phase,
+            };
+            (tr * self.sigma_s / pdf, Some(interaction))
+        } else {
+            (tr / pdf, None)
+        }
+    }
+}
@@ -0,0 +1,207 @@
+mod homogeneous;
+mod phase;
+
+pub use homogeneous::HomogeneousMedium;
+pub use phase::HenyeyGreenstein;
+
+use crate::{color::RgbaSpectrum, interaction::OffsetRayOrigin, ray::Ray};
+use cgmath::{InnerSpace, Point2, Point3, Vector3};
+
+/// The smallest absorption color component. A component of zero would need
+/// an infinite absorption coefficient.
+const MIN_ABSORPTION_COLOR: f32 = 1e-4;
+
+/// A participating medium, such as fog or a translucent solid, that absorbs and
+/// scatters light as it travels through a volume rather than only at surfaces.
+#[derive(Debug, Clone, Copy)]
+pub enum Medium {
+    Homogeneous(HomogeneousMedium),
+}
+
+impl Medium {
+    /// Create a medium with the same scattering properties throughout.
+    ///
+    /// * sigma_a - The absorption coefficient. The probability density per unit
+    ///   distance that light is absorbed.
+    /// * sigma_s - The scattering coefficient. The probability density per unit
+    ///   distance that light is scattered in another direction.
+    /// * g - The asymmetry parameter of the Henyey–Greenstein phase function.
+    pub fn homogeneous(sigma_a: RgbaSpectrum, sigma_s: RgbaSpectrum, g: f32) -> Self {
+        Self::Homogeneous(HomogeneousMedium::new(sigma_a, sigma_s, g))
+    }
+
+    /// Create a medium that absorbs light without scattering it, such as the
+    /// interior of tinted acrylic or resin. Light is attenuated according to
+    /// the Beer–Lambert law, so thick regions of a part appear more deeply
+    /// colored than thin ones.
+    ///
+    /// * color - The color that white light takes on after traveling
+    ///   `distance` through the medium. Components are clamped to (0, 1].
+    /// * distance - The distance, in world units, at which light takes on
+    ///   `color`. Smaller distances give a denser medium.
+    pub fn absorbing(color: RgbaSpectrum, distance: f32) -> Self {
+        let sigma_a = |c: f32| -c.clamp(MIN_ABSORPTION_COLOR, 1.0).ln() / distance;
+        Self::homogeneous(
+            RgbaSpectrum::from_rgb(sigma_a(color.r()), sigma_a(color.g()), sigma_a(color.b())),
+            RgbaSpectrum::black(),
+            0.0,
+        )
+    }
+
+    /// Return the beam transmittance along the ray from its origin to the
+    /// point at `ray.t_max`. This is the fraction of light that travels between
+    /// the two points without being absorbed or scattered.
+    pub fn tr(&self, ray: &Ray) -> RgbaSpectrum {
+        match self {
+            Medium::Homogeneous(m) => m.tr(ray),
+        }
+    }
+
+    /// Sample a point along the ray, before `ray.t_max`, at which light is
+    /// scattered by the medium.
+    ///
+    /// If a scattering point is sampled then the returned medium interaction
+    /// describes it. Otherwise the ray passes through the medium to
+    /// `ray.t_max`. In either case the returned spectrum is the weight that
+    /// the path's throughput should be multiplied by.
+    ///
+    /// * ray - The ray along which a scattering point is sampled.
+    /// * u - A 2D sample in [0, 1) x [0, 1).
+    pub fn sample(&self, ray: &Ray, u: &Point2<f32>) -> (RgbaSpectrum, Option<MediumInteraction>) {
+        match self {
+            Medium::Homogeneous(m) => m.sample(ray, u),
+        }
+    }
+}
+
+/// Describes the media on either side of a surface. A value of `None`
+/// indicates a vacuum.
+#[derive(Clone, Copy, Default)]
+pub struct MediumInterface<'mtrl> {
+    /// The medium on the side of the surface opposite the surface normal.
+    pub inside: Option<&'mtrl Medium>,
+
+    /// The medium on the side of the surface that the surface normal points
+    /// toward.
+    pub outside: Option<&'mtrl Medium>,
+}
+
+impl<'mtrl> MediumInterface<'mtrl> {
+    pub fn new(inside: Option<&'mtrl Medium>, outside: Option<&'mtrl Medium>) -> Self {
+        Self { inside, outside }
+    }
+
+    /// Return true if the media on either side of the surface differ.
+    pub fn is_medium_transition(&self) -> bool {
+        match (self.inside, self.outside) {
+            (Some(inside), Some(outside)) => !std::ptr::eq(inside, outside),
+            (None, None) => false,
+            _ => true,
+        }
+    }
+
+    /// Return the medium that a ray leaving the surface in the direction `w`
+    /// travels through. If the surface isn't a transition between media, the
+    /// ray stays in the medium that the incoming ray was in.
+    ///
+    /// * w - The direction of the ray leaving the surface.
+    /// * normal - The surface normal of the geometry.
+    /// * current - The medium that the incoming ray was in.
+    pub fn medium<'a>(
+        &self,
+        w: &Vector3<f32>,
+        normal: &Vector3<f32>,
+        current: Option<&'a Medium>,
+    ) -> Option<&'a Medium>
+    where
+        'mtrl: 'a,
+    {
+        if !self.is_medium_transition() {
+            current
+        } else if w.dot(*normal) > 0.0 {
+            self.outside
+        } else {
+            self.inside
+        }
+    }
+}
+
+/// A point in a participating medium at which light is scattered.
+#[derive(Debug, Clone, Copy)]
+pub struct MediumInteraction {
+    /// The point in world space where the scattering occurs.
+    pub point: Point3<f32>,
+
+    /// The normalized direction of the negative/outgoing ray.
+    pub neg_ray_direction: Vector3<f32>,
+
+    /// The phase function of the medium at the point.
+    pub phase: HenyeyGreenstein,
+}
+
+impl OffsetRayOrigin for MediumInteraction {
+    fn non_offset_ray_origin(&self) -> Point3<f32> {
+        self.point
+    }
+
+    fn offset_ray_origin(&self, _ray_direction: &Vector3<f32>) -> Point3<f32> {
+        // There is no surface at a point in a medium that a new ray could
+        // accidentally intersect, so the origin doesn't need to be offset.
+        self.point
+    }
+}
+
+#[cfg(test)]
+mod medium_tests {
+    use super::Medium;
+    use crate::{color::RgbaSpectrum, ray::Ray, test::ApproxEq};
+    use cgmath::{Point2, Point3, Vector3};
+
+    #[test]
+    fn transmittance_follows_beer_lambert_law() {
+        let medium = Medium::homogeneous(
+            RgbaSpectrum::from_rgb(0.5, 1.0, 0.0),
+            RgbaSpectrum::from_rgb(0.5, 0.0, 0.0),
+            0.0,
+        );
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 2.0, 0.0), 1.0);
+        let tr = medium.tr(&ray);
+        tr.r().assert_approx_eq(&(-2.0f32).exp());
+        tr.g().assert_approx_eq(&(-2.0f32).exp());
+        tr.b().assert_approx_eq(&1.0);
+    }
+
+    #[test]
+    fn absorbing_medium_reaches_color_at_distance() {
+        let medium = Medium::absorbing(RgbaSpectrum::from_rgb(0.8, 0.4, 1.0), 2.0);
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0), 2.0);
+        let tr = medium.tr(&ray);
+        tr.r().assert_approx_eq(&0.8);
+        tr.g().assert_approx_eq(&0.4);
+        tr.b().assert_approx_eq(&1.0);
+
+        // Twice the distance squares the transmittance.
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0), 4.0);
+        medium.tr(&ray).g().assert_approx_eq(&0.16);
+    }
+
+    #[test]
+    fn sampling_is_weighted_by_transmittance() {
+        // The medium absorbs light but doesn't scatter it, so a path either
+        // passes through with unit weight or is absorbed with zero weight.
+        let medium = Medium::homogeneous(
+            RgbaSpectrum::constant(1.0),
+            RgbaSpectrum::constant(0.0),
+            0.0,
+        );
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0), 3.0);
+
+        let (weight, interaction) = medium.sample(&ray, &Point2::new(0.5, 0.99));
+        assert!(interaction.is_none());
+        weight.r().assert_approx_eq(&1.0);
+
+        let (weight, interaction) = medium.sample(&ray, &Point2::new(0.5, 0.5));
+        assert!(interaction.is_some());
+        assert!(weight.is_black());
+    }
+}
@@ -0,0 +1,98 @@
+use crate::geometry::vector;
+use cgmath::{InnerSpace, Point2, Vector3};
+use std::f32::consts::PI;
+
+/// The Henyey–Greenstein phase function. Describes the distribution of
+/// directions in which light scatters at a point in a participating medium.
+#[derive(Debug, Clone, Copy)]
+pub struct HenyeyGreenstein {
+    /// The asymmetry parameter in (-1, 1). Negative values describe media that
+    /// mostly scatter light back toward where it came from, positive values
+    /// describe media that mostly scatter light forward, and 0 describes media
+    /// that scatter light equally in all directions.
+    g: f32,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(g: f32) -> Self {
+        Self { g }
+    }
+
+    /// Return the value of the phase function for the pair of directions.
+    ///
+    /// * wo - The normalized outgoing direction, pointing away from the
+    ///   scattering point.
+    /// * wi - The normalized incident direction, pointing away from the
+    ///   scattering point.
+    pub fn p(&self, wo: &Vector3<f32>, wi: &Vector3<f32>) -> f32 {
+        henyey_greenstein(wo.dot(*wi), self.g)
+    }
+
+    /// Sample an incident direction given the outgoing direction `wo`. Return
+    /// the incident direction and the value of the phase function, which is
+    /// also the probability density of sampling the incident direction.
+    ///
+    /// * wo - The normalized outgoing direction, pointing away from the
+    ///   scattering point.
+    /// * u - A 2D sample in [0, 1) x [0, 1).
+    pub fn sample_p(&self, wo: &Vector3<f32>, u: &Point2<f32>) -> (Vector3<f32>, f32) {
+        // Compute the cosine of the angle between the sampled direction and the
+        // direction in which light would continue without scattering.
+        let g = self.g;
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u.x
+        } else {
+            let sqr_term = (1.0 - g * g) / (1.0 + g - 2.0 * g * u.x);
+            -(1.0 + g * g - sqr_term * sqr_term) / (2.0 * g)
+        };
+
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * u.y;
+        let (v1, v2) = vector::arbitrary_coordinate_system(*wo);
+        let wi = sin_theta * phi.cos() * v1 + sin_theta * phi.sin() * v2 - cos_theta * wo;
+        (wi, henyey_greenstein(-cos_theta, g))
+    }
+}
+
+/// Evaluate the Henyey–Greenstein phase function.
+///
+/// * cos_theta - The cosine of the angle between the outgoing and incident
+///   directions, where both point away from the scattering point.
+/// * g - The asymmetry parameter.
+fn henyey_greenstein(cos_theta: f32, g: f32) -> f32 {
+    let denom = 1.0 + g * g + 2.0 * g * cos_theta;
+    (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+}
+
+#[cfg(test)]
+mod henyey_greenstein_tests {
+    use super::HenyeyGreenstein;
+    use crate::{sampling, test::ApproxEq};
+    use cgmath::{InnerSpace, Point2, Vector3};
+
+    #[test]
+    fn phase_function_integrates_to_one() {
+        let samples = sampling::grid_samples_2d(200);
+        let wo = Vector3::new(0.0, 0.6, 0.8);
+        for g in &[-0.5, 0.0, 0.3] {
+            let phase = HenyeyGreenstein::new(*g);
+            let sum: f32 = samples
+                .iter()
+                .map(|u| phase.p(&wo, &sampling::uniform_sample_sphere(u)))
+                .sum();
+            let integral = sum / (samples.len() as f32 * sampling::uniform_sphere_pdf());
+            assert!((integral - 1.0).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn sampled_density_matches_phase_function() {
+        let phase = HenyeyGreenstein::new(0.7);
+        let wo = Vector3::new(0.0, 0.6, 0.8);
+        for u in &[Point2::new(0.1, 0.2), Point2::new(0.8, 0.9)] {
+            let (wi, pdf) = phase.sample_p(&wo, u);
+            wi.magnitude().assert_approx_eq(&1.0);
+            pdf.assert_approx_eq(&phase.p(&wo, &wi));
+        }
+    }
+}
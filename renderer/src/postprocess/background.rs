@@ -0,0 +1,44 @@
+use super::PostProcess;
+use crate::color::RgbaSpectrum;
+use cgmath::Vector2;
+
+/// Composites the image over a solid background color, producing a fully
+/// opaque image.
+pub struct BackgroundComposite {
+    color: RgbaSpectrum,
+}
+
+impl BackgroundComposite {
+    pub fn new(color: RgbaSpectrum) -> Self {
+        Self { color }
+    }
+}
+
+impl PostProcess for BackgroundComposite {
+    fn apply(&self, pixels: &mut [RgbaSpectrum], _resolution: Vector2<usize>) {
+        for pixel in pixels {
+            let coverage = pixel.a().clamp(0.0, 1.0);
+            *pixel += self.color * (1.0 - coverage);
+            pixel.set_a(1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod background_composite_tests {
+    use super::BackgroundComposite;
+    use crate::{color::RgbaSpectrum, postprocess::PostProcess, test::ApproxEq};
+    use cgmath::Vector2;
+
+    #[test]
+    fn transparent_pixels_become_background() {
+        let background = BackgroundComposite::new(RgbaSpectrum::from_rgb(0.2, 0.4, 0.6));
+        let mut pixels = vec![
+            RgbaSpectrum::transparent(),
+            RgbaSpectrum::from_rgba(0.5, 0.5, 0.5, 0.5),
+        ];
+        background.apply(&mut pixels, Vector2::new(2, 1));
+        pixels[0].assert_approx_eq(&RgbaSpectrum::from_rgba(0.2, 0.4, 0.6, 1.0));
+        pixels[1].assert_approx_eq(&RgbaSpectrum::from_rgba(0.6, 0.7, 0.8, 1.0));
+    }
+}
@@ -0,0 +1,101 @@
+use super::PostProcess;
+use crate::color::RgbaSpectrum;
+use cgmath::Vector2;
+
+/// Remaps the red, green, and blue channels of every pixel through a
+/// piecewise-linear tone curve.
+///
+/// Colors are un-premultiplied before the curve is applied so that partially
+/// covered pixels at the edges of objects are remapped the same way as fully
+/// covered pixels.
+pub struct Curves {
+    /// Control points of the curve, sorted by input value.
+    points: Vec<(f32, f32)>,
+}
+
+impl Curves {
+    /// Create a tone curve.
+    ///
+    /// * points - The `(input, output)` control points of the curve. Inputs
+    ///   below the first point or above the last point are mapped to the
+    ///   output of the nearest point. Points whose input or output isn't
+    ///   finite are ignored. If there are no points then the curve is the
+    ///   identity.
+    pub fn new(points: Vec<(f32, f32)>) -> Self {
+        let mut points: Vec<(f32, f32)> = points
+            .into_iter()
+            .filter(|(x, y)| x.is_finite() && y.is_finite())
+            .collect();
+        points.sort_by(|(x1, _), (x2, _)| x1.total_cmp(x2));
+        Self { points }
+    }
+
+    /// Return the output of the curve for the given input. An input that is
+    /// NaN is mapped like an input below the first point.
+    fn eval(&self, x: f32) -> f32 {
+        let (first, last) = match (self.points.first(), self.points.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return x,
+        };
+        if x.is_nan() || x <= first.0 {
+            return first.1;
+        }
+        if x >= last.0 {
+            return last.1;
+        }
+
+        let i = self.points.iter().position(|(x1, _)| *x1 > x).unwrap();
+        let (x0, y0) = self.points[i - 1];
+        let (x1, y1) = self.points[i];
+        let t = (x - x0) / (x1 - x0);
+        y0 + t * (y1 - y0)
+    }
+}
+
+impl PostProcess for Curves {
+    fn apply(&self, pixels: &mut [RgbaSpectrum], _resolution: Vector2<usize>) {
+        for pixel in pixels {
+            let a = pixel.a();
+            if a <= 0.0 {
+                continue;
+            }
+            *pixel = RgbaSpectrum::from_rgba(
+                self.eval(pixel.r() / a) * a,
+                self.eval(pixel.g() / a) * a,
+                self.eval(pixel.b() / a) * a,
+                a,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod curves_tests {
+    use super::Curves;
+
+    #[test]
+    fn eval_interpolates_between_points() {
+        let curves = Curves::new(vec![(1.0, 0.5), (0.0, 0.0)]);
+        assert!((curves.eval(0.5) - 0.25).abs() < 0.0001);
+        assert!((curves.eval(-1.0) - 0.0).abs() < 0.0001);
+        assert!((curves.eval(2.0) - 0.5).abs() < 0.0001);
+    }
+
+    #[test]
+    fn non_finite_points_and_inputs_are_ignored() {
+        let curves = Curves::new(vec![
+            (f32::NAN, 1.0),
+            (1.0, 0.5),
+            (0.0, f32::NAN),
+            (0.0, 0.0),
+        ]);
+        assert!((curves.eval(0.5) - 0.25).abs() < 0.0001);
+        assert!((curves.eval(f32::NAN) - 0.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn empty_curve_is_identity() {
+        let curves = Curves::new(vec![]);
+        assert!((curves.eval(0.3) - 0.3).abs() < 0.0001);
+    }
+}
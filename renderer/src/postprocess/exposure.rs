@@ -0,0 +1,89 @@
+use super::PostProcess;
+use crate::color::RgbaSpectrum;
+use cgmath::Vector2;
+
+/// Scales the color of every pixel by a power of two, as if the camera's
+/// exposure had been adjusted by the given number of stops. Alpha is left
+/// unchanged.
+pub struct Exposure {
+    stops: f32,
+}
+
+impl Exposure {
+    pub fn new(stops: f32) -> Self {
+        Self { stops }
+    }
+
+    /// Create an exposure that maps radiance in candela per square meter to
+    /// pixel values the way a camera with the given exposure value at ISO 100
+    /// would.
+    ///
+    /// This uses the saturation-based sensitivity model, so the brightest
+    /// radiance that isn't clipped is 1.2 * 2^ev100. For example, an exposure
+    /// value of 15 suits a sunlit scene, and an exposure value of 7 suits a
+    /// brightly lit interior.
+    pub fn from_ev100(ev100: f32) -> Self {
+        Self::new(-ev100 - 1.2f32.log2())
+    }
+
+    /// Create an exposure that maps radiance in candela per square meter to
+    /// pixel values the way a camera with the given settings would.
+    ///
+    /// * iso - The sensor's sensitivity.
+    /// * shutter_seconds - The time that the shutter is open.
+    /// * f_number - The ratio of the lens's focal length to the diameter of
+    ///   its aperture.
+    pub fn from_camera_settings(iso: f32, shutter_seconds: f32, f_number: f32) -> Self {
+        Self::from_ev100(ev100(iso, shutter_seconds, f_number))
+    }
+}
+
+/// Return the exposure value at ISO 100 that is equivalent to the given
+/// camera settings.
+fn ev100(iso: f32, shutter_seconds: f32, f_number: f32) -> f32 {
+    (f_number * f_number / shutter_seconds * 100.0 / iso).log2()
+}
+
+impl PostProcess for Exposure {
+    fn apply(&self, pixels: &mut [RgbaSpectrum], _resolution: Vector2<usize>) {
+        let scale = 2.0f32.powf(self.stops);
+        for pixel in pixels {
+            let a = pixel.a();
+            *pixel *= scale;
+            pixel.set_a(a);
+        }
+    }
+}
+
+#[cfg(test)]
+mod exposure_tests {
+    use super::{ev100, Exposure};
+    use crate::{color::RgbaSpectrum, postprocess::PostProcess};
+    use cgmath::vec2;
+
+    #[test]
+    fn camera_settings_match_exposure_value() {
+        // The "sunny 16" rule: f/16 at a shutter speed of 1 / ISO.
+        assert!((ev100(100.0, 1.0 / 100.0, 16.0) - 14.644).abs() < 1e-3);
+        assert!((ev100(400.0, 1.0 / 400.0, 16.0) - 14.644).abs() < 1e-3);
+
+        // Radiance of 1.2 * 2^ev100 is mapped to white.
+        let mut pixels = vec![RgbaSpectrum::constant(1.2 * 2.0f32.powf(10.0))];
+        Exposure::from_camera_settings(100.0, 1.0, 2.0f32.powf(5.0)).apply(&mut pixels, vec2(1, 1));
+        assert!((pixels[0].r() - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn stops_scale_color_but_not_alpha() {
+        let mut pixels = vec![RgbaSpectrum::from_rgba(0.1, 0.2, 0.3, 0.5)];
+        Exposure::new(2.0).apply(&mut pixels, vec2(1, 1));
+        assert!((pixels[0].r() - 0.4).abs() < 1e-4);
+        assert!((pixels[0].g() - 0.8).abs() < 1e-4);
+        assert!((pixels[0].b() - 1.2).abs() < 1e-4);
+        assert!((pixels[0].a() - 0.5).abs() < 1e-4);
+
+        Exposure::new(-1.0).apply(&mut pixels, vec2(1, 1));
+        assert!((pixels[0].r() - 0.2).abs() < 1e-4);
+        assert!((pixels[0].a() - 0.5).abs() < 1e-4);
+    }
+}
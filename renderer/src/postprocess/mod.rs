@@ -0,0 +1,39 @@
+//! Provides image-space operations that are applied to a rendered image after
+//! the film's pixels have been reconstructed but before they are quantized
+//! into an 8-bit output image.
+
+mod background;
+mod curves;
+mod exposure;
+mod sharpen;
+mod tonemap;
+
+pub use {
+    background::BackgroundComposite,
+    curves::Curves,
+    exposure::Exposure,
+    sharpen::Sharpen,
+    tonemap::{Tonemap, TonemapOperator},
+};
+
+use crate::color::RgbaSpectrum;
+use cgmath::Vector2;
+
+/// An operation that modifies the pixels of a floating point image.
+///
+/// The pixels of the image are in row-major order, starting at the top-left
+/// pixel. Their colors are linear and have premultiplied alpha.
+pub trait PostProcess: Send + Sync {
+    fn apply(&self, pixels: &mut [RgbaSpectrum], resolution: Vector2<usize>);
+}
+
+/// Apply each post-processing step in the chain to the image, in order.
+pub fn apply_all(
+    chain: &[Box<dyn PostProcess>],
+    pixels: &mut [RgbaSpectrum],
+    resolution: Vector2<usize>,
+) {
+    for step in chain {
+        step.apply(pixels, resolution);
+    }
+}
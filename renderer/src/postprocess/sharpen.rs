@@ -0,0 +1,75 @@
+use super::PostProcess;
+use crate::color::RgbaSpectrum;
+use cgmath::Vector2;
+
+/// Sharpens the image with an unsharp mask.
+///
+/// Each pixel is pushed away from the average of its 3x3 neighborhood, which
+/// exaggerates edges. Pixels at the border of the image use only the
+/// neighbors that are inside the image.
+pub struct Sharpen {
+    amount: f32,
+}
+
+impl Sharpen {
+    /// Create a sharpening step.
+    ///
+    /// * amount - How strongly edges are exaggerated. An amount of 0 leaves
+    ///   the image unchanged.
+    pub fn new(amount: f32) -> Self {
+        Self { amount }
+    }
+}
+
+impl PostProcess for Sharpen {
+    fn apply(&self, pixels: &mut [RgbaSpectrum], resolution: Vector2<usize>) {
+        let original = pixels.to_vec();
+        for y in 0..resolution.y {
+            for x in 0..resolution.x {
+                let mut sum = RgbaSpectrum::transparent();
+                let mut count = 0;
+                for ny in y.saturating_sub(1)..(y + 2).min(resolution.y) {
+                    for nx in x.saturating_sub(1)..(x + 2).min(resolution.x) {
+                        sum += original[ny * resolution.x + nx];
+                        count += 1;
+                    }
+                }
+                let blurred = sum / count as f32;
+                let index = y * resolution.x + x;
+                let sharpened = original[index] + (original[index] - blurred) * self.amount;
+                pixels[index] = sharpened;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod sharpen_tests {
+    use super::Sharpen;
+    use crate::{color::RgbaSpectrum, postprocess::PostProcess, test::ApproxEq};
+    use cgmath::vec2;
+
+    #[test]
+    fn flat_image_is_unchanged() {
+        let mut pixels = vec![RgbaSpectrum::from_rgba(0.2, 0.4, 0.6, 1.0); 9];
+        Sharpen::new(1.0).apply(&mut pixels, vec2(3, 3));
+        for pixel in &pixels {
+            pixel.assert_approx_eq(&RgbaSpectrum::from_rgba(0.2, 0.4, 0.6, 1.0));
+        }
+    }
+
+    #[test]
+    fn edges_are_exaggerated() {
+        // A dark pixel next to a bright one in a 2x1 image. Each pixel's
+        // neighborhood is the whole image, whose average is 0.5.
+        let mut pixels = vec![RgbaSpectrum::constant(0.25), RgbaSpectrum::constant(0.75)];
+        Sharpen::new(1.0).apply(&mut pixels, vec2(2, 1));
+        pixels[0].assert_approx_eq(&RgbaSpectrum::constant(0.0));
+        pixels[1].assert_approx_eq(&RgbaSpectrum::constant(1.0));
+
+        let mut pixels = vec![RgbaSpectrum::constant(0.25), RgbaSpectrum::constant(0.75)];
+        Sharpen::new(0.0).apply(&mut pixels, vec2(2, 1));
+        pixels[0].assert_approx_eq(&RgbaSpectrum::constant(0.25));
+        pixels[1].assert_approx_eq(&RgbaSpectrum::constant(0.75));
+    }
+}
@@ -0,0 +1,156 @@
+use super::PostProcess;
+use crate::color::RgbaSpectrum;
+use cgmath::Vector2;
+
+/// A curve that compresses linear radiance, which is unbounded, into the
+/// range [0, 1] of a display.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TonemapOperator {
+    /// Leave colors unchanged, so values above 1 are clipped when the image is
+    /// quantized.
+    Clip,
+
+    /// Scale each color by `1 / (1 + L)`, where `L` is its luminance. This
+    /// preserves hue and never quite reaches white.
+    Reinhard,
+
+    /// Like `Reinhard`, but colors with a luminance of `white` or more are
+    /// mapped to white.
+    ReinhardExtended { white: f32 },
+
+    /// An approximation of the ACES filmic curve, which has a gentle toe and
+    /// shoulder and desaturates very bright colors the way film does.
+    AcesFilmic,
+}
+
+impl TonemapOperator {
+    /// Map the un-premultiplied linear color to display values.
+    fn map(&self, rgb: [f32; 3]) -> [f32; 3] {
+        match self {
+            TonemapOperator::Clip => rgb,
+            TonemapOperator::Reinhard => scale_luminance(rgb, |l| l / (1.0 + l)),
+            TonemapOperator::ReinhardExtended { white } => {
+                let inv_white_sq = 1.0 / (white * white);
+                scale_luminance(rgb, |l| l * (1.0 + l * inv_white_sq) / (1.0 + l))
+            }
+            TonemapOperator::AcesFilmic => {
+                // Krzysztof Narkowicz's fit of the ACES reference rendering
+                // and output transforms.
+                let aces = |x: f32| {
+                    let x = x * 0.6;
+                    ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0)
+                };
+                [aces(rgb[0]), aces(rgb[1]), aces(rgb[2])]
+            }
+        }
+    }
+}
+
+/// Scale the color so that its luminance is remapped by the given curve.
+fn scale_luminance(rgb: [f32; 3], curve: impl Fn(f32) -> f32) -> [f32; 3] {
+    let luminance = 0.212671 * rgb[0] + 0.715160 * rgb[1] + 0.072169 * rgb[2];
+    if luminance <= 0.0 {
+        return rgb;
+    }
+    let scale = curve(luminance) / luminance;
+    [rgb[0] * scale, rgb[1] * scale, rgb[2] * scale]
+}
+
+/// Exposes the image, compresses its colors with a tonemapping operator, and
+/// applies a display gamma, so that bright highlights roll off smoothly
+/// instead of clipping at 1.
+///
+/// Colors are un-premultiplied before they are tonemapped so that partially
+/// covered pixels at the edges of objects are mapped the same way as fully
+/// covered pixels.
+pub struct Tonemap {
+    operator: TonemapOperator,
+
+    /// The number of stops that colors are brightened by before they are
+    /// tonemapped.
+    exposure_stops: f32,
+
+    /// Each tonemapped component is raised to the power `1 / gamma`.
+    gamma: f32,
+}
+
+impl Tonemap {
+    pub fn new(operator: TonemapOperator) -> Self {
+        Self {
+            operator,
+            exposure_stops: 0.0,
+            gamma: 1.0,
+        }
+    }
+
+    /// Brighten (or darken, if negative) the image by the given number of
+    /// stops before it is tonemapped.
+    pub fn with_exposure(mut self, stops: f32) -> Self {
+        self.exposure_stops = stops;
+        self
+    }
+
+    /// Encode the tonemapped colors for a display with the given gamma, such
+    /// as 2.2. A gamma of 1 leaves the colors linear. Films already encode
+    /// their images as sRGB by default, so other gammas are only useful with
+    /// films that write linear colors.
+    pub fn with_gamma(mut self, gamma: f32) -> Self {
+        self.gamma = gamma;
+        self
+    }
+}
+
+impl PostProcess for Tonemap {
+    fn apply(&self, pixels: &mut [RgbaSpectrum], _resolution: Vector2<usize>) {
+        let scale = 2.0f32.powf(self.exposure_stops);
+        let inv_gamma = 1.0 / self.gamma;
+        for pixel in pixels {
+            let a = pixel.a();
+            if a <= 0.0 {
+                continue;
+            }
+            let [r, g, b] = self.operator.map([
+                pixel.r() / a * scale,
+                pixel.g() / a * scale,
+                pixel.b() / a * scale,
+            ]);
+            let encode = |c: f32| c.max(0.0).powf(inv_gamma) * a;
+            *pixel = RgbaSpectrum::from_rgba(encode(r), encode(g), encode(b), a);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tonemap_tests {
+    use super::{Tonemap, TonemapOperator};
+    use crate::{color::RgbaSpectrum, postprocess::PostProcess};
+    use cgmath::vec2;
+
+    #[test]
+    fn operators_compress_highlights_below_white() {
+        for operator in &[
+            TonemapOperator::Reinhard,
+            TonemapOperator::ReinhardExtended { white: 8.0 },
+            TonemapOperator::AcesFilmic,
+        ] {
+            let [r, g, b] = operator.map([4.0, 4.0, 4.0]);
+            assert!(r < 1.0 && r > 0.5, "{:?} mapped 4 to {}", operator, r);
+            assert!((r - g).abs() < 1e-4 && (g - b).abs() < 1e-4);
+        }
+        let [white, _, _] = TonemapOperator::ReinhardExtended { white: 8.0 }.map([8.0; 3]);
+        assert!((white - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn transparent_pixels_are_unchanged() {
+        let tonemap = Tonemap::new(TonemapOperator::Reinhard)
+            .with_exposure(1.0)
+            .with_gamma(2.2);
+        let mut pixels = [RgbaSpectrum::transparent(), RgbaSpectrum::constant(1.0)];
+        tonemap.apply(&mut pixels, vec2(2, 1));
+        assert_eq!(pixels[0].a(), 0.0);
+        assert_eq!(pixels[0].r(), 0.0);
+        // Exposed to 2, tonemapped to 2 / 3, and gamma encoded.
+        assert!((pixels[1].r() - (2.0f32 / 3.0).powf(1.0 / 2.2)).abs() < 1e-4);
+    }
+}
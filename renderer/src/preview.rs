@@ -0,0 +1,293 @@
+//! Provides a rasterizer that quickly renders a rough preview of a scene.
+//!
+//! The preview ignores materials and lights. Each triangle, point cloud
+//! splat, and visible voxel face in the scene is projected onto the image and
+//! hidden surfaces are removed with a z-buffer, which takes a fraction of the
+//! time needed to ray trace the scene.
+
+use crate::{
+    camera::OrthographicCamera,
+    color::RgbaSpectrum,
+    scene::Scene,
+    shape::{Shape, Splat},
+};
+use cgmath::{InnerSpace, Matrix4, Point2, Point3, SquareMatrix, Transform, Vector2, Vector3};
+use image::{ImageBuffer, Rgba};
+
+/// Describes how the surfaces in a preview are shaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewShading {
+    /// Each surface is gray, with a brightness that depends on how directly
+    /// it faces the camera.
+    Flat,
+
+    /// Each surface's color is its world-space normal, with the x, y, and z
+    /// components mapped from [-1, 1] to the red, green, and blue channels in
+    /// [0, 1].
+    Normal,
+}
+
+/// Rasterize the scene as seen by the camera. Pixels that aren't covered by
+/// any surface are transparent.
+///
+/// * resolution - The resolution of the preview. This should match the
+///   resolution that the camera was created with.
+pub fn rasterize(
+    scene: &Scene,
+    camera: &OrthographicCamera,
+    resolution: Vector2<usize>,
+    shading: PreviewShading,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let mut raster = Raster::new(camera, resolution, shading);
+    for primitive in scene.primitives.primitives() {
+        match primitive.shape {
+            Shape::Triangle(triangle) => {
+                let (p0, p1, p2) = triangle.0.positions();
+                raster.draw_triangle([p0, p1, p2]);
+            }
+            Shape::Splat(splat) => raster.draw_splat(&splat),
+            Shape::Voxels(voxel_grid) => {
+                for [p0, p1, p2, p3] in voxel_grid.exposed_faces() {
+                    raster.draw_triangle([p0, p1, p2]);
+                    raster.draw_triangle([p0, p2, p3]);
+                }
+            }
+        }
+    }
+    raster.into_image()
+}
+
+/// A z-buffer and the colors of the nearest surfaces that have been drawn
+/// into it.
+struct Raster {
+    world_to_raster: Matrix4<f32>,
+    raster_to_world: Matrix4<f32>,
+
+    /// The direction in which the camera looks, in world space.
+    view_direction: Vector3<f32>,
+
+    /// A world-space unit vector that is perpendicular to the view direction.
+    view_perpendicular: Vector3<f32>,
+
+    shading: PreviewShading,
+    resolution: Vector2<usize>,
+    depths: Vec<f32>,
+    colors: Vec<RgbaSpectrum>,
+}
+
+impl Raster {
+    fn new(
+        camera: &OrthographicCamera,
+        resolution: Vector2<usize>,
+        shading: PreviewShading,
+    ) -> Self {
+        let world_to_raster = camera.world_to_raster();
+        let pixel_count = resolution.x * resolution.y;
+        Self {
+            world_to_raster,
+            raster_to_world: world_to_raster.invert().unwrap_or_else(Matrix4::identity),
+            view_direction: camera
+                .camera_to_world
+                .transform_vector(Vector3::new(0.0, 0.0, 1.0))
+                .normalize(),
+            view_perpendicular: camera
+                .camera_to_world
+                .transform_vector(Vector3::new(1.0, 0.0, 0.0))
+                .normalize(),
+            shading,
+            resolution,
+            depths: vec![f32::INFINITY; pixel_count],
+            colors: vec![RgbaSpectrum::transparent(); pixel_count],
+        }
+    }
+
+    /// Draw the triangle, whose vertices are in world space.
+    fn draw_triangle(&mut self, vertices: [Point3<f32>; 3]) {
+        let [p0, p1, p2] = vertices;
+        let normal = (p1 - p0).cross(p2 - p0);
+        if normal.magnitude2() == 0.0 {
+            return;
+        }
+        let color = self.shade(normal.normalize());
+
+        let r0 = self.world_to_raster.transform_point(p0);
+        let r1 = self.world_to_raster.transform_point(p1);
+        let r2 = self.world_to_raster.transform_point(p2);
+        let (depths, colors) = (&mut self.depths, &mut self.colors);
+        rasterize_triangle(self.resolution, [r0, r1, r2], |index, depth| {
+            if (0.0..=1.0).contains(&depth) && depth < depths[index] {
+                depths[index] = depth;
+                colors[index] = color;
+            }
+        });
+    }
+
+    /// Draw the splat, which is a sphere, as a disk that is shaded with the
+    /// sphere's normal at each pixel.
+    fn draw_splat(&mut self, splat: &Splat) {
+        let center = self.world_to_raster.transform_point(splat.center);
+        let radius = self
+            .world_to_raster
+            .transform_vector(self.view_perpendicular * splat.radius)
+            .truncate()
+            .magnitude();
+        let min_x = (center.x - radius).floor().max(0.0) as usize;
+        let min_y = (center.y - radius).floor().max(0.0) as usize;
+        let max_x = ((center.x + radius).ceil().max(0.0) as usize).min(self.resolution.x);
+        let max_y = ((center.y + radius).ceil().max(0.0) as usize).min(self.resolution.y);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                // The pixel's center on the plane through the splat's center
+                // that faces the camera.
+                let on_plane = self.raster_to_world.transform_point(Point3::new(
+                    x as f32 + 0.5,
+                    y as f32 + 0.5,
+                    center.z,
+                ));
+                let distance_squared = (on_plane - splat.center).magnitude2();
+                if distance_squared > splat.radius * splat.radius {
+                    continue;
+                }
+                let height = (splat.radius * splat.radius - distance_squared).sqrt();
+                let surface = on_plane - self.view_direction * height;
+                let depth = self.world_to_raster.transform_point(surface).z;
+                let index = y * self.resolution.x + x;
+                if (0.0..=1.0).contains(&depth) && depth < self.depths[index] {
+                    self.depths[index] = depth;
+                    self.colors[index] = self.shade((surface - splat.center) / splat.radius);
+                }
+            }
+        }
+    }
+
+    /// Return the color of a surface with the given world-space unit normal.
+    fn shade(&self, normal: Vector3<f32>) -> RgbaSpectrum {
+        match self.shading {
+            PreviewShading::Flat => RgbaSpectrum::constant(normal.dot(self.view_direction).abs()),
+            PreviewShading::Normal => RgbaSpectrum::from_rgb(
+                0.5 * normal.x + 0.5,
+                0.5 * normal.y + 0.5,
+                0.5 * normal.z + 0.5,
+            ),
+        }
+    }
+
+    fn into_image(self) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        let resolution = self.resolution;
+        ImageBuffer::from_fn(resolution.x as u32, resolution.y as u32, |x, y| {
+            let index = y as usize * resolution.x + x as usize;
+            let mut color = self.colors[index];
+            if self.depths[index].is_finite() {
+                color.set_a(1.0);
+            }
+            color.into()
+        })
+    }
+}
+
+/// Call `f` with the row-major index and interpolated depth of each pixel
+/// whose center is covered by the triangle, whose vertices are in raster
+/// space.
+fn rasterize_triangle<F>(resolution: Vector2<usize>, vertices: [Point3<f32>; 3], mut f: F)
+where
+    F: FnMut(usize, f32),
+{
+    let [r0, r1, r2] = vertices;
+    let area = edge_function(&r0, &r1, &Point2::new(r2.x, r2.y));
+    if area == 0.0 {
+        return;
+    }
+
+    let min_x = r0.x.min(r1.x).min(r2.x).floor().max(0.0) as usize;
+    let min_y = r0.y.min(r1.y).min(r2.y).floor().max(0.0) as usize;
+    let max_x = (r0.x.max(r1.x).max(r2.x).ceil().max(0.0) as usize).min(resolution.x);
+    let max_y = (r0.y.max(r1.y).max(r2.y).ceil().max(0.0) as usize).min(resolution.y);
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let p = Point2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let w0 = edge_function(&r1, &r2, &p) / area;
+            let w1 = edge_function(&r2, &r0, &p) / area;
+            let w2 = edge_function(&r0, &r1, &p) / area;
+            if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                continue;
+            }
+            let depth = w0 * r0.z + w1 * r1.z + w2 * r2.z;
+            f(y * resolution.x + x, depth);
+        }
+    }
+}
+
+/// Return twice the signed area of the triangle formed by `a`, `b`, and `p`,
+/// ignoring z.
+fn edge_function(a: &Point3<f32>, b: &Point3<f32>, p: &Point2<f32>) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+#[cfg(test)]
+mod rasterize_tests {
+    use super::{rasterize, PreviewShading};
+    use crate::{
+        color::RgbaSpectrum,
+        material::MatteMaterial,
+        primitive::PrimitiveAggregate,
+        scene::Scene,
+        shape::{Shape, Splat, VoxelGrid},
+        validation,
+    };
+    use cgmath::{Matrix4, Point3, Vector2, Vector3};
+    use typed_arena::Arena;
+
+    #[test]
+    fn covered_pixels_are_opaque() {
+        let mesh_arena = Arena::new();
+        let material_arena = Arena::<MatteMaterial>::new();
+        let scene = validation::macbeth_chart_scene(&mesh_arena, &material_arena);
+        let resolution = Vector2::new(80, 60);
+        let camera = validation::macbeth_chart_camera(resolution);
+        let image = rasterize(&scene, &camera, resolution, PreviewShading::Flat);
+
+        // The corner of the image is outside the chart, and the center of the
+        // top-left patch is covered by a triangle that faces the camera.
+        let corner = image.get_pixel(0, 0);
+        assert_eq!(corner.0[3], 0);
+
+        let patch_center = image.get_pixel(9, 11);
+        assert_eq!(patch_center.0[3], 255);
+        assert_eq!(patch_center.0[0], 255);
+    }
+
+    #[test]
+    fn splats_and_voxels_are_drawn() {
+        let material = MatteMaterial::new(RgbaSpectrum::constant(1.0), 0.0);
+        let mut voxel_grid = VoxelGrid::new(
+            Vector3::new(1, 1, 1),
+            Matrix4::from_translation(Vector3::new(1.0, -0.5, 0.0)),
+        );
+        voxel_grid.set(Point3::new(0, 0, 0), true);
+        let scene = Scene::new(
+            PrimitiveAggregate::Vector(vec![
+                PrimitiveAggregate::primitive(
+                    Shape::Splat(Splat::new(Point3::new(-2.0, 0.0, 0.0), 0.5)),
+                    &material,
+                ),
+                PrimitiveAggregate::primitive(Shape::Voxels(&voxel_grid), &material),
+            ]),
+            vec![],
+        );
+        let resolution = Vector2::new(80, 60);
+        let camera = validation::macbeth_chart_camera(resolution);
+        let image = rasterize(&scene, &camera, resolution, PreviewShading::Flat);
+
+        // The camera sees about ten pixels per unit, and the center of the
+        // image is at the origin.
+        let splat_center = image.get_pixel(19, 30);
+        assert_eq!(splat_center.0[3], 255);
+        assert!(splat_center.0[0] > 240);
+        let voxel_center = image.get_pixel(55, 30);
+        assert_eq!(voxel_center.0[3], 255);
+        assert_eq!(voxel_center.0[0], 255);
+        assert_eq!(image.get_pixel(37, 30).0[3], 0);
+    }
+}
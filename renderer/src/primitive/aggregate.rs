@@ -1,7 +1,18 @@
 use super::Primitive;
 use crate::number;
-use crate::{interaction::SurfaceInteraction, material::Material, ray::Ray, triangle::Triangle};
-use bvh::bvh::BVH;
+use crate::{
+    interaction::SurfaceInteraction,
+    light::AreaEmission,
+    material::Material,
+    medium::MediumInterface,
+    ray::Ray,
+    scene::MemoryReport,
+    shape::{Shape, Splat},
+    triangle::Triangle,
+    triangle_simd,
+};
+use bvh::aabb::{Bounded, AABB};
+use bvh::bvh::{BVHNode, BVH};
 use mesh::Mesh;
 
 // An aggregate of primitives, each of which contains a shape and a material.
@@ -12,7 +23,7 @@ pub enum PrimitiveAggregate<'msh, 'mtrl> {
 }
 
 impl<'msh, 'mtrl> PrimitiveAggregate<'msh, 'mtrl> {
-    pub fn primitive(shape: Triangle<'msh>, material: &'mtrl (dyn Material + Send + Sync)) -> Self {
+    pub fn primitive(shape: Shape<'msh>, material: &'mtrl (dyn Material + Send + Sync)) -> Self {
         Self::Primitive(Primitive::new(shape, material))
     }
 
@@ -37,9 +48,21 @@ impl<'msh, 'mtrl> PrimitiveAggregate<'msh, 'mtrl> {
                 .min_by(|(t1, _, _), (t2, _, _)| number::f32::total_cmp(t1, t2)),
             PrimitiveAggregate::Bvh(ps, bvh) => {
                 let hit_primitives = bvh.traverse(&ray.into(), ps);
+                // Rule out the triangles that the ray misses in batches before
+                // testing the rest individually.
+                let triangles: Vec<_> = hit_primitives
+                    .iter()
+                    .map(|p| match p.shape {
+                        Shape::Triangle(Triangle(t)) => Some(t.positions()),
+                        _ => None,
+                    })
+                    .collect();
+                let may_hit = triangle_simd::may_intersect(ray, &triangles);
                 hit_primitives
                     .iter()
-                    .filter_map(|&&p| {
+                    .zip(may_hit)
+                    .filter(|(_, may_hit)| *may_hit)
+                    .filter_map(|(&&p, _)| {
                         p.shape
                             .ray_intersection(ray)
                             .map(|(t, interaction)| (t, p, interaction))
@@ -49,13 +72,207 @@ impl<'msh, 'mtrl> PrimitiveAggregate<'msh, 'mtrl> {
         }
     }
 
+    /// Return every primitive in the aggregate.
+    pub fn primitives(&self) -> Vec<&Primitive<'msh, 'mtrl>> {
+        match self {
+            PrimitiveAggregate::Primitive(p) => vec![p],
+            PrimitiveAggregate::Vector(ps) => ps.iter().flat_map(|p| p.primitives()).collect(),
+            PrimitiveAggregate::Bvh(ps, _) => ps.iter().collect(),
+        }
+    }
+
+    /// Add the aggregate's primitives to the memory report. Each aggregate of
+    /// primitives is counted as a group.
+    pub(crate) fn add_to_memory_report(&self, report: &mut MemoryReport) {
+        let primitive_size = std::mem::size_of::<Primitive>();
+        match self {
+            PrimitiveAggregate::Primitive(p) => {
+                report.add_group(std::iter::once(&p.shape), primitive_size, 0)
+            }
+            PrimitiveAggregate::Vector(ps) => {
+                for p in ps {
+                    p.add_to_memory_report(report);
+                }
+            }
+            PrimitiveAggregate::Bvh(ps, bvh) => {
+                report.add_group(ps.iter().map(|p| &p.shape), primitive_size, bvh.nodes.len())
+            }
+        }
+    }
+
+    /// Move the triangles of the `original` mesh onto the `deformed` mesh,
+    /// and update the bounds of each bounding volume hierarchy in the
+    /// aggregate to fit its primitives' new positions. The hierarchies keep
+    /// their structure instead of being rebuilt, which is much cheaper for
+    /// large meshes, but traversing them becomes slower the further the
+    /// vertices move, so a hierarchy should be rebuilt after large
+    /// deformations. Primitives that aren't part of `original` are unchanged.
+    ///
+    /// * original - The mesh that the aggregate's triangles were created from.
+    /// * deformed - A mesh with the same triangles as `original`, whose
+    ///   vertices have moved, such as a deflected or toleranced copy of it.
+    pub fn refit<'new>(
+        self,
+        original: &Mesh,
+        deformed: &'new Mesh,
+    ) -> PrimitiveAggregate<'new, 'mtrl>
+    where
+        'msh: 'new,
+    {
+        debug_assert_eq!(
+            original.triangle_vertex_indices.len(),
+            deformed.triangle_vertex_indices.len()
+        );
+        let move_primitive = |p: Primitive<'msh, 'mtrl>| -> Primitive<'new, 'mtrl> {
+            match p.shape {
+                Shape::Triangle(Triangle(t)) if std::ptr::eq(t.mesh, original) => Primitive {
+                    shape: Shape::Triangle(Triangle(mesh::Triangle {
+                        mesh: deformed,
+                        index_in_mesh: t.index_in_mesh,
+                    })),
+                    ..p
+                },
+                _ => p,
+            }
+        };
+        match self {
+            PrimitiveAggregate::Primitive(p) => PrimitiveAggregate::Primitive(move_primitive(p)),
+            PrimitiveAggregate::Vector(ps) => PrimitiveAggregate::Vector(
+                ps.into_iter()
+                    .map(|p| p.refit(original, deformed))
+                    .collect(),
+            ),
+            PrimitiveAggregate::Bvh(ps, mut bvh) => {
+                let ps: Vec<Primitive> = ps.into_iter().map(move_primitive).collect();
+                refit_bvh(&mut bvh, &ps);
+                PrimitiveAggregate::Bvh(ps, bvh)
+            }
+        }
+    }
+
     pub fn from_mesh(mesh: &'msh Mesh, material: &'mtrl (dyn Material + Send + Sync)) -> Self {
+        Self::from_mesh_with_medium_interface(mesh, material, MediumInterface::default())
+    }
+
+    /// Create an aggregate of the mesh's triangles, where the media on either
+    /// side of the mesh's surface are described by `medium_interface`.
+    pub fn from_mesh_with_medium_interface(
+        mesh: &'msh Mesh,
+        material: &'mtrl (dyn Material + Send + Sync),
+        medium_interface: MediumInterface<'mtrl>,
+    ) -> Self {
         let mut primitives: Vec<Primitive> = mesh
             .triangles()
             .into_iter()
-            .map(|t| Primitive::new(Triangle(t), material))
+            .map(|t| {
+                Primitive::new_with_medium_interface(
+                    Shape::Triangle(Triangle(t)),
+                    material,
+                    medium_interface,
+                )
+            })
             .collect();
         let bvh = BVH::build(&mut primitives);
         Self::Bvh(primitives, bvh)
     }
+
+    /// Create an aggregate of the mesh's triangles, each of which emits light.
+    /// The same emission should be given to an area light that's created
+    /// from the mesh with `Light::area_light`, so that the mesh also
+    /// illuminates the rest of the scene.
+    pub fn from_emissive_mesh(
+        mesh: &'msh Mesh,
+        material: &'mtrl (dyn Material + Send + Sync),
+        emission: AreaEmission,
+    ) -> Self {
+        let mut primitives: Vec<Primitive> = mesh
+            .triangles()
+            .into_iter()
+            .map(|t| Primitive::new(Shape::Triangle(Triangle(t)), material).with_emission(emission))
+            .collect();
+        let bvh = BVH::build(&mut primitives);
+        Self::Bvh(primitives, bvh)
+    }
+
+    /// Create an aggregate of splats, such as the points in a point cloud.
+    /// Each splat is paired with its own material so that points can be
+    /// colored individually.
+    pub fn from_splats(splats: Vec<(Splat, &'mtrl (dyn Material + Send + Sync))>) -> Self {
+        let mut primitives: Vec<Primitive> = splats
+            .into_iter()
+            .map(|(splat, material)| Primitive::new(Shape::Splat(splat), material))
+            .collect();
+        let bvh = BVH::build(&mut primitives);
+        Self::Bvh(primitives, bvh)
+    }
+}
+
+/// Update the bounds that each node of the hierarchy stores for its children
+/// so that they fit the shapes' current bounds.
+fn refit_bvh<T: Bounded>(bvh: &mut BVH, shapes: &[T]) {
+    if shapes.is_empty() {
+        return;
+    }
+    // A node's children are always stored after it, so visiting the nodes in
+    // reverse order updates each child's bounds before its parent's.
+    let mut node_bounds = vec![AABB::empty(); bvh.nodes.len()];
+    for index in (0..bvh.nodes.len()).rev() {
+        node_bounds[index] = match &mut bvh.nodes[index] {
+            BVHNode::Leaf { shape_index, .. } => shapes[*shape_index].aabb(),
+            BVHNode::Node {
+                child_l_index,
+                child_l_aabb,
+                child_r_index,
+                child_r_aabb,
+                ..
+            } => {
+                *child_l_aabb = node_bounds[*child_l_index];
+                *child_r_aabb = node_bounds[*child_r_index];
+                child_l_aabb.join(child_r_aabb)
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod aggregate_tests {
+    use super::PrimitiveAggregate;
+    use crate::{color::RgbaSpectrum, material::MatteMaterial, ray::Ray};
+    use cgmath::{point3, vec3, Matrix4};
+    use mesh::MeshBuilder;
+
+    #[test]
+    fn refit_aggregate_follows_deformed_mesh() {
+        let grid = |x: f32, y: f32| {
+            let positions = vec![
+                point3(x, y, 0.0),
+                point3(x + 1.0, y, 0.0),
+                point3(x + 1.0, y + 1.0, 0.0),
+                point3(x, y + 1.0, 0.0),
+            ];
+            MeshBuilder::new(
+                positions,
+                vec![vec3(0.0, 0.0, 1.0); 4],
+                vec![(0, 1, 2), (0, 2, 3)],
+            )
+            .build()
+        };
+        let material = MatteMaterial::new(RgbaSpectrum::constant(0.5), 0.0);
+        let original = grid(0.0, 0.0);
+        let other = grid(5.0, 0.0);
+        let mut deformed = grid(0.0, 0.0);
+        deformed.transform(Matrix4::from_translation(vec3(2.0, 0.0, 2.0)));
+        let aggregate = PrimitiveAggregate::Vector(vec![
+            PrimitiveAggregate::from_mesh(&original, &material),
+            PrimitiveAggregate::from_mesh(&other, &material),
+        ]);
+
+        let aggregate = aggregate.refit(&original, &deformed);
+
+        let down = |x: f32| Ray::new(point3(x, 0.5, 10.0), vec3(0.0, 0.0, -1.0), f32::INFINITY);
+        let (t, _, _) = aggregate.ray_intersection(&down(2.5)).unwrap();
+        assert!((t - 8.0).abs() < 1e-4);
+        let (t, _, _) = aggregate.ray_intersection(&down(5.5)).unwrap();
+        assert!((t - 10.0).abs() < 1e-4);
+    }
 }
@@ -2,29 +2,69 @@ mod aggregate;
 
 pub use aggregate::PrimitiveAggregate;
 
-use crate::{material::Material, triangle::Triangle};
+use crate::{
+    color::RgbaSpectrum, interaction::SurfaceInteraction, light::AreaEmission, material::Material,
+    medium::MediumInterface, shape::Shape,
+};
 use bvh::{aabb::Bounded, bounding_hierarchy::BHShape};
+use cgmath::Vector3;
 
 /// Combines a shape and a reference to a material. This is the basic primitive
 /// used in the construction of primitives aggregates.
 #[derive(Clone, Copy)]
 pub struct Primitive<'msh, 'mtrl> {
-    pub shape: Triangle<'msh>,
+    pub shape: Shape<'msh>,
     pub material: &'mtrl (dyn Material + Send + Sync),
 
+    /// The participating media on either side of the shape's surface.
+    pub medium_interface: MediumInterface<'mtrl>,
+
+    /// The light that the shape's surface emits if it's part of an area light.
+    pub emission: Option<AreaEmission>,
+
     /// Tracks the index of the primitives in a bounding volume
     /// hierarchy if it is stored in one.
     bvh_node_index: usize,
 }
 
 impl<'msh, 'mtrl> Primitive<'msh, 'mtrl> {
-    pub fn new(shape: Triangle<'msh>, material: &'mtrl (dyn Material + Send + Sync)) -> Self {
+    pub fn new(shape: Shape<'msh>, material: &'mtrl (dyn Material + Send + Sync)) -> Self {
+        Self::new_with_medium_interface(shape, material, MediumInterface::default())
+    }
+
+    pub fn new_with_medium_interface(
+        shape: Shape<'msh>,
+        material: &'mtrl (dyn Material + Send + Sync),
+        medium_interface: MediumInterface<'mtrl>,
+    ) -> Self {
         Self {
             shape,
             material,
+            medium_interface,
+            emission: None,
             bvh_node_index: 0,
         }
     }
+
+    /// Return the primitive, modified so that its surface emits light.
+    pub fn with_emission(mut self, emission: AreaEmission) -> Self {
+        self.emission = Some(emission);
+        self
+    }
+
+    /// Return the radiance that the primitive's surface emits from the
+    /// interaction point in the direction `w`. This is black unless the
+    /// primitive is part of an area light.
+    pub fn emitted_radiance(
+        &self,
+        interaction: &SurfaceInteraction,
+        w: &Vector3<f32>,
+    ) -> RgbaSpectrum {
+        match &self.emission {
+            Some(emission) => emission.l(&interaction.original_geometry.normal, w),
+            None => RgbaSpectrum::black(),
+        }
+    }
 }
 
 impl<'msh, 'mtrl> Bounded for Primitive<'msh, 'mtrl> {
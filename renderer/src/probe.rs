@@ -0,0 +1,213 @@
+//! Renders the chrome and gray ball pair that lighting artists use as a
+//! reference when they match renders to photographs.
+//!
+//! The balls are shaded directly from the lights rather than ray traced, as
+//! if they were alone in the lighting environment. The chrome ball is a
+//! perfect mirror, so it shows what surrounds the scene and where the lights
+//! are. The gray ball is a diffuse surface with an 18% albedo, so it shows
+//! the intensity and direction of the light that reaches each side of the
+//! part.
+
+use crate::{
+    color::RgbaSpectrum,
+    film::Film,
+    filter::BoxFilter,
+    interaction::SurfaceInteraction,
+    light::{Light, LightFlags},
+    ray::Ray,
+    sampling,
+};
+use cgmath::{InnerSpace, Matrix4, Point2, Point3, Transform, Vector2, Vector3};
+use rayon::prelude::*;
+use std::f32::consts::PI;
+
+/// The albedo of the gray ball, which is that of a standard gray card.
+const GRAY_ALBEDO: f32 = 0.18;
+
+/// The angular radius, in radians, of the disk that a light at a single point
+/// appears as in the chrome ball. A mirror would otherwise never reflect it.
+const HIGHLIGHT_ANGLE: f32 = 0.04;
+
+/// The number of samples along each side of the grid of samples that the
+/// light from lights which aren't at a single point is estimated with.
+const IRRADIANCE_GRID_SIZE: usize = 8;
+
+/// The number of samples along each side of the grid of samples taken in each
+/// pixel, which antialiases the balls' edges.
+const PIXEL_GRID_SIZE: usize = 4;
+
+/// The fraction of the height of the image that each ball's diameter fills.
+const BALL_SIZE: f32 = 0.9;
+
+/// Render the chrome ball on the left half of the image and the gray ball on
+/// the right half, as seen by a camera looking along the viewing direction.
+/// Pixels that aren't covered by a ball are transparent.
+///
+/// * lights - The lights that illuminate the balls. Their positions are
+///   relative to the balls, which are centered at the origin and have a
+///   radius of one, like a part that is scaled to fit in a unit sphere.
+/// * camera_to_world - The camera's orientation. Only its rotation matters.
+/// * resolution - The resolution of the image, which should be about twice as
+///   wide as it is tall.
+pub fn render_probes(
+    lights: &[Light],
+    camera_to_world: &Matrix4<f32>,
+    resolution: Vector2<usize>,
+) -> Film {
+    let right = camera_to_world
+        .transform_vector(Vector3::new(1.0, 0.0, 0.0))
+        .normalize();
+    let up = camera_to_world
+        .transform_vector(Vector3::new(0.0, 1.0, 0.0))
+        .normalize();
+    let view_direction = camera_to_world
+        .transform_vector(Vector3::new(0.0, 0.0, 1.0))
+        .normalize();
+
+    let half_width = resolution.x as f32 / 2.0;
+    let radius = 0.5 * BALL_SIZE * half_width.min(resolution.y as f32);
+    let pixel_samples = sampling::grid_samples_2d(PIXEL_GRID_SIZE);
+    let irradiance_samples = sampling::grid_samples_2d(IRRADIANCE_GRID_SIZE);
+
+    let pixels: Vec<RgbaSpectrum> = (0..resolution.x * resolution.y)
+        .into_par_iter()
+        .map(|index| {
+            let pixel = Point2::new((index % resolution.x) as f32, (index / resolution.x) as f32);
+            let sum = pixel_samples
+                .iter()
+                .fold(RgbaSpectrum::transparent(), |sum, offset| {
+                    let film_point = pixel + Vector2::new(offset.x, offset.y);
+                    let is_chrome = film_point.x < half_width;
+                    let center_x = if is_chrome { 0.5 } else { 1.5 } * half_width;
+                    let u = (film_point.x - center_x) / radius;
+                    let v = (0.5 * resolution.y as f32 - film_point.y) / radius;
+                    let w2 = 1.0 - u * u - v * v;
+                    if w2 < 0.0 {
+                        return sum;
+                    }
+                    // The point on the ball that faces the camera.
+                    let normal = (u * right + v * up - w2.sqrt() * view_direction).normalize();
+                    let mut radiance = if is_chrome {
+                        chrome_radiance(lights, &normal, &view_direction)
+                    } else {
+                        gray_radiance(lights, &normal, &irradiance_samples)
+                    };
+                    radiance.set_a(1.0);
+                    sum + radiance
+                });
+            sum / pixel_samples.len() as f32
+        })
+        .collect();
+
+    let mut film = Film::new(resolution);
+    let filter = BoxFilter::new(0.5, 0.5);
+    let sample_bounds = film.sample_bounds(0.5, 0.5);
+    if let Some(mut tile) = film.tile(&sample_bounds, 0.5, 0.5) {
+        for (index, pixel) in pixels.iter().enumerate() {
+            let center = Point2::new(
+                (index % resolution.x) as f32 + 0.5,
+                (index / resolution.x) as f32 + 0.5,
+            );
+            tile.add_sample(&center, pixel, 1.0, &filter);
+        }
+        film.merge_tile(&tile);
+    }
+    film
+}
+
+/// Return the radiance that a mirror ball reflects towards the camera from the
+/// point on its surface with the normal.
+fn chrome_radiance(
+    lights: &[Light],
+    normal: &Vector3<f32>,
+    view_direction: &Vector3<f32>,
+) -> RgbaSpectrum {
+    let reflected = view_direction - 2.0 * view_direction.dot(*normal) * normal;
+    let point = Point3::new(normal.x, normal.y, normal.z);
+    let ray = Ray::new(point, reflected, f32::INFINITY);
+    let interaction = ball_interaction(normal);
+    let highlight_solid_angle = 2.0 * PI * (1.0 - HIGHLIGHT_ANGLE.cos());
+    lights.iter().fold(RgbaSpectrum::black(), |sum, light| {
+        if light.flags().contains(LightFlags::DELTA_POSITION) {
+            // The light is drawn as a small disk whose radiance gives the
+            // same irradiance as the light.
+            let (li, wi, _vis, pdf) = light.sample_li(&interaction, &Point2::new(0.5, 0.5));
+            if pdf > 0.0 && wi.dot(reflected) >= HIGHLIGHT_ANGLE.cos() {
+                sum + li / (pdf * highlight_solid_angle)
+            } else {
+                sum
+            }
+        } else {
+            sum + light.le(&ray)
+        }
+    })
+}
+
+/// Return the radiance that a diffuse gray ball reflects from the point on its
+/// surface with the normal. The ball is convex, so no part of it shadows
+/// another.
+fn gray_radiance(
+    lights: &[Light],
+    normal: &Vector3<f32>,
+    irradiance_samples: &[Point2<f32>],
+) -> RgbaSpectrum {
+    let interaction = ball_interaction(normal);
+    let irradiance = lights.iter().fold(RgbaSpectrum::black(), |sum, light| {
+        let samples: &[Point2<f32>] = if light.flags().contains(LightFlags::DELTA_POSITION) {
+            &[Point2::new(0.5, 0.5)]
+        } else {
+            irradiance_samples
+        };
+        let light_sum = samples.iter().fold(RgbaSpectrum::black(), |sum, u| {
+            let (li, wi, _vis, pdf) = light.sample_li(&interaction, u);
+            let cos_theta = wi.dot(*normal);
+            if pdf == 0.0 || cos_theta <= 0.0 {
+                sum
+            } else {
+                sum + li * (cos_theta / pdf)
+            }
+        });
+        sum + light_sum / samples.len() as f32
+    });
+    irradiance * (GRAY_ALBEDO / PI)
+}
+
+/// Return the interaction at the point on the ball with the normal.
+fn ball_interaction(normal: &Vector3<f32>) -> SurfaceInteraction {
+    let tangent = if normal.x.abs() > 0.9 {
+        Vector3::unit_y()
+    } else {
+        Vector3::unit_x()
+    };
+    let dpdu = normal.cross(tangent).normalize();
+    let dpdv = normal.cross(dpdu);
+    SurfaceInteraction::new_with_normal(
+        Point3::new(normal.x, normal.y, normal.z),
+        Vector3::new(0.0, 0.0, 0.0),
+        *normal,
+        dpdu,
+        dpdv,
+        *normal,
+    )
+}
+
+#[cfg(test)]
+mod probe_tests {
+    use super::{render_probes, GRAY_ALBEDO};
+    use crate::{color::RgbaSpectrum, light::Light};
+    use cgmath::{Matrix4, SquareMatrix, Vector2};
+
+    #[test]
+    fn balls_under_a_uniform_sky_match_their_reflectances() {
+        let lights = vec![Light::uniform_infinite_light(RgbaSpectrum::constant(1.0))];
+        let film = render_probes(&lights, &Matrix4::identity(), Vector2::new(40, 20));
+        let pixels = film.to_rgba();
+        let chrome = pixels[10 * 40 + 10];
+        let gray = pixels[10 * 40 + 30];
+        let corner = pixels[0];
+        assert!((chrome.r() - 1.0).abs() < 0.02);
+        assert!((gray.r() - GRAY_ALBEDO).abs() < 0.02);
+        assert_eq!(chrome.a(), 1.0);
+        assert_eq!(corner.a(), 0.0);
+    }
+}
@@ -1,6 +1,6 @@
 use cgmath::{Matrix4, Point3, Transform, Vector3};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Ray {
     pub origin: Point3<f32>,
     pub direction: Vector3<f32>,
@@ -9,6 +9,11 @@ pub struct Ray {
     /// r(t) = o + t*d, 0 < t < time_max
     /// Limits the ray to a finite segment.
     pub t_max: f32,
+
+    /// Auxilary rays that are offset from this ray by one pixel on the film
+    /// plane. These are used to estimate the footprint of the ray on the
+    /// surfaces it hits.
+    pub differential: Option<RayDifferential>,
 }
 
 impl Ray {
@@ -17,13 +22,36 @@ impl Ray {
             origin,
             direction,
             t_max,
+            differential: None,
         }
     }
 
+    /// Return the ray with the given auxilary rays attached.
+    pub fn with_differential(mut self, differential: Option<RayDifferential>) -> Self {
+        self.differential = differential;
+        self
+    }
+
     /// Get the position along the ray for a given parametric value, `t`.
     pub fn at_t(&self, t: f32) -> Point3<f32> {
         self.origin + self.direction * t
     }
+
+    /// Scale the offsets between this ray and its auxilary rays.
+    ///
+    /// Camera ray differentials are offset by one pixel, but when several
+    /// samples are taken in each pixel each sample accounts for a smaller
+    /// footprint. Scaling by 1/sqrt(samples per pixel) accounts for this.
+    pub fn scale_differentials(&mut self, scale: f32) {
+        if let Some(differential) = &mut self.differential {
+            differential.dx_origin = self.origin + (differential.dx_origin - self.origin) * scale;
+            differential.dy_origin = self.origin + (differential.dy_origin - self.origin) * scale;
+            differential.dx_direction =
+                self.direction + (differential.dx_direction - self.direction) * scale;
+            differential.dy_direction =
+                self.direction + (differential.dy_direction - self.direction) * scale;
+        }
+    }
 }
 
 impl Into<bvh::ray::Ray> for &Ray {
@@ -44,6 +72,7 @@ impl crate::geometry::Transform<Ray> for Matrix4<f32> {
             // to scale an object.
             direction: self.transform_vector(ray.direction),
             t_max: ray.t_max,
+            differential: ray.differential.as_ref().map(|rd| self.transform(rd)),
         }
     }
 }
@@ -51,7 +80,7 @@ impl crate::geometry::Transform<Ray> for Matrix4<f32> {
 /// Contains the origin and direction of two auxilary rays for some primary ray.
 /// The auxilary rays are offset from the primary in the x and y directions,
 /// respectively, on the film plane.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct RayDifferential {
     /// Origin of a ray that is offset from some primary ray in the x direction
     /// on the film plane.
@@ -99,7 +128,7 @@ impl crate::geometry::transform::Transform<RayDifferential> for Matrix4<f32> {
 
 #[cfg(test)]
 mod tests {
-    use super::Ray;
+    use super::{Ray, RayDifferential};
     use crate::{geometry::Transform, test::ApproxEq};
     use cgmath::{Matrix4, Point3, Vector3};
 
@@ -109,6 +138,7 @@ mod tests {
             origin: Point3::new(2.0, 3.0, 4.0),
             direction: Vector3::new(1.0, 0.0, 0.0),
             t_max: f32::MAX,
+            differential: None,
         };
         assert!(ray.at_t(0.0).approx_eq(&Point3::new(2.0, 3.0, 4.0)));
         assert!(ray.at_t(1.0).approx_eq(&Point3::new(3.0, 3.0, 4.0)));
@@ -122,6 +152,7 @@ mod tests {
             origin: Point3::new(1.0, 2.0, 3.0),
             direction: Vector3::new(0.0, 1.0, 0.0),
             t_max: f32::MAX,
+            differential: None,
         };
         let t: Matrix4<f32> = Matrix4::from_translation(Vector3::new(3.0, 4.0, 5.0));
         let ray = t.transform(&ray);
@@ -135,10 +166,37 @@ mod tests {
             origin: Point3::new(1.0, 2.0, 3.0),
             direction: Vector3::new(0.0, 1.0, 0.0),
             t_max: f32::MAX,
+            differential: None,
         };
         let t: Matrix4<f32> = Matrix4::from_nonuniform_scale(2.0, 3.0, 4.0);
         let ray = t.transform(&ray);
         assert!(ray.origin.approx_eq(&Point3::new(2.0, 6.0, 12.0)));
         assert!(ray.direction.approx_eq(&Vector3::new(0.0, 3.0, 0.0)));
     }
+
+    #[test]
+    fn scaling_differentials() {
+        let mut ray = Ray::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            f32::MAX,
+        )
+        .with_differential(Some(RayDifferential::new(
+            Point3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Point3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, 1.0, 1.0),
+        )));
+        ray.scale_differentials(0.5);
+        let differential = ray.differential.unwrap();
+        assert!(differential
+            .dx_origin
+            .approx_eq(&Point3::new(0.5, 0.0, 0.0)));
+        assert!(differential
+            .dy_origin
+            .approx_eq(&Point3::new(0.0, 0.5, 0.0)));
+        assert!(differential
+            .dy_direction
+            .approx_eq(&Vector3::new(0.0, 0.5, 1.0)));
+    }
 }
@@ -0,0 +1,211 @@
+use super::IncrementalSampler;
+use crate::{color::RgbaSpectrum, film::SampleStatistics};
+use cgmath::Point2;
+
+/// Settings that let an `AdaptiveSampler` vary the number of samples taken in
+/// each pixel according to how noisy the pixel is.
+///
+/// Pixels are sampled until the estimated variance of their mean luminance
+/// falls below a threshold. Smooth pixels stop early, and noisy pixels, such
+/// as those on edges or in soft shadows, receive more samples than the sampler
+/// would otherwise generate.
+#[derive(Debug, Clone, Copy)]
+pub struct AdaptiveSampling {
+    /// The number of samples that are always taken in a pixel before its
+    /// variance is checked.
+    pub min_samples: usize,
+
+    /// The largest number of samples that are taken in a pixel.
+    pub max_samples: usize,
+
+    /// Sampling stops once the estimated variance of a pixel's mean luminance
+    /// falls below this value.
+    pub variance_threshold: f32,
+}
+
+impl AdaptiveSampling {
+    /// * min_samples - The number of samples that are always taken in a pixel
+    ///   before its variance is checked. At least two samples are needed to
+    ///   estimate variance.
+    /// * max_samples - The largest number of samples that are taken in a pixel.
+    /// * variance_threshold - Sampling stops once the estimated variance of a
+    ///   pixel's mean luminance falls below this value.
+    pub fn new(min_samples: usize, max_samples: usize, variance_threshold: f32) -> Self {
+        let min_samples = min_samples.max(2);
+        Self {
+            min_samples,
+            max_samples: max_samples.max(min_samples),
+            variance_threshold,
+        }
+    }
+
+    /// Return true if no more samples should be taken in a pixel with the
+    /// given statistics.
+    pub fn is_done(&self, statistics: &SampleStatistics) -> bool {
+        statistics.count >= self.max_samples
+            || (statistics.count >= self.min_samples
+                && statistics.variance_of_mean() < self.variance_threshold)
+    }
+}
+
+/// A sampler that wraps another sampler and varies the number of sample
+/// vectors it generates for each pixel according to how noisy the pixel is.
+///
+/// The render loop reports the radiance of each sample with `record_radiance`.
+/// Once the estimated variance of a pixel's mean luminance falls below the
+/// threshold, `start_next_sample` returns `false`. Noisy pixels keep receiving
+/// samples, up to the maximum. When the wrapped sampler runs out of sample
+/// vectors for a noisy pixel, it is restarted on the same pixel.
+pub struct AdaptiveSampler<S> {
+    sampler: S,
+    adaptive_sampling: AdaptiveSampling,
+    current_pixel: Point2<i32>,
+
+    /// The number of sample vectors that have been generated for the current
+    /// pixel, not counting the current one.
+    current_sample_index: usize,
+
+    /// Statistics of the radiance recorded for the current pixel's samples.
+    statistics: SampleStatistics,
+}
+
+impl<S: IncrementalSampler> AdaptiveSampler<S> {
+    /// Create a new adaptive sampler.
+    ///
+    /// * sampler - Generates the sample vectors.
+    /// * adaptive_sampling - Determines how many samples are taken in each
+    ///   pixel.
+    pub fn new(sampler: S, adaptive_sampling: AdaptiveSampling) -> Self {
+        Self {
+            sampler,
+            adaptive_sampling,
+            current_pixel: Point2::new(0, 0),
+            current_sample_index: 0,
+            statistics: SampleStatistics::default(),
+        }
+    }
+}
+
+impl<S: IncrementalSampler> IncrementalSampler for AdaptiveSampler<S> {
+    fn clone_with_seed(&self, seed: u64) -> Self {
+        Self::new(self.sampler.clone_with_seed(seed), self.adaptive_sampling)
+    }
+
+    /// Return the number of sample vectors that the wrapped sampler generates
+    /// for each pixel. The number actually generated varies from pixel to
+    /// pixel.
+    fn samples_per_pixel(&self) -> usize {
+        self.sampler.samples_per_pixel()
+    }
+
+    fn start_pixel(&mut self, pixel: Point2<i32>) {
+        self.current_pixel = pixel;
+        self.current_sample_index = 0;
+        self.statistics = SampleStatistics::default();
+        self.sampler.start_pixel(pixel);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        self.sampler.get_1d()
+    }
+
+    fn get_2d(&mut self) -> Point2<f32> {
+        self.sampler.get_2d()
+    }
+
+    fn start_next_sample(&mut self) -> bool {
+        self.current_sample_index += 1;
+        // The sample count is also checked here in case radiance isn't
+        // recorded, so that sampling always ends.
+        if self.current_sample_index >= self.adaptive_sampling.max_samples
+            || self.adaptive_sampling.is_done(&self.statistics)
+        {
+            return false;
+        }
+        if !self.sampler.start_next_sample() {
+            // The pixel is still too noisy, so start another round of samples
+            // in it.
+            self.sampler.start_pixel(self.current_pixel);
+        }
+        true
+    }
+
+    fn record_radiance(&mut self, radiance: &RgbaSpectrum) {
+        self.statistics.add(radiance);
+    }
+
+    fn prepare_1d_array(&mut self, count: usize) {
+        self.sampler.prepare_1d_array(count);
+    }
+
+    fn prepare_2d_array(&mut self, count: usize) {
+        self.sampler.prepare_2d_array(count);
+    }
+
+    fn round_count(&self, ideal_count: usize) -> usize {
+        self.sampler.round_count(ideal_count)
+    }
+
+    fn get_1d_vec(&mut self) -> Option<Vec<f32>> {
+        self.sampler.get_1d_vec()
+    }
+
+    fn get_2d_vec(&mut self) -> Option<Vec<Point2<f32>>> {
+        self.sampler.get_2d_vec()
+    }
+}
+
+#[cfg(test)]
+mod adaptive_sampler_tests {
+    use super::{AdaptiveSampler, AdaptiveSampling};
+    use crate::{
+        color::RgbaSpectrum, film::SampleStatistics, sampler::IncrementalSampler,
+        sampler::StratifiedSampler,
+    };
+    use cgmath::point2;
+
+    /// Return the number of samples the sampler takes in a pixel when each
+    /// sample's radiance is given by `radiance`.
+    fn sample_count(
+        sampler: &mut impl IncrementalSampler,
+        radiance: impl Fn(usize) -> f32,
+    ) -> usize {
+        sampler.start_pixel(point2(3, 4));
+        let mut count = 0;
+        loop {
+            sampler.get_2d();
+            sampler.record_radiance(&RgbaSpectrum::constant(radiance(count)));
+            count += 1;
+            if !sampler.start_next_sample() {
+                return count;
+            }
+        }
+    }
+
+    #[test]
+    fn noisy_pixels_receive_more_samples() {
+        let stratified = StratifiedSampler::new(2, 2, 5, 0, true);
+        let mut sampler = AdaptiveSampler::new(stratified, AdaptiveSampling::new(4, 32, 0.001));
+        assert_eq!(sample_count(&mut sampler, |_| 0.5), 4);
+        assert_eq!(sample_count(&mut sampler, |i| (i % 2) as f32), 32);
+    }
+
+    #[test]
+    fn smooth_pixels_stop_early_and_noisy_pixels_continue() {
+        let adaptive = AdaptiveSampling::new(4, 16, 0.001);
+
+        let mut smooth = SampleStatistics::default();
+        let mut noisy = SampleStatistics::default();
+        for i in 0..4 {
+            smooth.add(&RgbaSpectrum::constant(0.5));
+            noisy.add(&RgbaSpectrum::constant((i % 2) as f32));
+        }
+        assert!(adaptive.is_done(&smooth));
+        assert!(!adaptive.is_done(&noisy));
+
+        for i in 4..16 {
+            noisy.add(&RgbaSpectrum::constant((i % 2) as f32));
+        }
+        assert!(adaptive.is_done(&noisy));
+    }
+}
@@ -0,0 +1,311 @@
+use super::{IncrementalSampler, MAX_SAMPLE};
+use crate::color::RgbaSpectrum;
+use cgmath::Point2;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// The width and height, in pixels, of the blue-noise mask that tiles the
+/// image.
+const MASK_SIZE: usize = 64;
+
+/// The standard deviation, in pixels, of the Gaussian that measures how
+/// clustered the points of a pattern are while the mask is generated.
+const MASK_SIGMA: f32 = 1.5;
+
+/// A sampler that wraps another sampler and shifts each pixel's sample values
+/// by an amount read from a blue-noise mask.
+///
+/// Each dimension of every sample vector in a pixel is shifted by the same
+/// amount, wrapping around at 1, which is known as a Cranley-Patterson
+/// rotation. The shift preserves the distribution of each pixel's samples.
+/// Because the shifts of neighboring pixels are as different as possible, the
+/// error left in each pixel is too, so noise in renders with few samples per
+/// pixel is spread evenly at high frequencies rather than clumped, which looks
+/// more pleasant and is easier to denoise.
+///
+/// Each dimension reads the mask at a different offset, so that the shifts of
+/// different dimensions aren't correlated.
+pub struct BlueNoiseSampler<S> {
+    sampler: S,
+
+    /// The mask's values, in row-major order. Each value in [0, 1) appears
+    /// once, and values that are close to each other are spread far apart.
+    mask: Vec<f32>,
+
+    current_pixel: Point2<i32>,
+    current_dimension: usize,
+}
+
+impl<S: IncrementalSampler> BlueNoiseSampler<S> {
+    /// Create a new blue-noise sampler.
+    ///
+    /// * sampler - Generates the sample vectors that are shifted.
+    /// * seed - Seeds the generation of the blue-noise mask.
+    pub fn new(sampler: S, seed: u64) -> Self {
+        Self {
+            sampler,
+            mask: blue_noise_mask(MASK_SIZE, seed),
+            current_pixel: Point2::new(0, 0),
+            current_dimension: 0,
+        }
+    }
+
+    /// Shift the value of the next dimension of the current sample vector.
+    fn shift(&mut self, value: f32) -> f32 {
+        // Offset the mask for each dimension along the R2 low-discrepancy
+        // sequence, which spreads the offsets evenly over the mask.
+        let d = self.current_dimension as f32 + 1.0;
+        let offset_x = ((d * 0.754_877_7).fract() * MASK_SIZE as f32) as i32;
+        let offset_y = ((d * 0.569_840_3).fract() * MASK_SIZE as f32) as i32;
+        let x = (self.current_pixel.x + offset_x).rem_euclid(MASK_SIZE as i32) as usize;
+        let y = (self.current_pixel.y + offset_y).rem_euclid(MASK_SIZE as i32) as usize;
+        self.current_dimension += 1;
+        (value + self.mask[y * MASK_SIZE + x])
+            .fract()
+            .min(MAX_SAMPLE)
+    }
+}
+
+impl<S: IncrementalSampler> IncrementalSampler for BlueNoiseSampler<S> {
+    fn clone_with_seed(&self, seed: u64) -> Self {
+        // The mask is shared by every clone so that it tiles the whole image.
+        Self {
+            sampler: self.sampler.clone_with_seed(seed),
+            mask: self.mask.clone(),
+            current_pixel: Point2::new(0, 0),
+            current_dimension: 0,
+        }
+    }
+
+    fn samples_per_pixel(&self) -> usize {
+        self.sampler.samples_per_pixel()
+    }
+
+    fn start_pixel(&mut self, pixel: Point2<i32>) {
+        self.current_pixel = pixel;
+        self.current_dimension = 0;
+        self.sampler.start_pixel(pixel);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let value = self.sampler.get_1d();
+        self.shift(value)
+    }
+
+    fn get_2d(&mut self) -> Point2<f32> {
+        let value = self.sampler.get_2d();
+        let x = self.shift(value.x);
+        let y = self.shift(value.y);
+        Point2::new(x, y)
+    }
+
+    fn start_next_sample(&mut self) -> bool {
+        self.current_dimension = 0;
+        self.sampler.start_next_sample()
+    }
+
+    fn record_radiance(&mut self, radiance: &RgbaSpectrum) {
+        self.sampler.record_radiance(radiance);
+    }
+
+    fn prepare_1d_array(&mut self, count: usize) {
+        self.sampler.prepare_1d_array(count);
+    }
+
+    fn prepare_2d_array(&mut self, count: usize) {
+        self.sampler.prepare_2d_array(count);
+    }
+
+    fn round_count(&self, ideal_count: usize) -> usize {
+        self.sampler.round_count(ideal_count)
+    }
+
+    fn get_1d_vec(&mut self) -> Option<Vec<f32>> {
+        self.sampler.get_1d_vec()
+    }
+
+    fn get_2d_vec(&mut self) -> Option<Vec<Point2<f32>>> {
+        self.sampler.get_2d_vec()
+    }
+}
+
+/// Generate a `size`-by-`size` blue-noise mask that tiles seamlessly, with
+/// Ulichney's void-and-cluster method. Return the mask's values in row-major
+/// order.
+///
+/// Pixels are ranked in the order in which they're added to a pattern of
+/// points. Each point is added in the largest void between the points before
+/// it, so pixels with similar ranks are far apart. The mask's value at each
+/// pixel is the pixel's rank scaled to [0, 1).
+fn blue_noise_mask(size: usize, seed: u64) -> Vec<f32> {
+    let pixel_count = size * size;
+    let mut pattern = EnergyPattern::new(size);
+
+    // Start with a random pattern of a tenth of the pixels, and then move the
+    // point in the tightest cluster to the largest void until that no longer
+    // changes the pattern.
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let initial_count = (pixel_count / 10).max(1);
+    while pattern.count < initial_count {
+        let i = rng.gen_range(0..pixel_count);
+        if !pattern.is_set[i] {
+            pattern.toggle(i);
+        }
+    }
+    loop {
+        let cluster = pattern.tightest_cluster();
+        pattern.toggle(cluster);
+        let void = pattern.largest_void();
+        if void == cluster {
+            pattern.toggle(cluster);
+            break;
+        }
+        pattern.toggle(void);
+    }
+
+    let mut ranks = vec![0; pixel_count];
+
+    // Rank the initial points by removing them from the tightest cluster
+    // first.
+    let mut removed = pattern.clone();
+    for rank in (0..initial_count).rev() {
+        let cluster = removed.tightest_cluster();
+        removed.toggle(cluster);
+        ranks[cluster] = rank;
+    }
+
+    // Rank the remaining pixels by filling the largest void first.
+    for rank in initial_count..pixel_count {
+        let void = pattern.largest_void();
+        pattern.toggle(void);
+        ranks[void] = rank;
+    }
+
+    ranks
+        .into_iter()
+        .map(|rank| (rank as f32 + 0.5) / pixel_count as f32)
+        .collect()
+}
+
+/// A binary pattern of points on a toroidal grid, along with the energy at
+/// each pixel, which is the sum of a Gaussian of the pixel's distance to each
+/// point.
+#[derive(Clone)]
+struct EnergyPattern {
+    size: usize,
+
+    /// The value of the Gaussian for each offset between two pixels, in
+    /// row-major order.
+    kernel: Vec<f32>,
+
+    is_set: Vec<bool>,
+    energy: Vec<f32>,
+    count: usize,
+}
+
+impl EnergyPattern {
+    fn new(size: usize) -> Self {
+        let kernel = (0..size * size)
+            .map(|i| {
+                // Distances wrap around the edges of the grid.
+                let (dx, dy) = (i % size, i / size);
+                let dx = dx.min(size - dx) as f32;
+                let dy = dy.min(size - dy) as f32;
+                (-(dx * dx + dy * dy) / (2.0 * MASK_SIGMA * MASK_SIGMA)).exp()
+            })
+            .collect();
+        Self {
+            size,
+            kernel,
+            is_set: vec![false; size * size],
+            energy: vec![0.0; size * size],
+            count: 0,
+        }
+    }
+
+    /// Add a point at the pixel if it's empty, or remove the point if it
+    /// isn't.
+    fn toggle(&mut self, i: usize) {
+        let sign = if self.is_set[i] { -1.0 } else { 1.0 };
+        self.is_set[i] = !self.is_set[i];
+        self.count = if self.is_set[i] {
+            self.count + 1
+        } else {
+            self.count - 1
+        };
+        let (x, y) = (i % self.size, i / self.size);
+        for (j, energy) in self.energy.iter_mut().enumerate() {
+            let dx = (j % self.size + self.size - x) % self.size;
+            let dy = (j / self.size + self.size - y) % self.size;
+            *energy += sign * self.kernel[dy * self.size + dx];
+        }
+    }
+
+    /// Return the point with the most energy.
+    fn tightest_cluster(&self) -> usize {
+        self.extreme(true, |a, b| a > b)
+    }
+
+    /// Return the empty pixel with the least energy.
+    fn largest_void(&self) -> usize {
+        self.extreme(false, |a, b| a < b)
+    }
+
+    fn extreme<F: Fn(f32, f32) -> bool>(&self, is_set: bool, is_better: F) -> usize {
+        let mut best = None;
+        for (i, energy) in self.energy.iter().enumerate() {
+            if self.is_set[i] != is_set {
+                continue;
+            }
+            match best {
+                Some((_, best_energy)) if !is_better(*energy, best_energy) => {}
+                _ => best = Some((i, *energy)),
+            }
+        }
+        best.map_or(0, |(i, _)| i)
+    }
+}
+
+#[cfg(test)]
+mod blue_noise_sampler_tests {
+    use super::{blue_noise_mask, BlueNoiseSampler};
+    use crate::sampler::{ConstantSampler, IncrementalSampler};
+    use cgmath::point2;
+
+    #[test]
+    fn mask_contains_each_rank_once() {
+        let size = 16;
+        let mut mask = blue_noise_mask(size, 0);
+        mask.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        for (rank, value) in mask.iter().enumerate() {
+            assert_eq!(*value, (rank as f32 + 0.5) / (size * size) as f32);
+        }
+    }
+
+    #[test]
+    fn lowest_ranks_are_spread_apart() {
+        // The lowest tenth of the ranks should be roughly evenly spread, so
+        // no two of them are adjacent.
+        let size = 16;
+        let mask = blue_noise_mask(size, 0);
+        let is_low = |x: usize, y: usize| mask[(y % size) * size + x % size] < 0.1;
+        for y in 0..size {
+            for x in 0..size {
+                if is_low(x, y) {
+                    assert!(!is_low(x + 1, y) && !is_low(x, y + 1));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn neighboring_pixels_are_shifted_differently() {
+        let mut sampler = BlueNoiseSampler::new(ConstantSampler {}, 0);
+        sampler.start_pixel(point2(3, 4));
+        let a = sampler.get_1d();
+        sampler.start_pixel(point2(4, 4));
+        let b = sampler.get_1d();
+        assert!((0.0..1.0).contains(&a) && (0.0..1.0).contains(&b));
+        assert_ne!(a, b);
+    }
+}
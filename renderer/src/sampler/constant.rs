@@ -27,20 +27,4 @@ impl IncrementalSampler for ConstantSampler {
         // Only one sample per pixel.
         false
     }
-
-    // fn prepare_1d_array(&mut self, count: usize) {}
-
-    // fn prepare_2d_array(&mut self, count: usize) {}
-
-    // fn get_1d_vec(&mut self) -> Option<Vec<f32>> {
-    //     None
-    // }
-
-    // fn get_2d_vec(&mut self) -> Option<Vec<Point2<f32>>> {
-    //     None
-    // }
-
-    // fn start_nth_sample(&mut self, sample_index: usize) -> bool {
-    //     false
-    // }
 }
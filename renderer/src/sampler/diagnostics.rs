@@ -0,0 +1,155 @@
+use super::IncrementalSampler;
+use cgmath::Point2;
+use image::{ImageBuffer, Rgba};
+use std::io::{self, Write};
+
+/// The most sample vectors that are recorded, in case the sampler never runs
+/// out of samples for the pixel.
+const MAX_RECORDED_VECTORS: usize = 1 << 16;
+
+/// The sample vectors that a sampler generates for a single pixel. This is
+/// useful for checking visually that a sampler's samples are well stratified.
+///
+/// Each vector starts with the five dimensions that the camera uses: the
+/// sample's position in the pixel, the time, and the position on the lens.
+/// These are followed by pairs of dimensions that are requested with `get_2d`,
+/// as an integrator requests them.
+pub struct SampleDump {
+    pub pixel: Point2<i32>,
+    pub vectors: Vec<Vec<f32>>,
+}
+
+impl SampleDump {
+    /// Record every sample vector that the sampler generates for the pixel.
+    ///
+    /// * seed - The seed that the sampler is cloned with. The render loop
+    ///   derives it from the render's seed and the pass over the tile.
+    /// * extra_pairs - The number of 2D samples that are requested after the
+    ///   camera sample in each vector.
+    pub fn record<S: IncrementalSampler>(
+        sampler: &S,
+        pixel: Point2<i32>,
+        seed: u64,
+        extra_pairs: usize,
+    ) -> Self {
+        let mut sampler = sampler.clone_with_seed(seed);
+        sampler.start_pixel(pixel);
+        let mut vectors = vec![];
+        loop {
+            let camera_sample = sampler.get_camera_sample(pixel);
+            let mut vector = vec![
+                camera_sample.film_point.x - pixel.x as f32,
+                camera_sample.film_point.y - pixel.y as f32,
+                camera_sample.time,
+                camera_sample.lens_point.x,
+                camera_sample.lens_point.y,
+            ];
+            for _ in 0..extra_pairs {
+                let sample = sampler.get_2d();
+                vector.push(sample.x);
+                vector.push(sample.y);
+            }
+            vectors.push(vector);
+            if vectors.len() >= MAX_RECORDED_VECTORS || !sampler.start_next_sample() {
+                break;
+            }
+        }
+        Self { pixel, vectors }
+    }
+
+    /// Write the sample vectors as CSV, with a header row and one row per
+    /// vector.
+    pub fn write_csv<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let dimension_count = self.vectors.first().map_or(0, |vector| vector.len());
+        let mut header = vec!["index", "film_x", "film_y", "time", "lens_u", "lens_v"]
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<String>>();
+        header.extend((5..dimension_count).map(|d| format!("dimension_{}", d)));
+        writeln!(writer, "{}", header.join(","))?;
+        for (i, vector) in self.vectors.iter().enumerate() {
+            let values: Vec<String> = vector.iter().map(|v| v.to_string()).collect();
+            writeln!(writer, "{},{}", i, values.join(","))?;
+        }
+        Ok(())
+    }
+
+    /// Draw a scatter plot of each pair of dimensions, side by side in a
+    /// single row. The time dimension is plotted against the sample index.
+    ///
+    /// Each plot is divided by faint grid lines into a square grid with about
+    /// as many cells as there are samples, so that stratification is easy to
+    /// check.
+    ///
+    /// * plot_size - The width and height of each plot, in pixels.
+    pub fn scatter_plot(&self, plot_size: u32) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        let count = self.vectors.len();
+        let dimension_count = self.vectors.first().map_or(5, |vector| vector.len());
+        let index = |i: usize| (i as f32 + 0.5) / count as f32;
+        let mut plots: Vec<Vec<(f32, f32)>> = vec![
+            self.vectors.iter().map(|v| (v[0], v[1])).collect(),
+            self.vectors
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (v[2], index(i)))
+                .collect(),
+            self.vectors.iter().map(|v| (v[3], v[4])).collect(),
+        ];
+        for d in (5..dimension_count - 1).step_by(2) {
+            plots.push(self.vectors.iter().map(|v| (v[d], v[d + 1])).collect());
+        }
+
+        let margin = 4;
+        let width = plots.len() as u32 * (plot_size + margin) + margin;
+        let height = plot_size + 2 * margin;
+        let mut image = ImageBuffer::from_pixel(width, height, Rgba([255, 255, 255, 255]));
+        let strata = ((count as f32).sqrt().round() as u32).max(1);
+        for (p, points) in plots.iter().enumerate() {
+            let left = margin + p as u32 * (plot_size + margin);
+            let top = margin;
+            for offset in 0..plot_size {
+                for s in 0..=strata {
+                    let line = (s * (plot_size - 1)) / strata;
+                    image.put_pixel(left + line, top + offset, Rgba([210, 210, 210, 255]));
+                    image.put_pixel(left + offset, top + line, Rgba([210, 210, 210, 255]));
+                }
+            }
+            for (x, y) in points {
+                let px = (x.clamp(0.0, 1.0) * (plot_size - 1) as f32).round() as u32;
+                let py = (y.clamp(0.0, 1.0) * (plot_size - 1) as f32).round() as u32;
+                for dy in 0..3 {
+                    for dx in 0..3 {
+                        let (x, y) = ((px + dx).saturating_sub(1), (py + dy).saturating_sub(1));
+                        if x < plot_size && y < plot_size {
+                            image.put_pixel(left + x, top + y, Rgba([0, 0, 0, 255]));
+                        }
+                    }
+                }
+            }
+        }
+        image
+    }
+}
+
+#[cfg(test)]
+mod sample_dump_tests {
+    use super::SampleDump;
+    use crate::sampler::StratifiedSampler;
+    use cgmath::point2;
+
+    #[test]
+    fn records_each_sample_vector() {
+        let sampler = StratifiedSampler::new(2, 2, 5, 0, true);
+        let dump = SampleDump::record(&sampler, point2(3, 4), 0, 2);
+        assert_eq!(dump.vectors.len(), 4);
+        assert!(dump.vectors.iter().all(|vector| vector.len() == 9));
+
+        let mut csv = vec![];
+        dump.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with(
+            "index,film_x,film_y,time,lens_u,lens_v,dimension_5,dimension_6,dimension_7,dimension_8\n"
+        ));
+        assert_eq!(csv.lines().count(), 5);
+    }
+}
@@ -0,0 +1,207 @@
+use super::{mix, IncrementalSampler, PixelSeeds, MAX_SAMPLE};
+use cgmath::Point2;
+use rand::{prelude::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// The prime bases of the radical inverses used for each dimension of a sample
+/// vector. Dimensions beyond the last prime are sampled uniformly at random.
+const PRIMES: [u64; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89,
+    97, 101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191,
+    193, 197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293,
+    307, 311,
+];
+
+/// A sampler that generates low-discrepancy sample vectors from the Halton
+/// sequence. The `i`th dimension of each sample vector is the radical inverse
+/// of the sample's index in the `i`th prime base.
+///
+/// Unlike `StratifiedSampler`, this sampler can generate any number of samples
+/// per pixel, and its samples remain well distributed as the sample count
+/// grows and as more dimensions are requested.
+///
+/// The digits of each radical inverse are scrambled with a random permutation
+/// per base, which breaks up the correlations between dimensions with large
+/// bases. Each pixel also starts at a pseudo-random offset into the sequence,
+/// derived from the sampler's seed and the pixel, so neighboring pixels don't
+/// use identical sample vectors.
+pub struct HaltonSampler {
+    samples_per_pixel: usize,
+
+    /// A random permutation of the digits of each prime base, used to scramble
+    /// the radical inverse in that base.
+    digit_permutations: Vec<Vec<u64>>,
+
+    /// The index into the Halton sequence of the current pixel's first sample.
+    pixel_offset: u64,
+
+    /// The seed that each pixel's offset and pseudo-random stream are derived
+    /// from.
+    seed: u64,
+    pixel_seeds: PixelSeeds,
+
+    current_sample_index: usize,
+    current_dimension: usize,
+    rng: ChaCha8Rng,
+}
+
+impl HaltonSampler {
+    /// Create a new Halton sampler.
+    ///
+    /// * samples_per_pixel - The number of sample vectors generated for each
+    ///   pixel.
+    /// * seed - Seeds the digit permutations that scramble the sequence.
+    pub fn new(samples_per_pixel: usize, seed: u64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let digit_permutations = PRIMES
+            .iter()
+            .map(|base| {
+                let mut permutation: Vec<u64> = (0..*base).collect();
+                permutation.shuffle(&mut rng);
+                permutation
+            })
+            .collect();
+        Self {
+            samples_per_pixel,
+            digit_permutations,
+            pixel_offset: 0,
+            seed,
+            pixel_seeds: PixelSeeds::default(),
+            current_sample_index: 0,
+            current_dimension: 0,
+            rng,
+        }
+    }
+
+    /// Return the current sample vector's value for the next dimension.
+    fn next_dimension(&mut self) -> f32 {
+        let dimension = self.current_dimension;
+        self.current_dimension += 1;
+        match self.digit_permutations.get(dimension) {
+            Some(permutation) => {
+                let index = self.pixel_offset + self.current_sample_index as u64;
+                scrambled_radical_inverse(PRIMES[dimension], permutation, index)
+            }
+            None => self.rng.gen::<f32>().min(MAX_SAMPLE),
+        }
+    }
+}
+
+impl IncrementalSampler for HaltonSampler {
+    fn clone_with_seed(&self, seed: u64) -> Self {
+        // The digit permutations are shared by every clone so that all pixels
+        // draw from the same scrambled sequence.
+        Self {
+            samples_per_pixel: self.samples_per_pixel,
+            digit_permutations: self.digit_permutations.clone(),
+            pixel_offset: 0,
+            seed,
+            pixel_seeds: PixelSeeds::default(),
+            current_sample_index: 0,
+            current_dimension: 0,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    fn samples_per_pixel(&self) -> usize {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel(&mut self, pixel: Point2<i32>) {
+        let pixel_seed = self.pixel_seeds.start_pixel(self.seed, pixel);
+        // Keep the offset small enough that the radical inverses of the
+        // indices don't lose precision.
+        self.pixel_offset = mix(pixel_seed) & 0xffff_ffff;
+        self.rng = ChaCha8Rng::seed_from_u64(pixel_seed);
+        self.current_sample_index = 0;
+        self.current_dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        self.next_dimension()
+    }
+
+    fn get_2d(&mut self) -> Point2<f32> {
+        let x = self.next_dimension();
+        let y = self.next_dimension();
+        Point2::new(x, y)
+    }
+
+    fn start_next_sample(&mut self) -> bool {
+        self.current_sample_index += 1;
+        self.current_dimension = 0;
+        self.current_sample_index < self.samples_per_pixel
+    }
+}
+
+/// Return the radical inverse of `index` in the given base, with each digit
+/// replaced by its image under `permutation`.
+///
+/// Digits beyond the most significant digit of `index` are zeros, which the
+/// permutation maps to a nonzero digit. The infinite tail of those permuted
+/// zeros is a geometric series that is added in closed form.
+fn scrambled_radical_inverse(base: u64, permutation: &[u64], mut index: u64) -> f32 {
+    let inv_base = 1.0 / base as f64;
+    let mut reversed_digits = 0u64;
+    let mut inv_base_n = 1.0;
+    while index > 0 {
+        let next = index / base;
+        let digit = index - next * base;
+        reversed_digits = reversed_digits * base + permutation[digit as usize];
+        inv_base_n *= inv_base;
+        index = next;
+    }
+    let tail = inv_base * permutation[0] as f64 / (1.0 - inv_base);
+    ((inv_base_n * (reversed_digits as f64 + tail)) as f32).min(MAX_SAMPLE)
+}
+
+#[cfg(test)]
+mod halton_sampler_tests {
+    use super::{scrambled_radical_inverse, HaltonSampler};
+    use crate::sampler::IncrementalSampler;
+    use cgmath::point2;
+
+    #[test]
+    fn identity_permutation_gives_radical_inverse() {
+        let identity: Vec<u64> = (0..2).collect();
+        assert_eq!(scrambled_radical_inverse(2, &identity, 1), 0.5);
+        assert_eq!(scrambled_radical_inverse(2, &identity, 2), 0.25);
+        assert_eq!(scrambled_radical_inverse(2, &identity, 3), 0.75);
+        let identity: Vec<u64> = (0..3).collect();
+        assert!((scrambled_radical_inverse(3, &identity, 5) - 7.0 / 9.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn first_dimension_is_stratified() {
+        let samples_per_pixel = 16;
+        let mut sampler = HaltonSampler::new(samples_per_pixel, 0);
+        sampler.start_pixel(point2(3, 4));
+
+        // The first dimension of any 16 consecutive sample vectors is the base
+        // 2 radical inverse of 16 consecutive indices, so exactly one sample
+        // falls in each sixteenth of [0, 1).
+        let mut strata = vec![0; samples_per_pixel];
+        loop {
+            let sample = sampler.get_1d();
+            assert!((0.0..1.0).contains(&sample));
+            strata[(sample * samples_per_pixel as f32) as usize] += 1;
+            if !sampler.start_next_sample() {
+                break;
+            }
+        }
+        assert!(strata.iter().all(|count| *count == 1));
+    }
+
+    #[test]
+    fn restarted_pixel_gets_new_samples() {
+        let mut sampler = HaltonSampler::new(4, 0).clone_with_seed(7);
+        sampler.start_pixel(point2(3, 4));
+        let first = sampler.get_2d();
+        sampler.start_pixel(point2(3, 4));
+        assert_ne!(sampler.get_2d(), first);
+
+        let mut other = HaltonSampler::new(4, 0).clone_with_seed(8);
+        other.start_pixel(point2(3, 4));
+        assert_ne!(other.get_2d(), first);
+    }
+}
@@ -1,16 +1,72 @@
+mod adaptive;
+mod blue_noise;
 mod constant;
+mod diagnostics;
+mod halton;
 mod pixel;
+mod pmj02;
 mod stratified;
 
+pub use adaptive::{AdaptiveSampler, AdaptiveSampling};
+pub use blue_noise::BlueNoiseSampler;
 pub use constant::ConstantSampler;
+pub use diagnostics::SampleDump;
+pub use halton::HaltonSampler;
+pub use pmj02::Pmj02Sampler;
 pub use stratified::StratifiedSampler;
 
-use crate::camera::CameraSample;
+use crate::{camera::CameraSample, color::RgbaSpectrum};
 use cgmath::Point2;
 use core::f32;
 
 /// The maximum value that any sample can have, one minus the machine epsilon.
-const MAX_SAMPLE: f32 = 1.0 - f32::EPSILON;
+pub(crate) const MAX_SAMPLE: f32 = 1.0 - f32::EPSILON;
+
+/// Mix the bits of the value with the finalizer from SplitMix64. This turns
+/// structured values, such as pixel coordinates, into well-scrambled hashes.
+pub(crate) fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Return a seed for the pseudo-random streams of a render pass. Samplers are
+/// cloned with this seed, and they mix each pixel into it, so the streams of
+/// different passes and frames never collide.
+///
+/// * seed - A seed for the whole render, such as the frame number.
+/// * pass - The index of the pass over the tile.
+pub(crate) fn pass_seed(seed: u64, pass: u64) -> u64 {
+    mix(mix(seed) ^ pass)
+}
+
+/// Tracks the pixel that a sampler is generating sample vectors for, so that
+/// each pixel, and each round of samples in a pixel, gets its own seed. A
+/// round starts whenever sampling of the same pixel is restarted, such as by
+/// adaptive sampling, so the new round doesn't repeat the old one's samples.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct PixelSeeds {
+    pixel: Option<Point2<i32>>,
+    round: u64,
+}
+
+impl PixelSeeds {
+    /// Start generating sample vectors for the pixel and return the seed for
+    /// them.
+    ///
+    /// * seed - The seed that the sampler was cloned with.
+    /// * pixel - The pixel whose sample vectors are generated.
+    pub fn start_pixel(&mut self, seed: u64, pixel: Point2<i32>) -> u64 {
+        if self.pixel == Some(pixel) {
+            self.round += 1;
+        } else {
+            self.pixel = Some(pixel);
+            self.round = 0;
+        }
+        let pixel = ((pixel.x as u32 as u64) << 32) | pixel.y as u32 as u64;
+        mix(mix(seed ^ mix(pixel)) ^ self.round)
+    }
+}
 
 /// A sampler is responsible for generating sequences of n-dimensional sample
 /// vectors, where each element in a sample vector is in the range [0, 1).
@@ -31,8 +87,9 @@ pub trait IncrementalSampler {
     /// the given seed to initialize its pseudo-random number generator if it
     /// has one.
     ///
-    /// * seed - Samplers that use a pseudo-random number generator will use
-    ///   this seed to initialize the generator. Other samplers will ignore it.
+    /// * seed - Samplers that use pseudo-random numbers or scrambling mix this
+    ///   seed with each pixel, so that every pixel has its own stream. The
+    ///   render loop derives it from the render's seed and the pass.
     fn clone_with_seed(&self, seed: u64) -> Self;
 
     /// Return the number of n-dimensional sample vectors that will be generated
@@ -95,6 +152,11 @@ pub trait IncrementalSampler {
     /// generated. It returns `false` otherwise.
     fn start_next_sample(&mut self) -> bool;
 
+    /// Tell the sampler the radiance that was computed for the current sample
+    /// vector. Adaptive samplers use this to decide how many samples each
+    /// pixel needs. Other samplers ignore it.
+    fn record_radiance(&mut self, _radiance: &RgbaSpectrum) {}
+
     // /// Tell the sampler to start working on the sample with the given index for
     // /// the current pixel. This method mutates the sampler by updating the
     // /// current sample index and by reseting the current dimension to the first
@@ -105,53 +167,56 @@ pub trait IncrementalSampler {
     // /// It returns `false` otherwise.
     // fn start_nth_sample(&mut self, sample_index: usize) -> bool;
 
-    // /// Inform the sampler that we will want to request a vector of 1D values of
-    // /// length `count` for the current pixel. Each element in the vector is a 1D
-    // /// value from a separate sample.
-    // ///
-    // /// This tells the sampler to do the preprocessing necessary for it to
-    // /// return the vector of values in an efficient manner. This method must be
-    // /// called before rendering begins for the current pixel and before the
-    // /// samples are actually requested using `get_1d_array`.
-    // fn prepare_1d_array(&mut self, count: usize);
-
-    // /// Inform the sampler that we will want to request a vector of 2D values of
-    // /// length `count` for the current pixel. Each element in the vector is a 2D
-    // /// value from a separate sample.
-    // ///
-    // /// This tells the sampler to do the preprocessing necessary for it to
-    // /// return the vector of values in an efficient manner. This method must be
-    // /// called before rendering begins for the current pixel and before the
-    // /// samples are actually requested using `get_2d_array`.
-    // fn prepare_2d_array(&mut self, count: usize);
-
-    // /// When generating a vector of values for a sample vector, a sampler might
-    // /// be more efficient at generating value vectors of certain lengths. This
-    // /// method takes an ideal value count and returns a value count that is
-    // /// close and that the sampler can generate efficiently.
-    // fn round_count(&self, ideal_count: usize) -> usize {
-    //     ideal_count
-    // }
-
-    // /// Get a vector of 1D values for the next dimension. Each element in the
-    // /// vector is a 1D value from a separate sample.
-    // ///
-    // /// This method mutates the sampler by incrementing the current sample
-    // /// dimension by one.
-    // ///
-    // /// This must be called after `prepare_1d_array`. The returned vector will
-    // /// contain the number of values that is specified in the call to
-    // /// `prepare_1d_array`.
-    // fn get_1d_vec(&mut self) -> Option<Vec<f32>>;
+    /// Inform the sampler that we will want to request a vector of 1D values of
+    /// length `count` for each sample vector. This is useful when an
+    /// integrator takes several samples at a single intersection, such as when
+    /// it traces multiple shadow rays toward a light, and wants those samples
+    /// to be well distributed with respect to each other.
+    ///
+    /// This tells the sampler to do the preprocessing necessary for it to
+    /// return the vector of values in an efficient manner. This method must be
+    /// called before rendering begins, and the vectors are requested with
+    /// `get_1d_vec` in the same order that they were prepared.
+    ///
+    /// Samplers that don't support sample vectors ignore this call.
+    fn prepare_1d_array(&mut self, _count: usize) {}
 
-    // /// Get a vector of 2D values for the next two dimensions. Each element in
-    // /// the vector is a 2D value from a separate sample.
-    // ///
-    // /// This method mutates the sampler by incrementing the current sample
-    // /// dimension by two.
-    // ///
-    // /// This must be called after `prepare_2d_array`. The returned vector will
-    // /// contain the number of values that is specified in the call to
-    // /// `prepare_2d_array`.
-    // fn get_2d_vec(&mut self) -> Option<Vec<Point2<f32>>>;
+    /// Inform the sampler that we will want to request a vector of 2D values of
+    /// length `count` for each sample vector.
+    ///
+    /// This must be called before rendering begins, and the vectors are
+    /// requested with `get_2d_vec` in the same order that they were prepared.
+    ///
+    /// Samplers that don't support sample vectors ignore this call.
+    fn prepare_2d_array(&mut self, _count: usize) {}
+
+    /// When generating a vector of values for a sample vector, a sampler might
+    /// be more efficient at generating value vectors of certain lengths. This
+    /// method takes an ideal value count and returns a value count that is
+    /// close and that the sampler can generate efficiently.
+    fn round_count(&self, ideal_count: usize) -> usize {
+        ideal_count
+    }
+
+    /// Get the next prepared vector of 1D values for the current sample vector.
+    /// The values are well distributed with respect to each other.
+    ///
+    /// This returns `None` if all the vectors prepared with `prepare_1d_array`
+    /// have already been requested for the current sample vector, or if the
+    /// sampler doesn't support sample vectors. Callers should then fall back
+    /// to calling `get_1d` once for each value.
+    fn get_1d_vec(&mut self) -> Option<Vec<f32>> {
+        None
+    }
+
+    /// Get the next prepared vector of 2D values for the current sample vector.
+    /// The values are well distributed with respect to each other.
+    ///
+    /// This returns `None` if all the vectors prepared with `prepare_2d_array`
+    /// have already been requested for the current sample vector, or if the
+    /// sampler doesn't support sample vectors. Callers should then fall back
+    /// to calling `get_2d` once for each value.
+    fn get_2d_vec(&mut self) -> Option<Vec<Point2<f32>>> {
+        None
+    }
 }
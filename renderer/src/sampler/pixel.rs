@@ -27,9 +27,29 @@ pub struct PixelSamplerState {
     /// the `i`th 2D request for the pixel's `j`th sample.
     precomputed_2d: Vec<Vec<Point2<f32>>>,
 
+    /// The lengths of the vectors of 1D values that will be requested for each
+    /// sample vector, in the order they will be requested.
+    array_1d_sizes: Vec<usize>,
+
+    /// The lengths of the vectors of 2D values that will be requested for each
+    /// sample vector, in the order they will be requested.
+    array_2d_sizes: Vec<usize>,
+
+    /// Pre-computed vectors of 1D values for all sample vectors for the current
+    /// pixel. `arrays_1d[i][j]` is the `i`th requested vector for the pixel's
+    /// `j`th sample.
+    arrays_1d: Vec<Vec<Vec<f32>>>,
+
+    /// Pre-computed vectors of 2D values for all sample vectors for the current
+    /// pixel. `arrays_2d[i][j]` is the `i`th requested vector for the pixel's
+    /// `j`th sample.
+    arrays_2d: Vec<Vec<Vec<Point2<f32>>>>,
+
     current_sample_index: usize,
     current_1d_index: usize,
     current_2d_index: usize,
+    current_1d_array_index: usize,
+    current_2d_array_index: usize,
 }
 
 impl PixelSamplerState {
@@ -41,12 +61,49 @@ impl PixelSamplerState {
             samples_per_pixel,
             precomputed_1d,
             precomputed_2d,
+            array_1d_sizes: vec![],
+            array_2d_sizes: vec![],
+            arrays_1d: vec![],
+            arrays_2d: vec![],
             current_sample_index: 0,
             current_1d_index: 0,
             current_2d_index: 0,
+            current_1d_array_index: 0,
+            current_2d_array_index: 0,
         }
     }
 
+    pub fn samples_per_pixel(&self) -> usize {
+        self.samples_per_pixel
+    }
+
+    pub fn array_1d_sizes(&self) -> &[usize] {
+        &self.array_1d_sizes
+    }
+
+    pub fn array_2d_sizes(&self) -> &[usize] {
+        &self.array_2d_sizes
+    }
+
+    pub fn prepare_1d_array(&mut self, count: usize) {
+        self.array_1d_sizes.push(count);
+    }
+
+    pub fn prepare_2d_array(&mut self, count: usize) {
+        self.array_2d_sizes.push(count);
+    }
+
+    /// Set the pre-computed vectors of values for the current pixel. This
+    /// should be called after `start_pixel`.
+    pub fn set_arrays(
+        &mut self,
+        arrays_1d: Vec<Vec<Vec<f32>>>,
+        arrays_2d: Vec<Vec<Vec<Point2<f32>>>>,
+    ) {
+        self.arrays_1d = arrays_1d;
+        self.arrays_2d = arrays_2d;
+    }
+
     pub fn start_pixel(
         &mut self,
         precomputed_1d: Vec<Vec<f32>>,
@@ -55,6 +112,8 @@ impl PixelSamplerState {
         self.current_sample_index = 0;
         self.current_1d_index = 0;
         self.current_2d_index = 0;
+        self.current_1d_array_index = 0;
+        self.current_2d_array_index = 0;
         self.precomputed_1d = precomputed_1d;
         self.precomputed_2d = precomputed_2d;
     }
@@ -77,14 +136,36 @@ impl PixelSamplerState {
             .and_then(|vals_for_dim| vals_for_dim.get(self.current_sample_index))
             .copied()
             .unwrap_or_else(|| point2(0.5, 0.5)); // TODO: Use a random number instead of 0.5.
-        self.current_1d_index += 1;
+        self.current_2d_index += 1;
         val
     }
 
+    pub fn get_1d_vec(&mut self) -> Option<Vec<f32>> {
+        let vals = self
+            .arrays_1d
+            .get(self.current_1d_array_index)?
+            .get(self.current_sample_index)?
+            .clone();
+        self.current_1d_array_index += 1;
+        Some(vals)
+    }
+
+    pub fn get_2d_vec(&mut self) -> Option<Vec<Point2<f32>>> {
+        let vals = self
+            .arrays_2d
+            .get(self.current_2d_array_index)?
+            .get(self.current_sample_index)?
+            .clone();
+        self.current_2d_array_index += 1;
+        Some(vals)
+    }
+
     pub fn start_next_sample(&mut self) -> bool {
         self.current_sample_index += 1;
         self.current_1d_index = 0;
         self.current_2d_index = 0;
+        self.current_1d_array_index = 0;
+        self.current_2d_array_index = 0;
         self.current_sample_index < self.samples_per_pixel
     }
 }
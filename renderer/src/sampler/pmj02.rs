@@ -0,0 +1,172 @@
+use super::{mix, IncrementalSampler, PixelSeeds, MAX_SAMPLE};
+use cgmath::Point2;
+
+/// A sampler that generates progressive multi-jittered (0,2) sample vectors.
+///
+/// Each pair of dimensions is drawn from a (0,2)-sequence in base 2, so the
+/// first `2^k` samples in a pixel are stratified in every elementary interval
+/// of area `1 / 2^k`. The samples are jittered within the strata and, like
+/// Christensen et al.'s PMJ02 sequences, they are well distributed at every
+/// power of two samples, not only once the pixel is finished. This makes the
+/// sampler suited to progressive previews, which may stop at any sample count.
+///
+/// Every pair of dimensions, and every pixel, uses an independently scrambled
+/// copy of the sequence. The digits of each coordinate are Owen scrambled, and
+/// the sample indices are permuted by flipping bits, which maps every aligned
+/// block of `2^k` indices to another aligned block that is also stratified.
+pub struct Pmj02Sampler {
+    samples_per_pixel: usize,
+    seed: u64,
+
+    /// The seed that the sampler was cloned with, which is mixed with the
+    /// scrambling seed so that every pass gets its own scrambling.
+    stream_seed: u64,
+    pixel_seeds: PixelSeeds,
+
+    /// A hash of the current pixel and the seeds.
+    pixel_hash: u64,
+
+    current_sample_index: usize,
+    current_dimension: usize,
+}
+
+impl Pmj02Sampler {
+    /// Create a new PMJ02 sampler.
+    ///
+    /// * samples_per_pixel - The number of sample vectors generated for each
+    ///   pixel. Any count is allowed, but the samples are best distributed
+    ///   when it's a power of two.
+    /// * seed - Seeds the scrambling of the sequence.
+    pub fn new(samples_per_pixel: usize, seed: u64) -> Self {
+        Self {
+            samples_per_pixel,
+            seed,
+            stream_seed: 0,
+            pixel_seeds: PixelSeeds::default(),
+            pixel_hash: 0,
+            current_sample_index: 0,
+            current_dimension: 0,
+        }
+    }
+
+    /// Return the current sample vector's values for the next two dimensions.
+    fn next_pair(&mut self) -> (f32, f32) {
+        let hash = mix(self.pixel_hash ^ mix(self.current_dimension as u64));
+        self.current_dimension += 2;
+        let index = self.current_sample_index as u32 ^ hash as u32;
+        let (x, y) = sobol_02(index);
+        let x = owen_scramble(x, (hash >> 32) as u32);
+        let y = owen_scramble(y, mix(hash) as u32);
+        (to_sample(x), to_sample(y))
+    }
+}
+
+impl IncrementalSampler for Pmj02Sampler {
+    fn clone_with_seed(&self, seed: u64) -> Self {
+        Self {
+            stream_seed: seed,
+            ..Self::new(self.samples_per_pixel, self.seed)
+        }
+    }
+
+    fn samples_per_pixel(&self) -> usize {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel(&mut self, pixel: Point2<i32>) {
+        let seed = mix(self.seed) ^ self.stream_seed;
+        self.pixel_hash = self.pixel_seeds.start_pixel(seed, pixel);
+        self.current_sample_index = 0;
+        self.current_dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        self.next_pair().0
+    }
+
+    fn get_2d(&mut self) -> Point2<f32> {
+        let (x, y) = self.next_pair();
+        Point2::new(x, y)
+    }
+
+    fn start_next_sample(&mut self) -> bool {
+        self.current_sample_index += 1;
+        self.current_dimension = 0;
+        self.current_sample_index < self.samples_per_pixel
+    }
+}
+
+/// Return the first two dimensions of the Sobol sequence at the index, as
+/// fixed-point fractions with 32 bits. Together they form a (0,2)-sequence in
+/// base 2.
+fn sobol_02(index: u32) -> (u32, u32) {
+    let x = index.reverse_bits();
+    let mut y = 0;
+    let mut v = 1 << 31;
+    let mut i = index;
+    while i != 0 {
+        if i & 1 != 0 {
+            y ^= v;
+        }
+        i >>= 1;
+        v ^= v >> 1;
+    }
+    (x, y)
+}
+
+/// Scramble the digits of a fixed-point fraction so that each digit is flipped
+/// depending on the digits before it, which preserves the fraction's
+/// stratification. This is Laine and Karras's hash-based approximation of
+/// Owen scrambling.
+fn owen_scramble(value: u32, seed: u32) -> u32 {
+    let mut v = value.reverse_bits();
+    v ^= v.wrapping_mul(0x3d20_adea);
+    v = v.wrapping_add(seed);
+    v = v.wrapping_mul((seed >> 16) | 1);
+    v ^= v.wrapping_mul(0x0552_6c56);
+    v ^= v.wrapping_mul(0x53a2_2864);
+    v.reverse_bits()
+}
+
+fn to_sample(value: u32) -> f32 {
+    (value as f32 / 2.0f32.powi(32)).min(MAX_SAMPLE)
+}
+
+#[cfg(test)]
+mod pmj02_sampler_tests {
+    use super::Pmj02Sampler;
+    use crate::sampler::IncrementalSampler;
+    use cgmath::point2;
+
+    #[test]
+    fn every_power_of_two_prefix_is_stratified() {
+        let mut sampler = Pmj02Sampler::new(64, 7);
+        sampler.start_pixel(point2(3, 4));
+        let mut samples = vec![];
+        loop {
+            // Skip the first pair of dimensions.
+            sampler.get_2d();
+            samples.push(sampler.get_2d());
+            if !sampler.start_next_sample() {
+                break;
+            }
+        }
+
+        // For each prefix of 2^k samples, and for each way of dividing the
+        // unit square into 2^k congruent strata of size 2^-i by 2^-(k - i),
+        // every stratum holds exactly one sample.
+        for k in 0..=6 {
+            let n = 1 << k;
+            for i in 0..=k {
+                let (columns, rows) = (1 << i, 1 << (k - i));
+                let mut counts = vec![0; n];
+                for sample in &samples[..n] {
+                    let column = (sample.x * columns as f32) as usize;
+                    let row = (sample.y * rows as f32) as usize;
+                    counts[row * columns + column] += 1;
+                }
+                assert!(counts.iter().all(|count| *count == 1));
+            }
+        }
+    }
+}
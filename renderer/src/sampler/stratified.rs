@@ -1,4 +1,4 @@
-use super::{pixel::PixelSamplerState, IncrementalSampler, MAX_SAMPLE};
+use super::{pixel::PixelSamplerState, IncrementalSampler, PixelSeeds, MAX_SAMPLE};
 use cgmath::{point2, Point2};
 use rand::{prelude::SliceRandom, Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
@@ -6,8 +6,18 @@ use rand_chacha::ChaCha8Rng;
 pub struct StratifiedSampler {
     x_strata_count: usize,
     y_strata_count: usize,
+
+    /// The number of sample vectors generated for each pixel. This is at
+    /// least the number of 2D strata. Any extra 2D samples are Latin
+    /// hypercube samples.
+    samples_per_pixel: usize,
+
     max_dimension_requests: usize,
     pixel_sampler_state: PixelSamplerState,
+
+    /// The seed that each pixel's pseudo-random stream is derived from.
+    seed: u64,
+    pixel_seeds: PixelSeeds,
     rng: ChaCha8Rng,
     jitter: bool,
 }
@@ -43,13 +53,53 @@ impl StratifiedSampler {
         Self {
             x_strata_count,
             y_strata_count,
+            samples_per_pixel,
             max_dimension_requests,
             pixel_sampler_state: PixelSamplerState::new(samples_per_pixel, max_dimension_requests),
+            seed,
+            pixel_seeds: PixelSeeds::default(),
             rng: ChaCha8Rng::seed_from_u64(seed),
             jitter,
         }
     }
 
+    /// Create a new stratified sampler that generates the given number of
+    /// sample vectors for each pixel.
+    ///
+    /// The 2D sample space is divided into the largest grid of strata that
+    /// is close to square and has no more strata than `samples_per_pixel`.
+    /// The remaining 2D samples are Latin hypercube samples. 1D sample spaces
+    /// are divided into `samples_per_pixel` strata, as in `new`.
+    ///
+    /// * samples_per_pixel - The number of sample vectors generated for each
+    ///   pixel.
+    /// * max_dimension_requests - The number of 1D and the number of 2D
+    ///   samples that are stratified in each sample vector.
+    /// * seed - Seeds the pseudo-random number generator. Each pixel's
+    ///   generator is seeded with a mix of this seed and the pixel.
+    /// * jitter - Set to `true` to randomly place each sample in its strata.
+    pub fn from_samples_per_pixel(
+        samples_per_pixel: usize,
+        max_dimension_requests: usize,
+        seed: u64,
+        jitter: bool,
+    ) -> Self {
+        let samples_per_pixel = samples_per_pixel.max(1);
+        let x_strata_count = (samples_per_pixel as f32).sqrt().floor() as usize;
+        let y_strata_count = samples_per_pixel / x_strata_count;
+        Self {
+            samples_per_pixel,
+            pixel_sampler_state: PixelSamplerState::new(samples_per_pixel, max_dimension_requests),
+            ..Self::new(
+                x_strata_count,
+                y_strata_count,
+                max_dimension_requests,
+                seed,
+                jitter,
+            )
+        }
+    }
+
     /// Generate a 1D sample for each strata of the dimension being sampled.
     ///
     /// * strata_count - The number of strata that divide dimension being
@@ -106,38 +156,67 @@ impl StratifiedSampler {
             })
             .collect()
     }
-}
 
+    /// Generate `count` 2D samples with Latin hypercube sampling. Each of the
+    /// two dimensions is divided into `count` strata, and each strata of each
+    /// dimension contains exactly one sample.
+    ///
+    /// Unlike `stratified_samples_2d`, this works for any number of samples.
+    fn latin_hypercube_samples_2d(&mut self, count: usize, jitter: bool) -> Vec<Point2<f32>> {
+        let xs = self.stratified_samples_1d(count, jitter);
+        let mut ys = self.stratified_samples_1d(count, jitter);
+        ys.shuffle(&mut self.rng);
+        xs.into_iter()
+            .zip(ys)
+            .map(|(x, y)| point2(x, y))
+            .collect()
+    }
+}
 
 impl IncrementalSampler for StratifiedSampler {
     fn clone_with_seed(&self, seed: u64) -> Self {
-        let samples_per_pixel = self.x_strata_count * self.y_strata_count;
+        let mut pixel_sampler_state =
+            PixelSamplerState::new(self.samples_per_pixel, self.max_dimension_requests);
+        for count in self.pixel_sampler_state.array_1d_sizes() {
+            pixel_sampler_state.prepare_1d_array(*count);
+        }
+        for count in self.pixel_sampler_state.array_2d_sizes() {
+            pixel_sampler_state.prepare_2d_array(*count);
+        }
         Self {
             x_strata_count: self.x_strata_count,
             y_strata_count: self.y_strata_count,
+            samples_per_pixel: self.samples_per_pixel,
             max_dimension_requests: self.max_dimension_requests,
-            pixel_sampler_state: PixelSamplerState::new(
-                samples_per_pixel,
-                self.max_dimension_requests,
-            ),
+            pixel_sampler_state,
+            seed,
+            pixel_seeds: PixelSeeds::default(),
             rng: ChaCha8Rng::seed_from_u64(seed),
             jitter: self.jitter,
         }
     }
 
     fn samples_per_pixel(&self) -> usize {
-        self.x_strata_count * self.y_strata_count
+        self.samples_per_pixel
     }
 
-    fn start_pixel(&mut self, _pixel: Point2<i32>) {
+    fn start_pixel(&mut self, pixel: Point2<i32>) {
+        // Each pixel has its own stream, so its samples don't depend on the
+        // order in which pixels are visited.
+        self.rng = ChaCha8Rng::seed_from_u64(self.pixel_seeds.start_pixel(self.seed, pixel));
         let mut precomputed_1d: Vec<Vec<f32>> = (0..self.max_dimension_requests)
-            .map(|_| {
-                self.stratified_samples_1d(self.x_strata_count * self.y_strata_count, self.jitter)
-            })
+            .map(|_| self.stratified_samples_1d(self.samples_per_pixel, self.jitter))
             .collect();
+        let padding_count = self.samples_per_pixel - self.x_strata_count * self.y_strata_count;
         let mut precomputed_2d: Vec<Vec<Point2<f32>>> = (0..self.max_dimension_requests)
             .map(|_| {
-                self.stratified_samples_2d(self.x_strata_count, self.y_strata_count, self.jitter)
+                let mut samples = self.stratified_samples_2d(
+                    self.x_strata_count,
+                    self.y_strata_count,
+                    self.jitter,
+                );
+                samples.extend(self.latin_hypercube_samples_2d(padding_count, self.jitter));
+                samples
             })
             .collect();
 
@@ -154,6 +233,33 @@ impl IncrementalSampler for StratifiedSampler {
 
         self.pixel_sampler_state
             .start_pixel(precomputed_1d, precomputed_2d);
+
+        // Each sample vector gets its own vector of values for each prepared
+        // array.
+        let samples_per_pixel = self.pixel_sampler_state.samples_per_pixel();
+        let array_1d_sizes = self.pixel_sampler_state.array_1d_sizes().to_vec();
+        let array_2d_sizes = self.pixel_sampler_state.array_2d_sizes().to_vec();
+        let arrays_1d = array_1d_sizes
+            .iter()
+            .map(|count| {
+                (0..samples_per_pixel)
+                    .map(|_| {
+                        let mut vals = self.stratified_samples_1d(*count, self.jitter);
+                        vals.shuffle(&mut self.rng);
+                        vals
+                    })
+                    .collect()
+            })
+            .collect();
+        let arrays_2d = array_2d_sizes
+            .iter()
+            .map(|count| {
+                (0..samples_per_pixel)
+                    .map(|_| self.latin_hypercube_samples_2d(*count, self.jitter))
+                    .collect()
+            })
+            .collect();
+        self.pixel_sampler_state.set_arrays(arrays_1d, arrays_2d);
     }
 
     fn get_1d(&mut self) -> f32 {
@@ -167,6 +273,22 @@ impl IncrementalSampler for StratifiedSampler {
     fn start_next_sample(&mut self) -> bool {
         self.pixel_sampler_state.start_next_sample()
     }
+
+    fn prepare_1d_array(&mut self, count: usize) {
+        self.pixel_sampler_state.prepare_1d_array(count);
+    }
+
+    fn prepare_2d_array(&mut self, count: usize) {
+        self.pixel_sampler_state.prepare_2d_array(count);
+    }
+
+    fn get_1d_vec(&mut self) -> Option<Vec<f32>> {
+        self.pixel_sampler_state.get_1d_vec()
+    }
+
+    fn get_2d_vec(&mut self) -> Option<Vec<Point2<f32>>> {
+        self.pixel_sampler_state.get_2d_vec()
+    }
 }
 
 #[cfg(test)]
@@ -296,6 +418,76 @@ mod stratified_sampler_tests {
         }
     }
 
+    #[test]
+    fn generates_stratified_sample_arrays() {
+        let mut sampler = StratifiedSampler::new(2, 2, 5, 0, true);
+        sampler.prepare_1d_array(4);
+        sampler.prepare_2d_array(3);
+        let mut sampler = sampler.clone_with_seed(1);
+
+        sampler.start_pixel(point2(3, 4)); // The specific pixel doesn't matter.
+        for _ in 0..sampler.samples_per_pixel() {
+            let vals_1d = sampler.get_1d_vec().unwrap();
+            let vals_2d = sampler.get_2d_vec().unwrap();
+            assert!(sampler.get_1d_vec().is_none());
+            assert!(sampler.get_2d_vec().is_none());
+
+            // Each quarter of [0, 1) contains one of the 1D values.
+            assert_eq!(vals_1d.len(), 4);
+            for i in 0..4 {
+                let strata = (i as f32 / 4.0, (i + 1) as f32 / 4.0);
+                assert!(vals_1d.iter().any(|val| in_strata_1d(val, &strata)));
+            }
+
+            // Each third of [0, 1) contains one of the 2D values along each
+            // dimension.
+            assert_eq!(vals_2d.len(), 3);
+            for i in 0..3 {
+                let strata = (i as f32 / 3.0, (i + 1) as f32 / 3.0);
+                assert!(vals_2d.iter().any(|val| in_strata_1d(&val.x, &strata)));
+                assert!(vals_2d.iter().any(|val| in_strata_1d(&val.y, &strata)));
+            }
+
+            sampler.start_next_sample();
+        }
+    }
+
+    #[test]
+    fn generates_any_number_of_samples() {
+        let mut sampler = StratifiedSampler::from_samples_per_pixel(10, 5, 0, true);
+        assert_eq!(sampler.samples_per_pixel(), 10);
+        sampler.start_pixel(point2(3, 4));
+
+        let mut vals_1d = vec![];
+        let mut vals_2d = vec![];
+        loop {
+            vals_2d.push(sampler.get_2d());
+            vals_1d.push(sampler.get_1d());
+            if !sampler.start_next_sample() {
+                break;
+            }
+        }
+
+        // Each tenth of [0, 1) contains one of the 1D values.
+        assert_eq!(vals_1d.len(), 10);
+        for i in 0..10 {
+            let strata = (i as f32 / 10.0, (i + 1) as f32 / 10.0);
+            assert!(vals_1d.iter().any(|val| in_strata_1d(val, &strata)));
+        }
+
+        // Each of the 3-by-3 2D strata contains at least one of the 2D
+        // values.
+        for x in 0..3 {
+            for y in 0..3 {
+                let strata = (
+                    point2(x as f32 / 3.0, y as f32 / 3.0),
+                    point2((x + 1) as f32 / 3.0, (y + 1) as f32 / 3.0),
+                );
+                assert!(vals_2d.iter().any(|val| in_strata_2d(val, &strata)));
+            }
+        }
+    }
+
     /// Check if the sample is in the strata defined by the given min and max
     /// bounds. Bounds are inclusive.
     fn in_strata_1d(sample: &f32, min_max: &(f32, f32)) -> bool {
@@ -0,0 +1,274 @@
+//! Provides functions that transform uniformly distributed 2D samples in [0, 1)
+//! into samples from other distributions, such as directions on the unit
+//! sphere.
+
+use cgmath::{Point2, Vector3};
+use std::f32::consts::{FRAC_1_PI, FRAC_PI_2, FRAC_PI_4, PI};
+
+/// Map a uniformly distributed 2D sample to a direction that is uniformly
+/// distributed over the unit sphere.
+#[cfg(test)]
+pub fn uniform_sample_sphere(u: &Point2<f32>) -> Vector3<f32> {
+    let z = 1.0 - 2.0 * u.x;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.y;
+    Vector3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Return the probability density, with respect to solid angle, of any
+/// direction sampled by `uniform_sample_sphere`.
+#[cfg(test)]
+pub fn uniform_sphere_pdf() -> f32 {
+    1.0 / (4.0 * PI)
+}
+
+/// Map a uniformly distributed 2D sample to a direction that is uniformly
+/// distributed over the hemisphere around the positive z axis.
+pub fn uniform_sample_hemisphere(u: &Point2<f32>) -> Vector3<f32> {
+    let z = u.x;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.y;
+    Vector3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Return the probability density, with respect to solid angle, of any
+/// direction sampled by `uniform_sample_hemisphere`.
+pub fn uniform_hemisphere_pdf() -> f32 {
+    1.0 / (2.0 * PI)
+}
+
+/// Map a uniformly distributed 2D sample to a point that is uniformly
+/// distributed over the unit disk.
+///
+/// This uses Shirley's concentric mapping, which maps concentric squares to
+/// concentric circles so that stratified samples remain well distributed.
+pub fn concentric_sample_disk(u: &Point2<f32>) -> Point2<f32> {
+    // Map the sample to [-1, 1] x [-1, 1].
+    let offset = Point2::new(2.0 * u.x - 1.0, 2.0 * u.y - 1.0);
+    if offset.x == 0.0 && offset.y == 0.0 {
+        return Point2::new(0.0, 0.0);
+    }
+
+    let (r, theta) = if offset.x.abs() > offset.y.abs() {
+        (offset.x, FRAC_PI_4 * (offset.y / offset.x))
+    } else {
+        (offset.y, FRAC_PI_2 - FRAC_PI_4 * (offset.x / offset.y))
+    };
+    Point2::new(r * theta.cos(), r * theta.sin())
+}
+
+/// Map a uniformly distributed 2D sample to the barycentric coordinates of a
+/// point that is uniformly distributed over a triangle. The third coordinate
+/// is one minus the sum of the two that are returned.
+pub fn uniform_sample_triangle(u: &Point2<f32>) -> Point2<f32> {
+    let su0 = u.x.sqrt();
+    Point2::new(1.0 - su0, u.y * su0)
+}
+
+/// Map a uniformly distributed 2D sample to a direction in the hemisphere
+/// around the positive z axis. Directions are distributed according to the
+/// cosine of their angle from the z axis.
+pub fn cosine_sample_hemisphere(u: &Point2<f32>) -> Vector3<f32> {
+    // Malley's method: uniformly sample the unit disk and project the point up
+    // onto the hemisphere.
+    let d = concentric_sample_disk(u);
+    let z = (1.0 - d.x * d.x - d.y * d.y).max(0.0).sqrt();
+    Vector3::new(d.x, d.y, z)
+}
+
+/// Return the probability density, with respect to solid angle, of sampling a
+/// direction with `cosine_sample_hemisphere`.
+///
+/// * cos_theta - The cosine of the angle between the direction and the z axis.
+pub fn cosine_hemisphere_pdf(cos_theta: f32) -> f32 {
+    cos_theta * FRAC_1_PI
+}
+
+/// Return an `n`-by-`n` grid of 2D samples, one at the center of each cell in
+/// a grid that evenly divides [0, 1) x [0, 1).
+pub fn grid_samples_2d(n: usize) -> Vec<Point2<f32>> {
+    let inv_n = 1.0 / n as f32;
+    let xs = 0..n;
+    let ys = 0..n;
+    ys.flat_map(|y| xs.clone().map(move |x| (x, y)))
+        .map(|(x, y)| Point2::new((x as f32 + 0.5) * inv_n, (y as f32 + 0.5) * inv_n))
+        .collect()
+}
+
+/// A piecewise-constant distribution over [0, 1), which draws samples in
+/// proportion to a function that's tabulated at evenly spaced points.
+#[derive(Debug, Clone)]
+pub struct Distribution1D {
+    function: Vec<f32>,
+
+    /// The distribution's cumulative distribution function at the start of
+    /// each segment, and a final value of 1.
+    cdf: Vec<f32>,
+
+    /// The integral of the function over [0, 1).
+    integral: f32,
+}
+
+impl Distribution1D {
+    /// * function - The function's non-negative value in each of the equally
+    ///   sized segments of [0, 1). If it's zero everywhere, samples are drawn
+    ///   uniformly.
+    pub fn new(function: Vec<f32>) -> Self {
+        let n = function.len();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + function[i] / n as f32;
+        }
+        let integral = cdf[n];
+        for (i, c) in cdf.iter_mut().enumerate().skip(1) {
+            *c = if integral == 0.0 {
+                i as f32 / n as f32
+            } else {
+                *c / integral
+            };
+        }
+        Self {
+            function,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.function.len()
+    }
+
+    /// Return the integral of the function over [0, 1).
+    pub fn integral(&self) -> f32 {
+        self.integral
+    }
+
+    /// Map a uniformly distributed sample to a sample from the distribution.
+    /// Return the sample, the probability density at the sample, and the
+    /// index of the segment that it's in.
+    pub fn sample_continuous(&self, u: f32) -> (f32, f32, usize) {
+        // Find the last segment that starts at or before u.
+        let offset = self
+            .cdf
+            .partition_point(|c| *c <= u)
+            .saturating_sub(1)
+            .min(self.count() - 1);
+        let mut du = u - self.cdf[offset];
+        let segment_probability = self.cdf[offset + 1] - self.cdf[offset];
+        if segment_probability > 0.0 {
+            du /= segment_probability;
+        }
+        let pdf = self.pdf_of_segment(offset);
+        ((offset as f32 + du) / self.count() as f32, pdf, offset)
+    }
+
+    /// Map a uniformly distributed sample to one of the segments, chosen in
+    /// proportion to the function's value in it. Return the segment's index
+    /// and the probability that it's chosen.
+    pub fn sample_discrete(&self, u: f32) -> (usize, f32) {
+        let (_, _, offset) = self.sample_continuous(u);
+        (offset, self.discrete_pdf(offset))
+    }
+
+    /// Return the probability that `sample_discrete` chooses the segment.
+    pub fn discrete_pdf(&self, offset: usize) -> f32 {
+        self.pdf_of_segment(offset) / self.count() as f32
+    }
+
+    /// Return the probability density of the samples in the segment.
+    fn pdf_of_segment(&self, offset: usize) -> f32 {
+        if self.integral > 0.0 {
+            self.function[offset] / self.integral
+        } else {
+            1.0
+        }
+    }
+}
+
+/// A piecewise-constant distribution over [0, 1) x [0, 1), which draws
+/// samples in proportion to a function that's tabulated on a grid.
+#[derive(Debug, Clone)]
+pub struct Distribution2D {
+    /// The distribution of u given each row of the grid.
+    conditional: Vec<Distribution1D>,
+
+    /// The distribution of rows.
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// * function - The function's non-negative values in row-major order.
+    /// * width - The number of values in each row.
+    pub fn new(function: &[f32], width: usize) -> Self {
+        let conditional: Vec<Distribution1D> = function
+            .chunks(width)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(conditional.iter().map(|c| c.integral()).collect());
+        Self {
+            conditional,
+            marginal,
+        }
+    }
+
+    /// Return the size of the distribution's tables.
+    pub(crate) fn memory_bytes(&self) -> usize {
+        self.conditional
+            .iter()
+            .chain(std::iter::once(&self.marginal))
+            .map(|distribution| {
+                (distribution.function.len() + distribution.cdf.len()) * std::mem::size_of::<f32>()
+            })
+            .sum()
+    }
+
+    /// Map a uniformly distributed 2D sample to a sample from the
+    /// distribution, and return the sample and the probability density at
+    /// it.
+    pub fn sample_continuous(&self, u: &Point2<f32>) -> (Point2<f32>, f32) {
+        let (v, pdf_v, row) = self.marginal.sample_continuous(u.y);
+        let (u, pdf_u, _) = self.conditional[row].sample_continuous(u.x);
+        (Point2::new(u, v), pdf_u * pdf_v)
+    }
+
+    /// Return the probability density of the distribution at the point.
+    pub fn pdf(&self, p: &Point2<f32>) -> f32 {
+        let columns = self.conditional[0].count();
+        let rows = self.marginal.count();
+        let column = ((p.x * columns as f32) as usize).min(columns - 1);
+        let row = ((p.y * rows as f32) as usize).min(rows - 1);
+        if self.marginal.integral() == 0.0 {
+            return 1.0;
+        }
+        self.conditional[row].function[column] / self.marginal.integral()
+    }
+}
+
+#[cfg(test)]
+mod distribution_tests {
+    use super::{Distribution1D, Distribution2D};
+    use cgmath::Point2;
+
+    #[test]
+    fn samples_segments_in_proportion_to_function() {
+        let distribution = Distribution1D::new(vec![1.0, 3.0]);
+        assert!((distribution.integral() - 2.0).abs() < 1e-6);
+        // A quarter of the samples fall in the first segment.
+        let (x, pdf, offset) = distribution.sample_continuous(0.125);
+        assert_eq!(offset, 0);
+        assert!((x - 0.25).abs() < 1e-6);
+        assert!((pdf - 0.5).abs() < 1e-6);
+        let (x, pdf, offset) = distribution.sample_continuous(0.625);
+        assert_eq!(offset, 1);
+        assert!((x - 0.75).abs() < 1e-6);
+        assert!((pdf - 1.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn pdf_matches_sampled_density() {
+        let function = [0.0, 1.0, 2.0, 3.0, 4.0, 0.5];
+        let distribution = Distribution2D::new(&function, 3);
+        let (p, pdf) = distribution.sample_continuous(&Point2::new(0.3, 0.8));
+        assert!((distribution.pdf(&p) - pdf).abs() < 1e-5);
+    }
+}
@@ -1,27 +1,383 @@
 use crate::{
+    color::RgbaSpectrum,
     interaction::SurfaceInteraction,
-    light::Light,
+    light::{Light, LightFlags},
     primitive::{Primitive, PrimitiveAggregate},
     ray::Ray,
+    shape::Shape,
 };
+use bvh::aabb::{Bounded, AABB};
+use bvh::bvh::BVHNode;
+use cgmath::{InnerSpace, Point2, Point3, Vector3};
+use mesh::Mesh;
+use std::collections::HashSet;
+use std::mem::size_of;
+
+/// The maximum number of surfaces that `is_inside` will cross before
+/// giving up.
+const MAX_PARITY_CROSSINGS: usize = 1024;
+
+/// A scene whose geometry has bounds, which lights need when they're
+/// preprocessed.
+pub trait WorldBound {
+    /// Return a bounding box around every primitive in the scene. The box is
+    /// empty if the scene has no primitives.
+    fn world_bound(&self) -> AABB;
+}
 
 pub struct Scene<'msh, 'mtrl> {
     pub primitives: PrimitiveAggregate<'msh, 'mtrl>,
-    pub lights: Vec<Light>,
+
+    /// The lights, which are only set through `new` and `set_lights` so that
+    /// each of them is preprocessed with the scene's geometry.
+    lights: Vec<Light>,
 }
 
 impl<'msh, 'mtrl> Scene<'msh, 'mtrl> {
+    /// Create a scene. Each light is preprocessed with the scene's geometry.
     pub fn new(primitives: PrimitiveAggregate<'msh, 'mtrl>, lights: Vec<Light>) -> Self {
-        Self { primitives, lights }
+        let mut scene = Self {
+            primitives,
+            lights: vec![],
+        };
+        scene.set_lights(lights);
+        scene
+    }
+
+    /// Replace the scene's lights, preprocessing each of them with the
+    /// scene's geometry.
+    pub fn set_lights(&mut self, mut lights: Vec<Light>) {
+        for light in &mut lights {
+            light.preprocess(self);
+        }
+        self.lights = lights;
+    }
+
+    /// Return the scene's lights.
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
     }
 
     // Find the first primitive the ray intersects. Return the parametric value
     // at the intersection, a reference to the primitive, and a description of
-    // the primitive-ray interaction.
+    // the primitive-ray interaction. If the ray has differentials, the
+    // interaction's differentials are computed from them.
     pub fn ray_intersection(
         &self,
         ray: &Ray,
     ) -> Option<(f32, Primitive<'msh, 'mtrl>, SurfaceInteraction)> {
-        self.primitives.ray_intersection(ray)
+        let (t, primitive, mut interaction) = self.primitives.ray_intersection(ray)?;
+        interaction.compute_differentials(ray);
+        Some((t, primitive, interaction))
+    }
+
+    /// Return the parametric value at which a ray with the given origin and
+    /// direction first hits the scene's geometry, if it hits anything.
+    pub fn first_hit(&self, origin: Point3<f32>, direction: Vector3<f32>) -> Option<f32> {
+        let ray = Ray::new(origin, direction, f32::INFINITY);
+        self.primitives.ray_intersection(&ray).map(|(t, _, _)| t)
+    }
+
+    /// Return a bounding box around every primitive in the scene. The box is
+    /// empty if the scene has no primitives.
+    pub fn world_bound(&self) -> AABB {
+        self.primitives
+            .primitives()
+            .iter()
+            .fold(AABB::empty(), |bound, primitive| {
+                bound.join(&primitive.aabb())
+            })
+    }
+
+    /// Return the radiance that arrives along a ray that leaves the scene
+    /// without hitting anything, or `None` if no light surrounds the scene,
+    /// in which case escaped rays should be transparent.
+    pub fn escaped_radiance(&self, ray: &Ray) -> Option<RgbaSpectrum> {
+        let mut infinite_lights = self
+            .lights
+            .iter()
+            .filter(|light| light.flags().contains(LightFlags::INFINITE))
+            .peekable();
+        infinite_lights.peek()?;
+        let mut radiance =
+            infinite_lights.fold(RgbaSpectrum::black(), |sum, light| sum + light.le(ray));
+        radiance.set_a(1.0);
+        Some(radiance)
+    }
+
+    /// Return an estimate of the memory that the scene's geometry and the
+    /// images that its lights read from need.
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        self.primitives.add_to_memory_report(&mut report);
+        for light in &self.lights {
+            if let Some(bytes) = light.texture_bytes() {
+                report.add_texture(bytes);
+            }
+        }
+        report
+    }
+
+    /// Return `true` if the point is inside the scene's geometry.
+    ///
+    /// This casts a ray from the point and counts the surfaces it crosses. An
+    /// odd count means the point is enclosed. The result is only meaningful
+    /// for closed, watertight geometry.
+    pub fn is_inside(&self, point: Point3<f32>) -> bool {
+        is_inside(point, &self.world_bound(), |ray| {
+            self.primitives.ray_intersection(ray).map(|(t, _, _)| t)
+        })
+    }
+}
+
+impl<'msh, 'mtrl> WorldBound for Scene<'msh, 'mtrl> {
+    fn world_bound(&self) -> AABB {
+        Scene::world_bound(self)
+    }
+}
+
+/// An estimate of the memory that a scene's geometry and textures need, broken
+/// down by what it's spent on.
+///
+/// Primitives only refer to the meshes that their triangles belong to, so
+/// several aggregates, such as copies of a part with different materials, can
+/// be built from the same mesh. Each such aggregate is an instance of the mesh.
+/// A mesh's vertices are only counted once, no matter how many instances it
+/// has, but each instance has its own primitives and BVH nodes.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryReport {
+    /// The number of distinct meshes that the scene's triangles belong to.
+    pub mesh_count: usize,
+
+    /// The number of aggregates that are built from meshes.
+    pub mesh_instance_count: usize,
+
+    /// The size of the distinct meshes' vertices and triangle indices.
+    pub mesh_bytes: usize,
+
+    /// The number of distinct voxel grids in the scene.
+    pub voxel_grid_count: usize,
+
+    /// The size of the distinct voxel grids' voxels.
+    pub voxel_grid_bytes: usize,
+
+    pub primitive_count: usize,
+    pub primitive_bytes: usize,
+
+    pub bvh_node_count: usize,
+    pub bvh_node_bytes: usize,
+
+    /// The number of images that lights read their emission from, such as
+    /// environment maps and projected images. Materials are uniform, so they
+    /// don't have any.
+    pub texture_count: usize,
+
+    /// The size of the textures, including the tables that are used to
+    /// sample them.
+    pub texture_bytes: usize,
+
+    /// The addresses of the meshes and voxel grids that have been counted.
+    counted_meshes: HashSet<usize>,
+    counted_voxel_grids: HashSet<usize>,
+}
+
+impl MemoryReport {
+    /// Return the total size of everything in the report.
+    pub fn total_bytes(&self) -> usize {
+        self.mesh_bytes
+            + self.voxel_grid_bytes
+            + self.primitive_bytes
+            + self.bvh_node_bytes
+            + self.texture_bytes
+    }
+
+    /// Add a texture of the given size to the report.
+    pub(crate) fn add_texture(&mut self, bytes: usize) {
+        self.texture_count += 1;
+        self.texture_bytes += bytes;
+    }
+
+    /// Add a group of primitives that are stored together, such as the
+    /// primitives in one BVH, to the report.
+    ///
+    /// * shapes - The shapes of the primitives in the group.
+    /// * primitive_size - The size of each primitive.
+    /// * bvh_node_count - The number of nodes in the group's BVH, if any.
+    pub(crate) fn add_group<'a, 'msh: 'a>(
+        &mut self,
+        shapes: impl Iterator<Item = &'a Shape<'msh>>,
+        primitive_size: usize,
+        bvh_node_count: usize,
+    ) {
+        let mut group_meshes = HashSet::new();
+        for shape in shapes {
+            self.primitive_count += 1;
+            self.primitive_bytes += primitive_size;
+            match shape {
+                Shape::Triangle(triangle) => {
+                    let mesh = triangle.0.mesh;
+                    if group_meshes.insert(mesh as *const Mesh as usize)
+                        && self.counted_meshes.insert(mesh as *const Mesh as usize)
+                    {
+                        self.mesh_count += 1;
+                        self.mesh_bytes += mesh_bytes(mesh);
+                    }
+                }
+                Shape::Voxels(grid) => {
+                    if self.counted_voxel_grids.insert(*grid as *const _ as usize) {
+                        self.voxel_grid_count += 1;
+                        self.voxel_grid_bytes += grid.memory_bytes();
+                    }
+                }
+                Shape::Splat(_) => {}
+            }
+        }
+        self.mesh_instance_count += group_meshes.len();
+        self.bvh_node_count += bvh_node_count;
+        self.bvh_node_bytes += bvh_node_count * size_of::<BVHNode>();
+    }
+}
+
+/// Return the size of the mesh's vertices and triangle indices.
+fn mesh_bytes(mesh: &Mesh) -> usize {
+    mesh.positions.len() * size_of::<Point3<f32>>()
+        + mesh.normals.len() * size_of::<Vector3<f32>>()
+        + mesh.uvs.as_ref().map_or(0, |uvs| uvs.len()) * size_of::<Point2<f32>>()
+        + mesh.triangle_vertex_indices.len() * size_of::<(usize, usize, usize)>()
+}
+
+/// Return `true` if the point is enclosed by an odd number of surfaces.
+///
+/// * bound - A bounding box around the geometry.
+/// * ray_intersection - Returns the parametric value of the first surface that
+///   the ray hits, if any.
+pub(crate) fn is_inside<F>(point: Point3<f32>, bound: &AABB, ray_intersection: F) -> bool
+where
+    F: Fn(&Ray) -> Option<f32>,
+{
+    // An oblique direction makes it unlikely that the ray grazes an edge or a
+    // face of axis-aligned geometry.
+    let direction = Vector3::new(0.5773, 0.5774, 0.5775).normalize();
+    // Each new ray starts a small distance past the last surface it crossed,
+    // so that it doesn't hit that surface again.
+    let step = 1e-5 * (bound.max - bound.min).length().max(f32::EPSILON);
+    let mut ray = Ray::new(point, direction, f32::INFINITY);
+    let mut crossings = 0;
+    while let Some(t) = ray_intersection(&ray) {
+        crossings += 1;
+        if crossings >= MAX_PARITY_CROSSINGS {
+            break;
+        }
+        ray = Ray::new(ray.at_t(t + step), direction, f32::INFINITY);
+    }
+    crossings % 2 == 1
+}
+
+#[cfg(test)]
+mod scene_tests {
+    use super::Scene;
+    use crate::{
+        color::RgbaSpectrum,
+        light::Light,
+        material::MatteMaterial,
+        primitive::PrimitiveAggregate,
+        shape::{Shape, Splat},
+    };
+    use cgmath::{Matrix4, Point3, SquareMatrix, Vector2, Vector3};
+    use mesh::MeshBuilder;
+
+    #[test]
+    fn point_inside_sphere_is_inside() {
+        let material = MatteMaterial::new(RgbaSpectrum::constant(1.0), 0.0);
+        let splat = Splat::new(Point3::new(1.0, 2.0, 3.0), 2.0);
+        let scene = Scene::new(
+            PrimitiveAggregate::primitive(Shape::Splat(splat), &material),
+            vec![],
+        );
+        assert!(scene.is_inside(Point3::new(1.5, 2.0, 3.0)));
+        assert!(!scene.is_inside(Point3::new(4.0, 2.0, 3.0)));
+        assert!(!scene.is_inside(Point3::new(-2.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn new_preprocesses_lights_with_the_scene_bounds() {
+        let material = MatteMaterial::new(RgbaSpectrum::constant(1.0), 0.0);
+        let splat = Splat::new(Point3::new(0.0, 0.0, 0.0), 1.0);
+        let unprocessed = Light::uniform_infinite_light(RgbaSpectrum::constant(1.0));
+        let scene = Scene::new(
+            PrimitiveAggregate::primitive(Shape::Splat(splat), &material),
+            vec![Light::uniform_infinite_light(RgbaSpectrum::constant(1.0))],
+        );
+        // The light's power is that of a disk as wide as the bounds' diagonal,
+        // rather than that of one as wide as its default world radius.
+        let radius = 12f32.sqrt();
+        let power = scene.lights()[0].power().r();
+        assert!((power - std::f32::consts::PI * radius * radius).abs() < 1e-3 * power);
+        assert!(unprocessed.power().r() > 100.0 * power);
+    }
+
+    #[test]
+    fn shared_mesh_is_counted_once() {
+        let positions = vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        ];
+        let normals = vec![Vector3::new(0.0, 0.0, 1.0); 4];
+        let mesh = MeshBuilder::new(positions, normals, vec![(0, 1, 2), (0, 2, 3)]).build();
+        let red = MatteMaterial::new(RgbaSpectrum::from_rgb(1.0, 0.0, 0.0), 0.0);
+        let blue = MatteMaterial::new(RgbaSpectrum::from_rgb(0.0, 0.0, 1.0), 0.0);
+        let splat = Splat::new(Point3::new(0.0, 0.0, 2.0), 0.5);
+        let scene = Scene::new(
+            PrimitiveAggregate::Vector(vec![
+                PrimitiveAggregate::from_mesh(&mesh, &red),
+                PrimitiveAggregate::from_mesh(&mesh, &blue),
+                PrimitiveAggregate::primitive(Shape::Splat(splat), &red),
+            ]),
+            vec![],
+        );
+
+        let report = scene.memory_report();
+        assert_eq!(report.mesh_count, 1);
+        assert_eq!(report.mesh_instance_count, 2);
+        assert_eq!(report.primitive_count, 5);
+        let single = Scene::new(PrimitiveAggregate::from_mesh(&mesh, &red), vec![]);
+        assert_eq!(report.mesh_bytes, single.memory_report().mesh_bytes);
+        assert!(report.total_bytes() > report.mesh_bytes);
+    }
+
+    #[test]
+    fn light_images_are_counted_as_textures() {
+        let material = MatteMaterial::new(RgbaSpectrum::constant(1.0), 0.0);
+        let splat = Splat::new(Point3::new(0.0, 0.0, 0.0), 1.0);
+        let texel_size = std::mem::size_of::<RgbaSpectrum>();
+        let scene = Scene::new(
+            PrimitiveAggregate::primitive(Shape::Splat(splat), &material),
+            vec![
+                Light::point_light(Point3::new(0.0, 0.0, 5.0), RgbaSpectrum::constant(1.0)),
+                Light::infinite_area_light(
+                    Matrix4::identity(),
+                    vec![RgbaSpectrum::constant(1.0); 8 * 4],
+                    Vector2::new(8, 4),
+                ),
+                Light::projection_light(
+                    Matrix4::identity(),
+                    RgbaSpectrum::constant(1.0),
+                    vec![RgbaSpectrum::constant(1.0); 3 * 2],
+                    Vector2::new(3, 2),
+                    1.0,
+                ),
+            ],
+        );
+
+        let report = scene.memory_report();
+        assert_eq!(report.texture_count, 2);
+        // The environment map's sampling distribution is counted with it.
+        assert!(report.texture_bytes > (8 * 4 + 3 * 2) * texel_size);
+        assert_eq!(
+            report.total_bytes(),
+            report.primitive_bytes + report.texture_bytes
+        );
     }
 }
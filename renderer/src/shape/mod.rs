@@ -0,0 +1,60 @@
+mod splat;
+mod voxel;
+
+pub use splat::Splat;
+pub use voxel::VoxelGrid;
+
+use crate::{interaction::SurfaceInteraction, ray::Ray, triangle::Triangle};
+use bvh::aabb::Bounded;
+
+/// The geometry of a primitive.
+#[derive(Debug, Clone, Copy)]
+pub enum Shape<'msh> {
+    /// A triangle in a mesh.
+    Triangle(Triangle<'msh>),
+
+    /// A point from a point cloud.
+    Splat(Splat),
+
+    /// A grid of voxels, which is stored separately since it may be large.
+    Voxels(&'msh VoxelGrid),
+}
+
+impl<'msh> Shape<'msh> {
+    /// Returns information about the first ray-shape intersection, if any, in
+    /// the (0, `ray.t_max`) parametric range along the ray.
+    pub fn ray_intersection(&self, ray: &Ray) -> Option<(f32, SurfaceInteraction)> {
+        match self {
+            Shape::Triangle(triangle) => triangle.ray_intersection(ray),
+            Shape::Splat(splat) => splat.ray_intersection(ray),
+            Shape::Voxels(grid) => grid.ray_intersection(ray),
+        }
+    }
+
+    /// Returns an identifier for the object that the shape is part of. Every
+    /// triangle in a mesh shares its mesh's identifier, and every voxel in a
+    /// grid shares its grid's identifier. Each splat is its own object.
+    ///
+    /// Identifiers are only stable while the scene exists, since they're
+    /// derived from the addresses of the meshes and grids.
+    pub fn object_id(&self) -> u64 {
+        match self {
+            Shape::Triangle(triangle) => triangle.0.mesh as *const _ as u64,
+            Shape::Splat(splat) => {
+                let c = splat.center;
+                ((c.x.to_bits() as u64) << 32 | c.y.to_bits() as u64) ^ c.z.to_bits() as u64
+            }
+            Shape::Voxels(grid) => *grid as *const _ as u64,
+        }
+    }
+}
+
+impl<'msh> Bounded for Shape<'msh> {
+    fn aabb(&self) -> bvh::aabb::AABB {
+        match self {
+            Shape::Triangle(triangle) => triangle.aabb(),
+            Shape::Splat(splat) => splat.aabb(),
+            Shape::Voxels(grid) => grid.aabb(),
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use crate::number::efloat;
+use crate::{interaction::SurfaceInteraction, ray::Ray};
+use bvh::aabb::Bounded;
+use cgmath::{EuclideanSpace, InnerSpace, Point3, Vector3};
+use std::f32::consts::PI;
+
+/// A point from a point cloud, such as a LiDAR scan or an inspection
+/// measurement, drawn as a small sphere so that it looks the same from every
+/// direction.
+#[derive(Debug, Clone, Copy)]
+pub struct Splat {
+    pub center: Point3<f32>,
+    pub radius: f32,
+}
+
+impl Splat {
+    pub fn new(center: Point3<f32>, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// Returns information about the first ray-shape intersection, if any, in
+    /// the (0, `ray.t_max`) parametric range along the ray.
+    ///
+    /// The splat's center is in world space, `ray` is in world space, and the
+    /// returned surface interaction is in world space.
+    pub fn ray_intersection(&self, ray: &Ray) -> Option<(f32, SurfaceInteraction)> {
+        // Solve |o + t * d - c|^2 = r^2 for t. The discriminant is computed
+        // from the distance between the center and the closest point on the
+        // ray's line, and the roots from the numerically stable form of the
+        // quadratic formula, which avoids cancellation when a ray starts far
+        // from a small splat.
+        let oc = ray.origin - self.center;
+        let a = ray.direction.magnitude2();
+        let half_b = oc.dot(ray.direction);
+        let c = oc.magnitude2() - self.radius * self.radius;
+        let closest = oc - ray.direction * (half_b / a);
+        let discriminant = a * (self.radius * self.radius - closest.magnitude2());
+        if discriminant < 0.0 {
+            return None;
+        }
+        let q = -(half_b + half_b.signum() * discriminant.sqrt());
+        if q == 0.0 {
+            return None;
+        }
+        let (t0, t1) = (q / a, c / q);
+        let (t0, t1) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+
+        // Ignore hits too close to the origin to be distinguished from
+        // rounding error, which happen when a ray leaves the splat's surface.
+        let epsilon = efloat::gamma(5) * self.radius.max(oc.magnitude());
+        let t = if t0 > epsilon {
+            t0
+        } else if t1 > epsilon {
+            t1
+        } else {
+            return None;
+        };
+        if t >= ray.t_max {
+            return None;
+        }
+
+        // Project the hit onto the sphere to remove the error accumulated
+        // along the ray.
+        let normal = (ray.at_t(t) - self.center).normalize();
+        let point = self.center + normal * self.radius;
+        let point_error_bound = efloat::gamma(5)
+            * Vector3::new(point.x.abs(), point.y.abs(), point.z.abs())
+            + efloat::gamma(5) * self.center.to_vec().map(f32::abs);
+
+        // Parameterize the sphere by longitude, u, and latitude, v.
+        let local = normal * self.radius;
+        let phi_max = 2.0 * PI;
+        let dpdu = Vector3::new(-phi_max * local.y, phi_max * local.x, 0.0);
+        let dpdu = if dpdu.magnitude2() > 0.0 {
+            dpdu
+        } else {
+            // The hit is at a pole, so any direction in the tangent plane
+            // will do.
+            Vector3::new(self.radius, 0.0, 0.0)
+        };
+        let dpdv = normal.cross(dpdu);
+
+        let interaction = SurfaceInteraction::new_with_normal(
+            point,
+            point_error_bound,
+            -1.0 * ray.direction,
+            dpdu,
+            dpdv,
+            normal,
+        );
+        Some((t, interaction))
+    }
+}
+
+impl Bounded for Splat {
+    fn aabb(&self) -> bvh::aabb::AABB {
+        let r = Vector3::new(self.radius, self.radius, self.radius);
+        let min = self.center - r;
+        let max = self.center + r;
+        bvh::aabb::AABB::with_bounds(
+            bvh::Point3::new(min.x, min.y, min.z),
+            bvh::Point3::new(max.x, max.y, max.z),
+        )
+    }
+}
+
+#[cfg(test)]
+mod splat_tests {
+    use super::Splat;
+    use crate::{ray::Ray, test::ApproxEq};
+    use cgmath::{Point3, Vector3};
+
+    #[test]
+    fn ray_hits_near_side() {
+        let splat = Splat::new(Point3::new(0.0, 0.0, 5.0), 1.0);
+        let ray = Ray::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            f32::INFINITY,
+        );
+        let (t, interaction) = splat.ray_intersection(&ray).unwrap();
+        assert!(t.approx_eq(&4.0));
+        assert!(interaction.point.approx_eq(&Point3::new(0.0, 0.0, 4.0)));
+        assert!(interaction
+            .original_geometry
+            .normal
+            .approx_eq(&Vector3::new(0.0, 0.0, -1.0)));
+    }
+
+    #[test]
+    fn ray_misses_or_stops_short() {
+        let splat = Splat::new(Point3::new(0.0, 0.0, 5.0), 1.0);
+        let beside = Ray::new(
+            Point3::new(1.5, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            f32::INFINITY,
+        );
+        assert!(splat.ray_intersection(&beside).is_none());
+        let short = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0), 3.0);
+        assert!(splat.ray_intersection(&short).is_none());
+    }
+}
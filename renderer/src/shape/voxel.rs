@@ -0,0 +1,374 @@
+use crate::geometry::Transform as _;
+use crate::number::efloat;
+use crate::{interaction::SurfaceInteraction, ray::Ray};
+use bvh::aabb::Bounded;
+use cgmath::{InnerSpace, Matrix, Matrix4, Point3, SquareMatrix, Transform, Vector3};
+
+/// A grid of solid or empty cubic voxels, such as the lattice infill of a
+/// generatively designed part.
+///
+/// Rays are traced through the grid one voxel at a time with a 3D digital
+/// differential analyzer (DDA), so the cost of an intersection grows with the
+/// number of voxels a ray crosses rather than with the number of solid voxels.
+/// Each voxel takes a single bit of memory, which makes grids with billions of
+/// voxels practical where a triangle mesh of the same surface would not be.
+///
+/// The grid has its own coordinate system, in which voxel `(x, y, z)` spans
+/// `[x, x + 1] × [y, y + 1] × [z, z + 1]`, and a transformation from that
+/// coordinate system to world space.
+#[derive(Debug)]
+pub struct VoxelGrid {
+    /// The number of voxels along each axis.
+    resolution: Vector3<usize>,
+
+    /// One bit for each voxel, set if the voxel is solid. Voxels are ordered
+    /// with x varying fastest, then y, then z.
+    solid: Vec<u64>,
+
+    grid_to_world: Matrix4<f32>,
+    world_to_grid: Matrix4<f32>,
+}
+
+impl VoxelGrid {
+    /// Create a grid of empty voxels.
+    ///
+    /// * resolution - The number of voxels along each axis.
+    /// * grid_to_world - The transformation from the grid's coordinate system,
+    ///   where each voxel is a unit cube, to world space. It must be
+    ///   invertible.
+    pub fn new(resolution: Vector3<usize>, grid_to_world: Matrix4<f32>) -> Self {
+        let voxel_count = resolution.x * resolution.y * resolution.z;
+        Self {
+            resolution,
+            solid: vec![0; voxel_count.div_ceil(64)],
+            grid_to_world,
+            world_to_grid: grid_to_world.invert().unwrap_or_else(Matrix4::identity),
+        }
+    }
+
+    /// Create a grid from one byte per voxel, where a nonzero byte is a solid
+    /// voxel. The bytes are ordered with x varying fastest, then y, then z.
+    /// Return `None` if the number of bytes doesn't match the resolution.
+    pub fn from_bytes(
+        resolution: Vector3<usize>,
+        grid_to_world: Matrix4<f32>,
+        bytes: &[u8],
+    ) -> Option<Self> {
+        if bytes.len() != resolution.x * resolution.y * resolution.z {
+            return None;
+        }
+        let mut grid = Self::new(resolution, grid_to_world);
+        for (i, byte) in bytes.iter().enumerate() {
+            if *byte != 0 {
+                grid.solid[i / 64] |= 1 << (i % 64);
+            }
+        }
+        Some(grid)
+    }
+
+    /// Mark the voxel as solid or empty.
+    pub fn set(&mut self, voxel: Point3<usize>, solid: bool) {
+        let i = self.index(voxel);
+        if solid {
+            self.solid[i / 64] |= 1 << (i % 64);
+        } else {
+            self.solid[i / 64] &= !(1 << (i % 64));
+        }
+    }
+
+    pub fn is_solid(&self, voxel: Point3<usize>) -> bool {
+        let i = self.index(voxel);
+        self.solid[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    /// Return the size of the grid's voxels, which are stored as one bit each.
+    pub(crate) fn memory_bytes(&self) -> usize {
+        self.solid.len() * std::mem::size_of::<u64>()
+    }
+
+    /// Return the faces that separate solid voxels from empty voxels or from
+    /// the outside of the grid, which make up the grid's visible surface. Each
+    /// face is a quadrilateral with its corners in world space, in order
+    /// around its edge.
+    pub(crate) fn exposed_faces(&self) -> Vec<[Point3<f32>; 4]> {
+        let is_solid = |voxel: [isize; 3]| {
+            let resolution = [self.resolution.x, self.resolution.y, self.resolution.z];
+            (0..3).all(|axis| voxel[axis] >= 0 && (voxel[axis] as usize) < resolution[axis])
+                && self.is_solid(Point3::new(
+                    voxel[0] as usize,
+                    voxel[1] as usize,
+                    voxel[2] as usize,
+                ))
+        };
+
+        let mut faces = vec![];
+        for z in 0..self.resolution.z as isize {
+            for y in 0..self.resolution.y as isize {
+                for x in 0..self.resolution.x as isize {
+                    if !is_solid([x, y, z]) {
+                        continue;
+                    }
+                    for axis in 0..3 {
+                        for side in [0, 1] {
+                            let mut neighbor = [x, y, z];
+                            neighbor[axis] += 2 * side - 1;
+                            if is_solid(neighbor) {
+                                continue;
+                            }
+                            // The face lies in the plane at the voxel's near
+                            // or far side along the axis, and spans the other
+                            // two axes.
+                            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+                            let corner = |du: f32, dv: f32| {
+                                let mut p = [x as f32, y as f32, z as f32];
+                                p[axis] += side as f32;
+                                p[u] += du;
+                                p[v] += dv;
+                                self.grid_to_world
+                                    .transform_point(Point3::new(p[0], p[1], p[2]))
+                            };
+                            faces.push([
+                                corner(0.0, 0.0),
+                                corner(1.0, 0.0),
+                                corner(1.0, 1.0),
+                                corner(0.0, 1.0),
+                            ]);
+                        }
+                    }
+                }
+            }
+        }
+        faces
+    }
+
+    /// Apply the transformation to the grid, in addition to its existing
+    /// transformation to world space.
+    pub fn transform(&mut self, transformation: Matrix4<f32>) {
+        self.grid_to_world = transformation * self.grid_to_world;
+        self.world_to_grid = self
+            .grid_to_world
+            .invert()
+            .unwrap_or_else(Matrix4::identity);
+    }
+
+    fn index(&self, voxel: Point3<usize>) -> usize {
+        (voxel.z * self.resolution.y + voxel.y) * self.resolution.x + voxel.x
+    }
+
+    /// Returns information about the first ray-shape intersection, if any, in
+    /// the (0, `ray.t_max`) parametric range along the ray.
+    ///
+    /// `ray` is in world space, and the returned surface interaction is in
+    /// world space.
+    pub fn ray_intersection(&self, ray: &Ray) -> Option<(f32, SurfaceInteraction)> {
+        // The ray's direction isn't normalized after the transformation, so
+        // parametric values along it are the same in both spaces.
+        let grid_ray = self.world_to_grid.transform(ray);
+        let (t, axis, step) = self.traverse(&grid_ray)?;
+
+        let mut grid_normal = Vector3::new(0.0, 0.0, 0.0);
+        grid_normal[axis] = -(step as f32);
+        let mut dpdu = Vector3::new(0.0, 0.0, 0.0);
+        dpdu[(axis + 1) % 3] = 1.0;
+        let mut dpdv = Vector3::new(0.0, 0.0, 0.0);
+        dpdv[(axis + 2) % 3] = 1.0;
+
+        // Normals are transformed by the inverse transpose so that they stay
+        // perpendicular to the surface under non-uniform scaling.
+        let normal = self
+            .world_to_grid
+            .transpose()
+            .transform_vector(grid_normal)
+            .normalize();
+        let point = ray.at_t(t);
+        let point_error_bound =
+            efloat::gamma(7) * Vector3::new(point.x.abs(), point.y.abs(), point.z.abs());
+        let interaction = SurfaceInteraction::new_with_normal(
+            point,
+            point_error_bound,
+            -1.0 * ray.direction,
+            self.grid_to_world.transform_vector(dpdu),
+            self.grid_to_world.transform_vector(dpdv),
+            normal,
+        );
+        Some((t, interaction))
+    }
+
+    /// Step through the voxels that the grid-space ray crosses. Return the
+    /// parametric value at which the ray enters the first solid voxel, the
+    /// axis perpendicular to the face it enters through, and the direction,
+    /// 1 or -1, that the ray travels along that axis.
+    fn traverse(&self, ray: &Ray) -> Option<(f32, usize, i64)> {
+        let resolution = [
+            self.resolution.x as f32,
+            self.resolution.y as f32,
+            self.resolution.z as f32,
+        ];
+
+        // Clip the ray to the grid's bounds.
+        let (mut t_enter, mut t_exit) = (0.0, ray.t_max);
+        let mut enter_axis = None;
+        for (axis, size) in resolution.iter().enumerate() {
+            let inv_d = 1.0 / ray.direction[axis];
+            let mut t_near = (0.0 - ray.origin[axis]) * inv_d;
+            let mut t_far = (size - ray.origin[axis]) * inv_d;
+            if t_near > t_far {
+                std::mem::swap(&mut t_near, &mut t_far);
+            }
+            // A direction component of zero gives NaNs when the origin lies
+            // on a boundary, and the comparisons below skip them.
+            if t_near > t_enter {
+                t_enter = t_near;
+                enter_axis = Some(axis);
+            }
+            if t_far < t_exit {
+                t_exit = t_far;
+            }
+            if t_enter > t_exit {
+                return None;
+            }
+        }
+
+        let entry = ray.at_t(t_enter);
+        let mut voxel = [0i64; 3];
+        let mut step = [0i64; 3];
+        let mut t_next = [f32::INFINITY; 3];
+        let mut t_delta = [f32::INFINITY; 3];
+        for axis in 0..3 {
+            let max_index = self.resolution[axis] as i64 - 1;
+            voxel[axis] = (entry[axis].floor() as i64).clamp(0, max_index);
+            let d = ray.direction[axis];
+            if d > 0.0 {
+                step[axis] = 1;
+                t_next[axis] = ((voxel[axis] + 1) as f32 - ray.origin[axis]) / d;
+                t_delta[axis] = 1.0 / d;
+            } else if d < 0.0 {
+                step[axis] = -1;
+                t_next[axis] = (voxel[axis] as f32 - ray.origin[axis]) / d;
+                t_delta[axis] = -1.0 / d;
+            }
+        }
+
+        loop {
+            let solid = self.is_solid(Point3::new(
+                voxel[0] as usize,
+                voxel[1] as usize,
+                voxel[2] as usize,
+            ));
+            // A ray that starts inside a solid voxel is leaving the surface,
+            // so that voxel doesn't count as a hit.
+            if let (true, Some(axis)) = (solid, enter_axis) {
+                return Some((t_enter, axis, step[axis]));
+            }
+
+            let axis = if t_next[0] < t_next[1] && t_next[0] < t_next[2] {
+                0
+            } else if t_next[1] < t_next[2] {
+                1
+            } else {
+                2
+            };
+            if t_next[axis] > t_exit {
+                return None;
+            }
+            voxel[axis] += step[axis];
+            if voxel[axis] < 0 || voxel[axis] >= self.resolution[axis] as i64 {
+                return None;
+            }
+            t_enter = t_next[axis];
+            t_next[axis] += t_delta[axis];
+            enter_axis = Some(axis);
+        }
+    }
+}
+
+impl Bounded for VoxelGrid {
+    fn aabb(&self) -> bvh::aabb::AABB {
+        let (x, y, z) = (
+            self.resolution.x as f32,
+            self.resolution.y as f32,
+            self.resolution.z as f32,
+        );
+        let corners = [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(x, 0.0, 0.0),
+            Point3::new(0.0, y, 0.0),
+            Point3::new(0.0, 0.0, z),
+            Point3::new(x, y, 0.0),
+            Point3::new(x, 0.0, z),
+            Point3::new(0.0, y, z),
+            Point3::new(x, y, z),
+        ];
+        let mut aabb = bvh::aabb::AABB::empty();
+        for corner in corners.iter() {
+            let p = self.grid_to_world.transform_point(*corner);
+            aabb.grow_mut(&bvh::Point3::new(p.x, p.y, p.z));
+        }
+        aabb
+    }
+}
+
+#[cfg(test)]
+mod voxel_grid_tests {
+    use super::VoxelGrid;
+    use crate::{ray::Ray, test::ApproxEq};
+    use cgmath::{Matrix4, Point3, Vector3};
+
+    /// Return a 4x4x4 grid, scaled to span [0, 2] on each axis, with a single
+    /// solid voxel at (2, 1, 1).
+    fn grid() -> VoxelGrid {
+        let mut grid = VoxelGrid::new(Vector3::new(4, 4, 4), Matrix4::from_scale(0.5));
+        grid.set(Point3::new(2, 1, 1), true);
+        grid
+    }
+
+    #[test]
+    fn exposed_faces_are_between_solid_and_empty_voxels() {
+        let mut grid = grid();
+        assert_eq!(grid.exposed_faces().len(), 6);
+
+        // The face that the two voxels share is hidden.
+        grid.set(Point3::new(3, 1, 1), true);
+        let faces = grid.exposed_faces();
+        assert_eq!(faces.len(), 10);
+        assert!(faces.iter().all(|face| face
+            .iter()
+            .all(|p| p.x >= 1.0 && p.x <= 2.0 && p.y >= 0.5 && p.y <= 1.0)));
+    }
+
+    #[test]
+    fn ray_hits_first_solid_voxel() {
+        let ray = Ray::new(
+            Point3::new(-1.0, 0.75, 0.75),
+            Vector3::new(1.0, 0.0, 0.0),
+            f32::INFINITY,
+        );
+        let (t, interaction) = grid().ray_intersection(&ray).unwrap();
+        assert!(t.approx_eq(&2.0));
+        assert!(interaction.point.approx_eq(&Point3::new(1.0, 0.75, 0.75)));
+        assert!(interaction
+            .original_geometry
+            .normal
+            .approx_eq(&Vector3::new(-1.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn ray_through_empty_voxels_misses() {
+        let ray = Ray::new(
+            Point3::new(-1.0, 1.75, 0.75),
+            Vector3::new(1.0, 0.0, 0.2),
+            f32::INFINITY,
+        );
+        assert!(grid().ray_intersection(&ray).is_none());
+    }
+
+    #[test]
+    fn ray_leaving_solid_voxel_misses_it() {
+        let ray = Ray::new(
+            Point3::new(1.25, 0.75, 0.75),
+            Vector3::new(0.0, -1.0, 0.0),
+            f32::INFINITY,
+        );
+        assert!(grid().ray_intersection(&ray).is_none());
+    }
+}
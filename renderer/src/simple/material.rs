@@ -11,6 +11,11 @@ pub struct Material {
     /// The degree to which the material reflects light. 0 is completely
     /// nonreflective. 1 is a perfect mirror.
     pub reflective: f32,
+
+    /// The fraction of light that the surface blocks. 1 is completely opaque.
+    /// Surfaces that are less opaque show the surfaces behind them, which
+    /// ghosts a housing so that the components inside it are visible.
+    pub opacity: f32,
 }
 
 impl Material {
@@ -29,6 +34,14 @@ impl Material {
             specular,
             shininess,
             reflective,
+            opacity: 1.0,
         }
     }
+
+    /// Return the material, modified so that it blocks the given fraction of
+    /// light. The opacity is clamped to [0, 1].
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity.clamp(0.0, 1.0);
+        self
+    }
 }
@@ -2,6 +2,8 @@ use super::material::Material;
 use crate::interaction::SurfaceInteraction;
 use crate::number;
 use crate::ray::Ray;
+use crate::scene::MemoryReport;
+use crate::shape::{Shape, Splat};
 use crate::triangle::Triangle;
 use bvh::bvh::BVH;
 use bvh::{aabb::Bounded, bounding_hierarchy::BHShape};
@@ -11,7 +13,7 @@ use mesh::Mesh;
 /// used in the construction of primitives aggregates.
 #[derive(Clone, Copy)]
 pub struct Primitive<'msh> {
-    pub shape: Triangle<'msh>,
+    pub shape: Shape<'msh>,
     pub material: Material,
 
     /// Tracks the index of the primitives in a bounding volume
@@ -20,7 +22,7 @@ pub struct Primitive<'msh> {
 }
 
 impl<'msh> Primitive<'msh> {
-    pub fn new(shape: Triangle<'msh>, material: Material) -> Self {
+    pub fn new(shape: Shape<'msh>, material: Material) -> Self {
         Self {
             shape,
             material,
@@ -53,7 +55,7 @@ pub enum PrimitiveAggregate<'msh> {
 }
 
 impl<'msh> PrimitiveAggregate<'msh> {
-    pub fn primitive(shape: Triangle<'msh>, material: Material) -> Self {
+    pub fn primitive(shape: Shape<'msh>, material: Material) -> Self {
         Self::Primitive(Primitive::new(shape, material))
     }
 
@@ -90,11 +92,51 @@ impl<'msh> PrimitiveAggregate<'msh> {
         }
     }
 
+    /// Return every primitive in the aggregate.
+    pub fn primitives(&self) -> Vec<&Primitive<'msh>> {
+        match self {
+            PrimitiveAggregate::Primitive(p) => vec![p],
+            PrimitiveAggregate::Vector(ps) => ps.iter().flat_map(|p| p.primitives()).collect(),
+            PrimitiveAggregate::Bvh(ps, _) => ps.iter().collect(),
+        }
+    }
+
+    /// Add the aggregate's primitives to the memory report. Each aggregate of
+    /// primitives is counted as a group.
+    pub(crate) fn add_to_memory_report(&self, report: &mut MemoryReport) {
+        let primitive_size = std::mem::size_of::<Primitive>();
+        match self {
+            PrimitiveAggregate::Primitive(p) => {
+                report.add_group(std::iter::once(&p.shape), primitive_size, 0)
+            }
+            PrimitiveAggregate::Vector(ps) => {
+                for p in ps {
+                    p.add_to_memory_report(report);
+                }
+            }
+            PrimitiveAggregate::Bvh(ps, bvh) => {
+                report.add_group(ps.iter().map(|p| &p.shape), primitive_size, bvh.nodes.len())
+            }
+        }
+    }
+
     pub fn from_mesh(mesh: &'msh Mesh, material: Material) -> Self {
         let mut primitives: Vec<Primitive> = mesh
             .triangles()
             .into_iter()
-            .map(|t| Primitive::new(Triangle(t), material))
+            .map(|t| Primitive::new(Shape::Triangle(Triangle(t)), material))
+            .collect();
+        let bvh = BVH::build(&mut primitives);
+        Self::Bvh(primitives, bvh)
+    }
+
+    /// Create an aggregate of splats, such as the points in a point cloud.
+    /// Each splat is paired with its own material so that points can be
+    /// colored individually.
+    pub fn from_splats(splats: Vec<(Splat, Material)>) -> Self {
+        let mut primitives: Vec<Primitive> = splats
+            .into_iter()
+            .map(|(splat, material)| Primitive::new(Shape::Splat(splat), material))
             .collect();
         let bvh = BVH::build(&mut primitives);
         Self::Bvh(primitives, bvh)
@@ -1,11 +1,21 @@
 use crate::{
-    color::RgbaSpectrum, geometry::vector, integrator::RayTracer, interaction::SurfaceInteraction,
-    light::Light, ray::Ray, sampler::IncrementalSampler,
+    color::RgbaSpectrum,
+    geometry::vector,
+    integrator::{Aov, RayTracer},
+    interaction::{OffsetRayOrigin, SurfaceInteraction},
+    light::Light,
+    ray::Ray,
+    sampler::IncrementalSampler,
 };
-use cgmath::InnerSpace;
+use cgmath::{InnerSpace, Point2, Vector3};
 
 use super::{Material, Scene};
 
+/// The largest number of translucent surfaces that a ray passes through.
+/// Surfaces beyond them are ignored, which bounds the cost of rays that graze
+/// many layers of a ghosted part.
+const MAX_TRANSLUCENT_LAYERS: usize = 16;
+
 pub struct OriginalRayTracer {}
 
 impl<'msh, Sampler: IncrementalSampler> RayTracer<Scene<'msh>, Sampler> for OriginalRayTracer {
@@ -13,59 +23,110 @@ impl<'msh, Sampler: IncrementalSampler> RayTracer<Scene<'msh>, Sampler> for Orig
         &self,
         ray: &Ray,
         scene: &Scene<'msh>,
-        _sampler: &mut Sampler,
+        sampler: &mut Sampler,
         depth: usize,
         max_depth: usize,
     ) -> RgbaSpectrum {
-        Self::color_at(scene, ray, max_depth - depth)
+        Self::color_at(scene, ray, sampler, max_depth - depth)
+    }
+
+    fn aov(
+        &self,
+        aov: Aov,
+        ray: &Ray,
+        view_direction: &Vector3<f32>,
+        scene: &Scene<'msh>,
+    ) -> Option<RgbaSpectrum> {
+        let value = if let Some((t, primitive, interaction)) = scene.ray_intersection(ray) {
+            let mut value = match aov {
+                Aov::Normal => {
+                    let n = interaction.shading_geometry.normal.normalize();
+                    RgbaSpectrum::from_rgb(n.x, n.y, n.z)
+                }
+                Aov::Depth => RgbaSpectrum::constant(Aov::depth(ray, t, view_direction)),
+                // The material's color is the fraction of light that its
+                // diffuse term reflects.
+                Aov::Albedo => primitive.material.color,
+                Aov::ObjectId => Aov::object_id_color(primitive.shape.object_id()),
+            };
+            value.set_a(1.0);
+            value
+        } else {
+            RgbaSpectrum::transparent()
+        };
+        Some(value)
     }
 }
 
 impl OriginalRayTracer {
-    pub fn color_at(scene: &Scene, ray: &Ray, remaining: usize) -> RgbaSpectrum {
+    /// * sampler - Chooses the points on area lights and the directions of
+    ///   environment lights that each surface is shaded with.
+    pub fn color_at<S: IncrementalSampler>(
+        scene: &Scene,
+        ray: &Ray,
+        sampler: &mut S,
+        remaining: usize,
+    ) -> RgbaSpectrum {
+        Self::composited_color_at(scene, ray, sampler, remaining, MAX_TRANSLUCENT_LAYERS)
+    }
+
+    /// Return the color seen along the ray. Surfaces that aren't fully opaque
+    /// are composited in order, front to back, over the color seen through
+    /// them, until an opaque surface is hit or `layers` translucent surfaces
+    /// have been passed through.
+    fn composited_color_at<S: IncrementalSampler>(
+        scene: &Scene,
+        ray: &Ray,
+        sampler: &mut S,
+        remaining: usize,
+        layers: usize,
+    ) -> RgbaSpectrum {
         if let Some((_t, primitive, interaction)) = scene.primitives.ray_intersection(&ray) {
-            Self::shade_surface_interaction(scene, &interaction, &primitive.material, remaining)
+            let mut surface = Self::shade_surface_interaction(
+                scene,
+                &interaction,
+                &primitive.material,
+                sampler,
+                remaining,
+            );
+            let opacity = primitive.material.opacity;
+            if opacity >= 1.0 || layers == 0 {
+                return surface;
+            }
+
+            // Composite the surface over what is behind it. The colors are
+            // premultiplied, so the surface is treated as fully covering the
+            // pixel before it's weighted by its opacity.
+            let behind_ray = interaction.spawn_ray(&ray.direction);
+            let behind =
+                Self::composited_color_at(scene, &behind_ray, sampler, remaining, layers - 1);
+            surface.set_a(1.0);
+            opacity * surface + (1.0 - opacity) * behind
         } else {
-            RgbaSpectrum::transparent()
+            scene
+                .escaped_radiance(ray)
+                .unwrap_or_else(RgbaSpectrum::transparent)
         }
     }
 
-    pub fn shade_surface_interaction(
+    pub fn shade_surface_interaction<S: IncrementalSampler>(
         scene: &Scene,
         interaction: &SurfaceInteraction,
         material: &Material,
+        sampler: &mut S,
         remaining: usize,
     ) -> RgbaSpectrum {
         scene
             .lights
             .iter()
             .fold(RgbaSpectrum::constant(0.0), |color, light| {
-                // // Shift the interaction point away from the surface slightly, so that
-                // // the occlusion check doesn't accidentally intersect the surface.
-                // let in_shadow = Self::is_occluded(scene, interaction.over_point(), *light);
-                let surface = Self::shading(material, light, &interaction);
+                let surface =
+                    Self::shading(scene, material, light, &interaction, &sampler.get_2d());
                 // let reflected = Self::reflected_color(scene, material, interaction, remaining);
                 color + surface // + reflected
             })
     }
 
-    // /// Returns true if the specified point is occluded from the light.
-    // pub fn is_occluded(scene: &Scene, p: Point3<f32>, light: &LightSource) -> bool {
-    //     match light {
-    //         LightSource::PointLight(point_light) => {
-    //             let to_light = point_light.position - p;
-    //             let distance = to_light.magnitude();
-
-    //             let ray = Ray::new(p, to_light.normalize());
-    //             if let Some((t, _, _)) = scene.primitives.ray_intersection(&ray) {
-    //                 t < distance
-    //             } else {
-    //                 false
-    //             }
-    //         }
-    //     }
-    // }
-
     // fn reflected_color(
     //     scene: &Scene,
     //     material: &Material,
@@ -82,11 +143,20 @@ impl OriginalRayTracer {
     // }
 
     fn shading(
+        scene: &Scene,
         material: &Material,
         light: &Light, // FIXME
         interaction: &SurfaceInteraction,
+        u: &Point2<f32>,
     ) -> RgbaSpectrum {
-        let (incident_light, to_light, _) = light.li(interaction);
+        // Lights that aren't points are shaded with a single sampled
+        // direction, weighted so that the average over many samples is
+        // correct.
+        let (incident_light, to_light, vis, pdf) = light.sample_li(interaction, u);
+        if pdf == 0.0 {
+            return RgbaSpectrum::black();
+        }
+        let incident_light = incident_light / pdf;
         let effective_color = material.color * incident_light;
         let ambient = effective_color * material.ambient;
 
@@ -94,7 +164,9 @@ impl OriginalRayTracer {
         // If it's negative then the light is on the other side of the surface.
         let light_dot_normal = to_light.dot(interaction.original_geometry.normal);
 
-        let (diffuse, specular) = if light_dot_normal >= 0.0 {
+        // Surfaces in shadow only receive the light's ambient contribution.
+        // The shadow ray is only traced if the surface faces the light.
+        let (diffuse, specular) = if light_dot_normal >= 0.0 && vis.unocculuded(scene) {
             let diffuse = effective_color * material.diffuse * light_dot_normal;
 
             // reflect_dot_eye is the cosine of the angle between the reflection and
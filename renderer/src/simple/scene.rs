@@ -1,5 +1,13 @@
 use super::primitive::{Primitive, PrimitiveAggregate};
-use crate::{interaction::SurfaceInteraction, light::Light, ray::Ray};
+use crate::{
+    color::RgbaSpectrum,
+    interaction::SurfaceInteraction,
+    light::{Light, LightFlags},
+    ray::Ray,
+    scene::{MemoryReport, WorldBound},
+};
+use bvh::aabb::{Bounded, AABB};
+use cgmath::{Point3, Vector3};
 
 pub struct Scene<'msh> {
     pub primitives: PrimitiveAggregate<'msh>,
@@ -7,8 +15,23 @@ pub struct Scene<'msh> {
 }
 
 impl<'msh> Scene<'msh> {
+    /// Create a scene. Each light is preprocessed with the scene's geometry.
     pub fn new(primitives: PrimitiveAggregate<'msh>, lights: Vec<Light>) -> Self {
-        Self { primitives, lights }
+        let mut scene = Self {
+            primitives,
+            lights: vec![],
+        };
+        scene.set_lights(lights);
+        scene
+    }
+
+    /// Replace the scene's lights, preprocessing each of them with the
+    /// scene's geometry.
+    pub fn set_lights(&mut self, mut lights: Vec<Light>) {
+        for light in &mut lights {
+            light.preprocess(self);
+        }
+        self.lights = lights;
     }
 
     // Find the first primitive the ray intersects. Return the parametric value
@@ -20,4 +43,59 @@ impl<'msh> Scene<'msh> {
     ) -> Option<(f32, Primitive<'msh>, SurfaceInteraction)> {
         self.primitives.ray_intersection(ray)
     }
+
+    /// Return the parametric value at which a ray with the given origin and
+    /// direction first hits the scene's geometry, if it hits anything.
+    pub fn first_hit(&self, origin: Point3<f32>, direction: Vector3<f32>) -> Option<f32> {
+        let ray = Ray::new(origin, direction, f32::INFINITY);
+        self.primitives.ray_intersection(&ray).map(|(t, _, _)| t)
+    }
+
+    /// Return a bounding box around every primitive in the scene. The box is
+    /// empty if the scene has no primitives.
+    pub fn world_bound(&self) -> AABB {
+        self.primitives
+            .primitives()
+            .iter()
+            .fold(AABB::empty(), |bound, primitive| {
+                bound.join(&primitive.aabb())
+            })
+    }
+
+    /// Return the radiance that arrives along a ray that leaves the scene
+    /// without hitting anything, or `None` if no light surrounds the scene,
+    /// in which case escaped rays should be transparent.
+    pub fn escaped_radiance(&self, ray: &Ray) -> Option<RgbaSpectrum> {
+        let mut infinite_lights = self
+            .lights
+            .iter()
+            .filter(|light| light.flags().contains(LightFlags::INFINITE))
+            .peekable();
+        infinite_lights.peek()?;
+        let mut radiance =
+            infinite_lights.fold(RgbaSpectrum::black(), |sum, light| sum + light.le(ray));
+        radiance.set_a(1.0);
+        Some(radiance)
+    }
+
+    /// Return an estimate of the memory that the scene's geometry needs.
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        self.primitives.add_to_memory_report(&mut report);
+        report
+    }
+
+    /// Return `true` if the point is inside the scene's geometry. The result is
+    /// only meaningful for closed, watertight geometry.
+    pub fn is_inside(&self, point: Point3<f32>) -> bool {
+        crate::scene::is_inside(point, &self.world_bound(), |ray| {
+            self.primitives.ray_intersection(ray).map(|(t, _, _)| t)
+        })
+    }
+}
+
+impl<'msh> WorldBound for Scene<'msh> {
+    fn world_bound(&self) -> AABB {
+        Scene::world_bound(self)
+    }
 }
@@ -0,0 +1,183 @@
+use crate::material::Material;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+thread_local! {
+    /// The records that the current thread has made for the tile that it's
+    /// rendering, keyed by the address of the statistics they belong to and
+    /// then by the address of the material they describe.
+    static TILE_RECORDS: RefCell<HashMap<usize, HashMap<usize, MaterialRecord>>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Collects render-time statistics for each material in a scene. Integrators
+/// record the work they do at each surface interaction, and that work is
+/// attributed to the material at the interaction.
+///
+/// This is useful for finding out which material is responsible for a slow
+/// render.
+///
+/// The statistics can be shared between the threads that render separate
+/// tiles. Each thread accumulates its records for the tile that it's rendering
+/// on its own, and merges them into the shared records only when it finishes
+/// the tile, so threads don't contend for the records at every interaction.
+#[derive(Default)]
+pub struct MaterialStatistics {
+    /// Records keyed by the address of the material they describe.
+    records: Mutex<HashMap<usize, MaterialRecord>>,
+}
+
+/// The work that an integrator has attributed to a single material.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaterialRecord {
+    /// The name of the material's type.
+    pub name: String,
+
+    /// The number of surface interactions that were shaded with the material.
+    pub samples: usize,
+
+    /// The number of rays (shadow rays and scattered rays) that were spawned
+    /// from surface interactions with the material.
+    pub rays_spawned: usize,
+
+    /// The total time spent shading surface interactions with the material.
+    pub time: Duration,
+}
+
+impl MaterialStatistics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attribute the shading of a single surface interaction to the given
+    /// material.
+    ///
+    /// * material - The material at the surface interaction.
+    /// * rays_spawned - The number of rays that were spawned from the surface
+    ///   interaction.
+    /// * shading_start - The time at which shading of the surface interaction
+    ///   began.
+    pub fn record(&self, material: &dyn Material, rays_spawned: usize, shading_start: Instant) {
+        let elapsed = shading_start.elapsed();
+        let key = material as *const dyn Material as *const () as usize;
+        TILE_RECORDS.with(|tile_records| {
+            let mut tile_records = tile_records.borrow_mut();
+            let record = tile_records
+                .entry(self.key())
+                .or_default()
+                .entry(key)
+                .or_insert_with(|| MaterialRecord::new(material.name()));
+            record.samples += 1;
+            record.rays_spawned += rays_spawned;
+            record.time += elapsed;
+        });
+    }
+
+    /// Merge the records that the current thread has made since it last
+    /// finished a tile into the statistics. Integrators call this when the
+    /// thread finishes rendering a tile.
+    pub fn finish_tile(&self) {
+        let tile_records =
+            TILE_RECORDS.with(|tile_records| tile_records.borrow_mut().remove(&self.key()));
+        if let (Some(tile_records), Ok(mut records)) = (tile_records, self.records.lock()) {
+            for (key, tile_record) in tile_records {
+                let record = records
+                    .entry(key)
+                    .or_insert_with(|| MaterialRecord::new(&tile_record.name));
+                record.samples += tile_record.samples;
+                record.rays_spawned += tile_record.rays_spawned;
+                record.time += tile_record.time;
+            }
+        }
+    }
+
+    /// Return the address of the statistics, which identifies their records
+    /// among each thread's tile records.
+    fn key(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    /// Return a record for each material that has been shaded in a finished
+    /// tile, sorted so that the material with the most shading time comes
+    /// first.
+    pub fn records(&self) -> Vec<MaterialRecord> {
+        let mut records: Vec<MaterialRecord> = self
+            .records
+            .lock()
+            .map(|records| records.values().cloned().collect())
+            .unwrap_or_default();
+        records.sort_by_key(|r| std::cmp::Reverse(r.time));
+        records
+    }
+}
+
+impl MaterialRecord {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            samples: 0,
+            rays_spawned: 0,
+            time: Duration::ZERO,
+        }
+    }
+}
+
+impl fmt::Display for MaterialStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let records = self.records();
+        let total_time: Duration = records.iter().map(|r| r.time).sum();
+        writeln!(
+            f,
+            "{:<24} {:>12} {:>12} {:>12} {:>8}",
+            "Material", "Samples", "Rays", "Time (ms)", "Time %"
+        )?;
+        for r in &records {
+            let percent = if total_time.is_zero() {
+                0.0
+            } else {
+                100.0 * r.time.as_secs_f64() / total_time.as_secs_f64()
+            };
+            writeln!(
+                f,
+                "{:<24} {:>12} {:>12} {:>12.3} {:>7.1}%",
+                r.name,
+                r.samples,
+                r.rays_spawned,
+                r.time.as_secs_f64() * 1000.0,
+                percent
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod material_statistics_tests {
+    use super::MaterialStatistics;
+    use crate::{color::RgbaSpectrum, material::MatteMaterial};
+    use std::time::Instant;
+
+    #[test]
+    fn records_are_kept_per_material_instance() {
+        let matte1 = MatteMaterial::new(RgbaSpectrum::constant(0.5), 0.0);
+        let matte2 = MatteMaterial::new(RgbaSpectrum::constant(0.5), 0.0);
+        let stats = MaterialStatistics::new();
+
+        stats.record(&matte1, 2, Instant::now());
+        stats.record(&matte1, 3, Instant::now());
+        stats.record(&matte2, 1, Instant::now());
+        assert!(stats.records().is_empty());
+        stats.finish_tile();
+
+        let records = stats.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records.iter().map(|r| r.samples).sum::<usize>(), 3);
+        assert_eq!(records.iter().map(|r| r.rays_spawned).sum::<usize>(), 6);
+        assert!(records.iter().all(|r| r.name == "MatteMaterial"));
+    }
+}
@@ -0,0 +1,75 @@
+//! Provides functions that combine the images seen by a left and right eye into
+//! a single image that can be viewed in 3D on a standard display.
+
+use image::{imageops, ImageBuffer, Primitive, Rgba};
+
+/// Describes how a stereo pair of images is combined into one image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StereoLayout {
+    /// A red-cyan anaglyph. The red channel comes from the left eye's image
+    /// and the green and blue channels come from the right eye's image.
+    Anaglyph,
+
+    /// The left eye's image and the right eye's image placed next to each
+    /// other, for viewing with a stereoscope or with parallel viewing.
+    SideBySide,
+}
+
+/// Combine the images seen by the left and right eye. The images must have
+/// the same dimensions, and may have any channel type.
+pub fn combine<S: Primitive + 'static>(
+    left: &ImageBuffer<Rgba<S>, Vec<S>>,
+    right: &ImageBuffer<Rgba<S>, Vec<S>>,
+    layout: StereoLayout,
+) -> ImageBuffer<Rgba<S>, Vec<S>> {
+    assert_eq!(left.dimensions(), right.dimensions());
+    match layout {
+        StereoLayout::Anaglyph => anaglyph(left, right),
+        StereoLayout::SideBySide => side_by_side(left, right),
+    }
+}
+
+fn anaglyph<S: Primitive + 'static>(
+    left: &ImageBuffer<Rgba<S>, Vec<S>>,
+    right: &ImageBuffer<Rgba<S>, Vec<S>>,
+) -> ImageBuffer<Rgba<S>, Vec<S>> {
+    ImageBuffer::from_fn(left.width(), left.height(), |x, y| {
+        let Rgba([lr, _, _, la]) = *left.get_pixel(x, y);
+        let Rgba([_, rg, rb, ra]) = *right.get_pixel(x, y);
+        Rgba([lr, rg, rb, if la > ra { la } else { ra }])
+    })
+}
+
+fn side_by_side<S: Primitive + 'static>(
+    left: &ImageBuffer<Rgba<S>, Vec<S>>,
+    right: &ImageBuffer<Rgba<S>, Vec<S>>,
+) -> ImageBuffer<Rgba<S>, Vec<S>> {
+    let mut image = ImageBuffer::new(left.width() * 2, left.height());
+    imageops::replace(&mut image, left, 0, 0);
+    imageops::replace(&mut image, right, left.width(), 0);
+    image
+}
+
+#[cfg(test)]
+mod stereo_tests {
+    use super::{combine, StereoLayout};
+    use image::{ImageBuffer, Rgba};
+
+    #[test]
+    fn anaglyph_takes_red_from_left_eye() {
+        let left = ImageBuffer::from_pixel(2, 2, Rgba([200, 10, 20, 255]));
+        let right = ImageBuffer::from_pixel(2, 2, Rgba([30, 40, 50, 0]));
+        let image = combine(&left, &right, StereoLayout::Anaglyph);
+        assert_eq!(*image.get_pixel(1, 1), Rgba([200, 40, 50, 255]));
+    }
+
+    #[test]
+    fn side_by_side_doubles_width() {
+        let left = ImageBuffer::from_pixel(2, 3, Rgba([255, 0, 0, 255]));
+        let right = ImageBuffer::from_pixel(2, 3, Rgba([0, 0, 255, 255]));
+        let image = combine(&left, &right, StereoLayout::SideBySide);
+        assert_eq!(image.dimensions(), (4, 3));
+        assert_eq!(*image.get_pixel(1, 2), Rgba([255, 0, 0, 255]));
+        assert_eq!(*image.get_pixel(2, 0), Rgba([0, 0, 255, 255]));
+    }
+}
@@ -0,0 +1,267 @@
+//! Renders the standard swatch that materials are reviewed with: a ball of the
+//! material resting on a checkered floor, lit by a fixed studio environment.
+//! Every swatch is rendered the same way, so swatches of different materials
+//! can be compared side by side, independent of any part.
+//!
+//! The environment is generated rather than read from a file. It has a soft
+//! gradient sky, a dim floor, a large key softbox above and to the left of the
+//! camera, and a smaller rim light behind and to the right of the ball, which
+//! together show a material's diffuse color, the shape of its highlights, and
+//! its reflections.
+
+use crate::{
+    camera::OrthographicCamera,
+    color::RgbaSpectrum,
+    film::Film,
+    filter::MitchellFilter,
+    integrator::{render, PathTracer},
+    light::Light,
+    material::{Material, MatteMaterial},
+    primitive::PrimitiveAggregate,
+    sampler::StratifiedSampler,
+    scene::Scene,
+};
+use cgmath::{
+    point3, vec3, Angle, Deg, InnerSpace, Matrix4, Point2, Point3, SquareMatrix, Vector2, Vector3,
+};
+use mesh::{Mesh, MeshBuilder};
+use std::f32::consts::PI;
+
+/// The number of segments around the ball's equator. The ball has half as
+/// many segments from pole to pole.
+const BALL_SEGMENTS: usize = 64;
+
+/// The number of checks along each side of the floor.
+const FLOOR_CHECKS: usize = 12;
+
+/// The width and height of the environment map.
+const ENVIRONMENT_RESOLUTION: (usize, usize) = (256, 128);
+
+/// The angle in degrees above the horizon that the camera looks down at the
+/// ball from.
+const CAMERA_ELEVATION: f32 = 15.0;
+
+/// The maximum number of bounces along each path.
+const MAX_DEPTH: usize = 6;
+
+/// Render the swatch of the material.
+///
+/// * resolution - The resolution of the image. The ball fills most of the
+///   image's shorter side.
+/// * samples_per_pixel - The number of paths that are traced through each
+///   pixel.
+pub fn render_swatch(
+    material: &(dyn Material + Send + Sync),
+    resolution: Vector2<usize>,
+    samples_per_pixel: usize,
+) -> Film {
+    let ball = ball_mesh();
+    let (light_checks, dark_checks) = floor_meshes();
+    let light_material = MatteMaterial::new(RgbaSpectrum::constant(0.5), 0.0);
+    let dark_material = MatteMaterial::new(RgbaSpectrum::constant(0.1), 0.0);
+    let (radiance_map, map_resolution) = studio_environment();
+    let scene = Scene::new(
+        PrimitiveAggregate::Vector(vec![
+            PrimitiveAggregate::from_mesh(&ball, material),
+            PrimitiveAggregate::from_mesh(&light_checks, &light_material),
+            PrimitiveAggregate::from_mesh(&dark_checks, &dark_material),
+        ]),
+        vec![Light::infinite_area_light(
+            Matrix4::identity(),
+            radiance_map,
+            map_resolution,
+        )],
+    );
+
+    let camera = swatch_camera(resolution);
+    let mut film = Film::new(resolution);
+    let filter = MitchellFilter::new(2.0, 2.0, 1.0 / 3.0, 1.0 / 3.0);
+    let sampler = StratifiedSampler::from_samples_per_pixel(samples_per_pixel.max(1), 5, 0, true);
+    render(
+        &scene,
+        &camera,
+        &mut film,
+        &filter,
+        &sampler,
+        &PathTracer::default(),
+        MAX_DEPTH,
+    );
+    film
+}
+
+/// Return the camera that looks at the ball from in front of it and slightly
+/// above, along the positive y axis.
+fn swatch_camera(resolution: Vector2<usize>) -> OrthographicCamera {
+    let elevation = Deg(CAMERA_ELEVATION);
+    let eye = point3(0.0, -5.0 * elevation.cos(), 5.0 * elevation.sin());
+    // The camera looks towards its positive z axis, but `look_at_rh` looks
+    // towards the negative z axis.
+    let camera_to_world = Matrix4::look_at_rh(eye, point3(0.0, 0.0, 0.0), Vector3::unit_z())
+        .invert()
+        .unwrap_or_else(Matrix4::identity)
+        * Matrix4::from_angle_y(Deg(180.0));
+    let aspect = resolution.x as f32 / resolution.y.max(1) as f32;
+    // The ball has a diameter of 2, and is framed with a margin around it.
+    let size = 2.6;
+    let screen_size = if aspect >= 1.0 {
+        Vector2::new(aspect * size, size)
+    } else {
+        Vector2::new(size, size / aspect)
+    };
+    OrthographicCamera::new(camera_to_world, 0.0, 20.0, screen_size, resolution)
+}
+
+/// Return a ball with a radius of 1 centered at the origin.
+fn ball_mesh() -> Mesh {
+    let rows = BALL_SEGMENTS / 2;
+    let mut positions = vec![];
+    let mut uvs = vec![];
+    for row in 0..=rows {
+        let theta = PI * row as f32 / rows as f32;
+        for column in 0..=BALL_SEGMENTS {
+            let phi = 2.0 * PI * column as f32 / BALL_SEGMENTS as f32;
+            positions.push(point3(
+                theta.sin() * phi.cos(),
+                theta.sin() * phi.sin(),
+                theta.cos(),
+            ));
+            uvs.push(Point2::new(
+                column as f32 / BALL_SEGMENTS as f32,
+                row as f32 / rows as f32,
+            ));
+        }
+    }
+    let normals = positions
+        .iter()
+        .map(|p| p - point3(0.0, 0.0, 0.0))
+        .collect();
+    let index = |row: usize, column: usize| row * (BALL_SEGMENTS + 1) + column;
+    let mut triangles = vec![];
+    for row in 0..rows {
+        for column in 0..BALL_SEGMENTS {
+            // The triangles are wound counterclockwise when seen from outside
+            // of the ball. The triangles that would be degenerate at the poles
+            // are left out.
+            if row > 0 {
+                triangles.push((
+                    index(row, column),
+                    index(row + 1, column),
+                    index(row, column + 1),
+                ));
+            }
+            if row + 1 < rows {
+                triangles.push((
+                    index(row, column + 1),
+                    index(row + 1, column),
+                    index(row + 1, column + 1),
+                ));
+            }
+        }
+    }
+    MeshBuilder::new(positions, normals, triangles)
+        .uvs(uvs)
+        .build()
+}
+
+/// Return the light and dark checks of the floor that the ball rests on, which
+/// is the horizontal plane at the bottom of the ball.
+fn floor_meshes() -> (Mesh, Mesh) {
+    type Checks = (
+        Vec<Point3<f32>>,
+        Vec<Vector3<f32>>,
+        Vec<(usize, usize, usize)>,
+    );
+    let mut light: Checks = Default::default();
+    let mut dark: Checks = Default::default();
+    let half_width = FLOOR_CHECKS as f32 / 2.0;
+    for i in 0..FLOOR_CHECKS {
+        for j in 0..FLOOR_CHECKS {
+            let (positions, normals, triangles) = if (i + j) % 2 == 0 {
+                &mut light
+            } else {
+                &mut dark
+            };
+            let x = i as f32 - half_width;
+            let y = j as f32 - half_width;
+            let first = positions.len();
+            positions.extend_from_slice(&[
+                point3(x, y, -1.0),
+                point3(x + 1.0, y, -1.0),
+                point3(x + 1.0, y + 1.0, -1.0),
+                point3(x, y + 1.0, -1.0),
+            ]);
+            normals.extend_from_slice(&[Vector3::unit_z(); 4]);
+            triangles.push((first, first + 1, first + 2));
+            triangles.push((first, first + 2, first + 3));
+        }
+    }
+    (
+        MeshBuilder::new(light.0, light.1, light.2).build(),
+        MeshBuilder::new(dark.0, dark.1, dark.2).build(),
+    )
+}
+
+/// Return the studio environment's equirectangular radiance map in row-major
+/// order, starting at the top-left texel, and its resolution. The map's top
+/// row is the positive z axis, which is up.
+pub fn studio_environment() -> (Vec<RgbaSpectrum>, Vector2<usize>) {
+    let (width, height) = ENVIRONMENT_RESOLUTION;
+    // The camera looks along the positive y axis, and the positive x axis is
+    // on the left of the image.
+    let key = vec3(0.75, -0.35, 0.55).normalize();
+    let rim = vec3(-0.7, 0.7, 0.35).normalize();
+    let map = (0..height)
+        .flat_map(|row| (0..width).map(move |column| (row, column)))
+        .map(|(row, column)| {
+            let theta = PI * (row as f32 + 0.5) / height as f32;
+            let phi = 2.0 * PI * (column as f32 + 0.5) / width as f32;
+            let w = vec3(
+                theta.sin() * phi.cos(),
+                theta.sin() * phi.sin(),
+                theta.cos(),
+            );
+            let background = if w.z >= 0.0 {
+                // The sky brightens from the horizon to the zenith.
+                RgbaSpectrum::from_rgb(0.1, 0.11, 0.13) * (0.3 + 0.7 * w.z)
+            } else {
+                RgbaSpectrum::constant(0.03)
+            };
+            let softbox = |center: Vector3<f32>, angular_radius: f32, radiance: f32| {
+                if w.dot(center) >= angular_radius.cos() {
+                    radiance
+                } else {
+                    0.0
+                }
+            };
+            background + RgbaSpectrum::constant(softbox(key, 0.25, 14.0) + softbox(rim, 0.15, 4.0))
+        })
+        .collect();
+    (map, Vector2::new(width, height))
+}
+
+#[cfg(test)]
+mod swatch_tests {
+    use super::{ball_mesh, render_swatch};
+    use crate::{color::RgbaSpectrum, material::MatteMaterial, test::ApproxEq};
+    use cgmath::{point3, InnerSpace, Vector2};
+
+    #[test]
+    fn ball_vertices_are_on_the_unit_sphere() {
+        let ball = ball_mesh();
+        for position in &ball.positions {
+            (position - point3(0.0, 0.0, 0.0))
+                .magnitude()
+                .assert_approx_eq(&1.0);
+        }
+    }
+
+    #[test]
+    fn ball_fills_the_center_of_the_swatch() {
+        let material = MatteMaterial::new(RgbaSpectrum::constant(0.5), 0.0);
+        let film = render_swatch(&material, Vector2::new(8, 8), 1);
+        let image = film.write_image();
+        let center = image.get_pixel(4, 4);
+        assert_eq!(center[3], 255);
+        assert!(center[0] > 0);
+    }
+}
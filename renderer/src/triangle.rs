@@ -148,14 +148,7 @@ impl<'msh> Triangle<'msh> {
         let uv_hit = point::add_point2(vec![b0 * uv0, b1 * uv1, b2 * uv2]);
 
         // Test intersection against alpha texture went here...
-        let dp02 = p0 - p2;
-        let dp12 = p1 - p2;
-        let normal =
-            if self.0.mesh.reverse_orientation || self.0.mesh.transformation_swaps_handedness {
-                -1.0 * dp02.cross(dp12).normalize()
-            } else {
-                dp02.cross(dp12).normalize()
-            };
+        let normal = self.geometric_normal();
 
         // Fill in SurfaceInteraction for triangle hit
         let interaction = SurfaceInteraction::new_with_normal(
@@ -170,6 +163,26 @@ impl<'msh> Triangle<'msh> {
         Some((t, interaction))
     }
 
+    /// Return the unit normal of the plane that the triangle lies in. The
+    /// normal is flipped if the mesh's orientation is reversed or if its
+    /// transformation swaps handedness.
+    pub(crate) fn geometric_normal(&self) -> Vector3<f32> {
+        let (p0, p1, p2) = self.0.positions();
+        let dp02 = p0 - p2;
+        let dp12 = p1 - p2;
+        if self.0.mesh.reverse_orientation || self.0.mesh.transformation_swaps_handedness {
+            -1.0 * dp02.cross(dp12).normalize()
+        } else {
+            dp02.cross(dp12).normalize()
+        }
+    }
+
+    /// Return the triangle's surface area.
+    pub(crate) fn area(&self) -> f32 {
+        let (p0, p1, p2) = self.0.positions();
+        0.5 * (p1 - p0).cross(p2 - p0).magnitude()
+    }
+
     /// Calculates the partial derivatives of (x,y,z) positions on the triangle with
     /// respect to the texture coordinates, u and v. Returns the vectors
     /// (δx/δu,δy/δu,δz/δu) and (δx/δv,δy/δv,δz/δv) if the triangle is not
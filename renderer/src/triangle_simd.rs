@@ -0,0 +1,467 @@
+//! A SIMD kernel that tests a ray against several triangles at once and rules
+//! out the ones it can't hit, so that the exact ray-triangle intersection test
+//! only runs on the rest.
+//!
+//! The kernel repeats the first stage of the watertight test in
+//! `Triangle::ray_intersection` with the same floating point operations in the
+//! same order, so each lane computes exactly the values that the scalar test
+//! would. A triangle is only ruled out when the scalar test would reject it
+//! with those values, which keeps renders identical on every SIMD level.
+
+use crate::cpu::SimdLevel;
+use crate::geometry::{axis::Axis3, point, vector};
+use crate::ray::Ray;
+use cgmath::Point3;
+
+/// The vertex positions of a triangle, or `None` for another shape.
+type TrianglePositions = Option<(Point3<f32>, Point3<f32>, Point3<f32>)>;
+
+/// The most `f32` lanes that any SIMD level operates on.
+const MAX_LANES: usize = 8;
+
+/// The values that the watertight test derives from the ray alone, which every
+/// lane shares.
+struct RaySetup {
+    new_x_axis: Axis3,
+    new_y_axis: Axis3,
+    new_z_axis: Axis3,
+
+    /// The translation that moves the ray origin to the coordinate system's
+    /// origin, permuted like the triangle vertices.
+    translation: [f32; 3],
+
+    /// The shear coefficients that align the ray direction with the z axis.
+    sx: f32,
+    sy: f32,
+    sz: f32,
+
+    t_max: f32,
+}
+
+impl RaySetup {
+    fn new(ray: &Ray) -> Self {
+        let new_z_axis = vector::max_dimension(ray.direction);
+        let new_x_axis = match new_z_axis {
+            Axis3::X => Axis3::Y,
+            Axis3::Y => Axis3::Z,
+            Axis3::Z => Axis3::X,
+        };
+        let new_y_axis = match new_x_axis {
+            Axis3::X => Axis3::Y,
+            Axis3::Y => Axis3::Z,
+            Axis3::Z => Axis3::X,
+        };
+        let translation = Point3::new(0.0, 0.0, 0.0) - ray.origin;
+        let translation = vector::permute(translation, new_x_axis, new_y_axis, new_z_axis);
+        let dir_t = vector::permute(ray.direction, new_x_axis, new_y_axis, new_z_axis);
+        Self {
+            new_x_axis,
+            new_y_axis,
+            new_z_axis,
+            translation: [translation.x, translation.y, translation.z],
+            sx: -dir_t.x / dir_t.z,
+            sy: -dir_t.y / dir_t.z,
+            sz: 1.0 / dir_t.z,
+            t_max: ray.t_max,
+        }
+    }
+}
+
+/// The permuted vertex positions of a batch of triangles, stored as one array
+/// per vertex component with one element per lane.
+struct TriangleBatch {
+    x: [[f32; MAX_LANES]; 3],
+    y: [[f32; MAX_LANES]; 3],
+    z: [[f32; MAX_LANES]; 3],
+}
+
+impl TriangleBatch {
+    /// Gather the vertex positions of the triangles. A lane without a
+    /// triangle is filled with NaN, which the kernel never rules out.
+    fn gather(setup: &RaySetup, triangles: &[TrianglePositions]) -> Self {
+        let mut batch = Self {
+            x: [[f32::NAN; MAX_LANES]; 3],
+            y: [[f32::NAN; MAX_LANES]; 3],
+            z: [[f32::NAN; MAX_LANES]; 3],
+        };
+        for (lane, triangle) in triangles.iter().enumerate() {
+            if let Some((p0, p1, p2)) = triangle {
+                for (vertex, p) in [p0, p1, p2].iter().enumerate() {
+                    let p =
+                        point::permute(**p, setup.new_x_axis, setup.new_y_axis, setup.new_z_axis);
+                    batch.x[vertex][lane] = p.x;
+                    batch.y[vertex][lane] = p.y;
+                    batch.z[vertex][lane] = p.z;
+                }
+            }
+        }
+        batch
+    }
+}
+
+/// Return whether the ray may intersect each of the triangles. A triangle
+/// that this returns false for is certain to be missed, while one that it
+/// returns true for still needs the exact intersection test. Entries that
+/// are `None` are always returned as true, so that other shapes can be
+/// passed through in place.
+///
+/// The triangles are tested in batches with the widest SIMD instruction set
+/// the CPU supports. On CPUs without one every entry is returned as true.
+pub fn may_intersect(ray: &Ray, triangles: &[TrianglePositions]) -> Vec<bool> {
+    may_intersect_at_level(SimdLevel::current(), ray, triangles)
+}
+
+/// Return whether the ray may intersect each of the triangles, using the
+/// kernel for the given SIMD level. The CPU must support the level.
+fn may_intersect_at_level(
+    level: SimdLevel,
+    ray: &Ray,
+    triangles: &[TrianglePositions],
+) -> Vec<bool> {
+    let mut may_hit = vec![true; triangles.len()];
+    if level == SimdLevel::Scalar {
+        return may_hit;
+    }
+    let setup = RaySetup::new(ray);
+    let lanes = level.f32_lanes();
+    for (triangles, may_hit) in triangles.chunks(lanes).zip(may_hit.chunks_mut(lanes)) {
+        let batch = TriangleBatch::gather(&setup, triangles);
+        let missed = match level {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            SimdLevel::Avx2 => unsafe { x86::missed_avx2(&setup, &batch) },
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            SimdLevel::Sse41 => unsafe { x86::missed_sse41(&setup, &batch) },
+            #[cfg(target_arch = "aarch64")]
+            SimdLevel::Neon => unsafe { aarch64::missed_neon(&setup, &batch) },
+            _ => 0,
+        };
+        for (lane, may_hit) in may_hit.iter_mut().enumerate() {
+            *may_hit = missed & (1 << lane) == 0;
+        }
+    }
+    may_hit
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod x86 {
+    use super::{RaySetup, TriangleBatch};
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    /// Return a bit mask with a set bit for each of the first 4 lanes whose
+    /// triangle the ray misses.
+    #[target_feature(enable = "sse4.1")]
+    pub(super) unsafe fn missed_sse41(setup: &RaySetup, batch: &TriangleBatch) -> u32 {
+        let [tx, ty, tz] = setup.translation;
+        let (tx, ty, tz) = (_mm_set1_ps(tx), _mm_set1_ps(ty), _mm_set1_ps(tz));
+        let (sx, sy, sz) = (
+            _mm_set1_ps(setup.sx),
+            _mm_set1_ps(setup.sy),
+            _mm_set1_ps(setup.sz),
+        );
+
+        // Translate and shear the vertices.
+        let mut x = [_mm_setzero_ps(); 3];
+        let mut y = [_mm_setzero_ps(); 3];
+        let mut z = [_mm_setzero_ps(); 3];
+        for v in 0..3 {
+            z[v] = _mm_add_ps(_mm_loadu_ps(batch.z[v].as_ptr()), tz);
+            x[v] = _mm_add_ps(_mm_loadu_ps(batch.x[v].as_ptr()), tx);
+            x[v] = _mm_add_ps(x[v], _mm_mul_ps(sx, z[v]));
+            y[v] = _mm_add_ps(_mm_loadu_ps(batch.y[v].as_ptr()), ty);
+            y[v] = _mm_add_ps(y[v], _mm_mul_ps(sy, z[v]));
+        }
+
+        // Compute the edge functions.
+        let e0 = _mm_sub_ps(_mm_mul_ps(x[1], y[2]), _mm_mul_ps(y[1], x[2]));
+        let e1 = _mm_sub_ps(_mm_mul_ps(x[2], y[0]), _mm_mul_ps(y[2], x[0]));
+        let e2 = _mm_sub_ps(_mm_mul_ps(x[0], y[1]), _mm_mul_ps(y[0], x[1]));
+        let zero = _mm_setzero_ps();
+        // The scalar test recomputes edge functions that are zero in double
+        // precision, so those lanes can't be ruled out here.
+        let on_edge = _mm_or_ps(
+            _mm_or_ps(_mm_cmpeq_ps(e0, zero), _mm_cmpeq_ps(e1, zero)),
+            _mm_cmpeq_ps(e2, zero),
+        );
+        let any_negative = _mm_or_ps(
+            _mm_or_ps(_mm_cmplt_ps(e0, zero), _mm_cmplt_ps(e1, zero)),
+            _mm_cmplt_ps(e2, zero),
+        );
+        let any_positive = _mm_or_ps(
+            _mm_or_ps(_mm_cmpgt_ps(e0, zero), _mm_cmpgt_ps(e1, zero)),
+            _mm_cmpgt_ps(e2, zero),
+        );
+        let outside = _mm_and_ps(any_negative, any_positive);
+
+        // Compare the scaled hit distance against the ray's range.
+        let det = _mm_add_ps(_mm_add_ps(e0, e1), e2);
+        let t_scaled = _mm_add_ps(
+            _mm_add_ps(
+                _mm_mul_ps(e0, _mm_mul_ps(z[0], sz)),
+                _mm_mul_ps(e1, _mm_mul_ps(z[1], sz)),
+            ),
+            _mm_mul_ps(e2, _mm_mul_ps(z[2], sz)),
+        );
+        let t_max_det = _mm_mul_ps(_mm_set1_ps(setup.t_max), det);
+        let out_of_range_below = _mm_and_ps(
+            _mm_cmplt_ps(det, zero),
+            _mm_or_ps(
+                _mm_cmpge_ps(t_scaled, zero),
+                _mm_cmplt_ps(t_scaled, t_max_det),
+            ),
+        );
+        let out_of_range_above = _mm_and_ps(
+            _mm_cmpgt_ps(det, zero),
+            _mm_or_ps(
+                _mm_cmple_ps(t_scaled, zero),
+                _mm_cmpgt_ps(t_scaled, t_max_det),
+            ),
+        );
+
+        let missed = _mm_andnot_ps(
+            on_edge,
+            _mm_or_ps(outside, _mm_or_ps(out_of_range_below, out_of_range_above)),
+        );
+        _mm_movemask_ps(missed) as u32
+    }
+
+    /// Return a bit mask with a set bit for each of the 8 lanes whose triangle
+    /// the ray misses.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn missed_avx2(setup: &RaySetup, batch: &TriangleBatch) -> u32 {
+        let [tx, ty, tz] = setup.translation;
+        let (tx, ty, tz) = (_mm256_set1_ps(tx), _mm256_set1_ps(ty), _mm256_set1_ps(tz));
+        let (sx, sy, sz) = (
+            _mm256_set1_ps(setup.sx),
+            _mm256_set1_ps(setup.sy),
+            _mm256_set1_ps(setup.sz),
+        );
+
+        // Translate and shear the vertices.
+        let mut x = [_mm256_setzero_ps(); 3];
+        let mut y = [_mm256_setzero_ps(); 3];
+        let mut z = [_mm256_setzero_ps(); 3];
+        for v in 0..3 {
+            z[v] = _mm256_add_ps(_mm256_loadu_ps(batch.z[v].as_ptr()), tz);
+            x[v] = _mm256_add_ps(_mm256_loadu_ps(batch.x[v].as_ptr()), tx);
+            x[v] = _mm256_add_ps(x[v], _mm256_mul_ps(sx, z[v]));
+            y[v] = _mm256_add_ps(_mm256_loadu_ps(batch.y[v].as_ptr()), ty);
+            y[v] = _mm256_add_ps(y[v], _mm256_mul_ps(sy, z[v]));
+        }
+
+        // Compute the edge functions.
+        let e0 = _mm256_sub_ps(_mm256_mul_ps(x[1], y[2]), _mm256_mul_ps(y[1], x[2]));
+        let e1 = _mm256_sub_ps(_mm256_mul_ps(x[2], y[0]), _mm256_mul_ps(y[2], x[0]));
+        let e2 = _mm256_sub_ps(_mm256_mul_ps(x[0], y[1]), _mm256_mul_ps(y[0], x[1]));
+        let zero = _mm256_setzero_ps();
+        // The scalar test recomputes edge functions that are zero in double
+        // precision, so those lanes can't be ruled out here.
+        let on_edge = _mm256_or_ps(
+            _mm256_or_ps(
+                _mm256_cmp_ps::<_CMP_EQ_OQ>(e0, zero),
+                _mm256_cmp_ps::<_CMP_EQ_OQ>(e1, zero),
+            ),
+            _mm256_cmp_ps::<_CMP_EQ_OQ>(e2, zero),
+        );
+        let any_negative = _mm256_or_ps(
+            _mm256_or_ps(
+                _mm256_cmp_ps::<_CMP_LT_OQ>(e0, zero),
+                _mm256_cmp_ps::<_CMP_LT_OQ>(e1, zero),
+            ),
+            _mm256_cmp_ps::<_CMP_LT_OQ>(e2, zero),
+        );
+        let any_positive = _mm256_or_ps(
+            _mm256_or_ps(
+                _mm256_cmp_ps::<_CMP_GT_OQ>(e0, zero),
+                _mm256_cmp_ps::<_CMP_GT_OQ>(e1, zero),
+            ),
+            _mm256_cmp_ps::<_CMP_GT_OQ>(e2, zero),
+        );
+        let outside = _mm256_and_ps(any_negative, any_positive);
+
+        // Compare the scaled hit distance against the ray's range.
+        let det = _mm256_add_ps(_mm256_add_ps(e0, e1), e2);
+        let t_scaled = _mm256_add_ps(
+            _mm256_add_ps(
+                _mm256_mul_ps(e0, _mm256_mul_ps(z[0], sz)),
+                _mm256_mul_ps(e1, _mm256_mul_ps(z[1], sz)),
+            ),
+            _mm256_mul_ps(e2, _mm256_mul_ps(z[2], sz)),
+        );
+        let t_max_det = _mm256_mul_ps(_mm256_set1_ps(setup.t_max), det);
+        let out_of_range_below = _mm256_and_ps(
+            _mm256_cmp_ps::<_CMP_LT_OQ>(det, zero),
+            _mm256_or_ps(
+                _mm256_cmp_ps::<_CMP_GE_OQ>(t_scaled, zero),
+                _mm256_cmp_ps::<_CMP_LT_OQ>(t_scaled, t_max_det),
+            ),
+        );
+        let out_of_range_above = _mm256_and_ps(
+            _mm256_cmp_ps::<_CMP_GT_OQ>(det, zero),
+            _mm256_or_ps(
+                _mm256_cmp_ps::<_CMP_LE_OQ>(t_scaled, zero),
+                _mm256_cmp_ps::<_CMP_GT_OQ>(t_scaled, t_max_det),
+            ),
+        );
+
+        let missed = _mm256_andnot_ps(
+            on_edge,
+            _mm256_or_ps(
+                outside,
+                _mm256_or_ps(out_of_range_below, out_of_range_above),
+            ),
+        );
+        _mm256_movemask_ps(missed) as u32
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod aarch64 {
+    use super::{RaySetup, TriangleBatch};
+    use std::arch::aarch64::*;
+
+    /// Return a bit mask with a set bit for each of the first 4 lanes whose
+    /// triangle the ray misses.
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn missed_neon(setup: &RaySetup, batch: &TriangleBatch) -> u32 {
+        let [tx, ty, tz] = setup.translation;
+        let (tx, ty, tz) = (vdupq_n_f32(tx), vdupq_n_f32(ty), vdupq_n_f32(tz));
+        let (sx, sy, sz) = (
+            vdupq_n_f32(setup.sx),
+            vdupq_n_f32(setup.sy),
+            vdupq_n_f32(setup.sz),
+        );
+
+        // Translate and shear the vertices. Multiplies and adds are kept
+        // separate, since a fused multiply-add would round differently from
+        // the scalar test.
+        let mut x = [vdupq_n_f32(0.0); 3];
+        let mut y = [vdupq_n_f32(0.0); 3];
+        let mut z = [vdupq_n_f32(0.0); 3];
+        for v in 0..3 {
+            z[v] = vaddq_f32(vld1q_f32(batch.z[v].as_ptr()), tz);
+            x[v] = vaddq_f32(vld1q_f32(batch.x[v].as_ptr()), tx);
+            x[v] = vaddq_f32(x[v], vmulq_f32(sx, z[v]));
+            y[v] = vaddq_f32(vld1q_f32(batch.y[v].as_ptr()), ty);
+            y[v] = vaddq_f32(y[v], vmulq_f32(sy, z[v]));
+        }
+
+        // Compute the edge functions.
+        let e0 = vsubq_f32(vmulq_f32(x[1], y[2]), vmulq_f32(y[1], x[2]));
+        let e1 = vsubq_f32(vmulq_f32(x[2], y[0]), vmulq_f32(y[2], x[0]));
+        let e2 = vsubq_f32(vmulq_f32(x[0], y[1]), vmulq_f32(y[0], x[1]));
+        let zero = vdupq_n_f32(0.0);
+        // The scalar test recomputes edge functions that are zero in double
+        // precision, so those lanes can't be ruled out here.
+        let on_edge = vorrq_u32(
+            vorrq_u32(vceqq_f32(e0, zero), vceqq_f32(e1, zero)),
+            vceqq_f32(e2, zero),
+        );
+        let any_negative = vorrq_u32(
+            vorrq_u32(vcltq_f32(e0, zero), vcltq_f32(e1, zero)),
+            vcltq_f32(e2, zero),
+        );
+        let any_positive = vorrq_u32(
+            vorrq_u32(vcgtq_f32(e0, zero), vcgtq_f32(e1, zero)),
+            vcgtq_f32(e2, zero),
+        );
+        let outside = vandq_u32(any_negative, any_positive);
+
+        // Compare the scaled hit distance against the ray's range.
+        let det = vaddq_f32(vaddq_f32(e0, e1), e2);
+        let t_scaled = vaddq_f32(
+            vaddq_f32(
+                vmulq_f32(e0, vmulq_f32(z[0], sz)),
+                vmulq_f32(e1, vmulq_f32(z[1], sz)),
+            ),
+            vmulq_f32(e2, vmulq_f32(z[2], sz)),
+        );
+        let t_max_det = vmulq_f32(vdupq_n_f32(setup.t_max), det);
+        let out_of_range_below = vandq_u32(
+            vcltq_f32(det, zero),
+            vorrq_u32(vcgeq_f32(t_scaled, zero), vcltq_f32(t_scaled, t_max_det)),
+        );
+        let out_of_range_above = vandq_u32(
+            vcgtq_f32(det, zero),
+            vorrq_u32(vcleq_f32(t_scaled, zero), vcgtq_f32(t_scaled, t_max_det)),
+        );
+
+        let missed = vbicq_u32(
+            vorrq_u32(outside, vorrq_u32(out_of_range_below, out_of_range_above)),
+            on_edge,
+        );
+        let mut lanes = [0u32; 4];
+        vst1q_u32(lanes.as_mut_ptr(), missed);
+        lanes.iter().enumerate().fold(
+            0,
+            |mask, (lane, &m)| if m != 0 { mask | (1 << lane) } else { mask },
+        )
+    }
+}
+
+#[cfg(test)]
+mod triangle_simd_tests {
+    use super::{may_intersect, may_intersect_at_level};
+    use crate::{cpu::SimdLevel, ray::Ray, triangle::Triangle};
+    use cgmath::{Point3, Vector3};
+    use mesh::MeshBuilder;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn never_rules_out_a_hit() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let point = |rng: &mut ChaCha8Rng| {
+            Point3::new(
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+            )
+        };
+        let positions: Vec<Point3<f32>> = (0..300).map(|_| point(&mut rng)).collect();
+        let triangle_vertex_indices: Vec<(usize, usize, usize)> =
+            (0..100).map(|i| (3 * i, 3 * i + 1, 3 * i + 2)).collect();
+        let mesh = MeshBuilder::new(positions, vec![], triangle_vertex_indices).build();
+        let triangles: Vec<_> = mesh.triangles().into_iter().map(Triangle).collect();
+        let positions: Vec<_> = triangles.iter().map(|t| Some(t.0.positions())).collect();
+
+        // Test every kernel that this CPU can run, since only the widest is
+        // used when rendering.
+        let mut levels = vec![SimdLevel::current()];
+        if SimdLevel::current() == SimdLevel::Avx2 {
+            levels.push(SimdLevel::Sse41);
+        }
+        for level in levels {
+            let mut hits = 0;
+            let mut ruled_out = 0;
+            for _ in 0..200 {
+                let origin = point(&mut rng) * 3.0;
+                let target = point(&mut rng) * 0.5;
+                let ray = Ray::new(origin, target - origin, rng.gen_range(0.2..2.0));
+                let may_hit = may_intersect_at_level(level, &ray, &positions);
+                for (triangle, may_hit) in triangles.iter().zip(may_hit) {
+                    let hit = triangle.ray_intersection(&ray).is_some();
+                    assert!(may_hit || !hit, "{} ruled out a hit", level.name());
+                    hits += hit as usize;
+                    ruled_out += !may_hit as usize;
+                }
+            }
+            assert!(hits > 0);
+            if level != SimdLevel::Scalar {
+                assert!(ruled_out > 0, "{} ruled out nothing", level.name());
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_entries_that_are_not_triangles() {
+        let ray = Ray::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            f32::INFINITY,
+        );
+        assert_eq!(may_intersect(&ray, &[None; 5]), vec![true; 5]);
+    }
+}
@@ -0,0 +1,379 @@
+//! Provides scenes and tests that can be used to check the colorimetric
+//! accuracy and energy conservation of the renderer's materials.
+
+use crate::{
+    camera::OrthographicCamera,
+    color::{self, RgbaSpectrum},
+    film::Film,
+    filter::BoxFilter,
+    integrator::{render, PathTracer},
+    light::Light,
+    material::{Material, MatteMaterial},
+    primitive::PrimitiveAggregate,
+    sampler::StratifiedSampler,
+    scene::Scene,
+    shape::{Shape, Splat},
+};
+use cgmath::{Matrix4, Point3, Vector2, Vector3};
+use mesh::{Mesh, MeshBuilder};
+use std::f32::consts::PI;
+use typed_arena::Arena;
+
+/// The names and 8-bit sRGB colors of the 24 patches in a Macbeth
+/// ColorChecker chart, in row-major order starting at the top-left patch.
+pub const MACBETH_CHART_SRGB: [(&str, [u8; 3]); 24] = [
+    ("dark skin", [115, 82, 68]),
+    ("light skin", [194, 150, 130]),
+    ("blue sky", [98, 122, 157]),
+    ("foliage", [87, 108, 67]),
+    ("blue flower", [133, 128, 177]),
+    ("bluish green", [103, 189, 170]),
+    ("orange", [214, 126, 44]),
+    ("purplish blue", [80, 91, 166]),
+    ("moderate red", [193, 90, 99]),
+    ("purple", [94, 60, 108]),
+    ("yellow green", [157, 188, 64]),
+    ("orange yellow", [224, 163, 46]),
+    ("blue", [56, 61, 150]),
+    ("green", [70, 148, 73]),
+    ("red", [175, 54, 60]),
+    ("yellow", [231, 199, 31]),
+    ("magenta", [187, 86, 149]),
+    ("cyan", [8, 133, 161]),
+    ("white", [243, 243, 242]),
+    ("neutral 8", [200, 200, 200]),
+    ("neutral 6.5", [160, 160, 160]),
+    ("neutral 5", [122, 122, 121]),
+    ("neutral 3.5", [85, 85, 85]),
+    ("black", [52, 52, 52]),
+];
+
+const MACBETH_CHART_COLUMNS: usize = 6;
+const MACBETH_CHART_ROWS: usize = 4;
+const MACBETH_PATCH_SIZE: f32 = 1.0;
+const MACBETH_PATCH_GAP: f32 = 0.2;
+
+/// The distance between the Macbeth chart and the light that illuminates it.
+/// The light is placed far away so that the irradiance is nearly the same at
+/// every patch.
+const MACBETH_LIGHT_DISTANCE: f32 = 1000.0;
+
+const FURNACE_SPHERE_RADIUS: f32 = 1.0;
+
+/// The space between the sphere in a furnace test and the edges of the image,
+/// which shows the environment that the sphere is compared against.
+const FURNACE_MARGIN: f32 = 0.5;
+
+/// Return the linear reflectance of each patch in a Macbeth ColorChecker chart,
+/// in row-major order starting at the top-left patch.
+pub fn macbeth_chart_reflectances() -> Vec<RgbaSpectrum> {
+    MACBETH_CHART_SRGB
+        .iter()
+        .map(|(_, [r, g, b])| {
+            RgbaSpectrum::from_rgb(
+                srgb_u8_to_linear(*r),
+                srgb_u8_to_linear(*g),
+                srgb_u8_to_linear(*b),
+            )
+        })
+        .collect()
+}
+
+/// Return a scene containing a Macbeth ColorChecker chart.
+///
+/// The chart's patches are matte squares that lie in the z = 0 plane and face
+/// the negative z direction. The chart is lit by a single distant point light
+/// on the negative z axis whose intensity is chosen so that the outgoing
+/// radiance from each patch is equal to the patch's reflectance. A correctly
+/// rendered image of the chart, such as one taken by the camera returned from
+/// `macbeth_chart_camera`, will have pixel values that match
+/// `macbeth_chart_reflectances`.
+pub fn macbeth_chart_scene<'msh, 'mtrl>(
+    mesh_arena: &'msh Arena<Mesh>,
+    material_arena: &'mtrl Arena<MatteMaterial>,
+) -> Scene<'msh, 'mtrl> {
+    let (chart_width, chart_height) = macbeth_chart_size();
+    let patches = macbeth_chart_reflectances()
+        .into_iter()
+        .enumerate()
+        .map(|(i, reflectance)| {
+            let column = i % MACBETH_CHART_COLUMNS;
+            let row = i / MACBETH_CHART_COLUMNS;
+            let min_x =
+                -0.5 * chart_width + column as f32 * (MACBETH_PATCH_SIZE + MACBETH_PATCH_GAP);
+            let max_y = 0.5 * chart_height - row as f32 * (MACBETH_PATCH_SIZE + MACBETH_PATCH_GAP);
+            let mesh = mesh_arena.alloc(square_mesh(min_x, max_y - MACBETH_PATCH_SIZE));
+            let material = material_arena.alloc(MatteMaterial::new(reflectance, 0.0));
+            PrimitiveAggregate::from_mesh(mesh, material)
+        })
+        .collect();
+
+    // The outgoing radiance from a Lambertian surface facing the light is
+    // reflectance / PI * intensity / distance^2.
+    let intensity = PI * MACBETH_LIGHT_DISTANCE * MACBETH_LIGHT_DISTANCE;
+    let light = Light::point_light(
+        Point3::new(0.0, 0.0, -MACBETH_LIGHT_DISTANCE),
+        RgbaSpectrum::constant(intensity),
+    );
+
+    Scene::new(PrimitiveAggregate::Vector(patches), vec![light])
+}
+
+/// Return an orthographic camera that looks at the chart in the scene returned
+/// by `macbeth_chart_scene` and frames the entire chart.
+pub fn macbeth_chart_camera(resolution: Vector2<usize>) -> OrthographicCamera {
+    let (chart_width, chart_height) = macbeth_chart_size();
+    let margin = 2.0 * MACBETH_PATCH_GAP;
+    let aspect_ratio = resolution.x as f32 / resolution.y as f32;
+    let min_width = chart_width + 2.0 * margin;
+    let min_height = chart_height + 2.0 * margin;
+    let screen_size = if min_width / min_height > aspect_ratio {
+        Vector2::new(min_width, min_width / aspect_ratio)
+    } else {
+        Vector2::new(min_height * aspect_ratio, min_height)
+    };
+    OrthographicCamera::new(
+        Matrix4::from_translation(Vector3::new(0.0, 0.0, -10.0)),
+        0.0,
+        100.0,
+        screen_size,
+        resolution,
+    )
+}
+
+/// Render a white furnace test of the material.
+///
+/// A sphere made of the material is placed in a uniform white environment,
+/// where unit radiance arrives from every direction, and rendered with a path
+/// tracer through an orthographic camera that frames the sphere with a margin.
+/// The light leaving the sphere in each direction is the fraction of the
+/// environment's light that the material scatters in that direction. A
+/// material that conserves energy will never render brighter than the
+/// background, and a material that doesn't absorb any light will render the
+/// same as the background, so the sphere disappears. The pixels are returned
+/// in row-major order.
+///
+/// * material - The material being tested.
+/// * resolution - The width and height of the rendered image.
+/// * samples_per_pixel - The number of paths that are traced through each
+///   pixel.
+/// * max_depth - The maximum number of bounces in each path. Materials that
+///   transmit light need enough bounces for paths to pass through the sphere
+///   and back out.
+pub fn furnace_test(
+    material: &(dyn Material + Send + Sync),
+    resolution: Vector2<usize>,
+    samples_per_pixel: usize,
+    max_depth: usize,
+) -> Vec<RgbaSpectrum> {
+    let sphere = Splat::new(Point3::new(0.0, 0.0, 0.0), FURNACE_SPHERE_RADIUS);
+    let environment = Light::uniform_infinite_light(RgbaSpectrum::constant(1.0));
+    let scene = Scene::new(
+        PrimitiveAggregate::primitive(Shape::Splat(sphere), material),
+        vec![environment],
+    );
+
+    let min_size = 2.0 * FURNACE_SPHERE_RADIUS + 2.0 * FURNACE_MARGIN;
+    let aspect_ratio = resolution.x as f32 / resolution.y as f32;
+    let screen_size = if aspect_ratio > 1.0 {
+        Vector2::new(min_size * aspect_ratio, min_size)
+    } else {
+        Vector2::new(min_size, min_size / aspect_ratio)
+    };
+    let camera = OrthographicCamera::new(
+        Matrix4::from_translation(Vector3::new(0.0, 0.0, -10.0)),
+        0.0,
+        100.0,
+        screen_size,
+        resolution,
+    );
+
+    let mut film = Film::new(resolution);
+    let filter = BoxFilter::new(0.5, 0.5);
+    // Each bounce takes a sample for the light and one for the BSDF, and the
+    // camera takes two more.
+    let dimensions = 2 * max_depth + 2;
+    let sampler = StratifiedSampler::from_samples_per_pixel(samples_per_pixel, dimensions, 0, true);
+    render(
+        &scene,
+        &camera,
+        &mut film,
+        &filter,
+        &sampler,
+        &PathTracer::default(),
+        max_depth,
+    );
+    film.to_rgba()
+}
+
+/// Return the width and height of the Macbeth chart.
+fn macbeth_chart_size() -> (f32, f32) {
+    let width = MACBETH_CHART_COLUMNS as f32 * MACBETH_PATCH_SIZE
+        + (MACBETH_CHART_COLUMNS - 1) as f32 * MACBETH_PATCH_GAP;
+    let height = MACBETH_CHART_ROWS as f32 * MACBETH_PATCH_SIZE
+        + (MACBETH_CHART_ROWS - 1) as f32 * MACBETH_PATCH_GAP;
+    (width, height)
+}
+
+/// Return a mesh containing a single square patch in the z = 0 plane.
+fn square_mesh(min_x: f32, min_y: f32) -> Mesh {
+    let max_x = min_x + MACBETH_PATCH_SIZE;
+    let max_y = min_y + MACBETH_PATCH_SIZE;
+    let positions = vec![
+        Point3::new(min_x, min_y, 0.0),
+        Point3::new(max_x, min_y, 0.0),
+        Point3::new(max_x, max_y, 0.0),
+        Point3::new(min_x, max_y, 0.0),
+    ];
+    let normals = vec![Vector3::new(0.0, 0.0, -1.0); 4];
+    MeshBuilder::new(positions, normals, vec![(0, 1, 2), (0, 2, 3)]).build()
+}
+
+/// Convert an 8-bit sRGB encoded component into a linear component in [0, 1].
+fn srgb_u8_to_linear(c: u8) -> f32 {
+    color::srgb_to_linear(c as f32 / 255.0)
+}
+
+#[cfg(test)]
+mod furnace_test_tests {
+    use super::furnace_test;
+    use crate::{
+        color::RgbaSpectrum,
+        material::{GlassMaterial, Material, MatteMaterial, MetalMaterial, PlasticMaterial},
+    };
+    use cgmath::Vector2;
+
+    const RESOLUTION: usize = 16;
+
+    /// The width and height of the blocks of pixels that are averaged to
+    /// remove most of the noise.
+    const BLOCK_SIZE: usize = 4;
+
+    /// Render the material's furnace test and return the average of each
+    /// block of pixels.
+    fn render_blocks(material: &(dyn Material + Send + Sync)) -> Vec<RgbaSpectrum> {
+        let pixels = furnace_test(material, Vector2::new(RESOLUTION, RESOLUTION), 128, 16);
+        let blocks_per_row = RESOLUTION / BLOCK_SIZE;
+        (0..blocks_per_row * blocks_per_row)
+            .map(|block| {
+                let (bx, by) = (block % blocks_per_row, block / blocks_per_row);
+                let sum = (0..BLOCK_SIZE * BLOCK_SIZE)
+                    .map(|i| {
+                        let x = bx * BLOCK_SIZE + i % BLOCK_SIZE;
+                        let y = by * BLOCK_SIZE + i / BLOCK_SIZE;
+                        pixels[y * RESOLUTION + x]
+                    })
+                    .fold(RgbaSpectrum::black(), |sum, pixel| sum + pixel);
+                sum / (BLOCK_SIZE * BLOCK_SIZE) as f32
+            })
+            .collect()
+    }
+
+    fn channels(block: &RgbaSpectrum) -> [f32; 3] {
+        [block.r(), block.g(), block.b()]
+    }
+
+    #[test]
+    fn white_lambertian_sphere_disappears() {
+        let material = MatteMaterial::new(RgbaSpectrum::constant(1.0), 0.0);
+        for (i, block) in render_blocks(&material).iter().enumerate() {
+            for c in &channels(block) {
+                assert!((c - 1.0).abs() < 0.1, "block {} is {}", i, c);
+            }
+        }
+    }
+
+    #[test]
+    fn clear_glass_sphere_disappears() {
+        let material = GlassMaterial::new(
+            RgbaSpectrum::constant(1.0),
+            RgbaSpectrum::constant(1.0),
+            1.5,
+        );
+        for (i, block) in render_blocks(&material).iter().enumerate() {
+            for c in &channels(block) {
+                assert!((c - 1.0).abs() < 0.1, "block {} is {}", i, c);
+            }
+        }
+    }
+
+    #[test]
+    fn absorbing_spheres_are_no_brighter_than_environment() {
+        let materials: Vec<Box<dyn Material + Send + Sync>> = vec![
+            Box::new(MatteMaterial::new(RgbaSpectrum::constant(1.0), 0.5)),
+            Box::new(PlasticMaterial::new(
+                RgbaSpectrum::constant(0.5),
+                RgbaSpectrum::constant(0.5),
+                0.1,
+                true,
+            )),
+            Box::new(MetalMaterial::new(
+                RgbaSpectrum::from_rgb(0.2, 0.9, 1.1),
+                RgbaSpectrum::from_rgb(3.9, 2.4, 2.2),
+                0.2,
+                true,
+            )),
+        ];
+        for (m, material) in materials.iter().enumerate() {
+            let blocks = render_blocks(material.as_ref());
+            for (i, block) in blocks.iter().enumerate() {
+                for c in &channels(block) {
+                    assert!(*c < 1.05, "material {} block {} is {}", m, i, c);
+                }
+            }
+
+            // The center of the image is covered by the sphere, which absorbs
+            // some of the light, and the corner only shows the environment.
+            let blocks_per_row = RESOLUTION / BLOCK_SIZE;
+            let center = blocks[blocks_per_row + 1];
+            assert!(center.g() < 0.95, "material {} center is {}", m, center.g());
+            assert!((blocks[0].g() - 1.0).abs() < 0.01);
+        }
+    }
+}
+
+#[cfg(test)]
+mod macbeth_chart_tests {
+    use super::{macbeth_chart_camera, macbeth_chart_reflectances, macbeth_chart_scene};
+    use crate::{
+        film::Film, filter::BoxFilter, integrator::render, integrator::PathTracer,
+        sampler::StratifiedSampler,
+    };
+    use cgmath::Vector2;
+    use typed_arena::Arena;
+
+    #[test]
+    fn rendered_patches_match_reflectances() {
+        let mesh_arena = Arena::new();
+        let material_arena = Arena::new();
+        let scene = macbeth_chart_scene(&mesh_arena, &material_arena);
+        // The camera frames the chart with a margin, which gives ten pixels
+        // per unit of length at this resolution.
+        let resolution = Vector2::new(78, 54);
+        let camera = macbeth_chart_camera(resolution);
+        let mut film = Film::new(resolution);
+        let filter = BoxFilter::new(0.5, 0.5);
+        let sampler = StratifiedSampler::from_samples_per_pixel(1, 5, 0, false);
+        render(
+            &scene,
+            &camera,
+            &mut film,
+            &filter,
+            &sampler,
+            &PathTracer::default(),
+            5,
+        );
+
+        let pixels = film.to_rgba();
+        for (i, reflectance) in macbeth_chart_reflectances().iter().enumerate() {
+            // The center of each patch is 0.9 units from the edge of the
+            // image, and patches are 1.2 units apart.
+            let x = 9 + 12 * (i % super::MACBETH_CHART_COLUMNS);
+            let y = 9 + 12 * (i / super::MACBETH_CHART_COLUMNS);
+            let pixel = pixels[y * resolution.x + x];
+            assert!((pixel.r() - reflectance.r()).abs() < 0.01, "patch {}", i);
+            assert!((pixel.g() - reflectance.g()).abs() < 0.01, "patch {}", i);
+            assert!((pixel.b() - reflectance.b()).abs() < 0.01, "patch {}", i);
+        }
+    }
+}